@@ -103,6 +103,16 @@ impl PemCertificate {
         Ok(ValidityStatus::Valid { expired_in })
     }
 
+    /// Return the validity period of the certificate, i.e. its `not_before` and `not_after` dates
+    pub fn validity_period(&self) -> Result<(OffsetDateTime, OffsetDateTime), CertificateError> {
+        let x509 = PemCertificate::extract_certificate(&self.pem)?;
+        let validity = &x509.tbs_certificate.validity;
+        Ok((
+            validity.not_before.to_datetime(),
+            validity.not_after.to_datetime(),
+        ))
+    }
+
     pub fn serial(&self) -> Result<String, CertificateError> {
         let x509 = PemCertificate::extract_certificate(&self.pem)?;
         Ok(x509.tbs_certificate.serial.to_string())
@@ -594,6 +604,39 @@ mod tests {
         assert_eq!(not_after, "Sat, 10 Apr 2021 15:39:57 +0000");
     }
 
+    #[test]
+    fn self_signed_cert_validity_period() {
+        let config = CsrTemplate {
+            validity_period_days: 10,
+            ..Default::default()
+        };
+        let id = "some-id";
+        let birthdate = datetime!(2021-03-31 16:39:57 +01:00);
+
+        let (params, signing_key) = KeyCertPair::create_selfsigned_certificate_parameters(
+            &config,
+            id,
+            &KeyKind::New,
+            birthdate,
+        )
+        .expect("Fail to get a certificate parameters");
+
+        let keypair = KeyCertPair {
+            certificate: params
+                .self_signed(&signing_key)
+                .expect("Fail to create a certificate"),
+            params,
+            signing_key,
+        };
+
+        let pem = pem_of_keypair(&keypair);
+        let (not_before, not_after) = pem
+            .validity_period()
+            .expect("Fail to extract the validity period");
+        assert_eq!(not_before, birthdate);
+        assert_eq!(not_after, datetime!(2021-04-10 16:39:57 +01:00));
+    }
+
     #[test]
     fn create_certificate_sign_request() {
         // Create a certificate with a given birthdate.
@@ -2,6 +2,8 @@ pub mod partial_response;
 use crate::download::partial_response::PartialResponse;
 use crate::error::DownloadError;
 use crate::error::ErrContext;
+use crate::verification::verify_file;
+use crate::verification::TrustStore;
 use anyhow::anyhow;
 use backoff::future::retry_notify;
 use backoff::ExponentialBackoff;
@@ -53,6 +55,18 @@ pub struct DownloadInfo {
     pub url: String,
     #[serde(skip)]
     pub headers: HeaderMap,
+
+    /// Expected SHA-256 digest of the file, hex encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    /// Expected SHA-512 digest of the file, hex encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+
+    /// Detached signature of the file, base64 encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 impl From<&str> for DownloadInfo {
@@ -67,6 +81,9 @@ impl DownloadInfo {
         Self {
             url: url.into(),
             headers: HeaderMap::new(),
+            sha256: None,
+            sha512: None,
+            signature: None,
         }
     }
 
@@ -78,10 +95,39 @@ impl DownloadInfo {
         }
     }
 
+    /// Sets the expected SHA-256 digest of the file.
+    pub fn with_sha256(self, sha256: impl Into<String>) -> Self {
+        Self {
+            sha256: Some(sha256.into()),
+            ..self
+        }
+    }
+
+    /// Sets the expected SHA-512 digest of the file.
+    pub fn with_sha512(self, sha512: impl Into<String>) -> Self {
+        Self {
+            sha512: Some(sha512.into()),
+            ..self
+        }
+    }
+
+    /// Sets the base64 encoded detached signature of the file.
+    pub fn with_signature(self, signature: impl Into<String>) -> Self {
+        Self {
+            signature: Some(signature.into()),
+            ..self
+        }
+    }
+
     pub fn url(&self) -> &str {
         self.url.as_str()
     }
 
+    /// Returns true if the downloaded file has to be checked against digests or a signature.
+    pub fn requires_verification(&self) -> bool {
+        self.sha256.is_some() || self.sha512.is_some() || self.signature.is_some()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.url.trim().is_empty()
     }
@@ -93,6 +139,7 @@ pub struct Downloader {
     target_filename: PathBuf,
     backoff: ExponentialBackoff,
     client: Client,
+    trust_store: Option<TrustStore>,
 }
 
 impl Downloader {
@@ -112,6 +159,7 @@ impl Downloader {
             target_filename: target_path,
             backoff: default_backoff(),
             client,
+            trust_store: None,
         }
     }
 
@@ -119,6 +167,13 @@ impl Downloader {
         self.backoff = backoff;
     }
 
+    /// Sets the keys trusted to sign the downloaded files.
+    ///
+    /// Without a trust store, any download request providing a signature fails.
+    pub fn set_trust_store(&mut self, trust_store: Option<TrustStore>) {
+        self.trust_store = trust_store;
+    }
+
     /// Downloads a file using an exponential backoff strategy.
     ///
     /// Partial backoff has a minimal interval of 30s and max elapsed time of
@@ -133,6 +188,10 @@ impl Downloader {
     ///
     /// Requests partial ranges if a transient error happened while downloading
     /// and the server response included `Accept-Ranges` header.
+    ///
+    /// If the [`DownloadInfo`] provides digests or a signature, the downloaded
+    /// file is checked before being moved to its final destination, and a
+    /// [`DownloadError::VerificationFailed`] error is returned on mismatch.
     pub async fn download(&self, url: &DownloadInfo) -> Result<(), DownloadError> {
         let tmp_target_path = self.temp_filename().await?;
         let target_file_path = self.target_filename.as_path();
@@ -175,6 +234,19 @@ impl Downloader {
             }
         }
 
+        if url.requires_verification() {
+            debug!("Verifying downloaded file {:?}", file.path());
+            verify_file(
+                file.path(),
+                url.sha256.clone(),
+                url.sha512.clone(),
+                url.signature.clone(),
+                self.trust_store.clone(),
+            )
+            .await
+            .map_err(DownloadError::VerificationFailed)?;
+        }
+
         // Move the downloaded file to the final destination
         debug!(
             "Moving downloaded file from {:?} to {:?}",
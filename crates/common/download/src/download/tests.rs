@@ -1,7 +1,10 @@
 use super::*;
+use crate::verification::VerificationError;
 use axum::Router;
+use base64::prelude::*;
 use http::StatusCode;
 use hyper::header::AUTHORIZATION;
+use rcgen::SigningKey;
 use rustls::pki_types::pem::PemObject;
 use rustls::pki_types::PrivateKeyDer;
 use rustls::RootCertStore;
@@ -301,6 +304,146 @@ async fn doesnt_leave_tmpfiles_on_errors() {
     assert_eq!(fs::read_dir(target_dir_path.path()).unwrap().count(), 0);
 }
 
+const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+const HELLO_SHA512: &str = "9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca72323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043";
+
+#[tokio::test]
+async fn downloaded_file_matches_expected_digests() {
+    let url = |url: DownloadInfo| {
+        url.with_sha256(HELLO_SHA256)
+            .with_sha512(HELLO_SHA512.to_uppercase())
+    };
+    let (result, target_dir) = download_hello(url, None).await;
+
+    result.unwrap();
+    let content = std::fs::read(target_dir.path().join("hello")).unwrap();
+    assert_eq!(content, b"hello");
+}
+
+#[tokio::test]
+async fn downloaded_file_not_matching_expected_digest_is_rejected() {
+    let url = |url: DownloadInfo| url.with_sha256(HELLO_SHA256).with_sha512("0badc0de");
+    let (result, target_dir) = download_hello(url, None).await;
+
+    let err = result.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            DownloadError::VerificationFailed(VerificationError::DigestMismatch {
+                algorithm: "sha512",
+                ..
+            })
+        ),
+        "{err:?}"
+    );
+    assert_eq!(fs::read_dir(target_dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn downloaded_file_signed_by_a_trusted_key_is_accepted() {
+    let trusted_dir = TempDir::new().unwrap();
+    let key = trusted_key(&trusted_dir);
+    let signature = BASE64_STANDARD.encode(key.sign(b"hello").unwrap());
+    let trust_store = TrustStore::load(trusted_dir.path()).unwrap();
+
+    let url = |url: DownloadInfo| url.with_signature(signature);
+    let (result, target_dir) = download_hello(url, Some(trust_store)).await;
+
+    result.unwrap();
+    let content = std::fs::read(target_dir.path().join("hello")).unwrap();
+    assert_eq!(content, b"hello");
+}
+
+#[tokio::test]
+async fn downloaded_file_signed_by_an_unknown_key_is_rejected() {
+    let trusted_dir = TempDir::new().unwrap();
+    trusted_key(&trusted_dir);
+    let trust_store = TrustStore::load(trusted_dir.path()).unwrap();
+
+    let untrusted_key = rcgen::KeyPair::generate().unwrap();
+    let signature = BASE64_STANDARD.encode(untrusted_key.sign(b"hello").unwrap());
+
+    let url = |url: DownloadInfo| url.with_signature(signature);
+    let (result, target_dir) = download_hello(url, Some(trust_store)).await;
+
+    let err = result.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            DownloadError::VerificationFailed(VerificationError::UntrustedSignature)
+        ),
+        "{err:?}"
+    );
+    assert_eq!(fs::read_dir(target_dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn non_pem_files_of_the_trust_store_directory_are_skipped() {
+    let trusted_dir = TempDir::new().unwrap();
+    let key = trusted_key(&trusted_dir);
+    std::fs::write(
+        trusted_dir.path().join("README"),
+        "Trusted software publishers",
+    )
+    .unwrap();
+    let signature = BASE64_STANDARD.encode(key.sign(b"hello").unwrap());
+    let trust_store = TrustStore::load(trusted_dir.path()).unwrap();
+
+    let url = |url: DownloadInfo| url.with_signature(signature);
+    let (result, _target_dir) = download_hello(url, Some(trust_store)).await;
+
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn signed_file_is_rejected_when_no_trust_store_is_configured() {
+    let key = rcgen::KeyPair::generate().unwrap();
+    let signature = BASE64_STANDARD.encode(key.sign(b"hello").unwrap());
+
+    let url = |url: DownloadInfo| url.with_signature(signature);
+    let (result, _target_dir) = download_hello(url, None).await;
+
+    let err = result.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            DownloadError::VerificationFailed(VerificationError::NoTrustStore)
+        ),
+        "{err:?}"
+    );
+}
+
+/// Download a file with "hello" content, returning the directory where the file is downloaded
+async fn download_hello(
+    url: impl FnOnce(DownloadInfo) -> DownloadInfo,
+    trust_store: Option<TrustStore>,
+) -> (Result<(), DownloadError>, TempDir) {
+    let mut server = mockito::Server::new_async().await;
+    let _mock = server
+        .mock("GET", "/hello")
+        .with_status(200)
+        .with_body(b"hello")
+        .create_async()
+        .await;
+
+    let target_dir = TempDir::new().unwrap();
+    let target_path = target_dir.path().join("hello");
+    let url = url(DownloadInfo::new(&format!("{}/hello", server.url())));
+
+    let mut downloader = Downloader::new(target_path, None, CloudHttpConfig::test_value());
+    downloader.set_trust_store(trust_store);
+    (downloader.download(&url).await, target_dir)
+}
+
+/// Generate a key pair, storing its self-signed certificate in the trust store directory
+fn trusted_key(trust_store_dir: &TempDir) -> rcgen::KeyPair {
+    let key = rcgen::KeyPair::generate().unwrap();
+    let params = rcgen::CertificateParams::new(vec!["software-publisher".to_string()]).unwrap();
+    let cert = params.self_signed(&key).unwrap();
+    std::fs::write(trust_store_dir.path().join("publisher.pem"), cert.pem()).unwrap();
+    key
+}
+
 // Parameters:
 //
 // - status code
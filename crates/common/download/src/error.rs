@@ -1,4 +1,5 @@
 use super::download::InvalidResponseError;
+use crate::verification::VerificationError;
 use std::io;
 use std::path::PathBuf;
 
@@ -23,6 +24,9 @@ pub enum DownloadError {
 
     #[error("Invalid server response")]
     InvalidResponse(#[from] InvalidResponseError),
+
+    #[error("Verification of the downloaded file failed: {0}")]
+    VerificationFailed(VerificationError),
 }
 
 /// A trait for attaching context string to io-like errors.
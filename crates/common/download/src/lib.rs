@@ -7,6 +7,7 @@
 //! - implementing reasonable exponential backoff strategy
 //! - performing partial downloads if a portion of a file has already been
 //!   downloaded
+//! - verifying the downloaded files against expected digests and signatures
 //!
 //! # Usage
 //!
@@ -46,7 +47,10 @@
 
 mod download;
 mod error;
+mod verification;
 
 pub use crate::download::DownloadInfo;
 pub use crate::download::Downloader;
 pub use crate::error::DownloadError;
+pub use crate::verification::TrustStore;
+pub use crate::verification::VerificationError;
@@ -0,0 +1,309 @@
+//! Integrity and authenticity checks of downloaded files.
+//!
+//! A [`DownloadInfo`](crate::DownloadInfo) can be given the expected SHA-256 and/or SHA-512 digests
+//! of the file, as well as a detached signature of the file.
+//! These are checked by the [`Downloader`](crate::Downloader) before the file is persisted,
+//! the signature being verified against the public keys of a [`TrustStore`].
+//!
+//! The signatures are standard detached signatures, as produced by `openssl dgst -sign` or `cosign sign-blob`.
+//! They are verified over the digests of the file, so the file is read in a single streaming pass,
+//! whatever its size.
+
+use aws_lc_rs::digest;
+use aws_lc_rs::signature;
+use aws_lc_rs::signature::UnparsedPublicKey;
+use aws_lc_rs::signature::VerificationAlgorithm;
+use base64::prelude::*;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use tracing::debug;
+use tracing::warn;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::pem::Pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+/// An error returned when a downloaded file cannot be verified.
+#[derive(Debug, thiserror::Error)]
+pub enum VerificationError {
+    #[error("{algorithm} digest mismatch: expected {expected}, got {actual}")]
+    DigestMismatch {
+        algorithm: &'static str,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("The signature is not base64 encoded")]
+    InvalidSignatureEncoding(#[from] base64::DecodeError),
+
+    #[error("The file is signed but no trust store is configured to verify the signature")]
+    NoTrustStore,
+
+    #[error("The signature doesn't match any of the trusted keys")]
+    UntrustedSignature,
+
+    #[error("Fail to read {path:?}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Invalid trust store {path:?}: {reason}")]
+    InvalidTrustStore { path: PathBuf, reason: String },
+}
+
+/// The public keys trusted to sign downloaded files.
+///
+/// These keys are loaded from PEM files,
+/// which can contain X.509 certificates (`CERTIFICATE` blocks) and public keys (`PUBLIC KEY` blocks).
+/// Only the public keys are used: the certificates are neither checked for expiry nor chained to a root CA.
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    keys: Vec<TrustedKey>,
+}
+
+#[derive(Debug, Clone)]
+struct TrustedKey {
+    /// The file from where the key has been loaded
+    source: PathBuf,
+
+    /// The subject public key, i.e. the content of the SPKI bit string
+    public_key: Vec<u8>,
+}
+
+impl TrustStore {
+    /// Load the trusted keys from a PEM file or from all the PEM files of a directory
+    ///
+    /// The files of a directory that cannot be read as PEM files are skipped with a warning.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, VerificationError> {
+        let path = path.as_ref();
+        let mut trust_store = TrustStore::default();
+        if path.is_dir() {
+            let entries = std::fs::read_dir(path).map_err(|source| VerificationError::Io {
+                path: path.to_owned(),
+                source,
+            })?;
+            let mut files: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect();
+            files.sort();
+            for file in files {
+                if let Err(err) = trust_store.add_pem_file(&file) {
+                    warn!("Ignoring {file:?} in the download trust store: {err}");
+                }
+            }
+        } else {
+            trust_store.add_pem_file(path)?;
+        }
+
+        if trust_store.keys.is_empty() {
+            return Err(VerificationError::InvalidTrustStore {
+                path: path.to_owned(),
+                reason: "no certificate nor public key found".to_string(),
+            });
+        }
+        Ok(trust_store)
+    }
+
+    fn add_pem_file(&mut self, path: &Path) -> Result<(), VerificationError> {
+        let invalid = |reason: String| VerificationError::InvalidTrustStore {
+            path: path.to_owned(),
+            reason,
+        };
+        let content = std::fs::read(path).map_err(|source| VerificationError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        for pem in Pem::iter_from_buffer(&content) {
+            let pem = pem.map_err(|err| invalid(err.to_string()))?;
+            let public_key = match pem.label.as_str() {
+                "CERTIFICATE" => {
+                    let (_, cert) = X509Certificate::from_der(&pem.contents)
+                        .map_err(|err| invalid(err.to_string()))?;
+                    cert.public_key().subject_public_key.data.to_vec()
+                }
+                "PUBLIC KEY" => {
+                    let (_, spki) = SubjectPublicKeyInfo::from_der(&pem.contents)
+                        .map_err(|err| invalid(err.to_string()))?;
+                    spki.subject_public_key.data.to_vec()
+                }
+                _ => continue,
+            };
+            self.keys.push(TrustedKey {
+                source: path.to_owned(),
+                public_key,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that the signature of a file has been produced by one of the trusted keys
+    ///
+    /// The signature is verified against the digest of the file computed by the signature scheme,
+    /// the supported schemes being RSA PKCS#1 v1.5 and PSS, and ECDSA with the P-256, P-384 and P-521 curves,
+    /// using SHA-256, SHA-384 or SHA-512.
+    /// Ed25519 signatures are not supported, as they cannot be verified against a digest.
+    fn verify(&self, digests: &SignedDigests, signature: &[u8]) -> Result<(), VerificationError> {
+        for key in self.keys.iter() {
+            if digests.iter().any(|(algorithm, digest)| {
+                UnparsedPublicKey::new(algorithm, &key.public_key)
+                    .verify_digest(digest, signature)
+                    .is_ok()
+            }) {
+                debug!("Signature verified with a key from {:?}", key.source);
+                return Ok(());
+            }
+        }
+        Err(VerificationError::UntrustedSignature)
+    }
+}
+
+/// The digests of a file, as computed by the supported signature schemes
+struct SignedDigests {
+    sha256: digest::Digest,
+    sha384: digest::Digest,
+    sha512: digest::Digest,
+}
+
+impl SignedDigests {
+    fn iter(&self) -> impl Iterator<Item = (&'static dyn VerificationAlgorithm, &digest::Digest)> {
+        let sha256: [&'static dyn VerificationAlgorithm; 3] = [
+            &signature::RSA_PKCS1_2048_8192_SHA256,
+            &signature::RSA_PSS_2048_8192_SHA256,
+            &signature::ECDSA_P256_SHA256_ASN1,
+        ];
+        let sha384: [&'static dyn VerificationAlgorithm; 3] = [
+            &signature::RSA_PKCS1_2048_8192_SHA384,
+            &signature::RSA_PSS_2048_8192_SHA384,
+            &signature::ECDSA_P384_SHA384_ASN1,
+        ];
+        let sha512: [&'static dyn VerificationAlgorithm; 3] = [
+            &signature::RSA_PKCS1_2048_8192_SHA512,
+            &signature::RSA_PSS_2048_8192_SHA512,
+            &signature::ECDSA_P521_SHA512_ASN1,
+        ];
+        let sha256 = sha256
+            .into_iter()
+            .map(|algorithm| (algorithm, &self.sha256));
+        let sha384 = sha384
+            .into_iter()
+            .map(|algorithm| (algorithm, &self.sha384));
+        let sha512 = sha512
+            .into_iter()
+            .map(|algorithm| (algorithm, &self.sha512));
+        sha256.chain(sha384).chain(sha512)
+    }
+}
+
+/// Check a file against its expected digests and signature
+///
+/// The file is read in a blocking task, not to block the async runtime on large files.
+pub(crate) async fn verify_file(
+    path: &Path,
+    sha256: Option<String>,
+    sha512: Option<String>,
+    signature: Option<String>,
+    trust_store: Option<TrustStore>,
+) -> Result<(), VerificationError> {
+    let file_path = path.to_owned();
+    tokio::task::spawn_blocking(move || {
+        verify_file_content(
+            &file_path,
+            sha256.as_deref(),
+            sha512.as_deref(),
+            signature.as_deref(),
+            trust_store.as_ref(),
+        )
+    })
+    .await
+    .map_err(|err| VerificationError::Io {
+        path: path.to_owned(),
+        source: io::Error::other(err),
+    })?
+}
+
+fn verify_file_content(
+    path: &Path,
+    sha256: Option<&str>,
+    sha512: Option<&str>,
+    signature: Option<&str>,
+    trust_store: Option<&TrustStore>,
+) -> Result<(), VerificationError> {
+    // Check the signature can be verified before reading the file
+    let signature = match signature {
+        None => None,
+        Some(signature) => {
+            let trust_store = trust_store.ok_or(VerificationError::NoTrustStore)?;
+            let signature = BASE64_STANDARD.decode(signature.trim())?;
+            Some((trust_store, signature))
+        }
+    };
+
+    // The digests are computed in a single pass over the file
+    let io_error = |source: io::Error| VerificationError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let mut file = File::open(path).map_err(io_error)?;
+    let mut sha256_hasher =
+        (sha256.is_some() || signature.is_some()).then(|| digest::Context::new(&digest::SHA256));
+    let mut sha384_hasher = signature
+        .is_some()
+        .then(|| digest::Context::new(&digest::SHA384));
+    let mut sha512_hasher =
+        (sha512.is_some() || signature.is_some()).then(|| digest::Context::new(&digest::SHA512));
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let len = file.read(&mut buffer).map_err(io_error)?;
+        if len == 0 {
+            break;
+        }
+        for hasher in [&mut sha256_hasher, &mut sha384_hasher, &mut sha512_hasher] {
+            if let Some(hasher) = hasher.as_mut() {
+                hasher.update(&buffer[..len]);
+            }
+        }
+    }
+
+    let sha256_digest = sha256_hasher.map(|hasher| hasher.finish());
+    let sha384_digest = sha384_hasher.map(|hasher| hasher.finish());
+    let sha512_digest = sha512_hasher.map(|hasher| hasher.finish());
+    if let (Some(expected), Some(digest)) = (sha256, &sha256_digest) {
+        check_digest("sha256", digest.as_ref(), expected)?;
+    }
+    if let (Some(expected), Some(digest)) = (sha512, &sha512_digest) {
+        check_digest("sha512", digest.as_ref(), expected)?;
+    }
+    if let (Some((trust_store, signature)), Some(sha256), Some(sha384), Some(sha512)) =
+        (signature, sha256_digest, sha384_digest, sha512_digest)
+    {
+        let digests = SignedDigests {
+            sha256,
+            sha384,
+            sha512,
+        };
+        trust_store.verify(&digests, &signature)?;
+    }
+    Ok(())
+}
+
+fn check_digest(
+    algorithm: &'static str,
+    digest: &[u8],
+    expected: &str,
+) -> Result<(), VerificationError> {
+    let actual: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(VerificationError::DigestMismatch {
+            algorithm,
+            expected: expected.to_string(),
+            actual,
+        })
+    }
+}
@@ -0,0 +1,192 @@
+//! The MQTT 3.1.1 and MQTT 5 clients of `rumqttc` behind a common interface
+//!
+//! `rumqttc` provides two distinct clients, with distinct packet types,
+//! the MQTT 5 packets carrying properties that have no MQTT 3.1.1 counterparts.
+//! The connection only uses the features shared by both protocols,
+//! the MQTT 5 properties being carried by the messages.
+use crate::messages::qos_to_v5;
+use crate::Config;
+use crate::MqttError;
+use crate::MqttMessage;
+use crate::MqttProtocol;
+use rumqttc::v5;
+use rumqttc::Event;
+use rumqttc::Outgoing;
+use rumqttc::Packet;
+use rumqttc::SubscribeFilter;
+
+/// An MQTT client using the protocol version of the connection config
+#[derive(Clone)]
+pub(crate) enum MqttClient {
+    V311(rumqttc::AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+/// The event loop of an [MqttClient]
+pub(crate) enum MqttEventLoop {
+    V311(rumqttc::EventLoop),
+    V5(v5::EventLoop),
+}
+
+/// The MQTT events processed by a connection, whatever the protocol version
+pub(crate) enum MqttEvent {
+    ConnAck {
+        session_present: bool,
+        error: Option<MqttError>,
+    },
+    SubAck {
+        error: Option<MqttError>,
+    },
+    Publish(MqttMessage),
+    /// Either sent or received
+    Disconnect,
+    OutgoingPublish(u16),
+    PubAck(u16),
+    PubComp(u16),
+    Other,
+}
+
+impl MqttClient {
+    pub(crate) fn new(config: &Config) -> Result<(MqttClient, MqttEventLoop), MqttError> {
+        match config.protocol {
+            MqttProtocol::V311 => {
+                let mqtt_options = config.rumqttc_options()?;
+                let (client, event_loop) =
+                    rumqttc::AsyncClient::new(mqtt_options, config.queue_capacity);
+                Ok((MqttClient::V311(client), MqttEventLoop::V311(event_loop)))
+            }
+            MqttProtocol::V5 => {
+                let mqtt_options = config.rumqttc_v5_options()?;
+                let (client, event_loop) =
+                    v5::AsyncClient::new(mqtt_options, config.queue_capacity);
+                Ok((MqttClient::V5(client), MqttEventLoop::V5(event_loop)))
+            }
+        }
+    }
+
+    /// Publish a message, along its properties when connected with MQTT 5
+    pub(crate) async fn publish(&self, message: MqttMessage) -> Result<(), MqttError> {
+        let payload = Vec::from(message.payload_bytes());
+        match self {
+            MqttClient::V311(client) => {
+                client
+                    .publish(message.topic, message.qos, message.retain, payload)
+                    .await?
+            }
+            MqttClient::V5(client) if message.properties.is_empty() => {
+                client
+                    .publish(
+                        message.topic.name,
+                        qos_to_v5(message.qos),
+                        message.retain,
+                        payload,
+                    )
+                    .await?
+            }
+            MqttClient::V5(client) => {
+                client
+                    .publish_with_properties(
+                        message.topic.name,
+                        qos_to_v5(message.qos),
+                        message.retain,
+                        payload,
+                        message.properties.into(),
+                    )
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn subscribe_many(
+        &self,
+        filters: Vec<SubscribeFilter>,
+    ) -> Result<(), MqttError> {
+        match self {
+            MqttClient::V311(client) => client.subscribe_many(filters).await?,
+            MqttClient::V5(client) => {
+                let filters = filters.into_iter().map(|filter| {
+                    v5::mqttbytes::v5::Filter::new(filter.path, qos_to_v5(filter.qos))
+                });
+                client.subscribe_many(filters).await?
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn unsubscribe(&self, topic: String) -> Result<(), MqttError> {
+        match self {
+            MqttClient::V311(client) => client.unsubscribe(topic).await?,
+            MqttClient::V5(client) => client.unsubscribe(topic).await?,
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn disconnect(&self) -> Result<(), MqttError> {
+        match self {
+            MqttClient::V311(client) => client.disconnect().await?,
+            MqttClient::V5(client) => client.disconnect().await?,
+        }
+        Ok(())
+    }
+}
+
+impl MqttEventLoop {
+    pub(crate) async fn poll(&mut self) -> Result<MqttEvent, MqttError> {
+        match self {
+            MqttEventLoop::V311(event_loop) => Ok(event_loop.poll().await?.into()),
+            MqttEventLoop::V5(event_loop) => Ok(event_loop.poll().await?.into()),
+        }
+    }
+
+    /// The number of published messages awaiting acknowledgement
+    pub(crate) fn inflight(&self) -> u16 {
+        match self {
+            MqttEventLoop::V311(event_loop) => event_loop.state.inflight(),
+            MqttEventLoop::V5(event_loop) => event_loop.state.inflight(),
+        }
+    }
+}
+
+impl From<Event> for MqttEvent {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::Incoming(Packet::ConnAck(ack)) => MqttEvent::ConnAck {
+                session_present: ack.session_present,
+                error: MqttError::maybe_connection_error(&ack),
+            },
+            Event::Incoming(Packet::SubAck(ack)) => MqttEvent::SubAck {
+                error: MqttError::maybe_subscription_error(&ack),
+            },
+            Event::Incoming(Packet::Publish(msg)) => MqttEvent::Publish(msg.into()),
+            Event::Incoming(Packet::Disconnect) | Event::Outgoing(Outgoing::Disconnect) => {
+                MqttEvent::Disconnect
+            }
+            Event::Outgoing(Outgoing::Publish(pkid)) => MqttEvent::OutgoingPublish(pkid),
+            Event::Incoming(Packet::PubAck(ack)) => MqttEvent::PubAck(ack.pkid),
+            Event::Incoming(Packet::PubComp(ack)) => MqttEvent::PubComp(ack.pkid),
+            _ => MqttEvent::Other,
+        }
+    }
+}
+
+impl From<v5::Event> for MqttEvent {
+    fn from(event: v5::Event) -> Self {
+        match event {
+            v5::Event::Incoming(v5::Incoming::ConnAck(ack)) => MqttEvent::ConnAck {
+                session_present: ack.session_present,
+                error: MqttError::maybe_v5_connection_error(&ack),
+            },
+            v5::Event::Incoming(v5::Incoming::SubAck(ack)) => MqttEvent::SubAck {
+                error: MqttError::maybe_v5_subscription_error(&ack),
+            },
+            v5::Event::Incoming(v5::Incoming::Publish(msg)) => MqttEvent::Publish(msg.into()),
+            v5::Event::Incoming(v5::Incoming::Disconnect(_))
+            | v5::Event::Outgoing(Outgoing::Disconnect) => MqttEvent::Disconnect,
+            v5::Event::Outgoing(Outgoing::Publish(pkid)) => MqttEvent::OutgoingPublish(pkid),
+            v5::Event::Incoming(v5::Incoming::PubAck(ack)) => MqttEvent::PubAck(ack.pkid),
+            v5::Event::Incoming(v5::Incoming::PubComp(ack)) => MqttEvent::PubComp(ack.pkid),
+            _ => MqttEvent::Other,
+        }
+    }
+}
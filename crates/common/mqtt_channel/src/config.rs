@@ -1,9 +1,11 @@
+use crate::messages::qos_to_v5;
 use crate::MqttMessage;
 use crate::TopicFilter;
 use certificate::parse_root_certificate;
 use certificate::CertificateError;
 use rumqttc::tokio_rustls::rustls;
 use rumqttc::tokio_rustls::rustls::pki_types::CertificateDer;
+use rumqttc::v5::mqttbytes::v5::LastWillProperties;
 use rumqttc::LastWill;
 use std::fmt::Debug;
 use std::fmt::Formatter;
@@ -73,6 +75,31 @@ pub struct Config {
     ///
     /// Default: `None`
     pub connection_timeout: Option<Duration>,
+
+    /// Version of the MQTT protocol used to connect the broker
+    ///
+    /// Default: `MqttProtocol::V311`
+    pub protocol: MqttProtocol,
+}
+
+/// Version of the MQTT protocol
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum MqttProtocol {
+    /// MQTT 3.1.1, with no message properties
+    #[default]
+    V311,
+
+    /// MQTT 5, with message properties and shared subscriptions
+    V5,
+}
+
+impl std::fmt::Display for MqttProtocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MqttProtocol::V311 => write!(f, "MQTT 3.1.1"),
+            MqttProtocol::V5 => write!(f, "MQTT 5"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -278,6 +305,7 @@ impl Default for Config {
             last_will_message: None,
             initial_message: None,
             connection_timeout: None,
+            protocol: MqttProtocol::default(),
         }
     }
 }
@@ -402,6 +430,11 @@ impl Config {
         }
     }
 
+    /// Set the version of the MQTT protocol
+    pub fn with_protocol(self, protocol: MqttProtocol) -> Self {
+        Self { protocol, ..self }
+    }
+
     pub fn with_client_auth(
         &mut self,
         config: AuthenticationConfig,
@@ -454,6 +487,65 @@ impl Config {
 
         Ok(mqtt_options)
     }
+
+    /// Wrap this config into an internal set of options for the MQTT 5 client of `rumqttc`.
+    pub fn rumqttc_v5_options(&self) -> Result<rumqttc::v5::MqttOptions, rustls::Error> {
+        let id = match &self.session_name {
+            None => std::iter::repeat_with(fastrand::lowercase)
+                .take(10)
+                .collect(),
+            Some(name) => name.clone(),
+        };
+
+        let broker_config = &self.broker;
+
+        let mut mqtt_options =
+            rumqttc::v5::MqttOptions::new(id, &broker_config.host, broker_config.port);
+
+        if self.session_name.is_none() {
+            // There is no point to have a session with a random name that will not be reused.
+            mqtt_options.set_clean_start(true);
+        } else {
+            mqtt_options.set_clean_start(self.clean_session);
+            // Unlike with MQTT 3.1.1, an MQTT 5 session ends with the connection unless an expiry interval is set
+            if !self.clean_session {
+                mqtt_options.set_session_expiry_interval(Some(u32::MAX));
+            }
+        }
+
+        if let Some(authentication_config) = &broker_config.authentication {
+            if let Some((username, password)) = authentication_config.get_credentials() {
+                mqtt_options.set_credentials(username, password.clone().to_string());
+            }
+            if let Some(tls_config) = authentication_config.to_rustls_client_config()? {
+                mqtt_options.set_transport(rumqttc::Transport::tls_with_config(tls_config.into()));
+            }
+        }
+
+        mqtt_options.set_max_packet_size(Some(MAX_PACKET_SIZE as u32));
+
+        if let Some(lwp) = &self.last_will_message {
+            let properties = (!lwp.properties.is_empty()).then(|| LastWillProperties {
+                delay_interval: None,
+                payload_format_indicator: None,
+                message_expiry_interval: lwp.properties.message_expiry_interval,
+                content_type: lwp.properties.content_type.clone(),
+                response_topic: lwp.properties.response_topic.clone(),
+                correlation_data: lwp.properties.correlation_data.clone().map(Into::into),
+                user_properties: lwp.properties.user_properties.clone(),
+            });
+            let last_will_message = rumqttc::v5::mqttbytes::v5::LastWill::new(
+                &lwp.topic.name,
+                lwp.payload().clone(),
+                qos_to_v5(lwp.qos),
+                lwp.retain,
+                properties,
+            );
+            mqtt_options.set_last_will(last_will_message);
+        }
+
+        Ok(mqtt_options)
+    }
 }
 
 /// Read the first line of the given file and return it.
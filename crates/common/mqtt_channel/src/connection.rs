@@ -1,3 +1,6 @@
+use crate::client::MqttClient;
+use crate::client::MqttEvent;
+use crate::client::MqttEventLoop;
 use crate::Config;
 use crate::ErrChannel;
 use crate::MqttError;
@@ -9,11 +12,6 @@ use futures::channel::mpsc;
 use futures::channel::oneshot;
 use futures::SinkExt;
 use futures::StreamExt;
-use rumqttc::AsyncClient;
-use rumqttc::Event;
-use rumqttc::EventLoop;
-use rumqttc::Outgoing;
-use rumqttc::Packet;
 use rumqttc::SubscribeFilter;
 use std::collections::HashSet;
 use std::sync::atomic::AtomicUsize;
@@ -49,12 +47,12 @@ pub struct Connection {
 #[derive(Clone)]
 /// A client for changing the subscribed topics
 pub struct SubscriberHandle {
-    client: AsyncClient,
+    client: MqttClient,
     pub(crate) subscriptions: Arc<Mutex<TopicFilter>>,
 }
 
 impl SubscriberHandle {
-    pub fn new(client: AsyncClient, subscriptions: Arc<Mutex<TopicFilter>>) -> Self {
+    pub(crate) fn new(client: MqttClient, subscriptions: Arc<Mutex<TopicFilter>>) -> Self {
         Self {
             client,
             subscriptions,
@@ -88,10 +86,15 @@ impl SubscriberOps for SubscriberHandle {
             }
         }
         self.client
-            .subscribe_many(topics.into_iter().map(|path| SubscribeFilter {
-                path,
-                qos: rumqttc::QoS::AtLeastOnce,
-            }))
+            .subscribe_many(
+                topics
+                    .into_iter()
+                    .map(|path| SubscribeFilter {
+                        path,
+                        qos: rumqttc::QoS::AtLeastOnce,
+                    })
+                    .collect(),
+            )
             .await?;
         Ok(())
     }
@@ -222,7 +225,7 @@ impl Connection {
         mut message_sender: mpsc::UnboundedSender<MqttMessage>,
         mut error_sender: mpsc::UnboundedSender<MqttError>,
         subscriptions: Arc<Mutex<TopicFilter>>,
-    ) -> Result<(AsyncClient, EventLoop), MqttError> {
+    ) -> Result<(MqttClient, MqttEventLoop), MqttError> {
         const INSECURE_MQTT_PORT: u16 = 1883;
         const SECURE_MQTT_PORT: u16 = 8883;
 
@@ -233,12 +236,11 @@ impl Connection {
             warn!(target: "MQTT", "Connecting on port 8883 for secure MQTT without a CA file");
         }
 
-        let mqtt_options = config.rumqttc_options()?;
-        let (mqtt_client, mut event_loop) = AsyncClient::new(mqtt_options, config.queue_capacity);
+        let (mqtt_client, mut event_loop) = MqttClient::new(config)?;
 
         info!(target: "MQTT",
-            "Connecting to broker: host={}:{}, session_name={:?}",
-            config.broker.host, config.broker.port, config.session_name
+            "Connecting to broker: host={}:{}, session_name={:?}, protocol={}",
+            config.broker.host, config.broker.port, config.session_name, config.protocol
         );
 
         // None / zero means retry forever (historical behaviour).
@@ -263,8 +265,8 @@ impl Connection {
             };
 
             match poll_result {
-                Ok(Event::Incoming(Packet::ConnAck(ack))) => {
-                    if let Some(err) = MqttError::maybe_connection_error(&ack) {
+                Ok(MqttEvent::ConnAck { error, .. }) => {
+                    if let Some(err) = error {
                         return Err(err);
                     };
                     info!(target: "MQTT", "Connection established");
@@ -279,22 +281,22 @@ impl Connection {
                     Connection::subscribe_to_topics(&mqtt_client, subscriptions).await?
                 }
 
-                Ok(Event::Incoming(Packet::SubAck(ack))) => {
-                    if let Some(err) = MqttError::maybe_subscription_error(&ack) {
+                Ok(MqttEvent::SubAck { error }) => {
+                    if let Some(err) = error {
                         return Err(err);
                     };
                     break;
                 }
 
-                Ok(Event::Incoming(Packet::Publish(msg))) => {
+                Ok(MqttEvent::Publish(msg)) => {
                     // Messages can be received before a sub ack
                     // Errors on send are ignored: it just means the client has closed the receiving channel.
-                    if msg.payload.len() > config.max_packet_size {
+                    if msg.payload_bytes().len() > config.max_packet_size {
                         error!(target: "MQTT", "Dropping message received on topic {} with payload size {} that exceeds the maximum packet size of {}",
-                            msg.topic, msg.payload.len(), config.max_packet_size);
+                            msg.topic, msg.payload_bytes().len(), config.max_packet_size);
                         continue;
                     }
-                    let _ = message_sender.send(msg.into()).await;
+                    let _ = message_sender.send(msg).await;
                 }
 
                 Err(err) => {
@@ -305,7 +307,7 @@ impl Connection {
                     );
 
                     // Errors on send are ignored: it just means the client has closed the receiving channel.
-                    let _ = error_sender.send(err.into()).await;
+                    let _ = error_sender.send(err).await;
 
                     if let Some(timeout) = connection_timeout {
                         let remaining = timeout.saturating_sub(started_at.elapsed());
@@ -327,9 +329,9 @@ impl Connection {
 
     #[allow(clippy::too_many_arguments)]
     async fn receiver_loop(
-        mqtt_client: AsyncClient,
+        mqtt_client: MqttClient,
         config: Config,
-        mut event_loop: EventLoop,
+        mut event_loop: MqttEventLoop,
         mut message_sender: mpsc::UnboundedSender<MqttMessage>,
         mut error_sender: mpsc::UnboundedSender<MqttError>,
         done: oneshot::Sender<()>,
@@ -349,7 +351,7 @@ impl Connection {
                 // Are there any queued publishes?
                 pub_count.load(Ordering::SeqCst) == 0&&
                 // And are any published messages awaiting acknowledgement?
-                event_loop.state.inflight() == 0;
+                event_loop.inflight() == 0;
             if disconnect_permit.is_some() && !triggered_disconnect && remaining_events_empty {
                 // `sender_loop` is not running and we have no remaining
                 // publishes to process
@@ -376,36 +378,32 @@ impl Connection {
             };
 
             match event {
-                Ok(Event::Incoming(Packet::Publish(msg))) => {
-                    if msg.payload.len() > config.max_packet_size {
+                Ok(MqttEvent::Publish(msg)) => {
+                    if msg.payload_bytes().len() > config.max_packet_size {
                         error!(target: "MQTT", "Dropping message received on topic {} with payload size {} that exceeds the maximum packet size of {}",
-                            msg.topic, msg.payload.len(), config.max_packet_size);
+                            msg.topic, msg.payload_bytes().len(), config.max_packet_size);
                         continue;
                     }
                     // Errors on send are ignored: it just means the client has closed the receiving channel.
                     // One has to continue the loop though, because rumqttc relies on this polling.
-                    let _ = message_sender.send(msg.into()).await;
+                    let _ = message_sender.send(msg).await;
                 }
 
-                Ok(Event::Incoming(Packet::ConnAck(ack))) => {
-                    if let Some(err) = MqttError::maybe_connection_error(&ack) {
+                Ok(MqttEvent::ConnAck {
+                    session_present,
+                    error,
+                }) => {
+                    if let Some(err) = error {
                         error!(target: "MQTT", "Connection Error {err}");
                     } else {
                         info!(target: "MQTT", "Connection re-established");
                         if let Some(ref imsg_fn) = config.initial_message {
                             // publish the initial message on connect
                             let message = imsg_fn.new_init_message();
-                            mqtt_client
-                                .publish(
-                                    message.topic.name.clone(),
-                                    message.qos,
-                                    message.retain,
-                                    message.payload_bytes().to_vec(),
-                                )
-                                .await?;
+                            mqtt_client.publish(message).await?;
                         }
 
-                        if config.session_name.is_none() || !ack.session_present {
+                        if config.session_name.is_none() || !session_present {
                             // Workaround for  https://github.com/bytebeamio/rumqtt/issues/250
                             // If session_name is not provided or if the broker session persistence
                             // is not enabled or working, then re-subscribe
@@ -420,31 +418,26 @@ impl Connection {
                     }
                 }
 
-                Ok(Event::Incoming(Packet::Disconnect))
-                | Ok(Event::Outgoing(Outgoing::Disconnect)) => {
+                Ok(MqttEvent::Disconnect) => {
                     break;
                 }
 
-                Ok(Event::Outgoing(Outgoing::Publish(p))) => {
-                    if !awaiting_ack.contains(&p) {
+                Ok(MqttEvent::OutgoingPublish(pkid)) => {
+                    if !awaiting_ack.contains(&pkid) {
                         pub_count.fetch_sub(1, Ordering::SeqCst);
                     }
-                    awaiting_ack.insert(p);
-                }
-
-                Ok(Event::Incoming(Packet::PubAck(p))) => {
-                    awaiting_ack.remove(&p.pkid);
+                    awaiting_ack.insert(pkid);
                 }
 
-                Ok(Event::Incoming(Packet::PubComp(p))) => {
-                    awaiting_ack.remove(&p.pkid);
+                Ok(MqttEvent::PubAck(pkid)) | Ok(MqttEvent::PubComp(pkid)) => {
+                    awaiting_ack.remove(&pkid);
                 }
 
                 Err(err) => {
                     error!(target: "MQTT", "Connection error: {err}");
 
                     // Errors on send are ignored: it just means the client has closed the receiving channel.
-                    let _ = error_sender.send(err.into()).await;
+                    let _ = error_sender.send(err).await;
 
                     Connection::do_pause().await;
                 }
@@ -469,7 +462,7 @@ impl Connection {
     }
 
     async fn sender_loop(
-        mqtt_client: AsyncClient,
+        mqtt_client: MqttClient,
         mut messages_receiver: mpsc::UnboundedReceiver<MqttMessage>,
         mut error_sender: mpsc::UnboundedSender<MqttError>,
         last_will: Option<MqttMessage>,
@@ -477,12 +470,8 @@ impl Connection {
         pub_count: Arc<AtomicUsize>,
     ) {
         while let Some(message) = messages_receiver.next().await {
-            let payload = Vec::from(message.payload_bytes());
-            if let Err(err) = mqtt_client
-                .publish(message.topic, message.qos, message.retain, payload)
-                .await
-            {
-                let _ = error_sender.send(err.into()).await;
+            if let Err(err) = mqtt_client.publish(message).await {
+                let _ = error_sender.send(err).await;
             } else {
                 pub_count.fetch_add(1, Ordering::SeqCst);
             }
@@ -491,12 +480,7 @@ impl Connection {
         // As the broker doesn't send the last will when the client disconnects gracefully
         // one has first to explicitly send the last will message.
         if let Some(last_will) = last_will {
-            let payload = Vec::from(last_will.payload_bytes());
-            if mqtt_client
-                .publish(last_will.topic, last_will.qos, last_will.retain, payload)
-                .await
-                .is_ok()
-            {
+            if mqtt_client.publish(last_will).await.is_ok() {
                 pub_count.fetch_add(1, Ordering::SeqCst);
             }
         }
@@ -510,12 +494,9 @@ impl Connection {
     }
 
     pub(crate) async fn subscribe_to_topics(
-        mqtt_client: &AsyncClient,
+        mqtt_client: &MqttClient,
         subscriptions: Vec<rumqttc::SubscribeFilter>,
     ) -> Result<(), MqttError> {
-        mqtt_client
-            .subscribe_many(subscriptions)
-            .await
-            .map_err(MqttError::ClientError)
+        mqtt_client.subscribe_many(subscriptions).await
     }
 }
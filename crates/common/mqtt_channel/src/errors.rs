@@ -26,6 +26,15 @@ pub enum MqttError {
     #[error("MQTT connection rejected: {0:?}")]
     ConnectionRejected(rumqttc::ConnectReturnCode),
 
+    #[error("MQTT client error: {0}")]
+    V5ClientError(#[from] rumqttc::v5::ClientError),
+
+    #[error("MQTT connection error: {0}")]
+    V5ConnectionError(#[from] rumqttc::v5::ConnectionError),
+
+    #[error("MQTT connection rejected: {0:?}")]
+    V5ConnectionRejected(rumqttc::v5::mqttbytes::v5::ConnectReturnCode),
+
     #[error("MQTT connection timed out after {timeout:?}")]
     ConnectionTimeout { timeout: Duration },
 
@@ -73,6 +82,25 @@ impl MqttError {
         None
     }
 
+    pub fn maybe_v5_connection_error(
+        ack: &rumqttc::v5::mqttbytes::v5::ConnAck,
+    ) -> Option<MqttError> {
+        match ack.code {
+            rumqttc::v5::mqttbytes::v5::ConnectReturnCode::Success => None,
+            err => Some(MqttError::V5ConnectionRejected(err)),
+        }
+    }
+
+    pub fn maybe_v5_subscription_error(
+        ack: &rumqttc::v5::mqttbytes::v5::SubAck,
+    ) -> Option<MqttError> {
+        use rumqttc::v5::mqttbytes::v5::SubscribeReasonCode;
+        ack.return_codes
+            .iter()
+            .any(|code| !matches!(code, SubscribeReasonCode::Success(_)))
+            .then_some(MqttError::SubscriptionFailure)
+    }
+
     pub fn new_invalid_utf8_payload(bytes: &[u8], from: std::str::Utf8Error) -> MqttError {
         const EXCERPT_LEN: usize = 80;
         let index = from.valid_up_to();
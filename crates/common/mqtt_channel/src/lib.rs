@@ -30,6 +30,7 @@
 #![forbid(unsafe_code)]
 
 mod channel;
+mod client;
 mod config;
 mod connection;
 mod errors;
@@ -1,5 +1,7 @@
 use crate::errors::MqttError;
 use crate::topics::Topic;
+use bytes::Bytes;
+use rumqttc::v5::mqttbytes::v5::PublishProperties;
 use rumqttc::Publish;
 use rumqttc::QoS;
 use serde::Deserialize;
@@ -19,6 +21,67 @@ pub struct MqttMessage {
     #[serde(serialize_with = "serialize_qos", deserialize_with = "deserialize_qos")]
     pub qos: QoS,
     pub retain: bool,
+    #[serde(default, skip_serializing_if = "MqttProperties::is_empty")]
+    pub properties: MqttProperties,
+}
+
+/// The MQTT 5 properties of a message
+///
+/// These properties are only exchanged with the broker over an MQTT 5 connection,
+/// and are dropped when the connection uses MQTT 3.1.1.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MqttProperties {
+    /// Application specific key-value pairs, in order and possibly with duplicated keys
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_properties: Vec<(String, String)>,
+
+    /// MIME type of the payload, e.g. `application/json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+
+    /// Lifetime of the message in seconds, after which the broker discards it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_expiry_interval: Option<u32>,
+
+    /// Topic on which a response to this message is expected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_topic: Option<String>,
+
+    /// Opaque data used to correlate a response with its request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_data: Option<Vec<u8>>,
+}
+
+impl MqttProperties {
+    pub fn is_empty(&self) -> bool {
+        self == &MqttProperties::default()
+    }
+}
+
+impl From<PublishProperties> for MqttProperties {
+    fn from(properties: PublishProperties) -> Self {
+        MqttProperties {
+            user_properties: properties.user_properties,
+            content_type: properties.content_type,
+            message_expiry_interval: properties.message_expiry_interval,
+            response_topic: properties.response_topic,
+            correlation_data: properties.correlation_data.map(|data| data.to_vec()),
+        }
+    }
+}
+
+impl From<MqttProperties> for PublishProperties {
+    fn from(properties: MqttProperties) -> Self {
+        PublishProperties {
+            user_properties: properties.user_properties,
+            content_type: properties.content_type,
+            message_expiry_interval: properties.message_expiry_interval,
+            response_topic: properties.response_topic,
+            correlation_data: properties.correlation_data.map(Bytes::from),
+            ..PublishProperties::default()
+        }
+    }
 }
 
 impl Display for MqttMessage {
@@ -217,6 +280,7 @@ impl MqttMessage {
             payload: DebugPayload::new(payload),
             qos: QoS::AtLeastOnce,
             retain: false,
+            properties: MqttProperties::default(),
         }
     }
 
@@ -224,6 +288,49 @@ impl MqttMessage {
         Self { qos, ..self }
     }
 
+    /// Set the MQTT 5 properties of this message
+    pub fn with_properties(self, properties: MqttProperties) -> Self {
+        Self { properties, ..self }
+    }
+
+    /// Add an MQTT 5 user property to this message
+    pub fn with_user_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties
+            .user_properties
+            .push((key.into(), value.into()));
+        self
+    }
+
+    /// Set the MQTT 5 content type of this message
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.properties.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Set the MQTT 5 response topic and correlation data of this request
+    pub fn with_response_topic(
+        mut self,
+        response_topic: &Topic,
+        correlation_data: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.properties.response_topic = Some(response_topic.name.clone());
+        self.properties.correlation_data = Some(correlation_data.into());
+        self
+    }
+
+    /// Build a response to this message, if a response topic has been given by the requester
+    ///
+    /// The response carries the correlation data of the request, if any.
+    pub fn response<B>(&self, payload: B) -> Option<MqttMessage>
+    where
+        B: Into<Payload>,
+    {
+        let response_topic = self.properties.response_topic.as_ref()?;
+        let mut response = MqttMessage::new(&Topic::new_unchecked(response_topic), payload);
+        response.properties.correlation_data = self.properties.correlation_data.clone();
+        Some(response)
+    }
+
     pub fn with_retain(self) -> Self {
         Self {
             retain: true,
@@ -284,10 +391,63 @@ impl From<Publish> for MqttMessage {
             payload: DebugPayload::new(payload),
             qos,
             retain,
+            properties: MqttProperties::default(),
+        }
+    }
+}
+
+impl From<MqttMessage> for rumqttc::v5::mqttbytes::v5::Publish {
+    fn from(val: MqttMessage) -> Self {
+        let properties = (!val.properties.is_empty()).then(|| val.properties.into());
+        let mut publish = rumqttc::v5::mqttbytes::v5::Publish::new(
+            &val.topic.name,
+            qos_to_v5(val.qos),
+            val.payload.0,
+            properties,
+        );
+        publish.retain = val.retain;
+        publish
+    }
+}
+
+impl From<rumqttc::v5::mqttbytes::v5::Publish> for MqttMessage {
+    fn from(msg: rumqttc::v5::mqttbytes::v5::Publish) -> Self {
+        let rumqttc::v5::mqttbytes::v5::Publish {
+            topic,
+            payload,
+            qos,
+            retain,
+            properties,
+            ..
+        } = msg;
+
+        MqttMessage {
+            topic: Topic::new_unchecked(&String::from_utf8_lossy(&topic)),
+            payload: DebugPayload::new(payload),
+            qos: qos_from_v5(qos),
+            retain,
+            properties: properties.map(MqttProperties::from).unwrap_or_default(),
         }
     }
 }
 
+/// Convert a QoS to its MQTT 5 counterpart, rumqttc using distinct types for MQTT 3.1.1 and MQTT 5
+pub fn qos_to_v5(qos: QoS) -> rumqttc::v5::mqttbytes::QoS {
+    match qos {
+        QoS::AtMostOnce => rumqttc::v5::mqttbytes::QoS::AtMostOnce,
+        QoS::AtLeastOnce => rumqttc::v5::mqttbytes::QoS::AtLeastOnce,
+        QoS::ExactlyOnce => rumqttc::v5::mqttbytes::QoS::ExactlyOnce,
+    }
+}
+
+pub fn qos_from_v5(qos: rumqttc::v5::mqttbytes::QoS) -> QoS {
+    match qos {
+        rumqttc::v5::mqttbytes::QoS::AtMostOnce => QoS::AtMostOnce,
+        rumqttc::v5::mqttbytes::QoS::AtLeastOnce => QoS::AtLeastOnce,
+        rumqttc::v5::mqttbytes::QoS::ExactlyOnce => QoS::ExactlyOnce,
+    }
+}
+
 impl<T, U> From<(T, U)> for MqttMessage
 where
     T: AsRef<str>,
@@ -412,10 +572,76 @@ mod tests {
             payload: DebugPayload::new("test-payload"),
             qos: QoS::AtMostOnce,
             retain: true,
+            properties: MqttProperties::default(),
         };
 
         let json = serde_json::to_value(&message).expect("Serialization failed");
         assert_eq!(json.get("payload").unwrap(), &json!("test-payload"));
+        assert!(json.get("properties").is_none());
+        let deserialized: MqttMessage =
+            serde_json::from_value(json).expect("Deserialization failed");
+        assert_eq!(deserialized, message);
+    }
+
+    #[test]
+    fn properties_are_preserved_by_mqtt5_publish_packets() {
+        let request = MqttMessage::new(&Topic::new_unchecked("te/request"), "{}")
+            .with_qos(QoS::ExactlyOnce)
+            .with_retain()
+            .with_content_type("application/json")
+            .with_user_property("source", "child01")
+            .with_user_property("source", "child02")
+            .with_response_topic(&Topic::new_unchecked("te/response"), b"id-1234".to_vec());
+
+        let publish: rumqttc::v5::mqttbytes::v5::Publish = request.clone().into();
+        assert_eq!(publish.topic, "te/request");
+        assert_eq!(publish.qos, rumqttc::v5::mqttbytes::QoS::ExactlyOnce);
+        assert!(publish.retain);
+        let properties = publish.properties.clone().unwrap();
+        assert_eq!(properties.content_type.as_deref(), Some("application/json"));
+        assert_eq!(properties.response_topic.as_deref(), Some("te/response"));
+
+        let received = MqttMessage::from(publish);
+        assert_eq!(received, request);
+    }
+
+    #[test]
+    fn mqtt5_publish_packets_without_properties() {
+        let message = MqttMessage::new(&Topic::new_unchecked("te/plain"), "hello");
+
+        let publish: rumqttc::v5::mqttbytes::v5::Publish = message.clone().into();
+        assert_eq!(publish.properties, None);
+        assert_eq!(MqttMessage::from(publish), message);
+    }
+
+    #[test]
+    fn responses_carry_the_correlation_data_of_the_request() {
+        let request = MqttMessage::new(&Topic::new_unchecked("te/request"), "ping")
+            .with_response_topic(&Topic::new_unchecked("te/response"), b"id-1234".to_vec());
+
+        let response = request.response("pong").unwrap();
+        assert_eq!(response.topic.name, "te/response");
+        assert_eq!(response.payload_str().unwrap(), "pong");
+        assert_eq!(
+            response.properties.correlation_data.as_deref(),
+            Some(&b"id-1234"[..])
+        );
+
+        let no_response_expected = MqttMessage::new(&Topic::new_unchecked("te/event"), "ping");
+        assert_eq!(no_response_expected.response("pong"), None);
+    }
+
+    #[test]
+    fn properties_are_serialized_when_set() {
+        let message = MqttMessage::new(&Topic::new_unchecked("test"), "payload")
+            .with_user_property("key", "value")
+            .with_content_type("text/plain");
+
+        let json = serde_json::to_value(&message).expect("Serialization failed");
+        assert_eq!(
+            json.get("properties").unwrap(),
+            &json!({"userProperties": [["key", "value"]], "contentType": "text/plain"})
+        );
         let deserialized: MqttMessage =
             serde_json::from_value(json).expect("Deserialization failed");
         assert_eq!(deserialized, message);
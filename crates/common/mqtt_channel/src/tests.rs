@@ -360,6 +360,7 @@ async fn ensure_that_last_will_message_is_delivered() -> Result<(), anyhow::Erro
                 payload: "good bye".to_string().into(),
                 qos: QoS::AtLeastOnce,
                 retain: false,
+                properties: Default::default(),
             });
         let mut con = Connection::new(&mqtt_config).await.expect("a connection");
 
@@ -630,6 +631,7 @@ async fn connection_can_be_closed_after_last_will_published() -> Result<(), anyh
             payload: "test".to_owned().into(),
             qos: QoS::AtLeastOnce,
             retain: true,
+            properties: Default::default(),
         });
 
     let con = Connection::new(&mqtt_config).await?;
@@ -67,7 +67,11 @@ impl TopicFilter {
     pub fn new(pattern: &str) -> Result<TopicFilter, MqttError> {
         let pattern = String::from(pattern);
         let qos = QoS::AtLeastOnce;
-        if !pattern.is_empty() && !pattern.contains('\0') && rumqttc::valid_filter(&pattern) {
+        if !pattern.is_empty()
+            && !pattern.contains('\0')
+            && rumqttc::valid_filter(&pattern)
+            && valid_shared_subscription(&pattern)
+        {
             Ok(TopicFilter {
                 patterns: vec![pattern],
                 qos,
@@ -118,7 +122,7 @@ impl TopicFilter {
     pub fn accept_topic_name(&self, topic: &str) -> bool {
         self.patterns
             .iter()
-            .any(|pattern| rumqttc::matches(topic, pattern))
+            .any(|pattern| rumqttc::matches(topic, unshared_filter(pattern)))
     }
 
     /// Check if the given topic matches this filter pattern.
@@ -200,6 +204,38 @@ impl TopicFilter {
     }
 }
 
+/// The prefix of the MQTT 5 shared subscriptions: `$share/<group>/<filter>`
+const SHARED_SUBSCRIPTION_PREFIX: &str = "$share/";
+
+/// Check if a topic filter is a shared subscription: `$share/<group>/<filter>`
+pub fn is_shared_subscription(pattern: &str) -> bool {
+    pattern.starts_with(SHARED_SUBSCRIPTION_PREFIX)
+}
+
+/// The topic filter of a shared subscription, i.e. the pattern without its `$share/<group>/` prefix
+///
+/// The messages received on a shared subscription are published on topics matching this filter,
+/// the prefix only telling the broker to dispatch these messages among the subscribers of the group.
+pub fn unshared_filter(pattern: &str) -> &str {
+    pattern
+        .strip_prefix(SHARED_SUBSCRIPTION_PREFIX)
+        .and_then(|group_and_filter| group_and_filter.split_once('/'))
+        .map_or(pattern, |(_, filter)| filter)
+}
+
+/// Check that a shared subscription has a group name with no wildcard, and a non-empty filter
+fn valid_shared_subscription(pattern: &str) -> bool {
+    let Some(group_and_filter) = pattern.strip_prefix(SHARED_SUBSCRIPTION_PREFIX) else {
+        return true;
+    };
+    match group_and_filter.split_once('/') {
+        Some((group, filter)) => {
+            !group.is_empty() && !group.contains(['+', '#']) && !filter.is_empty()
+        }
+        None => false,
+    }
+}
+
 impl TryInto<Topic> for &str {
     type Error = MqttError;
 
@@ -329,6 +365,22 @@ mod tests {
         assert!(TopicFilter::new("bad\0filter").is_err());
     }
 
+    #[test]
+    fn check_shared_subscriptions() {
+        assert!(TopicFilter::new("$share/mappers/te/+/+/+/+/m/+").is_ok());
+        assert!(TopicFilter::new("$share//te/#").is_err());
+        assert!(TopicFilter::new("$share/group+/te/#").is_err());
+        assert!(TopicFilter::new("$share/mappers").is_err());
+
+        let filter = TopicFilter::new_unchecked("$share/mappers/te/+/+/+/+/m/+");
+        assert!(filter.accept_topic_name("te/device/main///m/temperature"));
+        assert!(!filter.accept_topic_name("te/device/main///e/login"));
+        assert_eq!(unshared_filter("$share/mappers/te/#"), "te/#");
+        assert_eq!(unshared_filter("te/#"), "te/#");
+        assert!(is_shared_subscription("$share/mappers/te/#"));
+        assert!(!is_shared_subscription("te/#"));
+    }
+
     #[test]
     fn check_removing_overlapping_patterns() {
         let mut topics = TopicFilter::empty();
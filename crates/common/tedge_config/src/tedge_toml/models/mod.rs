@@ -9,6 +9,7 @@ pub mod cryptoki;
 pub mod flag;
 pub mod host_port;
 pub mod ipaddress;
+pub mod mqtt_protocol;
 pub mod path;
 pub mod port;
 pub mod proxy_scheme;
@@ -37,6 +38,7 @@ pub use self::flag::*;
 #[doc(inline)]
 pub use self::host_port::HostPort;
 pub use self::ipaddress::*;
+pub use self::mqtt_protocol::*;
 pub use self::path::*;
 pub use self::port::*;
 pub use self::seconds::*;
@@ -0,0 +1,53 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+/// Version of the MQTT protocol used by the thin-edge clients to connect the local broker
+#[derive(
+    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Eq, PartialEq, doku::Document,
+)]
+pub enum MqttProtocolVersion {
+    #[serde(rename = "3.1.1")]
+    V311,
+    #[serde(rename = "5")]
+    V5,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Failed to parse MQTT protocol version: {input}. Supported values are: 3.1.1, 5")]
+pub struct InvalidMqttProtocolVersion {
+    input: String,
+}
+
+impl FromStr for MqttProtocolVersion {
+    type Err = InvalidMqttProtocolVersion;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "3.1.1" | "v311" => Ok(MqttProtocolVersion::V311),
+            "5" | "v5" => Ok(MqttProtocolVersion::V5),
+            _ => Err(InvalidMqttProtocolVersion {
+                input: input.to_string(),
+            }),
+        }
+    }
+}
+
+impl Display for MqttProtocolVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            MqttProtocolVersion::V311 => "3.1.1",
+            MqttProtocolVersion::V5 => "5",
+        };
+        output.fmt(f)
+    }
+}
+
+impl From<MqttProtocolVersion> for mqtt_channel::MqttProtocol {
+    fn from(version: MqttProtocolVersion) -> Self {
+        match version {
+            MqttProtocolVersion::V311 => mqtt_channel::MqttProtocol::V311,
+            MqttProtocolVersion::V5 => mqtt_channel::MqttProtocol::V5,
+        }
+    }
+}
@@ -24,6 +24,7 @@ use super::models::ConnectUrl;
 use super::models::Cryptoki;
 use super::models::HostPort;
 use super::models::MqttPayloadLimit;
+use super::models::MqttProtocolVersion;
 use super::models::SecondsOrHumanTime;
 use super::models::SoftwareManagementApiFlag;
 use super::models::TemplatesSet;
@@ -577,6 +578,30 @@ define_tedge_config! {
             minimum_duration: SecondsOrHumanTime,
         },
 
+        renewal: {
+            /// Enable the automatic renewal of the device certificates by the agent
+            #[tedge_config(example = "true", default(value = false))]
+            enable: bool,
+
+            /// Fraction of the certificate validity period after which the agent renews the certificate
+            #[tedge_config(note = "The value must be greater than 0 and lower than 1")]
+            #[tedge_config(example = "0.75", default(value = 0.75f64))]
+            threshold: f64,
+
+            /// Interval at which the agent checks the validity of the device certificates
+            #[tedge_config(example = "1h", default(from_str = "1h"))]
+            interval: SecondsOrHumanTime,
+
+            /// The clouds for which the agent renews the device certificate, each with an optional profile
+            #[tedge_config(example = "c8y", example = "c8y,aws@production", default(function = "TemplatesSet::default"))]
+            clouds: TemplatesSet,
+
+            /// Certificate Authority used to renew the device certificates
+            #[tedge_config(note = "One of `c8y`, `est` or `self-signed`, as for `tedge cert renew --ca`")]
+            #[tedge_config(example = "est", default(value = "c8y"))]
+            ca: String,
+        },
+
         /// Organization name used for certificate signing requests
         #[tedge_config(example = "ACME", default(value = "Thin Edge"))]
         organization: Arc<str>,
@@ -584,6 +609,25 @@ define_tedge_config! {
         /// Organization unit used for certificate signing requests
         #[tedge_config(example = "IoT", default(value = "Device"))]
         organization_unit: Arc<str>,
+
+        est: {
+            /// EST server (RFC 7030) used to enroll and renew the device certificate, with optional port
+            #[tedge_config(example = "est.example.com", example = "est.example.com:8443")]
+            url: HostPort<HTTPS_PORT>,
+
+            /// Label of the Certificate Authority to use, when the EST server hosts several CAs
+            #[tedge_config(example = "iot-devices")]
+            label: String,
+
+            /// The path where the root certificate(s) used to authenticate the EST server are stored
+            #[tedge_config(note = "The value can be a directory path as well as the path of the certificate file.")]
+            #[tedge_config(example = "/etc/tedge/est-trusted-root-certificates.pem", default(function = "default_root_cert_path"))]
+            root_cert_path: AbsolutePath,
+
+            /// The path where the CA certificates returned by the EST server are stored
+            #[tedge_config(example = "/etc/tedge/device-certs/est-ca-certificates.pem", default(function = "default_est_ca_path"))]
+            ca_path: AbsolutePath,
+        },
     },
 
     #[tedge_config(multi, reader(private))]
@@ -1123,6 +1167,11 @@ define_tedge_config! {
             #[doku(as = "u16")]
             port: NonZeroU16,
 
+            /// The version of the MQTT protocol used by the thin-edge MQTT clients
+            #[tedge_config(note = "MQTT 5 is required to exchange message properties such as user properties, content type, message expiry and response topics, as well as to use shared subscriptions.")]
+            #[tedge_config(example = "3.1.1", example = "5", default(variable = "MqttProtocolVersion::V311"))]
+            protocol: MqttProtocolVersion,
+
             #[tedge_config(reader(private))]
             auth: {
                 /// Path to the CA certificate used by MQTT clients to use when authenticating the MQTT broker
@@ -1197,6 +1246,11 @@ define_tedge_config! {
             /// Enables the built-in bridge when running tedge-mapper
             built_in: bool,
 
+            /// The version of the MQTT protocol used by the built-in bridge to connect both the local and the cloud brokers
+            #[tedge_config(note = "MQTT 5 is required to forward the message properties such as user properties, content type, message expiry and response topics. Both brokers have to support MQTT 5. After changing this value, run `tedge reconnect <cloud>` to apply the changes")]
+            #[tedge_config(example = "3.1.1", example = "5", default(variable = "MqttProtocolVersion::V311"))]
+            protocol: MqttProtocolVersion,
+
             reconnect_policy: {
                 /// The minimum time the built-in bridge will wait before reconnecting
                 #[tedge_config(example = "30s", default(from_str = "5s"))]
@@ -1210,6 +1264,23 @@ define_tedge_config! {
                 #[tedge_config(example = "5m", default(from_str = "5m"))]
                 reset_window: SecondsOrHumanTime,
             },
+
+            spool: {
+                /// Store cloud-bound messages on disk while the cloud connection is down,
+                /// and forward them in order once reconnected
+                #[tedge_config(example = "true", default(value = false))]
+                #[tedge_config(note = "After changing this value, run `tedge reconnect <cloud>` to apply the changes")]
+                enable: bool,
+
+                /// The maximum number of bytes stored on disk by the built-in bridge spool.
+                /// When exceeded, the oldest messages are dropped first
+                #[tedge_config(example = "10485760", default(value = 10485760u32))]
+                max_size: u32,
+
+                /// The maximum time a message is kept in the spool before being dropped
+                #[tedge_config(example = "1d", default(from_str = "1d"))]
+                max_age: SecondsOrHumanTime,
+            },
         },
     },
 
@@ -1320,6 +1391,13 @@ define_tedge_config! {
             /// The filtering criterion, in form of regex, that is used to filter out packages from the output list
             #[tedge_config(example = "^(glibc|lib|kernel-|iptables-module).*")]
             exclude: String,
+        },
+
+        update: {
+            /// Whether the modules updated by a failed software update are restored to their previous versions
+            #[tedge_config(example = "true", default(value = false))]
+            #[tedge_config(note = "The software plugins have to implement the `rollback` command")]
+            transactional: bool,
         }
     },
 
@@ -1361,6 +1439,14 @@ define_tedge_config! {
         path: AbsolutePath,
     },
 
+    download: {
+        /// Path to a PEM file, or a directory of PEM files, with the certificates and public keys
+        /// trusted to sign the downloaded software packages, firmware and workflow files
+        #[tedge_config(example = "/etc/tedge/download-trust-store")]
+        #[tedge_config(note = "Downloads providing a signature are rejected when no trust store is configured or when it cannot be loaded")]
+        trust_store: AbsolutePath,
+    },
+
     firmware: {
         child: {
             update: {
@@ -1381,6 +1467,10 @@ define_tedge_config! {
         #[tedge_config(example = "unix")]
         #[tedge_config(default(variable = "TimeFormat::Unix"))]
         timestamp_format: TimeFormat,
+
+        /// Delay after which an internal component of a service, with messages waiting for it, is reported as stalled
+        #[tedge_config(example = "60s", default(from_str = "60s"))]
+        stall_timeout: SecondsOrHumanTime,
     },
 
     apt: {
@@ -1738,6 +1828,15 @@ fn default_device_csr(location: &TEdgeConfigLocation) -> AbsolutePath {
         .unwrap()
 }
 
+fn default_est_ca_path(location: &TEdgeConfigLocation) -> AbsolutePath {
+    location
+        .tedge_config_root_path()
+        .join("device-certs")
+        .join("est-ca-certificates.pem")
+        .try_into()
+        .unwrap()
+}
+
 fn default_credentials_path(location: &TEdgeConfigLocation) -> AbsolutePath {
     location
         .tedge_config_root_path()
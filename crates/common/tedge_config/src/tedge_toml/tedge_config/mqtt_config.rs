@@ -157,7 +157,8 @@ impl TEdgeConfig {
 
         let mut mqtt_config = mqtt_channel::Config::default()
             .with_host(host)
-            .with_port(port);
+            .with_port(port)
+            .with_protocol(self.mqtt.client.protocol.into());
 
         let mqtt_client_auth_config = self.mqtt_client_auth_config();
         mqtt_config.with_client_auth(mqtt_client_auth_config.try_into()?)?;
@@ -3,6 +3,7 @@ use camino::Utf8Path;
 use certificate::CloudHttpConfig;
 use csv::ReaderBuilder;
 use download::Downloader;
+use download::TrustStore;
 use regex::Regex;
 use reqwest::Identity;
 use serde::Deserialize;
@@ -59,6 +60,23 @@ pub trait Plugin {
         command_log: Option<&mut CommandLog>,
     ) -> Result<Option<String>, SoftwareError>;
 
+    /// Restore the modules to their state before a failed update
+    ///
+    /// The given updates are those reverting the changes made by the failed update.
+    async fn rollback(
+        &self,
+        updates: &[SoftwareModuleUpdate],
+        command_log: Option<&mut CommandLog>,
+    ) -> Result<(), SoftwareError>;
+
+    /// List all the installed modules, as a reference to restore on a failed update
+    async fn snapshot(
+        &self,
+        command_log: Option<&mut CommandLog>,
+    ) -> Result<Vec<SoftwareModule>, SoftwareError> {
+        self.list(command_log).await
+    }
+
     async fn apply(
         &self,
         update: &SoftwareModuleUpdate,
@@ -77,6 +95,7 @@ pub trait Plugin {
                             download_path,
                             self.identity(),
                             self.cloud_root_certs().clone(),
+                            self.trust_store(),
                         )
                         .await?
                     }
@@ -92,6 +111,9 @@ pub trait Plugin {
     fn identity(&self) -> Option<&Identity>;
     fn cloud_root_certs(&self) -> &CloudHttpConfig;
 
+    /// The keys trusted to sign the downloaded modules
+    fn trust_store(&self) -> Option<&TrustStore>;
+
     async fn apply_all(
         &self,
         mut updates: Vec<SoftwareModuleUpdate>,
@@ -122,6 +144,7 @@ pub trait Plugin {
                     download_path,
                     self.identity(),
                     self.cloud_root_certs().clone(),
+                    self.trust_store(),
                 )
                 .await
                 {
@@ -169,6 +192,24 @@ pub trait Plugin {
         failed_updates
     }
 
+    /// Restore the modules touched by some updates to their state as listed by a snapshot
+    ///
+    /// Return the updates applied to revert the changes, if any.
+    async fn restore(
+        &self,
+        snapshot: &[SoftwareModule],
+        updates: &[SoftwareModuleUpdate],
+        mut command_log: Option<&mut CommandLog>,
+    ) -> Result<Vec<SoftwareModuleUpdate>, SoftwareError> {
+        let current = self.snapshot(command_log.as_deref_mut()).await?;
+        let rollback = rollback_updates(snapshot, &current, updates);
+        if !rollback.is_empty() {
+            info!(target: "SM plugins", "Rolling back {} modules", rollback.len());
+            self.rollback(&rollback, command_log).await?;
+        }
+        Ok(rollback)
+    }
+
     async fn install_from_url(
         &self,
         module: &mut SoftwareModule,
@@ -177,6 +218,7 @@ pub trait Plugin {
         download_path: &Path,
         identity: Option<&Identity>,
         cloud_root_certs: CloudHttpConfig,
+        trust_store: Option<&TrustStore>,
     ) -> Result<(), SoftwareError> {
         let downloader = Self::download_from_url(
             module,
@@ -185,6 +227,7 @@ pub trait Plugin {
             download_path,
             identity,
             cloud_root_certs,
+            trust_store,
         )
         .await?;
         let result = self.install(module, command_log.as_deref_mut()).await;
@@ -200,10 +243,12 @@ pub trait Plugin {
         download_path: &Path,
         identity: Option<&Identity>,
         cloud_root_certs: CloudHttpConfig,
+        trust_store: Option<&TrustStore>,
     ) -> Result<Downloader, SoftwareError> {
         let sm_path = sm_path(&module.name, &module.version, download_path);
-        let downloader =
+        let mut downloader =
             Downloader::new(sm_path, identity.map(|id| id.to_owned()), cloud_root_certs);
+        downloader.set_trust_store(trust_store.cloned());
 
         if let Some(ref mut logger) = command_log {
             logger
@@ -277,6 +322,7 @@ pub struct ExternalPluginCommand {
     include: Option<String>,
     identity: Option<Identity>,
     cloud_root_certs: CloudHttpConfig,
+    trust_store: Option<TrustStore>,
     pub tmp_dir: Arc<Utf8Path>,
 }
 
@@ -302,10 +348,19 @@ impl ExternalPluginCommand {
             include,
             identity,
             cloud_root_certs,
+            trust_store: None,
             tmp_dir,
         }
     }
 
+    /// Sets the keys trusted to sign the modules downloaded by this plugin
+    pub fn with_trust_store(self, trust_store: Option<TrustStore>) -> Self {
+        Self {
+            trust_store,
+            ..self
+        }
+    }
+
     pub fn command(
         &self,
         action: &str,
@@ -345,6 +400,54 @@ impl ExternalPluginCommand {
         Ok(output)
     }
 
+    /// Execute a plugin command, passing the list of updates on its stdin
+    pub async fn execute_with_updates(
+        &self,
+        action: &str,
+        updates: &[SoftwareModuleUpdate],
+        command_log: Option<&mut CommandLog>,
+    ) -> Result<Output, SoftwareError> {
+        let mut command = self.command(action, None)?;
+
+        let mut child = command.spawn()?;
+        let child_stdin =
+            child
+                .inner_child
+                .stdin
+                .as_mut()
+                .ok_or_else(|| SoftwareError::IoError {
+                    reason: "Plugin stdin unavailable".into(),
+                })?;
+
+        for update in updates {
+            let action = match update {
+                SoftwareModuleUpdate::Install { module } => {
+                    format!(
+                        "install\t{}\t{}\t{}\n",
+                        module.name,
+                        module.version.clone().map_or("".into(), |v| v),
+                        module.file_path.clone().map_or("".into(), |v| v
+                            .to_str()
+                            .map_or("".into(), |u| u.to_string()))
+                    )
+                }
+
+                SoftwareModuleUpdate::Remove { module } => {
+                    format!(
+                        "remove\t{}\t{}\t\n",
+                        module.name,
+                        module.version.clone().map_or("".into(), |v| v),
+                    )
+                }
+            };
+
+            child_stdin.write_all(action.as_bytes()).await?;
+            child_stdin.flush().await?;
+        }
+
+        Ok(child.wait_with_output(command_log).await?)
+    }
+
     pub fn content(&self, bytes: Vec<u8>) -> Result<String, SoftwareError> {
         String::from_utf8(bytes).map_err(|err| self.plugin_error(err))
     }
@@ -374,6 +477,7 @@ const PREPARE: &str = "prepare";
 const INSTALL: &str = "install";
 const REMOVE: &str = "remove";
 const UPDATE_LIST: &str = "update-list";
+const ROLLBACK: &str = "rollback";
 const FINALIZE: &str = "finalize";
 pub const LIST: &str = "list";
 const VERSION: &str = "version";
@@ -435,45 +539,9 @@ impl Plugin for ExternalPluginCommand {
         updates: &[SoftwareModuleUpdate],
         command_log: Option<&mut CommandLog>,
     ) -> Result<(), SoftwareError> {
-        let mut command = self.command(UPDATE_LIST, None)?;
-
-        let mut child = command.spawn()?;
-        let child_stdin =
-            child
-                .inner_child
-                .stdin
-                .as_mut()
-                .ok_or_else(|| SoftwareError::IoError {
-                    reason: "Plugin stdin unavailable".into(),
-                })?;
-
-        for update in updates {
-            let action = match update {
-                SoftwareModuleUpdate::Install { module } => {
-                    format!(
-                        "install\t{}\t{}\t{}\n",
-                        module.name,
-                        module.version.clone().map_or("".into(), |v| v),
-                        module.file_path.clone().map_or("".into(), |v| v
-                            .to_str()
-                            .map_or("".into(), |u| u.to_string()))
-                    )
-                }
-
-                SoftwareModuleUpdate::Remove { module } => {
-                    format!(
-                        "remove\t{}\t{}\t\n",
-                        module.name,
-                        module.version.clone().map_or("".into(), |v| v),
-                    )
-                }
-            };
-
-            child_stdin.write_all(action.as_bytes()).await?;
-            child_stdin.flush().await?;
-        }
-
-        let output = child.wait_with_output(command_log).await?;
+        let output = self
+            .execute_with_updates(UPDATE_LIST, updates, command_log)
+            .await?;
         match output.status.code() {
             Some(0) => Ok(()),
             Some(1) => Err(SoftwareError::UpdateListNotSupported(self.name.clone())),
@@ -488,6 +556,48 @@ impl Plugin for ExternalPluginCommand {
         }
     }
 
+    async fn rollback(
+        &self,
+        updates: &[SoftwareModuleUpdate],
+        command_log: Option<&mut CommandLog>,
+    ) -> Result<(), SoftwareError> {
+        let output = self
+            .execute_with_updates(ROLLBACK, updates, command_log)
+            .await?;
+        match output.status.code() {
+            Some(0) => Ok(()),
+            Some(1) => Err(SoftwareError::RollbackNotSupported(self.name.clone())),
+            Some(_) => Err(SoftwareError::Rollback {
+                software_type: self.name.clone(),
+                reason: self.content(output.stderr)?,
+            }),
+            None => Err(SoftwareError::Rollback {
+                software_type: self.name.clone(),
+                reason: "Interrupted".into(),
+            }),
+        }
+    }
+
+    async fn snapshot(
+        &self,
+        command_log: Option<&mut CommandLog>,
+    ) -> Result<Vec<SoftwareModule>, SoftwareError> {
+        // Unlike `list`, no filter is applied: the whole list is required to restore the modules
+        let command = self.command(LIST, None)?;
+        let output = self.execute(command, command_log).await?;
+        if output.status.success() {
+            Ok(deserialize_module_info(
+                self.name.clone(),
+                &output.stdout[..],
+            )?)
+        } else {
+            Err(SoftwareError::Plugin {
+                software_type: self.name.clone(),
+                reason: self.content(output.stderr)?,
+            })
+        }
+    }
+
     async fn finalize(&self, command_log: Option<&mut CommandLog>) -> Result<(), SoftwareError> {
         let command = self.command(FINALIZE, None)?;
         let output = self.execute(command, command_log).await?;
@@ -593,6 +703,10 @@ impl Plugin for ExternalPluginCommand {
     fn cloud_root_certs(&self) -> &CloudHttpConfig {
         &self.cloud_root_certs
     }
+
+    fn trust_store(&self) -> Option<&TrustStore> {
+        self.trust_store.as_ref()
+    }
 }
 
 pub fn deserialize_module_info(
@@ -618,6 +732,57 @@ pub fn deserialize_module_info(
     Ok(software_list)
 }
 
+/// The updates restoring the modules touched by an update to their versions before this update
+///
+/// - `snapshot` is the list of modules installed before the update,
+/// - `current` is the list of modules installed after the update.
+pub fn rollback_updates(
+    snapshot: &[SoftwareModule],
+    current: &[SoftwareModule],
+    updates: &[SoftwareModuleUpdate],
+) -> Vec<SoftwareModuleUpdate> {
+    let installed = |modules: &[SoftwareModule], name: &str| {
+        modules
+            .iter()
+            .find(|module| module.name == name)
+            .map(|module| module.version.clone())
+    };
+
+    let mut rollback = Vec::new();
+    let mut names = Vec::new();
+    for update in updates {
+        let module = update.module();
+        if names.contains(&&module.name) {
+            continue;
+        }
+        names.push(&module.name);
+
+        let before = installed(snapshot, &module.name);
+        let after = installed(current, &module.name);
+        if before == after {
+            continue;
+        }
+        let rollback_update = match before {
+            Some(version) => SoftwareModuleUpdate::install(SoftwareModule::new(
+                module.module_type.clone(),
+                module.name.clone(),
+                version,
+                None,
+                None,
+            )),
+            None => SoftwareModuleUpdate::remove(SoftwareModule::new(
+                module.module_type.clone(),
+                module.name.clone(),
+                after.flatten(),
+                None,
+                None,
+            )),
+        };
+        rollback.push(rollback_update);
+    }
+    rollback
+}
+
 pub fn sm_path(name: &str, version: &Option<String>, target_dir_path: impl AsRef<Path>) -> PathBuf {
     let mut filename = name.to_string();
     if let Some(version) = version {
@@ -2,6 +2,7 @@ use crate::plugin::ExternalPluginCommand;
 use crate::plugin::Plugin;
 use crate::plugin::LIST;
 use camino::Utf8PathBuf;
+use download::TrustStore;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fs;
@@ -13,6 +14,7 @@ use tedge_api::commands::SoftwareListCommand;
 use tedge_api::commands::SoftwareUpdateCommand;
 use tedge_api::CommandLog;
 use tedge_api::SoftwareError;
+use tedge_api::SoftwareModuleUpdate;
 use tedge_api::SoftwareType;
 use tedge_api::DEFAULT;
 use tedge_config::SudoCommandBuilder;
@@ -46,6 +48,8 @@ pub struct ExternalPlugins {
     default_plugin_type: Option<SoftwareType>,
     sudo: SudoCommandBuilder,
     config_dir: Utf8PathBuf,
+    /// Roll back all the updates of a plugin if any fails
+    transactional: bool,
 }
 
 impl Plugins for ExternalPlugins {
@@ -103,6 +107,7 @@ impl ExternalPlugins {
             default_plugin_type: default_plugin_type.clone(),
             sudo,
             config_dir,
+            transactional: false,
         };
         if let Err(e) = plugins.load().await {
             warn!(target: "SM plugins",
@@ -140,6 +145,18 @@ impl ExternalPlugins {
             .await
             .map_err(|err| io::Error::other(format!("Failed to load tedge config: {}", err)))?;
 
+        self.transactional = config.software.update.transactional;
+
+        // Without a trust store, signed packages are rejected
+        let trust_store = match config.download.trust_store.or_none().map(TrustStore::load) {
+            Some(Ok(trust_store)) => Some(trust_store),
+            Some(Err(err)) => {
+                error!(target: "SM plugins", "Fail to load the download.trust_store: {err}");
+                None
+            }
+            None => None,
+        };
+
         for maybe_entry in fs::read_dir(&self.plugin_dir)? {
             let entry = maybe_entry?;
             let path = entry.path();
@@ -184,7 +201,8 @@ impl ExternalPlugins {
                             identity,
                             config.cloud_root_certs().await?,
                             config.tmp.path.as_path().into(),
-                        );
+                        )
+                        .with_trust_store(trust_store.clone());
                         self.plugin_map.insert(plugin_name.into(), plugin);
                     }
                 }
@@ -232,39 +250,153 @@ impl ExternalPlugins {
         let mut response = request.clone().with_status(CommandStatus::Executing);
         let mut error_messages = Vec::new();
 
+        let failures = if self.transactional {
+            self.apply_transaction(&request, command_log.as_mut(), download_path)
+                .await
+        } else {
+            self.apply_all(&request, command_log.as_mut(), download_path)
+                .await
+        };
+
+        for (software_type, errors) in failures {
+            let message = errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            error_messages.push(message);
+            response.add_errors(&software_type, errors);
+        }
+
+        if let Some(reason) = ExternalPlugins::error_message(error_messages, command_log) {
+            response.with_error(reason)
+        } else {
+            response.with_status(CommandStatus::Successful)
+        }
+    }
+
+    /// Apply the updates of all the software types, independently of each other
+    ///
+    /// Return the errors of the software types that failed.
+    async fn apply_all(
+        &self,
+        request: &SoftwareUpdateCommand,
+        mut command_log: Option<&mut CommandLog>,
+        download_path: &Path,
+    ) -> Vec<(SoftwareType, Vec<SoftwareError>)> {
+        let mut failures = Vec::new();
         for software_type in request.modules_types() {
             let updates = request.updates_for(&software_type);
-            let errors = if let Some(plugin) = self.by_software_type(&software_type) {
-                plugin
-                    .apply_all(updates, command_log.as_mut(), download_path)
-                    .await
-            } else {
-                let error = SoftwareError::UnknownSoftwareType {
-                    software_type: software_type.clone(),
-                    updates,
-                };
-                if let Some(command_log) = &mut command_log {
-                    command_log.log_error(&error.to_string()).await;
+            let errors = match self.by_software_type(&software_type) {
+                Some(plugin) => {
+                    plugin
+                        .apply_all(updates, command_log.as_deref_mut(), download_path)
+                        .await
+                }
+                None => {
+                    let error = Self::unknown_software_type(
+                        software_type.clone(),
+                        updates,
+                        command_log.as_deref_mut(),
+                    )
+                    .await;
+                    vec![error]
                 }
-                vec![error]
             };
+            if !errors.is_empty() {
+                failures.push((software_type, errors));
+            }
+        }
+        failures
+    }
 
+    /// Apply the updates of all the software types or none
+    ///
+    /// The software types are updated one after the other.
+    /// On failure, the remaining types are left untouched,
+    /// and all the types updated so far, including the failed one, are rolled back.
+    async fn apply_transaction(
+        &self,
+        request: &SoftwareUpdateCommand,
+        mut command_log: Option<&mut CommandLog>,
+        download_path: &Path,
+    ) -> Vec<(SoftwareType, Vec<SoftwareError>)> {
+        // Nothing is updated unless all the software types are supported
+        let mut plugins = Vec::new();
+        for software_type in request.modules_types() {
+            let updates = request.updates_for(&software_type);
+            match self.by_software_type(&software_type) {
+                Some(plugin) => plugins.push((software_type, plugin, updates)),
+                None => {
+                    let error = Self::unknown_software_type(
+                        software_type.clone(),
+                        updates,
+                        command_log.as_deref_mut(),
+                    )
+                    .await;
+                    return vec![(software_type, vec![error])];
+                }
+            }
+        }
+
+        let mut applied = Vec::new();
+        let mut failure = None;
+        for (software_type, plugin, updates) in plugins {
+            let snapshot = match plugin.snapshot(command_log.as_deref_mut()).await {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    failure = Some((software_type, vec![err]));
+                    break;
+                }
+            };
+            let errors = plugin
+                .apply_all(updates.clone(), command_log.as_deref_mut(), download_path)
+                .await;
+            applied.push((software_type.clone(), plugin, snapshot, updates));
             if !errors.is_empty() {
-                let message = errors
-                    .iter()
-                    .map(|e| e.to_string())
-                    .collect::<Vec<_>>()
-                    .join(",");
-                error_messages.push(message);
-                response.add_errors(&software_type, errors);
+                failure = Some((software_type, errors));
+                break;
             }
         }
+        let Some(failure) = failure else {
+            return Vec::new();
+        };
 
-        if let Some(reason) = ExternalPlugins::error_message(error_messages, command_log) {
-            response.with_error(reason)
-        } else {
-            response.with_status(CommandStatus::Successful)
+        // Roll back the software types in the reverse order of their updates
+        let mut failures = vec![failure];
+        for (software_type, plugin, snapshot, updates) in applied.into_iter().rev() {
+            let error = match plugin
+                .restore(&snapshot, &updates, command_log.as_deref_mut())
+                .await
+            {
+                Ok(rollback) if rollback.is_empty() => continue,
+                Ok(rollback) => SoftwareError::RolledBack { updates: rollback },
+                Err(err) => err,
+            };
+            match failures
+                .iter_mut()
+                .find(|(failed, _)| *failed == software_type)
+            {
+                Some((_, errors)) => errors.push(error),
+                None => failures.push((software_type, vec![error])),
+            }
+        }
+        failures
+    }
+
+    async fn unknown_software_type(
+        software_type: SoftwareType,
+        updates: Vec<SoftwareModuleUpdate>,
+        command_log: Option<&mut CommandLog>,
+    ) -> SoftwareError {
+        let error = SoftwareError::UnknownSoftwareType {
+            software_type,
+            updates,
+        };
+        if let Some(command_log) = command_log {
+            command_log.log_error(&error.to_string()).await;
         }
+        error
     }
 
     fn error_message(errors: Vec<String>, command_log: Option<CommandLog>) -> Option<String> {
@@ -3,6 +3,7 @@ mod tests {
     use camino::Utf8PathBuf;
     use certificate::CloudHttpConfig;
     use plugin_sm::plugin::deserialize_module_info;
+    use plugin_sm::plugin::rollback_updates;
     use plugin_sm::plugin::sm_path;
     use plugin_sm::plugin::ExternalPluginCommand;
     use std::path::Path;
@@ -10,6 +11,7 @@ mod tests {
     use std::sync::Arc;
     use tedge_api::SoftwareError;
     use tedge_api::SoftwareModule;
+    use tedge_api::SoftwareModuleUpdate;
     use tedge_config::SudoCommandBuilder;
     use tedge_config::TEdgeConfig;
     use test_case::test_case;
@@ -188,6 +190,49 @@ mod tests {
         assert_eq!(res, expected_path);
     }
 
+    #[test]
+    fn rollback_restores_the_modules_touched_by_the_update() {
+        let snapshot = vec![
+            module("a", Some("1.0")),
+            module("b", Some("2.0")),
+            module("d", None),
+            module("untouched", Some("1.0")),
+        ];
+        let current = vec![
+            module("a", Some("1.1")),
+            module("b", Some("2.0")),
+            module("c", Some("3.0")),
+            module("untouched", Some("1.1")),
+        ];
+        let updates = vec![
+            SoftwareModuleUpdate::install(module("a", Some("1.1"))),
+            SoftwareModuleUpdate::install(module("b", Some("2.1"))),
+            SoftwareModuleUpdate::install(module("c", None)),
+            SoftwareModuleUpdate::remove(module("d", None)),
+        ];
+
+        let rollback = rollback_updates(&snapshot, &current, &updates);
+
+        assert_eq!(
+            rollback,
+            vec![
+                SoftwareModuleUpdate::install(module("a", Some("1.0"))),
+                SoftwareModuleUpdate::remove(module("c", Some("3.0"))),
+                SoftwareModuleUpdate::install(module("d", None)),
+            ]
+        );
+    }
+
+    fn module(name: &str, version: Option<&str>) -> SoftwareModule {
+        SoftwareModule::new(
+            Some("test".into()),
+            name.into(),
+            version.map(str::to_string),
+            None,
+            None,
+        )
+    }
+
     fn get_dummy_plugin_path() -> PathBuf {
         // To get the plugin binary path we need to find the `target` directory which is 3 levels above the `Cargo.toml` file of the package
         // CARGO_MANIFEST_DIR == ./thin-edge.io/crates/core/plugin_sm
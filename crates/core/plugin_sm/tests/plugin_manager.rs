@@ -3,6 +3,13 @@ mod tests {
     use plugin_sm::plugin_manager::ExternalPlugins;
     use plugin_sm::plugin_manager::Plugins;
     use std::fs::File;
+    use std::os::unix::fs::PermissionsExt;
+    use tedge_api::commands::CommandStatus;
+    use tedge_api::commands::SoftwareUpdateCommand;
+    use tedge_api::mqtt_topics::EntityTopicId;
+    use tedge_api::SoftwareError;
+    use tedge_api::SoftwareModule;
+    use tedge_api::SoftwareModuleUpdate;
     use tedge_config::SudoCommandBuilder;
     use tedge_test_utils::fs::TempTedgeDir;
 
@@ -73,4 +80,172 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn failed_transaction_is_rolled_back() {
+        let config_dir = transactional_config_dir();
+        fake_plugin(&config_dir, "test", "a\t1.0\nb\t2.0\n");
+        let plugins = load_plugins(&config_dir).await;
+
+        let mut request =
+            SoftwareUpdateCommand::new(&EntityTopicId::default_main_device(), "1234".into());
+        request.add_update(SoftwareModuleUpdate::install(module("test", "a", "1.1")));
+        request.add_update(SoftwareModuleUpdate::install(module(
+            "test", "broken", "1.0",
+        )));
+        request.add_update(SoftwareModuleUpdate::install(module("test", "c", "3.0")));
+
+        let response = plugins.process(request, None, config_dir.path()).await;
+
+        assert!(
+            matches!(response.status(), CommandStatus::Failed { .. }),
+            "{response:?}"
+        );
+        let [failures] = &response.payload.failures[..] else {
+            panic!("Expected a single failed software type: {response:?}");
+        };
+        assert!(
+            failures
+                .errors
+                .first()
+                .unwrap()
+                .starts_with("Failed to install"),
+            "{failures:?}"
+        );
+        let rolled_back = SoftwareError::RolledBack {
+            updates: vec![
+                SoftwareModuleUpdate::install(module("test", "a", "1.0")),
+                SoftwareModuleUpdate::remove(module("test", "c", "3.0")),
+            ],
+        };
+        assert_eq!(failures.errors.last(), Some(&rolled_back.to_string()));
+        assert_eq!(installed_modules(&config_dir, "test"), "a\t1.0\nb\t2.0\n");
+    }
+
+    #[tokio::test]
+    async fn successful_transaction_is_not_rolled_back() {
+        let config_dir = transactional_config_dir();
+        fake_plugin(&config_dir, "test", "a\t1.0\n");
+        let plugins = load_plugins(&config_dir).await;
+
+        let mut request =
+            SoftwareUpdateCommand::new(&EntityTopicId::default_main_device(), "1234".into());
+        request.add_update(SoftwareModuleUpdate::install(module("test", "a", "1.1")));
+        request.add_update(SoftwareModuleUpdate::install(module("test", "c", "3.0")));
+
+        let response = plugins.process(request, None, config_dir.path()).await;
+
+        assert_eq!(response.status(), CommandStatus::Successful, "{response:?}");
+        assert_eq!(installed_modules(&config_dir, "test"), "a\t1.1\nc\t3.0\n");
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_rolls_back_all_the_software_types() {
+        let config_dir = transactional_config_dir();
+        fake_plugin(&config_dir, "first", "a\t1.0\n");
+        fake_plugin(&config_dir, "second", "b\t1.0\n");
+        let plugins = load_plugins(&config_dir).await;
+
+        let mut request =
+            SoftwareUpdateCommand::new(&EntityTopicId::default_main_device(), "1234".into());
+        request.add_update(SoftwareModuleUpdate::install(module("first", "a", "1.1")));
+        request.add_update(SoftwareModuleUpdate::install(module("second", "b", "1.1")));
+        request.add_update(SoftwareModuleUpdate::install(module(
+            "second", "broken", "1.0",
+        )));
+
+        let response = plugins.process(request, None, config_dir.path()).await;
+
+        assert!(
+            matches!(response.status(), CommandStatus::Failed { .. }),
+            "{response:?}"
+        );
+        for software_type in ["first", "second"] {
+            let failures = response
+                .payload
+                .failures
+                .iter()
+                .find(|list| list.plugin_type == software_type)
+                .unwrap_or_else(|| panic!("Expected {software_type} to fail: {response:?}"));
+            assert!(
+                failures
+                    .errors
+                    .iter()
+                    .any(|error| error.starts_with("Rolled back")),
+                "Expected {software_type} to be rolled back: {failures:?}"
+            );
+        }
+        assert_eq!(installed_modules(&config_dir, "first"), "a\t1.0\n");
+        assert_eq!(installed_modules(&config_dir, "second"), "b\t1.0\n");
+    }
+
+    /// A config directory with the software updates applied as transactions
+    fn transactional_config_dir() -> TempTedgeDir {
+        let config_dir = TempTedgeDir::new();
+        config_dir
+            .file("tedge.toml")
+            .with_raw_content("[software.update]\ntransactional = true\n");
+        config_dir.dir("sm-plugins");
+        config_dir.dir("state");
+        config_dir
+    }
+
+    async fn load_plugins(config_dir: &TempTedgeDir) -> ExternalPlugins {
+        let mut plugins = ExternalPlugins::open(
+            config_dir.path().join("sm-plugins"),
+            None,
+            SudoCommandBuilder::enabled(false),
+            config_dir.utf8_path_buf(),
+        )
+        .await
+        .unwrap();
+        plugins.load().await.unwrap();
+        plugins
+    }
+
+    fn module(software_type: &str, name: &str, version: &str) -> SoftwareModule {
+        SoftwareModule::new(
+            Some(software_type.into()),
+            name.into(),
+            Some(version.into()),
+            None,
+            None,
+        )
+    }
+
+    /// A plugin managing the modules listed in a state file, failing to install any `broken` module
+    fn fake_plugin(config_dir: &TempTedgeDir, name: &str, installed: &str) {
+        std::fs::write(config_dir.path().join("state").join(name), installed).unwrap();
+
+        let script = r#"#!/bin/sh
+STATE="$(dirname "$0")/../state/$(basename "$0")"
+TAB="$(printf '\t')"
+case "$1" in
+    list) cat "$STATE" ;;
+    prepare|finalize) ;;
+    install)
+        if [ "$2" = "broken" ]; then echo "broken package" >&2; exit 2; fi
+        grep -v "^$2$TAB" "$STATE" > "$STATE.tmp"
+        printf '%s\t%s\n' "$2" "$4" >> "$STATE.tmp"
+        mv "$STATE.tmp" "$STATE"
+        ;;
+    remove)
+        grep -v "^$2$TAB" "$STATE" > "$STATE.tmp"
+        mv "$STATE.tmp" "$STATE"
+        ;;
+    rollback)
+        while IFS="$TAB" read -r ACTION MODULE VERSION FILE; do
+            "$0" "$ACTION" "$MODULE" --module-version "$VERSION" || exit 2
+        done
+        ;;
+esac
+"#;
+        let plugin_path = config_dir.path().join("sm-plugins").join(name);
+        std::fs::write(&plugin_path, script).unwrap();
+        std::fs::set_permissions(&plugin_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn installed_modules(config_dir: &TempTedgeDir, name: &str) -> String {
+        std::fs::read_to_string(config_dir.path().join("state").join(name)).unwrap()
+    }
 }
@@ -813,6 +813,7 @@ topic = "data"
             local_prefix: local_prefix.into(),
             remote_prefix: remote_prefix.into(),
             topic: topic.into(),
+            options: Default::default(),
         }
     }
 
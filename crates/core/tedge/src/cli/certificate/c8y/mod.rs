@@ -15,7 +15,7 @@ pub use upload::UploadCertCmd;
 /// Create a device private key and CSR
 ///
 /// Return the CSR in the format expected by c8y CA
-async fn create_device_csr(
+pub(super) async fn create_device_csr(
     common_name: String,
     key: super::create_csr::Key,
     current_cert: Option<Utf8PathBuf>,
@@ -38,7 +38,7 @@ async fn create_device_csr(
 }
 
 /// Return the CSR in the format expected by c8y CA
-async fn read_csr_from_file(csr_path: &Utf8PathBuf) -> Result<String, CertError> {
+pub(super) async fn read_csr_from_file(csr_path: &Utf8PathBuf) -> Result<String, CertError> {
     let csr = read_cert_to_string(csr_path).await?;
     Ok(parse_csr_contents(csr))
 }
@@ -67,7 +67,10 @@ fn parse_csr_contents(csr: String) -> String {
 ///
 /// The c8y CA being EST compliant, its response is encoded along PKCS#7
 /// and the x509 certificate has to be extracted from this envelop.
-async fn store_device_cert(cert_path: &Utf8PathBuf, pk7_base64: String) -> Result<(), CertError> {
+pub(super) async fn store_device_cert(
+    cert_path: &Utf8PathBuf,
+    pk7_base64: String,
+) -> Result<(), CertError> {
     let x509_pem = pk7_to_x509(pk7_base64)?;
 
     override_public_key(cert_path, x509_pem).await
@@ -6,6 +6,9 @@ use super::show::ShowCertCmd;
 use crate::certificate_is_self_signed;
 use crate::cli::certificate::c8y;
 use crate::cli::certificate::create_csr::Key;
+use crate::cli::certificate::est;
+use crate::cli::certificate::est::EstClient;
+use crate::cli::certificate::est::EstCredentials;
 use crate::cli::common::Cloud;
 use crate::cli::common::CloudArg;
 use crate::cli::hsm::CreateKeyArgs;
@@ -86,13 +89,14 @@ pub enum TEdgeCertCli {
 
         /// Certificate Authority (CA) used to renew the certificate
         ///
-        /// Cumulocity CA is currently the only supported one and this is the default,
+        /// Cumulocity CA is the default,
         /// even if the current certificate has not been signed by Cumulocity.
         /// In most cases, the default behavior is what you want:
         /// substitute a proper CA-signed certificate for a self-signed certificate.
         ///
         /// However, if this is not the case, or if the cloud endpoint doesn't provide a CA:
-        /// use `--ca self-signed` to get a renewed self-signed certificate.
+        /// use `--ca est` to get a certificate renewed by the EST server set by `certificate.est.url`,
+        /// or `--ca self-signed` to get a renewed self-signed certificate.
         #[clap(long = "ca", default_value_t = CA::C8y, global = true)]
         ca: CA,
 
@@ -100,6 +104,56 @@ pub enum TEdgeCertCli {
         cloud: Option<CloudArg>,
     },
 
+    /// Request a new device certificate from a Certificate Authority
+    ///
+    /// The certificate is requested from the EST server (RFC 7030) set by `certificate.est.url`,
+    /// using a CSR generated with the device id and private key configured for the given cloud profile.
+    /// The certificates of the CA are stored along, at `certificate.est.ca_path`.
+    ///
+    /// When the EST server requires the device to authenticate, the credentials are provided
+    /// with `--username` and `--password`.
+    Request {
+        /// The device identifier to be used as the common name for the certificate
+        #[clap(long = "device-id", global = true)]
+        id: Option<String>,
+
+        /// Path to a Certificate Signing Request (CSR) ready to be used
+        ///
+        /// Providing the CSR is notably required when the request has to be signed
+        /// by a tier tool owning the private key of the device.
+        ///
+        /// If none is provided a CSR is generated using the device id and private key
+        /// configured for the given cloud profile.
+        #[clap(long = "csr-path", global = true, value_hint = ValueHint::FilePath)]
+        csr_path: Option<Utf8PathBuf>,
+
+        /// Certificate Authority (CA) used to issue the certificate
+        ///
+        /// EST is currently the only supported CA for this command.
+        /// Use `tedge cert download c8y` to get a certificate from Cumulocity,
+        /// and `tedge cert create` to get a self-signed certificate.
+        #[clap(long = "ca", default_value_t = CA::Est, global = true)]
+        ca: CA,
+
+        /// Username used to authenticate the device on the EST server
+        #[clap(long = "username", global = true)]
+        #[arg(env = "EST_USERNAME", hide_env_values = true)]
+        username: Option<String>,
+
+        /// Password used to authenticate the device on the EST server
+        #[clap(long = "password", global = true, allow_hyphen_values = true)]
+        #[arg(env = "EST_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+
+        #[clap(long, default_value = "10m", global = true)]
+        #[arg(value_parser = humantime::parse_duration)]
+        /// Maximum time waiting for the certificate request to be approved
+        max_timeout: Duration,
+
+        #[clap(subcommand)]
+        cloud: Option<CloudArg>,
+    },
+
     /// Check if the device certificate has to be renewed
     ///
     /// Exit code:
@@ -155,6 +209,9 @@ pub enum CA {
 
     #[strum(serialize = "c8y")]
     C8y,
+
+    #[strum(serialize = "est")]
+    Est,
 }
 
 #[async_trait::async_trait]
@@ -371,6 +428,54 @@ impl BuildCommand for TEdgeCertCli {
                 cmd.into_boxed()
             }
 
+            TEdgeCertCli::Request {
+                id,
+                csr_path,
+                ca,
+                username,
+                password,
+                max_timeout,
+                cloud,
+            } => {
+                if ca != CA::Est {
+                    return Err(anyhow!(
+                        "Cannot request a certificate from {ca} CA: use `tedge cert download c8y` or `tedge cert create` instead"
+                    )
+                    .into());
+                }
+                let cloud: Option<Cloud> = cloud.map(<_>::try_into).transpose()?;
+                let credentials = match (username, password) {
+                    (Some(username), Some(password)) => Some(EstCredentials { username, password }),
+                    (None, None) => None,
+                    _ => {
+                        return Err(anyhow!(
+                            "--username and --password have to be provided together"
+                        )
+                        .into())
+                    }
+                };
+                let (csr_path, generate_csr) = match csr_path {
+                    None => (config.device_csr_path(cloud.as_ref())?.into(), true),
+                    Some(csr_path) => (csr_path, false),
+                };
+                let cmd = est::RequestCertCmd {
+                    device_id: get_device_id(id, config, &cloud).await?,
+                    est_url: est_url(config)?,
+                    root_cert_path: config.certificate.est.root_cert_path.clone().into(),
+                    credentials,
+                    cert_path: config.device_cert_path(cloud.as_ref())?.into(),
+                    ca_path: config.certificate.est.ca_path.clone().into(),
+                    key: device_key(config, cloud.as_ref())?,
+                    csr_path,
+                    generate_csr,
+                    max_timeout,
+                    csr_template,
+                    user,
+                    group,
+                };
+                cmd.into_boxed()
+            }
+
             TEdgeCertCli::Renew {
                 csr_path,
                 cloud,
@@ -409,6 +514,25 @@ impl BuildCommand for TEdgeCertCli {
                     return Err(
                         anyhow!("Cannot renew certificate with self-signed ca: {cert_path} is not self-signed").into()
                     );
+                } else if ca == CA::Est {
+                    let (csr_path, generate_csr) = match csr_path {
+                        None => (config.device_csr_path(cloud.as_ref())?.into(), true),
+                        Some(csr_path) => (csr_path, false),
+                    };
+                    let cmd = est::RenewCertCmd {
+                        est_url: est_url(config)?,
+                        root_cert_path: config.certificate.est.root_cert_path.clone().into(),
+                        cert_path,
+                        new_cert_path,
+                        ca_path: config.certificate.est.ca_path.clone().into(),
+                        key: device_key(config, cloud.as_ref())?,
+                        csr_path,
+                        generate_csr,
+                        csr_template,
+                        user,
+                        group,
+                    };
+                    cmd.into_boxed()
                 } else {
                     let (csr_path, generate_csr) = match csr_path {
                         None => (config.device_csr_path(cloud.as_ref())?.into(), true),
@@ -493,6 +617,30 @@ pub enum UploadCertCli {
     },
 }
 
+/// The base URL of the EST server configured by `certificate.est.url` and `certificate.est.label`
+fn est_url(config: &TEdgeConfig) -> Result<url::Url, ConfigError> {
+    let server = config.certificate.est.url.or_config_not_set()?;
+    let label = config
+        .certificate
+        .est
+        .label
+        .or_none()
+        .map(|label| label.as_str());
+    Ok(EstClient::base_url(server, label)?)
+}
+
+/// The private key of the device, either stored on the file system or on an HSM
+fn device_key(config: &TEdgeConfig, cloud: Option<&Cloud>) -> Result<Key, ConfigError> {
+    let cloud_config = match cloud {
+        Some(c) => Some(config.as_cloud_config(c.into())?),
+        None => None,
+    };
+    let cryptoki = config.device.cryptoki_config(cloud_config.as_deref())?;
+    Ok(cryptoki
+        .map(super::create_csr::Key::Cryptoki)
+        .unwrap_or(Key::Local(config.device_key_path(cloud)?.into())))
+}
+
 /// Returns the device ID from the config if no ID is provided by CLI
 async fn get_device_id(
     id: Option<String>,
@@ -0,0 +1,415 @@
+//! Enrollment over Secure Transport ([RFC 7030](https://datatracker.ietf.org/doc/html/rfc7030))
+//!
+//! Only the mandatory operations are supported:
+//! - `/cacerts` to get the certificates of the CA,
+//! - `/simpleenroll` to get a first certificate, the device being authenticated with HTTP basic auth,
+//! - `/simplereenroll` to renew a certificate, the device being authenticated by its current certificate.
+mod renew;
+mod request;
+
+use crate::cli::certificate::create_csr::Key;
+use crate::get_webpki_error_from_reqwest;
+use anyhow::anyhow;
+use anyhow::Error;
+use camino::Utf8Path;
+use certificate::parse_root_certificate::create_tls_config;
+use certificate::parse_root_certificate::create_tls_config_cryptoki;
+use certificate::parse_root_certificate::create_tls_config_without_client_cert;
+use hyper::header::CONTENT_TYPE;
+use hyper::header::RETRY_AFTER;
+use hyper::StatusCode;
+pub use renew::RenewCertCmd;
+pub use request::RequestCertCmd;
+use reqwest::Response;
+use std::time::Duration;
+use tedge_config::models::HostPort;
+use tedge_config::models::HTTPS_PORT;
+use url::Url;
+
+/// Delay before polling again the EST server, when the server doesn't tell
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Credentials used to authenticate the device on its first enrollment
+#[derive(Clone, Debug)]
+pub struct EstCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Outcome of an enrollment request
+#[derive(Debug, Eq, PartialEq)]
+pub enum Enrollment {
+    /// The CA issued the certificate, returned as a base64-encoded PKCS#7 envelop
+    Issued(String),
+
+    /// The request has been accepted but is waiting for an approval
+    Pending { retry_after: Duration },
+}
+
+/// An HTTP client for an EST server
+pub struct EstClient {
+    http: reqwest::Client,
+    base_url: Url,
+}
+
+impl EstClient {
+    pub fn new(http: reqwest::Client, base_url: Url) -> Self {
+        EstClient { http, base_url }
+    }
+
+    /// The base URL of the EST operations for a CA
+    ///
+    /// `https://<host>:<port>/.well-known/est[/<label>]`
+    pub fn base_url(server: &HostPort<HTTPS_PORT>, label: Option<&str>) -> Result<Url, Error> {
+        let url = match label {
+            Some(label) => format!("https://{server}/.well-known/est/{label}"),
+            None => format!("https://{server}/.well-known/est"),
+        };
+        Ok(Url::parse(&url)?)
+    }
+
+    /// The HTTP client used for the first enrollment, when the device has no certificate yet
+    pub fn http_client(root_cert_path: &Utf8Path) -> Result<reqwest::Client, Error> {
+        let tls_config = create_tls_config_without_client_cert(root_cert_path)?;
+        Ok(reqwest::Client::builder()
+            .use_preconfigured_tls(tls_config)
+            .build()?)
+    }
+
+    /// The HTTP client used to renew the certificate, the device being authenticated by its current certificate
+    pub fn http_client_with_identity(
+        root_cert_path: &Utf8Path,
+        cert_path: &Utf8Path,
+        key: &Key,
+    ) -> Result<reqwest::Client, Error> {
+        let tls_config = match key {
+            Key::Local(key_path) => create_tls_config(root_cert_path, key_path, cert_path)?,
+            Key::Cryptoki(cryptoki) => {
+                create_tls_config_cryptoki(root_cert_path, cert_path, cryptoki.clone())?
+            }
+        };
+        Ok(reqwest::Client::builder()
+            .use_preconfigured_tls(tls_config)
+            .build()?)
+    }
+
+    /// Request a first certificate for the given CSR
+    pub async fn simple_enroll(
+        &self,
+        csr: &str,
+        credentials: Option<&EstCredentials>,
+    ) -> Result<Enrollment, Error> {
+        let request = self.csr_request("simpleenroll", csr)?;
+        let request = match credentials {
+            Some(credentials) => {
+                request.basic_auth(&credentials.username, Some(&credentials.password))
+            }
+            None => request,
+        };
+        let response = self.send(request).await?;
+        Self::enrollment(response).await
+    }
+
+    /// Request a new certificate for the given CSR, re-using the identity of the current certificate
+    pub async fn simple_reenroll(&self, csr: &str) -> Result<Enrollment, Error> {
+        let request = self.csr_request("simplereenroll", csr)?;
+        let response = self.send(request).await?;
+        Self::enrollment(response).await
+    }
+
+    /// Get the certificates of the CA, as a base64-encoded PKCS#7 envelop
+    pub async fn ca_certs(&self) -> Result<String, Error> {
+        let request = self.http.get(self.operation_url("cacerts")?);
+        let response = self.send(request).await?;
+        match response.status() {
+            StatusCode::OK => Ok(response.text().await?),
+            _ => Err(Self::unexpected_response(response).await),
+        }
+    }
+
+    fn operation_url(&self, operation: &str) -> Result<Url, Error> {
+        let base_url = self.base_url.as_str().trim_end_matches('/');
+        Ok(Url::parse(&format!("{base_url}/{operation}"))?)
+    }
+
+    fn csr_request(&self, operation: &str, csr: &str) -> Result<reqwest::RequestBuilder, Error> {
+        Ok(self
+            .http
+            .post(self.operation_url(operation)?)
+            .header(CONTENT_TYPE, "application/pkcs10")
+            .header("Content-Transfer-Encoding", "base64")
+            .body(csr.to_string()))
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Response, Error> {
+        request.send().await.map_err(|err| {
+            Error::new(get_webpki_error_from_reqwest(err))
+                .context(format!("Fail to connect to EST server {}", self.base_url))
+        })
+    }
+
+    async fn enrollment(response: Response) -> Result<Enrollment, Error> {
+        match response.status() {
+            StatusCode::OK => match response.text().await {
+                Ok(cert) => Ok(Enrollment::Issued(cert)),
+                Err(err) => Err(anyhow!(
+                    "Fail to extract a certificate from the response: {err}"
+                )),
+            },
+            StatusCode::ACCEPTED => {
+                let retry_after = response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(DEFAULT_RETRY_AFTER);
+                Ok(Enrollment::Pending { retry_after })
+            }
+            _ => Err(Self::unexpected_response(response).await),
+        }
+    }
+
+    async fn unexpected_response(response: Response) -> Error {
+        anyhow!(
+            "The request failed with {}:\n\t{}",
+            response.status(),
+            response.text().await.unwrap_or_default()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::HeaderMap;
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::routing::post;
+    use axum::Router;
+    use base64::prelude::*;
+    use hyper::header::AUTHORIZATION;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use x509_parser::certification_request::X509CertificationRequest;
+    use x509_parser::der_parser::asn1_rs::FromDer;
+
+    #[test]
+    fn base_url_with_and_without_label() {
+        let server = HostPort::<HTTPS_PORT>::try_from("est.example.com").unwrap();
+        assert_eq!(
+            EstClient::base_url(&server, None).unwrap().as_str(),
+            "https://est.example.com/.well-known/est"
+        );
+
+        let server = HostPort::<HTTPS_PORT>::try_from("est.example.com:8443").unwrap();
+        assert_eq!(
+            EstClient::base_url(&server, Some("iot-devices"))
+                .unwrap()
+                .as_str(),
+            "https://est.example.com:8443/.well-known/est/iot-devices"
+        );
+    }
+
+    /// A local stand-in for an EST server, signing with a test CA all the CSRs it receives
+    pub struct EstServer {
+        port: u16,
+    }
+
+    struct TestCa {
+        params: rcgen::CertificateParams,
+        key: rcgen::KeyPair,
+        cert: rcgen::Certificate,
+
+        /// Expected `Authorization` header for `/simpleenroll`
+        authorization: String,
+
+        /// Number of enrollment requests to be left pending before issuing a certificate
+        pending: AtomicUsize,
+    }
+
+    impl EstServer {
+        pub fn credentials() -> EstCredentials {
+            EstCredentials {
+                username: "test-device".to_string(),
+                password: "s3cr3t".to_string(),
+            }
+        }
+
+        pub async fn start() -> Self {
+            Self::start_with_pending_requests(0).await
+        }
+
+        pub async fn start_with_pending_requests(pending: usize) -> Self {
+            let ca = Arc::new(TestCa::new(pending));
+            let app = Router::new()
+                .route("/.well-known/est/cacerts", get(ca_certs))
+                .route("/.well-known/est/simpleenroll", post(simple_enroll))
+                .route("/.well-known/est/simplereenroll", post(simple_reenroll))
+                .with_state(ca);
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            EstServer { port }
+        }
+
+        pub fn url(&self) -> Url {
+            Url::parse(&format!("http://127.0.0.1:{}/.well-known/est", self.port)).unwrap()
+        }
+
+        pub fn client(&self) -> EstClient {
+            EstClient::new(reqwest::Client::new(), self.url())
+        }
+    }
+
+    impl TestCa {
+        fn new(pending: usize) -> Self {
+            let key = rcgen::KeyPair::generate().unwrap();
+            let mut params = rcgen::CertificateParams::default();
+            params.distinguished_name = rcgen::DistinguishedName::new();
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, "Test EST CA");
+            params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            let cert = params.self_signed(&key).unwrap();
+
+            let EstCredentials { username, password } = EstServer::credentials();
+            let authorization = format!(
+                "Basic {}",
+                BASE64_STANDARD.encode(format!("{username}:{password}"))
+            );
+
+            TestCa {
+                params,
+                key,
+                cert,
+                authorization,
+                pending: AtomicUsize::new(pending),
+            }
+        }
+
+        /// Sign a base64-encoded CSR, returning the certificate as a base64-encoded PKCS#7 envelop
+        fn sign(&self, csr: &str) -> String {
+            let csr = BASE64_STANDARD
+                .decode(csr.replace(['\n', '\r'], ""))
+                .unwrap();
+            let (_, csr) = X509CertificationRequest::from_der(&csr).unwrap();
+            let info = &csr.certification_request_info;
+            let common_name = info
+                .subject
+                .iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .unwrap()
+                .to_string();
+            let public_key = CsrPublicKey(info.subject_pki.subject_public_key.data.to_vec());
+
+            let mut params = rcgen::CertificateParams::default();
+            params.distinguished_name = rcgen::DistinguishedName::new();
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, common_name);
+            let issuer = rcgen::Issuer::from_params(&self.params, &self.key);
+            let cert = params.signed_by(&public_key, &issuer).unwrap();
+
+            certs_only_pk7(&[cert.der().as_ref()])
+        }
+    }
+
+    /// The public key of a CSR
+    ///
+    /// Only ECDSA P256 keys are expected, as generated by `tedge cert create-csr`.
+    struct CsrPublicKey(Vec<u8>);
+
+    impl rcgen::PublicKeyData for CsrPublicKey {
+        fn der_bytes(&self) -> &[u8] {
+            &self.0
+        }
+
+        fn algorithm(&self) -> &'static rcgen::SignatureAlgorithm {
+            &rcgen::PKCS_ECDSA_P256_SHA256
+        }
+    }
+
+    async fn ca_certs(State(ca): State<Arc<TestCa>>) -> impl IntoResponse {
+        certs_only_response(certs_only_pk7(&[ca.cert.der().as_ref()]))
+    }
+
+    async fn simple_enroll(
+        State(ca): State<Arc<TestCa>>,
+        headers: HeaderMap,
+        csr: String,
+    ) -> axum::response::Response {
+        let authorization = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+        if authorization != Some(ca.authorization.as_str()) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        if ca
+            .pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+        {
+            return (StatusCode::ACCEPTED, [(RETRY_AFTER, "0")]).into_response();
+        }
+        certs_only_response(ca.sign(&csr)).into_response()
+    }
+
+    async fn simple_reenroll(State(ca): State<Arc<TestCa>>, csr: String) -> impl IntoResponse {
+        certs_only_response(ca.sign(&csr))
+    }
+
+    fn certs_only_response(pk7: String) -> impl IntoResponse {
+        (
+            StatusCode::OK,
+            [(
+                CONTENT_TYPE,
+                "application/pkcs7-mime; smime-type=certs-only",
+            )],
+            pk7,
+        )
+    }
+
+    /// Build a degenerate PKCS#7 SignedData with no signers, i.e. a certs-only response
+    fn certs_only_pk7(certs: &[&[u8]]) -> String {
+        const SIGNED_DATA_OID: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+        const DATA_OID: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x01];
+
+        let signed_data = der(
+            0x30,
+            &[
+                der(0x02, &[1]),                   // version
+                der(0x31, &[]),                    // digestAlgorithms
+                der(0x30, &[der(0x06, DATA_OID)]), // encapContentInfo
+                der(0xA0, &certs.concat()),        // certificates
+                der(0x31, &[]),                    // signerInfos
+            ]
+            .concat(),
+        );
+        let content_info = der(
+            0x30,
+            &[der(0x06, SIGNED_DATA_OID), der(0xA0, &signed_data)].concat(),
+        );
+        BASE64_STANDARD.encode(content_info)
+    }
+
+    /// DER encoding of a tag-length-value
+    fn der(tag: u8, value: &[u8]) -> Vec<u8> {
+        let len = value.len();
+        let mut tlv = vec![tag];
+        if len < 0x80 {
+            tlv.push(len as u8);
+        } else {
+            let len_bytes: Vec<u8> = len
+                .to_be_bytes()
+                .into_iter()
+                .skip_while(|b| *b == 0)
+                .collect();
+            tlv.push(0x80 | len_bytes.len() as u8);
+            tlv.extend(len_bytes);
+        }
+        tlv.extend_from_slice(value);
+        tlv
+    }
+}
@@ -0,0 +1,167 @@
+use super::Enrollment;
+use super::EstClient;
+use crate::certificate_cn;
+use crate::cli::certificate::c8y::create_device_csr;
+use crate::cli::certificate::c8y::read_csr_from_file;
+use crate::cli::certificate::c8y::store_device_cert;
+use crate::cli::certificate::create_csr::Key;
+use crate::cli::certificate::show::ShowCertCmd;
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use anyhow::Context;
+use anyhow::Error;
+use camino::Utf8PathBuf;
+use certificate::CsrTemplate;
+use tedge_config::TEdgeConfig;
+use tracing::debug;
+use tracing::instrument;
+use url::Url;
+
+/// Command to renew a device certificate from an EST server
+pub struct RenewCertCmd {
+    /// Base URL of the EST operations
+    pub est_url: Url,
+
+    /// Root certificates used to authenticate the EST server
+    pub root_cert_path: Utf8PathBuf,
+
+    /// The path of the certificate to be renewed
+    pub cert_path: Utf8PathBuf,
+
+    /// The path where the new certificate will be stored
+    pub new_cert_path: Utf8PathBuf,
+
+    /// The path where the certificates of the CA will be stored
+    pub ca_path: Utf8PathBuf,
+
+    /// The private key to re-use
+    pub key: Key,
+
+    /// The path where the device CSR file will be stored
+    pub csr_path: Utf8PathBuf,
+
+    /// Tell if the CSR has to be generated or is ready to be used
+    pub generate_csr: bool,
+
+    /// CSR template
+    pub csr_template: CsrTemplate,
+
+    /// The user who will own the CSR file
+    pub user: String,
+
+    /// The group who will own the CSR file
+    pub group: String,
+}
+
+#[async_trait::async_trait]
+impl Command for RenewCertCmd {
+    fn description(&self) -> String {
+        format!(
+            "renew the device certificate via the EST server {}",
+            self.est_url
+        )
+    }
+
+    async fn execute(&self, _: TEdgeConfig) -> Result<(), MaybeFancy<Error>> {
+        // The device is authenticated by its current certificate
+        let http =
+            EstClient::http_client_with_identity(&self.root_cert_path, &self.cert_path, &self.key)?;
+        let est = EstClient::new(http, self.est_url.clone());
+        self.renew_device_certificate(&est).await?;
+        eprintln!("Certificate renewed successfully");
+        eprintln!("    For an un-interrupted service:");
+        eprintln!("    => the device has to be reconnected to the cloud\n");
+        ShowCertCmd::show(&self.new_cert_path).await?;
+        Ok(())
+    }
+}
+
+impl RenewCertCmd {
+    #[instrument(skip_all)]
+    async fn renew_device_certificate(&self, est: &EstClient) -> Result<(), Error> {
+        if self.generate_csr {
+            let common_name = certificate_cn(&self.cert_path).await?;
+            create_device_csr(
+                common_name,
+                self.key.clone(),
+                Some(self.cert_path.clone()),
+                self.csr_path.clone(),
+                self.csr_template.clone(),
+                self.user.clone(),
+                self.group.clone(),
+            )
+            .await?;
+        }
+        let csr = read_csr_from_file(&self.csr_path).await?;
+        debug!(?self.csr_path, "Created CSR");
+
+        match est.simple_reenroll(&csr).await? {
+            Enrollment::Issued(cert) => store_device_cert(&self.new_cert_path, cert).await?,
+            Enrollment::Pending { retry_after } => anyhow::bail!(
+                "The certificate renewal is pending approval: retry in {} seconds",
+                retry_after.as_secs()
+            ),
+        }
+
+        // The CA certificates might have been renewed too
+        let ca_certs = est
+            .ca_certs()
+            .await
+            .context("Fail to get the CA certificates")?;
+        store_device_cert(&self.ca_path, ca_certs).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::certificate::est::tests::EstServer;
+    use crate::CreateCertCmd;
+    use certificate::PemCertificate;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[tokio::test]
+    async fn renew_self_signed_certificate() {
+        let server = EstServer::start().await;
+        let ttd = TempTedgeDir::new();
+        let dir = ttd.utf8_path();
+        let cert_path = dir.join("tedge-certificate.pem");
+        let key_path = dir.join("tedge-private-key.pem");
+        CreateCertCmd {
+            id: "test-device".to_string(),
+            cert_path: cert_path.clone(),
+            key_path: key_path.clone(),
+            user: "mosquitto".to_string(),
+            group: "mosquitto".to_string(),
+            csr_template: CsrTemplate::default(),
+        }
+        .create_test_certificate(&CsrTemplate::default())
+        .await
+        .unwrap();
+
+        let cmd = RenewCertCmd {
+            est_url: server.url(),
+            root_cert_path: dir.join("unused-root-cert.pem"),
+            cert_path: cert_path.clone(),
+            new_cert_path: dir.join("tedge-certificate.pem.new"),
+            ca_path: dir.join("est-ca-certificates.pem"),
+            key: Key::Local(key_path),
+            csr_path: dir.join("tedge.csr"),
+            generate_csr: true,
+            csr_template: CsrTemplate::default(),
+            user: "mosquitto".to_string(),
+            group: "mosquitto".to_string(),
+        };
+        cmd.renew_device_certificate(&server.client())
+            .await
+            .unwrap();
+
+        let old_cert = PemCertificate::from_pem_file(&cert_path).unwrap();
+        assert_eq!(old_cert.issuer().unwrap(), old_cert.subject().unwrap());
+        let new_cert = PemCertificate::from_pem_file(&cmd.new_cert_path).unwrap();
+        assert_eq!(new_cert.subject_common_name().unwrap(), "test-device");
+        assert_eq!(new_cert.issuer().unwrap(), "CN=Test EST CA");
+        assert!(cmd.ca_path.exists());
+    }
+}
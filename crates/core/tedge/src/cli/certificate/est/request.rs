@@ -0,0 +1,209 @@
+use super::Enrollment;
+use super::EstClient;
+use super::EstCredentials;
+use crate::cli::certificate::c8y::create_device_csr;
+use crate::cli::certificate::c8y::read_csr_from_file;
+use crate::cli::certificate::c8y::store_device_cert;
+use crate::cli::certificate::create_csr::Key;
+use crate::cli::certificate::show::ShowCertCmd;
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use crate::warning;
+use anyhow::Context;
+use anyhow::Error;
+use camino::Utf8PathBuf;
+use certificate::CsrTemplate;
+use std::time::Duration;
+use tedge_config::TEdgeConfig;
+use tracing::debug;
+use tracing::instrument;
+use url::Url;
+
+/// Command to request a first device certificate from an EST server
+pub struct RequestCertCmd {
+    /// The device identifier to be used as the common name for the certificate
+    pub device_id: String,
+
+    /// Base URL of the EST operations
+    pub est_url: Url,
+
+    /// Root certificates used to authenticate the EST server
+    pub root_cert_path: Utf8PathBuf,
+
+    /// Credentials authenticating the device, if required by the EST server
+    pub credentials: Option<EstCredentials>,
+
+    /// The path where the device certificate will be stored
+    pub cert_path: Utf8PathBuf,
+
+    /// The path where the certificates of the CA will be stored
+    pub ca_path: Utf8PathBuf,
+
+    /// The private key of the device
+    pub key: Key,
+
+    /// The path where the device CSR file will be stored
+    pub csr_path: Utf8PathBuf,
+
+    /// Tell if the CSR has to be generated or is ready to be used
+    pub generate_csr: bool,
+
+    /// Maximum time waiting for the request to be approved
+    pub max_timeout: Duration,
+
+    /// CSR template
+    pub csr_template: CsrTemplate,
+
+    /// The user who will own the CSR file
+    pub user: String,
+
+    /// The group who will own the CSR file
+    pub group: String,
+}
+
+#[async_trait::async_trait]
+impl Command for RequestCertCmd {
+    fn description(&self) -> String {
+        format!(
+            "request a certificate from {} for the device {}",
+            self.est_url, self.device_id
+        )
+    }
+
+    async fn execute(&self, _: TEdgeConfig) -> Result<(), MaybeFancy<Error>> {
+        let est = EstClient::new(
+            EstClient::http_client(&self.root_cert_path)?,
+            self.est_url.clone(),
+        );
+        self.request_device_certificate(&est).await?;
+        eprintln!("Certificate received successfully");
+        eprintln!("    => the device can now be connected\n");
+        ShowCertCmd::show(&self.cert_path).await?;
+        Ok(())
+    }
+}
+
+impl RequestCertCmd {
+    #[instrument(skip_all)]
+    async fn request_device_certificate(&self, est: &EstClient) -> Result<(), Error> {
+        if self.generate_csr {
+            create_device_csr(
+                self.device_id.clone(),
+                self.key.clone(),
+                None,
+                self.csr_path.clone(),
+                self.csr_template.clone(),
+                self.user.clone(),
+                self.group.clone(),
+            )
+            .await
+            .with_context(|| format!("Fail to create the device CSR {}", self.csr_path))?;
+        }
+        let csr = read_csr_from_file(&self.csr_path).await?;
+        debug!(?self.csr_path, "Created CSR");
+
+        let started = std::time::Instant::now();
+        let cert = loop {
+            match est.simple_enroll(&csr, self.credentials.as_ref()).await? {
+                Enrollment::Issued(cert) => break cert,
+                Enrollment::Pending { retry_after } => {
+                    if started.elapsed() + retry_after > self.max_timeout {
+                        anyhow::bail!(
+                            "Maximum timeout elapsed. The certificate request is still pending approval"
+                        );
+                    }
+                    warning!(
+                        "The certificate request is pending approval. Will retry in {} seconds",
+                        retry_after.as_secs()
+                    );
+                    tokio::time::sleep(retry_after).await;
+                }
+            }
+        };
+        store_device_cert(&self.cert_path, cert).await?;
+
+        let ca_certs = est
+            .ca_certs()
+            .await
+            .context("Fail to get the CA certificates")?;
+        store_device_cert(&self.ca_path, ca_certs).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::certificate::est::tests::EstServer;
+    use certificate::PemCertificate;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[tokio::test]
+    async fn enroll_device_with_basic_auth() {
+        let server = EstServer::start().await;
+        let ttd = TempTedgeDir::new();
+        let cmd = request_cmd(&ttd, &server, Some(EstServer::credentials()));
+
+        cmd.request_device_certificate(&server.client())
+            .await
+            .unwrap();
+
+        let cert = PemCertificate::from_pem_file(&cmd.cert_path).unwrap();
+        assert_eq!(cert.subject_common_name().unwrap(), "test-device");
+        assert_eq!(cert.issuer().unwrap(), "CN=Test EST CA");
+        let ca = PemCertificate::from_pem_file(&cmd.ca_path).unwrap();
+        assert_eq!(ca.subject().unwrap(), "CN=Test EST CA");
+    }
+
+    #[tokio::test]
+    async fn enrollment_is_rejected_without_credentials() {
+        let server = EstServer::start().await;
+        let ttd = TempTedgeDir::new();
+        let cmd = request_cmd(&ttd, &server, None);
+
+        let err = cmd
+            .request_device_certificate(&server.client())
+            .await
+            .unwrap_err();
+
+        assert!(format!("{err}").contains("401 Unauthorized"), "{err}");
+        assert!(!cmd.cert_path.exists());
+    }
+
+    #[tokio::test]
+    async fn wait_for_the_enrollment_to_be_approved() {
+        let server = EstServer::start_with_pending_requests(2).await;
+        let ttd = TempTedgeDir::new();
+        let cmd = request_cmd(&ttd, &server, Some(EstServer::credentials()));
+
+        cmd.request_device_certificate(&server.client())
+            .await
+            .unwrap();
+
+        let cert = PemCertificate::from_pem_file(&cmd.cert_path).unwrap();
+        assert_eq!(cert.subject_common_name().unwrap(), "test-device");
+    }
+
+    fn request_cmd(
+        ttd: &TempTedgeDir,
+        server: &EstServer,
+        credentials: Option<EstCredentials>,
+    ) -> RequestCertCmd {
+        let dir = ttd.utf8_path();
+        RequestCertCmd {
+            device_id: "test-device".to_string(),
+            est_url: server.url(),
+            root_cert_path: dir.join("unused-root-cert.pem"),
+            credentials,
+            cert_path: dir.join("tedge-certificate.pem"),
+            ca_path: dir.join("est-ca-certificates.pem"),
+            key: Key::Local(dir.join("tedge-private-key.pem")),
+            csr_path: dir.join("tedge.csr"),
+            generate_csr: true,
+            max_timeout: Duration::from_secs(10),
+            csr_template: CsrTemplate::default(),
+            user: "mosquitto".to_string(),
+            group: "mosquitto".to_string(),
+        }
+    }
+}
@@ -7,6 +7,7 @@ mod cli;
 mod create;
 mod create_csr;
 mod error;
+mod est;
 mod remove;
 mod renew;
 mod shift;
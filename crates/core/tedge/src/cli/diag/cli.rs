@@ -1,4 +1,5 @@
 use crate::cli::diag::collect::DiagCollectCommand;
+use crate::cli::diag::runtime::DiagRuntimeCommand;
 use crate::command::BuildCommand;
 use crate::command::Command;
 use crate::warning;
@@ -6,6 +7,9 @@ use crate::ConfigError;
 use camino::Utf8PathBuf;
 use clap::ValueHint;
 use std::collections::BTreeSet;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::service_runtime_request_topic;
+use tedge_api::service_runtime_status_topic;
 use tedge_config::models::AbsolutePath;
 use tedge_config::models::SecondsOrHumanTime;
 use tedge_config::TEdgeConfig;
@@ -42,6 +46,23 @@ pub enum TEdgeDiagCli {
         #[clap(long, default_value = "60s")]
         forceful_timeout: SecondsOrHumanTime,
     },
+
+    /// Display the actors of a running service, with their mailboxes and peers
+    ///
+    /// The service is requested over MQTT for a snapshot of its actors,
+    /// which is printed as a table, or as JSON with --json.
+    Runtime {
+        /// Name of the service, e.g. tedge-agent or tedge-mapper-c8y
+        service: String,
+
+        /// Print the snapshot as received from the service, i.e. as JSON
+        #[clap(long)]
+        json: bool,
+
+        /// How long to wait for the service to respond
+        #[clap(long, default_value = "5s")]
+        timeout: SecondsOrHumanTime,
+    },
 }
 
 #[async_trait::async_trait]
@@ -79,6 +100,42 @@ impl BuildCommand for TEdgeDiagCli {
                 .into_boxed();
                 Ok(cmd)
             }
+            TEdgeDiagCli::Runtime {
+                service,
+                json,
+                timeout,
+            } => {
+                let mqtt_schema = MqttSchema::with_root(config.mqtt.topic_root.clone());
+                let service_topic_id = config
+                    .mqtt
+                    .device_topic_id
+                    .default_service_for_device(&service)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "The device topic id {} doesn't follow the default scheme",
+                            config.mqtt.device_topic_id
+                        )
+                    })?;
+                let request_topic = service_runtime_request_topic(&mqtt_schema, &service_topic_id);
+                let status_topic = service_runtime_status_topic(&mqtt_schema, &service_topic_id);
+                let mqtt_config = config
+                    .mqtt_config()
+                    .map_err(anyhow::Error::from)?
+                    .with_session_prefix("tedge-diag-runtime")
+                    .with_clean_session(true)
+                    .with_subscriptions(status_topic.clone().into());
+
+                let cmd = DiagRuntimeCommand {
+                    service,
+                    mqtt_config,
+                    request_topic,
+                    status_topic,
+                    timeout: timeout.duration(),
+                    json,
+                }
+                .into_boxed();
+                Ok(cmd)
+            }
         }
     }
 }
@@ -1,5 +1,6 @@
 mod cli;
 mod collect;
 mod logger;
+mod runtime;
 
 pub use cli::TEdgeDiagCli;
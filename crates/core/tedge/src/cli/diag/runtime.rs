@@ -0,0 +1,167 @@
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use anyhow::Context;
+use mqtt_channel::MqttMessage;
+use mqtt_channel::PubChannel;
+use mqtt_channel::StreamExt;
+use mqtt_channel::Topic;
+use serde::Deserialize;
+use std::time::Duration;
+use tedge_config::TEdgeConfig;
+
+/// Request a running service for a snapshot of its actors
+pub struct DiagRuntimeCommand {
+    pub service: String,
+    pub mqtt_config: mqtt_channel::Config,
+    pub request_topic: Topic,
+    pub status_topic: Topic,
+    pub timeout: Duration,
+    pub json: bool,
+}
+
+/// A snapshot of the actors of a service, as published by the service
+#[derive(Debug, Deserialize)]
+struct RuntimeSnapshot {
+    actors: Vec<ActorSnapshot>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActorSnapshot {
+    name: String,
+    status: String,
+    #[serde(default)]
+    critical: bool,
+    #[serde(default)]
+    pending: usize,
+    #[serde(default)]
+    received: u64,
+    #[serde(default)]
+    throughput: f64,
+    #[serde(default)]
+    mailboxes: Vec<MailboxSnapshot>,
+    #[serde(default)]
+    sends_to: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MailboxSnapshot {
+    capacity: Option<usize>,
+}
+
+#[async_trait::async_trait]
+impl Command for DiagRuntimeCommand {
+    fn description(&self) -> String {
+        format!("request a snapshot of the actors of {}", self.service)
+    }
+
+    async fn execute(&self, _: TEdgeConfig) -> Result<(), MaybeFancy<anyhow::Error>> {
+        let payload = self.request_snapshot().await?;
+        if self.json {
+            println!("{payload}");
+        } else {
+            let snapshot: RuntimeSnapshot = serde_json::from_str(&payload)
+                .with_context(|| format!("Invalid runtime snapshot received: {payload}"))?;
+            print!("{}", snapshot.table());
+        }
+        Ok(())
+    }
+}
+
+impl DiagRuntimeCommand {
+    async fn request_snapshot(&self) -> Result<String, anyhow::Error> {
+        let mut mqtt = mqtt_channel::Connection::new(&self.mqtt_config).await?;
+        mqtt.published
+            .publish(MqttMessage::new(&self.request_topic, "{}"))
+            .await?;
+
+        let response = tokio::time::timeout(self.timeout, async {
+            while let Some(message) = mqtt.received.next().await {
+                if message.topic == self.status_topic {
+                    return Some(message);
+                }
+            }
+            None
+        })
+        .await;
+        mqtt.close().await;
+
+        match response {
+            Ok(Some(message)) => Ok(message.payload_str()?.to_string()),
+            Ok(None) => anyhow::bail!("Disconnected from the MQTT broker"),
+            Err(_) => anyhow::bail!(
+                "No response received from {} within {:?}: is the service running?",
+                self.service,
+                self.timeout
+            ),
+        }
+    }
+}
+
+impl RuntimeSnapshot {
+    fn table(&self) -> String {
+        let width = self
+            .actors
+            .iter()
+            .map(|actor| actor.name.len())
+            .max()
+            .unwrap_or_default()
+            .max("ACTOR".len());
+        let mut table = format!(
+            "{:width$}  {:8}  {:8}  {:>9}  {:>10}  {:>8}  SENDS TO\n",
+            "ACTOR", "STATUS", "CRITICAL", "PENDING", "RECEIVED", "MSG/S"
+        );
+        for actor in &self.actors {
+            let critical = if actor.critical { "yes" } else { "no" };
+            let capacity: Option<usize> = actor.mailboxes.iter().map(|m| m.capacity).sum();
+            let pending = match capacity {
+                Some(capacity) if !actor.mailboxes.is_empty() => {
+                    format!("{}/{capacity}", actor.pending)
+                }
+                _ => actor.pending.to_string(),
+            };
+            table.push_str(&format!(
+                "{:width$}  {:8}  {:8}  {:>9}  {:>10}  {:>8.2}  {}\n",
+                actor.name,
+                actor.status,
+                critical,
+                pending,
+                actor.received,
+                actor.throughput,
+                actor.sends_to.join(", ")
+            ));
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_runtime_snapshot_as_a_table() {
+        let snapshot: RuntimeSnapshot = serde_json::from_str(
+            r#"{
+                "pid": 1234,
+                "actors": [
+                    {"name":"MQTT-0","status":"up","critical":true,"pending":2,"received":120,"throughput":1.5,
+                     "mailboxes":[{"name":"MQTT","capacity":10,"pending":2,"received":120,"idle":0.1}],
+                     "receives_from":["Converter-1"],"sends_to":["Converter-1"]},
+                    {"name":"Converter-1","status":"stalled","critical":false,"pending":16,"received":60,"throughput":0.75,
+                     "mailboxes":[{"name":"Converter","capacity":16,"pending":16,"received":60,"idle":65.0}],
+                     "receives_from":["MQTT-0"],"sends_to":["MQTT-0"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            snapshot.table(),
+            "\
+ACTOR        STATUS    CRITICAL    PENDING    RECEIVED     MSG/S  SENDS TO
+MQTT-0       up        yes            2/10         120      1.50  Converter-1
+Converter-1  stalled   no            16/16          60      0.75  MQTT-0
+"
+        );
+    }
+}
@@ -36,6 +36,7 @@ mod mqtt;
 mod reconnect;
 mod refresh_bridges;
 mod upload;
+mod workflow;
 
 #[derive(clap::Parser, Debug)]
 #[clap(
@@ -170,6 +171,10 @@ pub enum TEdgeOpt {
     #[clap(subcommand)]
     Mapper(mapper::MapperCli),
 
+    /// Validate and simulate operation workflows
+    #[clap(subcommand)]
+    Workflow(workflow::TEdgeWorkflowCli),
+
     /// Run thin-edge services and plugins
     Run(ComponentOpt),
 
@@ -251,6 +256,7 @@ impl BuildCommand for TEdgeOpt {
             TEdgeOpt::Reconnect(opt) => opt.build_command(config).await,
             TEdgeOpt::Flows(opt) => opt.build_command(config).await,
             TEdgeOpt::Mapper(opt) => opt.build_command(config).await,
+            TEdgeOpt::Workflow(opt) => opt.build_command(config).await,
             TEdgeOpt::Bridge(opt) => opt.build_command(config).await,
             TEdgeOpt::Run(_) => {
                 // This method has to be kept in sync with tedge::redirect_if_multicall()
@@ -0,0 +1,111 @@
+use crate::cli::workflow::simulate::SimulateCommand;
+use crate::cli::workflow::validate::ValidateCommand;
+use crate::command::BuildCommand;
+use crate::command::Command;
+use crate::ConfigError;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Error;
+use camino::Utf8PathBuf;
+use clap::ValueHint;
+use std::str::FromStr;
+use tedge_api::workflow::OperationWorkflow;
+use tedge_config::TEdgeConfig;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum TEdgeWorkflowCli {
+    /// Check an operation workflow definition
+    ///
+    /// On top of the checks done by the agent when loading the workflow,
+    /// this command reports states that are not defined but used as targets,
+    /// states that cannot be reached from the init state,
+    /// and builtin operations or actions that are not supported by the agent.
+    Validate {
+        /// Path to the TOML workflow definition
+        #[clap(value_hint = ValueHint::FilePath)]
+        file: Utf8PathBuf,
+    },
+
+    /// Step through the states of an operation workflow, without executing any action
+    ///
+    /// The outcome of each action is simulated using an exit code:
+    /// 0 (the default) for a success, any other value for a failure.
+    /// The payload of the command is printed at each step.
+    /// Failed steps are retried according to their retry policy, without any delay.
+    Simulate {
+        /// Path to the TOML workflow definition
+        #[clap(value_hint = ValueHint::FilePath)]
+        file: Utf8PathBuf,
+
+        /// JSON object used as the initial command payload
+        ///
+        /// This can be a path to a JSON file or an inlined JSON object.
+        /// The simulation starts on the "init" state, unless a status is provided.
+        #[clap(long)]
+        input: Option<String>,
+
+        /// Exit code to be used to simulate the action of a state, as in `--exit-code <STATE>=<CODE>`
+        ///
+        /// Can be repeated for different states.
+        #[clap(long = "exit-code", value_parser = parse_exit_code)]
+        exit_codes: Vec<(String, u8)>,
+
+        /// Maximum number of steps, to stop the simulation of a workflow which never terminates
+        #[clap(long, default_value_t = 100)]
+        max_steps: usize,
+    },
+}
+
+#[async_trait::async_trait]
+impl BuildCommand for TEdgeWorkflowCli {
+    async fn build_command(self, _config: &TEdgeConfig) -> Result<Box<dyn Command>, ConfigError> {
+        match self {
+            TEdgeWorkflowCli::Validate { file } => Ok(ValidateCommand { file }.into_boxed()),
+
+            TEdgeWorkflowCli::Simulate {
+                file,
+                input,
+                exit_codes,
+                max_steps,
+            } => Ok(SimulateCommand {
+                file,
+                input,
+                exit_codes,
+                max_steps,
+            }
+            .into_boxed()),
+        }
+    }
+}
+
+impl TEdgeWorkflowCli {
+    pub async fn load_workflow(file: &Utf8PathBuf) -> Result<OperationWorkflow, Error> {
+        let content = tokio::fs::read_to_string(file)
+            .await
+            .with_context(|| format!("reading {file}"))?;
+        let workflow = toml::from_str(&content).with_context(|| format!("parsing {file}"))?;
+        Ok(workflow)
+    }
+
+    pub async fn load_input(input: &str) -> Result<serde_json::Value, Error> {
+        let path = Utf8PathBuf::from(input);
+        let json = match tokio::fs::read_to_string(path).await {
+            Ok(file_content) => serde_json::Value::from_str(&file_content),
+            Err(_) => serde_json::Value::from_str(input),
+        };
+        match json {
+            Ok(payload @ serde_json::Value::Object(_)) => Ok(payload),
+            _ => Err(anyhow!("Invalid input: expecting a JSON object")),
+        }
+    }
+}
+
+fn parse_exit_code(value: &str) -> Result<(String, u8), String> {
+    let Some((state, code)) = value.split_once('=') else {
+        return Err("expecting <STATE>=<CODE>".to_string());
+    };
+    let code = code
+        .parse()
+        .map_err(|_| format!("invalid exit code: {code}"))?;
+    Ok((state.to_string(), code))
+}
@@ -0,0 +1,5 @@
+mod cli;
+mod simulate;
+mod validate;
+
+pub use cli::TEdgeWorkflowCli;
@@ -0,0 +1,59 @@
+use crate::cli::workflow::TEdgeWorkflowCli;
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use anyhow::Error;
+use camino::Utf8PathBuf;
+use serde_json::json;
+use tedge_config::TEdgeConfig;
+
+pub struct SimulateCommand {
+    pub file: Utf8PathBuf,
+    pub input: Option<String>,
+    pub exit_codes: Vec<(String, u8)>,
+    pub max_steps: usize,
+}
+
+#[async_trait::async_trait]
+impl Command for SimulateCommand {
+    fn description(&self) -> String {
+        format!("simulate the operation workflow defined in {}", self.file)
+    }
+
+    async fn execute(&self, _config: TEdgeConfig) -> Result<(), MaybeFancy<Error>> {
+        let workflow = TEdgeWorkflowCli::load_workflow(&self.file).await?;
+        let payload = match &self.input {
+            Some(input) => TEdgeWorkflowCli::load_input(input).await?,
+            None => json!({}),
+        };
+
+        let mut state = workflow.simulation_init_state(payload);
+        for _ in 0..self.max_steps {
+            println!("{}", state.payload);
+
+            let exit_code = self.exit_code(&state.status);
+            match workflow
+                .simulate_step(state, exit_code)
+                .map_err(Error::from)?
+            {
+                Some(new_state) => state = new_state,
+                None => return Ok(()),
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "The simulation has been stopped after {} steps",
+            self.max_steps
+        ))?
+    }
+}
+
+impl SimulateCommand {
+    fn exit_code(&self, state: &str) -> u8 {
+        self.exit_codes
+            .iter()
+            .rev()
+            .find(|(s, _)| s == state)
+            .map(|(_, code)| *code)
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,51 @@
+use crate::cli::workflow::TEdgeWorkflowCli;
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use anyhow::Error;
+use camino::Utf8PathBuf;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_config::TEdgeConfig;
+
+pub struct ValidateCommand {
+    pub file: Utf8PathBuf,
+}
+
+#[async_trait::async_trait]
+impl Command for ValidateCommand {
+    fn description(&self) -> String {
+        format!("validate the operation workflow defined in {}", self.file)
+    }
+
+    async fn execute(&self, _config: TEdgeConfig) -> Result<(), MaybeFancy<Error>> {
+        let workflow = TEdgeWorkflowCli::load_workflow(&self.file).await?;
+        let builtin_steps = tedge_config_manager::builtin_operation_steps();
+        let issues = workflow.validate(&builtin_operations(), &builtin_steps);
+
+        for issue in issues.iter() {
+            println!("{issue}");
+        }
+
+        if issues.is_empty() {
+            println!("{}: {} workflow is valid", self.file, workflow.operation);
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "{} issue(s) found in the {} workflow",
+                issues.len(),
+                workflow.operation
+            ))?
+        }
+    }
+}
+
+/// The operations for which the agent provides a builtin implementation
+fn builtin_operations() -> Vec<OperationType> {
+    vec![
+        OperationType::Restart,
+        OperationType::SoftwareList,
+        OperationType::SoftwareUpdate,
+        OperationType::ConfigSnapshot,
+        OperationType::ConfigUpdate,
+        OperationType::LogUpload,
+    ]
+}
@@ -593,6 +593,135 @@ mod tests {
     }
 
     // Helper functions for restrict_cloud_config_update tests
+    #[test]
+    fn workflow_validate_reports_issues() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let config_dir = temp_dir.path().to_str().unwrap();
+        let valid = temp_path(&temp_dir, "valid.toml");
+        std::fs::write(
+            &valid,
+            r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "set"
+
+[set]
+action = "builtin:config_update:set"
+on_success = "successful"
+"#,
+        )?;
+        let invalid = temp_path(&temp_dir, "invalid.toml");
+        std::fs::write(
+            &invalid,
+            r#"
+operation = "firmware_upgrade"
+
+[init]
+operation = "builtin:firmware_upgrade"
+on_exec = "upgrading"
+
+[upgrading]
+action = "await-operation-completion"
+on_success = "successful"
+on_error = "rollback"
+"#,
+        )?;
+
+        tedge_command(["--config-dir", config_dir, "workflow", "validate", &valid])?
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("config_update workflow is valid"));
+
+        tedge_command(["--config-dir", config_dir, "workflow", "validate", &invalid])?
+            .assert()
+            .failure()
+            .stdout(predicate::str::contains(
+                "Unknown builtin operation on init state: builtin:firmware_upgrade",
+            ))
+            .stdout(predicate::str::contains(
+                "The upgrading state moves to an undefined state: rollback",
+            ))
+            .stderr(predicate::str::contains(
+                "2 issue(s) found in the firmware_upgrade workflow",
+            ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn workflow_simulate_applies_exit_codes_and_retries() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempfile::tempdir()?;
+        let config_dir = temp_dir.path().to_str().unwrap();
+        let workflow = temp_path(&temp_dir, "config_update.toml");
+        std::fs::write(
+            &workflow,
+            r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "set"
+
+[set]
+action = "builtin:config_update:set"
+retry = { max_attempts = 3, backoff = 60, on_exhausted = { status = "failed", reason = "too many attempts" } }
+on_success = "successful"
+on_error = "failed"
+"#,
+        )?;
+
+        tedge_command([
+            "--config-dir",
+            config_dir,
+            "workflow",
+            "simulate",
+            &workflow,
+            "--input",
+            r#"{"url":"http://example.com/config"}"#,
+        ])?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""status":"successful""#))
+        .stdout(predicate::str::contains("@retries").not());
+
+        tedge_command([
+            "--config-dir",
+            config_dir,
+            "workflow",
+            "simulate",
+            &workflow,
+            "--exit-code",
+            "set=1",
+        ])?
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""@retries":{"set":2}"#))
+        .stdout(predicate::str::contains(r#""reason":"too many attempts""#))
+        .stdout(predicate::str::contains(r#""status":"failed""#));
+
+        tedge_command([
+            "--config-dir",
+            config_dir,
+            "workflow",
+            "simulate",
+            &workflow,
+            "--exit-code",
+            "set=1",
+            "--max-steps",
+            "3",
+        ])?
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The simulation has been stopped after 3 steps",
+        ));
+
+        Ok(())
+    }
+
     fn setup_legacy_config(temp_dir: &tempfile::TempDir, cloud: &str) {
         let tedge_toml = temp_dir.path().join("tedge.toml");
         let content = format!("[{cloud}]\nurl = \"example.com\"\n");
@@ -61,10 +61,12 @@
 //!   using an `impl From<SourceMessage> for SinkMessage`. This flexibility allows an actor to receive
 //!   messages from several independent sources (see the [fan_in_message_type](crate::fan_in_message_type) macro).
 use crate::mpsc;
+use crate::register_probe;
 use crate::CloneSender;
 use crate::DynSender;
 use crate::LoggingReceiver;
 use crate::LoggingSender;
+use crate::MailboxProbe;
 use crate::MappingSender;
 use crate::Message;
 use crate::NullSender;
@@ -390,7 +392,8 @@ impl<I: Message, O: Message> SimpleMessageBoxBuilder<I, O> {
         let (signal_sender, signal_receiver) = mpsc::channel(4);
         let output_sender = NullSender.into();
         let input_receiver =
-            LoggingReceiver::new(name.to_string(), input_receiver, signal_receiver);
+            LoggingReceiver::new(name.to_string(), input_receiver, signal_receiver)
+                .with_probe(MailboxProbe::for_mailbox(name, capacity));
 
         SimpleMessageBoxBuilder {
             name: name.to_string(),
@@ -435,7 +438,10 @@ impl<I: Message, O: Message, C> MessageSource<O, C> for SimpleMessageBoxBuilder<
 /// A `SimpleMessageBoxBuilder<Input,Output>` is a [MessageSink] of `Input` messages with no specific config.
 impl<I: Message, O: Message> MessageSink<I> for SimpleMessageBoxBuilder<I, O> {
     fn get_sender(&self) -> DynSender<I> {
-        self.input_sender.sender_clone()
+        match self.input_receiver.probe() {
+            Some(probe) => probe.sender(self.input_sender.clone()).into(),
+            None => self.input_sender.sender_clone(),
+        }
     }
 }
 
@@ -458,6 +464,9 @@ impl<Req: Message, Res: Message> Builder<SimpleMessageBox<Req, Res>>
     }
 
     fn build(self) -> SimpleMessageBox<Req, Res> {
+        if let Some(probe) = self.input_receiver.probe() {
+            register_probe(probe);
+        }
         let sender = LoggingSender::new(self.name, self.output_sender);
         SimpleMessageBox::new(self.input_receiver, sender)
     }
@@ -0,0 +1,256 @@
+//! Live snapshot of the actors running on a runtime
+//!
+//! A snapshot tells for each actor:
+//! - its mailboxes, with their capacity, the number of pending messages and of received messages,
+//! - its peers, i.e. the actors that have sent messages to this actor and the actors this actor has sent messages to.
+//!
+//! Only the mailboxes tracked by a [MailboxProbe](crate::MailboxProbe) are reported;
+//! and only the messages sent from an actor task, and not from a task spawned by an actor,
+//! are attributed to a peer.
+use crate::liveness::ActorStatus;
+use crate::liveness::RuntimeHealth;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// A snapshot of an actor
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActorSnapshot {
+    /// The name of the actor, as given by the runtime
+    pub name: String,
+
+    pub critical: bool,
+
+    pub status: ActorStatus,
+
+    /// The time elapsed since the actor has been spawned
+    pub uptime: Duration,
+
+    pub mailboxes: Vec<MailboxSnapshot>,
+
+    /// The actors that have sent messages to this actor
+    pub receives_from: Vec<String>,
+
+    /// The actors this actor has sent messages to
+    pub sends_to: Vec<String>,
+}
+
+/// A snapshot of a mailbox
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MailboxSnapshot {
+    pub name: String,
+
+    /// The capacity of the underlying channel, if bounded
+    pub capacity: Option<usize>,
+
+    /// The number of messages waiting to be processed
+    pub pending: usize,
+
+    /// The number of messages received by the actor since started
+    pub received: u64,
+
+    /// The time elapsed since the actor received a message
+    pub idle_time: Duration,
+}
+
+impl ActorSnapshot {
+    /// The number of messages waiting in the mailboxes of the actor
+    pub fn pending(&self) -> usize {
+        self.mailboxes.iter().map(|mailbox| mailbox.pending).sum()
+    }
+
+    /// The number of messages received by the actor since started
+    pub fn received(&self) -> u64 {
+        self.mailboxes.iter().map(|mailbox| mailbox.received).sum()
+    }
+
+    /// The average number of messages received per second since the actor started
+    pub fn throughput(&self) -> f64 {
+        let uptime = self.uptime.as_secs_f64();
+        if uptime > 0.0 {
+            self.received() as f64 / uptime
+        } else {
+            0.0
+        }
+    }
+}
+
+impl RuntimeHealth {
+    /// Take a snapshot of the running actors
+    ///
+    /// An actor is reported as stalled if messages are waiting in one of its mailboxes for more than the given timeout.
+    pub(crate) fn snapshot(&self, stall_timeout: Duration) -> Vec<ActorSnapshot> {
+        let actors = self.lock();
+
+        let mut sends_to: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        let mut receives_from: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+        for (name, actor) in actors.iter() {
+            let senders = receives_from.entry(name.as_str()).or_default();
+            for probe in actor.probes.iter() {
+                senders.extend(probe.senders());
+            }
+        }
+        for (name, senders) in receives_from.iter() {
+            for sender in senders {
+                if let Some((sender, _)) = actors.get_key_value(sender) {
+                    sends_to.entry(sender.as_str()).or_default().insert(name);
+                }
+            }
+        }
+
+        actors
+            .iter()
+            .map(|(name, actor)| {
+                let probes: Vec<_> = actor.probes.iter().filter(|p| p.is_tracked()).collect();
+                let stalled = probes.iter().any(|p| p.is_stalled(stall_timeout));
+                ActorSnapshot {
+                    name: name.clone(),
+                    critical: actor.critical,
+                    status: if stalled {
+                        ActorStatus::Stalled
+                    } else {
+                        ActorStatus::Up
+                    },
+                    uptime: actor.started.elapsed(),
+                    mailboxes: probes
+                        .iter()
+                        .map(|probe| MailboxSnapshot {
+                            name: probe.name().to_string(),
+                            capacity: probe.capacity(),
+                            pending: probe.pending(),
+                            received: probe.received(),
+                            idle_time: probe.idle_time(),
+                        })
+                        .collect(),
+                    receives_from: receives_from
+                        .get(name.as_str())
+                        .map(|senders| senders.iter().cloned().collect())
+                        .unwrap_or_default(),
+                    sends_to: sends_to
+                        .get(name.as_str())
+                        .map(|receivers| receivers.iter().map(|r| r.to_string()).collect())
+                        .unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Actor;
+    use crate::Builder;
+    use crate::DynSender;
+    use crate::MessageReceiver;
+    use crate::MessageSink;
+    use crate::MessageSource;
+    use crate::NoConfig;
+    use crate::NoMessage;
+    use crate::Runtime;
+    use crate::RuntimeError;
+    use crate::RuntimeRequest;
+    use crate::RuntimeRequestSink;
+    use crate::Sender;
+    use crate::SimpleMessageBox;
+    use crate::SimpleMessageBoxBuilder;
+    use async_trait::async_trait;
+    use std::convert::Infallible;
+
+    /// An actor forwarding its input to its output
+    struct Relay {
+        name: String,
+        messages: SimpleMessageBox<u32, u32>,
+    }
+
+    #[async_trait]
+    impl Actor for Relay {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        async fn run(mut self) -> Result<(), RuntimeError> {
+            while let Some(message) = self.messages.recv().await {
+                self.messages.send(message).await?;
+            }
+            Ok(())
+        }
+    }
+
+    struct RelayBuilder {
+        name: String,
+        box_builder: SimpleMessageBoxBuilder<u32, u32>,
+    }
+
+    impl RelayBuilder {
+        fn new(name: &str) -> Self {
+            RelayBuilder {
+                name: name.to_string(),
+                box_builder: SimpleMessageBoxBuilder::new(name, 8),
+            }
+        }
+    }
+
+    impl RuntimeRequestSink for RelayBuilder {
+        fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+            self.box_builder.get_signal_sender()
+        }
+    }
+
+    impl Builder<Relay> for RelayBuilder {
+        type Error = Infallible;
+
+        fn try_build(self) -> Result<Relay, Self::Error> {
+            Ok(Relay {
+                name: self.name,
+                messages: self.box_builder.build(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot_actors_and_their_peers() {
+        let mut runtime = Runtime::new();
+        let mut first = RelayBuilder::new("First");
+        let mut second = RelayBuilder::new("Second");
+        let output: SimpleMessageBoxBuilder<u32, NoMessage> =
+            SimpleMessageBoxBuilder::new("Output", 8);
+        first
+            .box_builder
+            .connect_sink(NoConfig, &second.box_builder);
+        second.box_builder.connect_sink(NoConfig, &output);
+        let mut input = first.box_builder.get_sender();
+        let mut output = output.build();
+
+        runtime.spawn(first).await.unwrap();
+        runtime.spawn_critical(second).await.unwrap();
+        for i in 0..3u32 {
+            input.send(i).await.unwrap();
+            assert_eq!(output.recv().await, Some(i));
+        }
+
+        let snapshot = runtime
+            .get_handle()
+            .snapshot(Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(snapshot.len(), 2);
+
+        let first = &snapshot[0];
+        assert_eq!(first.name, "First-0");
+        assert!(!first.critical);
+        assert_eq!(first.status, ActorStatus::Up);
+        assert_eq!(first.pending(), 0);
+        assert_eq!(first.received(), 3);
+        assert_eq!(first.mailboxes[0].capacity, Some(8));
+        assert!(first.receives_from.is_empty());
+        assert_eq!(first.sends_to, vec!["Second-1".to_string()]);
+
+        let second = &snapshot[1];
+        assert_eq!(second.name, "Second-1");
+        assert!(second.critical);
+        assert_eq!(second.received(), 3);
+        assert_eq!(second.receives_from, vec!["First-0".to_string()]);
+        assert!(second.sends_to.is_empty());
+    }
+}
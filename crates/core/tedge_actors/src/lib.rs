@@ -304,6 +304,8 @@ pub mod builders;
 pub mod channels;
 pub mod converter;
 mod errors;
+pub mod introspection;
+pub mod liveness;
 pub mod message_boxes;
 mod messages;
 #[doc(hidden)]
@@ -316,6 +318,8 @@ pub use builders::*;
 pub use channels::*;
 pub use converter::*;
 pub use errors::*;
+pub use introspection::*;
+pub use liveness::*;
 pub use message_boxes::*;
 pub use messages::*;
 pub use runtime::*;
@@ -0,0 +1,439 @@
+//! Liveness of the actors, as observed from their mailboxes
+//!
+//! The runtime cannot tell if an actor is stuck from the actor task alone:
+//! an actor waiting for its next message and an actor deadlocked on a lock are both pending.
+//! What differs is the mailbox: an actor is considered __stalled__
+//! when messages are waiting in its mailbox while no message has been received by the actor for some time.
+//! An actor with no pending messages is considered alive, whatever its last activity.
+//!
+//! The mailboxes built by [SimpleMessageBoxBuilder](crate::SimpleMessageBoxBuilder)
+//! and [ServerMessageBoxBuilder](crate::ServerMessageBoxBuilder) are tracked out of the box.
+//! An actor builder using its own channels can track them using a [MailboxProbe]:
+//! - wrapping with [MailboxProbe::sender] the sender given to the peers,
+//! - calling [MailboxProbe::message_received] on each message received by the actor,
+//! - calling [register_probe] when the actor is built.
+use crate::ChannelError;
+use crate::Message;
+use crate::Sender;
+use async_trait::async_trait;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Count the messages waiting in a mailbox and record the last activity of the actor reading this mailbox
+#[derive(Clone, Debug)]
+pub struct MailboxProbe {
+    state: Arc<ProbeState>,
+}
+
+#[derive(Debug)]
+struct ProbeState {
+    name: String,
+    capacity: Option<usize>,
+    pending: AtomicUsize,
+    received: AtomicU64,
+    created: Instant,
+    /// The time of the last activity, in nanoseconds since the creation of the probe
+    last_activity: AtomicU64,
+    tracked: AtomicBool,
+    senders: Mutex<BTreeSet<String>>,
+}
+
+impl Default for MailboxProbe {
+    fn default() -> Self {
+        MailboxProbe::new()
+    }
+}
+
+impl MailboxProbe {
+    pub fn new() -> Self {
+        MailboxProbe::build(String::new(), None)
+    }
+
+    /// A probe for a named mailbox, backed by a channel of the given capacity
+    pub fn for_mailbox(name: &str, capacity: usize) -> Self {
+        MailboxProbe::build(name.to_string(), Some(capacity))
+    }
+
+    fn build(name: String, capacity: Option<usize>) -> Self {
+        MailboxProbe {
+            state: Arc::new(ProbeState {
+                name,
+                capacity,
+                pending: AtomicUsize::new(0),
+                received: AtomicU64::new(0),
+                created: Instant::now(),
+                last_activity: AtomicU64::new(0),
+                tracked: AtomicBool::new(true),
+                senders: Mutex::new(BTreeSet::new()),
+            }),
+        }
+    }
+
+    /// Wrap a sender so the messages sent to the mailbox are counted by this probe
+    pub fn sender<S>(&self, sender: S) -> ProbedSender<S> {
+        ProbedSender {
+            inner: sender,
+            probe: self.clone(),
+            sender_recorded: false,
+        }
+    }
+
+    /// Record a message sent to the mailbox
+    pub fn message_sent(&self) {
+        if self.state.pending.fetch_add(1, Ordering::SeqCst) == 0 {
+            // The actor is given time to process a backlog from its very first message
+            self.touch();
+        }
+    }
+
+    /// Record the actor running the current task as a peer sending messages to the mailbox
+    ///
+    /// Return false if not called from an actor task.
+    pub fn record_sender(&self) -> bool {
+        CURRENT_ACTOR
+            .try_with(|actor| {
+                let mut senders = self
+                    .state
+                    .senders
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner());
+                if !senders.contains(actor.as_str()) {
+                    senders.insert(actor.clone());
+                }
+            })
+            .is_ok()
+    }
+
+    /// Record a message that has not been delivered to the mailbox
+    pub fn message_not_sent(&self) {
+        self.decrement();
+    }
+
+    /// Record a message received by the actor from the mailbox
+    pub fn message_received(&self) {
+        self.decrement();
+        self.state.received.fetch_add(1, Ordering::Relaxed);
+        self.touch();
+    }
+
+    /// Stop tracking this mailbox
+    ///
+    /// This is used when the actor reads its messages directly from the underlying channel,
+    /// bypassing the probe.
+    pub fn untrack(&self) {
+        self.state.tracked.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_tracked(&self) -> bool {
+        self.state.tracked.load(Ordering::SeqCst)
+    }
+
+    /// The number of messages waiting in the mailbox
+    pub fn pending(&self) -> usize {
+        self.state.pending.load(Ordering::SeqCst)
+    }
+
+    /// The name of the mailbox, if given
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// The capacity of the channel backing the mailbox, if known
+    pub fn capacity(&self) -> Option<usize> {
+        self.state.capacity
+    }
+
+    /// The number of messages received by the actor from the mailbox
+    pub fn received(&self) -> u64 {
+        self.state.received.load(Ordering::Relaxed)
+    }
+
+    /// The names of the actors that have sent messages to the mailbox
+    pub fn senders(&self) -> Vec<String> {
+        self.state
+            .senders
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// The time elapsed since a message has been received by the actor
+    ///
+    /// or since the first message of the current backlog has been sent
+    pub fn idle_time(&self) -> Duration {
+        self.state
+            .created
+            .elapsed()
+            .saturating_sub(self.last_activity())
+    }
+
+    /// Tell if messages are pending for more than the given timeout
+    pub fn is_stalled(&self, timeout: Duration) -> bool {
+        self.is_tracked() && self.pending() > 0 && self.idle_time() > timeout
+    }
+
+    /// The time of the last activity, as elapsed since the creation of the probe
+    fn last_activity(&self) -> Duration {
+        Duration::from_nanos(self.state.last_activity.load(Ordering::Relaxed))
+    }
+
+    fn touch(&self) {
+        let now = self.state.created.elapsed().as_nanos() as u64;
+        self.state.last_activity.fetch_max(now, Ordering::Relaxed);
+    }
+
+    fn decrement(&self) {
+        let _ = self
+            .state
+            .pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    }
+}
+
+/// A sender that counts the messages sent to a mailbox
+pub struct ProbedSender<S> {
+    inner: S,
+    probe: MailboxProbe,
+    /// Whether the actor using this sender has been recorded as a peer of the mailbox
+    sender_recorded: bool,
+}
+
+impl<S: Clone> Clone for ProbedSender<S> {
+    fn clone(&self) -> Self {
+        // A clone might be given to another actor
+        ProbedSender {
+            inner: self.inner.clone(),
+            probe: self.probe.clone(),
+            sender_recorded: false,
+        }
+    }
+}
+
+#[async_trait]
+impl<M: Message, S: Sender<M>> Sender<M> for ProbedSender<S> {
+    async fn send(&mut self, message: M) -> Result<(), ChannelError> {
+        // The message is counted before being sent, so the receiver never sees an uncounted message
+        self.probe.message_sent();
+        if !self.sender_recorded {
+            // The sending actor is recorded once, to avoid locking the probe on each message
+            self.sender_recorded = self.probe.record_sender();
+        }
+        let result = self.inner.send(message).await;
+        if result.is_err() {
+            self.probe.message_not_sent();
+        }
+        result
+    }
+}
+
+tokio::task_local! {
+    /// The name of the actor running the current task, if any
+    static CURRENT_ACTOR: String;
+}
+
+/// Run an actor task, so the messages sent by this actor are attributed to it
+pub(crate) async fn run_as_actor<F: Future>(running_name: String, task: F) -> F::Output {
+    CURRENT_ACTOR.scope(running_name, task).await
+}
+
+thread_local! {
+    /// The probes of the actor under construction, if any
+    static ACTOR_PROBES: RefCell<Option<Vec<MailboxProbe>>> = const { RefCell::new(None) };
+}
+
+/// Register the probe of a mailbox as one of the mailboxes of the actor under construction
+///
+/// This has no effect unless called while the runtime is building an actor.
+pub fn register_probe(probe: &MailboxProbe) {
+    ACTOR_PROBES.with(|probes| {
+        if let Some(probes) = probes.borrow_mut().as_mut() {
+            probes.push(probe.clone())
+        }
+    })
+}
+
+/// Build an actor, collecting the probes registered by its mailboxes
+pub(crate) fn collect_probes<T>(build: impl FnOnce() -> T) -> (T, Vec<MailboxProbe>) {
+    let outer = ACTOR_PROBES.with(|probes| probes.replace(Some(vec![])));
+    let actor = build();
+    let probes = ACTOR_PROBES.with(|probes| probes.replace(outer));
+    (actor, probes.unwrap_or_default())
+}
+
+/// The liveness of an actor
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActorLiveness {
+    /// The name of the actor, as given by the runtime
+    pub name: String,
+
+    /// A critical actor is required for the whole process to be considered alive
+    pub critical: bool,
+
+    pub status: ActorStatus,
+
+    /// The number of messages waiting in the mailboxes of the actor
+    pub pending: usize,
+
+    /// The time elapsed since the actor received a message, if its mailboxes are tracked
+    pub idle_time: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ActorStatus {
+    /// The actor is processing its messages or waiting for new ones
+    Up,
+
+    /// Messages are waiting for the actor for too long
+    Stalled,
+}
+
+impl ActorStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActorStatus::Up => "up",
+            ActorStatus::Stalled => "stalled",
+        }
+    }
+}
+
+/// The liveness of the actors running on a runtime
+#[derive(Clone, Default)]
+pub struct RuntimeHealth {
+    actors: Arc<Mutex<BTreeMap<String, ActorProbes>>>,
+}
+
+pub(crate) struct ActorProbes {
+    pub(crate) critical: bool,
+    pub(crate) started: Instant,
+    pub(crate) probes: Vec<MailboxProbe>,
+}
+
+impl RuntimeHealth {
+    pub(crate) fn insert(&self, name: String, critical: bool, probes: Vec<MailboxProbe>) {
+        self.lock().insert(
+            name,
+            ActorProbes {
+                critical,
+                started: Instant::now(),
+                probes,
+            },
+        );
+    }
+
+    pub(crate) fn remove(&self, name: &str) {
+        self.lock().remove(name);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Check the liveness of the running actors
+    ///
+    /// An actor is stalled if messages are waiting in one of its mailboxes for more than the given timeout.
+    pub fn check(&self, stall_timeout: Duration) -> Vec<ActorLiveness> {
+        self.lock()
+            .iter()
+            .map(|(name, actor)| {
+                let probes: Vec<&MailboxProbe> =
+                    actor.probes.iter().filter(|p| p.is_tracked()).collect();
+                let stalled = probes.iter().any(|p| p.is_stalled(stall_timeout));
+                ActorLiveness {
+                    name: name.clone(),
+                    critical: actor.critical,
+                    status: if stalled {
+                        ActorStatus::Stalled
+                    } else {
+                        ActorStatus::Up
+                    },
+                    pending: probes.iter().map(|p| p.pending()).sum(),
+                    idle_time: probes.iter().map(|p| p.idle_time()).min(),
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, ActorProbes>> {
+        self.actors.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+    use crate::MessageReceiver;
+    use crate::MessageSink;
+    use crate::SimpleMessageBox;
+    use crate::SimpleMessageBoxBuilder;
+
+    #[tokio::test]
+    async fn probes_count_pending_messages() {
+        let builder: SimpleMessageBoxBuilder<u32, u32> = SimpleMessageBoxBuilder::new("test", 16);
+        let mut sender = builder.get_sender();
+        let (mut message_box, probes): (SimpleMessageBox<u32, u32>, _) =
+            collect_probes(|| builder.build());
+        assert_eq!(probes.len(), 1);
+        let probe = &probes[0];
+
+        sender.send(1u32).await.unwrap();
+        sender.send(2u32).await.unwrap();
+        assert_eq!(probe.pending(), 2);
+        assert!(!probe.is_stalled(Duration::from_secs(60)));
+        assert!(probe.is_stalled(Duration::ZERO));
+
+        assert_eq!(message_box.recv().await, Some(1));
+        assert_eq!(message_box.recv().await, Some(2));
+        assert_eq!(probe.pending(), 0);
+        assert!(!probe.is_stalled(Duration::ZERO));
+    }
+
+    #[test]
+    fn probes_are_only_collected_while_building_an_actor() {
+        let builder: SimpleMessageBoxBuilder<u32, u32> = SimpleMessageBoxBuilder::new("test", 16);
+        let _message_box = builder.build();
+
+        let (_, probes) = collect_probes(|| ());
+        assert!(probes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn runtime_health_reports_stalled_actors() {
+        let health = RuntimeHealth::default();
+        let idle = MailboxProbe::new();
+        let busy = MailboxProbe::new();
+        busy.message_sent();
+        health.insert("Idle-0".to_string(), false, vec![idle]);
+        health.insert("Busy-1".to_string(), true, vec![busy]);
+        health.insert("Untracked-2".to_string(), false, vec![]);
+
+        let statuses: Vec<_> = health
+            .check(Duration::ZERO)
+            .into_iter()
+            .map(|actor| (actor.name, actor.critical, actor.status, actor.pending))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("Busy-1".to_string(), true, ActorStatus::Stalled, 1),
+                ("Idle-0".to_string(), false, ActorStatus::Up, 0),
+                ("Untracked-2".to_string(), false, ActorStatus::Up, 0),
+            ]
+        );
+
+        health.remove("Busy-1");
+        assert_eq!(health.check(Duration::ZERO).len(), 2);
+    }
+}
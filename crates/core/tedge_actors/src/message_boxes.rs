@@ -88,6 +88,7 @@ use crate::channels::Sender;
 use crate::ChannelError;
 use crate::CloneSender;
 use crate::DynSender;
+use crate::MailboxProbe;
 use crate::Message;
 use crate::RuntimeRequest;
 use async_trait::async_trait;
@@ -116,6 +117,7 @@ pub trait MessageReceiver<Input> {
 pub struct LoggingReceiver<Input: Debug> {
     name: String,
     receiver: CombinedReceiver<Input>,
+    probe: Option<MailboxProbe>,
 }
 
 impl<Input: Debug> LoggingReceiver<Input> {
@@ -125,7 +127,32 @@ impl<Input: Debug> LoggingReceiver<Input> {
         signal_receiver: mpsc::Receiver<RuntimeRequest>,
     ) -> Self {
         let receiver = CombinedReceiver::new(input_receiver, signal_receiver);
-        Self { name, receiver }
+        Self {
+            name,
+            receiver,
+            probe: None,
+        }
+    }
+
+    /// Track with the given probe the messages received from this receiver
+    ///
+    /// The senders of the messages must be wrapped by the [same probe](MailboxProbe::sender).
+    pub fn with_probe(self, probe: MailboxProbe) -> Self {
+        Self {
+            probe: Some(probe),
+            ..self
+        }
+    }
+
+    /// The probe tracking the messages received from this receiver, if any
+    pub fn probe(&self) -> Option<&MailboxProbe> {
+        self.probe.as_ref()
+    }
+
+    fn untrack(&self) {
+        if let Some(probe) = &self.probe {
+            probe.untrack()
+        }
     }
 
     /// Splits a `LoggingReceiver` into an input receiver and a signal receiver,
@@ -151,6 +178,7 @@ impl<Input: Debug> LoggingReceiver<Input> {
     /// This method returns consumes the `LoggingReceiver` and returns owned
     /// receivers, which can then be separately moved.
     pub fn into_split(self) -> (mpsc::Receiver<Input>, mpsc::Receiver<RuntimeRequest>) {
+        self.untrack();
         (self.receiver.input_receiver, self.receiver.signal_receiver)
     }
 
@@ -165,12 +193,18 @@ impl<Input: Send + Debug> MessageReceiver<Input> for LoggingReceiver<Input> {
     async fn try_recv(&mut self) -> Result<Option<Input>, RuntimeRequest> {
         let message = self.receiver.try_recv().await;
         debug!(target: &self.name, "recv {:?}", message);
+        if let (Ok(Some(_)), Some(probe)) = (&message, &self.probe) {
+            probe.message_received()
+        }
         message
     }
 
     async fn recv(&mut self) -> Option<Input> {
         let message = self.receiver.recv().await;
         debug!(target: &self.name, "recv {:?}", message);
+        if let (Some(_), Some(probe)) = (&message, &self.probe) {
+            probe.message_received()
+        }
         message
     }
 
@@ -456,7 +490,11 @@ impl<T: Send> MessageReceiverNoblock<T> for CombinedReceiver<T> {
 
 impl<T: Send + Debug> MessageReceiverNoblock<T> for LoggingReceiver<T> {
     fn recv_noblock(&mut self) -> Result<T, RecvNoblockError> {
-        self.receiver.recv_noblock()
+        let message = self.receiver.recv_noblock();
+        if let (Ok(_), Some(probe)) = (&message, &self.probe) {
+            probe.message_received()
+        }
+        message
     }
 }
 
@@ -1,3 +1,5 @@
+use crate::liveness::collect_probes;
+use crate::liveness::MailboxProbe;
 use crate::Actor;
 use crate::Builder;
 use crate::DynSender;
@@ -11,6 +13,8 @@ use std::fmt::Formatter;
 pub struct RunActor {
     actor: Box<dyn Actor>,
     runtime_request_sender: DynSender<RuntimeRequest>,
+    probes: Vec<MailboxProbe>,
+    critical: bool,
 }
 
 impl RunActor {
@@ -21,6 +25,8 @@ impl RunActor {
         RunActor {
             actor,
             runtime_request_sender,
+            probes: vec![],
+            critical: false,
         }
     }
 
@@ -30,14 +36,34 @@ impl RunActor {
         T: Builder<A> + RuntimeRequestSink,
     {
         let runtime_request_sender = actor_builder.get_signal_sender();
-        let actor = actor_builder.build();
-        RunActor::new(Box::new(actor), runtime_request_sender)
+        let (actor, probes) = collect_probes(|| actor_builder.build());
+        RunActor {
+            probes,
+            ..RunActor::new(Box::new(actor), runtime_request_sender)
+        }
+    }
+
+    /// Mark this actor as critical: the process is not alive when this actor is stalled
+    pub fn critical(self) -> Self {
+        RunActor {
+            critical: true,
+            ..self
+        }
     }
 
     pub fn name(&self) -> &str {
         self.actor.name()
     }
 
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// The probes of the mailboxes of this actor
+    pub(crate) fn probes(&self) -> Vec<MailboxProbe> {
+        self.probes.clone()
+    }
+
     pub async fn run(self) -> Result<(), RuntimeError> {
         self.actor.run_boxed().await
     }
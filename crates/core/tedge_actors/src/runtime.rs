@@ -1,5 +1,8 @@
 //! Supervise the actors of an application
 //!
+use crate::introspection::ActorSnapshot;
+use crate::liveness::run_as_actor;
+use crate::liveness::RuntimeHealth;
 use crate::run_actor::RunActor;
 use crate::Actor;
 use crate::Builder;
@@ -9,6 +12,7 @@ use crate::MessageSink;
 use crate::RuntimeError;
 use crate::RuntimeRequestSink;
 use futures::channel::mpsc;
+use futures::channel::oneshot;
 use futures::prelude::*;
 use futures::stream::FuturesUnordered;
 use log::debug;
@@ -27,6 +31,11 @@ use tracing::Span;
 pub enum RuntimeAction {
     Shutdown,
     Spawn(RunActor),
+    /// Request a snapshot of the running actors
+    Snapshot {
+        stall_timeout: Duration,
+        reply_to: oneshot::Sender<Vec<ActorSnapshot>>,
+    },
 }
 
 /// Requests sent by the runtime to actors
@@ -71,16 +80,21 @@ impl Runtime {
         // `component` span, giving per-component attribution in the shared log stream;
         // standalone components build with no ambient span, so this is a no-op.
         let span = Span::current();
+        let health = RuntimeHealth::default();
         let runtime_actor = RuntimeActor::new(
             actions_receiver,
             events_sender,
             Duration::from_secs(60),
             span.clone(),
+            health.clone(),
         );
 
         let runtime_task = tokio::spawn(runtime_actor.run().instrument(span));
         Runtime {
-            handle: RuntimeHandle { actions_sender },
+            handle: RuntimeHandle {
+                actions_sender,
+                health,
+            },
             bg_task: runtime_task,
         }
     }
@@ -89,6 +103,11 @@ impl Runtime {
         self.handle.clone()
     }
 
+    /// The liveness of the actors spawned on this runtime
+    pub fn health(&self) -> RuntimeHealth {
+        self.handle.health()
+    }
+
     /// Spawn an actor
     pub async fn spawn<T, A>(&mut self, actor_builder: T) -> Result<(), RuntimeError>
     where
@@ -98,6 +117,15 @@ impl Runtime {
         self.handle.spawn(actor_builder).await
     }
 
+    /// Spawn an actor which liveness is required for the process to be considered alive
+    pub async fn spawn_critical<T, A>(&mut self, actor_builder: T) -> Result<(), RuntimeError>
+    where
+        T: Builder<A> + RuntimeRequestSink,
+        A: Actor,
+    {
+        self.handle.spawn_critical(actor_builder).await
+    }
+
     /// Run the runtime up to completion
     ///
     /// I.e until
@@ -140,6 +168,7 @@ impl Runtime {
 #[derive(Clone)]
 pub struct RuntimeHandle {
     actions_sender: mpsc::Sender<RuntimeAction>,
+    health: RuntimeHealth,
 }
 
 impl RuntimeHandle {
@@ -159,6 +188,38 @@ impl RuntimeHandle {
         Ok(self.send(RuntimeAction::Spawn(run_actor)).await?)
     }
 
+    /// Spawn an actor which liveness is required for the process to be considered alive
+    pub async fn spawn_critical<A, T>(&mut self, actor_builder: T) -> Result<(), RuntimeError>
+    where
+        A: Actor,
+        T: Builder<A> + RuntimeRequestSink,
+    {
+        let run_actor = RunActor::from_builder(actor_builder).critical();
+
+        Ok(self.send(RuntimeAction::Spawn(run_actor)).await?)
+    }
+
+    /// The liveness of the actors spawned on this runtime
+    pub fn health(&self) -> RuntimeHealth {
+        self.health.clone()
+    }
+
+    /// Take a snapshot of the running actors
+    ///
+    /// An actor is reported as stalled if messages are waiting in one of its mailboxes for more than the given timeout.
+    pub async fn snapshot(
+        &mut self,
+        stall_timeout: Duration,
+    ) -> Result<Vec<ActorSnapshot>, RuntimeError> {
+        let (reply_to, snapshot) = oneshot::channel();
+        self.send(RuntimeAction::Snapshot {
+            stall_timeout,
+            reply_to,
+        })
+        .await?;
+        Ok(snapshot.await.map_err(|_| ChannelError::ReceiveError())?)
+    }
+
     /// Send an action to the runtime
     async fn send(&mut self, action: RuntimeAction) -> Result<(), ChannelError> {
         debug!(target: "Runtime", "schedule {:?}", action);
@@ -180,6 +241,7 @@ struct RuntimeActor {
     cleanup_duration: Duration,
     futures: FuturesUnordered<JoinHandle<Result<String, (String, RuntimeError)>>>,
     running_actors: HashMap<String, DynSender<RuntimeRequest>>,
+    health: RuntimeHealth,
     /// Span attributing this runtime's actor tasks to their component (see
     /// [`Runtime::with_events_sender`]).
     span: Span,
@@ -191,6 +253,7 @@ impl RuntimeActor {
         events: Option<DynSender<RuntimeEvent>>,
         cleanup_duration: Duration,
         span: Span,
+        health: RuntimeHealth,
     ) -> Self {
         Self {
             actions,
@@ -198,6 +261,7 @@ impl RuntimeActor {
             cleanup_duration,
             futures: FuturesUnordered::new(),
             running_actors: HashMap::default(),
+            health,
             span,
         }
     }
@@ -220,9 +284,13 @@ impl RuntimeActor {
                                     })
                                     .await;
                                     self.running_actors.insert(running_name.clone(), actor.get_signal_sender());
+                                    self.health.insert(running_name.clone(), actor.is_critical(), actor.probes());
                                     self.futures.push(tokio::spawn(run_task(actor, running_name, self.span.clone())));
                                     actors_count += 1;
                                }
+                               RuntimeAction::Snapshot { stall_timeout, reply_to } => {
+                                    let _ = reply_to.send(self.health.snapshot(stall_timeout));
+                               }
                                RuntimeAction::Shutdown => {
                                     info!(target: "Runtime", "Shutting down");
                                     shutdown_actors(&mut self.running_actors).await;
@@ -281,6 +349,7 @@ impl RuntimeActor {
         }
 
         self.running_actors.clear();
+        self.health.clear();
     }
 
     async fn handle_actor_finishing(
@@ -294,12 +363,14 @@ impl RuntimeActor {
             }
             Ok(Ok(actor)) => {
                 self.running_actors.remove(&actor);
+                self.health.remove(&actor);
                 info!(target: "Runtime", "Actor has finished: {actor}");
                 self.send_event(RuntimeEvent::Stopped { task: actor }).await;
                 Ok(())
             }
             Ok(Err((actor, error))) => {
                 self.running_actors.remove(&actor);
+                self.health.remove(&actor);
                 error!(target: "Runtime", "Actor {actor} has finished unsuccessfully: {error:?}");
                 self.send_event(RuntimeEvent::Aborted {
                     task: actor.clone(),
@@ -341,7 +412,8 @@ async fn run_task(
     running_name: String,
     span: Span,
 ) -> Result<String, (String, RuntimeError)> {
-    match panic::AssertUnwindSafe(task.run().instrument(span))
+    let task = run_as_actor(running_name.clone(), task.run());
+    match panic::AssertUnwindSafe(task.instrument(span))
         .catch_unwind()
         .await
     {
@@ -498,6 +570,7 @@ mod tests {
             Some(Box::new(events_sender)),
             Duration::from_millis(1),
             Span::none(),
+            RuntimeHealth::default(),
         );
         (actions_sender, events_receiver, ra)
     }
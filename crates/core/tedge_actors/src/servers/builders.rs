@@ -1,4 +1,5 @@
 use crate::mpsc;
+use crate::register_probe;
 use crate::Actor;
 use crate::Builder;
 use crate::CloneSender;
@@ -6,6 +7,7 @@ use crate::ConcurrentServerActor;
 use crate::ConcurrentServerMessageBox;
 use crate::DynSender;
 use crate::LoggingReceiver;
+use crate::MailboxProbe;
 use crate::Message;
 use crate::MessageSink;
 use crate::RequestEnvelope;
@@ -33,7 +35,8 @@ impl<Request: Message, Response: Message> ServerMessageBoxBuilder<Request, Respo
         let (request_sender, request_receiver) = mpsc::channel(capacity);
         let (signal_sender, signal_receiver) = mpsc::channel(4);
         let request_receiver =
-            LoggingReceiver::new(server_name.to_string(), request_receiver, signal_receiver);
+            LoggingReceiver::new(server_name.to_string(), request_receiver, signal_receiver)
+                .with_probe(MailboxProbe::for_mailbox(server_name, capacity));
 
         ServerMessageBoxBuilder {
             max_concurrency,
@@ -52,18 +55,29 @@ impl<Request: Message, Response: Message> ServerMessageBoxBuilder<Request, Respo
 
     /// Return a sender for the requests
     pub fn request_sender(&self) -> DynSender<RequestEnvelope<Request, Response>> {
-        self.request_sender.sender_clone()
+        match self.request_receiver.probe() {
+            Some(probe) => probe.sender(self.request_sender.clone()).into(),
+            None => self.request_sender.sender_clone(),
+        }
     }
 
     /// Build a message box ready to be used by the server actor
     fn build_server(self) -> ServerMessageBox<Request, Response> {
+        self.register_probe();
         self.request_receiver
     }
 
     /// Build a message box aimed to concurrently serve requests
     fn build_concurrent(self) -> ConcurrentServerMessageBox<Request, Response> {
+        self.register_probe();
         ConcurrentServerMessageBox::new(self.max_concurrency, self.request_receiver)
     }
+
+    fn register_probe(&self) {
+        if let Some(probe) = self.request_receiver.probe() {
+            register_probe(probe);
+        }
+    }
 }
 
 impl<Req: Message, Res: Message> RuntimeRequestSink for ServerMessageBoxBuilder<Req, Res> {
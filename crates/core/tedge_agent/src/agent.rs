@@ -1,3 +1,8 @@
+use crate::certificate_renewal::builder::CertificateRenewalBuilder;
+use crate::certificate_renewal::config::CertificateRenewalConfig;
+use crate::command_manager;
+use crate::command_manager::server::CommandRequest;
+use crate::command_manager::server::CommandServer;
 use crate::device_profile_manager::DeviceProfileManagerBuilder;
 use crate::entity_manager;
 use crate::entity_manager::server::EntityStoreRequest;
@@ -20,6 +25,7 @@ use crate::Capabilities;
 use anyhow::Context;
 use camino::Utf8PathBuf;
 use certificate::CloudHttpConfig;
+use download::TrustStore;
 use reqwest::Identity;
 use serde_json::json;
 use std::fmt::Debug;
@@ -51,16 +57,21 @@ use tedge_config_manager::ConfigManagerOptions;
 use tedge_downloader_ext::DownloaderActor;
 use tedge_file_system_ext::FsWatchActorBuilder;
 use tedge_health_ext::HealthMonitorBuilder;
+use tedge_log_manager::ForwardRule;
+use tedge_log_manager::LogForwarderBuilder;
+use tedge_log_manager::LogForwarderConfig;
 use tedge_log_manager::LogManagerBuilder;
 use tedge_log_manager::LogManagerConfig;
 use tedge_log_manager::LogManagerOptions;
 use tedge_log_manager::PluginConfig;
+use tedge_log_manager::DEFAULT_CHECKPOINT_FILE_NAME;
 use tedge_mqtt_ext::MqttActorBuilder;
 use tedge_mqtt_ext::MqttConfig;
 use tedge_script_ext::ScriptActor;
 use tedge_uploader_ext::UploaderActor;
 use tedge_utils::paths::ManagedDir;
 use tedge_utils::paths::TedgePaths;
+use tracing::error;
 use tracing::info;
 use tracing::instrument;
 
@@ -71,6 +82,7 @@ pub(crate) struct AgentConfig {
     pub mqtt_config: MqttConfig,
     pub http_config: HttpServerConfig,
     pub restart_config: RestartManagerConfig,
+    pub certificate_renewal_config: Option<CertificateRenewalConfig>,
     pub sw_update_config: SoftwareManagerConfig,
     pub operation_config: OperationConfig,
     pub config_dir: TedgePaths,
@@ -87,6 +99,7 @@ pub(crate) struct AgentConfig {
     pub service: TEdgeConfigReaderService,
     pub identity: Option<Identity>,
     pub cloud_root_certs: CloudHttpConfig,
+    pub download_trust_store: Option<TrustStore>,
     pub is_sudo_enabled: bool,
     pub capabilities: Capabilities,
     pub log_plugin_dirs: Vec<Utf8PathBuf>,
@@ -157,6 +170,24 @@ impl AgentConfig {
         let restart_config =
             RestartManagerConfig::from_tedge_config(&mqtt_device_topic_id, &tedge_config).await?;
 
+        // Certificate renewal config
+        // An invalid renewal config must not prevent the agent to start: the renewal is then disabled
+        let certificate_renewal_config = if tedge_config.certificate.renewal.enable {
+            match CertificateRenewalConfig::from_tedge_config(
+                MqttSchema::with_root(mqtt_topic_root.to_string()),
+                &mqtt_device_topic_id,
+                &tedge_config,
+            ) {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    error!("Certificate renewal is disabled: {err:#}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         // Software update config
         let sw_update_config = SoftwareManagerConfig::from_tedge_config(&tedge_config).await?;
 
@@ -176,6 +207,18 @@ impl AgentConfig {
 
         let identity = tedge_config.http.client.auth.identity()?;
         let cloud_root_certs = tedge_config.cloud_root_certs().await?;
+        // As done by the software plugins, an invalid trust store doesn't prevent the agent to start:
+        // without a trust store, signed files are rejected
+        let download_trust_store = match tedge_config.download.trust_store.or_none() {
+            None => None,
+            Some(path) => match TrustStore::load(path) {
+                Ok(trust_store) => Some(trust_store),
+                Err(err) => {
+                    error!("Fail to load the download.trust_store: {err}");
+                    None
+                }
+            },
+        };
 
         let is_sudo_enabled = tedge_config.sudo.enable;
 
@@ -206,6 +249,7 @@ impl AgentConfig {
             mqtt_config,
             http_config,
             restart_config,
+            certificate_renewal_config,
             sw_update_config,
             operation_config,
             config_dir,
@@ -221,6 +265,7 @@ impl AgentConfig {
             file_transfer_urls,
             identity,
             cloud_root_certs,
+            download_trust_store,
             is_sudo_enabled,
             service: tedge_config.service.clone(),
             capabilities,
@@ -292,6 +337,7 @@ impl Agent {
             self.config.identity.clone(),
             self.config.cloud_root_certs.clone(),
         )
+        .with_trust_store(self.config.download_trust_store)
         .builder();
         let mut uploader_actor_builder =
             UploaderActor::new(self.config.identity, self.config.cloud_root_certs).builder();
@@ -342,7 +388,8 @@ impl Agent {
             &mut mqtt_actor_builder,
             &mqtt_schema,
             &self.config.service,
-        );
+        )
+        .with_runtime(runtime.get_handle());
 
         // Instantiate config manager actor if either config_snapshot or config_update operation is enabled
         let config_actor_builder: Option<ConfigManagerBuilder> =
@@ -395,9 +442,11 @@ impl Agent {
                 is_sudo_enabled: self.config.is_sudo_enabled,
             })?;
 
-            let plugin_config =
-                PluginConfig::from_file(log_manager_config.plugin_config_path.path().as_ref())
-                    .await;
+            let log_manager_config_path = log_manager_config.plugin_config_path.path().to_owned();
+            // An invalid plugin config is reported by the log manager, which then refuses log uploads
+            let plugin_config = PluginConfig::from_file(log_manager_config_path.as_ref())
+                .await
+                .unwrap_or_default();
             let mut log_actor = LogManagerBuilder::try_new(
                 log_manager_config,
                 plugin_config,
@@ -407,7 +456,23 @@ impl Agent {
             .await?;
             workflow_actor_builder.register_builtin_operation(&mut log_actor);
             workflow_actor_builder.register_sync_signal_sink(OperationType::LogUpload, &log_actor);
-            Some(log_actor)
+
+            let state_dir = agent_state_dir(&self.config.state_dir, &self.config.config_dir);
+            let log_forwarder_config = LogForwarderConfig {
+                mqtt_schema: mqtt_schema.clone(),
+                device_topic_id: device_topic_id.clone(),
+                plugin_config_path: log_manager_config_path.clone(),
+                checkpoint_path: state_dir.path().join(DEFAULT_CHECKPOINT_FILE_NAME),
+            };
+            let forward_rules =
+                ForwardRule::load_rules(log_manager_config_path.as_std_path()).await;
+            let log_forwarder = LogForwarderBuilder::new(
+                log_forwarder_config,
+                forward_rules,
+                &mut mqtt_actor_builder,
+                &mut fs_watch_actor_builder,
+            );
+            Some((log_actor, log_forwarder))
         } else {
             None
         };
@@ -450,20 +515,44 @@ impl Agent {
                 },
             );
 
+            let command_server = CommandServer::new(mqtt_schema.clone(), &mut mqtt_actor_builder);
+            let mut command_actor_builder =
+                ServerActorBuilder::new(command_server, &ServerConfig::default(), Sequential);
+            mqtt_actor_builder.connect_mapped_sink(
+                command_manager::server::subscriptions(&mqtt_schema),
+                &command_actor_builder,
+                |message| {
+                    Some(RequestEnvelope {
+                        request: CommandRequest::MqttMessage(message),
+                        reply_to: Box::new(NullSender),
+                    })
+                },
+            );
+
             let file_transfer_server_builder = HttpServerBuilder::try_bind(
                 self.config.http_config,
                 &mut entity_store_actor_builder,
+                &mut command_actor_builder,
             )
             .await?;
 
             runtime.spawn(file_transfer_server_builder).await?;
             runtime.spawn(entity_store_actor_builder).await?;
+            runtime.spawn(command_actor_builder).await?;
+
+            if let Some(certificate_renewal_config) = self.config.certificate_renewal_config {
+                let certificate_renewal_builder = CertificateRenewalBuilder::new(
+                    certificate_renewal_config,
+                    &mut mqtt_actor_builder,
+                );
+                runtime.spawn(certificate_renewal_builder).await?;
+            }
         } else {
             info!("Running as a child device: File Transfer Service disabled");
         }
 
         // Spawn all
-        runtime.spawn(mqtt_actor_builder).await?;
+        runtime.spawn_critical(mqtt_actor_builder).await?;
         runtime.spawn(fs_watch_actor_builder).await?;
         runtime.spawn(twin_manager_builder).await?;
         runtime.spawn(downloader_actor_builder).await?;
@@ -471,12 +560,15 @@ impl Agent {
         if let Some(config_actor_builder) = config_actor_builder {
             runtime.spawn(config_actor_builder).await?;
         }
-        if let Some(log_actor_builder) = log_actor_builder {
+        if let Some((log_actor_builder, log_forwarder_builder)) = log_actor_builder {
             runtime.spawn(log_actor_builder).await?;
+            runtime.spawn(log_forwarder_builder).await?;
         }
         runtime.spawn(restart_actor_builder).await?;
         runtime.spawn(software_update_builder).await?;
         runtime.spawn(script_runner).await?;
+        // Not critical: the workflow steps (scripts, downloads, uploads) are awaited by the actor,
+        // and a long step must not be taken for a stalled agent
         runtime.spawn(workflow_actor_builder).await?;
         runtime.spawn(health_actor).await?;
         runtime.spawn(config_publisher_builder).await?;
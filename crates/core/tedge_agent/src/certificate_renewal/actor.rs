@@ -0,0 +1,285 @@
+use crate::certificate_renewal::config::CertificateRenewalConfig;
+use crate::certificate_renewal::config::CloudCertificate;
+use anyhow::Context;
+use async_trait::async_trait;
+use camino::Utf8Path;
+use certificate::PemCertificate;
+use serde_json::json;
+use tedge_actors::Actor;
+use tedge_actors::LoggingSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::mqtt_topics::Channel;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::QoS;
+use tedge_write::CopyOptions;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+/// Type of the event published when a certificate has been renewed
+const CERTIFICATE_RENEWED_EVENT: &str = "certificate_renewed";
+
+pub struct CertificateRenewalActor {
+    config: CertificateRenewalConfig,
+    messages: SimpleMessageBox<NoMessage, NoMessage>,
+    mqtt_publisher: LoggingSender<MqttMessage>,
+}
+
+#[async_trait]
+impl Actor for CertificateRenewalActor {
+    fn name(&self) -> &str {
+        "CertificateRenewal"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        loop {
+            for certificate in self.config.certificates.clone() {
+                self.check_certificate(&certificate).await?;
+            }
+
+            // Wait for the next check, unless the runtime is shutting down
+            if let Ok(None) = tokio::time::timeout(self.config.interval, self.messages.recv()).await
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl CertificateRenewalActor {
+    pub fn new(
+        config: CertificateRenewalConfig,
+        messages: SimpleMessageBox<NoMessage, NoMessage>,
+        mqtt_publisher: LoggingSender<MqttMessage>,
+    ) -> Self {
+        Self {
+            config,
+            messages,
+            mqtt_publisher,
+        }
+    }
+
+    /// Renew the certificate if the renewal threshold has been reached and publish the outcome
+    async fn check_certificate(
+        &mut self,
+        certificate: &CloudCertificate,
+    ) -> Result<(), RuntimeError> {
+        let (not_before, not_after) = match validity_period(&certificate.cert_path) {
+            Ok(period) => period,
+            Err(err) => {
+                warn!(
+                    "Cannot check the validity of the {} certificate {}: {err}",
+                    certificate.name, certificate.cert_path
+                );
+                return Ok(());
+            }
+        };
+        if !self
+            .config
+            .renewal_is_due(not_before, not_after, OffsetDateTime::now_utc())
+        {
+            return Ok(());
+        }
+
+        info!(
+            "Renewing the {} certificate {}, valid until {}",
+            certificate.name,
+            certificate.cert_path,
+            format_date(not_after)
+        );
+        match self.renew_certificate(certificate).await {
+            Ok(not_after) => {
+                info!(
+                    "The {} certificate has been renewed, valid until {}",
+                    certificate.name,
+                    format_date(not_after)
+                );
+                self.publish_renewal_event(certificate, not_after).await?;
+                self.publish_renewal_alarm(certificate, None).await
+            }
+            Err(err) => {
+                error!(
+                    "Fail to renew the {} certificate: {err:#}",
+                    certificate.name
+                );
+                self.publish_renewal_alarm(certificate, Some(err)).await
+            }
+        }
+    }
+
+    /// Renew a certificate and reconnect the cloud, rolling back to the previous certificate on failure
+    ///
+    /// Return the end of the validity period of the new certificate.
+    async fn renew_certificate(
+        &self,
+        certificate: &CloudCertificate,
+    ) -> Result<OffsetDateTime, anyhow::Error> {
+        let previous_cert = tokio::fs::read(&certificate.cert_path)
+            .await
+            .with_context(|| format!("Fail to read {}", certificate.cert_path))?;
+
+        let mut renew_args = vec!["cert", "renew", "--ca", self.config.ca.as_str()];
+        renew_args.extend(certificate.cloud_args.iter().map(String::as_str));
+        self.tedge(&renew_args).await?;
+
+        // On success, `tedge reconnect` promotes the new certificate as the active one.
+        // However, the previous certificate is kept unchanged if the new one is rejected by the cloud endpoint.
+        let reconnected = self.reconnect(certificate).await;
+        let current_cert = tokio::fs::read(&certificate.cert_path)
+            .await
+            .unwrap_or_default();
+        let switched = current_cert != previous_cert;
+
+        match reconnected {
+            Ok(()) if switched => {
+                let (_, not_after) = validity_period(&certificate.cert_path)?;
+                Ok(not_after)
+            }
+            Ok(()) => anyhow::bail!("The new certificate has not been activated"),
+            Err(err) if !switched => Err(err.context("Still using the previous certificate")),
+            Err(err) => {
+                warn!(
+                    "Fail to reconnect {} with the new certificate: restoring the previous certificate",
+                    certificate.name
+                );
+                self.restore_certificate(&certificate.cert_path, previous_cert)
+                    .await
+                    .context("Fail to restore the previous certificate")?;
+                self.reconnect(certificate)
+                    .await
+                    .context("Fail to reconnect with the previous certificate")?;
+                Err(err.context("Rolled back to the previous certificate"))
+            }
+        }
+    }
+
+    async fn reconnect(&self, certificate: &CloudCertificate) -> Result<(), anyhow::Error> {
+        let mut reconnect_args = vec!["reconnect"];
+        reconnect_args.extend(certificate.cloud_args.iter().map(String::as_str));
+        self.tedge(&reconnect_args).await
+    }
+
+    /// Run a `tedge` command, using sudo if enabled
+    async fn tedge(&self, args: &[&str]) -> Result<(), anyhow::Error> {
+        let command_line = format!("tedge {}", args.join(" "));
+        let mut command = tokio::process::Command::from(self.config.sudo.command("tedge"));
+        command
+            .arg("--config-dir")
+            .arg(self.config.config_dir.as_str())
+            .args(args);
+        let output = command
+            .output()
+            .await
+            .with_context(|| format!("Fail to execute `{command_line}`"))?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .next_back()
+            .unwrap_or("no error message");
+        anyhow::bail!("`{command_line}` failed with {}: {reason}", output.status)
+    }
+
+    /// Overwrite the certificate with its previous content, using tedge-write as the agent doesn't own the file
+    async fn restore_certificate(
+        &self,
+        cert_path: &Utf8Path,
+        content: Vec<u8>,
+    ) -> Result<(), anyhow::Error> {
+        let file_name = cert_path.file_name().unwrap_or("certificate.pem");
+        let backup_path = self.config.tmp_dir.join(format!("{file_name}.previous"));
+        tokio::fs::write(&backup_path, content)
+            .await
+            .with_context(|| format!("Fail to write {backup_path}"))?;
+
+        let sudo = self.config.sudo.clone();
+        let from = backup_path.clone();
+        let to = cert_path.to_owned();
+        let restored = tokio::task::spawn_blocking(move || {
+            CopyOptions {
+                from: &from,
+                to: &to,
+                sudo,
+                mode: None,
+                user: None,
+                group: None,
+            }
+            .copy()
+        })
+        .await?;
+
+        let _ = tokio::fs::remove_file(&backup_path).await;
+        restored
+    }
+
+    async fn publish_renewal_event(
+        &mut self,
+        certificate: &CloudCertificate,
+        not_after: OffsetDateTime,
+    ) -> Result<(), RuntimeError> {
+        let topic = self.config.mqtt_schema.topic_for(
+            &self.config.device_topic_id,
+            &Channel::Event {
+                event_type: CERTIFICATE_RENEWED_EVENT.to_string(),
+            },
+        );
+        let payload = json!({
+            "text": format!("The {} certificate has been renewed", certificate.name),
+            "cloud": certificate.name,
+            "not_after": format_date(not_after),
+        });
+        let message = MqttMessage::new(&topic, payload.to_string()).with_qos(QoS::AtLeastOnce);
+        self.mqtt_publisher.send(message).await?;
+        Ok(())
+    }
+
+    /// Raise an alarm if the renewal failed, clear the alarm otherwise
+    async fn publish_renewal_alarm(
+        &mut self,
+        certificate: &CloudCertificate,
+        error: Option<anyhow::Error>,
+    ) -> Result<(), RuntimeError> {
+        let topic = self.config.mqtt_schema.topic_for(
+            &self.config.device_topic_id,
+            &Channel::Alarm {
+                alarm_type: format!("certificate_renewal_{}", certificate.name),
+            },
+        );
+        let payload = match error {
+            None => String::new(),
+            Some(err) => json!({
+                "text": format!("Fail to renew the {} certificate: {err:#}", certificate.name),
+                "severity": "major",
+                "cloud": certificate.name,
+            })
+            .to_string(),
+        };
+        let message = MqttMessage::new(&topic, payload)
+            .with_retain()
+            .with_qos(QoS::AtLeastOnce);
+        self.mqtt_publisher.send(message).await?;
+        Ok(())
+    }
+}
+
+fn validity_period(
+    cert_path: &Utf8Path,
+) -> Result<(OffsetDateTime, OffsetDateTime), anyhow::Error> {
+    let pem = PemCertificate::from_pem_file(cert_path)?;
+    Ok(pem.validity_period()?)
+}
+
+fn format_date(date: OffsetDateTime) -> String {
+    date.format(&Rfc3339).unwrap_or_else(|_| date.to_string())
+}
@@ -0,0 +1,54 @@
+use crate::certificate_renewal::actor::CertificateRenewalActor;
+use crate::certificate_renewal::config::CertificateRenewalConfig;
+use std::convert::Infallible;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::LoggingSender;
+use tedge_actors::MessageSink;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_mqtt_ext::MqttMessage;
+
+pub struct CertificateRenewalBuilder {
+    config: CertificateRenewalConfig,
+    message_box: SimpleMessageBoxBuilder<NoMessage, NoMessage>,
+    mqtt_publisher: LoggingSender<MqttMessage>,
+}
+
+impl CertificateRenewalBuilder {
+    pub fn new(
+        config: CertificateRenewalConfig,
+        mqtt_actor: &mut impl MessageSink<MqttMessage>,
+    ) -> Self {
+        let message_box = SimpleMessageBoxBuilder::new("CertificateRenewal", 1);
+        let mqtt_publisher = LoggingSender::new(
+            "CertificateRenewalToMqttPublisher".into(),
+            mqtt_actor.get_sender(),
+        );
+        Self {
+            config,
+            message_box,
+            mqtt_publisher,
+        }
+    }
+}
+
+impl RuntimeRequestSink for CertificateRenewalBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl Builder<CertificateRenewalActor> for CertificateRenewalBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<CertificateRenewalActor, Self::Error> {
+        Ok(CertificateRenewalActor::new(
+            self.config,
+            self.message_box.build(),
+            self.mqtt_publisher,
+        ))
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::Context;
+use camino::Utf8PathBuf;
+use std::time::Duration;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::models::CloudType;
+use tedge_config::tedge_toml::Cloud;
+use tedge_config::tedge_toml::ProfileName;
+use tedge_config::SudoCommandBuilder;
+use tedge_config::TEdgeConfig;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone)]
+pub struct CertificateRenewalConfig {
+    pub mqtt_schema: MqttSchema,
+
+    /// The device on which the renewal events and alarms are published
+    pub device_topic_id: EntityTopicId,
+
+    /// Interval between two checks of the certificates validity
+    pub interval: Duration,
+
+    /// Fraction of the validity period after which a certificate is renewed
+    pub threshold: f64,
+
+    /// Certificate Authority used to renew the certificates
+    pub ca: String,
+
+    pub certificates: Vec<CloudCertificate>,
+
+    /// The configuration directory given to the `tedge` commands
+    pub config_dir: Utf8PathBuf,
+
+    pub tmp_dir: Utf8PathBuf,
+
+    pub sudo: SudoCommandBuilder,
+}
+
+/// The certificate used by the device to connect a cloud endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloudCertificate {
+    /// The cloud as named in `certificate.renewal.clouds`, e.g. `c8y` or `c8y@production`
+    pub name: String,
+
+    /// The `tedge` command arguments selecting this cloud, e.g. `["c8y", "--profile", "production"]`
+    pub cloud_args: Vec<String>,
+
+    pub cert_path: Utf8PathBuf,
+}
+
+impl CertificateRenewalConfig {
+    pub fn from_tedge_config(
+        mqtt_schema: MqttSchema,
+        device_topic_id: &EntityTopicId,
+        tedge_config: &TEdgeConfig,
+    ) -> Result<CertificateRenewalConfig, anyhow::Error> {
+        let renewal = &tedge_config.certificate.renewal;
+        let threshold = renewal.threshold;
+        if !(threshold > 0.0 && threshold < 1.0) {
+            anyhow::bail!(
+                "Invalid certificate.renewal.threshold: {threshold} is not greater than 0 and lower than 1"
+            );
+        }
+
+        let certificates = renewal
+            .clouds
+            .0
+            .iter()
+            .map(|cloud| CloudCertificate::from_tedge_config(cloud, tedge_config))
+            .collect::<Result<_, _>>()?;
+
+        Ok(CertificateRenewalConfig {
+            mqtt_schema,
+            device_topic_id: device_topic_id.clone(),
+            interval: renewal.interval.duration(),
+            threshold,
+            ca: renewal.ca.clone(),
+            certificates,
+            config_dir: tedge_config.root_dir().to_owned(),
+            tmp_dir: tedge_config.tmp.path.clone().into(),
+            sudo: SudoCommandBuilder::new(tedge_config),
+        })
+    }
+
+    /// Tell if a certificate valid from `not_before` till `not_after` has to be renewed at `now`
+    pub fn renewal_is_due(
+        &self,
+        not_before: OffsetDateTime,
+        not_after: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> bool {
+        let validity = (not_after - not_before).as_seconds_f64();
+        let elapsed = (now - not_before).as_seconds_f64();
+        elapsed >= self.threshold * validity
+    }
+}
+
+impl CloudCertificate {
+    /// Build the certificate of a cloud given as `<cloud>` or `<cloud>@<profile>`
+    fn from_tedge_config(cloud: &str, tedge_config: &TEdgeConfig) -> Result<Self, anyhow::Error> {
+        let (cloud_type, profile) = match cloud.split_once('@') {
+            None => (cloud, None),
+            Some((cloud_type, profile)) => (cloud_type, Some(profile)),
+        };
+        let cloud_type: CloudType = cloud_type
+            .parse()
+            .with_context(|| format!("Invalid cloud in certificate.renewal.clouds: {cloud}"))?;
+        let profile: Option<ProfileName> = profile
+            .map(|profile| profile.parse())
+            .transpose()
+            .with_context(|| format!("Invalid profile in certificate.renewal.clouds: {cloud}"))?;
+
+        let cert_path = match cloud_type {
+            CloudType::C8y => tedge_config.device_cert_path(Some(Cloud::C8y(profile.as_ref())))?,
+            CloudType::Az => tedge_config.device_cert_path(Some(Cloud::Az(profile.as_ref())))?,
+            CloudType::Aws => tedge_config.device_cert_path(Some(Cloud::Aws(profile.as_ref())))?,
+        };
+
+        let mut cloud_args = vec![cloud_type.to_string()];
+        if let Some(profile) = profile {
+            cloud_args.push("--profile".to_string());
+            cloud_args.push(profile.to_string());
+        }
+
+        Ok(CloudCertificate {
+            name: cloud.to_string(),
+            cloud_args,
+            cert_path: cert_path.into(),
+        })
+    }
+}
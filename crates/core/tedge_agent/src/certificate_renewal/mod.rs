@@ -0,0 +1,14 @@
+//! Automatic renewal of the device certificates
+//!
+//! The agent periodically checks the certificates used to connect the clouds listed by `certificate.renewal.clouds`.
+//! When a configurable fraction of the validity period of a certificate has elapsed, the agent:
+//! - renews the certificate with `tedge cert renew`,
+//! - reconnects the cloud with `tedge reconnect`, which switches to the new certificate,
+//! - restores the previous certificate if the cloud cannot be reconnected with the new one,
+//! - publishes the outcome on the main device, as a `certificate_renewed` event or a `certificate_renewal_<cloud>` alarm.
+pub mod actor;
+pub mod builder;
+pub mod config;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,221 @@
+use crate::certificate_renewal::builder::CertificateRenewalBuilder;
+use crate::certificate_renewal::config::CertificateRenewalConfig;
+use crate::certificate_renewal::config::CloudCertificate;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde_json::Value;
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::SudoCommandBuilder;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_test_utils::fs::TempTedgeDir;
+use time::OffsetDateTime;
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// `tedge reconnect` promoting the new certificate
+const RECONNECT_OK: &str = r#"[ -f "$CERT.new" ] && mv "$CERT.new" "$CERT"; exit 0"#;
+
+/// `tedge reconnect` keeping the current certificate, the new one being rejected by the cloud
+const RECONNECT_REJECTED: &str =
+    r#"echo "Successfully connected, however not using the new certificate" >&2; exit 3"#;
+
+/// `tedge reconnect` promoting the new certificate, but failing to connect the cloud with it
+const RECONNECT_BROKEN: &str = r#"if [ -f "$CERT.new" ]; then mv "$CERT.new" "$CERT"; echo "Connection check failed" >&2; exit 1; fi; exit 0"#;
+
+#[test]
+fn renewal_is_due_after_the_threshold() {
+    let ttd = TempTedgeDir::new();
+    let config = renewal_config(ttd.utf8_path(), "sudo");
+    let not_before = OffsetDateTime::now_utc();
+    let not_after = not_before + time::Duration::days(100);
+
+    assert!(!config.renewal_is_due(not_before, not_after, not_before));
+    assert!(!config.renewal_is_due(not_before, not_after, not_before + time::Duration::days(74)));
+    assert!(config.renewal_is_due(not_before, not_after, not_before + time::Duration::days(75)));
+    assert!(config.renewal_is_due(not_before, not_after, not_after + time::Duration::days(1)));
+}
+
+#[tokio::test]
+async fn certificate_is_not_renewed_before_the_threshold() {
+    let ttd = TempTedgeDir::new();
+    let device = TestDevice::new(&ttd, 10, 100);
+    let mut mqtt = device.spawn_renewal_actor(RECONNECT_OK);
+
+    assert!(mqtt.recv().await.is_none());
+    assert_eq!(device.current_certificate(), device.initial_cert);
+}
+
+#[tokio::test]
+async fn certificate_is_renewed_after_the_threshold() {
+    let ttd = TempTedgeDir::new();
+    let device = TestDevice::new(&ttd, 80, 20);
+    let mut mqtt = device.spawn_renewal_actor(RECONNECT_OK);
+
+    let event = mqtt.recv().await.expect("a renewal event");
+    assert_eq!(event.topic.name, "te/device/main///e/certificate_renewed");
+    let payload: Value = serde_json::from_slice(event.payload_bytes()).unwrap();
+    assert_eq!(payload["text"], "The c8y certificate has been renewed");
+    assert_eq!(payload["cloud"], "c8y");
+
+    let alarm = mqtt.recv().await.expect("an alarm clear message");
+    assert_eq!(
+        alarm.topic.name,
+        "te/device/main///a/certificate_renewal_c8y"
+    );
+    assert!(alarm.payload_bytes().is_empty());
+
+    assert_eq!(device.current_certificate(), device.renewed_cert);
+}
+
+#[tokio::test]
+async fn alarm_is_raised_when_the_new_certificate_is_rejected() {
+    let ttd = TempTedgeDir::new();
+    let device = TestDevice::new(&ttd, 80, 20);
+    let mut mqtt = device.spawn_renewal_actor(RECONNECT_REJECTED);
+
+    let alarm = mqtt.recv().await.expect("an alarm");
+    assert_eq!(
+        alarm.topic.name,
+        "te/device/main///a/certificate_renewal_c8y"
+    );
+    let payload: Value = serde_json::from_slice(alarm.payload_bytes()).unwrap();
+    let text = payload["text"].as_str().unwrap();
+    assert!(
+        text.contains("Still using the previous certificate"),
+        "{text}"
+    );
+    assert!(text.contains("not using the new certificate"), "{text}");
+
+    assert_eq!(device.current_certificate(), device.initial_cert);
+}
+
+#[tokio::test]
+async fn previous_certificate_is_restored_when_the_cloud_cannot_be_reconnected() {
+    let ttd = TempTedgeDir::new();
+    let device = TestDevice::new(&ttd, 80, 20);
+    let mut mqtt = device.spawn_renewal_actor(RECONNECT_BROKEN);
+
+    let alarm = mqtt.recv().await.expect("an alarm");
+    assert_eq!(
+        alarm.topic.name,
+        "te/device/main///a/certificate_renewal_c8y"
+    );
+    let payload: Value = serde_json::from_slice(alarm.payload_bytes()).unwrap();
+    let text = payload["text"].as_str().unwrap();
+    assert!(
+        text.contains("Rolled back to the previous certificate"),
+        "{text}"
+    );
+    assert!(text.contains("Connection check failed"), "{text}");
+
+    assert_eq!(device.current_certificate(), device.initial_cert);
+}
+
+struct TestDevice {
+    dir: Utf8PathBuf,
+    cert_path: Utf8PathBuf,
+    initial_cert: String,
+    renewed_cert: String,
+}
+
+impl TestDevice {
+    /// Create a device certificate issued `age` days ago and expiring in `remaining` days
+    fn new(ttd: &TempTedgeDir, age: i64, remaining: i64) -> Self {
+        let dir = ttd.utf8_path_buf();
+        let now = OffsetDateTime::now_utc();
+        let initial_cert = self_signed_certificate(
+            now - time::Duration::days(age),
+            now + time::Duration::days(remaining),
+        );
+        let renewed_cert =
+            self_signed_certificate(now, now + time::Duration::days(age + remaining));
+        let cert_path = dir.join("tedge-certificate.pem");
+        std::fs::write(&cert_path, &initial_cert).unwrap();
+        std::fs::write(dir.join("renewed-certificate.pem"), &renewed_cert).unwrap();
+        TestDevice {
+            dir,
+            cert_path,
+            initial_cert,
+            renewed_cert,
+        }
+    }
+
+    fn current_certificate(&self) -> String {
+        std::fs::read_to_string(&self.cert_path).unwrap()
+    }
+
+    /// Spawn a renewal actor using a fake `sudo` that simulates `tedge` and `tedge-write`
+    fn spawn_renewal_actor(
+        &self,
+        reconnect: &str,
+    ) -> TimedMessageBox<SimpleMessageBox<MqttMessage, NoMessage>> {
+        let sudo = self.dir.join("fake-sudo");
+        let script = format!(
+            r#"#!/bin/sh
+CERT="{cert}"
+shift
+program="$1"
+shift
+case "$program" in
+    *tedge-write) cat > "$1"; exit 0 ;;
+esac
+shift 2
+case "$1" in
+    cert) cp "{dir}/renewed-certificate.pem" "$CERT.new" ;;
+    reconnect) {reconnect} ;;
+esac
+"#,
+            cert = self.cert_path,
+            dir = self.dir,
+        );
+        std::fs::write(&sudo, script).unwrap();
+        std::fs::set_permissions(&sudo, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut config = renewal_config(&self.dir, sudo.as_str());
+        config.certificates = vec![CloudCertificate {
+            name: "c8y".to_string(),
+            cloud_args: vec!["c8y".to_string()],
+            cert_path: self.cert_path.clone(),
+        }];
+
+        let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, NoMessage> =
+            SimpleMessageBoxBuilder::new("MQTT", 16);
+        let actor = CertificateRenewalBuilder::new(config, &mut mqtt_builder).build();
+        tokio::spawn(async move { actor.run().await });
+
+        mqtt_builder.build().with_timeout(TEST_TIMEOUT)
+    }
+}
+
+fn renewal_config(dir: &Utf8Path, sudo: &str) -> CertificateRenewalConfig {
+    CertificateRenewalConfig {
+        mqtt_schema: MqttSchema::default(),
+        device_topic_id: EntityTopicId::default_main_device(),
+        interval: Duration::from_secs(3600),
+        threshold: 0.75,
+        ca: "c8y".to_string(),
+        certificates: vec![],
+        config_dir: dir.to_owned(),
+        tmp_dir: dir.to_owned(),
+        sudo: SudoCommandBuilder::with_program(sudo),
+    }
+}
+
+fn self_signed_certificate(not_before: OffsetDateTime, not_after: OffsetDateTime) -> String {
+    let mut params = rcgen::CertificateParams::new(vec!["test-device".to_string()]).unwrap();
+    params.not_before = not_before;
+    params.not_after = not_after;
+    let key = rcgen::KeyPair::generate().unwrap();
+    params.self_signed(&key).unwrap().pem()
+}
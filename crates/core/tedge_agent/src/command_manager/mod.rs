@@ -0,0 +1,4 @@
+pub(crate) mod server;
+
+#[cfg(test)]
+mod tests;
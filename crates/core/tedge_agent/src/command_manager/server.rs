@@ -0,0 +1,290 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use tedge_actors::LoggingSender;
+use tedge_actors::MessageSink;
+use tedge_actors::Sender;
+use tedge_actors::Server;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_api::workflow::GenericCommandState;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_mqtt_ext::TopicFilter;
+use tokio::sync::watch;
+use tracing::error;
+
+/// The latest known state of a command, `None` if there is no such command or it has been cleared
+pub type CommandWatcher = watch::Receiver<Option<GenericCommandState>>;
+
+/// Identifies a command by its target entity, operation and id
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommandTarget {
+    pub entity: EntityTopicId,
+    pub operation: OperationType,
+    pub cmd_id: String,
+}
+
+/// Selects the commands of a given entity and/or operation, all the commands if none is given
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CommandFilter {
+    pub entity: Option<EntityTopicId>,
+    pub operation: Option<OperationType>,
+}
+
+#[derive(Debug)]
+pub enum CommandRequest {
+    Create(CommandTarget, Value),
+    Get(CommandTarget),
+    List(CommandFilter),
+    Clear(CommandTarget),
+    Watch(CommandTarget),
+    MqttMessage(MqttMessage),
+}
+
+#[derive(Debug)]
+pub enum CommandResponse {
+    Create(Result<GenericCommandState, CommandError>),
+    Get(Option<GenericCommandState>),
+    List(Vec<(CommandTarget, GenericCommandState)>),
+    /// The cleared command, `None` if there is no such command
+    Clear(Result<Option<GenericCommandState>, CommandError>),
+    /// A watcher on the command, `None` if there is no such command
+    Watch(Option<CommandWatcher>),
+    Ok,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CommandError {
+    #[error("The {operation} operation is not supported by {entity}")]
+    UnsupportedOperation {
+        entity: EntityTopicId,
+        operation: OperationType,
+    },
+
+    #[error("A command is already in progress on {0}")]
+    DuplicatedCommand(String),
+
+    #[error("A command payload must be a JSON object")]
+    InvalidPayload,
+
+    #[error("Failed to publish the command over MQTT")]
+    ChannelError(#[from] tedge_actors::ChannelError),
+}
+
+/// Tracks the commands and capabilities published over MQTT,
+/// so commands can be created, retrieved, watched and cleared by local clients.
+///
+/// The commands themselves are processed by the operation workflows,
+/// this server being only a proxy to the MQTT command topics.
+pub struct CommandServer {
+    mqtt_schema: MqttSchema,
+    mqtt_publisher: LoggingSender<MqttMessage>,
+    // Capability topics, i.e. `<root>/<entity>/cmd/<operation>`, with a non-empty retained message
+    capabilities: HashSet<String>,
+    // Latest state of each command, indexed by command topic
+    commands: HashMap<String, watch::Sender<Option<GenericCommandState>>>,
+}
+
+impl CommandServer {
+    pub fn new(mqtt_schema: MqttSchema, mqtt_actor: &mut impl MessageSink<MqttMessage>) -> Self {
+        let mqtt_publisher = LoggingSender::new("MqttPublisher".into(), mqtt_actor.get_sender());
+        CommandServer {
+            mqtt_schema,
+            mqtt_publisher,
+            capabilities: HashSet::new(),
+            commands: HashMap::new(),
+        }
+    }
+
+    fn command_topic(&self, target: &CommandTarget) -> Topic {
+        self.mqtt_schema.topic_for(
+            &target.entity,
+            &Channel::Command {
+                operation: target.operation.clone(),
+                cmd_id: target.cmd_id.clone(),
+            },
+        )
+    }
+
+    async fn create_command(
+        &mut self,
+        target: CommandTarget,
+        payload: Value,
+    ) -> Result<GenericCommandState, CommandError> {
+        if !payload.is_object() {
+            return Err(CommandError::InvalidPayload);
+        }
+        let capability_topic = self
+            .mqtt_schema
+            .capability_topic_for(&target.entity, target.operation.clone());
+        if !self.capabilities.contains(&capability_topic.name) {
+            return Err(CommandError::UnsupportedOperation {
+                entity: target.entity,
+                operation: target.operation,
+            });
+        }
+        let topic = self.command_topic(&target);
+        if self.get_command(&topic).is_some() {
+            return Err(CommandError::DuplicatedCommand(topic.name));
+        }
+
+        let state = GenericCommandState::new(topic, "init".to_string(), payload);
+
+        self.mqtt_publisher
+            .send(state.clone().into_message())
+            .await?;
+        self.update_command(state.clone());
+        Ok(state)
+    }
+
+    async fn clear_command(
+        &mut self,
+        topic: &Topic,
+    ) -> Result<Option<GenericCommandState>, CommandError> {
+        let Some(state) = self.get_command(topic) else {
+            return Ok(None);
+        };
+        if let Err(err) = self
+            .mqtt_publisher
+            .send(state.clone().clear().into_message())
+            .await
+        {
+            error!("Failed to clear command {}: {err}", topic.name);
+            return Err(err.into());
+        }
+        self.update_command(state.clone().clear());
+        Ok(Some(state))
+    }
+
+    fn get_command(&self, topic: &Topic) -> Option<GenericCommandState> {
+        self.commands
+            .get(&topic.name)
+            .and_then(|sender| sender.borrow().clone())
+    }
+
+    fn list_commands(&self, filter: &CommandFilter) -> Vec<(CommandTarget, GenericCommandState)> {
+        let mut commands: Vec<(CommandTarget, GenericCommandState)> = self
+            .commands
+            .values()
+            .filter_map(|sender| sender.borrow().clone())
+            .filter_map(
+                |state| match self.mqtt_schema.entity_channel_of(&state.topic) {
+                    Ok((entity, Channel::Command { operation, cmd_id })) => {
+                        let target = CommandTarget {
+                            entity,
+                            operation,
+                            cmd_id,
+                        };
+                        Some((target, state))
+                    }
+                    _ => None,
+                },
+            )
+            .filter(|(target, _)| {
+                filter.entity.as_ref().is_none_or(|e| e == &target.entity)
+                    && filter
+                        .operation
+                        .as_ref()
+                        .is_none_or(|o| o == &target.operation)
+            })
+            .collect();
+        commands.sort_by(|(_, a), (_, b)| a.topic.name.cmp(&b.topic.name));
+        commands
+    }
+
+    fn watch_command(&mut self, topic: &Topic) -> Option<CommandWatcher> {
+        // Forget the commands cleared while being watched, and no more watched
+        self.commands
+            .retain(|_, sender| sender.borrow().is_some() || sender.receiver_count() > 0);
+
+        let sender = self.commands.get(&topic.name)?;
+        if sender.borrow().is_none() {
+            return None;
+        }
+        Some(sender.subscribe())
+    }
+
+    fn update_command(&mut self, state: GenericCommandState) {
+        let topic = state.topic.name.clone();
+        if state.is_cleared() {
+            if let Some(sender) = self.commands.get(&topic) {
+                sender.send_replace(None);
+                if sender.receiver_count() == 0 {
+                    self.commands.remove(&topic);
+                }
+            }
+        } else {
+            self.commands
+                .entry(topic)
+                .or_insert_with(|| watch::channel(None).0)
+                .send_replace(Some(state));
+        }
+    }
+
+    fn process_mqtt_message(&mut self, message: MqttMessage) {
+        match self.mqtt_schema.entity_channel_of(&message.topic) {
+            Ok((_, Channel::CommandMetadata { .. })) => {
+                if message.payload_bytes().is_empty() {
+                    self.capabilities.remove(&message.topic.name);
+                } else {
+                    self.capabilities.insert(message.topic.name.clone());
+                }
+            }
+            Ok((_, Channel::Command { .. })) => {
+                match GenericCommandState::from_command_message(&message) {
+                    Ok(state) => self.update_command(state),
+                    Err(err) => error!("Invalid command payload on {}: {err}", message.topic.name),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[async_trait]
+impl Server for CommandServer {
+    type Request = CommandRequest;
+    type Response = CommandResponse;
+
+    fn name(&self) -> &str {
+        "CommandServer"
+    }
+
+    async fn handle(&mut self, request: CommandRequest) -> CommandResponse {
+        match request {
+            CommandRequest::Create(target, payload) => {
+                let res = self.create_command(target, payload).await;
+                CommandResponse::Create(res)
+            }
+            CommandRequest::Get(target) => {
+                let topic = self.command_topic(&target);
+                CommandResponse::Get(self.get_command(&topic))
+            }
+            CommandRequest::List(filter) => CommandResponse::List(self.list_commands(&filter)),
+            CommandRequest::Clear(target) => {
+                let topic = self.command_topic(&target);
+                CommandResponse::Clear(self.clear_command(&topic).await)
+            }
+            CommandRequest::Watch(target) => {
+                let topic = self.command_topic(&target);
+                CommandResponse::Watch(self.watch_command(&topic))
+            }
+            CommandRequest::MqttMessage(message) => {
+                self.process_mqtt_message(message);
+                CommandResponse::Ok
+            }
+        }
+    }
+}
+
+pub fn subscriptions(mqtt_schema: &MqttSchema) -> TopicFilter {
+    let mut topics = mqtt_schema.topics(EntityFilter::AnyEntity, ChannelFilter::AnyCommand);
+    topics.add_all(mqtt_schema.topics(EntityFilter::AnyEntity, ChannelFilter::AnyCommandMetadata));
+    topics
+}
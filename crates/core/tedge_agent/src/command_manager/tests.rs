@@ -0,0 +1,228 @@
+use crate::command_manager::server::CommandError;
+use crate::command_manager::server::CommandFilter;
+use crate::command_manager::server::CommandRequest;
+use crate::command_manager::server::CommandResponse;
+use crate::command_manager::server::CommandServer;
+use crate::command_manager::server::CommandTarget;
+use serde_json::json;
+use tedge_actors::Builder;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::Server;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_mqtt_ext::test_helpers::assert_received_includes_json;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+
+#[tokio::test]
+async fn command_creation_is_rejected_for_unsupported_operations() {
+    let (mut server, _mqtt) = command_server();
+
+    let response = server
+        .handle(CommandRequest::Create(restart("123"), json!({})))
+        .await;
+
+    assert!(matches!(
+        response,
+        CommandResponse::Create(Err(CommandError::UnsupportedOperation { .. }))
+    ));
+}
+
+#[tokio::test]
+async fn command_creation_publishes_an_init_state() {
+    let (mut server, mut mqtt) = command_server();
+    declare_capability(&mut server, "te/device/main///cmd/restart").await;
+
+    let response = server
+        .handle(CommandRequest::Create(
+            restart("123"),
+            json!({"foo": "bar"}),
+        ))
+        .await;
+    assert!(matches!(response, CommandResponse::Create(Ok(_))));
+
+    assert_received_includes_json(
+        &mut mqtt,
+        [(
+            "te/device/main///cmd/restart/123",
+            json!({"status": "init", "foo": "bar"}),
+        )],
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn duplicated_commands_are_rejected() {
+    let (mut server, _mqtt) = command_server();
+    declare_capability(&mut server, "te/device/main///cmd/restart").await;
+
+    server
+        .handle(CommandRequest::Create(restart("123"), json!({})))
+        .await;
+    let response = server
+        .handle(CommandRequest::Create(restart("123"), json!({})))
+        .await;
+
+    assert!(matches!(
+        response,
+        CommandResponse::Create(Err(CommandError::DuplicatedCommand(_)))
+    ));
+}
+
+#[tokio::test]
+async fn command_states_are_updated_from_mqtt() {
+    let (mut server, _mqtt) = command_server();
+
+    server
+        .handle(CommandRequest::MqttMessage(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/restart/123"),
+            r#"{"status":"executing"}"#,
+        )))
+        .await;
+
+    let CommandResponse::Get(Some(state)) =
+        server.handle(CommandRequest::Get(restart("123"))).await
+    else {
+        panic!("Expected the command to be known")
+    };
+    assert_eq!(state.status, "executing");
+}
+
+#[tokio::test]
+async fn cleared_commands_are_no_longer_returned() {
+    let (mut server, mut mqtt) = command_server();
+
+    server
+        .handle(CommandRequest::MqttMessage(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/restart/123"),
+            r#"{"status":"successful"}"#,
+        )))
+        .await;
+
+    let response = server.handle(CommandRequest::Clear(restart("123"))).await;
+    assert!(matches!(response, CommandResponse::Clear(Ok(Some(_)))));
+
+    let message = mqtt.recv().await.unwrap();
+    assert_eq!(message.topic.name, "te/device/main///cmd/restart/123");
+    assert!(message.payload_bytes().is_empty());
+
+    let response = server.handle(CommandRequest::Get(restart("123"))).await;
+    assert!(matches!(response, CommandResponse::Get(None)));
+}
+
+#[tokio::test]
+async fn watchers_are_notified_of_command_updates() {
+    let (mut server, _mqtt) = command_server();
+
+    server
+        .handle(CommandRequest::MqttMessage(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/restart/123"),
+            r#"{"status":"init"}"#,
+        )))
+        .await;
+
+    let CommandResponse::Watch(Some(mut watcher)) =
+        server.handle(CommandRequest::Watch(restart("123"))).await
+    else {
+        panic!("Expected a command watcher")
+    };
+
+    server
+        .handle(CommandRequest::MqttMessage(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/restart/123"),
+            r#"{"status":"executing"}"#,
+        )))
+        .await;
+
+    watcher.changed().await.unwrap();
+    let status = watcher.borrow().as_ref().map(|state| state.status.clone());
+    assert_eq!(status, Some("executing".to_string()));
+}
+
+#[tokio::test]
+async fn unknown_commands_cannot_be_watched() {
+    let (mut server, _mqtt) = command_server();
+
+    let response = server.handle(CommandRequest::Watch(restart("123"))).await;
+    assert!(matches!(response, CommandResponse::Watch(None)));
+
+    // Watching an unknown command doesn't register it
+    let response = server
+        .handle(CommandRequest::List(CommandFilter::default()))
+        .await;
+    assert!(matches!(response, CommandResponse::List(commands) if commands.is_empty()));
+}
+
+#[tokio::test]
+async fn commands_are_listed_by_entity_and_operation() {
+    let (mut server, _mqtt) = command_server();
+
+    for (topic, payload) in [
+        (
+            "te/device/main///cmd/restart/123",
+            r#"{"status":"executing"}"#,
+        ),
+        (
+            "te/device/child01///cmd/restart/456",
+            r#"{"status":"init"}"#,
+        ),
+        (
+            "te/device/main///cmd/software_update/789",
+            r#"{"status":"init"}"#,
+        ),
+    ] {
+        server
+            .handle(CommandRequest::MqttMessage(MqttMessage::new(
+                &Topic::new_unchecked(topic),
+                payload,
+            )))
+            .await;
+    }
+
+    let CommandResponse::List(commands) = server
+        .handle(CommandRequest::List(CommandFilter::default()))
+        .await
+    else {
+        panic!("Expected a list of commands")
+    };
+    assert_eq!(commands.len(), 3);
+
+    let CommandResponse::List(commands) = server
+        .handle(CommandRequest::List(CommandFilter {
+            entity: Some(EntityTopicId::default_main_device()),
+            operation: Some(OperationType::Restart),
+        }))
+        .await
+    else {
+        panic!("Expected a list of commands")
+    };
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].0, restart("123"));
+    assert_eq!(commands[0].1.status, "executing");
+}
+
+fn restart(cmd_id: &str) -> CommandTarget {
+    CommandTarget {
+        entity: EntityTopicId::default_main_device(),
+        operation: OperationType::Restart,
+        cmd_id: cmd_id.to_string(),
+    }
+}
+
+async fn declare_capability(server: &mut CommandServer, topic: &str) {
+    server
+        .handle(CommandRequest::MqttMessage(
+            MqttMessage::new(&Topic::new_unchecked(topic), "{}").with_retain(),
+        ))
+        .await;
+}
+
+fn command_server() -> (CommandServer, SimpleMessageBox<MqttMessage, NoMessage>) {
+    let mut mqtt_actor = SimpleMessageBoxBuilder::new("MQTT", 16);
+    let server = CommandServer::new(MqttSchema::default(), &mut mqtt_actor);
+    (server, mqtt_actor.build())
+}
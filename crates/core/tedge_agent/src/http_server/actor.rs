@@ -1,3 +1,5 @@
+use crate::command_manager::server::CommandRequest;
+use crate::command_manager::server::CommandResponse;
 use crate::entity_manager::server::EntityStoreRequest;
 use crate::entity_manager::server::EntityStoreResponse;
 use crate::http_server::error::HttpServerError;
@@ -35,6 +37,7 @@ pub struct HttpServerActor {
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
     listener: TcpListener,
     entity_store_handle: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+    command_handle: ClientMessageBox<CommandRequest, CommandResponse>,
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +64,7 @@ impl Actor for HttpServerActor {
             self.file_transfer_dir,
             self.data_dir,
             self.entity_store_handle,
+            self.command_handle,
         );
 
         let server = http_server(self.listener, self.rustls_config, agent_state)?;
@@ -86,18 +90,21 @@ pub struct HttpServerBuilder {
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
     listener: TcpListener,
     entity_store_handle: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+    command_handle: ClientMessageBox<CommandRequest, CommandResponse>,
 }
 
 impl HttpServerBuilder {
     pub(crate) async fn try_bind(
         config: HttpServerConfig<impl PemReader, impl TrustStoreLoader>,
         entity_store_service: &mut impl Service<EntityStoreRequest, EntityStoreResponse>,
+        command_service: &mut impl Service<CommandRequest, CommandResponse>,
     ) -> Result<Self, anyhow::Error> {
         let listener = TcpListener::bind(config.bind_addr)
             .await
             .with_context(|| format!("Binding file-transfer server to {}", config.bind_addr))?;
         let (signal_sender, signal_receiver) = mpsc::channel(10);
         let entity_store_handle = ClientMessageBox::new(entity_store_service);
+        let command_handle = ClientMessageBox::new(command_service);
 
         Ok(Self {
             rustls_config: load_ssl_config(
@@ -112,6 +119,7 @@ impl HttpServerBuilder {
             signal_receiver,
             listener,
             entity_store_handle,
+            command_handle,
         })
     }
 }
@@ -133,6 +141,7 @@ impl Builder<HttpServerActor> for HttpServerBuilder {
             signal_receiver: self.signal_receiver,
             listener: self.listener,
             entity_store_handle: self.entity_store_handle,
+            command_handle: self.command_handle,
         })
     }
 }
@@ -182,10 +191,14 @@ mod tests {
         let ttd = TempTedgeDir::new();
         let (_listener, port_in_use) = create_listener().await?;
         let mut entity_store_service = ServerMessageBoxBuilder::new("EntityStoreBox", 16);
+        let mut command_service = ServerMessageBoxBuilder::new("CommandBox", 16);
 
-        let binding_res =
-            HttpServerBuilder::try_bind(http_config(&ttd, port_in_use), &mut entity_store_service)
-                .await;
+        let binding_res = HttpServerBuilder::try_bind(
+            http_config(&ttd, port_in_use),
+            &mut entity_store_service,
+            &mut command_service,
+        )
+        .await;
 
         ensure!(
             binding_res.is_err(),
@@ -258,8 +271,10 @@ mod tests {
             let config = http_config(&temp_dir, 0);
             let (tx, rx) = mpsc::channel(1);
             let mut entity_store_service = ServerMessageBoxBuilder::new("EntityStoreBox", 16);
+            let mut command_service = ServerMessageBoxBuilder::new("CommandBox", 16);
 
-            let port = Self::spawn(config, tx, &mut entity_store_service).await?;
+            let port =
+                Self::spawn(config, tx, &mut entity_store_service, &mut command_service).await?;
 
             Ok(TestFileTransferService {
                 port,
@@ -300,8 +315,10 @@ mod tests {
             let config = https_config(&temp_dir, &server_cert, trusted_root)?;
             let (tx, rx) = mpsc::channel(1);
             let mut entity_store_service = ServerMessageBoxBuilder::new("EntityStoreBox", 16);
+            let mut command_service = ServerMessageBoxBuilder::new("CommandBox", 16);
 
-            let port = Self::spawn(config, tx, &mut entity_store_service).await?;
+            let port =
+                Self::spawn(config, tx, &mut entity_store_service, &mut command_service).await?;
 
             Ok(TestFileTransferService {
                 port,
@@ -358,8 +375,10 @@ mod tests {
             config: TestConfig,
             mut error_tx: Sender<RuntimeError>,
             entity_store_service: &mut impl Service<EntityStoreRequest, EntityStoreResponse>,
+            command_service: &mut impl Service<CommandRequest, CommandResponse>,
         ) -> anyhow::Result<u16> {
-            let builder = HttpServerBuilder::try_bind(config, entity_store_service).await?;
+            let builder =
+                HttpServerBuilder::try_bind(config, entity_store_service, command_service).await?;
             let port = builder.listener.local_addr()?.port();
             let actor = builder.build();
 
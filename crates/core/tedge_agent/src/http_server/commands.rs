@@ -0,0 +1,727 @@
+//! This module defines the axum routes and handlers for the command REST APIs.
+//! The following endpoints are currently supported:
+//!
+//! - `GET /v1/commands`: Lists the current commands, optionally filtered by `entity` and `operation`.
+//! - `POST /v1/commands/{entity}/{operation}/{cmd-id}`: Creates a new command from a JSON payload.
+//! - `GET /v1/commands/{entity}/{operation}/{cmd-id}`: Retrieves the current state of a command.
+//! - `DELETE /v1/commands/{entity}/{operation}/{cmd-id}`: Clears a command.
+//!
+//! A GET request can be turned into a long-poll request waiting for a state transition,
+//! using the `status` and `timeout` query parameters:
+//! `GET /v1/commands/device/main///restart/123?status=executing&timeout=60`
+//! returns as soon as the command is no longer `executing` or after 60 seconds.
+use super::entity_store::topic_id_from_path_segments;
+use super::entity_store::HTTP_MAX_PAYLOAD_SIZE;
+use super::server::AgentState;
+use crate::command_manager::server::CommandError;
+use crate::command_manager::server::CommandFilter;
+use crate::command_manager::server::CommandRequest;
+use crate::command_manager::server::CommandResponse;
+use crate::command_manager::server::CommandTarget;
+use axum::extract::DefaultBodyLimit;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Json;
+use axum::Router;
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use serde_json::Value;
+use std::time::Duration;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::TopicIdError;
+use tedge_api::workflow::GenericCommandState;
+
+/// Default duration of a long-poll request
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum duration of a long-poll request
+const MAX_WAIT_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default, Deserialize)]
+pub struct WatchParams {
+    /// The status the client is aware of; the request returns when the command moves to another status
+    #[serde(default)]
+    status: Option<String>,
+
+    /// The maximum number of seconds to wait for a status change
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListParams {
+    /// Only list the commands targeting this entity
+    #[serde(default)]
+    entity: Option<String>,
+
+    /// Only list the commands of this operation
+    #[serde(default)]
+    operation: Option<String>,
+}
+
+impl TryFrom<ListParams> for CommandFilter {
+    type Error = TopicIdError;
+
+    fn try_from(params: ListParams) -> Result<Self, Self::Error> {
+        let entity = params
+            .entity
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<EntityTopicId>())
+            .transpose()?;
+        let operation = params
+            .operation
+            .filter(|v| !v.is_empty())
+            .map(|v| v.as_str().into());
+        Ok(CommandFilter { entity, operation })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+    #[error(transparent)]
+    InvalidEntityTopicId(#[from] TopicIdError),
+
+    #[error(transparent)]
+    CommandError(#[from] CommandError),
+
+    #[error("Command not found: {0}")]
+    CommandNotFound(String),
+
+    #[allow(clippy::enum_variant_names)]
+    #[error("Failed to forward the request to the command server")]
+    ChannelError(#[from] tedge_actors::ChannelError),
+
+    #[error("Received unexpected response from command server")]
+    InvalidCommandServerResponse,
+
+    #[error(transparent)]
+    FromSerdeJson(#[from] serde_json::Error),
+
+    #[error("Not Found")]
+    ResourceNotFound,
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status_code = match &self {
+            Error::InvalidEntityTopicId(_) => StatusCode::BAD_REQUEST,
+            Error::CommandError(err) => match err {
+                CommandError::UnsupportedOperation { .. } => StatusCode::NOT_FOUND,
+                CommandError::DuplicatedCommand(_) => StatusCode::CONFLICT,
+                CommandError::InvalidPayload => StatusCode::BAD_REQUEST,
+                CommandError::ChannelError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            Error::CommandNotFound(_) => StatusCode::NOT_FOUND,
+            Error::ChannelError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::InvalidCommandServerResponse => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::FromSerdeJson(_) => StatusCode::BAD_REQUEST,
+            Error::ResourceNotFound => StatusCode::NOT_FOUND,
+        };
+        let error_message = self.to_string();
+
+        (status_code, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+pub(crate) fn command_router(state: AgentState) -> Router {
+    Router::new()
+        .route("/v1/commands", get(list_commands))
+        .route(
+            "/v1/commands/{*path}",
+            get(get_command).post(create_command).delete(clear_command),
+        )
+        .layer(DefaultBodyLimit::max(HTTP_MAX_PAYLOAD_SIZE))
+        .with_state(state)
+}
+
+async fn list_commands(
+    State(state): State<AgentState>,
+    Query(params): Query<ListParams>,
+) -> Result<Response, Error> {
+    let filter = CommandFilter::try_from(params)?;
+    let response = state
+        .command_handle
+        .clone()
+        .await_response(CommandRequest::List(filter))
+        .await?;
+    let CommandResponse::List(commands) = response else {
+        return Err(Error::InvalidCommandServerResponse);
+    };
+
+    let commands: Vec<Value> = commands
+        .into_iter()
+        .map(|(target, command)| {
+            json!({
+                "@topic-id": target.entity.as_str(),
+                "operation": target.operation.to_string(),
+                "cmdId": target.cmd_id,
+                "payload": command.payload,
+            })
+        })
+        .collect();
+    Ok((StatusCode::OK, Json(commands)).into_response())
+}
+
+async fn create_command(
+    State(state): State<AgentState>,
+    Path(path): Path<String>,
+    payload: String,
+) -> Result<Response, Error> {
+    let target = parse_path(&path)?;
+    let payload: Value = serde_json::from_str(&payload)?;
+    let response = state
+        .command_handle
+        .clone()
+        .await_response(CommandRequest::Create(target, payload))
+        .await?;
+    let CommandResponse::Create(res) = response else {
+        return Err(Error::InvalidCommandServerResponse);
+    };
+
+    let command = res?;
+    Ok((StatusCode::CREATED, Json(command.payload)).into_response())
+}
+
+async fn get_command(
+    State(state): State<AgentState>,
+    Path(path): Path<String>,
+    Query(params): Query<WatchParams>,
+) -> Result<Response, Error> {
+    let target = parse_path(&path)?;
+    let command = match params.status {
+        None => fetch_command(state, target.clone()).await?,
+        Some(status) => {
+            let timeout = params
+                .timeout
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_WAIT_TIMEOUT)
+                .min(MAX_WAIT_TIMEOUT);
+            wait_for_status_change(state, target.clone(), status, timeout).await?
+        }
+    };
+
+    match command {
+        Some(command) => Ok((StatusCode::OK, Json(command.payload)).into_response()),
+        None => Err(Error::CommandNotFound(target_name(&target))),
+    }
+}
+
+async fn clear_command(
+    State(state): State<AgentState>,
+    Path(path): Path<String>,
+) -> Result<Response, Error> {
+    let target = parse_path(&path)?;
+    let response = state
+        .command_handle
+        .clone()
+        .await_response(CommandRequest::Clear(target.clone()))
+        .await?;
+    let CommandResponse::Clear(cleared) = response else {
+        return Err(Error::InvalidCommandServerResponse);
+    };
+
+    match cleared? {
+        Some(command) => Ok((StatusCode::OK, Json(command.payload)).into_response()),
+        None => Err(Error::CommandNotFound(target_name(&target))),
+    }
+}
+
+async fn fetch_command(
+    state: AgentState,
+    target: CommandTarget,
+) -> Result<Option<GenericCommandState>, Error> {
+    let response = state
+        .command_handle
+        .clone()
+        .await_response(CommandRequest::Get(target))
+        .await?;
+    let CommandResponse::Get(command) = response else {
+        return Err(Error::InvalidCommandServerResponse);
+    };
+    Ok(command)
+}
+
+/// Wait till the command moves to a status different from the given one
+///
+/// Return the latest known state of the command, when moved to a new status or on timeout.
+async fn wait_for_status_change(
+    state: AgentState,
+    target: CommandTarget,
+    status: String,
+    timeout: Duration,
+) -> Result<Option<GenericCommandState>, Error> {
+    let response = state
+        .command_handle
+        .clone()
+        .await_response(CommandRequest::Watch(target))
+        .await?;
+    let CommandResponse::Watch(watcher) = response else {
+        return Err(Error::InvalidCommandServerResponse);
+    };
+    let Some(mut watcher) = watcher else {
+        return Ok(None);
+    };
+
+    let status_changed = watcher.wait_for(|command| match command {
+        None => false,
+        Some(command) => command.status != status,
+    });
+    match tokio::time::timeout(timeout, status_changed).await {
+        Ok(Ok(command)) => Ok(command.clone()),
+        Ok(Err(_)) | Err(_) => Ok(watcher.borrow().clone()),
+    }
+}
+
+/// Parses a command HTTP resource path into the command's entity topic id, operation and id
+fn parse_path(path: &str) -> Result<CommandTarget, Error> {
+    let segments = path.split('/').collect::<Vec<&str>>();
+    match segments.as_slice() {
+        [seg1, seg2, seg3, seg4, operation, cmd_id]
+            if !operation.is_empty() && !cmd_id.is_empty() =>
+        {
+            let entity = topic_id_from_path_segments(seg1, Some(seg2), Some(seg3), Some(seg4))?;
+            Ok(CommandTarget {
+                entity,
+                operation: (*operation).into(),
+                cmd_id: cmd_id.to_string(),
+            })
+        }
+        _ => Err(Error::ResourceNotFound),
+    }
+}
+
+fn target_name(target: &CommandTarget) -> String {
+    format!("{}/{}/{}", target.entity, target.operation, target.cmd_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AgentState;
+    use crate::command_manager::server::CommandError;
+    use crate::command_manager::server::CommandFilter;
+    use crate::command_manager::server::CommandRequest;
+    use crate::command_manager::server::CommandResponse;
+    use crate::command_manager::server::CommandTarget;
+    use crate::entity_manager::server::EntityStoreRequest;
+    use crate::entity_manager::server::EntityStoreResponse;
+    use crate::http_server::commands::command_router;
+    use axum::body::Body;
+    use axum::response::Response;
+    use axum::Router;
+    use http_body_util::BodyExt as _;
+    use hyper::Method;
+    use hyper::Request;
+    use hyper::StatusCode;
+    use serde_json::json;
+    use serde_json::Value;
+    use tedge_actors::Builder;
+    use tedge_actors::ClientMessageBox;
+    use tedge_actors::MessageReceiver;
+    use tedge_actors::ServerMessageBox;
+    use tedge_actors::ServerMessageBoxBuilder;
+    use tedge_api::mqtt_topics::EntityTopicId;
+    use tedge_api::mqtt_topics::MqttSchema;
+    use tedge_api::mqtt_topics::OperationType;
+    use tedge_api::path::DataDir;
+    use tedge_api::workflow::GenericCommandState;
+    use tedge_test_utils::fs::TempTedgeDir;
+    use tedge_utils::paths::TedgePaths;
+    use tower::Service;
+
+    #[tokio::test]
+    async fn command_create() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::Create(target, payload) = req.request {
+                    assert_eq!(target, restart_target("device/child01//", "123"));
+                    let state = command_state(&target, "init", payload);
+                    req.reply_to
+                        .send(CommandResponse::Create(Ok(state)))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/v1/commands/device/child01///restart/123")
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"foo":"bar"}"#))
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response_json(response).await,
+            json!({"status": "init", "foo": "bar"})
+        );
+    }
+
+    #[tokio::test]
+    async fn command_create_for_unsupported_operation() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::Create(target, _) = req.request {
+                    req.reply_to
+                        .send(CommandResponse::Create(Err(
+                            CommandError::UnsupportedOperation {
+                                entity: target.entity,
+                                operation: target.operation,
+                            },
+                        )))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/v1/commands/device/main///restart/123")
+            .body(Body::from("{}"))
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn command_create_with_invalid_json() {
+        let TestHandle { mut app, .. } = setup();
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/v1/commands/device/main///restart/123")
+            .body(Body::from("not json"))
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn command_get() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::Get(target) = req.request {
+                    let state = command_state(&target, "executing", json!({}));
+                    req.reply_to
+                        .send(CommandResponse::Get(Some(state)))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/commands/device/main///restart/123")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response_json(response).await,
+            json!({"status": "executing"})
+        );
+    }
+
+    #[tokio::test]
+    async fn command_get_unknown() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::Get(_) = req.request {
+                    req.reply_to.send(CommandResponse::Get(None)).await.unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/commands/device/main///restart/123")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn command_get_waits_for_status_change() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response, moving the command from executing to successful
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::Watch(target) = req.request {
+                    let executing = command_state(&target, "executing", json!({}));
+                    let (sender, watcher) = tokio::sync::watch::channel(Some(executing));
+                    req.reply_to
+                        .send(CommandResponse::Watch(Some(watcher)))
+                        .await
+                        .unwrap();
+
+                    let successful = command_state(&target, "successful", json!({}));
+                    sender.send_replace(Some(successful));
+                    // Keep the sender alive till the client gets the update
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/commands/device/main///restart/123?status=executing&timeout=10")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response_json(response).await,
+            json!({"status": "successful"})
+        );
+    }
+
+    #[tokio::test]
+    async fn command_get_waits_for_unknown_command() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::Watch(_) = req.request {
+                    req.reply_to
+                        .send(CommandResponse::Watch(None))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/commands/device/main///restart/123?status=executing&timeout=60")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = tokio::time::timeout(std::time::Duration::from_secs(5), app.call(req))
+            .await
+            .expect("no wait for an unknown command")
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn command_list() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::List(filter) = req.request {
+                    assert_eq!(
+                        filter,
+                        CommandFilter {
+                            entity: Some("device/child01//".parse().unwrap()),
+                            operation: Some(OperationType::Restart),
+                        }
+                    );
+                    let target = restart_target("device/child01//", "123");
+                    let state = command_state(&target, "executing", json!({}));
+                    req.reply_to
+                        .send(CommandResponse::List(vec![(target, state)]))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/commands?entity=device/child01//&operation=restart")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response_json(response).await,
+            json!([{
+                "@topic-id": "device/child01//",
+                "operation": "restart",
+                "cmdId": "123",
+                "payload": {"status": "executing"}
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn command_clear() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::Clear(target) = req.request {
+                    let state = command_state(&target, "successful", json!({}));
+                    req.reply_to
+                        .send(CommandResponse::Clear(Ok(Some(state))))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::DELETE)
+            .uri("/v1/commands/device/main///restart/123")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn command_clear_failing_to_publish() {
+        let TestHandle {
+            mut app,
+            mut command_box,
+        } = setup();
+
+        // Mock command server response
+        tokio::spawn(async move {
+            if let Some(mut req) = command_box.recv().await {
+                if let CommandRequest::Clear(_) = req.request {
+                    let error = tedge_actors::ChannelError::ReceiveError();
+                    req.reply_to
+                        .send(CommandResponse::Clear(Err(error.into())))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::DELETE)
+            .uri("/v1/commands/device/main///restart/123")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn command_path_without_command_id() {
+        let TestHandle { mut app, .. } = setup();
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/commands/device/main///restart")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    fn restart_target(entity: &str, cmd_id: &str) -> CommandTarget {
+        CommandTarget {
+            entity: entity.parse::<EntityTopicId>().unwrap(),
+            operation: OperationType::Restart,
+            cmd_id: cmd_id.to_string(),
+        }
+    }
+
+    fn command_state(target: &CommandTarget, status: &str, payload: Value) -> GenericCommandState {
+        let topic = MqttSchema::default().topic_for(
+            &target.entity,
+            &tedge_api::mqtt_topics::Channel::Command {
+                operation: target.operation.clone(),
+                cmd_id: target.cmd_id.clone(),
+            },
+        );
+        GenericCommandState::new(topic, status.to_string(), payload)
+    }
+
+    async fn response_json(response: Response) -> Value {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    struct TestHandle {
+        app: Router,
+        command_box: ServerMessageBox<CommandRequest, CommandResponse>,
+    }
+
+    fn setup() -> TestHandle {
+        let ttd: TempTedgeDir = TempTedgeDir::new();
+        let data_dir: DataDir =
+            TedgePaths::from_root_with_defaults(ttd.utf8_path_buf(), "", "").into();
+        let file_transfer_dir = data_dir.file_transfer_dir();
+
+        let mut entity_store_box: ServerMessageBoxBuilder<EntityStoreRequest, EntityStoreResponse> =
+            ServerMessageBoxBuilder::new("EntityStoreBox", 16);
+        let entity_store_handle = ClientMessageBox::new(&mut entity_store_box);
+        let mut command_box = ServerMessageBoxBuilder::new("CommandBox", 16);
+        let command_handle = ClientMessageBox::new(&mut command_box);
+
+        let agent_state = AgentState {
+            file_transfer_dir,
+            data_dir,
+            entity_store_handle,
+            command_handle,
+        };
+        let app: Router = command_router(agent_state);
+
+        TestHandle {
+            app,
+            command_box: command_box.build(),
+        }
+    }
+}
@@ -375,7 +375,7 @@ fn parse_path(path: &str) -> Result<(EntityTopicId, ResourceChannel), Error> {
     }
 }
 
-fn topic_id_from_path_segments(
+pub(super) fn topic_id_from_path_segments(
     seg1: &str,
     seg2: Option<&str>,
     seg3: Option<&str>,
@@ -2025,11 +2025,14 @@ mod tests {
 
         let mut entity_store_box = ServerMessageBoxBuilder::new("EntityStoreBox", 16);
         let entity_store_handle = ClientMessageBox::new(&mut entity_store_box);
+        let mut command_box = ServerMessageBoxBuilder::new("CommandBox", 16);
+        let command_handle = ClientMessageBox::new(&mut command_box);
 
         let agent_state = AgentState {
             file_transfer_dir,
             data_dir,
             entity_store_handle,
+            command_handle,
         };
         // TODO: Add a timeout to this router. Attempts to add a tower_http::timer::TimeoutLayer as a layer failed.
         let app: Router = entity_store_router(agent_state);
@@ -1,4 +1,5 @@
 pub mod actor;
+mod commands;
 mod entity_store;
 pub mod error;
 mod file_transfer;
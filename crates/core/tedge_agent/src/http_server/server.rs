@@ -1,6 +1,9 @@
+use super::commands::command_router;
 use super::entity_store::entity_store_router;
 use super::file_transfer::file_transfer_legacy_router;
 use super::file_transfer::file_transfer_router;
+use crate::command_manager::server::CommandRequest;
+use crate::command_manager::server::CommandResponse;
 use crate::entity_manager::server::EntityStoreRequest;
 use crate::entity_manager::server::EntityStoreResponse;
 use crate::http_server::error::HttpServerError;
@@ -19,6 +22,7 @@ pub(crate) struct AgentState {
     pub(crate) file_transfer_dir: ManagedDir,
     pub(crate) data_dir: DataDir,
     pub(crate) entity_store_handle: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+    pub(crate) command_handle: ClientMessageBox<CommandRequest, CommandResponse>,
 }
 
 impl AgentState {
@@ -26,11 +30,13 @@ impl AgentState {
         file_transfer_dir: ManagedDir,
         data_dir: DataDir,
         entity_store_handle: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+        command_handle: ClientMessageBox<CommandRequest, CommandResponse>,
     ) -> Self {
         AgentState {
             file_transfer_dir,
             data_dir,
             entity_store_handle,
+            command_handle,
         }
     }
 }
@@ -60,9 +66,15 @@ fn router(state: AgentState) -> Router {
         file_transfer_legacy_router(state.file_transfer_dir.clone(), state.data_dir.clone());
     let file_transfer_router =
         file_transfer_router(state.file_transfer_dir.clone(), state.data_dir.clone());
+    let command_router = command_router(state.clone());
     let entity_store_router = entity_store_router(state);
 
     Router::new()
-        .nest("/te", entity_store_router.merge(file_transfer_router))
+        .nest(
+            "/te",
+            entity_store_router
+                .merge(command_router)
+                .merge(file_transfer_router),
+        )
         .merge(file_transfer_legacy_router)
 }
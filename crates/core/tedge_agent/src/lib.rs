@@ -10,6 +10,7 @@
 //! - File transfer HTTP server
 //! - Restart management
 //! - Software management
+//! - Device certificate renewal
 
 use std::sync::Arc;
 
@@ -27,6 +28,8 @@ use tedge_supervisor::UnitKind;
 use tracing::info;
 
 mod agent;
+mod certificate_renewal;
+mod command_manager;
 mod device_profile_manager;
 mod entity_manager;
 mod http_server;
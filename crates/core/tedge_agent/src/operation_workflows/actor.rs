@@ -1,3 +1,6 @@
+use crate::operation_workflows::javascript::JavaScriptOutcome;
+use crate::operation_workflows::javascript::JavaScriptRequest;
+use crate::operation_workflows::javascript::JavaScriptRunner;
 use crate::operation_workflows::message_box::CommandDispatcher;
 use crate::operation_workflows::message_box::SyncSignalDispatcher;
 use crate::operation_workflows::persist::WorkflowRepository;
@@ -7,6 +10,7 @@ use async_trait::async_trait;
 use camino::Utf8PathBuf;
 use serde_json::json;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::process::Output;
 use std::time::Duration;
 use tedge_actors::fan_in_message_type;
@@ -37,6 +41,8 @@ use tedge_api::workflow::OperationName;
 use tedge_api::workflow::OperationStep;
 use tedge_api::workflow::OperationStepRequest;
 use tedge_api::workflow::OperationStepResponse;
+use tedge_api::workflow::RetryDecision;
+use tedge_api::workflow::RetryPolicy;
 use tedge_api::workflow::WorkflowExecutionError;
 use tedge_api::CommandLog;
 use tedge_downloader_ext::DownloadRequest;
@@ -48,6 +54,8 @@ use tedge_script_ext::Execute;
 use tedge_uploader_ext::UploadRequest;
 use tedge_uploader_ext::UploadResult;
 use tokio::time::sleep;
+use tokio::time::sleep_until;
+use tokio::time::Instant;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
@@ -62,7 +70,19 @@ type UploaderResult = (String, UploadResult);
 #[derive(Debug)]
 pub struct InternalCommandState(GenericCommandState);
 
-fan_in_message_type!(AgentInput[MqttMessage, InternalCommandState, GenericCommandData, FsWatchEvent] : Debug);
+/// Sent by the [WorkflowActor] to itself when an await step reaches its timeout
+#[derive(Debug)]
+pub struct StepTimeout {
+    topic: String,
+    step: String,
+    deadline: Instant,
+}
+
+/// Sent by the [WorkflowActor] to itself when the backoff delay of a retried step expires
+#[derive(Debug)]
+pub struct StepRetry(GenericCommandState);
+
+fan_in_message_type!(AgentInput[MqttMessage, InternalCommandState, GenericCommandData, FsWatchEvent, JavaScriptOutcome, StepTimeout, StepRetry] : Debug);
 
 pub struct WorkflowActor {
     pub(crate) mqtt_schema: MqttSchema,
@@ -79,11 +99,22 @@ pub struct WorkflowActor {
     >,
     pub(crate) sync_signal_dispatcher: SyncSignalDispatcher,
     pub(crate) command_sender: DynSender<InternalCommandState>,
+    pub(crate) timeout_sender: DynSender<StepTimeout>,
+    pub(crate) retry_sender: DynSender<StepRetry>,
     pub(crate) mqtt_publisher: LoggingSender<MqttMessage>,
     pub(crate) script_runner: ClientMessageBox<Execute, std::io::Result<Output>>,
     pub(crate) downloader: ClientMessageBox<DownloaderRequest, DownloaderResult>,
     pub(crate) uploader: ClientMessageBox<UploaderRequest, UploaderResult>,
+    pub(crate) javascript_runner: JavaScriptRunner,
     pub(crate) tmp_dir: Utf8PathBuf,
+    /// The command requests awaiting a response, indexed by command topic
+    ///
+    /// Only the requests received along an MQTT 5 response topic are recorded.
+    pub(crate) command_requests: HashMap<String, MqttMessage>,
+    /// The deadlines of the await steps, indexed by command topic
+    pub(crate) step_deadlines: HashMap<String, (String, Instant)>,
+    /// The commands which init step is retried, and which init state is expected back over MQTT
+    pub(crate) retried_init_commands: HashSet<String>,
 }
 
 #[async_trait]
@@ -113,6 +144,15 @@ impl Actor for WorkflowActor {
                 )) => {
                     self.publish_builtin_capability(operation, payload).await?;
                 }
+                AgentInput::JavaScriptOutcome(outcome) => {
+                    self.process_javascript_outcome(outcome).await?;
+                }
+                AgentInput::StepTimeout(timeout) => {
+                    self.process_step_timeout(timeout).await?;
+                }
+                AgentInput::StepRetry(StepRetry(retry_state)) => {
+                    self.process_step_retry(retry_state).await?;
+                }
                 AgentInput::FsWatchEvent(file_update) => {
                     if let Some(updated_capability) = self
                         .workflow_repository
@@ -240,6 +280,14 @@ impl WorkflowActor {
             return Ok(());
         };
         let step = state.status.clone();
+        if state.is_init() && self.retried_init_commands.remove(&message.topic.name) {
+            // This is the init state published by the agent itself to retry the command
+            return Ok(());
+        }
+        if state.is_init() && message.properties.response_topic.is_some() {
+            self.command_requests
+                .insert(message.topic.name.clone(), message);
+        }
 
         let mut log_file = self.open_command_log(&state, &operation, &cmd_id).await;
 
@@ -430,6 +478,18 @@ impl WorkflowActor {
                 log_file.log_script_output(output.as_ref()).await;
                 Ok(())
             }
+            OperationAction::JavaScript(module, handlers) => {
+                let step = &state.status;
+                info!("Processing {operation} operation {step} step with JavaScript: {module}");
+
+                // The outcome is processed by `process_javascript_outcome`
+                self.javascript_runner.run(JavaScriptRequest {
+                    state,
+                    module,
+                    handlers,
+                });
+                Ok(())
+            }
             OperationAction::Download(input_excerpt, handlers) => {
                 let step = &state.status;
                 info!("Processing {operation} operation {step} step with download builtin action");
@@ -462,7 +522,16 @@ impl WorkflowActor {
                 let temp_filename = format!("{operation}_{cmd_id}");
                 let temp_path = self.tmp_dir.join(&temp_filename);
 
-                let download_request = DownloadRequest::new(url, temp_path.as_std_path());
+                // The expected digests and signature, if any, are taken from the input or the command
+                let expected = |property: &str| {
+                    GenericCommandState::extract_text_property(&input, property)
+                        .or_else(|| state.get_text_property(property))
+                        .map(str::to_string)
+                };
+                let download_request = DownloadRequest::new(url, temp_path.as_std_path())
+                    .with_sha256(expected("sha256"))
+                    .with_sha512(expected("sha512"))
+                    .with_signature(expected("signature"));
                 let (_topic, download_result) = self
                     .downloader
                     .await_response((state.topic.name.clone(), download_request))
@@ -687,15 +756,18 @@ impl WorkflowActor {
                             .await?;
                     } else {
                         // Nothing specific has to be done: the current state has been persisted
-                        // and will be resumed on completion of the sub-operation
-                        // TODO: Register a timeout event
+                        // and will be resumed on completion of the sub-operation or on timeout
+                        self.schedule_step_timeout(&state, handlers.timeout);
                         log_file
                             .log_info(&format!(
                                 "=> {sub_operation} sub-operation is still running"
                             ))
                             .await;
                     }
-                };
+                } else {
+                    // The sub-operation state is not known yet
+                    self.schedule_step_timeout(&state, handlers.timeout);
+                }
 
                 Ok(())
             }
@@ -717,6 +789,68 @@ impl WorkflowActor {
                 }
                 Ok(())
             }
+            OperationAction::FanOut(fan_out, handlers) => {
+                let step = &state.status;
+                info!("{operation} operation {step}: fanning out sub-operations");
+
+                let finished_sub_states: Vec<GenericCommandState> = self
+                    .workflow_repository
+                    .sub_command_states(&state)
+                    .into_iter()
+                    .filter(|sub_state| sub_state.is_finished())
+                    .cloned()
+                    .collect();
+                let progress = match fan_out.process(&state, &finished_sub_states, &handlers) {
+                    Ok(progress) => progress,
+                    Err(err) => {
+                        error!("Fan-out failed due to: {err}");
+                        let new_state = state.update(handlers.on_error);
+                        return self.publish_command_state(new_state, &mut log_file).await;
+                    }
+                };
+
+                // Persist the progress before launching any new sub-operation
+                let finished = progress
+                    .new_state
+                    .as_ref()
+                    .is_some_and(|new_state| new_state.status != state.status);
+                if !finished {
+                    self.schedule_step_timeout(&state, handlers.timeout);
+                }
+                if let Some(new_state) = progress.new_state {
+                    self.publish_command_state(new_state, &mut log_file).await?;
+                }
+
+                for sub_state in finished_sub_states {
+                    let sub_operation = sub_state.operation().unwrap_or_default();
+                    log_file
+                        .log_info(&format!(
+                            "=> {sub_operation} sub-operation {} is {}",
+                            sub_state.topic.as_ref(),
+                            sub_state.status
+                        ))
+                        .await;
+                    self.publish_command_state(sub_state.clear(), &mut log_file)
+                        .await?;
+                }
+
+                for (index, sub_operation, sub_cmd_input) in progress.launch {
+                    let sub_cmd_init_state = GenericCommandState::fan_out_sub_command_init_state(
+                        &self.mqtt_schema,
+                        &self.device_topic_id,
+                        operation.clone(),
+                        cmd_id.clone(),
+                        sub_operation,
+                        index,
+                    )
+                    .update_with_json(sub_cmd_input)
+                    .update_with_json(GenericStateUpdate::init_payload());
+                    self.mqtt_publisher
+                        .send(sub_cmd_init_state.into_message())
+                        .await?;
+                }
+                Ok(())
+            }
         }
     }
 
@@ -752,9 +886,7 @@ impl WorkflowActor {
             error!("Fail to persist workflow operation state: {err}");
         }
         self.persist_command_board().await?;
-        self.mqtt_publisher
-            .send(adapted_state.clone().into_message())
-            .await?;
+        self.publish_command_message(adapted_state.clone()).await?;
         self.process_command_update(adapted_state).await
     }
 
@@ -808,6 +940,12 @@ impl WorkflowActor {
         new_state: GenericCommandState,
         log_file: &mut CommandLog,
     ) -> Result<(), RuntimeError> {
+        let new_state = match self.apply_retry_policy(new_state) {
+            RetryDecision::GiveUp(new_state) => new_state,
+            RetryDecision::Retry(retry_state, delay) => {
+                return self.schedule_retry(retry_state, delay, log_file).await
+            }
+        };
         if let Err(err) = self
             .workflow_repository
             .apply_internal_update(new_state.clone())
@@ -821,10 +959,231 @@ impl WorkflowActor {
                 .send(InternalCommandState(new_state.clone()))
                 .await?;
         }
-        self.mqtt_publisher.send(new_state.into_message()).await?;
+        self.publish_command_message(new_state).await
+    }
+
+    /// Publish a command state, responding to the requester when the command is finished
+    ///
+    /// A response is only sent if the command request was received along a response topic,
+    /// i.e. if the requester uses MQTT 5 request/response correlation.
+    /// The response carries the final state of the command and the correlation data of the request.
+    async fn publish_command_message(
+        &mut self,
+        state: GenericCommandState,
+    ) -> Result<(), RuntimeError> {
+        let is_finished = state.is_finished();
+        let message = state.into_message();
+        if is_finished || message.payload_bytes().is_empty() {
+            let response = self
+                .command_requests
+                .remove(&message.topic.name)
+                .filter(|_| is_finished)
+                .and_then(|request| request.response(message.payload_bytes().to_vec()));
+            if let Some(response) = response {
+                self.mqtt_publisher.send(response).await?;
+            }
+        }
+        self.mqtt_publisher.send(message).await?;
         Ok(())
     }
 
+    /// Check if a command moving to a new state has to be retried
+    ///
+    /// This is the case when the action of the current state has failed,
+    /// and a retry policy with remaining attempts is attached to that state.
+    /// Otherwise, the new state is returned unchanged
+    /// (except when giving up or when the number of failed attempts has to be cleared).
+    fn apply_retry_policy(&self, new_state: GenericCommandState) -> RetryDecision {
+        if new_state.is_cleared() {
+            return RetryDecision::GiveUp(new_state);
+        }
+        let Some(current_state) = self
+            .workflow_repository
+            .get_state(new_state.topic.as_ref())
+            .filter(|current_state| current_state.status != new_state.status)
+        else {
+            return RetryDecision::GiveUp(new_state);
+        };
+        let Some(policy) = self.workflow_repository.get_retry_policy(current_state) else {
+            return RetryDecision::GiveUp(new_state);
+        };
+        match self.workflow_repository.get_action(current_state) {
+            Ok(action) if action.is_failure(&new_state) => {
+                policy.on_failure(current_state, new_state)
+            }
+            _ => RetryDecision::GiveUp(RetryPolicy::reset(&current_state.status, new_state)),
+        }
+    }
+
+    /// Persist a command state to be retried, and resume it after the given delay
+    ///
+    /// The retry state is published only when the delay expires, along its processing,
+    /// and provided the command has not been cleared or moved to another state meanwhile.
+    /// The number of failed attempts being persisted with the command state,
+    /// the retries are resumed when the agent restarts (without waiting for the delay).
+    async fn schedule_retry(
+        &mut self,
+        retry_state: GenericCommandState,
+        delay: Duration,
+        log_file: &mut CommandLog,
+    ) -> Result<(), RuntimeError> {
+        let step = &retry_state.status;
+        log_file
+            .log_info(&format!("{step} step failed: retrying in {delay:?}"))
+            .await;
+        if let Err(err) = self
+            .workflow_repository
+            .apply_internal_update(retry_state.clone())
+        {
+            error!("Fail to persist workflow operation state: {err}");
+        }
+        self.persist_command_board().await?;
+        if retry_state.is_init() {
+            // Not to be processed twice, when received back as a new request
+            self.retried_init_commands
+                .insert(retry_state.topic.name.clone());
+        }
+
+        let mut retry_sender = self.retry_sender.sender_clone();
+        tokio::spawn(async move {
+            sleep(delay).await;
+            let _ = retry_sender.send(StepRetry(retry_state)).await;
+        });
+        Ok(())
+    }
+
+    /// Resume a retried step once its backoff delay has expired, if still pending
+    async fn process_step_retry(
+        &mut self,
+        retry_state: GenericCommandState,
+    ) -> Result<(), RuntimeError> {
+        let topic = &retry_state.topic.name;
+        if self.workflow_repository.get_state(topic) != Some(&retry_state) {
+            info!(
+                "Cancelling the retry of {topic} {} step: the command has been updated meanwhile",
+                retry_state.status
+            );
+            self.retried_init_commands.remove(topic);
+            return Ok(());
+        }
+        self.mqtt_publisher
+            .send(retry_state.clone().into_message())
+            .await?;
+        self.command_sender
+            .send(InternalCommandState(retry_state))
+            .await?;
+        Ok(())
+    }
+
+    /// Schedule the timeout of an await step, unless already scheduled for this step
+    ///
+    /// The timer is not persisted: on restart, an await step is given its full timeout.
+    fn schedule_step_timeout(&mut self, state: &GenericCommandState, timeout: Option<Duration>) {
+        let Some(timeout) = timeout else {
+            return;
+        };
+        let topic = state.topic.name.clone();
+        if let Some((step, _)) = self.step_deadlines.get(&topic) {
+            if step == &state.status {
+                return;
+            }
+        }
+
+        let step = state.status.clone();
+        let deadline = Instant::now() + timeout;
+        self.step_deadlines
+            .insert(topic.clone(), (step.clone(), deadline));
+        let mut timeout_sender = self.timeout_sender.sender_clone();
+        tokio::spawn(async move {
+            sleep_until(deadline).await;
+            let _ = timeout_sender
+                .send(StepTimeout {
+                    topic,
+                    step,
+                    deadline,
+                })
+                .await;
+        });
+    }
+
+    /// Move a command to its `on_timeout` state, if still awaiting at the step that timed out
+    async fn process_step_timeout(&mut self, timeout: StepTimeout) -> Result<(), RuntimeError> {
+        let StepTimeout {
+            topic,
+            step,
+            deadline,
+        } = timeout;
+        match self.step_deadlines.get(&topic) {
+            Some((pending_step, pending_deadline))
+                if pending_step == &step && pending_deadline == &deadline =>
+            {
+                self.step_deadlines.remove(&topic);
+            }
+            _ => return Ok(()),
+        }
+        let Some(state) = self.workflow_repository.get_state(&topic).cloned() else {
+            return Ok(());
+        };
+        if state.status != step {
+            return Ok(());
+        }
+        let on_timeout = match self.workflow_repository.get_action(&state) {
+            Ok(OperationAction::AwaitOperationCompletion(handlers, _))
+            | Ok(OperationAction::FanOut(_, handlers)) => handlers.on_timeout,
+            _ => return Ok(()),
+        };
+        let Ok((operation, cmd_id)) = self.extract_command_identifiers(&topic) else {
+            return Ok(());
+        };
+
+        info!("{operation} operation {step} step timed out");
+        let mut log_file = self.open_command_log(&state, &operation, &cmd_id).await;
+        log_file
+            .log_info(&format!("=> {step} step timed out"))
+            .await;
+        let new_state = state.update(on_timeout);
+        self.publish_command_state(new_state, &mut log_file).await
+    }
+
+    /// Move a command to its next state once a JavaScript step has been executed
+    ///
+    /// The outcome is ignored if the command is no longer at the step that ran the script,
+    /// e.g. if the command has been cleared while the script was running.
+    async fn process_javascript_outcome(
+        &mut self,
+        outcome: JavaScriptOutcome,
+    ) -> Result<(), RuntimeError> {
+        let JavaScriptOutcome {
+            state,
+            module,
+            handlers,
+            result,
+        } = outcome;
+        match self.workflow_repository.get_state(&state.topic.name) {
+            Some(current_state) if current_state.status == state.status => (),
+            _ => {
+                info!(
+                    "Ignoring the outcome of JavaScript {module}: {} is no longer at the {} step",
+                    state.topic.name, state.status
+                );
+                return Ok(());
+            }
+        }
+        let Ok((operation, cmd_id)) = self.extract_command_identifiers(&state.topic.name) else {
+            error!("Unknown command channel: {}", state.topic.name);
+            return Ok(());
+        };
+
+        let mut log_file = self.open_command_log(&state, &operation, &cmd_id).await;
+        if let Err(error) = result.as_ref() {
+            log_file
+                .log_error(&format!("JavaScript {module} failed: {error}"))
+                .await;
+        }
+        let new_state = state.update_with_javascript_result(&module, result, handlers);
+        self.publish_command_state(new_state, &mut log_file).await
+    }
+
     /// Reload from disk the current state of the pending command requests
     async fn load_command_board(&mut self) -> Result<(), RuntimeError> {
         match self.state_repository.load().await {
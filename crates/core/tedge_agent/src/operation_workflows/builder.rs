@@ -2,6 +2,7 @@ use crate::operation_workflows::actor::AgentInput;
 use crate::operation_workflows::actor::InternalCommandState;
 use crate::operation_workflows::actor::WorkflowActor;
 use crate::operation_workflows::config::OperationConfig;
+use crate::operation_workflows::javascript::JavaScriptRunner;
 use crate::operation_workflows::message_box::CommandDispatcher;
 use crate::operation_workflows::message_box::SyncSignalDispatcher;
 use crate::operation_workflows::persist::WorkflowRepository;
@@ -9,6 +10,7 @@ use crate::state_repository::state::agent_state_dir;
 use crate::state_repository::state::AgentStateRepository;
 use camino::Utf8PathBuf;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::process::Output;
 use tedge_actors::futures::channel::mpsc;
@@ -227,10 +229,16 @@ impl Builder<WorkflowActor> for WorkflowActorBuilder {
             sync_signal_dispatcher: self.sync_signal_dispatcher,
             mqtt_publisher: self.mqtt_publisher,
             command_sender: self.command_sender,
+            timeout_sender: self.input_sender.sender_clone(),
+            retry_sender: self.input_sender.sender_clone(),
             script_runner: self.script_runner,
             downloader: self.downloader,
             uploader: self.uploader,
+            javascript_runner: JavaScriptRunner::new(self.input_sender.sender_clone()),
             tmp_dir: self.config.tmp_dir.root().into(),
+            command_requests: HashMap::new(),
+            step_deadlines: HashMap::new(),
+            retried_init_commands: HashSet::new(),
         }
     }
 }
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::SystemTime;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::Sender;
+use tedge_api::workflow::ExitHandlers;
+use tedge_api::workflow::GenericCommandState;
+use tedge_flows::JsRuntime;
+use tedge_flows::JsRuntimeConfig;
+use tedge_flows::LoadError;
+use tokio::sync::mpsc;
+use tracing::info;
+
+/// Time given to a JavaScript step when no `timeout_second` is set by the workflow
+const DEFAULT_JAVASCRIPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A JavaScript step to be executed on behalf of the [WorkflowActor](super::actor::WorkflowActor)
+#[derive(Debug)]
+pub struct JavaScriptRequest {
+    pub state: GenericCommandState,
+    pub module: String,
+    pub handlers: ExitHandlers,
+}
+
+/// The outcome of a JavaScript step, sent back to the workflow actor
+#[derive(Debug)]
+pub struct JavaScriptOutcome {
+    pub state: GenericCommandState,
+    pub module: String,
+    pub handlers: ExitHandlers,
+    pub result: Result<serde_json::Value, String>,
+}
+
+/// Run the JavaScript steps of the workflows
+///
+/// All the steps share a single JS runtime, owned by a background task,
+/// so the workflow actor is never blocked by a script.
+/// The modules are compiled once, and compiled again only when their source file is updated.
+///
+/// A step is interrupted after its `timeout_second` (5 seconds by default).
+/// The runtime is then discarded and a fresh one is used for the next steps.
+pub struct JavaScriptRunner {
+    requests: Option<mpsc::UnboundedSender<JavaScriptRequest>>,
+    outcomes: DynSender<JavaScriptOutcome>,
+}
+
+impl JavaScriptRunner {
+    pub fn new(outcomes: DynSender<JavaScriptOutcome>) -> Self {
+        JavaScriptRunner {
+            requests: None,
+            outcomes,
+        }
+    }
+
+    /// Schedule the execution of a JavaScript step
+    ///
+    /// The outcome is sent asynchronously to the workflow actor.
+    pub fn run(&mut self, request: JavaScriptRequest) {
+        let request = match self.requests.as_ref() {
+            Some(requests) => match requests.send(request) {
+                Ok(()) => return,
+                Err(mpsc::error::SendError(request)) => request,
+            },
+            None => request,
+        };
+
+        // The background task is started on first use (or restarted if it has died)
+        let (requests, receiver) = mpsc::unbounded_channel();
+        let _ = requests.send(request);
+        tokio::spawn(Self::serve(receiver, self.outcomes.sender_clone()));
+        self.requests = Some(requests);
+    }
+
+    async fn serve(
+        mut requests: mpsc::UnboundedReceiver<JavaScriptRequest>,
+        mut outcomes: DynSender<JavaScriptOutcome>,
+    ) {
+        let mut modules = JavaScriptModules::default();
+        while let Some(JavaScriptRequest {
+            state,
+            module,
+            handlers,
+        }) = requests.recv().await
+        {
+            let timeout = handlers
+                .graceful_timeout()
+                .unwrap_or(DEFAULT_JAVASCRIPT_TIMEOUT);
+            let result =
+                match tokio::time::timeout(timeout, modules.on_command(&module, &state)).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        modules.reset();
+                        Err(format!(
+                            "JavaScript {module} timed out after {}s",
+                            timeout.as_secs()
+                        ))
+                    }
+                };
+            let outcome = JavaScriptOutcome {
+                state,
+                module,
+                handlers,
+                result,
+            };
+            if outcomes.send(outcome).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// The JS runtime shared by the workflow steps, along with the modules loaded so far
+#[derive(Default)]
+struct JavaScriptModules {
+    runtime: Option<JsRuntime>,
+    /// The modification time and size of the module files, when loaded
+    loaded: HashMap<String, Option<(Option<SystemTime>, u64)>>,
+}
+
+impl JavaScriptModules {
+    /// Run the `onCommand` function of a JavaScript module over the payload of a command
+    async fn on_command(
+        &mut self,
+        module: &str,
+        state: &GenericCommandState,
+    ) -> Result<serde_json::Value, String> {
+        self.load(module).await.map_err(|err| err.to_string())?;
+        let runtime = self.runtime.as_ref().expect("the runtime has been created");
+        match runtime
+            .call_function(module, "onCommand", vec![state.payload.clone().into()])
+            .await
+        {
+            Ok(output) => Ok(output.into()),
+            Err(LoadError::JsException { message, .. }) => Err(message),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    /// Load a module, unless already loaded and not updated since
+    async fn load(&mut self, module: &str) -> Result<(), LoadError> {
+        let modified = tokio::fs::metadata(module)
+            .await
+            .ok()
+            .map(|metadata| (metadata.modified().ok(), metadata.len()));
+        if self.runtime.is_some() && self.loaded.get(module) == Some(&modified) {
+            return Ok(());
+        }
+
+        if self.runtime.is_none() {
+            // The step timeout is enforced by the runner, not by the runtime
+            let config = JsRuntimeConfig {
+                execution_timeout: Duration::from_secs(24 * 3600),
+                ..JsRuntimeConfig::default()
+            };
+            self.runtime = Some(JsRuntime::with_config(config).await?);
+        }
+        let runtime = self.runtime.as_mut().expect("the runtime has been created");
+        info!("Loading JavaScript module: {module}");
+        runtime.load_module(module.to_string(), module).await?;
+        self.loaded.insert(module.to_string(), modified);
+        Ok(())
+    }
+
+    /// Discard the runtime, which will be re-created on the next step
+    fn reset(&mut self) {
+        self.runtime = None;
+        self.loaded.clear();
+    }
+}
@@ -1,6 +1,7 @@
 mod actor;
 mod builder;
 mod config;
+mod javascript;
 mod message_box;
 mod persist;
 
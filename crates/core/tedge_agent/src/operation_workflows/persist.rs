@@ -13,6 +13,7 @@ use tedge_api::workflow::IllFormedOperationWorkflow;
 use tedge_api::workflow::OperationAction;
 use tedge_api::workflow::OperationName;
 use tedge_api::workflow::OperationWorkflow;
+use tedge_api::workflow::RetryPolicy;
 use tedge_api::workflow::WorkflowExecutionError;
 use tedge_api::workflow::WorkflowSupervisor;
 use tedge_api::workflow::WorkflowVersion;
@@ -446,6 +447,10 @@ impl WorkflowRepository {
         self.workflows.get_action(command_state)
     }
 
+    pub fn get_retry_policy(&self, command_state: &GenericCommandState) -> Option<&RetryPolicy> {
+        self.workflows.get_retry_policy(command_state)
+    }
+
     pub fn get_state(&self, command: &str) -> Option<&GenericCommandState> {
         self.workflows.get_state(command)
     }
@@ -471,6 +476,13 @@ impl WorkflowRepository {
         self.workflows.sub_command_state(command_state)
     }
 
+    pub fn sub_command_states(
+        &self,
+        command_state: &GenericCommandState,
+    ) -> Vec<&GenericCommandState> {
+        self.workflows.sub_command_states(command_state)
+    }
+
     pub fn adapt_builtin_response(
         &self,
         command_state: GenericCommandState,
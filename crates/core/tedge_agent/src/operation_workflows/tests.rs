@@ -8,12 +8,14 @@ use crate::software_manager::actor::SoftwareCommand;
 use crate::Capabilities;
 use camino::Utf8Path;
 use serde_json::json;
+use std::future::Future;
 use std::process::Output;
 use std::sync::Arc;
 use std::time::Duration;
 use tedge_actors::test_helpers::MessageReceiverExt;
 use tedge_actors::test_helpers::TimedMessageBox;
 use tedge_actors::Actor;
+use tedge_actors::ActorStatus;
 use tedge_actors::Builder;
 use tedge_actors::DynError;
 use tedge_actors::DynSender;
@@ -24,6 +26,7 @@ use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
 use tedge_actors::NoMessage;
 use tedge_actors::RequestEnvelope;
+use tedge_actors::Runtime;
 use tedge_actors::RuntimeError;
 use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
@@ -236,6 +239,54 @@ async fn convert_outgoing_software_list_response() -> Result<(), DynError> {
     Ok(())
 }
 
+#[tokio::test]
+async fn respond_to_command_requests_with_a_response_topic() -> Result<(), DynError> {
+    let TestHandler {
+        mut software_box,
+        mut mqtt_box,
+        ..
+    } = spawn_mqtt_operation_converter("device/main//", vec![]).await?;
+
+    software_box
+        .send(SoftwareCommand::SoftwareCommandMetadata(
+            SoftwareCommandMetadata {
+                types: vec!["apt".into(), "docker".into()],
+            },
+        ))
+        .await?;
+    skip_capability_messages(&mut mqtt_box, "device/main//").await;
+
+    // Simulate a SoftwareList request sent by an MQTT 5 client expecting a response
+    let response_topic = Topic::new_unchecked("client/responses");
+    let request = MqttMessage::new(
+        &Topic::new_unchecked("te/device/main///cmd/software_list/1234"),
+        r#"{ "status": "init" }"#,
+    )
+    .with_response_topic(&response_topic, b"request-1234".to_vec());
+    mqtt_box.send(request).await?;
+
+    let Some(SoftwareCommand::SoftwareListCommand(command)) = software_box.recv().await else {
+        panic!("Expected a software list command");
+    };
+    software_box
+        .send(command.with_status(CommandStatus::Successful).into())
+        .await?;
+
+    while let Some(message) = mqtt_box.recv().await {
+        if message.topic == response_topic {
+            assert_eq!(
+                message.properties.correlation_data,
+                Some(b"request-1234".to_vec())
+            );
+            assert!(!message.retain);
+            let payload: serde_json::Value = serde_json::from_slice(message.payload_bytes())?;
+            assert_eq!(payload["status"], "successful");
+            return Ok(());
+        }
+    }
+    panic!("No response received on {response_topic:?}");
+}
+
 #[tokio::test]
 async fn publish_capabilities_on_start() -> Result<(), DynError> {
     // Spawn outgoing mqtt message converter
@@ -411,6 +462,134 @@ action = "cleanup"
     Ok(())
 }
 
+#[tokio::test]
+async fn long_steps_do_not_degrade_the_agent_health() -> Result<(), DynError> {
+    let workflow = r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "download"
+
+[download]
+action = "download"
+input.url = "${.payload.remoteUrl}"
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let runtime = Runtime::new();
+    let health = runtime.health();
+    let TestHandler {
+        mut mqtt_box,
+        mut downloader_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter_on_runtime(
+        "device/main//",
+        vec![("config_update.toml".to_string(), workflow.to_string())],
+        runtime,
+    )
+    .await?;
+
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/config_update/123"),
+            r#"{"status":"init","remoteUrl":"http://example.com/file"}"#,
+        ))
+        .await?;
+    // The download is never completed
+    let _download_request =
+        recv_or_fail_on_actor_exit(&mut downloader_box, &mut actor_handle, "download request")
+            .await
+            .expect("download request expected");
+
+    // While the download is in progress, other requests are waiting for the workflow actor
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/config_update/456"),
+            r#"{"status":"init","remoteUrl":"http://example.com/other"}"#,
+        ))
+        .await?;
+    let stall_timeout = Duration::from_millis(100);
+    tokio::time::sleep(stall_timeout * 2).await;
+
+    let actors = health.check(stall_timeout);
+    assert!(actors
+        .iter()
+        .any(|actor| actor.name.starts_with("WorkflowActor")));
+    assert!(
+        !actors
+            .iter()
+            .any(|actor| actor.critical && actor.status == ActorStatus::Stalled),
+        "{actors:?}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn download_action_with_digest_and_signature() -> Result<(), DynError> {
+    let workflow = r#"
+operation = "firmware_update"
+
+[init]
+action = "proceed"
+on_success = "download"
+
+[download]
+action = "download"
+input.url = "${.payload.remoteUrl}"
+input.signature = "${.payload.firmwareSignature}"
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        mut mqtt_box,
+        mut downloader_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("firmware_update.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    // Trigger the operation
+    let mqtt_message = MqttMessage::new(
+        &Topic::new_unchecked("te/device/main///cmd/firmware_update/123"),
+        r#"{"status":"init","remoteUrl":"http://example.com/firmware","sha256":"abcd","firmwareSignature":"c2lnbmF0dXJl"}"#,
+    );
+    mqtt_box.send(mqtt_message).await?;
+
+    // The digest is taken from the command, the signature from the action input
+    let RequestEnvelope {
+        request: (_, download_request),
+        reply_to: _,
+    } = recv_or_fail_on_actor_exit(&mut downloader_box, &mut actor_handle, "download request")
+        .await
+        .expect("download request expected");
+    assert_eq!(download_request.url, "http://example.com/firmware");
+    assert_eq!(download_request.sha256.as_deref(), Some("abcd"));
+    assert_eq!(download_request.sha512, None);
+    assert_eq!(download_request.signature.as_deref(), Some("c2lnbmF0dXJl"));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn download_action_without_input_url() -> Result<(), DynError> {
     let workflow = r#"
@@ -999,25 +1178,30 @@ action = "cleanup"
 }
 
 #[tokio::test]
-async fn sync_signal_sent_to_listeners_on_successful_workflow_operation() -> Result<(), DynError> {
-    // A workflow-based `config_update` operation reaching its `successful` state must notify
-    // the actors listening for its completion. Unlike a monolithic builtin operation, this
-    // terminal state flows through `process_command_update` (not `process_builtin_command_update`),
-    // so this test guards the fix that emits the sync signal on that path.
+async fn failed_action_is_retried_till_exhaustion() -> Result<(), DynError> {
     let workflow = r#"
 operation = "config_update"
 
 [init]
 action = "proceed"
+on_success = "set"
+
+[set]
+action = "builtin:config_update:set"
+retry = { max_attempts = 2, backoff = 0, on_exhausted = { status = "failed", reason = "too many attempts" } }
 on_success = "successful"
+on_error = "failed"
 
 [successful]
 action = "cleanup"
+
+[failed]
+action = "cleanup"
 "#;
 
     let TestHandler {
         mut mqtt_box,
-        mut sync_signal_box,
+        mut config_box,
         mut actor_handle,
         ..
     } = spawn_mqtt_operation_converter(
@@ -1026,33 +1210,76 @@ action = "cleanup"
     )
     .await?;
 
-    // Trigger the operation
     let init_message = MqttMessage::new(
         &Topic::new_unchecked("te/device/main///cmd/config_update/123"),
         r#"{"status":"init"}"#,
     );
     mqtt_box.send(init_message).await?;
 
-    // The listener registered for `config_update` is notified once the workflow finishes
-    let signal =
-        recv_or_fail_on_actor_exit(&mut sync_signal_box, &mut actor_handle, "sync signal").await;
-    assert!(
-        signal.is_some(),
-        "expected a sync signal on operation completion"
-    );
+    // First attempt
+    let RequestEnvelope { mut reply_to, .. } = recv_or_fail_on_actor_exit(
+        &mut config_box,
+        &mut actor_handle,
+        "builtin operation step request",
+    )
+    .await
+    .expect("expected builtin operation step request");
+    reply_to.send(Err("not yet".to_string())).await?;
+
+    // The failed attempt is persisted and the step is retried
+    let payload = recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/config_update/123",
+        "set",
+    )
+    .await;
+    assert_eq!(payload["@retries"], json!({"set": 1}));
+
+    let RequestEnvelope {
+        request,
+        mut reply_to,
+    } = recv_or_fail_on_actor_exit(
+        &mut config_box,
+        &mut actor_handle,
+        "builtin operation step request",
+    )
+    .await
+    .expect("expected a second builtin operation step request");
+    assert_eq!(request.command_state.payload["@retries"], json!({"set": 1}));
+    reply_to.send(Err("still not".to_string())).await?;
+
+    // No more attempts
+    let payload = recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/config_update/123",
+        "failed",
+    )
+    .await;
+    assert_eq!(payload["reason"], json!("too many attempts"));
+    assert_eq!(payload.get("@retries"), None);
 
     Ok(())
 }
 
 #[tokio::test]
-async fn sync_signal_sent_to_listeners_on_failed_workflow_operation() -> Result<(), DynError> {
-    // Listeners must also be notified when the operation reaches its `failed` terminal state.
+async fn retried_action_can_succeed() -> Result<(), DynError> {
     let workflow = r#"
 operation = "config_update"
 
 [init]
 action = "proceed"
-on_success = "failed"
+on_success = "set"
+
+[set]
+action = "builtin:config_update:set"
+retry = { max_attempts = 3, backoff = 0 }
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
 
 [failed]
 action = "cleanup"
@@ -1060,7 +1287,7 @@ action = "cleanup"
 
     let TestHandler {
         mut mqtt_box,
-        mut sync_signal_box,
+        mut config_box,
         mut actor_handle,
         ..
     } = spawn_mqtt_operation_converter(
@@ -1069,47 +1296,665 @@ action = "cleanup"
     )
     .await?;
 
-    // Trigger the operation
     let init_message = MqttMessage::new(
         &Topic::new_unchecked("te/device/main///cmd/config_update/123"),
         r#"{"status":"init"}"#,
     );
     mqtt_box.send(init_message).await?;
 
-    // The listener registered for `config_update` is notified once the workflow finishes
-    let signal =
-        recv_or_fail_on_actor_exit(&mut sync_signal_box, &mut actor_handle, "sync signal").await;
-    assert!(
-        signal.is_some(),
-        "expected a sync signal on operation completion"
-    );
+    let RequestEnvelope { mut reply_to, .. } = recv_or_fail_on_actor_exit(
+        &mut config_box,
+        &mut actor_handle,
+        "builtin operation step request",
+    )
+    .await
+    .expect("expected builtin operation step request");
+    reply_to.send(Err("not yet".to_string())).await?;
+
+    let RequestEnvelope { mut reply_to, .. } = recv_or_fail_on_actor_exit(
+        &mut config_box,
+        &mut actor_handle,
+        "builtin operation step request",
+    )
+    .await
+    .expect("expected a second builtin operation step request");
+    reply_to.send(Ok(json!({}))).await?;
+
+    // The attempt count is cleared once the step is successful
+    let payload = recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/config_update/123",
+        "successful",
+    )
+    .await;
+    assert_eq!(payload.get("@retries"), None);
 
     Ok(())
 }
 
-struct TestHandler {
-    tmp_dir: Arc<TempTedgeDir>,
-    actor_handle: JoinHandle<Result<(), RuntimeError>>,
-    mqtt_box: TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>>,
-    software_box: TimedMessageBox<SimpleMessageBox<SoftwareCommand, SoftwareCommand>>,
-    restart_box: TimedMessageBox<SimpleMessageBox<RestartCommand, RestartCommand>>,
-    _inotify_box: TimedMessageBox<SimpleMessageBox<NoMessage, FsWatchEvent>>,
-    downloader_box: TimedMessageBox<
-        SimpleMessageBox<RequestEnvelope<DownloaderRequest, DownloaderResult>, NoMessage>,
-    >,
-    uploader_box: TimedMessageBox<
-        SimpleMessageBox<RequestEnvelope<UploaderRequest, UploaderResult>, NoMessage>,
-    >,
-    config_box: TimedMessageBox<
-        SimpleMessageBox<RequestEnvelope<OperationStepRequest, OperationStepResponse>, NoMessage>,
-    >,
-    sync_signal_box: TimedMessageBox<SimpleMessageBox<CmdMetaSyncSignal, NoMessage>>,
-}
+#[tokio::test]
+async fn retried_init_step_is_published_after_the_backoff_and_run_once() -> Result<(), DynError> {
+    let workflow = r#"
+operation = "config_update"
 
-async fn spawn_mqtt_operation_converter(
-    device_topic_id: &str,
-    workflows: Vec<(String, String)>,
-) -> Result<TestHandler, DynError> {
+[init]
+action = "builtin:config_update:set"
+retry = { max_attempts = 2, backoff = 1 }
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        mut mqtt_box,
+        mut config_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("config_update.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    let topic = "te/device/main///cmd/config_update/123";
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked(topic),
+            r#"{"status":"init"}"#,
+        ))
+        .await?;
+    let RequestEnvelope { mut reply_to, .. } = recv_or_fail_on_actor_exit(
+        &mut config_box,
+        &mut actor_handle,
+        "builtin operation step request",
+    )
+    .await
+    .expect("expected builtin operation step request");
+    reply_to.send(Err("not yet".to_string())).await?;
+
+    // Nothing is published till the backoff expires
+    let early = tokio::time::timeout(Duration::from_millis(500), async {
+        while let Some(message) = mqtt_box.recv().await {
+            if message.topic.name == topic {
+                return Some(message);
+            }
+        }
+        None
+    })
+    .await;
+    assert!(matches!(early, Err(_) | Ok(None)), "{early:?}");
+
+    let retry_message = loop {
+        let message = recv_or_fail_on_actor_exit(&mut mqtt_box, &mut actor_handle, "retry")
+            .await
+            .expect("expected the retry state");
+        if message.topic.name == topic {
+            break message;
+        }
+    };
+    let payload: serde_json::Value = serde_json::from_slice(retry_message.payload_bytes())?;
+    assert_eq!(payload["status"], json!("init"));
+    assert_eq!(payload["@retries"], json!({"init": 1}));
+
+    // The retry state is received back from the broker, but not processed twice
+    mqtt_box.send(retry_message).await?;
+    let RequestEnvelope { mut reply_to, .. } = recv_or_fail_on_actor_exit(
+        &mut config_box,
+        &mut actor_handle,
+        "builtin operation step request",
+    )
+    .await
+    .expect("expected a second builtin operation step request");
+    reply_to.send(Ok(json!({}))).await?;
+    recv_command_state_with_status(&mut mqtt_box, &mut actor_handle, topic, "successful").await;
+
+    let extra = tokio::time::timeout(Duration::from_millis(500), config_box.recv()).await;
+    assert!(
+        matches!(extra, Err(_) | Ok(None)),
+        "unexpected third attempt"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn retries_are_cancelled_when_the_command_is_cleared_during_the_backoff(
+) -> Result<(), DynError> {
+    let workflow = r#"
+operation = "config_update"
+
+[init]
+action = "builtin:config_update:set"
+retry = { max_attempts = 2, backoff = 1 }
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        mut mqtt_box,
+        mut config_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("config_update.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    let topic = "te/device/main///cmd/config_update/123";
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked(topic),
+            r#"{"status":"init"}"#,
+        ))
+        .await?;
+    let RequestEnvelope { mut reply_to, .. } = recv_or_fail_on_actor_exit(
+        &mut config_box,
+        &mut actor_handle,
+        "builtin operation step request",
+    )
+    .await
+    .expect("expected builtin operation step request");
+    reply_to.send(Err("not yet".to_string())).await?;
+
+    // The command is cleared before the backoff expires
+    mqtt_box
+        .send(MqttMessage::new(&Topic::new_unchecked(topic), "").with_retain())
+        .await?;
+
+    // Neither the retry state is published nor the step is run again
+    let retry = tokio::time::timeout(Duration::from_millis(2000), async {
+        while let Some(message) = mqtt_box.recv().await {
+            if message.topic.name == topic && !message.payload_bytes().is_empty() {
+                return Some(message);
+            }
+        }
+        None
+    })
+    .await;
+    assert!(matches!(retry, Err(_) | Ok(None)), "{retry:?}");
+    let extra = tokio::time::timeout(Duration::from_millis(500), config_box.recv()).await;
+    assert!(
+        matches!(extra, Err(_) | Ok(None)),
+        "unexpected second attempt"
+    );
+    assert!(!actor_handle.is_finished());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn javascript_steps_update_the_command_payload() -> Result<(), DynError> {
+    let workflow = r#"
+operation = "count"
+
+[init]
+javascript = "${.payload.module}"
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        tmp_dir,
+        mut mqtt_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("count.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    let module = tmp_dir.path().join("count.js");
+    let module = module.to_str().unwrap();
+    std::fs::write(
+        module,
+        "export function onCommand(payload) { return { count: payload.count + 1 } }",
+    )?;
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/count/1"),
+            json!({"status":"init", "module": module, "count": 41}).to_string(),
+        ))
+        .await?;
+    let payload = recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/count/1",
+        "successful",
+    )
+    .await;
+    assert_eq!(payload["count"], json!(42));
+
+    // The module is reloaded when updated
+    std::fs::write(
+        module,
+        "export function onCommand(payload) { throw new Error(`cannot count ${payload.count}`) }",
+    )?;
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/count/2"),
+            json!({"status":"init", "module": module, "count": 1}).to_string(),
+        ))
+        .await?;
+    let payload = recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/count/2",
+        "failed",
+    )
+    .await;
+    assert!(payload["reason"]
+        .as_str()
+        .unwrap()
+        .contains("cannot count 1"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn javascript_steps_are_interrupted_on_timeout() -> Result<(), DynError> {
+    let workflow = r#"
+operation = "count"
+
+[init]
+javascript = "${.payload.module}"
+timeout_second = 1
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        tmp_dir,
+        mut mqtt_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("count.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    // A module that cannot be loaded till something is written on the pipe
+    let module = tmp_dir.path().join("blocked.js");
+    let status = std::process::Command::new("mkfifo").arg(&module).status()?;
+    assert!(status.success());
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/count/1"),
+            json!({"status":"init", "module": module}).to_string(),
+        ))
+        .await?;
+    let payload = recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/count/1",
+        "failed",
+    )
+    .await;
+    assert!(payload["reason"].as_str().unwrap().contains("timed out"));
+
+    // Release the pending read
+    drop(std::fs::OpenOptions::new().write(true).open(&module)?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn javascript_outcomes_are_ignored_once_the_command_is_cleared() -> Result<(), DynError> {
+    let workflow = r#"
+operation = "count"
+
+[init]
+javascript = "${.payload.module}"
+timeout_second = 1
+on_success = "successful"
+on_error = "failed"
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        tmp_dir,
+        mut mqtt_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("count.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    // A module that cannot be loaded till something is written on the pipe
+    let module = tmp_dir.path().join("blocked.js");
+    let status = std::process::Command::new("mkfifo").arg(&module).status()?;
+    assert!(status.success());
+    let topic = "te/device/main///cmd/count/1";
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked(topic),
+            json!({"status":"init", "module": module}).to_string(),
+        ))
+        .await?;
+
+    // The command is cleared while the script is running
+    mqtt_box
+        .send(MqttMessage::new(&Topic::new_unchecked(topic), "").with_retain())
+        .await?;
+
+    // The outcome of the script, received after its timeout, is ignored
+    let late = tokio::time::timeout(Duration::from_millis(2000), async {
+        while let Some(message) = mqtt_box.recv().await {
+            if message.topic.name == topic && !message.payload_bytes().is_empty() {
+                return Some(message);
+            }
+        }
+        None
+    })
+    .await;
+    assert!(matches!(late, Err(_) | Ok(None)), "{late:?}");
+    assert!(!actor_handle.is_finished());
+
+    // Release the pending read
+    drop(std::fs::OpenOptions::new().write(true).open(&module)?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn awaited_sub_operation_times_out() -> Result<(), DynError> {
+    let workflow = r#"
+operation = "parent"
+
+[init]
+operation = "child"
+on_exec = "awaiting"
+
+[awaiting]
+action = "await-operation-completion"
+timeout_second = 1
+on_success = "successful"
+on_error = "failed"
+on_timeout = { status = "failed", reason = "child timeout" }
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        mut mqtt_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("parent.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/parent/1"),
+            r#"{"status":"init"}"#,
+        ))
+        .await?;
+
+    // The sub-operation is launched but never completes
+    recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/child/sub:parent:1",
+        "init",
+    )
+    .await;
+    let payload = recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/parent/1",
+        "failed",
+    )
+    .await;
+    assert_eq!(payload["reason"], json!("child timeout"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn fan_out_times_out() -> Result<(), DynError> {
+    let workflow = r#"
+operation = "batch"
+
+[init]
+iterate = "${.payload.items}"
+fan_out = "child"
+timeout_second = 1
+on_success = "successful"
+on_error = "failed"
+on_timeout = { status = "failed", reason = "batch timeout" }
+
+[successful]
+action = "cleanup"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        mut mqtt_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("batch.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/batch/1"),
+            json!({"status":"init", "items": [{"id": "a"}, {"id": "b"}]}).to_string(),
+        ))
+        .await?;
+
+    // The sub-operations are launched but never complete
+    recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/child/sub:batch@1:1",
+        "init",
+    )
+    .await;
+    let payload = recv_command_state_with_status(
+        &mut mqtt_box,
+        &mut actor_handle,
+        "te/device/main///cmd/batch/1",
+        "failed",
+    )
+    .await;
+    assert_eq!(payload["reason"], json!("batch timeout"));
+    assert_eq!(
+        payload["@results"],
+        json!([{"status": "executing"}, {"status": "executing"}])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_signal_sent_to_listeners_on_successful_workflow_operation() -> Result<(), DynError> {
+    // A workflow-based `config_update` operation reaching its `successful` state must notify
+    // the actors listening for its completion. Unlike a monolithic builtin operation, this
+    // terminal state flows through `process_command_update` (not `process_builtin_command_update`),
+    // so this test guards the fix that emits the sync signal on that path.
+    let workflow = r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "successful"
+
+[successful]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        mut mqtt_box,
+        mut sync_signal_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("config_update.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    // Trigger the operation
+    let init_message = MqttMessage::new(
+        &Topic::new_unchecked("te/device/main///cmd/config_update/123"),
+        r#"{"status":"init"}"#,
+    );
+    mqtt_box.send(init_message).await?;
+
+    // The listener registered for `config_update` is notified once the workflow finishes
+    let signal =
+        recv_or_fail_on_actor_exit(&mut sync_signal_box, &mut actor_handle, "sync signal").await;
+    assert!(
+        signal.is_some(),
+        "expected a sync signal on operation completion"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_signal_sent_to_listeners_on_failed_workflow_operation() -> Result<(), DynError> {
+    // Listeners must also be notified when the operation reaches its `failed` terminal state.
+    let workflow = r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "failed"
+
+[failed]
+action = "cleanup"
+"#;
+
+    let TestHandler {
+        mut mqtt_box,
+        mut sync_signal_box,
+        mut actor_handle,
+        ..
+    } = spawn_mqtt_operation_converter(
+        "device/main//",
+        vec![("config_update.toml".to_string(), workflow.to_string())],
+    )
+    .await?;
+
+    // Trigger the operation
+    let init_message = MqttMessage::new(
+        &Topic::new_unchecked("te/device/main///cmd/config_update/123"),
+        r#"{"status":"init"}"#,
+    );
+    mqtt_box.send(init_message).await?;
+
+    // The listener registered for `config_update` is notified once the workflow finishes
+    let signal =
+        recv_or_fail_on_actor_exit(&mut sync_signal_box, &mut actor_handle, "sync signal").await;
+    assert!(
+        signal.is_some(),
+        "expected a sync signal on operation completion"
+    );
+
+    Ok(())
+}
+
+struct TestHandler {
+    tmp_dir: Arc<TempTedgeDir>,
+    actor_handle: JoinHandle<Result<(), RuntimeError>>,
+    mqtt_box: TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>>,
+    software_box: TimedMessageBox<SimpleMessageBox<SoftwareCommand, SoftwareCommand>>,
+    restart_box: TimedMessageBox<SimpleMessageBox<RestartCommand, RestartCommand>>,
+    _inotify_box: TimedMessageBox<SimpleMessageBox<NoMessage, FsWatchEvent>>,
+    downloader_box: TimedMessageBox<
+        SimpleMessageBox<RequestEnvelope<DownloaderRequest, DownloaderResult>, NoMessage>,
+    >,
+    uploader_box: TimedMessageBox<
+        SimpleMessageBox<RequestEnvelope<UploaderRequest, UploaderResult>, NoMessage>,
+    >,
+    config_box: TimedMessageBox<
+        SimpleMessageBox<RequestEnvelope<OperationStepRequest, OperationStepResponse>, NoMessage>,
+    >,
+    sync_signal_box: TimedMessageBox<SimpleMessageBox<CmdMetaSyncSignal, NoMessage>>,
+}
+
+async fn spawn_mqtt_operation_converter(
+    device_topic_id: &str,
+    workflows: Vec<(String, String)>,
+) -> Result<TestHandler, DynError> {
+    spawn_workflow_actor(
+        device_topic_id,
+        workflows,
+        |workflow_actor_builder| async move { workflow_actor_builder.build().run().await },
+    )
+    .await
+}
+
+/// Spawn the workflow actor on a runtime, as done by the agent
+async fn spawn_mqtt_operation_converter_on_runtime(
+    device_topic_id: &str,
+    workflows: Vec<(String, String)>,
+    mut runtime: Runtime,
+) -> Result<TestHandler, DynError> {
+    spawn_workflow_actor(
+        device_topic_id,
+        workflows,
+        |workflow_actor_builder| async move {
+            runtime.spawn(workflow_actor_builder).await?;
+            runtime.run_to_completion().await
+        },
+    )
+    .await
+}
+
+async fn spawn_workflow_actor<F>(
+    device_topic_id: &str,
+    workflows: Vec<(String, String)>,
+    run: impl FnOnce(WorkflowActorBuilder) -> F,
+) -> Result<TestHandler, DynError>
+where
+    F: Future<Output = Result<(), RuntimeError>> + Send + 'static,
+{
     let mut software_builder = SoftwareActor(SimpleMessageBoxBuilder::new("Software", 5));
     let mut restart_builder = RestartActor(SimpleMessageBoxBuilder::new("Restart", 5));
     let mut config_builder = ConfigActorBuilder(SimpleMessageBoxBuilder::new("Config", 5));
@@ -1185,12 +2030,12 @@ async fn spawn_mqtt_operation_converter(
     let uploader_box = uploader_builder.build().with_timeout(TEST_TIMEOUT_MS);
     let _inotify_box = inotify_builder.build().with_timeout(TEST_TIMEOUT_MS);
 
-    let workflow_actor = workflow_actor_builder.build();
+    let workflow_actor = run(workflow_actor_builder);
     let tmp_dir_guard = Arc::clone(&tmp_dir);
     let actor_handle = tokio::spawn(async move {
         // Keep tmp_dir alive for the full actor lifetime.
         let _tmp_dir_guard = tmp_dir_guard;
-        workflow_actor.run().await
+        workflow_actor.await
     });
 
     Ok(TestHandler {
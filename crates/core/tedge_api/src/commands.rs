@@ -657,6 +657,27 @@ impl From<SoftwareError> for Vec<SoftwareModuleItem> {
                     })
                     .collect()
             }
+            SoftwareError::RolledBack { updates } => updates
+                .into_iter()
+                .map(|update| {
+                    let action = update.action();
+                    let module = update.into_module();
+                    let reason = match (&action, &module.version) {
+                        (SoftwareModuleAction::Install, Some(version)) => {
+                            format!("Rolled back to version {version}")
+                        }
+                        (SoftwareModuleAction::Install, None) => "Rolled back".to_string(),
+                        (SoftwareModuleAction::Remove, _) => "Rolled back: removed".to_string(),
+                    };
+                    SoftwareModuleItem {
+                        name: module.name,
+                        version: module.version,
+                        url: None,
+                        action: Some(action),
+                        reason: Some(reason),
+                    }
+                })
+                .collect(),
             _ => vec![],
         }
     }
@@ -782,12 +803,36 @@ pub struct LogUploadCmdPayload {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub search_text: Option<String>,
     pub lines: usize,
+    /// Compression applied to the uploaded file, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<LogCompression>,
+    /// When set, `type` is a glob pattern and all the matching log types are uploaded as a tar archive
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bundle: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub log_path: Option<Utf8PathBuf>,
 }
 
 impl Jsonify for LogUploadCmdPayload {}
 
+/// Compression algorithm applied to an uploaded log file
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LogCompression {
+    Gzip,
+    Zstd,
+}
+
+impl LogCompression {
+    /// The extension appended to the name of the compressed file
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LogCompression::Gzip => "gz",
+            LogCompression::Zstd => "zst",
+        }
+    }
+}
+
 impl CommandPayload for LogUploadCmdPayload {
     fn operation_type() -> OperationType {
         OperationType::LogUpload
@@ -934,6 +979,15 @@ pub struct FirmwareUpdateCmdPayload {
     pub remote_url: String,
     pub name: String,
     pub version: String,
+    /// Expected SHA-256 digest of the firmware, checked by the workflow `download` action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Expected SHA-512 digest of the firmware, checked by the workflow `download` action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+    /// Base64 encoded detached signature of the firmware, checked by the workflow `download` action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub log_path: Option<Utf8PathBuf>,
 }
@@ -1031,6 +1085,25 @@ mod tests {
         assert_eq!(parsed_request, request);
     }
 
+    #[test]
+    fn serde_software_module_with_digest_and_signature() {
+        let json = r#"{"status":"init","updateList":[{"type":"debian","modules":[{"name":"debian1","version":"0.0.1","url":"test.com","sha256":"abcd","signature":"c2lnbmF0dXJl","action":"install"}]}]}"#;
+
+        let request =
+            SoftwareUpdateCommandPayload::from_json(json).expect("Fail to parse the json request");
+        let module = &request.update_list[0].modules[0];
+        assert_eq!(
+            module.url,
+            Some(
+                DownloadInfo::new("test.com")
+                    .with_sha256("abcd")
+                    .with_signature("c2lnbmF0dXJl")
+            )
+        );
+
+        assert_eq!(request.to_json(), json);
+    }
+
     #[test]
     fn serde_custom_command_status() {
         let request = SoftwareListCommandPayload {
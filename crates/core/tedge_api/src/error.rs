@@ -99,6 +99,18 @@ pub enum SoftwareError {
     #[error("The update-list command is not supported by this: {0} plugin")]
     UpdateListNotSupported(String),
 
+    #[error("Failed to roll back updates for {software_type:?}")]
+    Rollback {
+        software_type: SoftwareType,
+        reason: String,
+    },
+
+    #[error("The rollback command is not supported by this: {0} plugin")]
+    RollbackNotSupported(String),
+
+    #[error("Rolled back the modules to their previous versions: {:?}", module_names(.updates))]
+    RolledBack { updates: Vec<SoftwareModuleUpdate> },
+
     #[error("I/O error: {reason:?}")]
     IoError { reason: String },
 
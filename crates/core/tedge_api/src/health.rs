@@ -2,6 +2,7 @@ use crate::mqtt_topics::Channel;
 use crate::mqtt_topics::EntityTopicId;
 use crate::mqtt_topics::MqttSchema;
 use crate::mqtt_topics::ServiceTopicId;
+use crate::mqtt_topics::SignalType;
 use clock::Clock;
 use clock::WallClock;
 use mqtt_channel::MqttMessage;
@@ -26,6 +27,37 @@ pub fn service_health_topic(
     )
 }
 
+/// The topic on which a service is requested for a snapshot of its actors
+///
+/// This is a signal and not a command: the request has no lifecycle and the response is published on
+/// [service_runtime_status_topic].
+pub fn service_runtime_request_topic(
+    mqtt_schema: &MqttSchema,
+    service_topic_id: &EntityTopicId,
+) -> Topic {
+    mqtt_schema.topic_for(
+        service_topic_id,
+        &Channel::Signal {
+            signal_type: SignalType::Custom(RUNTIME_SNAPSHOT.to_string()),
+        },
+    )
+}
+
+/// The topic on which a service publishes a snapshot of its actors
+pub fn service_runtime_status_topic(
+    mqtt_schema: &MqttSchema,
+    service_topic_id: &EntityTopicId,
+) -> Topic {
+    mqtt_schema.topic_for(
+        service_topic_id,
+        &Channel::Status {
+            component: RUNTIME_SNAPSHOT.to_string(),
+        },
+    )
+}
+
+const RUNTIME_SNAPSHOT: &str = "runtime";
+
 /// Encodes a valid health topic.
 ///
 /// Health topics are topics on which messages about health status of services are published. To be
@@ -68,10 +100,16 @@ impl ServiceHealthTopic {
             .into(),
             qos: mqtt_channel::QoS::AtLeastOnce,
             retain: true,
+            properties: Default::default(),
         }
     }
 
     pub fn up_message(&self) -> MqttMessage {
+        self.health_message(Status::Up, None)
+    }
+
+    /// A health message with the given status, along with the status of the actors of the service, if known
+    pub fn health_message(&self, status: Status, actors: Option<serde_json::Value>) -> MqttMessage {
         let now = WallClock.now();
         let time_format = self.time_format;
         let timestamp = time_format.to_json(now).unwrap_or_else(|err| {
@@ -81,12 +119,15 @@ impl ServiceHealthTopic {
             now.to_string().into()
         });
 
-        let health_status = json!({
-            "status": "up",
+        let mut health_status = json!({
+            "status": status,
             "pid": process::id(),
             "time": timestamp
-        })
-        .to_string();
+        });
+        if let Some(actors) = actors {
+            health_status["actors"] = actors;
+        }
+        let health_status = health_status.to_string();
 
         let response_topic_health = Topic::new_unchecked(self.as_str());
 
@@ -113,6 +154,8 @@ pub struct HealthStatus {
 pub enum Status {
     Up,
     Down,
+    /// The service is running, but some of its components are stalled
+    Degraded,
     #[serde(untagged)]
     Other(String),
 }
@@ -128,6 +171,7 @@ impl Display for Status {
         let status = match self {
             Status::Up => "up",
             Status::Down => "down",
+            Status::Degraded => "degraded",
             Status::Other(val) if val.is_empty() => "unknown",
             Status::Other(val) => val,
         };
@@ -191,6 +235,12 @@ mod tests {
         Status::Down;
         "service-health-status-down"
     )]
+    #[test_case(
+        "te/device/main/service/tedge-mapper-c8y/status/health",
+        r#"{"status":"degraded","actors":{"MqttActor-0":{"status":"stalled"}}}"#,
+        Status::Degraded;
+        "service-health-status-degraded"
+    )]
     #[test_case(
         "te/device/main/service/tedge-mapper-c8y/status/health",
         r#"{"status":"foo"}"#,
@@ -290,4 +340,21 @@ mod tests {
 
         assert_matches!(timestamp, Value::Number(..))
     }
+
+    #[test]
+    fn degraded_health_message_with_actors() {
+        let health_topic = ServiceHealthTopic {
+            topic: "te/device/main/service/test_daemon/status/health".into(),
+            time_format: TimeFormat::Unix,
+        };
+        let msg = health_topic.health_message(
+            Status::Degraded,
+            Some(json!({"Converter-1": {"status": "stalled", "pending": 3, "critical": true}})),
+        );
+
+        let payload: Value = serde_json::from_slice(msg.payload_bytes()).unwrap();
+        assert_eq!(payload["status"], "degraded");
+        assert_eq!(payload["actors"]["Converter-1"]["status"], "stalled");
+        assert!(msg.retain);
+    }
 }
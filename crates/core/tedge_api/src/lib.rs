@@ -95,6 +95,7 @@ mod tests {
             payload: r#"{"status":"init"}"#.to_string().into(),
             qos: QoS::AtLeastOnce,
             retain: true,
+            properties: Default::default(),
         };
         let actual_msg = request.command_message(&mqtt_schema);
         assert_eq!(actual_msg, expected_msg);
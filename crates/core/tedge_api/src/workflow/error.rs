@@ -21,6 +21,9 @@ pub enum WorkflowDefinitionError {
     #[error("The provided target {0} is not a valid path expression")]
     InvalidPathExpression(String),
 
+    #[error("No retry policy can be attached to the {state} state: {action}")]
+    InvalidRetryPolicy { state: String, action: String },
+
     #[error("The `builtin:{builtin_operation}` cannot be invoked from `{main_operation}`, but only from `{builtin_operation}`")]
     InvalidBuiltinOperation {
         main_operation: String,
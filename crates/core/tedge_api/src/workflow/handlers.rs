@@ -1,9 +1,11 @@
+use crate::workflow::GenericCommandState;
 use crate::workflow::GenericStateUpdate;
 use crate::workflow::ScriptDefinitionError;
 use serde_json::json;
 use serde_json::Value;
 use std::cmp::max;
 use std::fmt::Display;
+use std::num::NonZeroU32;
 use std::os::unix::prelude::ExitStatusExt;
 use std::time::Duration;
 
@@ -200,6 +202,69 @@ impl ExitHandlers {
         }
     }
 
+    pub fn state_update_on_javascript_result(
+        &self,
+        module: &str,
+        result: Result<Value, String>,
+    ) -> Value {
+        match (&self.on_success, result) {
+            (Some(successful_state), Ok(dynamic_update)) => {
+                successful_state.clone().inject_into_json(dynamic_update)
+            }
+            (None, Ok(dynamic_update)) => {
+                if dynamic_update
+                    .get("status")
+                    .and_then(Value::as_str)
+                    .is_some()
+                {
+                    dynamic_update
+                } else {
+                    GenericStateUpdate::failed(format!("JavaScript {module} returned no status"))
+                        .into_json()
+                }
+            }
+            (_, Err(reason)) => self
+                .on_error
+                .clone()
+                .unwrap_or_else(|| GenericStateUpdate::failed(reason.clone()))
+                .inject_into_json(json!({ "reason": reason })),
+        }
+    }
+
+    /// Tell if the given status is the one used when the action fails or is killed
+    pub fn is_failure(&self, status: &str) -> bool {
+        let failed = GenericStateUpdate::unknown_error();
+        [self.on_error.as_ref(), self.on_kill.as_ref()]
+            .into_iter()
+            .map(|update| update.unwrap_or(&failed))
+            .any(|update| update.status == status)
+    }
+
+    /// The state defined by these handlers for a successful outcome
+    ///
+    /// Return `None` if no such state is defined,
+    /// the action being then in charge of the state reached on success.
+    pub(crate) fn success_state(&self) -> Option<&GenericStateUpdate> {
+        self.on_success.as_ref()
+    }
+
+    /// The states which can be returned by the action on its stdout
+    pub(crate) fn stdout_states(&self) -> &[String] {
+        &self.on_stdout
+    }
+
+    /// The states defined by these handlers for a failed outcome
+    ///
+    /// Missing `on_error` and `on_kill` handlers default to the `failed` state.
+    pub(crate) fn failure_states(&self) -> Vec<GenericStateUpdate> {
+        let failed = GenericStateUpdate::unknown_error();
+        [self.on_error.as_ref(), self.on_kill.as_ref()]
+            .into_iter()
+            .map(|update| update.unwrap_or(&failed).clone())
+            .chain(self.on_exit.iter().map(|(_, _, update)| update.clone()))
+            .collect()
+    }
+
     pub fn graceful_timeout(&self) -> Option<Duration> {
         self.timeout
     }
@@ -329,6 +394,92 @@ impl IterateHandlers {
     }
 }
 
+/// Define how the action of a state is retried on failure
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one
+    pub max_attempts: NonZeroU32,
+
+    /// The delay before the first retry, doubled on each subsequent retry
+    pub backoff: Duration,
+
+    /// The state to move to when all the attempts failed
+    ///
+    /// If none, the command is moved to the error state given by the action handlers.
+    pub on_exhausted: Option<GenericStateUpdate>,
+}
+
+/// What to do when the action of a state with a [RetryPolicy] fails
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RetryDecision {
+    /// Retry the action after some delay, from a state tracking the number of failed attempts
+    Retry(GenericCommandState, Duration),
+
+    /// Give up and move to the given state
+    GiveUp(GenericCommandState),
+}
+
+/// The key of the command payload where the number of failed attempts is tracked for each state
+const RETRIES: &str = "@retries";
+
+impl RetryPolicy {
+    /// Decide if a failed action has to be retried
+    ///
+    /// - `current_state` is the state of the command when the action has been launched
+    /// - `failed_state` is the state the command has been moved to by the action failure
+    pub fn on_failure(
+        &self,
+        current_state: &GenericCommandState,
+        failed_state: GenericCommandState,
+    ) -> RetryDecision {
+        let step = current_state.status.as_str();
+        let failed_attempts = current_state
+            .payload
+            .get(RETRIES)
+            .and_then(|retries| retries.get(step))
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+            + 1;
+
+        if failed_attempts < u64::from(self.max_attempts.get()) {
+            let mut retry_state = current_state.clone();
+            if let Some(payload) = retry_state.payload.as_object_mut() {
+                let retries = payload.entry(RETRIES).or_insert_with(|| json!({}));
+                if !retries.is_object() {
+                    *retries = json!({});
+                }
+                retries[step] = failed_attempts.into();
+            }
+            RetryDecision::Retry(retry_state, self.delay(failed_attempts))
+        } else {
+            let given_up_state = Self::reset(step, failed_state);
+            match &self.on_exhausted {
+                None => RetryDecision::GiveUp(given_up_state),
+                Some(update) => RetryDecision::GiveUp(given_up_state.update(update.clone())),
+            }
+        }
+    }
+
+    /// The delay before a new attempt, given the number of attempts that already failed
+    pub fn delay(&self, failed_attempts: u64) -> Duration {
+        let exponent = failed_attempts.saturating_sub(1).min(31) as u32;
+        self.backoff.saturating_mul(2u32.pow(exponent))
+    }
+
+    /// Remove from a command state the number of failed attempts tracked for a step
+    pub fn reset(step: &str, mut state: GenericCommandState) -> GenericCommandState {
+        if let Some(payload) = state.payload.as_object_mut() {
+            if let Some(retries) = payload.get_mut(RETRIES).and_then(Value::as_object_mut) {
+                retries.remove(step);
+                if retries.is_empty() {
+                    payload.remove(RETRIES);
+                }
+            }
+        }
+        state
+    }
+}
+
 /// Define default handlers for all state of an operation workflow
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct DefaultHandlers {
@@ -805,6 +956,62 @@ timeout_second = 15
         panic!("Expect a script with handlers")
     }
 
+    #[test]
+    fn failed_attempts_are_tracked_till_exhaustion() {
+        let policy = RetryPolicy {
+            max_attempts: NonZeroU32::new(3).unwrap(),
+            backoff: Duration::from_secs(10),
+            on_exhausted: Some(GenericStateUpdate::failed("gave up".to_string())),
+        };
+        let current = GenericCommandState::new(
+            "te/device/main///cmd/do_it/123".try_into().unwrap(),
+            "download".to_string(),
+            json!({"status": "download", "url": "http://example.com"}),
+        );
+        let failed = current.clone().update("download_failed".into());
+
+        let RetryDecision::Retry(retry, delay) = policy.on_failure(&current, failed.clone()) else {
+            panic!("Expected a retry")
+        };
+        assert_eq!(delay, Duration::from_secs(10));
+        assert_eq!(retry.status, "download");
+        assert_eq!(retry.payload["@retries"], json!({"download": 1}));
+
+        let RetryDecision::Retry(retry, delay) = policy.on_failure(&retry, failed.clone()) else {
+            panic!("Expected a retry")
+        };
+        assert_eq!(delay, Duration::from_secs(20));
+        assert_eq!(retry.payload["@retries"], json!({"download": 2}));
+
+        let failed = retry.clone().update("download_failed".into());
+        let RetryDecision::GiveUp(given_up) = policy.on_failure(&retry, failed) else {
+            panic!("Expected to give up")
+        };
+        assert_eq!(given_up.status, "failed");
+        assert_eq!(given_up.failure_reason(), Some("gave up"));
+        assert_eq!(given_up.payload.get("@retries"), None);
+    }
+
+    #[test]
+    fn failure_state_is_kept_when_no_on_exhausted_handler() {
+        let policy = RetryPolicy {
+            max_attempts: NonZeroU32::new(1).unwrap(),
+            backoff: Duration::ZERO,
+            on_exhausted: None,
+        };
+        let current = GenericCommandState::new(
+            "te/device/main///cmd/do_it/123".try_into().unwrap(),
+            "download".to_string(),
+            json!({"status": "download"}),
+        );
+        let failed = current.clone().update("download_failed".into());
+
+        assert_eq!(
+            policy.on_failure(&current, failed.clone()),
+            RetryDecision::GiveUp(failed)
+        );
+    }
+
     fn handlers_from_toml(file: &str) -> ExitHandlers {
         let (_, handlers) = script_from_toml(&format!(
             r#"
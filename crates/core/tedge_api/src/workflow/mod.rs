@@ -2,9 +2,11 @@ pub mod error;
 pub mod handlers;
 pub mod log;
 mod on_disk;
+mod simulation;
 pub mod state;
 pub mod supervisor;
 mod toml_config;
+pub mod validation;
 
 use crate::mqtt_topics::EntityTopicId;
 use crate::mqtt_topics::MqttSchema;
@@ -23,8 +25,10 @@ pub use state::*;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::num::NonZeroUsize;
 pub use supervisor::*;
 use tracing::info;
+pub use validation::*;
 
 pub type OperationName = String;
 pub type OperationStep = String;
@@ -66,6 +70,9 @@ pub struct OperationWorkflow {
 
     /// The states of the state machine
     pub states: HashMap<StateName, OperationAction>,
+
+    /// The retry policies attached to some states
+    pub retry_policies: HashMap<StateName, RetryPolicy>,
 }
 
 /// What needs to be done to advance an operation request in some state
@@ -131,6 +138,20 @@ pub enum OperationAction {
     /// ```
     BgScript(ShellScript, ExecHandlers),
 
+    /// Run a JavaScript function in-process over the command payload
+    ///
+    /// The script must export an `onCommand(payload)` function,
+    /// which returns a JSON object to be merged into the payload of the command.
+    /// If no `on_success` handler is given, this object must provide the next `status`.
+    /// Any exception thrown by the function moves the command to the `on_error` state.
+    ///
+    /// ```toml
+    /// javascript = "/etc/tedge/operations/step.js"
+    /// on_success = "<state>"
+    /// on_error = "<state>"
+    /// ```
+    JavaScript(String, ExitHandlers),
+
     /// Generic download action (reusable across operations)
     ///
     /// Downloads a file from `input.url` extracted from the payload and adds the `downloadedPath` to the payload.
@@ -219,6 +240,23 @@ pub enum OperationAction {
     /// on_error = "failed"
     /// ```
     Iterate(JsonPath, IterateHandlers),
+
+    /// Launch a sub-operation for each item of the specified target array in the state payload,
+    /// running up to `max_parallelism` of these sub-operations concurrently.
+    /// The item processed by a sub-operation is available as `@item` (with its index as `@index`)
+    /// to build the sub-operation name and input.
+    /// The outcome of each sub-operation is collected into a `@results` array of the state payload.
+    ///
+    /// ```toml
+    /// iterate = "${.payload.children}"
+    /// fan_out = "firmware_update"
+    /// max_parallelism = 4
+    /// input.url = "${.payload.@item.url}"
+    /// output.version = "${.payload.version}"
+    /// on_success = "successful"
+    /// on_error = "failed"
+    /// ```
+    FanOut(FanOut, AwaitHandlers),
 }
 
 impl Display for OperationAction {
@@ -230,6 +268,7 @@ impl Display for OperationAction {
             OperationAction::RestartAgent(_) => "restart agent".to_string(),
             OperationAction::Script(script, _) => script.to_string(),
             OperationAction::BgScript(script, _) => script.to_string(),
+            OperationAction::JavaScript(module, _) => format!("execute JavaScript {module}"),
             OperationAction::Download(_, _) => "builtin download action".to_string(),
             OperationAction::Upload(_, _) => "builtin upload action".to_string(),
             OperationAction::Operation(operation, maybe_script, _, _) => match maybe_script {
@@ -252,6 +291,12 @@ impl Display for OperationAction {
             OperationAction::Iterate(json_path, _) => {
                 format!("iterate over {json_path}").to_string()
             }
+            OperationAction::FanOut(fan_out, _) => {
+                format!(
+                    "execute {} as sub-operation for each item of {}",
+                    fan_out.operation, fan_out.target
+                )
+            }
         };
         f.write_str(&str)
     }
@@ -316,9 +361,37 @@ impl OperationWorkflow {
             operation,
             handlers,
             states,
+            retry_policies: HashMap::new(),
         })
     }
 
+    /// Attach retry policies to some states of this workflow
+    ///
+    /// Only actions that can fail on their own can be retried.
+    pub fn with_retry_policies(
+        mut self,
+        retry_policies: HashMap<StateName, RetryPolicy>,
+    ) -> Result<Self, WorkflowDefinitionError> {
+        for state in retry_policies.keys() {
+            match self.states.get(state) {
+                Some(action) if action.is_retryable() => continue,
+                Some(action) => {
+                    return Err(WorkflowDefinitionError::InvalidRetryPolicy {
+                        state: state.clone(),
+                        action: action.to_string(),
+                    })
+                }
+                None => {
+                    return Err(WorkflowDefinitionError::MissingState {
+                        state: state.clone(),
+                    })
+                }
+            }
+        }
+        self.retry_policies = retry_policies;
+        Ok(self)
+    }
+
     /// Create a built-in operation workflow
     pub fn built_in(operation: OperationType) -> Self {
         let operation_name = operation.to_string();
@@ -348,6 +421,7 @@ impl OperationWorkflow {
             operation,
             handlers: DefaultHandlers::default(),
             states,
+            retry_policies: HashMap::new(),
         }
     }
 
@@ -372,6 +446,7 @@ impl OperationWorkflow {
             operation: operation.as_str().into(),
             handlers: DefaultHandlers::default(),
             states,
+            retry_policies: HashMap::new(),
         }
     }
 
@@ -419,9 +494,41 @@ impl OperationWorkflow {
             })
             .map(|action| action.inject_state(command_state))
     }
+
+    /// Return the retry policy attached to the current state of a command, if any
+    pub fn get_retry_policy(&self, command_state: &GenericCommandState) -> Option<&RetryPolicy> {
+        self.retry_policies.get(&command_state.status)
+    }
 }
 
 impl OperationAction {
+    /// Tell if this action can fail on its own, and hence be retried
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            OperationAction::Script(_, _)
+                | OperationAction::JavaScript(_, _)
+                | OperationAction::Download(_, _)
+                | OperationAction::Upload(_, _)
+                | OperationAction::BuiltInOperationStep(_, _, _, _)
+                | OperationAction::Operation(_, _, _, _)
+        )
+    }
+
+    /// Tell if the new state of a command denotes a failure of this action
+    pub fn is_failure(&self, new_state: &GenericCommandState) -> bool {
+        match self {
+            OperationAction::Script(_, handlers)
+            | OperationAction::JavaScript(_, handlers)
+            | OperationAction::Download(_, handlers)
+            | OperationAction::Upload(_, handlers)
+            | OperationAction::BuiltInOperationStep(_, _, _, handlers) => {
+                handlers.is_failure(&new_state.status)
+            }
+            _ => new_state.is_failed(),
+        }
+    }
+
     pub fn inject_state(&self, state: &GenericCommandState) -> Self {
         match self {
             OperationAction::Script(script, handlers) => {
@@ -430,6 +537,10 @@ impl OperationAction {
             OperationAction::BgScript(script, handlers) => {
                 OperationAction::BgScript(script.inject_values(state), handlers.clone())
             }
+            OperationAction::JavaScript(module, handlers) => OperationAction::JavaScript(
+                state.inject_values_into_template(module),
+                handlers.clone(),
+            ),
             OperationAction::Operation(operation_expr, optional_script, input, handlers) => {
                 let operation = state.inject_values_into_template(operation_expr);
                 let optional_script = optional_script
@@ -578,6 +689,170 @@ pub enum IterationError {
     IndexOutOfBounds(usize),
 }
 
+/// Launch a sub-operation for each item of an array, with a bounded parallelism
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FanOut {
+    /// Path to the array of items in the command state
+    pub target: JsonPath,
+
+    /// The sub-operation to launch for each item (possibly a template referring to `@item`)
+    pub operation: OperationName,
+
+    /// The maximum number of sub-operations running concurrently, if bounded
+    pub max_parallelism: Option<NonZeroUsize>,
+
+    /// Values to be injected into the init state of each sub-operation
+    pub input: StateExcerpt,
+
+    /// Values to be extracted from the final state of each sub-operation into its result
+    pub output: StateExcerpt,
+}
+
+/// Progress made by a [FanOut] on a command state
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct FanOutProgress {
+    /// The new state of the command, if any progress has been made
+    pub new_state: Option<GenericCommandState>,
+
+    /// The sub-operations to be launched, with the index of the item to process and the init payload
+    pub launch: Vec<(usize, OperationName, Value)>,
+}
+
+const FAN_OUT_RESULTS: &str = "@results";
+const FAN_OUT_EXECUTING: &str = "executing";
+const FAN_OUT_SUCCESSFUL: &str = "successful";
+const FAN_OUT_SKIPPED: &str = "skipped";
+
+impl FanOut {
+    /// Update the progress of a fan-out, given the sub-commands which have reached a terminal state
+    ///
+    /// The per-item outcomes are collected into the `@results` array of the command payload:
+    /// - `null` for an item which sub-operation has not been launched yet,
+    /// - `{"status": "executing"}` for an item which sub-operation is running,
+    /// - the final `status` and `reason` of the sub-operation, along the values extracted by `output`,
+    /// - `{"status": "skipped"}` for an item marked with `"@skip": true`.
+    ///
+    /// Once all the sub-operations are finished, the command is moved to the `on_success` state
+    /// if all of them are successful, and to the `on_error` state otherwise.
+    pub fn process(
+        &self,
+        state: &GenericCommandState,
+        finished_sub_commands: &[GenericCommandState],
+        handlers: &AwaitHandlers,
+    ) -> Result<FanOutProgress, IterationError> {
+        let Some(target) = state.extract_value(&self.target) else {
+            return Err(IterationError::InvalidTarget(self.target.clone()));
+        };
+        let Some(items) = target.as_array() else {
+            return Err(IterationError::TargetNotArray(self.target.clone()));
+        };
+
+        let previous_results = state.payload.get(FAN_OUT_RESULTS);
+        let mut results = match previous_results.and_then(Value::as_array) {
+            Some(results) if results.len() == items.len() => results.clone(),
+            _ => vec![Value::Null; items.len()],
+        };
+
+        // Collect the outcome of the sub-operations that are finished
+        for sub_state in finished_sub_commands {
+            let Some(result) = sub_state
+                .fan_out_index()
+                .and_then(|index| results.get_mut(index))
+            else {
+                continue;
+            };
+            if fan_out_status(result) != Some(FAN_OUT_EXECUTING) {
+                continue;
+            }
+            let mut outcome = self.output.extract_value_from(sub_state);
+            if !outcome.is_object() {
+                outcome = json!({});
+            }
+            outcome["status"] = sub_state.status.clone().into();
+            if sub_state.is_failed() {
+                if let Some(reason) = sub_state.failure_reason() {
+                    outcome["reason"] = reason.into();
+                }
+            }
+            *result = outcome;
+        }
+
+        // Launch new sub-operations, up to the max parallelism
+        let mut running = results
+            .iter()
+            .filter(|result| fan_out_status(result) == Some(FAN_OUT_EXECUTING))
+            .count();
+        let mut launch = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            if !results[index].is_null() {
+                continue;
+            }
+            if item.get("@skip").and_then(Value::as_bool).unwrap_or(false) {
+                results[index] = json!({ "status": FAN_OUT_SKIPPED });
+                continue;
+            }
+            if self.max_parallelism.is_some_and(|max| running >= max.get()) {
+                continue;
+            }
+            let item_state = state
+                .clone()
+                .update_with_json(json!({ "@item": item, "@index": index }));
+            let operation = item_state.inject_values_into_template(&self.operation);
+            let input = self.input.extract_value_from(&item_state);
+            launch.push((index, operation, input));
+            results[index] = json!({ "status": FAN_OUT_EXECUTING });
+            running += 1;
+        }
+
+        let finished = results
+            .iter()
+            .all(|result| !result.is_null() && fan_out_status(result) != Some(FAN_OUT_EXECUTING));
+        let failed = results
+            .iter()
+            .filter(|result| {
+                !matches!(
+                    fan_out_status(result),
+                    Some(FAN_OUT_SUCCESSFUL) | Some(FAN_OUT_SKIPPED)
+                )
+            })
+            .count();
+        let results = Value::Array(results);
+        if finished {
+            let new_state = state
+                .clone()
+                .update_with_json(json!({ FAN_OUT_RESULTS: results }));
+            let new_state = if failed == 0 {
+                new_state.update(handlers.on_success.clone())
+            } else {
+                let mut on_error = handlers.on_error.clone();
+                if on_error.reason.is_none() {
+                    on_error.reason =
+                        Some(format!("{failed} of {} sub-operations failed", items.len()));
+                }
+                new_state.update(on_error)
+            };
+            Ok(FanOutProgress {
+                new_state: Some(new_state),
+                launch,
+            })
+        } else if previous_results != Some(&results) {
+            let new_state = state
+                .clone()
+                .update_with_json(json!({ FAN_OUT_RESULTS: results }));
+            Ok(FanOutProgress {
+                new_state: Some(new_state),
+                launch,
+            })
+        } else {
+            Ok(FanOutProgress::default())
+        }
+    }
+}
+
+fn fan_out_status(result: &Value) -> Option<&str> {
+    result.get("status").and_then(Value::as_str)
+}
+
 /// An actor builder must implement this if it to receive sync signals on completion of other commands
 pub trait SyncOnCommand {
     /// Return the list of operations for which this actor wants to receive sync signals
@@ -586,6 +861,8 @@ pub trait SyncOnCommand {
 
 #[cfg(test)]
 mod tests {
+    use super::AwaitHandlers;
+    use super::FanOut;
     use super::GenericCommandState;
     use super::GenericStateUpdate;
     use super::IterateHandlers;
@@ -595,6 +872,7 @@ mod tests {
     use assert_json_diff::assert_json_include;
     use assert_matches::assert_matches;
     use serde_json::json;
+    use std::num::NonZeroUsize;
 
     #[test]
     fn test_iterate_first_iteration() {
@@ -1013,4 +1291,168 @@ mod tests {
             })
         );
     }
+
+    fn fan_out(max_parallelism: usize) -> FanOut {
+        FanOut {
+            target: ".payload.children".to_string(),
+            operation: "${.payload.@item.operation}".to_string(),
+            max_parallelism: NonZeroUsize::new(max_parallelism),
+            input: json!({"name": "${.payload.@item.name}", "index": "${.payload.@index}"}).into(),
+            output: json!({"version": "${.payload.version}"}).into(),
+        }
+    }
+
+    fn fan_out_handlers() -> AwaitHandlers {
+        AwaitHandlers {
+            timeout: None,
+            on_success: "done".into(),
+            on_error: "partial".into(),
+            on_timeout: GenericStateUpdate::timeout(),
+        }
+    }
+
+    fn fan_out_state(payload: serde_json::Value) -> GenericCommandState {
+        GenericCommandState::new(
+            "te/device/main///cmd/fan/123".try_into().unwrap(),
+            "updating".to_string(),
+            payload,
+        )
+    }
+
+    fn fan_out_sub_command(
+        index: usize,
+        status: &str,
+        payload: serde_json::Value,
+    ) -> GenericCommandState {
+        GenericCommandState::new(
+            format!("te/device/main///cmd/update/sub:fan@{index}:123")
+                .as_str()
+                .try_into()
+                .unwrap(),
+            status.to_string(),
+            payload,
+        )
+    }
+
+    #[test]
+    fn fan_out_launches_sub_operations_up_to_max_parallelism() {
+        let state = fan_out_state(json!({
+            "status": "updating",
+            "children": [
+                {"operation": "update", "name": "a"},
+                {"operation": "update", "name": "b", "@skip": true},
+                {"operation": "update", "name": "c"},
+                {"operation": "update", "name": "d"},
+            ]
+        }));
+
+        let progress = fan_out(2)
+            .process(&state, &[], &fan_out_handlers())
+            .unwrap();
+
+        assert_eq!(
+            progress.launch,
+            vec![
+                (0, "update".to_string(), json!({"name": "a", "index": 0})),
+                (2, "update".to_string(), json!({"name": "c", "index": 2})),
+            ]
+        );
+        let new_state = progress.new_state.unwrap();
+        assert_eq!(new_state.status, "updating");
+        assert_json_eq!(
+            new_state.payload["@results"],
+            json!([{"status": "executing"}, {"status": "skipped"}, {"status": "executing"}, null])
+        );
+
+        // Nothing changes until a sub-operation is finished
+        let progress = fan_out(2)
+            .process(&new_state, &[], &fan_out_handlers())
+            .unwrap();
+        assert_eq!(progress.new_state, None);
+        assert!(progress.launch.is_empty());
+    }
+
+    #[test]
+    fn fan_out_collects_sub_operation_outcomes() {
+        let state = fan_out_state(json!({
+            "status": "updating",
+            "children": [
+                {"operation": "update", "name": "a"},
+                {"operation": "update", "name": "b"},
+            ],
+            "@results": [{"status": "executing"}, null]
+        }));
+        let finished = fan_out_sub_command(0, "successful", json!({"version": "1.2"}));
+
+        let progress = fan_out(1)
+            .process(&state, &[finished], &fan_out_handlers())
+            .unwrap();
+
+        assert_eq!(
+            progress.launch,
+            vec![(1, "update".to_string(), json!({"name": "b", "index": 1}))]
+        );
+        let new_state = progress.new_state.unwrap();
+        assert_eq!(new_state.status, "updating");
+        assert_json_eq!(
+            new_state.payload["@results"],
+            json!([{"status": "successful", "version": "1.2"}, {"status": "executing"}])
+        );
+
+        let failed = fan_out_sub_command(1, "failed", json!({"reason": "no space left"}));
+        let progress = fan_out(1)
+            .process(&new_state, &[failed], &fan_out_handlers())
+            .unwrap();
+
+        assert!(progress.launch.is_empty());
+        let final_state = progress.new_state.unwrap();
+        assert_eq!(final_state.status, "partial");
+        assert_eq!(
+            final_state.failure_reason(),
+            Some("1 of 2 sub-operations failed")
+        );
+        assert_json_eq!(
+            final_state.payload["@results"],
+            json!([
+                {"status": "successful", "version": "1.2"},
+                {"status": "failed", "reason": "no space left", "version": null}
+            ])
+        );
+    }
+
+    #[test]
+    fn fan_out_is_successful_when_all_sub_operations_are_successful() {
+        let state = fan_out_state(json!({
+            "status": "updating",
+            "children": [{"operation": "update", "name": "a"}],
+            "@results": [{"status": "executing"}]
+        }));
+        let finished = fan_out_sub_command(0, "successful", json!({"version": "1.2"}));
+
+        let progress = fan_out(1)
+            .process(&state, &[finished], &fan_out_handlers())
+            .unwrap();
+
+        let final_state = progress.new_state.unwrap();
+        assert_eq!(final_state.status, "done");
+        assert_json_eq!(
+            final_state.payload["@results"],
+            json!([{"status": "successful", "version": "1.2"}])
+        );
+    }
+
+    #[test]
+    fn fan_out_over_an_empty_array_is_successful() {
+        let state = fan_out_state(json!({
+            "status": "updating",
+            "children": []
+        }));
+
+        let progress = fan_out(1)
+            .process(&state, &[], &fan_out_handlers())
+            .unwrap();
+
+        assert!(progress.launch.is_empty());
+        assert_eq!(progress.new_state.unwrap().status, "done");
+    }
 }
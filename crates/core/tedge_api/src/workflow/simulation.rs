@@ -0,0 +1,259 @@
+use crate::mqtt_topics::Channel;
+use crate::mqtt_topics::EntityTopicId;
+use crate::mqtt_topics::MqttSchema;
+use crate::workflow::GenericCommandState;
+use crate::workflow::GenericStateUpdate;
+use crate::workflow::OperationAction;
+use crate::workflow::OperationWorkflow;
+use crate::workflow::RetryDecision;
+use crate::workflow::RetryPolicy;
+use crate::workflow::WorkflowExecutionError;
+use serde_json::json;
+use serde_json::Value;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::process::Output;
+
+impl OperationWorkflow {
+    /// Create the init state of a command to be used to simulate this workflow
+    ///
+    /// The command is created for the main device, using the default MQTT schema.
+    pub fn simulation_init_state(&self, payload: Value) -> GenericCommandState {
+        let schema = MqttSchema::default();
+        let topic = schema.topic_for(
+            &EntityTopicId::default_main_device(),
+            &Channel::Command {
+                operation: self.operation.clone(),
+                cmd_id: "simulation".to_string(),
+            },
+        );
+        let status = GenericCommandState::extract_text_property(&payload, "status")
+            .unwrap_or("init")
+            .to_string();
+        GenericCommandState::new(topic, status, payload)
+    }
+
+    /// Compute the next state of a command, simulating the action of its current state
+    ///
+    /// No script nor action is actually executed: the outcome of the action is stubbed by an exit code,
+    /// `0` simulating a success and any other value a failure.
+    /// - Scripts are simulated with this exit code and an empty stdout.
+    /// - Builtin actions, downloads, uploads and JavaScript functions return an empty object on success.
+    /// - Sub-operations and agent restarts are assumed to complete, successfully or not.
+    ///
+    /// The retry policy of the current state is applied as done by the agent:
+    /// a failed step is retried, without any delay, till the max number of attempts is reached.
+    ///
+    /// Return `None` when the command is cleared.
+    pub fn simulate_step(
+        &self,
+        state: GenericCommandState,
+        exit_code: u8,
+    ) -> Result<Option<GenericCommandState>, WorkflowExecutionError> {
+        let action = self.get_action(&state)?;
+        let retry = self
+            .get_retry_policy(&state)
+            .map(|policy| (policy, action.clone(), state.clone()));
+        let success = exit_code == 0;
+        let failure_reason = || format!("simulated failure with exit code {exit_code}");
+        let new_state = match action {
+            OperationAction::Clear => return Ok(None),
+            OperationAction::MoveTo(update) => state.move_to(update),
+            OperationAction::BuiltIn(exec_handlers, await_handlers) => {
+                if exec_handlers.on_exec.status != state.status {
+                    state.update(exec_handlers.on_exec)
+                } else if success {
+                    state.update(await_handlers.on_success)
+                } else {
+                    state.update(await_handlers.on_error)
+                }
+            }
+            OperationAction::BuiltInOperation(_, handlers)
+            | OperationAction::RestartAgent(handlers)
+            | OperationAction::BgScript(_, handlers)
+            | OperationAction::Operation(_, _, _, handlers) => state.update(handlers.on_exec),
+            OperationAction::AwaitingAgentRestart(handlers) => {
+                if success {
+                    state.update(handlers.on_success)
+                } else {
+                    state.update(handlers.on_timeout)
+                }
+            }
+            OperationAction::AwaitOperationCompletion(handlers, _)
+            | OperationAction::FanOut(_, handlers) => {
+                if success {
+                    state.update(handlers.on_success)
+                } else {
+                    state.update(handlers.on_error)
+                }
+            }
+            OperationAction::Script(script, handlers) => {
+                let output = Output {
+                    status: ExitStatus::from_raw((exit_code as i32) << 8),
+                    stdout: vec![],
+                    stderr: vec![],
+                };
+                state.update_with_script_output(script.command, Ok(output), handlers)
+            }
+            OperationAction::JavaScript(module, handlers) => {
+                let result = if success {
+                    Ok(json!({}))
+                } else {
+                    Err(failure_reason())
+                };
+                state.update_with_javascript_result(&module, result, handlers)
+            }
+            OperationAction::Download(_, ref handlers)
+            | OperationAction::Upload(_, ref handlers)
+            | OperationAction::BuiltInOperationStep(_, _, _, ref handlers) => {
+                let result = if success {
+                    Ok(json!({}))
+                } else {
+                    Err(failure_reason())
+                };
+                let update = handlers.state_update_on_result(&action.to_string(), result);
+                state.update_with_json(update)
+            }
+            OperationAction::Iterate(target, handlers) => {
+                let on_error = handlers.on_error.clone();
+                match OperationAction::process_iterate(state.clone(), &target, handlers) {
+                    Ok(new_state) => new_state,
+                    Err(err) => state.update(GenericStateUpdate {
+                        reason: Some(err.to_string()),
+                        ..on_error
+                    }),
+                }
+            }
+        };
+
+        let new_state = match retry {
+            Some((_, _, current_state)) if current_state.status == new_state.status => new_state,
+            Some((policy, action, current_state)) if action.is_failure(&new_state) => {
+                match policy.on_failure(&current_state, new_state) {
+                    RetryDecision::Retry(retry_state, _) => retry_state,
+                    RetryDecision::GiveUp(new_state) => new_state,
+                }
+            }
+            Some((_, _, current_state)) => RetryPolicy::reset(&current_state.status, new_state),
+            None => new_state,
+        };
+        Ok(Some(new_state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(workflow: &OperationWorkflow, exit_codes: &[(&str, u8)]) -> Vec<String> {
+        let mut trace = vec![];
+        let mut state = workflow.simulation_init_state(json!({"status": "init"}));
+        while trace.len() < 10 {
+            trace.push(state.status.clone());
+            let exit_code = exit_codes
+                .iter()
+                .find(|(step, _)| *step == state.status)
+                .map(|(_, code)| *code)
+                .unwrap_or(0);
+            match workflow.simulate_step(state, exit_code).unwrap() {
+                Some(new_state) => state = new_state,
+                None => break,
+            }
+        }
+        trace
+    }
+
+    #[test]
+    fn simulate_script_exit_codes() {
+        let file = r#"
+operation = "check"
+
+[init]
+action = "proceed"
+on_success = "checking"
+
+[checking]
+script = "/some/check.sh"
+on_exit.0 = "successful"
+on_exit.1 = "repairing"
+on_exit._ = "failed"
+
+[repairing]
+script = "/some/repair.sh"
+on_success = "checking"
+"#;
+        let workflow: OperationWorkflow = toml::from_str(file).unwrap();
+
+        assert_eq!(
+            trace(&workflow, &[]),
+            vec!["init", "checking", "successful"]
+        );
+        assert_eq!(
+            trace(&workflow, &[("checking", 2)]),
+            vec!["init", "checking", "failed"]
+        );
+        assert_eq!(
+            trace(&workflow, &[("checking", 1), ("repairing", 1)]),
+            vec!["init", "checking", "repairing", "failed"]
+        );
+    }
+
+    #[test]
+    fn simulate_builtin_actions() {
+        let file = r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "download"
+
+[download]
+action = "download"
+input.url = "${.payload.remoteUrl}"
+on_success = "set"
+on_error = "download_failed"
+
+[download_failed]
+action = "proceed"
+on_success = { status = "failed", reason = "cannot download" }
+
+[set]
+action = "builtin:config_update:set"
+on_success = "successful"
+"#;
+        let workflow: OperationWorkflow = toml::from_str(file).unwrap();
+
+        assert_eq!(
+            trace(&workflow, &[]),
+            vec!["init", "download", "set", "successful"]
+        );
+        assert_eq!(
+            trace(&workflow, &[("download", 1)]),
+            vec!["init", "download", "download_failed", "failed"]
+        );
+    }
+
+    #[test]
+    fn simulate_retries() {
+        let file = r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "set"
+
+[set]
+action = "builtin:config_update:set"
+retry = { max_attempts = 3, backoff = 10, on_exhausted = { status = "failed", reason = "too many attempts" } }
+on_success = "successful"
+on_error = "failed"
+"#;
+        let workflow: OperationWorkflow = toml::from_str(file).unwrap();
+
+        assert_eq!(trace(&workflow, &[]), vec!["init", "set", "successful"]);
+        assert_eq!(
+            trace(&workflow, &[("set", 1)]),
+            vec!["init", "set", "set", "set", "failed"]
+        );
+    }
+}
@@ -95,6 +95,47 @@ impl GenericCommandState {
         sub_operation: OperationName,
     ) -> GenericCommandState {
         let sub_cmd_id = Self::sub_command_id(&operation, &cmd_id);
+        Self::init_state_with_sub_command_id(
+            schema,
+            entity,
+            operation,
+            cmd_id,
+            sub_operation,
+            sub_cmd_id,
+        )
+    }
+
+    /// Create an init state for one of the sub-operations launched by a fan-out
+    ///
+    /// The index of the item processed by this sub-operation is encoded in the sub command id,
+    /// so it can be retrieved with [GenericCommandState::fan_out_index].
+    pub fn fan_out_sub_command_init_state(
+        schema: &MqttSchema,
+        entity: &EntityTopicId,
+        operation: OperationType,
+        cmd_id: CommandId,
+        sub_operation: OperationName,
+        index: usize,
+    ) -> GenericCommandState {
+        let sub_cmd_id = Self::sub_command_id(&format!("{operation}@{index}"), &cmd_id);
+        Self::init_state_with_sub_command_id(
+            schema,
+            entity,
+            operation,
+            cmd_id,
+            sub_operation,
+            sub_cmd_id,
+        )
+    }
+
+    fn init_state_with_sub_command_id(
+        schema: &MqttSchema,
+        entity: &EntityTopicId,
+        operation: OperationType,
+        cmd_id: CommandId,
+        sub_operation: OperationName,
+        sub_cmd_id: CommandId,
+    ) -> GenericCommandState {
         let topic = schema.topic_for(
             entity,
             &Channel::Command {
@@ -243,6 +284,17 @@ impl GenericCommandState {
         self.update_with_json(json_update)
     }
 
+    /// Update the command state with the value returned by a JavaScript action
+    pub fn update_with_javascript_result(
+        self,
+        module: &str,
+        result: Result<Value, String>,
+        handlers: ExitHandlers,
+    ) -> Self {
+        let json_update = handlers.state_update_on_javascript_result(module, result);
+        self.update_with_json(json_update)
+    }
+
     /// Update the command state with the result of a builtin action
     pub async fn update_with_builtin_action_result(
         self,
@@ -387,9 +439,25 @@ impl GenericCommandState {
     ///
     /// Return None if the given id is not a sub command identifier, i.e. if not generated with [sub_command_id].
     fn extract_invoking_command_id(sub_cmd_id: &str) -> Option<(&str, &str)> {
-        sub_cmd_id
+        Self::split_sub_command_id(sub_cmd_id).map(|(op, _, id)| (op, id))
+    }
+
+    /// Split a sub command identifier into the invoking operation and command identifier,
+    /// plus the index of the processed item when the sub command has been launched by a fan-out.
+    fn split_sub_command_id(sub_cmd_id: &str) -> Option<(&str, Option<usize>, &str)> {
+        let (op, id) = sub_cmd_id
             .strip_prefix("sub:")
-            .and_then(|op_id| op_id.split_once(':'))
+            .and_then(|op_id| op_id.split_once(':'))?;
+        match op.split_once('@') {
+            None => Some((op, None, id)),
+            Some((op, index)) => Some((op, index.parse().ok(), id)),
+        }
+    }
+
+    /// Return the index of the item processed by this command, if launched by a fan-out
+    pub fn fan_out_index(&self) -> Option<usize> {
+        let cmd_id = self.cmd_id()?;
+        Self::split_sub_command_id(&cmd_id).and_then(|(_, index, _)| index)
     }
 
     /// Extract the invoking operation names from a command identifier
@@ -815,6 +883,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn retrieve_invoking_command_of_fan_out_sub_command() {
+        let topic = Topic::new_unchecked("te/device/main///cmd/do_it/sub:make_it@3:456");
+        let payload = r#"{ "status":"successful" }"#;
+        let command = mqtt_channel::MqttMessage::new(&topic, payload);
+        let cmd = GenericCommandState::from_command_message(&command).expect("parsing error");
+        assert_eq!(cmd.fan_out_index(), Some(3));
+        assert_eq!(cmd.invoking_operation_names(), vec!["make_it".to_string()]);
+        assert_eq!(
+            cmd.invoking_command_topic(),
+            Some("te/device/main///cmd/make_it/456")
+        );
+
+        let schema = MqttSchema::default();
+        let sub_cmd = GenericCommandState::fan_out_sub_command_init_state(
+            &schema,
+            &EntityTopicId::default_main_device(),
+            OperationType::Custom("make_it".to_string()),
+            "456".to_string(),
+            "do_it".to_string(),
+            3,
+        );
+        assert_eq!(sub_cmd.topic, topic);
+        assert_eq!(
+            sub_cmd.invoking_command_topic(),
+            Some("te/device/main///cmd/make_it/456")
+        );
+    }
+
     #[test]
     fn parse_empty_payload() {
         let topic = Topic::new_unchecked("te/device/main///cmd/make_it/123");
@@ -145,6 +145,7 @@ impl WorkflowSupervisor {
             payload: "".to_string().into(),
             qos: QoS::AtLeastOnce,
             retain: true,
+            properties: Default::default(),
         }
     }
 
@@ -221,6 +222,16 @@ impl WorkflowSupervisor {
             .and_then(|workflow| workflow.get_action(command_state))
     }
 
+    /// Return the retry policy attached to the current state of a command, if any
+    pub fn get_retry_policy(&self, command_state: &GenericCommandState) -> Option<&RetryPolicy> {
+        let operation_name = command_state.operation()?;
+        let version = command_state.workflow_version()?;
+        self.workflows
+            .get(&operation_name.as_str().into())
+            .and_then(|versions| versions.get(version).ok())
+            .and_then(|workflow| workflow.get_retry_policy(command_state))
+    }
+
     /// Return the current state of a command (identified by its topic)
     pub fn get_state(&self, command: &str) -> Option<&GenericCommandState> {
         self.commands.get_state(command).map(|(_, state)| state)
@@ -272,6 +283,15 @@ impl WorkflowSupervisor {
             .lookup_sub_command(command_state.command_topic())
     }
 
+    /// Return all the sub commands of a command, as launched by a fan-out
+    pub fn sub_command_states(
+        &self,
+        command_state: &GenericCommandState,
+    ) -> Vec<&GenericCommandState> {
+        self.commands
+            .lookup_sub_commands(command_state.command_topic())
+    }
+
     /// Return the state of the root command which execution leads to the execution of a leaf-command
     ///
     /// Return None, if the given command is not a sub-command
@@ -488,6 +508,15 @@ impl CommandBoard {
             .map(|(_, command)| command)
     }
 
+    /// Return all the sub commands of a command
+    pub fn lookup_sub_commands(&self, command_topic: &TopicName) -> Vec<&GenericCommandState> {
+        self.commands
+            .values()
+            .filter(|(_, command)| command.invoking_command_topic() == Some(command_topic))
+            .map(|(_, command)| command)
+            .collect()
+    }
+
     /// Iterate over the pending commands
     pub fn iter(&self) -> impl Iterator<Item = &(Timestamp, GenericCommandState)> {
         self.commands.values()
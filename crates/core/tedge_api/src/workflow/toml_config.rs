@@ -5,11 +5,13 @@ use crate::workflow::AwaitHandlers;
 use crate::workflow::DefaultHandlers;
 use crate::workflow::ExecHandlers;
 use crate::workflow::ExitHandlers;
+use crate::workflow::FanOut;
 use crate::workflow::GenericCommandState;
 use crate::workflow::GenericStateUpdate;
 use crate::workflow::IterateHandlers;
 use crate::workflow::OperationAction;
 use crate::workflow::OperationWorkflow;
+use crate::workflow::RetryPolicy;
 use crate::workflow::ScriptDefinitionError;
 use crate::workflow::WorkflowDefinitionError;
 use serde::de::Error;
@@ -22,6 +24,8 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Write;
+use std::num::NonZeroU32;
+use std::num::NonZeroUsize;
 use std::num::ParseIntError;
 use std::str::FromStr;
 use std::time::Duration;
@@ -65,6 +69,48 @@ pub struct TomlOperationState {
     /// Values to be extracted from the sub-operation final state
     #[serde(default)]
     pub output: Option<Value>,
+
+    /// Sub-operation to be launched for each item of an iteration
+    #[serde(default)]
+    pub fan_out: Option<String>,
+
+    /// Maximum number of sub-operations launched concurrently by a fan-out
+    #[serde(default)]
+    pub max_parallelism: Option<NonZeroUsize>,
+
+    /// How the action is retried on failure
+    #[serde(default)]
+    pub retry: Option<TomlRetryPolicy>,
+}
+
+/// User-friendly representation of a [RetryPolicy]
+///
+/// ```toml
+/// retry = { max_attempts = 3, backoff = 10, on_exhausted = "failed" }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TomlRetryPolicy {
+    /// The maximum number of attempts, including the first one
+    pub max_attempts: NonZeroU32,
+
+    /// The delay in seconds before the first retry, doubled on each subsequent retry
+    #[serde(default)]
+    pub backoff: u64,
+
+    /// The state to move to when all the attempts failed
+    #[serde(default)]
+    pub on_exhausted: Option<TomlStateUpdate>,
+}
+
+impl From<TomlRetryPolicy> for RetryPolicy {
+    fn from(value: TomlRetryPolicy) -> Self {
+        RetryPolicy {
+            max_attempts: value.max_attempts,
+            backoff: Duration::from_secs(value.backoff),
+            on_exhausted: value.on_exhausted.map(|u| u.into()),
+        }
+    }
 }
 
 /// User-friendly representation of an [OperationAction]
@@ -73,6 +119,7 @@ pub struct TomlOperationState {
 pub enum TomlOperationAction {
     Script(ShellScript),
     BackgroundScript(ShellScript),
+    Javascript(String),
     Action(String),
     Operation(String),
     Iterate(String),
@@ -132,6 +179,10 @@ impl TryFrom<(TomlOperationState, DefaultHandlers)> for OperationAction {
                 let handlers = ExecHandlers::try_from((input.handlers, defaults))?;
                 Ok(OperationAction::BgScript(script, handlers))
             }
+            TomlOperationAction::Javascript(module) => {
+                let handlers = ExitHandlers::try_from((input.handlers, defaults))?;
+                Ok(OperationAction::JavaScript(module, handlers))
+            }
             TomlOperationAction::Operation(operation) => match operation.strip_prefix("builtin:") {
                 None => {
                     let handlers = ExecHandlers::try_from((input.handlers, defaults))?;
@@ -153,13 +204,28 @@ impl TryFrom<(TomlOperationState, DefaultHandlers)> for OperationAction {
                 }
             },
             TomlOperationAction::Iterate(target_json_path) => {
-                let handlers = IterateHandlers::try_from((input.handlers, defaults))?;
                 let Some(json_path) = GenericCommandState::extract_path(&target_json_path) else {
                     return Err(WorkflowDefinitionError::InvalidPathExpression(
                         target_json_path,
                     ));
                 };
-                Ok(OperationAction::Iterate(json_path.to_string(), handlers))
+                match input.fan_out {
+                    None => {
+                        let handlers = IterateHandlers::try_from((input.handlers, defaults))?;
+                        Ok(OperationAction::Iterate(json_path.to_string(), handlers))
+                    }
+                    Some(operation) => {
+                        let handlers = AwaitHandlers::try_from((input.handlers, defaults))?;
+                        let fan_out = FanOut {
+                            target: json_path.to_string(),
+                            operation,
+                            max_parallelism: input.max_parallelism,
+                            input: input.input.try_into()?,
+                            output: input.output.try_into()?,
+                        };
+                        Ok(OperationAction::FanOut(fan_out, handlers))
+                    }
+                }
             }
             TomlOperationAction::Action(command) => match command.as_str() {
                 "cleanup" => Ok(OperationAction::Clear),
@@ -234,12 +300,17 @@ impl TryFrom<TomlOperationWorkflow> for OperationWorkflow {
         let operation = input.operation;
         let default_handlers = DefaultHandlers::try_from(input.handlers)?;
         let mut states = HashMap::new();
+        let mut retry_policies = HashMap::new();
         for (state, action_spec) in input.states.into_iter() {
+            if let Some(retry) = action_spec.retry.clone() {
+                retry_policies.insert(state.clone(), retry.into());
+            }
             let action = OperationAction::try_from((action_spec, default_handlers.clone()))?;
             states.insert(state, action);
         }
 
-        OperationWorkflow::try_new(operation, default_handlers, states)
+        OperationWorkflow::try_new(operation, default_handlers, states)?
+            .with_retry_policies(retry_policies)
     }
 }
 
@@ -808,6 +879,143 @@ action = "cleanup"
         }
     }
 
+    #[test]
+    fn parse_javascript_toml() {
+        let file = r#"
+operation = "custom_operation"
+
+[init]
+javascript = "/etc/tedge/operations/${.payload.step}.js"
+on_success = "successful"
+on_error = "failed"
+"#;
+        let input: TomlOperationWorkflow = toml::from_str(file).unwrap();
+        let workflow = OperationWorkflow::try_from(input).unwrap();
+
+        match workflow.states.get("init").unwrap() {
+            OperationAction::JavaScript(module, handlers) => {
+                assert_eq!(module, "/etc/tedge/operations/${.payload.step}.js");
+                assert_eq!(
+                    handlers.state_update_on_javascript_result(module, Ok(json!({}))),
+                    json!({"status": "successful"})
+                );
+                assert_eq!(
+                    handlers.state_update_on_javascript_result(module, Err("oops".to_string())),
+                    json!({"status": "failed", "reason": "oops"})
+                );
+            }
+            other => panic!("Expected javascript action, but got {other}"),
+        }
+    }
+
+    #[test]
+    fn parse_fan_out_toml() {
+        let file = r#"
+operation = "custom_operation"
+
+[init]
+iterate = "${.payload.children}"
+fan_out = "firmware_update"
+max_parallelism = 4
+input.url = "${.payload.@item.url}"
+on_success = "successful"
+on_error = "failed"
+"#;
+        let input: TomlOperationWorkflow = toml::from_str(file).unwrap();
+        let workflow = OperationWorkflow::try_from(input).unwrap();
+
+        match workflow.states.get("init").unwrap() {
+            OperationAction::FanOut(fan_out, handlers) => {
+                assert_eq!(fan_out.target, ".payload.children");
+                assert_eq!(fan_out.operation, "firmware_update");
+                assert_eq!(fan_out.max_parallelism, NonZeroUsize::new(4));
+                assert_eq!(
+                    fan_out.input,
+                    StateExcerpt::ExcerptMap(HashMap::from([(
+                        "url".to_string(),
+                        StateExcerpt::PathExpr(".payload.@item.url".to_string())
+                    )]))
+                );
+                assert_eq!(handlers.on_success, "successful".into());
+                assert_eq!(handlers.on_error, "failed".into());
+            }
+            other => panic!("Expected fan-out action, but got {other}"),
+        }
+    }
+
+    #[test]
+    fn fan_out_parse_fails_with_zero_parallelism() {
+        let file = r#"
+operation = "custom_operation"
+
+[init]
+iterate = "${.payload.children}"
+fan_out = "firmware_update"
+max_parallelism = 0
+on_success = "successful"
+"#;
+        assert!(toml::from_str::<TomlOperationWorkflow>(file).is_err());
+    }
+
+    #[test]
+    fn parse_retry_policy() {
+        let file = r#"
+operation = "custom_operation"
+
+[init]
+script = "/some/script/which/may/fail"
+retry = { max_attempts = 3, backoff = 5, on_exhausted = { status = "failed", reason = "too many attempts" } }
+on_success = "successful"
+on_error = "failed"
+"#;
+        let input: TomlOperationWorkflow = toml::from_str(file).unwrap();
+        let workflow = OperationWorkflow::try_from(input).unwrap();
+
+        assert_eq!(
+            workflow.retry_policies.get("init"),
+            Some(&RetryPolicy {
+                max_attempts: NonZeroU32::new(3).unwrap(),
+                backoff: Duration::from_secs(5),
+                on_exhausted: Some(GenericStateUpdate::failed("too many attempts".to_string())),
+            })
+        );
+    }
+
+    #[test]
+    fn reject_retry_policy_on_actions_that_cannot_fail() {
+        let file = r#"
+operation = "custom_operation"
+
+[init]
+action = "proceed"
+retry = { max_attempts = 3 }
+on_success = "successful"
+"#;
+        let input: TomlOperationWorkflow = toml::from_str(file).unwrap();
+        let error = OperationWorkflow::try_from(input).unwrap_err();
+        assert_matches!(error, WorkflowDefinitionError::InvalidRetryPolicy { state, .. } if state == "init");
+    }
+
+    #[test]
+    fn reject_retry_policy_on_await_steps() {
+        let file = r#"
+operation = "custom_operation"
+
+[init]
+operation = "sub_operation"
+retry = { max_attempts = 3 }
+on_exec = "awaiting"
+
+[awaiting]
+action = "await-operation-completion"
+retry = { max_attempts = 3 }
+on_success = "successful"
+"#;
+        let input: TomlOperationWorkflow = toml::from_str(file).unwrap();
+        let error = OperationWorkflow::try_from(input).unwrap_err();
+        assert_matches!(error, WorkflowDefinitionError::InvalidRetryPolicy { state, .. } if state == "awaiting");
+    }
+
     #[test]
     fn iterate_parse_fails_without_on_next() {
         let file = r#"
@@ -0,0 +1,358 @@
+use crate::mqtt_topics::OperationType;
+use crate::workflow::AwaitHandlers;
+use crate::workflow::ExitHandlers;
+use crate::workflow::GenericStateUpdate;
+use crate::workflow::OperationAction;
+use crate::workflow::OperationStep;
+use crate::workflow::OperationWorkflow;
+use crate::workflow::StateName;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+
+/// Issue detected by the static analysis of a workflow
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum WorkflowIssue {
+    #[error("The {state} state moves to an undefined state: {target}")]
+    UndefinedTarget { state: StateName, target: StateName },
+
+    #[error("The {state} state cannot be reached from the init state")]
+    UnreachableState { state: StateName },
+
+    #[error("Unknown builtin operation on {state} state: builtin:{operation}")]
+    UnknownBuiltinOperation { state: StateName, operation: String },
+
+    #[error("Unknown builtin action on {state} state: builtin:{operation}:{step}")]
+    UnknownBuiltinStep {
+        state: StateName,
+        operation: String,
+        step: OperationStep,
+    },
+}
+
+/// The states to which a command can move from a given state
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct NextStates {
+    /// The states statically defined by the workflow
+    pub states: BTreeSet<StateName>,
+
+    /// Set when the next state is determined at runtime, say from a script output
+    pub dynamic: bool,
+}
+
+impl NextStates {
+    fn add(&mut self, update: &GenericStateUpdate) {
+        if update.status.contains("${") {
+            self.dynamic = true;
+        } else {
+            self.states.insert(update.status.clone());
+        }
+    }
+
+    fn add_status(&mut self, status: &str) {
+        self.add(&GenericStateUpdate {
+            status: status.to_string(),
+            reason: None,
+        })
+    }
+
+    fn add_await_handlers(&mut self, handlers: &AwaitHandlers) {
+        self.add(&handlers.on_success);
+        self.add(&handlers.on_error);
+        self.add(&handlers.on_timeout);
+    }
+
+    /// Add the states targeted by exit handlers
+    ///
+    /// When no `on_success` handler is provided, the next state on success is:
+    /// - either the `default_on_success` state provided by the action,
+    /// - or determined by the action output, possibly restricted to the `on_stdout` states.
+    fn add_exit_handlers(&mut self, handlers: &ExitHandlers, default_on_success: Option<&str>) {
+        match (handlers.success_state(), default_on_success) {
+            (Some(update), _) => self.add(update),
+            (None, Some(status)) => self.add_status(status),
+            (None, None) if handlers.stdout_states().is_empty() => self.dynamic = true,
+            (None, None) => {
+                for status in handlers.stdout_states() {
+                    self.add_status(status)
+                }
+            }
+        }
+        for update in handlers.failure_states() {
+            self.add(&update)
+        }
+    }
+}
+
+impl OperationAction {
+    /// Return the states to which a command can move from a state ruled by this action
+    pub fn next_states(&self) -> NextStates {
+        let mut next = NextStates::default();
+        match self {
+            OperationAction::Clear => {}
+            OperationAction::MoveTo(update) => next.add(update),
+            OperationAction::BuiltIn(exec_handlers, await_handlers) => {
+                next.add(&exec_handlers.on_exec);
+                next.add_await_handlers(await_handlers);
+            }
+            OperationAction::BuiltInOperation(_, handlers)
+            | OperationAction::RestartAgent(handlers)
+            | OperationAction::BgScript(_, handlers)
+            | OperationAction::Operation(_, _, _, handlers) => next.add(&handlers.on_exec),
+            OperationAction::AwaitingAgentRestart(handlers)
+            | OperationAction::AwaitOperationCompletion(handlers, _)
+            | OperationAction::FanOut(_, handlers) => next.add_await_handlers(handlers),
+            OperationAction::Script(_, handlers) | OperationAction::JavaScript(_, handlers) => {
+                next.add_exit_handlers(handlers, None)
+            }
+            OperationAction::Download(_, handlers)
+            | OperationAction::Upload(_, handlers)
+            | OperationAction::BuiltInOperationStep(_, _, _, handlers) => {
+                next.add_exit_handlers(handlers, Some("successful"))
+            }
+            OperationAction::Iterate(_, handlers) => {
+                next.add(&handlers.on_next);
+                next.add(&handlers.on_success);
+                next.add(&handlers.on_error);
+            }
+        }
+        next
+    }
+}
+
+impl OperationWorkflow {
+    /// Check this workflow for issues that are not detected when the workflow is parsed
+    ///
+    /// - All the states targeted by a state handler must be defined.
+    /// - All the states must be reachable from the `init` state.
+    ///   This cannot be checked when some states are dynamically determined, say by a script.
+    /// - All the `builtin:<operation>` operations and `builtin` actions must refer to one of the `builtin_operations`.
+    /// - All the `builtin:<operation>:<step>` actions must be in the list of the `builtin_steps`.
+    pub fn validate(
+        &self,
+        builtin_operations: &[OperationType],
+        builtin_steps: &[(OperationType, OperationStep)],
+    ) -> Vec<WorkflowIssue> {
+        let mut issues = Vec::new();
+        let states: BTreeSet<&StateName> = self.states.keys().collect();
+
+        for state in states.iter() {
+            let action = &self.states[*state];
+            match action {
+                OperationAction::BuiltInOperationStep(operation, step, _, _) => {
+                    let builtin_step = (OperationType::from(operation.as_str()), step.clone());
+                    if !builtin_steps.contains(&builtin_step) {
+                        issues.push(WorkflowIssue::UnknownBuiltinStep {
+                            state: state.to_string(),
+                            operation: operation.clone(),
+                            step: step.clone(),
+                        })
+                    }
+                }
+                OperationAction::BuiltInOperation(operation, _) => {
+                    if !builtin_operations.contains(&OperationType::from(operation.as_str())) {
+                        issues.push(WorkflowIssue::UnknownBuiltinOperation {
+                            state: state.to_string(),
+                            operation: operation.clone(),
+                        })
+                    }
+                }
+                OperationAction::BuiltIn(_, _) => {
+                    if !builtin_operations.contains(&self.operation) {
+                        issues.push(WorkflowIssue::UnknownBuiltinOperation {
+                            state: state.to_string(),
+                            operation: self.operation.to_string(),
+                        })
+                    }
+                }
+                _ => {}
+            }
+            for target in action.next_states().states {
+                if !self.states.contains_key(&target) {
+                    issues.push(WorkflowIssue::UndefinedTarget {
+                        state: state.to_string(),
+                        target,
+                    })
+                }
+            }
+        }
+
+        let (reachable, dynamic) = self.reachable_states();
+        if !dynamic {
+            for state in states {
+                let terminal = self.states[state] == OperationAction::Clear;
+                if !terminal && !reachable.contains(state) {
+                    issues.push(WorkflowIssue::UnreachableState {
+                        state: state.to_string(),
+                    })
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Return the states reachable from the `init` state
+    ///
+    /// along with a flag telling if some of these states move to a dynamically determined state.
+    fn reachable_states(&self) -> (BTreeSet<StateName>, bool) {
+        let mut reachable = BTreeSet::new();
+        let mut dynamic = false;
+        let mut pending = VecDeque::from(["init".to_string()]);
+        while let Some(state) = pending.pop_front() {
+            let Some(action) = self.states.get(&state) else {
+                continue;
+            };
+            if !reachable.insert(state) {
+                continue;
+            }
+            let next = action.next_states();
+            dynamic |= next.dynamic;
+            pending.extend(next.states);
+        }
+        (reachable, dynamic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin_operations() -> Vec<OperationType> {
+        vec![OperationType::ConfigUpdate, OperationType::Restart]
+    }
+
+    fn builtin_steps() -> Vec<(OperationType, OperationStep)> {
+        vec![(OperationType::ConfigUpdate, "set".to_string())]
+    }
+
+    #[test]
+    fn a_well_formed_workflow_has_no_issues() {
+        let file = r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "download"
+
+[download]
+action = "download"
+input.url = "${.payload.remoteUrl}"
+on_success = "set"
+
+[set]
+action = "builtin:config_update:set"
+on_success = "successful"
+on_error = "failed"
+"#;
+        let workflow: OperationWorkflow = toml::from_str(file).unwrap();
+        assert_eq!(
+            workflow.validate(&builtin_operations(), &builtin_steps()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn detect_undefined_targets() {
+        let file = r#"
+operation = "infinite"
+
+[init]
+action = "proceed"
+on_success = "scheduled"
+
+[scheduled]
+script = "/some/script.sh"
+on_success = "executing"
+on_error = "crashed"
+"#;
+        let workflow: OperationWorkflow = toml::from_str(file).unwrap();
+        assert_eq!(
+            workflow.validate(&builtin_operations(), &builtin_steps()),
+            vec![
+                WorkflowIssue::UndefinedTarget {
+                    state: "scheduled".to_string(),
+                    target: "crashed".to_string(),
+                },
+                WorkflowIssue::UndefinedTarget {
+                    state: "scheduled".to_string(),
+                    target: "executing".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn detect_unreachable_states_and_unknown_builtin_steps() {
+        let file = r#"
+operation = "config_update"
+
+[init]
+action = "proceed"
+on_success = "successful"
+
+[orphan]
+action = "builtin:config_update:apply"
+on_success = "successful"
+"#;
+        let workflow: OperationWorkflow = toml::from_str(file).unwrap();
+        assert_eq!(
+            workflow.validate(&builtin_operations(), &builtin_steps()),
+            vec![
+                WorkflowIssue::UnknownBuiltinStep {
+                    state: "orphan".to_string(),
+                    operation: "config_update".to_string(),
+                    step: "apply".to_string(),
+                },
+                WorkflowIssue::UnreachableState {
+                    state: "orphan".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reachability_is_not_checked_when_next_states_are_dynamic() {
+        let file = r#"
+operation = "dynamic"
+
+[init]
+script = "/some/script.sh"
+
+[executing]
+action = "proceed"
+on_success = "successful"
+"#;
+        let workflow: OperationWorkflow = toml::from_str(file).unwrap();
+        assert_eq!(
+            workflow.validate(&builtin_operations(), &builtin_steps()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn detect_unknown_builtin_operations() {
+        let file = r#"
+operation = "firmware_upgrade"
+
+[init]
+action = "proceed"
+on_success = "scheduled"
+
+[scheduled]
+operation = "builtin:firmware_upgrade"
+on_exec = "executing"
+
+[executing]
+action = "await-operation-completion"
+on_success = "successful"
+"#;
+        let workflow: OperationWorkflow = toml::from_str(file).unwrap();
+        assert_eq!(
+            workflow.validate(&builtin_operations(), &builtin_steps()),
+            vec![WorkflowIssue::UnknownBuiltinOperation {
+                state: "scheduled".to_string(),
+                operation: "firmware_upgrade".to_string(),
+            }]
+        );
+    }
+}
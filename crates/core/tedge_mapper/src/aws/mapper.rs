@@ -54,10 +54,17 @@ impl TEdgeComponent for AwsMapper {
         let (mut runtime, mut mqtt_actor) =
             start_basic_actors(&aws_mapper_name, &tedge_config, exposed_config).await?;
         let mqtt_schema = MqttSchema::with_root(tedge_config.mqtt.topic_root.clone());
+        let device_topic_id = tedge_config.mqtt.device_topic_id.clone();
+        let bridge_service_name = if tedge_config.mqtt.bridge.built_in {
+            format!("tedge-mapper-bridge-{prefix}")
+        } else {
+            format!("mosquitto-{prefix}-bridge")
+        };
+        let bridge_health_topic =
+            service_health_topic(&mqtt_schema, &device_topic_id, &bridge_service_name);
 
         if tedge_config.mqtt.bridge.built_in {
             let device_id = aws_config.device.id()?;
-            let device_topic_id = tedge_config.mqtt.device_topic_id.clone();
 
             let rules = bridge_rules(&tedge_config, self.profile.as_ref()).await?;
 
@@ -76,13 +83,10 @@ impl TEdgeComponent for AwsMapper {
 
             configure_proxy(&tedge_config, &mut cloud_config)?;
 
-            let bridge_name = format!("tedge-mapper-bridge-{prefix}");
-            let health_topic = service_health_topic(&mqtt_schema, &device_topic_id, &bridge_name);
-
             let bridge_actor = MqttBridgeActorBuilder::new(
                 &tedge_config,
-                &bridge_name,
-                &health_topic,
+                &bridge_service_name,
+                &bridge_health_topic,
                 rules,
                 cloud_config,
                 None,
@@ -100,11 +104,12 @@ impl TEdgeComponent for AwsMapper {
             prefix.value().clone(),
             aws_config.mapper.mqtt.max_payload_size.0,
             aws_config.topics.to_string(),
+            bridge_health_topic,
         );
         let mapper_dir = self.mapper_dir(config_dir);
         let mut flows = crate::mapper_flow_registry(&tedge_config, &mapper_dir).await?;
         aws_converter.persist_builtin_flow(&mut flows).await?;
-        let service_config = flows_config(&tedge_config, &aws_mapper_name)?;
+        let service_config = flows_config(&tedge_config, &aws_mapper_name).await?;
 
         let mut fs_actor = FsWatchActorBuilder::new();
         let mut cmd_watcher_actor = WatchActorBuilder::new();
@@ -114,10 +119,10 @@ impl TEdgeComponent for AwsMapper {
         flows_mapper.connect_fs(&mut fs_actor);
         flows_mapper.connect_cmd(&mut cmd_watcher_actor);
 
-        runtime.spawn(flows_mapper).await?;
+        runtime.spawn_critical(flows_mapper).await?;
         runtime.spawn(fs_actor).await?;
         runtime.spawn(cmd_watcher_actor).await?;
-        runtime.spawn(mqtt_actor).await?;
+        runtime.spawn_critical(mqtt_actor).await?;
 
         Ok(runtime)
     }
@@ -239,6 +244,13 @@ mod tests {
             "$aws/things/test-device-id/shadow/#"
         ));
 
+        // Jobs (bidirectional)
+        assert!(has_local_subscription(&rules, "aws/jobs/#"));
+        assert!(has_remote_subscription(
+            &rules,
+            "$aws/things/test-device-id/jobs/#"
+        ));
+
         // Connection check
         assert!(has_local_subscription(&rules, "aws/test-connection"));
         assert!(has_remote_subscription(
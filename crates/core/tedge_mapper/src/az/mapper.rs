@@ -54,10 +54,16 @@ impl TEdgeComponent for AzureMapper {
         let (mut runtime, mut mqtt_actor) =
             start_basic_actors(&az_mapper_name, &tedge_config, exposed_config).await?;
         let mqtt_schema = MqttSchema::with_root(tedge_config.mqtt.topic_root.clone());
+        let device_topic_id = tedge_config.mqtt.device_topic_id.clone();
+        let bridge_service_name = if tedge_config.mqtt.bridge.built_in {
+            format!("tedge-mapper-bridge-{prefix}")
+        } else {
+            format!("mosquitto-{prefix}-bridge")
+        };
+        let bridge_health_topic =
+            service_health_topic(&mqtt_schema, &device_topic_id, &bridge_service_name);
 
         if tedge_config.mqtt.bridge.built_in {
-            let device_topic_id = tedge_config.mqtt.device_topic_id.clone();
-
             let remote_clientid = az_config.device.id()?;
             let rules = bridge_rules(&tedge_config, self.profile.as_ref()).await?;
 
@@ -83,14 +89,10 @@ impl TEdgeComponent for AzureMapper {
 
             configure_proxy(&tedge_config, &mut cloud_config)?;
 
-            let built_in_bridge_name = format!("tedge-mapper-bridge-{prefix}");
-            let health_topic =
-                service_health_topic(&mqtt_schema, &device_topic_id, &built_in_bridge_name);
-
             let bridge_actor = MqttBridgeActorBuilder::new(
                 &tedge_config,
-                &built_in_bridge_name,
-                &health_topic,
+                &bridge_service_name,
+                &bridge_health_topic,
                 rules,
                 cloud_config,
                 None,
@@ -108,11 +110,12 @@ impl TEdgeComponent for AzureMapper {
             prefix,
             az_config.mapper.mqtt.max_payload_size.0,
             az_config.topics.to_string(),
+            bridge_health_topic,
         );
         let mapper_dir = self.mapper_dir(config_dir);
         let mut flows = crate::mapper_flow_registry(&tedge_config, &mapper_dir).await?;
         az_converter.persist_builtin_flow(&mut flows).await?;
-        let service_config = flows_config(&tedge_config, &az_mapper_name)?;
+        let service_config = flows_config(&tedge_config, &az_mapper_name).await?;
         let mut fs_actor = FsWatchActorBuilder::new();
         let mut cmd_watcher_actor = WatchActorBuilder::new();
 
@@ -121,10 +124,10 @@ impl TEdgeComponent for AzureMapper {
         flows_mapper.connect_fs(&mut fs_actor);
         flows_mapper.connect_cmd(&mut cmd_watcher_actor);
 
-        runtime.spawn(flows_mapper).await?;
+        runtime.spawn_critical(flows_mapper).await?;
         runtime.spawn(fs_actor).await?;
         runtime.spawn(cmd_watcher_actor).await?;
-        runtime.spawn(mqtt_actor).await?;
+        runtime.spawn_critical(mqtt_actor).await?;
 
         Ok(runtime)
     }
@@ -245,8 +248,15 @@ mod tests {
 
         // Digital twin
         assert!(has_local_subscription(&rules, "az/twin/GET/#"));
-        assert!(has_local_subscription(&rules, "az/twin/PATCH/#"));
+        assert!(has_local_subscription(
+            &rules,
+            "az/twin/PATCH/properties/reported/#"
+        ));
         assert!(has_remote_subscription(&rules, "$iothub/twin/res/#"));
+        assert!(has_remote_subscription(
+            &rules,
+            "$iothub/twin/PATCH/properties/desired/#"
+        ));
     }
 
     #[tokio::test]
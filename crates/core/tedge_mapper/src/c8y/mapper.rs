@@ -173,7 +173,7 @@ impl TEdgeComponent for CumulocityMapper {
         let mapper_dir = self.mapper_dir(cfg_dir);
         let mut flows = crate::mapper_flow_registry(&tedge_config, &mapper_dir).await?;
         c8y_mapper_actor.persist_builtin_flows(&mut flows).await?;
-        let service_config = flows_config(&tedge_config, &c8y_mapper_name)?;
+        let service_config = flows_config(&tedge_config, &c8y_mapper_name).await?;
 
         let mut flows_mapper = FlowsMapperBuilder::try_new(flows, service_config).await?;
         flows_mapper.connect(&mut mqtt_actor);
@@ -181,14 +181,14 @@ impl TEdgeComponent for CumulocityMapper {
         flows_mapper.connect_cmd(&mut cmd_watcher_actor);
         c8y_mapper_actor.set_flow_context(flows_mapper.context_handle());
 
-        runtime.spawn(flows_mapper).await?;
+        runtime.spawn_critical(flows_mapper).await?;
         runtime.spawn(cmd_watcher_actor).await?;
-        runtime.spawn(mqtt_actor).await?;
+        runtime.spawn_critical(mqtt_actor).await?;
         runtime.spawn(http_actor).await?;
         runtime.spawn(c8y_auth_proxy_actor).await?;
         runtime.spawn(fs_watch_actor).await?;
         runtime.spawn(timer_actor).await?;
-        runtime.spawn(c8y_mapper_actor).await?;
+        runtime.spawn_critical(c8y_mapper_actor).await?;
         runtime.spawn(service_monitor_actor).await?;
         runtime.spawn(uploader_actor).await?;
         runtime.spawn(downloader_actor).await?;
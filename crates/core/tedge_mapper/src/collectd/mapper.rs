@@ -50,9 +50,9 @@ impl TEdgeComponent for CollectdMapper {
             collectd_ext::converter::batch_into_mqtt_messages(&output_topic, batch)
         });
 
-        runtime.spawn(collectd_actor).await?;
+        runtime.spawn_critical(collectd_actor).await?;
         runtime.spawn(batching_actor).await?;
-        runtime.spawn(mqtt_actor).await?;
+        runtime.spawn_critical(mqtt_actor).await?;
 
         Ok(runtime)
     }
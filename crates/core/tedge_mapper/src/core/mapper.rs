@@ -41,7 +41,8 @@ pub async fn start_basic_actors(
         &mut mqtt_actor,
         &mqtt_schema,
         &config.service,
-    );
+    )
+    .with_runtime(runtime.get_handle());
 
     let config_publisher = ConfigPublisherBuilder::new(
         mqtt_schema,
@@ -297,7 +297,9 @@ async fn build_flows_actors(
         stats_config.on_message,
         stats_config.on_interval,
         stats_config.on_startup,
-    );
+    )
+    .with_context_dir(crate::flows_context_dir(tedge_config, service_name))
+    .with_http_config(crate::flows_http_config(tedge_config));
 
     let flows = crate::mapper_flow_registry(tedge_config, mapper_dir).await?;
     let fs_actor = FsWatchActorBuilder::new();
@@ -387,11 +389,11 @@ impl TEdgeComponent for CustomMapper {
         flows_mapper.connect_fs(&mut fs_actor);
         flows_mapper.connect_cmd(&mut cmd_watcher_actor);
 
-        runtime.spawn(flows_mapper).await?;
+        runtime.spawn_critical(flows_mapper).await?;
         runtime.spawn(fs_actor).await?;
         runtime.spawn(cmd_watcher_actor).await?;
 
-        runtime.spawn(mqtt_actor).await?;
+        runtime.spawn_critical(mqtt_actor).await?;
 
         Ok(runtime)
     }
@@ -10,6 +10,7 @@ use crate::custom::mapper::CustomMapper;
 use anyhow::bail;
 use anyhow::Context;
 use camino::Utf8Path;
+use camino::Utf8PathBuf;
 use clap::Parser;
 use flockfile::check_another_instance_is_not_running;
 use flockfile::Flockfile;
@@ -18,6 +19,7 @@ use futures::FutureExt;
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::Arc;
 use tedge_actors::Runtime;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_config::cli::CommonArgs;
@@ -28,6 +30,7 @@ use tedge_flows::BaseFlowRegistry;
 use tedge_flows::ConnectedFlowRegistry;
 use tedge_flows::FlowRegistryExt;
 use tedge_flows::FlowsMapperConfig;
+use tedge_flows::HttpConfigLoader;
 use tedge_flows::UpdateFlowRegistryError;
 use tedge_supervisor::Supervisor;
 use tedge_supervisor::UnitKind;
@@ -388,7 +391,7 @@ pub fn mapper_dir(
     config_dir.dir(format!("mappers/{profiled_name}")).unwrap()
 }
 
-pub(crate) fn flows_config(
+pub(crate) async fn flows_config(
     tedge_config: &TEdgeConfig,
     mapper_name: &str,
 ) -> Result<FlowsMapperConfig, anyhow::Error> {
@@ -407,10 +410,39 @@ pub(crate) fn flows_config(
     .with_js_config(
         mem_config.heap_size as usize,
         mem_config.stack_size as usize,
-    );
+    )
+    .with_context_dir(flows_context_dir(tedge_config, mapper_name))
+    .with_http_config(flows_http_config(tedge_config));
     Ok(flows_config)
 }
 
+/// Load the root certificates, proxy and client identity used by the HTTP outputs of the flows
+///
+/// These are only loaded when a message is first sent to an HTTP output,
+/// so a mapper not using HTTP outputs doesn't fail on invalid certificates.
+pub(crate) fn flows_http_config(tedge_config: &TEdgeConfig) -> HttpConfigLoader {
+    let config_dir = tedge_config.root_dir().to_owned();
+    Arc::new(move || {
+        let config_dir = config_dir.clone();
+        async move {
+            let tedge_config = TEdgeConfig::load(&config_dir).await?;
+            let http_config = tedge_config.cloud_root_certs().await?;
+            let identity = tedge_config.http.client.auth.identity()?;
+            Ok((http_config, identity))
+        }
+        .boxed()
+    })
+}
+
+/// The directory where a mapper persists the flow context entries selected by its flows
+pub(crate) fn flows_context_dir(tedge_config: &TEdgeConfig, mapper_name: &str) -> Utf8PathBuf {
+    tedge_config
+        .data_root()
+        .root()
+        .join("flows")
+        .join(mapper_name)
+}
+
 fn load_builtin_transformers(flows: &mut impl FlowRegistryExt) {
     #[cfg(feature = "c8y")]
     c8y_mapper_ext::load_builtin_transformers(flows);
@@ -10,6 +10,7 @@ use mqtt_channel::Topic;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
 use std::process;
 use std::process::Command;
 use std::process::ExitStatus;
@@ -49,6 +50,27 @@ struct HealthStatusExt {
     /// Used for tracking service restarts
     pub pid: Option<u32>,
     pub time: Option<JsonValue>,
+    /// Liveness of the actors of the service, if reported
+    #[serde(default)]
+    pub actors: BTreeMap<String, ActorHealthStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ActorHealthStatus {
+    pub status: String,
+    #[serde(default)]
+    pub critical: bool,
+}
+
+impl HealthStatusExt {
+    /// The critical actors of the service that have stalled
+    fn stalled_critical_actors(&self) -> Vec<&str> {
+        self.actors
+            .iter()
+            .filter(|(_, actor)| actor.critical && actor.status == "stalled")
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
 }
 
 pub async fn start_watchdog(tedge_config: TEdgeConfig) -> Result<(), anyhow::Error> {
@@ -216,7 +238,14 @@ async fn monitor_tedge_service(
         {
             Ok(health_status) => {
                 let health_status = health_status?;
-                if let Some(pid) = health_status.pid {
+                let stalled_actors = health_status.stalled_critical_actors();
+                if !stalled_actors.is_empty() {
+                    // Letting systemd restart the service
+                    error!(
+                        "Not notifying systemd for {name}: critical actors have stalled: {}",
+                        stalled_actors.join(", ")
+                    )
+                } else if let Some(pid) = health_status.pid {
                     debug!("Sending notification for {} with pid: {}", name, pid);
                     notify_systemd(pid, "WATCHDOG=1")?;
                 } else {
@@ -384,6 +413,30 @@ mod tests {
         assert_eq!(health_status.unwrap().time, Some(payload_timestamp));
     }
 
+    #[test]
+    fn detect_stalled_critical_actors() {
+        let health_status: HealthStatusExt = serde_json::from_value(json!({
+            "status": "degraded",
+            "pid": 123u32,
+            "time": 1702650663,
+            "actors": {
+                "MqttActor-0": {"status": "stalled", "pending": 10, "critical": true},
+                "Uploader-1": {"status": "stalled", "pending": 2, "critical": false},
+                "C8yMapper-2": {"status": "up", "pending": 0, "critical": true},
+            }
+        }))
+        .unwrap();
+        assert_eq!(health_status.stalled_critical_actors(), vec!["MqttActor-0"]);
+
+        let health_status: HealthStatusExt = serde_json::from_value(json!({
+            "status": "up",
+            "pid": 123u32,
+            "time": 1702650663,
+        }))
+        .unwrap();
+        assert!(health_status.stalled_critical_actors().is_empty());
+    }
+
     fn parse_timestamp(timestamp: &str) -> OffsetDateTime {
         #[expect(
             clippy::disallowed_methods,
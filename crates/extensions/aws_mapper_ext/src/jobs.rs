@@ -0,0 +1,330 @@
+//! Translation of AWS IoT Jobs into thin-edge commands
+//!
+//! The pending job executions of the thing, as notified on `jobs/notify-next`,
+//! are turned into commands published on `te/<entity>/cmd/<operation>/aws-<job-id>`.
+//! The job document gives the operation, the target entity (the main device by default)
+//! and the command specific properties:
+//!
+//! ```json
+//! {
+//!     "operation": "software_update",
+//!     "target": "device/child//",
+//!     "updateList": [ ... ]
+//! }
+//! ```
+//!
+//! The progress of these commands is reported back on `jobs/<job-id>/update`,
+//! and the operations supported by each entity are published as the `supported_operations`
+//! reported state of the entity shadow.
+//!
+//! The jobs in progress are tracked using the retained commands, hence are not lost on restart.
+//! On startup, a job for an operation not advertised yet is deferred until the capabilities of the entities
+//! have been received, failing only if the operation is still not supported after [CAPABILITIES_TIMEOUT].
+//!
+//! Ref: https://docs.aws.amazon.com/iot/latest/developerguide/jobs-mqtt-api.html
+use crate::shadow::shadow_topic;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::SystemTime;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_flows::CloudCommands;
+use tedge_flows::CommandMessage;
+use tedge_flows::ConfigError;
+use tedge_flows::FlowContextHandle;
+use tedge_flows::FlowError;
+use tedge_flows::JsonValue;
+use tedge_flows::Message;
+
+/// Prefix of the ids of the commands created for AWS jobs
+///
+/// Disjoint from the prefix of the commands created for the shadow desired states (`aws-shadow-`).
+const COMMAND_ID_PREFIX: &str = "aws-job-";
+
+/// How long after startup a job for an operation not advertised yet is deferred
+/// rather than rejected, giving time to the retained capabilities to be received
+const CAPABILITIES_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct AwsJobs {
+    prefix: String,
+
+    /// The jobs for which a command has been created but not completed yet
+    pending_jobs: HashSet<String>,
+
+    /// The jobs received on startup for operations not advertised yet, with their job documents
+    deferred_jobs: BTreeMap<String, Value>,
+    started_at: Option<SystemTime>,
+
+    commands: CloudCommands,
+}
+
+impl Default for AwsJobs {
+    fn default() -> Self {
+        AwsJobs {
+            prefix: "aws".to_string(),
+            pending_jobs: HashSet::new(),
+            deferred_jobs: BTreeMap::new(),
+            started_at: None,
+            commands: CloudCommands::new(COMMAND_ID_PREFIX),
+        }
+    }
+}
+
+impl tedge_flows::Transformer for AwsJobs {
+    fn name(&self) -> &str {
+        "aws-jobs"
+    }
+
+    fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError> {
+        let prefix = config.string_property("prefix").unwrap_or("aws");
+        let topic_root = config.string_property("topic_root").unwrap_or("te");
+        self.prefix = prefix.to_owned();
+        self.commands.set_topic_root(topic_root);
+        Ok(())
+    }
+
+    fn on_message(
+        &mut self,
+        timestamp: SystemTime,
+        message: &Message,
+        _context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        if let Some(jobs_topic) = message
+            .topic
+            .strip_prefix(&self.prefix)
+            .and_then(|topic| topic.strip_prefix("/jobs/"))
+        {
+            return match jobs_topic {
+                "notify-next" | "$next/get/accepted" => self.on_job_execution(timestamp, message),
+                _ => Ok(vec![]),
+            };
+        }
+
+        match self.commands.on_message(message) {
+            Some(CommandMessage::CapabilityUpdate { entity }) => {
+                let mut messages = self.advertise_operations(&entity)?;
+                messages.extend(self.start_deferred_jobs(false));
+                Ok(messages)
+            }
+            Some(CommandMessage::StatusUpdate {
+                entity,
+                operation,
+                request_id,
+                ..
+            }) => self.on_command_status(&entity, operation, request_id, message),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn is_periodic(&self) -> bool {
+        true
+    }
+
+    fn on_interval(
+        &mut self,
+        timestamp: SystemTime,
+        _context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        if self.awaiting_capabilities(timestamp) {
+            return Ok(vec![]);
+        }
+        Ok(self.start_deferred_jobs(true))
+    }
+
+    fn has_startup(&self) -> bool {
+        true
+    }
+
+    fn on_startup(
+        &mut self,
+        timestamp: SystemTime,
+        _context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        self.started_at = Some(timestamp);
+
+        // Request the next pending job execution, in case it has been queued while the mapper was down
+        let prefix = &self.prefix;
+        Ok(vec![Message::new(format!("{prefix}/jobs/$next/get"), "{}")])
+    }
+}
+
+impl AwsJobs {
+    /// Check if the capabilities of the entities might not have been all received yet
+    fn awaiting_capabilities(&self, timestamp: SystemTime) -> bool {
+        self.started_at
+            .is_some_and(|started_at| timestamp < started_at + CAPABILITIES_TIMEOUT)
+    }
+
+    /// Create the commands of the deferred jobs which operations are now supported
+    ///
+    /// Once the capabilities have been received, all the deferred jobs are processed,
+    /// those for unsupported operations being rejected.
+    fn start_deferred_jobs(&mut self, capabilities_received: bool) -> Vec<Message> {
+        let ready: Vec<String> = self
+            .deferred_jobs
+            .iter()
+            .filter(|(_, document)| capabilities_received || self.is_supported(document))
+            .map(|(job_id, _)| job_id.clone())
+            .collect();
+        ready
+            .into_iter()
+            .filter_map(|job_id| {
+                let document = self.deferred_jobs.remove(&job_id)?;
+                Some(self.start_job(&job_id, &document))
+            })
+            .collect()
+    }
+
+    /// Create a command for a pending job execution
+    fn on_job_execution(
+        &mut self,
+        timestamp: SystemTime,
+        message: &Message,
+    ) -> Result<Vec<Message>, FlowError> {
+        let notification: Value = serde_json::from_slice(&message.payload).map_err(|err| {
+            FlowError::UnsupportedMessage(format!("Invalid job execution: {err}"))
+        })?;
+
+        // No execution means there is no pending job
+        let Some(execution) = notification.get("execution") else {
+            return Ok(vec![]);
+        };
+        let Some(job_id) = execution.get("jobId").and_then(Value::as_str) else {
+            return Ok(vec![]);
+        };
+
+        // Only queued jobs are started, the jobs in progress being already handled by a command
+        let status = execution.get("status").and_then(Value::as_str);
+        if status != Some("QUEUED") || self.pending_jobs.contains(job_id) {
+            return Ok(vec![]);
+        }
+
+        let document = execution.get("jobDocument").unwrap_or(&Value::Null);
+        if self.awaiting_capabilities(timestamp) && !self.is_supported(document) {
+            self.deferred_jobs
+                .insert(job_id.to_string(), document.clone());
+            return Ok(vec![]);
+        }
+        Ok(vec![self.start_job(job_id, document)])
+    }
+
+    /// Create the command requested by a job, or reject the job
+    fn start_job(&mut self, job_id: &str, document: &Value) -> Message {
+        match self.job_command(job_id, document) {
+            Ok(command) => {
+                self.pending_jobs.insert(job_id.to_string());
+                command
+            }
+            Err(reason) => self.job_update(job_id, "FAILED", json!({ "reason": reason })),
+        }
+    }
+
+    /// Check if the operation requested by a job document is supported by its target
+    fn is_supported(&self, document: &Value) -> bool {
+        let Some(operation) = document.get("operation").and_then(Value::as_str) else {
+            return false;
+        };
+        let main_device = EntityTopicId::default_main_device();
+        let target = document
+            .get("target")
+            .and_then(Value::as_str)
+            .unwrap_or(main_device.as_str());
+        self.commands.is_supported(target, operation)
+    }
+
+    /// Build the command requested by a job document
+    fn job_command(&self, job_id: &str, document: &Value) -> Result<Message, String> {
+        let Some(document) = document.as_object() else {
+            return Err("The job document is not a JSON object".to_string());
+        };
+        let Some(operation) = document.get("operation").and_then(Value::as_str) else {
+            return Err("No operation is given by the job document".to_string());
+        };
+        let main_device = EntityTopicId::default_main_device();
+        let target = document
+            .get("target")
+            .and_then(Value::as_str)
+            .unwrap_or(main_device.as_str());
+
+        if !self.commands.is_supported(target, operation) {
+            return Err(format!("{operation} is not supported by {target}"));
+        }
+
+        let payload: Map<String, Value> = document
+            .iter()
+            .filter(|(key, _)| *key != "operation" && *key != "target")
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Ok(self
+            .commands
+            .init_command(target, operation, job_id, payload))
+    }
+
+    /// Advertise the operations supported by an entity on its shadow
+    fn advertise_operations(&self, entity: &str) -> Result<Vec<Message>, FlowError> {
+        let operations = self.commands.operations(entity);
+        let payload = json!({
+            "state": {
+                "reported": {
+                    "supported_operations": operations
+                }
+            }
+        });
+        let topic = shadow_topic(&self.prefix, entity, "update")?;
+        Ok(vec![Message::new(topic, payload.to_string())])
+    }
+
+    /// Report the progress of a command to the job it has been created for
+    fn on_command_status(
+        &mut self,
+        entity: &str,
+        operation: &str,
+        job_id: &str,
+        message: &Message,
+    ) -> Result<Vec<Message>, FlowError> {
+        let command: Value = serde_json::from_slice(&message.payload).map_err(|err| {
+            FlowError::UnsupportedMessage(format!("Invalid {operation} command: {err}"))
+        })?;
+        let status = command.get("status").and_then(Value::as_str).unwrap_or("");
+
+        // The retained commands are received again on restart, rebuilding the set of pending jobs
+        self.pending_jobs.insert(job_id.to_string());
+
+        let (job_status, details) = match status {
+            "executing" => ("IN_PROGRESS", json!({})),
+            "successful" => ("SUCCEEDED", json!({})),
+            "failed" => {
+                let reason = command
+                    .get("reason")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Unknown reason");
+                ("FAILED", json!({ "reason": reason }))
+            }
+            _ => return Ok(vec![]),
+        };
+
+        let mut messages = vec![self.job_update(job_id, job_status, details)];
+        if job_status != "IN_PROGRESS" {
+            // The command is completed and can be cleared
+            self.pending_jobs.remove(job_id);
+            messages.push(self.commands.clear_command(entity, operation, job_id));
+        }
+        Ok(messages)
+    }
+
+    fn job_update(&self, job_id: &str, status: &str, details: Value) -> Message {
+        let prefix = &self.prefix;
+        let payload = json!({
+            "status": status,
+            "statusDetails": details,
+        });
+        Message::new(
+            format!("{prefix}/jobs/{job_id}/update"),
+            payload.to_string(),
+        )
+    }
+}
@@ -1,3 +1,9 @@
+mod jobs;
+mod shadow;
+
+pub use jobs::AwsJobs;
+pub use shadow::ShadowDeltaToTwin;
+pub use shadow::TwinToShadow;
 use std::time::SystemTime;
 use tedge_api::mqtt_topics::MqttSchema;
 use tedge_config::models::TopicPrefix;
@@ -15,7 +21,9 @@ use tedge_utils::timestamp::TimeFormat;
 pub struct AwsConverter {
     input_topics: String,
     topic_prefix: TopicPrefix,
+    topic_root: String,
     errors_topic: Topic,
+    bridge_health_topic: Topic,
     size_threshold: usize,
     add_timestamp: bool,
     time_format: TimeFormat,
@@ -29,13 +37,16 @@ impl AwsConverter {
         topic_prefix: TopicPrefix,
         max_payload_size: u32,
         input_topics: String,
+        bridge_health_topic: Topic,
     ) -> Self {
         let errors_topic = mqtt_schema.error_topic();
         let size_threshold = max_payload_size as usize;
         AwsConverter {
             input_topics,
             topic_prefix,
+            topic_root: mqtt_schema.root.clone(),
             errors_topic,
+            bridge_health_topic,
             size_threshold,
             add_timestamp,
             time_format,
@@ -48,6 +59,18 @@ impl AwsConverter {
     ) -> Result<(), UpdateFlowRegistryError> {
         flows
             .persist_builtin_flow("mea", self.builtin_flow().as_str())
+            .await?;
+
+        flows
+            .persist_builtin_flow("twin", self.twin_flow().as_str())
+            .await?;
+
+        flows
+            .persist_builtin_flow("shadow", self.shadow_flow().as_str())
+            .await?;
+
+        flows
+            .persist_builtin_flow("jobs", self.jobs_flow().as_str())
             .await
     }
 
@@ -82,6 +105,75 @@ errors.mqtt.topic = "{errors_topic}"
             version = env!("CARGO_PKG_VERSION"),
         )
     }
+
+    /// The flow publishing the twin data of the entities to their device shadows
+    fn twin_flow(&self) -> String {
+        format!(
+            r#"version = "{version}"
+
+input.mqtt.topics = ["{topic_root}/+/+/+/+/twin/+"]
+
+steps = [
+    {{ builtin = "twin-to-aws-shadow", config = {{ prefix = "{topic_prefix}" }} }},
+]
+
+errors.mqtt.topic = "{errors_topic}"
+"#,
+            topic_root = self.topic_root,
+            topic_prefix = self.topic_prefix,
+            errors_topic = self.errors_topic,
+            version = env!("CARGO_PKG_VERSION"),
+        )
+    }
+
+    /// The flow applying the desired state of the device shadows to the entities
+    ///
+    /// The shadows of the registered entities are requested each time the bridge connects.
+    fn shadow_flow(&self) -> String {
+        format!(
+            r#"version = "{version}"
+
+input.mqtt.topics = ["{topic_prefix}/shadow/update/delta", "{topic_prefix}/shadow/name/+/update/delta", "{topic_prefix}/shadow/get/accepted", "{topic_prefix}/shadow/name/+/get/accepted", "{topic_root}/+/+/+/+", "{topic_root}/+/+/+/+/cmd/config_update/+", "{bridge_health_topic}"]
+
+# The commands created by this flow are also consumed by this flow to track their progress
+expect_loop = true
+
+steps = [
+    {{ builtin = "aws-shadow-delta", config = {{ prefix = "{topic_prefix}", topic_root = "{topic_root}", bridge_health_topic = "{bridge_health_topic}" }} }},
+]
+
+errors.mqtt.topic = "{errors_topic}"
+"#,
+            topic_root = self.topic_root,
+            topic_prefix = self.topic_prefix,
+            bridge_health_topic = self.bridge_health_topic,
+            errors_topic = self.errors_topic,
+            version = env!("CARGO_PKG_VERSION"),
+        )
+    }
+
+    /// The flow translating the AWS jobs into commands and reporting their progress
+    fn jobs_flow(&self) -> String {
+        format!(
+            r#"version = "{version}"
+
+input.mqtt.topics = ["{topic_prefix}/jobs/notify-next", "{topic_prefix}/jobs/$next/get/accepted", "{topic_root}/+/+/+/+/cmd/+", "{topic_root}/+/+/+/+/cmd/+/+"]
+
+# The commands created by this flow are also consumed by this flow to track their progress
+expect_loop = true
+
+steps = [
+    {{ builtin = "aws-jobs", interval = "1s", config = {{ prefix = "{topic_prefix}", topic_root = "{topic_root}" }} }},
+]
+
+errors.mqtt.topic = "{errors_topic}"
+"#,
+            topic_root = self.topic_root,
+            topic_prefix = self.topic_prefix,
+            errors_topic = self.errors_topic,
+            version = env!("CARGO_PKG_VERSION"),
+        )
+    }
 }
 
 // We need to reduce the number of levels in the topic because AWS IoT only supports topics with 7
@@ -145,6 +237,9 @@ impl tedge_flows::Transformer for SetAwsTopic {
 
 pub fn load_builtin_transformers(flows: &mut impl FlowRegistryExt) {
     flows.register_builtin(SetAwsTopic::default());
+    flows.register_builtin(TwinToShadow::default());
+    flows.register_builtin(ShadowDeltaToTwin::default());
+    flows.register_builtin(AwsJobs::default());
 }
 
 #[cfg(test)]
@@ -154,7 +249,11 @@ mod tests {
     use assert_matches::*;
     use camino::Utf8PathBuf;
     use serde_json::json;
+    use std::collections::BTreeMap;
     use std::collections::HashMap;
+    use std::collections::VecDeque;
+    use std::time::Duration;
+    use std::time::Instant;
     use tedge_config::tedge_toml::AWS_MQTT_PAYLOAD_LIMIT;
     use tedge_flows::ConnectedFlowRegistry;
     use tedge_flows::FlowResult;
@@ -163,6 +262,8 @@ mod tests {
     use tedge_mqtt_ext::MqttMessage;
     use tedge_utils::paths::TedgePaths;
     use time::macros::datetime;
+    static BRIDGE_HEALTH_TOPIC: &str =
+        "te/device/main/service/tedge-mapper-bridge-aws/status/health";
     static TE_MEA_TOPICS: &str =
         r#"["te/+/+/+/+/m/+", "te/+/+/+/+/e/+", "te/+/+/+/+/a/+", "te/+/+/+/+/status/health"]"#;
 
@@ -744,6 +845,576 @@ mod tests {
         assert_eq!(res[0], expected_msg);
     }
 
+    #[tokio::test]
+    async fn main_device_twin_is_reported_to_the_classic_shadow() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///twin/maintenance"),
+            r#"{"mode":"on"}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, "aws/shadow/update");
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({"state": {"reported": {"maintenance": {"mode": "on"}}}})
+        );
+    }
+
+    #[tokio::test]
+    async fn child_device_twin_is_reported_to_a_named_shadow() {
+        let mut converter = create_test_converter(false, None, Some("custom-prefix")).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("te/device/child/service/app/twin/firmware"),
+            r#"{"version":"1.2"}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(
+            output[0].topic.name,
+            "custom-prefix/shadow/name/device:child:service:app/update"
+        );
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({"state": {"reported": {"firmware": {"version": "1.2"}}}})
+        );
+    }
+
+    #[tokio::test]
+    async fn cleared_twin_is_removed_from_the_shadow() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("te/device/child///twin/maintenance"),
+            "",
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert_eq!(output[0].topic.name, "aws/shadow/name/device:child/update");
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({"state": {"reported": {"maintenance": null}}})
+        );
+    }
+
+    #[tokio::test]
+    async fn shadow_delta_is_applied_to_the_twin() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/shadow/update/delta"),
+            r#"{"version":12,"timestamp":1702029646,"state":{"maintenance":{"mode":"off"},"location":null}}"#,
+        );
+        let mut output = converter.convert(&input).await.unwrap();
+        output.sort_by(|a, b| a.topic.name.cmp(&b.topic.name));
+
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].topic.name, "te/device/main///twin/location");
+        assert!(output[0].payload_bytes().is_empty());
+        assert_eq!(output[1].topic.name, "te/device/main///twin/maintenance");
+        assert_eq!(output[1].payload_str().unwrap(), r#"{"mode":"off"}"#);
+        assert!(output.iter().all(|message| message.retain));
+    }
+
+    #[tokio::test]
+    async fn named_shadow_delta_is_applied_to_the_entity_twin() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/shadow/name/device:child:service:app/update/delta"),
+            r#"{"version":3,"state":{"log_level":"debug"}}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(
+            output[0].topic.name,
+            "te/device/child/service/app/twin/log_level"
+        );
+        assert_eq!(output[0].payload_str().unwrap(), r#""debug""#);
+    }
+
+    #[tokio::test]
+    async fn shadows_are_requested_when_the_bridge_connects() {
+        let mut converter = create_test_converter(false, None, None).await;
+        let mut broker = LocalBroker::default();
+        let health = |status: &str| {
+            MqttMessage::new(
+                &Topic::new_unchecked(BRIDGE_HEALTH_TOPIC),
+                json!({"status": status}).to_string(),
+            )
+        };
+
+        for registration in ["te/device/main//", "te/device/child//"] {
+            broker
+                .publish(
+                    &mut converter,
+                    MqttMessage::new(
+                        &Topic::new_unchecked(registration),
+                        r#"{"@type":"child-device"}"#,
+                    )
+                    .with_retain(),
+                )
+                .await;
+        }
+        assert!(broker.published_on("aws/shadow/get").is_empty());
+
+        broker.publish(&mut converter, health("down")).await;
+        assert!(broker.published_on("aws/shadow/get").is_empty());
+
+        broker.publish(&mut converter, health("up")).await;
+        assert_eq!(broker.published_on("aws/shadow/get").len(), 1);
+        assert_eq!(
+            broker
+                .published_on("aws/shadow/name/device:child/get")
+                .len(),
+            1
+        );
+
+        // The entities registered once connected are requested on registration
+        broker
+            .publish(
+                &mut converter,
+                MqttMessage::new(
+                    &Topic::new_unchecked("te/device/other//"),
+                    r#"{"@type":"child-device"}"#,
+                )
+                .with_retain(),
+            )
+            .await;
+        assert_eq!(
+            broker
+                .published_on("aws/shadow/name/device:other/get")
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn pending_deltas_of_the_requested_shadows_are_applied() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/shadow/name/device:child/get/accepted"),
+            r#"{
+                "version": 9,
+                "state": {
+                    "desired": {"log_level": "debug", "maintenance": "on"},
+                    "reported": {"log_level": "info", "maintenance": "on"},
+                    "delta": {"log_level": "debug"}
+                }
+            }"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, "te/device/child///twin/log_level");
+        assert_eq!(output[0].payload_str().unwrap(), r#""debug""#);
+        assert!(output[0].retain);
+    }
+
+    #[tokio::test]
+    async fn config_update_in_progress_is_not_created_again_on_connection() {
+        let mut converter = create_test_converter(false, None, None).await;
+        let desired =
+            r#"{"config_update":{"type":"mosquitto","url":"https://example.com/mosquitto.conf"}}"#;
+
+        let executing = MqttMessage::new(
+            &Topic::new_unchecked("te/device/child///cmd/config_update/aws-shadow-7"),
+            r#"{"status":"executing","type":"mosquitto","remoteUrl":"https://example.com/mosquitto.conf"}"#,
+        )
+        .with_retain();
+        converter.convert(&executing).await.unwrap();
+
+        let shadow = MqttMessage::new(
+            &Topic::new_unchecked("aws/shadow/name/device:child/get/accepted"),
+            format!(r#"{{"version":8,"state":{{"desired":{desired},"delta":{desired}}}}}"#),
+        );
+        let output = converter.convert(&shadow).await.unwrap();
+        assert!(output.is_empty(), "{output:?}");
+    }
+
+    #[tokio::test]
+    async fn desired_config_update_is_turned_into_a_command() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/shadow/name/device:child/update/delta"),
+            r#"{"version":7,"state":{"config_update":{"type":"mosquitto","url":"https://example.com/mosquitto.conf"}}}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        // The desired state is reported only once the command has been executed
+        assert_eq!(output.len(), 1);
+        assert_eq!(
+            output[0].topic.name,
+            "te/device/child///cmd/config_update/aws-shadow-7"
+        );
+        assert!(output[0].retain);
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({
+                "status": "init",
+                "type": "mosquitto",
+                "remoteUrl": "https://example.com/mosquitto.conf",
+                "serverUrl": "https://example.com/mosquitto.conf",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn config_update_is_reported_to_the_shadow_once_successful() {
+        let mut converter = create_test_converter(false, None, None).await;
+        let mut broker = LocalBroker::default();
+        let command_topic = "te/device/child///cmd/config_update/aws-shadow-7";
+
+        broker
+            .publish(
+                &mut converter,
+                MqttMessage::new(
+                    &Topic::new_unchecked("aws/shadow/name/device:child/update/delta"),
+                    r#"{"version":7,"state":{"config_update":{"type":"mosquitto","url":"https://example.com/mosquitto.conf"}}}"#,
+                ),
+            )
+            .await;
+        assert!(broker.retained.contains_key(command_topic));
+        assert!(broker
+            .published_on("aws/shadow/name/device:child/update")
+            .is_empty());
+
+        broker
+            .publish(
+                &mut converter,
+                MqttMessage::new(
+                    &Topic::new_unchecked(command_topic),
+                    r#"{"status":"successful","type":"mosquitto","remoteUrl":"https://example.com/mosquitto.conf"}"#,
+                )
+                .with_retain(),
+            )
+            .await;
+        assert!(!broker.retained.contains_key(command_topic));
+        let reported = broker.published_on("aws/shadow/name/device:child/update");
+        assert_eq!(reported.len(), 1);
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(reported[0].payload_str().unwrap()).unwrap(),
+            json!({"state": {"reported": {"config_update": {
+                "type": "mosquitto",
+                "url": "https://example.com/mosquitto.conf",
+            }}}})
+        );
+
+        // The command is not mistaken for a job
+        assert!(broker.published_on("aws/jobs/shadow-7/update").is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_config_update_is_cleared_without_being_reported() {
+        let mut converter = create_test_converter(false, None, None).await;
+        let mut broker = LocalBroker::default();
+        let command_topic = "te/device/main///cmd/config_update/aws-shadow-3";
+
+        broker
+            .publish(
+                &mut converter,
+                MqttMessage::new(
+                    &Topic::new_unchecked("aws/shadow/update/delta"),
+                    r#"{"version":3,"state":{"config_update":{"type":"mosquitto","url":"https://example.com/mosquitto.conf"}}}"#,
+                ),
+            )
+            .await;
+        assert!(broker.retained.contains_key(command_topic));
+
+        broker
+            .publish(
+                &mut converter,
+                MqttMessage::new(
+                    &Topic::new_unchecked(command_topic),
+                    r#"{"status":"failed","reason":"Download failed"}"#,
+                )
+                .with_retain(),
+            )
+            .await;
+        assert!(!broker.retained.contains_key(command_topic));
+        assert!(broker.published_on("aws/shadow/update").is_empty());
+    }
+
+    #[tokio::test]
+    async fn entities_without_valid_shadow_names_are_not_synchronized() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("te/device/child.1///twin/maintenance"),
+            r#"{"mode":"on"}"#,
+        );
+        let result = converter.convert(&input).await;
+        assert_matches!(result, Err(FlowError::UnsupportedMessage(_)));
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/shadow/name/device:child$1/update/delta"),
+            r#"{"version":1,"state":{"log_level":"debug"}}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[tokio::test]
+    async fn desired_states_that_are_not_valid_topic_levels_are_ignored() {
+        let mut converter = create_test_converter(false, None, None).await;
+        let mut broker = LocalBroker::default();
+
+        broker
+            .publish(
+                &mut converter,
+                MqttMessage::new(
+                    &Topic::new_unchecked("aws/shadow/update/delta"),
+                    r##"{"version":5,"state":{"log_level":"debug","cmd/restart/x":{},"#":1,"a+b":2}}"##,
+                ),
+            )
+            .await;
+
+        let retained: Vec<&String> = broker.retained.keys().collect();
+        assert_eq!(retained, vec!["te/device/main///twin/log_level"]);
+    }
+
+    #[tokio::test]
+    async fn delta_of_shadows_not_named_after_an_entity_is_ignored() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/shadow/name/settings/update/delta"),
+            r#"{"version":1,"state":{"log_level":"debug"}}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert!(output.is_empty());
+    }
+
+    #[tokio::test]
+    async fn supported_operations_are_reported_to_the_entity_shadow() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        converter
+            .convert(&capability("te/device/child///cmd/restart"))
+            .await
+            .unwrap();
+        let output = converter
+            .convert(&capability("te/device/child///cmd/software_update"))
+            .await
+            .unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, "aws/shadow/name/device:child/update");
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({"state": {"reported": {"supported_operations": ["restart", "software_update"]}}})
+        );
+    }
+
+    #[tokio::test]
+    async fn queued_job_is_turned_into_a_command() {
+        let mut converter = create_test_converter(false, None, None).await;
+        converter
+            .convert(&capability("te/device/child///cmd/software_update"))
+            .await
+            .unwrap();
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/jobs/notify-next"),
+            json!({
+                "timestamp": 1702029646,
+                "execution": {
+                    "jobId": "42",
+                    "status": "QUEUED",
+                    "jobDocument": {
+                        "operation": "software_update",
+                        "target": "device/child//",
+                        "updateList": [{"type": "apt", "modules": [{"name": "nodered", "action": "install"}]}]
+                    }
+                }
+            })
+            .to_string(),
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(
+            output[0].topic.name,
+            "te/device/child///cmd/software_update/aws-job-42"
+        );
+        assert!(output[0].retain);
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({
+                "status": "init",
+                "updateList": [{"type": "apt", "modules": [{"name": "nodered", "action": "install"}]}]
+            })
+        );
+
+        // The same job is not started twice
+        let output = converter.convert(&input).await.unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[tokio::test]
+    async fn job_for_an_unsupported_operation_is_rejected() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/jobs/$next/get/accepted"),
+            r#"{"execution":{"jobId":"job-1","status":"QUEUED","jobDocument":{"operation":"restart"}}}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, "aws/jobs/job-1/update");
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({
+                "status": "FAILED",
+                "statusDetails": {"reason": "restart is not supported by device/main//"}
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn command_progress_is_reported_to_the_job() {
+        let mut converter = create_test_converter(false, None, None).await;
+        let command_topic = Topic::new_unchecked("te/device/main///cmd/restart/aws-job-7");
+
+        let executing = MqttMessage::new(&command_topic, r#"{"status":"executing"}"#);
+        let output = converter.convert(&executing).await.unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, "aws/jobs/7/update");
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({"status": "IN_PROGRESS", "statusDetails": {}})
+        );
+
+        let failed = MqttMessage::new(
+            &command_topic,
+            r#"{"status":"failed","reason":"Restart timed out"}"#,
+        );
+        let output = converter.convert(&failed).await.unwrap();
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].topic.name, "aws/jobs/7/update");
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({"status": "FAILED", "statusDetails": {"reason": "Restart timed out"}})
+        );
+        assert_eq!(output[1].topic.name, command_topic.name);
+        assert!(output[1].payload_bytes().is_empty());
+        assert!(output[1].retain);
+    }
+
+    #[tokio::test]
+    async fn jobs_and_shadow_commands_are_not_mixed_up() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        // A job which id starts with `shadow-`
+        let command_topic = Topic::new_unchecked("te/device/main///cmd/restart/aws-job-shadow-7");
+        let executing = MqttMessage::new(&command_topic, r#"{"status":"executing"}"#);
+        let output = converter.convert(&executing).await.unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, "aws/jobs/shadow-7/update");
+
+        // A command created for a shadow desired state
+        let shadow_command = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/config_update/aws-shadow-7"),
+            r#"{"status":"executing"}"#,
+        );
+        let output = converter.convert(&shadow_command).await.unwrap();
+        assert!(output.is_empty(), "{output:?}");
+    }
+
+    #[tokio::test]
+    async fn jobs_in_progress_are_not_started_again_after_a_restart() {
+        let mut converter = create_test_converter(false, None, None).await;
+        converter
+            .convert(&capability("te/device/main///cmd/restart"))
+            .await
+            .unwrap();
+
+        // The retained command created before the restart
+        let command = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/restart/aws-job-7"),
+            r#"{"status":"init"}"#,
+        )
+        .with_retain();
+        let output = converter.convert(&command).await.unwrap();
+        assert!(output.is_empty(), "{output:?}");
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("aws/jobs/$next/get/accepted"),
+            r#"{"execution":{"jobId":"7","status":"QUEUED","jobDocument":{"operation":"restart"}}}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+        assert!(output.is_empty(), "{output:?}");
+    }
+
+    #[tokio::test]
+    async fn jobs_are_deferred_on_startup_until_the_capabilities_are_received() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let output = converter.startup().await;
+        assert!(output
+            .iter()
+            .any(|message| message.topic.name == "aws/jobs/$next/get"));
+
+        for (job_id, operation) in [("1", "restart"), ("2", "reboot")] {
+            let input = MqttMessage::new(
+                &Topic::new_unchecked("aws/jobs/$next/get/accepted"),
+                json!({"execution": {"jobId": job_id, "status": "QUEUED", "jobDocument": {"operation": operation}}})
+                    .to_string(),
+            );
+            let output = converter.convert(&input).await.unwrap();
+            assert!(output.is_empty(), "{output:?}");
+        }
+
+        // The deferred jobs are started as soon as their operations are supported
+        let output = converter
+            .convert(&capability("te/device/main///cmd/restart"))
+            .await
+            .unwrap();
+        assert!(output
+            .iter()
+            .any(|message| message.topic.name == "te/device/main///cmd/restart/aws-job-1"));
+
+        // And rejected if still not supported once the capabilities have been received
+        let output = converter.tick(Duration::from_secs(5)).await;
+        assert!(output.is_empty(), "{output:?}");
+        let output = converter.tick(Duration::from_secs(11)).await;
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, "aws/jobs/2/update");
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            json!({
+                "status": "FAILED",
+                "statusDetails": {"reason": "reboot is not supported by device/main//"}
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn commands_not_created_for_a_job_are_ignored() {
+        let mut converter = create_test_converter(false, None, None).await;
+
+        let input = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/restart/c8y-mapper-1234"),
+            r#"{"status":"successful"}"#,
+        );
+        let output = converter.convert(&input).await.unwrap();
+
+        assert!(output.is_empty());
+    }
+
+    fn capability(topic: &str) -> MqttMessage {
+        MqttMessage::new(&Topic::new_unchecked(topic), "{}").with_retain()
+    }
+
     async fn create_test_converter(
         add_timestamp: bool,
         size_threshold: Option<u32>,
@@ -756,6 +1427,7 @@ mod tests {
             TopicPrefix::try_from(prefix.unwrap_or("aws")).unwrap(),
             size_threshold.unwrap_or(AWS_MQTT_PAYLOAD_LIMIT),
             TE_MEA_TOPICS.to_string(),
+            Topic::new_unchecked(BRIDGE_HEALTH_TOPIC),
         );
         let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
         let flows_dir = Utf8PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
@@ -774,6 +1446,40 @@ mod tests {
         }
     }
 
+    /// A local stand-in for the MQTT broker
+    ///
+    /// Keep the retained messages and dispatch the messages published by the flows back to the flows.
+    #[derive(Default)]
+    struct LocalBroker {
+        retained: BTreeMap<String, MqttMessage>,
+        published: Vec<MqttMessage>,
+    }
+
+    impl LocalBroker {
+        async fn publish(&mut self, converter: &mut AwsFlows, message: MqttMessage) {
+            let mut pending = VecDeque::from([message]);
+            while let Some(message) = pending.pop_front() {
+                if message.retain {
+                    if message.payload_bytes().is_empty() {
+                        self.retained.remove(&message.topic.name);
+                    } else {
+                        self.retained
+                            .insert(message.topic.name.clone(), message.clone());
+                    }
+                }
+                pending.extend(converter.convert(&message).await.unwrap());
+                self.published.push(message);
+            }
+        }
+
+        fn published_on(&self, topic: &str) -> Vec<&MqttMessage> {
+            self.published
+                .iter()
+                .filter(|message| message.topic.name == topic)
+                .collect()
+        }
+    }
+
     struct AwsFlows {
         runtime: MessageProcessor<ConnectedFlowRegistry>,
         _flows_dir: tempfile::TempDir,
@@ -784,6 +1490,22 @@ mod tests {
             self.try_convert(input).await
         }
 
+        async fn startup(&mut self) -> Vec<MqttMessage> {
+            let now = SystemTime::from(datetime!(2021-04-07 19:00:00 +00));
+            let results = self.runtime.on_startup(now).await;
+            Self::published_messages(results).unwrap()
+        }
+
+        /// Trigger the periodic steps, some time after the startup
+        async fn tick(&mut self, elapsed: Duration) -> Vec<MqttMessage> {
+            let now = SystemTime::from(datetime!(2021-04-07 19:00:00 +00)) + elapsed;
+            let results = self
+                .runtime
+                .on_interval(now, Instant::now() + Duration::from_secs(60))
+                .await;
+            Self::published_messages(results).unwrap()
+        }
+
         async fn try_convert(
             &mut self,
             input: &MqttMessage,
@@ -794,7 +1516,10 @@ mod tests {
                 .runtime
                 .on_message(now, &SourceTag::Mqtt, &message)
                 .await;
+            Self::published_messages(results)
+        }
 
+        fn published_messages(results: Vec<FlowResult>) -> Result<Vec<MqttMessage>, FlowError> {
             let mut output = vec![];
             for result in results {
                 match result {
@@ -0,0 +1,412 @@
+//! Synchronization of the thin-edge entity twin data with the AWS IoT Device Shadows
+//!
+//! - The main device is mapped to the classic (unnamed) shadow of the thing.
+//! - The other entities are mapped to named shadows, the shadow names being derived from the entity topic ids,
+//!   e.g. `device:child` for `device/child//` and `device:main:service:nodered` for `device/main/service/nodered`.
+//!
+//! The twin fragments published on `te/<entity>/twin/<fragment>` are forwarded as shadow `reported` state,
+//! while the `desired` state changes received as shadow `delta` documents
+//! are turned into twin updates or `config_update` commands.
+//! A desired `config_update` is reported back to the shadow only once the command has successfully completed.
+//!
+//! Each time the bridge connects, the shadows of the main device and of the registered entities are requested,
+//! and their pending deltas applied, to catch up with the desired states changed while disconnected.
+//!
+//! The entities which topic ids cannot be turned into valid shadow names are not synchronized.
+//!
+//! Ref: https://docs.aws.amazon.com/iot/latest/developerguide/device-shadow-mqtt.html
+use crate::normalize_source_name;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::time::SystemTime;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_flows::ConfigError;
+use tedge_flows::FlowContextHandle;
+use tedge_flows::FlowError;
+use tedge_flows::JsonValue;
+use tedge_flows::Message;
+
+/// The desired state fragment which is turned into a `config_update` command rather than a twin update
+const CONFIG_UPDATE_FRAGMENT: &str = "config_update";
+
+/// Prefix of the ids of the `config_update` commands created for desired states
+const COMMAND_ID_PREFIX: &str = "aws-shadow-";
+
+/// Maximum length of a shadow name
+///
+/// Ref: https://docs.aws.amazon.com/general/latest/gr/iot-core.html#device-shadow-limits
+const MAX_SHADOW_NAME_LENGTH: usize = 64;
+
+/// Publish the twin fragments of the entities as reported state of their AWS shadows
+#[derive(Clone, Default)]
+pub struct TwinToShadow {
+    prefix: String,
+}
+
+impl tedge_flows::Transformer for TwinToShadow {
+    fn name(&self) -> &str {
+        "twin-to-aws-shadow"
+    }
+
+    fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError> {
+        let prefix = config.string_property("prefix").unwrap_or("aws");
+        self.prefix = prefix.to_owned();
+        Ok(())
+    }
+
+    fn on_message(
+        &mut self,
+        _timestamp: SystemTime,
+        message: &Message,
+        _context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        let (entity, fragment) = match message.topic.split('/').collect::<Vec<_>>()[..] {
+            [_, e1, e2, e3, e4, "twin", fragment] if !fragment.is_empty() => {
+                (format!("{e1}/{e2}/{e3}/{e4}"), fragment)
+            }
+            _ => return Ok(vec![]),
+        };
+
+        // An empty payload clears the twin fragment, hence the reported property
+        let value = if message.payload.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&message.payload).map_err(|err| {
+                FlowError::UnsupportedMessage(format!("Invalid twin value for {fragment}: {err}"))
+            })?
+        };
+        let payload = json!({
+            "state": {
+                "reported": {
+                    fragment: value
+                }
+            }
+        });
+
+        let topic = shadow_topic(&self.prefix, &entity, "update")?;
+        Ok(vec![Message::new(topic, payload.to_string())])
+    }
+}
+
+/// Turn the desired state changes of the AWS shadows into twin updates and `config_update` commands
+#[derive(Clone, Default)]
+pub struct ShadowDeltaToTwin {
+    prefix: String,
+    topic_root: String,
+
+    /// The health topic of the bridge, used to request the shadows on connection
+    bridge_health_topic: String,
+    bridge_up: bool,
+
+    /// The registered entities, which named shadows are requested on connection
+    entities: BTreeSet<String>,
+
+    /// The entities with a `config_update` command in progress, not to be created again on connection
+    pending_config_updates: BTreeSet<String>,
+}
+
+impl tedge_flows::Transformer for ShadowDeltaToTwin {
+    fn name(&self) -> &str {
+        "aws-shadow-delta"
+    }
+
+    fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError> {
+        let prefix = config.string_property("prefix").unwrap_or("aws");
+        let topic_root = config.string_property("topic_root").unwrap_or("te");
+        let bridge_health_topic = config.string_property("bridge_health_topic").unwrap_or("");
+        self.prefix = prefix.to_owned();
+        self.topic_root = topic_root.to_owned();
+        self.bridge_health_topic = bridge_health_topic.to_owned();
+        Ok(())
+    }
+
+    fn on_message(
+        &mut self,
+        _timestamp: SystemTime,
+        message: &Message,
+        _context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        if message.topic == self.bridge_health_topic {
+            return Ok(self.on_bridge_health(message));
+        }
+        if let Some((entity, cmd_id)) = self.command_entity(&message.topic) {
+            return self.on_command_status(&entity, cmd_id, message);
+        }
+        if let Some(entity) = self.registered_entity(&message.topic) {
+            return Ok(self.on_entity_registration(entity, message));
+        }
+
+        if let Some(entity) = self.shadow_entity(&message.topic, "update/delta") {
+            let delta: Value = serde_json::from_slice(&message.payload).map_err(|err| {
+                FlowError::UnsupportedMessage(format!("Invalid shadow delta document: {err}"))
+            })?;
+            let state = delta.get("state").unwrap_or(&Value::Null);
+            return self.apply_desired_state(&entity, &delta, state, false);
+        }
+        if let Some(entity) = self.shadow_entity(&message.topic, "get/accepted") {
+            let shadow: Value = serde_json::from_slice(&message.payload).map_err(|err| {
+                FlowError::UnsupportedMessage(format!("Invalid shadow document: {err}"))
+            })?;
+            // Only the desired properties which differ from the reported ones are listed in the delta
+            let state = shadow.pointer("/state/delta").unwrap_or(&Value::Null);
+            return self.apply_desired_state(&entity, &shadow, state, true);
+        }
+        Ok(vec![])
+    }
+}
+
+impl ShadowDeltaToTwin {
+    /// Request the shadows of the main device and of the registered entities when the bridge is up
+    fn on_bridge_health(&mut self, message: &Message) -> Vec<Message> {
+        // The mosquitto bridge publishes `1` while the built-in bridge publishes `{"status":"up"}`
+        let is_up = message.payload == b"1"
+            || serde_json::from_slice::<Value>(&message.payload)
+                .is_ok_and(|health| health.get("status").and_then(Value::as_str) == Some("up"));
+        let connected = is_up && !self.bridge_up;
+        self.bridge_up = is_up;
+        if !connected {
+            return vec![];
+        }
+
+        let main_device = EntityTopicId::default_main_device();
+        std::iter::once(main_device.as_str())
+            .chain(self.entities.iter().map(String::as_str))
+            .filter_map(|entity| self.shadow_request(entity))
+            .collect()
+    }
+
+    /// Track the registered entities, requesting the shadow of a new entity if the bridge is up
+    fn on_entity_registration(&mut self, entity: String, message: &Message) -> Vec<Message> {
+        if message.payload.is_empty() {
+            self.entities.remove(&entity);
+            return vec![];
+        }
+        if entity == EntityTopicId::default_main_device().as_str()
+            || !self.entities.insert(entity.clone())
+            || !self.bridge_up
+        {
+            return vec![];
+        }
+        self.shadow_request(&entity).into_iter().collect()
+    }
+
+    /// The request for the full shadow of an entity, if it can be mapped to a shadow
+    fn shadow_request(&self, entity: &str) -> Option<Message> {
+        let topic = shadow_topic(&self.prefix, entity, "get").ok()?;
+        Some(Message::new(topic, ""))
+    }
+
+    /// Apply the desired state of a shadow, as received in a delta document or a full shadow document
+    ///
+    /// When catching up with a full shadow document, no `config_update` command is created
+    /// for an entity which has already one in progress.
+    fn apply_desired_state(
+        &self,
+        entity: &str,
+        document: &Value,
+        state: &Value,
+        catching_up: bool,
+    ) -> Result<Vec<Message>, FlowError> {
+        let Some(state) = state.as_object() else {
+            return Ok(vec![]);
+        };
+        let version = document.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+        let topic_root = &self.topic_root;
+        let mut messages = vec![];
+        for (fragment, value) in state {
+            if fragment == CONFIG_UPDATE_FRAGMENT {
+                if catching_up && self.pending_config_updates.contains(entity) {
+                    continue;
+                }
+                messages.push(self.config_update_command(entity, version, value)?);
+                continue;
+            }
+            if !is_valid_topic_level(fragment) {
+                continue;
+            }
+
+            let topic = format!("{topic_root}/{entity}/twin/{fragment}");
+            let payload = match value {
+                Value::Null => vec![],
+                value => value.to_string().into_bytes(),
+            };
+            messages.push(Message::new(topic, payload).with_retain());
+        }
+        Ok(messages)
+    }
+
+    /// Extract the entity from the topic of a shadow document, such as `update/delta` or `get/accepted`
+    ///
+    /// Return `None` for the named shadows which are not named after an entity
+    fn shadow_entity(&self, topic: &str, action: &str) -> Option<String> {
+        let shadow = topic.strip_prefix(&self.prefix)?.strip_prefix("/shadow/")?;
+        if shadow == action {
+            return Some(EntityTopicId::default_main_device().to_string());
+        }
+
+        let name = shadow
+            .strip_prefix("name/")?
+            .strip_suffix(action)?
+            .strip_suffix('/')?;
+        if !is_valid_shadow_name(name) {
+            return None;
+        }
+        let mut segments: Vec<&str> = name.split(':').collect();
+        if !(2..=4).contains(&segments.len()) || segments.iter().any(|s| s.is_empty()) {
+            return None;
+        }
+        segments.resize(4, "");
+        Some(segments.join("/"))
+    }
+
+    /// Extract the entity from the topic of an entity registration message
+    fn registered_entity(&self, topic: &str) -> Option<String> {
+        let te_topic = topic
+            .strip_prefix(&self.topic_root)
+            .and_then(|topic| topic.strip_prefix('/'))?;
+        match te_topic.split('/').collect::<Vec<_>>()[..] {
+            [_, _, _, _] => Some(te_topic.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Extract the entity and the command id from the topic of a command created for a desired state
+    fn command_entity<'a>(&self, topic: &'a str) -> Option<(String, &'a str)> {
+        let te_topic = topic
+            .strip_prefix(&self.topic_root)
+            .and_then(|topic| topic.strip_prefix('/'))?;
+        match te_topic.split('/').collect::<Vec<_>>()[..] {
+            [e1, e2, e3, e4, "cmd", CONFIG_UPDATE_FRAGMENT, cmd_id]
+                if cmd_id.starts_with(COMMAND_ID_PREFIX) =>
+            {
+                Some((format!("{e1}/{e2}/{e3}/{e4}"), cmd_id))
+            }
+            _ => None,
+        }
+    }
+
+    /// Clear a completed `config_update` command, reporting the desired state as applied on success
+    ///
+    /// On failure, the desired state is left pending in the shadow.
+    fn on_command_status(
+        &mut self,
+        entity: &str,
+        cmd_id: &str,
+        message: &Message,
+    ) -> Result<Vec<Message>, FlowError> {
+        if message.payload.is_empty() {
+            self.pending_config_updates.remove(entity);
+            return Ok(vec![]);
+        }
+        self.pending_config_updates.insert(entity.to_string());
+        let command: Value = serde_json::from_slice(&message.payload).map_err(|err| {
+            FlowError::UnsupportedMessage(format!(
+                "Invalid {CONFIG_UPDATE_FRAGMENT} command: {err}"
+            ))
+        })?;
+
+        let topic_root = &self.topic_root;
+        let topic = format!("{topic_root}/{entity}/cmd/{CONFIG_UPDATE_FRAGMENT}/{cmd_id}");
+        let clear_command = Message::new(topic, "").with_retain();
+        match command.get("status").and_then(Value::as_str) {
+            Some("successful") => {
+                let applied = json!({
+                    "type": command.get("type"),
+                    "url": command.get("remoteUrl"),
+                });
+                Ok(vec![
+                    clear_command,
+                    self.acknowledge_desired_state(entity, CONFIG_UPDATE_FRAGMENT, &applied)?,
+                ])
+            }
+            Some("failed") => Ok(vec![clear_command]),
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// Build a `config_update` command from a desired state such as `{"type": "mosquitto", "url": "https://..."}`
+    fn config_update_command(
+        &self,
+        entity: &str,
+        version: u64,
+        desired: &Value,
+    ) -> Result<Message, FlowError> {
+        let invalid = |reason: &str| {
+            FlowError::UnsupportedMessage(format!(
+                "Invalid desired {CONFIG_UPDATE_FRAGMENT} for {entity}: {reason}"
+            ))
+        };
+        let config_type = desired
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("missing type"))?;
+        let url = desired
+            .get("url")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("missing url"))?;
+
+        let topic_root = &self.topic_root;
+        let topic = format!(
+            "{topic_root}/{entity}/cmd/{CONFIG_UPDATE_FRAGMENT}/{COMMAND_ID_PREFIX}{version}"
+        );
+        let payload = json!({
+            "status": "init",
+            "type": config_type,
+            "remoteUrl": url,
+            "serverUrl": url,
+        });
+        Ok(Message::new(topic, payload.to_string()).with_retain())
+    }
+
+    /// Report a desired state as handled, so the same delta is not received again
+    fn acknowledge_desired_state(
+        &self,
+        entity: &str,
+        fragment: &str,
+        value: &Value,
+    ) -> Result<Message, FlowError> {
+        let payload = json!({
+            "state": {
+                "reported": {
+                    fragment: value
+                }
+            }
+        });
+        let topic = shadow_topic(&self.prefix, entity, "update")?;
+        Ok(Message::new(topic, payload.to_string()))
+    }
+}
+
+/// The topic of a shadow action for an entity, using the classic shadow for the main device
+///
+/// Fail if the entity topic id cannot be turned into a valid shadow name.
+pub(crate) fn shadow_topic(prefix: &str, entity: &str, action: &str) -> Result<String, FlowError> {
+    if entity == EntityTopicId::default_main_device().as_str() {
+        return Ok(format!("{prefix}/shadow/{action}"));
+    }
+
+    // `normalize_source_name` expects a full topic, the first level being the topic root
+    let shadow_name = normalize_source_name(&format!("te/{entity}"));
+    if !is_valid_shadow_name(&shadow_name) {
+        return Err(FlowError::UnsupportedMessage(format!(
+            "{entity} cannot be mapped to an AWS shadow: invalid shadow name {shadow_name:?}"
+        )));
+    }
+    Ok(format!("{prefix}/shadow/name/{shadow_name}/{action}"))
+}
+
+/// Check that a shadow name is made only of the characters accepted by AWS: `a-zA-Z0-9:_-`
+fn is_valid_shadow_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_SHADOW_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '_' | '-'))
+}
+
+/// Check that a name can be used as an MQTT topic level
+fn is_valid_topic_level(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '+', '#'])
+}
@@ -1,3 +1,7 @@
+mod methods;
+mod twin;
+
+pub use methods::DirectMethods;
 use tedge_api::mqtt_topics::MqttSchema;
 use tedge_config::models::timestamp::TimeFormat;
 use tedge_config::models::TopicPrefix;
@@ -5,11 +9,16 @@ use tedge_flows::ConnectedFlowRegistry;
 use tedge_flows::FlowRegistryExt;
 use tedge_flows::UpdateFlowRegistryError;
 use tedge_mqtt_ext::Topic;
+pub use twin::DesiredPropertiesToTwin;
+pub use twin::TwinToReportedProperties;
 
 pub struct AzureConverter {
     input_topics: String,
+    topic_prefix: TopicPrefix,
+    topic_root: String,
     output_topic: Topic,
     errors_topic: Topic,
+    bridge_health_topic: Topic,
     add_timestamp: bool,
     time_format: TimeFormat,
     size_threshold: usize,
@@ -23,14 +32,18 @@ impl AzureConverter {
         topic_prefix: &TopicPrefix,
         max_payload_size: u32,
         input_topics: String,
+        bridge_health_topic: Topic,
     ) -> Self {
         let output_topic = Topic::new_unchecked(&format!("{topic_prefix}/messages/events/"));
         let errors_topic = mqtt_schema.error_topic();
         let size_threshold = max_payload_size as usize;
         AzureConverter {
             input_topics,
+            topic_prefix: topic_prefix.clone(),
+            topic_root: mqtt_schema.root.clone(),
             output_topic,
             errors_topic,
+            bridge_health_topic,
             add_timestamp,
             time_format,
             size_threshold,
@@ -43,6 +56,18 @@ impl AzureConverter {
     ) -> Result<(), UpdateFlowRegistryError> {
         flows
             .persist_builtin_flow("mea", self.builtin_flow().as_str())
+            .await?;
+
+        flows
+            .persist_builtin_flow("methods", self.methods_flow().as_str())
+            .await?;
+
+        flows
+            .persist_builtin_flow("twin", self.twin_flow().as_str())
+            .await?;
+
+        flows
+            .persist_builtin_flow("desired", self.desired_flow().as_str())
             .await
     }
 
@@ -77,7 +102,116 @@ errors.mqtt.topic = "{errors_topic}"
             version = env!("CARGO_PKG_VERSION"),
         )
     }
+
+    /// The flow translating the direct methods into commands and responding once these are completed
+    fn methods_flow(&self) -> String {
+        format!(
+            r#"version = "{version}"
+
+input.mqtt.topics = ["{topic_prefix}/methods/POST/#", "{topic_root}/+/+/+/+/cmd/+", "{topic_root}/+/+/+/+/cmd/+/+"]
+
+# The commands created by this flow are also consumed by this flow to track their progress
+expect_loop = true
+
+steps = [
+    {{ builtin = "azure-direct-methods", interval = "1s", config = {{ prefix = "{topic_prefix}", topic_root = "{topic_root}", timeout = "30s" }} }},
+]
+
+errors.mqtt.topic = "{errors_topic}"
+"#,
+            topic_root = self.topic_root,
+            topic_prefix = self.topic_prefix,
+            errors_topic = self.errors_topic,
+            version = env!("CARGO_PKG_VERSION"),
+        )
+    }
+
+    /// The flow publishing the twin data of the entities as reported properties of the device twin
+    fn twin_flow(&self) -> String {
+        format!(
+            r#"version = "{version}"
+
+input.mqtt.topics = ["{topic_root}/+/+/+/+/twin/+"]
+
+steps = [
+    {{ builtin = "twin-to-azure-reported-properties", config = {{ prefix = "{topic_prefix}" }} }},
+]
+
+# The ids of the reported properties requests are not reused after a restart
+persist.scopes = ["script"]
+
+errors.mqtt.topic = "{errors_topic}"
+"#,
+            topic_root = self.topic_root,
+            topic_prefix = self.topic_prefix,
+            errors_topic = self.errors_topic,
+            version = env!("CARGO_PKG_VERSION"),
+        )
+    }
+
+    /// The flow applying the desired properties of the device twin to the entities
+    ///
+    /// The full device twin is requested each time the bridge connects.
+    fn desired_flow(&self) -> String {
+        format!(
+            r#"version = "{version}"
+
+input.mqtt.topics = ["{topic_prefix}/twin/PATCH/properties/desired/#", "{topic_prefix}/twin/res/200/#", "{bridge_health_topic}"]
+
+steps = [
+    {{ builtin = "azure-desired-properties", config = {{ prefix = "{topic_prefix}", topic_root = "{topic_root}", bridge_health_topic = "{bridge_health_topic}" }} }},
+]
+
+errors.mqtt.topic = "{errors_topic}"
+"#,
+            topic_root = self.topic_root,
+            topic_prefix = self.topic_prefix,
+            bridge_health_topic = self.bridge_health_topic,
+            errors_topic = self.errors_topic,
+            version = env!("CARGO_PKG_VERSION"),
+        )
+    }
+}
+
+/// The name of the device twin property grouping the twin fragments of an entity
+///
+/// The entity topic id is shortened, removing the empty segments and using `:` as separator,
+/// e.g. `device:child` for `device/child//`.
+fn property_from_entity(entity: &str) -> String {
+    entity
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<&str>>()
+        .join(":")
 }
-pub fn load_builtin_transformers(_flows: &mut impl FlowRegistryExt) {
-    // nothing specific for az
+
+/// The entity topic id of a device twin property named after an entity
+///
+/// Only the properties named after the entities of the default topic scheme are recognized,
+/// i.e. `device:<id>` and `device:<id>:service:<name>`,
+/// so ordinary properties which names contain `:` are not mistaken for entities.
+///
+/// Return `None` if the property is not named after an entity.
+fn entity_from_property(property: &str) -> Option<String> {
+    let segments: Vec<&str> = property.split(':').collect();
+    match segments[..] {
+        ["device", device] if is_valid_topic_level(device) => Some(format!("device/{device}//")),
+        ["device", device, "service", service]
+            if is_valid_topic_level(device) && is_valid_topic_level(service) =>
+        {
+            Some(format!("device/{device}/service/{service}"))
+        }
+        _ => None,
+    }
+}
+
+/// Check that a name can be used as an MQTT topic level
+fn is_valid_topic_level(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '+', '#'])
+}
+
+pub fn load_builtin_transformers(flows: &mut impl FlowRegistryExt) {
+    flows.register_builtin(DirectMethods::default());
+    flows.register_builtin(TwinToReportedProperties::default());
+    flows.register_builtin(DesiredPropertiesToTwin::default());
 }
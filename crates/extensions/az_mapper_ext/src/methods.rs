@@ -0,0 +1,348 @@
+//! Translation of Azure IoT Hub direct methods into thin-edge commands
+//!
+//! A direct method invocation received on `$iothub/methods/POST/<method>/?$rid=<request-id>`
+//! is turned into a command published on `te/<entity>/cmd/<method>/az-<request-id>`,
+//! the method payload being used as the command payload.
+//! The target entity is the main device, unless a `target` entity topic id is given by the payload:
+//!
+//! ```json
+//! {
+//!     "target": "device/child//"
+//! }
+//! ```
+//!
+//! The method response is sent on `$iothub/methods/res/<status>/?$rid=<request-id>`
+//! when the command is completed, with a `200` status when successful and a `500` status on failure.
+//! If the command is not completed in time, a `504` status is returned.
+//!
+//! Ref: https://learn.microsoft.com/en-us/azure/iot/iot-mqtt-connect-to-iot-hub#respond-to-a-direct-method
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::SystemTime;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_flows::CloudCommands;
+use tedge_flows::CommandMessage;
+use tedge_flows::ConfigError;
+use tedge_flows::FlowContextHandle;
+use tedge_flows::FlowError;
+use tedge_flows::JsonValue;
+use tedge_flows::Message;
+
+/// Prefix of the ids of the commands created for direct methods
+const COMMAND_ID_PREFIX: &str = "az-";
+
+/// Default time given to a command to complete before responding to the method with a timeout
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct DirectMethods {
+    prefix: String,
+    timeout: Duration,
+
+    /// The deadlines of the method invocations waiting for a response, indexed by request id
+    pending_requests: HashMap<String, SystemTime>,
+
+    commands: CloudCommands,
+}
+
+impl Default for DirectMethods {
+    fn default() -> Self {
+        DirectMethods {
+            prefix: "az".to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            pending_requests: HashMap::new(),
+            commands: CloudCommands::new(COMMAND_ID_PREFIX),
+        }
+    }
+}
+
+impl tedge_flows::Transformer for DirectMethods {
+    fn name(&self) -> &str {
+        "azure-direct-methods"
+    }
+
+    fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError> {
+        if let Some(prefix) = config.string_property("prefix") {
+            self.prefix = prefix.to_owned();
+        }
+        if let Some(topic_root) = config.string_property("topic_root") {
+            self.commands.set_topic_root(topic_root);
+        }
+        if let Some(timeout) = config.string_property("timeout") {
+            let Ok(timeout) = humantime::parse_duration(timeout) else {
+                return Err(ConfigError::IncorrectSetting(format!(
+                    "Invalid timeout: not a duration: {timeout}"
+                )));
+            };
+            self.timeout = timeout;
+        }
+        Ok(())
+    }
+
+    fn on_message(
+        &mut self,
+        timestamp: SystemTime,
+        message: &Message,
+        _context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        if let Some(method) = message
+            .topic
+            .strip_prefix(&self.prefix)
+            .and_then(|topic| topic.strip_prefix("/methods/POST/"))
+        {
+            return Ok(self.on_method_invocation(timestamp, method, message));
+        }
+
+        match self.commands.on_message(message) {
+            Some(CommandMessage::StatusUpdate {
+                entity,
+                operation,
+                request_id,
+                ..
+            }) => self.on_command_status(&entity, operation, request_id, message),
+            Some(CommandMessage::CapabilityUpdate { .. }) | None => Ok(vec![]),
+        }
+    }
+
+    fn is_periodic(&self) -> bool {
+        true
+    }
+
+    fn on_interval(
+        &mut self,
+        timestamp: SystemTime,
+        _context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        let expired: Vec<String> = self
+            .pending_requests
+            .iter()
+            .filter(|(_, deadline)| **deadline <= timestamp)
+            .map(|(request_id, _)| request_id.clone())
+            .collect();
+
+        let mut messages = vec![];
+        for request_id in expired {
+            self.pending_requests.remove(&request_id);
+            let reason = format!("No response received within {:?}", self.timeout);
+            messages.push(self.method_response(&request_id, 504, json!({ "reason": reason })));
+        }
+        Ok(messages)
+    }
+}
+
+impl DirectMethods {
+    /// Create a command for a direct method invocation, given the topic suffix `<method>/?$rid=<request-id>`
+    fn on_method_invocation(
+        &mut self,
+        timestamp: SystemTime,
+        method: &str,
+        message: &Message,
+    ) -> Vec<Message> {
+        let Some((operation, request_id)) = method
+            .split_once('/')
+            .and_then(|(operation, query)| Some((operation, query.strip_prefix("?$rid=")?)))
+        else {
+            return vec![];
+        };
+
+        match self.method_command(operation, request_id, &message.payload) {
+            Ok(command) => {
+                self.pending_requests
+                    .insert(request_id.to_string(), timestamp + self.timeout);
+                vec![command]
+            }
+            Err((status, reason)) => {
+                vec![self.method_response(request_id, status, json!({ "reason": reason }))]
+            }
+        }
+    }
+
+    /// Build the command requested by a direct method, returning the response status on error
+    fn method_command(
+        &self,
+        operation: &str,
+        request_id: &str,
+        payload: &[u8],
+    ) -> Result<Message, (u16, String)> {
+        let mut payload: Map<String, Value> = if payload.is_empty() {
+            Map::new()
+        } else {
+            match serde_json::from_slice(payload) {
+                Ok(Value::Object(payload)) => payload,
+                Ok(Value::Null) => Map::new(),
+                _ => return Err((400, "The method payload is not a JSON object".to_string())),
+            }
+        };
+
+        let target = match payload.remove("target") {
+            None => EntityTopicId::default_main_device().to_string(),
+            Some(Value::String(target)) => target,
+            Some(_) => return Err((400, "The target is not an entity topic id".to_string())),
+        };
+        if !self.commands.is_supported(&target, operation) {
+            return Err((404, format!("{operation} is not supported by {target}")));
+        }
+
+        Ok(self
+            .commands
+            .init_command(&target, operation, request_id, payload))
+    }
+
+    /// Respond to a direct method once the associated command is completed
+    fn on_command_status(
+        &mut self,
+        entity: &str,
+        operation: &str,
+        request_id: &str,
+        message: &Message,
+    ) -> Result<Vec<Message>, FlowError> {
+        let command: Value = serde_json::from_slice(&message.payload)
+            .map_err(|err| FlowError::UnsupportedMessage(format!("Invalid command: {err}")))?;
+        let status = match command.get("status").and_then(Value::as_str) {
+            Some("successful") => 200,
+            Some("failed") => 500,
+            _ => return Ok(vec![]),
+        };
+
+        // The command is cleared even if the method has already been responded with a timeout
+        let mut messages = vec![];
+        if self.pending_requests.remove(request_id).is_some() {
+            messages.push(self.method_response(request_id, status, command));
+        }
+        messages.push(self.commands.clear_command(entity, operation, request_id));
+        Ok(messages)
+    }
+
+    fn method_response(&self, request_id: &str, status: u16, payload: Value) -> Message {
+        let prefix = &self.prefix;
+        Message::new(
+            format!("{prefix}/methods/res/{status}/?$rid={request_id}"),
+            payload.to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_flows::Transformer;
+
+    #[test]
+    fn direct_method_is_turned_into_a_command() {
+        let context = FlowContextHandle::default();
+        let mut methods = DirectMethods::default();
+        let now = SystemTime::now();
+
+        methods
+            .on_message(now, &capability("te/device/child///cmd/restart"), &context)
+            .unwrap();
+        let invocation = Message::new(
+            "az/methods/POST/restart/?$rid=1f",
+            r#"{"target":"device/child//","delay":10}"#,
+        );
+        let output = methods.on_message(now, &invocation, &context).unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic, "te/device/child///cmd/restart/az-1f");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&output[0].payload).unwrap(),
+            json!({"status": "init", "delay": 10})
+        );
+    }
+
+    #[test]
+    fn unsupported_method_is_rejected() {
+        let context = FlowContextHandle::default();
+        let mut methods = DirectMethods::default();
+
+        let invocation = Message::new("az/methods/POST/reboot/?$rid=2", "{}");
+        let output = methods
+            .on_message(SystemTime::now(), &invocation, &context)
+            .unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic, "az/methods/res/404/?$rid=2");
+        assert_eq!(
+            output[0].payload_str().unwrap(),
+            r#"{"reason":"reboot is not supported by device/main//"}"#
+        );
+    }
+
+    #[test]
+    fn method_is_responded_when_the_command_completes() {
+        let context = FlowContextHandle::default();
+        let mut methods = DirectMethods::default();
+        let now = SystemTime::now();
+
+        methods
+            .on_message(now, &capability("te/device/main///cmd/restart"), &context)
+            .unwrap();
+        let invocation = Message::new("az/methods/POST/restart/?$rid=3", "");
+        methods.on_message(now, &invocation, &context).unwrap();
+
+        let executing = Message::new(
+            "te/device/main///cmd/restart/az-3",
+            r#"{"status":"executing"}"#,
+        );
+        assert!(methods
+            .on_message(now, &executing, &context)
+            .unwrap()
+            .is_empty());
+
+        let successful = Message::new(
+            "te/device/main///cmd/restart/az-3",
+            r#"{"status":"successful"}"#,
+        );
+        let output = methods.on_message(now, &successful, &context).unwrap();
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].topic, "az/methods/res/200/?$rid=3");
+        assert_eq!(
+            output[0].payload_str().unwrap(),
+            r#"{"status":"successful"}"#
+        );
+        assert_eq!(output[1].topic, "te/device/main///cmd/restart/az-3");
+        assert!(output[1].payload.is_empty());
+    }
+
+    #[test]
+    fn method_is_responded_with_a_timeout_when_the_command_takes_too_long() {
+        let context = FlowContextHandle::default();
+        let mut methods = DirectMethods::default();
+        methods
+            .set_config(JsonValue::from_value(json!({"timeout": "10s"})).unwrap())
+            .unwrap();
+        let now = SystemTime::now();
+
+        methods
+            .on_message(now, &capability("te/device/main///cmd/restart"), &context)
+            .unwrap();
+        let invocation = Message::new("az/methods/POST/restart/?$rid=4", "");
+        methods.on_message(now, &invocation, &context).unwrap();
+
+        assert!(methods
+            .on_interval(now + Duration::from_secs(5), &context)
+            .unwrap()
+            .is_empty());
+        let output = methods
+            .on_interval(now + Duration::from_secs(10), &context)
+            .unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic, "az/methods/res/504/?$rid=4");
+
+        // The late command is cleared without responding twice
+        let failed = Message::new(
+            "te/device/main///cmd/restart/az-4",
+            r#"{"status":"failed","reason":"too late"}"#,
+        );
+        let output = methods.on_message(now, &failed, &context).unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic, "te/device/main///cmd/restart/az-4");
+    }
+
+    fn capability(topic: &str) -> Message {
+        Message::new(topic, "{}")
+    }
+}
@@ -0,0 +1,396 @@
+//! Synchronization of the thin-edge entity twin data with the Azure IoT Hub device twin
+//!
+//! The twin fragments of the main device are mapped to top-level properties of the device twin,
+//! while the fragments of the other entities are grouped under a property named after the entity,
+//! e.g. `device:child` for `device/child//` and `device:main:service:nodered` for `device/main/service/nodered`.
+//!
+//! The desired properties are applied on each patch, as well as when the bridge connects,
+//! the full device twin being then requested to catch up with the changes missed while disconnected.
+//!
+//! Ref: https://learn.microsoft.com/en-us/azure/iot/iot-mqtt-connect-to-iot-hub#update-device-twins-reported-properties
+use crate::entity_from_property;
+use crate::is_valid_topic_level;
+use crate::property_from_entity;
+use serde_json::json;
+use serde_json::Value;
+use std::time::SystemTime;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_flows::ConfigError;
+use tedge_flows::FlowContextHandle;
+use tedge_flows::FlowError;
+use tedge_flows::JsonValue;
+use tedge_flows::Message;
+
+/// The key under which the last request id is stored in the step context
+const REQUEST_COUNT_KEY: &str = "request_count";
+
+/// The request id used to get the full device twin
+///
+/// Not a number, so it cannot be mistaken for the id of a reported properties patch.
+const GET_TWIN_REQUEST_ID: &str = "tedge-get-twin";
+
+/// Publish the twin fragments of the entities as reported properties of the device twin
+#[derive(Clone, Default)]
+pub struct TwinToReportedProperties {
+    prefix: String,
+
+    /// Name of the step context holding the last id of the PATCH requests,
+    /// so the ids are not reused after a restart when this context is persisted
+    step_name: String,
+}
+
+impl tedge_flows::Transformer for TwinToReportedProperties {
+    fn name(&self) -> &str {
+        "twin-to-azure-reported-properties"
+    }
+
+    fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError> {
+        let prefix = config.string_property("prefix").unwrap_or("az");
+        self.prefix = prefix.to_owned();
+        Ok(())
+    }
+
+    fn set_step_name(&mut self, step_name: &str) {
+        self.step_name = step_name.to_owned();
+    }
+
+    fn on_message(
+        &mut self,
+        _timestamp: SystemTime,
+        message: &Message,
+        context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        let (entity, fragment) = match message.topic.split('/').collect::<Vec<_>>()[..] {
+            [_, e1, e2, e3, e4, "twin", fragment] if !fragment.is_empty() => {
+                (format!("{e1}/{e2}/{e3}/{e4}"), fragment)
+            }
+            _ => return Ok(vec![]),
+        };
+
+        // An empty payload clears the twin fragment, hence the reported property
+        let value = if message.payload.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&message.payload).map_err(|err| {
+                FlowError::UnsupportedMessage(format!("Invalid twin value for {fragment}: {err}"))
+            })?
+        };
+        let payload = if entity == EntityTopicId::default_main_device().as_str() {
+            json!({ fragment: value })
+        } else {
+            json!({ property_from_entity(&entity): { fragment: value } })
+        };
+
+        let request_id = self.next_request_id(context);
+        let prefix = &self.prefix;
+        let topic = format!("{prefix}/twin/PATCH/properties/reported/?$rid={request_id}");
+        Ok(vec![Message::new(topic, payload.to_string())])
+    }
+}
+
+impl TwinToReportedProperties {
+    fn next_request_id(&self, context: &FlowContextHandle) -> u64 {
+        let last: Value = context
+            .get_step_value(&self.step_name, REQUEST_COUNT_KEY)
+            .into();
+        let request_id = last.as_u64().unwrap_or(0) + 1;
+        context.set_step_value(
+            &self.step_name,
+            REQUEST_COUNT_KEY,
+            JsonValue::from(json!(request_id)),
+        );
+        request_id
+    }
+}
+
+/// Apply the desired properties of the device twin to the twin fragments of the entities
+#[derive(Clone, Default)]
+pub struct DesiredPropertiesToTwin {
+    prefix: String,
+    topic_root: String,
+
+    /// The health topic of the bridge, used to request the full device twin on connection
+    bridge_health_topic: String,
+}
+
+impl tedge_flows::Transformer for DesiredPropertiesToTwin {
+    fn name(&self) -> &str {
+        "azure-desired-properties"
+    }
+
+    fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError> {
+        let prefix = config.string_property("prefix").unwrap_or("az");
+        let topic_root = config.string_property("topic_root").unwrap_or("te");
+        let bridge_health_topic = config.string_property("bridge_health_topic").unwrap_or("");
+        self.prefix = prefix.to_owned();
+        self.topic_root = topic_root.to_owned();
+        self.bridge_health_topic = bridge_health_topic.to_owned();
+        Ok(())
+    }
+
+    fn on_message(
+        &mut self,
+        _timestamp: SystemTime,
+        message: &Message,
+        _context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        if message.topic == self.bridge_health_topic {
+            return Ok(self.on_bridge_health(message));
+        }
+
+        let Some(twin_topic) = message
+            .topic
+            .strip_prefix(&self.prefix)
+            .and_then(|topic| topic.strip_prefix("/twin/"))
+        else {
+            return Ok(vec![]);
+        };
+        if twin_topic.starts_with("PATCH/properties/desired/") {
+            let patch: Value = serde_json::from_slice(&message.payload).map_err(|err| {
+                FlowError::UnsupportedMessage(format!("Invalid desired properties: {err}"))
+            })?;
+            return Ok(self.apply_desired_properties(&patch));
+        }
+        if twin_topic == format!("res/200/?$rid={GET_TWIN_REQUEST_ID}") {
+            let twin: Value = serde_json::from_slice(&message.payload).map_err(|err| {
+                FlowError::UnsupportedMessage(format!("Invalid device twin: {err}"))
+            })?;
+            let desired = twin.get("desired").unwrap_or(&Value::Null);
+            return Ok(self.apply_desired_properties(desired));
+        }
+        Ok(vec![])
+    }
+}
+
+impl DesiredPropertiesToTwin {
+    /// Request the full device twin when the bridge is up
+    fn on_bridge_health(&self, message: &Message) -> Vec<Message> {
+        // The mosquitto bridge publishes `1` while the built-in bridge publishes `{"status":"up"}`
+        let is_up = message.payload == b"1"
+            || serde_json::from_slice::<Value>(&message.payload)
+                .is_ok_and(|health| health.get("status").and_then(Value::as_str) == Some("up"));
+        if !is_up {
+            return vec![];
+        }
+
+        let prefix = &self.prefix;
+        let topic = format!("{prefix}/twin/GET/?$rid={GET_TWIN_REQUEST_ID}");
+        vec![Message::new(topic, "")]
+    }
+
+    /// Apply desired properties, either a patch or the full desired state
+    fn apply_desired_properties(&self, desired: &Value) -> Vec<Message> {
+        let Some(properties) = desired.as_object() else {
+            return vec![];
+        };
+
+        let mut messages = vec![];
+        for (property, value) in properties {
+            // Skip the metadata, such as `$version`
+            if property.starts_with('$') {
+                continue;
+            }
+
+            match (entity_from_property(property), value) {
+                (Some(entity), Value::Object(fragments)) => {
+                    for (fragment, value) in fragments {
+                        messages.extend(self.twin_update(&entity, fragment, value));
+                    }
+                }
+                _ => messages.extend(self.twin_update(
+                    EntityTopicId::default_main_device().as_str(),
+                    property,
+                    value,
+                )),
+            }
+        }
+        messages
+    }
+
+    /// The twin update for a desired property
+    ///
+    /// Return `None` if the property name cannot be used as a twin fragment name, i.e. as an MQTT topic level.
+    fn twin_update(&self, entity: &str, fragment: &str, value: &Value) -> Option<Message> {
+        if !is_valid_topic_level(fragment) {
+            return None;
+        }
+        let topic_root = &self.topic_root;
+        let topic = format!("{topic_root}/{entity}/twin/{fragment}");
+        let payload = match value {
+            Value::Null => vec![],
+            value => value.to_string().into_bytes(),
+        };
+        Some(Message::new(topic, payload).with_retain())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_flows::Transformer;
+
+    #[test]
+    fn twin_fragments_are_reported() {
+        let context = FlowContextHandle::default();
+        let mut twin = TwinToReportedProperties::default();
+        twin.set_config(JsonValue::from_value(json!({"prefix": "az"})).unwrap())
+            .unwrap();
+
+        let main = Message::new("te/device/main///twin/maintenance", r#"{"mode":"on"}"#);
+        let output = twin.on_message(SystemTime::now(), &main, &context).unwrap();
+        assert_eq!(output[0].topic, "az/twin/PATCH/properties/reported/?$rid=1");
+        assert_eq!(
+            output[0].payload_str().unwrap(),
+            r#"{"maintenance":{"mode":"on"}}"#
+        );
+
+        let child = Message::new("te/device/child///twin/maintenance", "");
+        let output = twin
+            .on_message(SystemTime::now(), &child, &context)
+            .unwrap();
+        assert_eq!(output[0].topic, "az/twin/PATCH/properties/reported/?$rid=2");
+        assert_eq!(
+            output[0].payload_str().unwrap(),
+            r#"{"device:child":{"maintenance":null}}"#
+        );
+    }
+
+    #[test]
+    fn desired_properties_are_applied_to_the_twin() {
+        let context = FlowContextHandle::default();
+        let mut twin = DesiredPropertiesToTwin::default();
+        twin.set_config(JsonValue::from_value(json!({"topic_root": "te"})).unwrap())
+            .unwrap();
+
+        let patch = Message::new(
+            "az/twin/PATCH/properties/desired/?$version=4",
+            r#"{"log_level":"debug","device:child:service:app":{"port":8080,"host":null},"$version":4}"#,
+        );
+        let mut output = twin
+            .on_message(SystemTime::now(), &patch, &context)
+            .unwrap();
+        output.sort_by(|a, b| a.topic.cmp(&b.topic));
+
+        assert_eq!(output.len(), 3);
+        assert_eq!(output[0].topic, "te/device/child/service/app/twin/host");
+        assert!(output[0].payload.is_empty());
+        assert_eq!(output[1].topic, "te/device/child/service/app/twin/port");
+        assert_eq!(output[1].payload_str().unwrap(), "8080");
+        assert_eq!(output[2].topic, "te/device/main///twin/log_level");
+        assert_eq!(output[2].payload_str().unwrap(), r#""debug""#);
+    }
+
+    #[test]
+    fn only_the_properties_named_after_entities_are_applied_to_these_entities() {
+        let context = FlowContextHandle::default();
+        let mut twin = DesiredPropertiesToTwin::default();
+        twin.set_config(JsonValue::from_value(json!({"topic_root": "te"})).unwrap())
+            .unwrap();
+
+        let patch = Message::new(
+            "az/twin/PATCH/properties/desired/?$version=5",
+            r#"{"urn:settings":{"mode":"eco"},"device:child":{"mode":"on"}}"#,
+        );
+        let mut output = twin
+            .on_message(SystemTime::now(), &patch, &context)
+            .unwrap();
+        output.sort_by(|a, b| a.topic.cmp(&b.topic));
+
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].topic, "te/device/child///twin/mode");
+        assert_eq!(output[1].topic, "te/device/main///twin/urn:settings");
+        assert_eq!(output[1].payload_str().unwrap(), r#"{"mode":"eco"}"#);
+    }
+
+    #[test]
+    fn desired_properties_which_are_not_valid_topic_levels_are_ignored() {
+        let context = FlowContextHandle::default();
+        let mut twin = DesiredPropertiesToTwin::default();
+        twin.set_config(JsonValue::from_value(json!({"topic_root": "te"})).unwrap())
+            .unwrap();
+
+        let patch = Message::new(
+            "az/twin/PATCH/properties/desired/?$version=6",
+            r##"{"a/b":1,"a+":2,"#":3,"device:child":{"c/d":4,"ok":5}}"##,
+        );
+        let output = twin
+            .on_message(SystemTime::now(), &patch, &context)
+            .unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic, "te/device/child///twin/ok");
+        assert_eq!(output[0].payload_str().unwrap(), "5");
+    }
+
+    #[test]
+    fn request_ids_are_not_reused_by_a_new_instance() {
+        let context = FlowContextHandle::default();
+        let step = "twin.toml|0|twin-to-azure-reported-properties";
+        let message = Message::new("te/device/main///twin/maintenance", "{}");
+
+        let mut twin = TwinToReportedProperties::default();
+        twin.set_step_name(step);
+        twin.on_message(SystemTime::now(), &message, &context)
+            .unwrap();
+        twin.on_message(SystemTime::now(), &message, &context)
+            .unwrap();
+
+        // Simulated restart, the step context being restored
+        let mut twin = TwinToReportedProperties::default();
+        twin.set_step_name(step);
+        let output = twin
+            .on_message(SystemTime::now(), &message, &context)
+            .unwrap();
+        assert_eq!(output[0].topic, "az/twin/PATCH/properties/reported/?$rid=3");
+    }
+
+    #[test]
+    fn full_twin_is_requested_when_the_bridge_connects() {
+        let context = FlowContextHandle::default();
+        let mut twin = DesiredPropertiesToTwin::default();
+        twin.set_config(
+            JsonValue::from_value(json!({
+                "prefix": "az",
+                "topic_root": "te",
+                "bridge_health_topic": "te/device/main/service/tedge-mapper-bridge-az/status/health"
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let down = Message::new(
+            "te/device/main/service/tedge-mapper-bridge-az/status/health",
+            r#"{"status":"down"}"#,
+        );
+        assert!(twin
+            .on_message(SystemTime::now(), &down, &context)
+            .unwrap()
+            .is_empty());
+
+        let up = Message::new(
+            "te/device/main/service/tedge-mapper-bridge-az/status/health",
+            r#"{"status":"up"}"#,
+        );
+        let output = twin.on_message(SystemTime::now(), &up, &context).unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic, "az/twin/GET/?$rid=tedge-get-twin");
+
+        let response = Message::new(
+            "az/twin/res/200/?$rid=tedge-get-twin",
+            r#"{"desired":{"log_level":"info","$version":7},"reported":{"log_level":"debug"}}"#,
+        );
+        let output = twin
+            .on_message(SystemTime::now(), &response, &context)
+            .unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic, "te/device/main///twin/log_level");
+        assert_eq!(output[0].payload_str().unwrap(), r#""info""#);
+
+        // The responses to other requests are ignored
+        let other = Message::new("az/twin/res/200/?$rid=42", r#"{"desired":{"a":1}}"#);
+        assert!(twin
+            .on_message(SystemTime::now(), &other, &context)
+            .unwrap()
+            .is_empty());
+    }
+}
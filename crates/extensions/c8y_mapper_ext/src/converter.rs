@@ -2393,6 +2393,7 @@ pub(crate) mod tests {
                 payload: json!({"status":"init"}).to_string().into(),
                 qos: QoS::AtLeastOnce,
                 retain: true,
+                properties: Default::default(),
             },]
         );
         assert_eq!(converter.try_convert(&operation).await.unwrap(), vec![]);
@@ -2415,6 +2416,7 @@ pub(crate) mod tests {
                 payload: json!({"status":"init"}).to_string().into(),
                 qos: QoS::AtLeastOnce,
                 retain: true,
+                properties: Default::default(),
             },]
         );
         let local_completion = MqttMessage::new(
@@ -2514,6 +2516,7 @@ pub(crate) mod tests {
             payload: json!({"status":"init"}).to_string().into(),
             qos: QoS::AtLeastOnce,
             retain: true,
+            properties: Default::default(),
         };
 
         assert_eq!(
@@ -2583,6 +2586,7 @@ pub(crate) mod tests {
             payload: json!({"status":"init"}).to_string().into(),
             qos: QoS::AtLeastOnce,
             retain: true,
+            properties: Default::default(),
         };
         converter
             .try_convert(&existing_pending_operation)
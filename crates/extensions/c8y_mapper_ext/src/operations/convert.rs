@@ -140,6 +140,8 @@ impl CumulocityConverter {
             date_to: log_request.date_to,
             search_text: Some(log_request.search_text).filter(|s| !s.is_empty()),
             lines: log_request.maximum_lines,
+            compression: None,
+            bundle: false,
             log_path: None,
         };
 
@@ -217,6 +219,10 @@ impl CumulocityConverter {
             remote_url: firmware_request.url,
             name: firmware_request.name,
             version: firmware_request.version,
+            // c8y_Firmware operations provide neither digests nor signature for the firmware
+            sha256: None,
+            sha512: None,
+            signature: None,
             log_path: None,
         };
 
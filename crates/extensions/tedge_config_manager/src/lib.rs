@@ -316,18 +316,23 @@ impl SyncOnCommand for ConfigManagerBuilder {
 
 impl OperationStepHandler for ConfigManagerBuilder {
     fn supported_operation_steps(&self) -> Vec<(OperationType, OperationStep)> {
-        let mut steps: Vec<(OperationType, OperationStep)> = ConfigOperationStep::update_steps()
-            .iter()
-            .map(|step| (OperationType::ConfigUpdate, step.as_str().into()))
-            .collect();
-        steps.push((
-            OperationType::ConfigSnapshot,
-            ConfigOperationStep::Get.as_str().into(),
-        ));
-        steps
+        builtin_operation_steps()
     }
 }
 
+/// Return the builtin operation steps which can be invoked by a workflow on the config manager
+pub fn builtin_operation_steps() -> Vec<(OperationType, OperationStep)> {
+    let mut steps: Vec<(OperationType, OperationStep)> = ConfigOperationStep::update_steps()
+        .iter()
+        .map(|step| (OperationType::ConfigUpdate, step.as_str().into()))
+        .collect();
+    steps.push((
+        OperationType::ConfigSnapshot,
+        ConfigOperationStep::Get.as_str().into(),
+    ));
+    steps
+}
+
 impl MessageSink<RequestEnvelope<OperationStepRequest, OperationStepResponse>>
     for ConfigManagerBuilder
 {
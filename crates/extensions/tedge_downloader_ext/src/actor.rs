@@ -3,6 +3,7 @@ use certificate::CloudHttpConfig;
 use download::DownloadError;
 use download::DownloadInfo;
 use download::Downloader;
+use download::TrustStore;
 use reqwest::header::HeaderMap;
 use reqwest::Identity;
 use std::marker::PhantomData;
@@ -22,6 +23,9 @@ pub struct DownloadRequest {
     pub file_path: PathBuf,
     pub headers: HeaderMap,
     pub permission: Option<PermissionEntry>,
+    pub sha256: Option<String>,
+    pub sha512: Option<String>,
+    pub signature: Option<String>,
 }
 
 impl DownloadRequest {
@@ -31,6 +35,9 @@ impl DownloadRequest {
             file_path: file_path.into(),
             headers: HeaderMap::new(),
             permission: None,
+            sha256: None,
+            sha512: None,
+            signature: None,
         }
     }
 
@@ -40,6 +47,21 @@ impl DownloadRequest {
             ..self
         }
     }
+
+    /// Sets the expected SHA-256 digest of the downloaded file
+    pub fn with_sha256(self, sha256: Option<String>) -> Self {
+        Self { sha256, ..self }
+    }
+
+    /// Sets the expected SHA-512 digest of the downloaded file
+    pub fn with_sha512(self, sha512: Option<String>) -> Self {
+        Self { sha512, ..self }
+    }
+
+    /// Sets the base64 encoded signature of the downloaded file
+    pub fn with_signature(self, signature: Option<String>) -> Self {
+        Self { signature, ..self }
+    }
 }
 
 pub type DownloadResult = Result<DownloadResponse, DownloadError>;
@@ -65,6 +87,7 @@ pub struct DownloaderActor<T> {
     key: std::marker::PhantomData<T>,
     identity: Option<Identity>,
     cloud_root_certs: CloudHttpConfig,
+    trust_store: Option<TrustStore>,
 }
 
 impl<T> Clone for DownloaderActor<T> {
@@ -74,6 +97,7 @@ impl<T> Clone for DownloaderActor<T> {
             key: self.key,
             identity: self.identity.clone(),
             cloud_root_certs: self.cloud_root_certs.clone(),
+            trust_store: self.trust_store.clone(),
         }
     }
 }
@@ -85,6 +109,15 @@ impl<T: Message + Default> DownloaderActor<T> {
             key: PhantomData,
             identity,
             cloud_root_certs,
+            trust_store: None,
+        }
+    }
+
+    /// Sets the keys trusted to sign the downloaded files
+    pub fn with_trust_store(self, trust_store: Option<TrustStore>) -> Self {
+        Self {
+            trust_store,
+            ..self
         }
     }
 
@@ -112,13 +145,17 @@ impl<T: Message> Server for DownloaderActor<T> {
     async fn handle(&mut self, id_request: Self::Request) -> Self::Response {
         let (id, request) = id_request;
 
-        let download_info = DownloadInfo::new(&request.url).with_headers(request.headers);
+        let mut download_info = DownloadInfo::new(&request.url).with_headers(request.headers);
+        download_info.sha256 = request.sha256;
+        download_info.sha512 = request.sha512;
+        download_info.signature = request.signature;
 
-        let downloader = Downloader::new(
+        let mut downloader = Downloader::new(
             request.file_path.clone(),
             self.identity.clone(),
             self.cloud_root_certs.clone(),
         );
+        downloader.set_trust_store(self.trust_store.clone());
 
         info!(
             "Downloading from url {} to location {}",
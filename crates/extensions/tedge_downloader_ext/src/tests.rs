@@ -75,6 +75,41 @@ async fn download_with_auth() {
     assert_eq!(response.as_ref().unwrap().url, server_url);
 }
 
+#[tokio::test]
+async fn download_rejected_when_digest_does_not_match() {
+    let ttd = TempTedgeDir::new();
+    let mut server = mockito::Server::new_async().await;
+    let _mock = server
+        .mock("GET", "/")
+        .with_status(200)
+        .with_header("content-type", "text/plain")
+        .with_body("corrupted")
+        .create_async()
+        .await;
+
+    let target_path = ttd.path().join("downloaded_file");
+    let server_url = server.url();
+    let download_request = DownloadRequest::new(&server_url, &target_path).with_sha256(Some(
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string(),
+    ));
+
+    let mut requester = spawn_downloader_actor().await;
+
+    let (_, response) = timeout(
+        TEST_TIMEOUT,
+        requester.await_response(("id".to_string(), download_request)),
+    )
+    .await
+    .expect("timeout")
+    .expect("channel error");
+
+    assert!(matches!(
+        response,
+        Err(DownloadError::VerificationFailed(_))
+    ));
+    assert!(!target_path.exists());
+}
+
 async fn spawn_downloader_actor(
 ) -> ClientMessageBox<(String, DownloadRequest), (String, DownloadResult)> {
     let mut downloader_actor_builder =
@@ -5,6 +5,9 @@ use crate::flow::FlowOutput;
 use crate::flow::FlowResult;
 use crate::flow::Message;
 use crate::flow::SourceTag;
+use crate::http_output::HttpOutput;
+use crate::http_output::HttpOutputFailure;
+use crate::http_output::HttpOutputs;
 use crate::params::is_params_file;
 use crate::registry::FlowRegistryExt;
 use crate::registry::RegistrationStatus;
@@ -50,6 +53,7 @@ pub struct FlowsMapper {
     subscriptions: TopicFilter,
     watched_commands: HashSet<String>,
     processor: MessageProcessor<ConnectedFlowRegistry>,
+    http_outputs: HttpOutputs,
     next_dump: Instant,
     deferred_tick: bool,
 }
@@ -65,6 +69,7 @@ impl FlowsMapper {
     ) -> Self {
         let watched_commands = HashSet::new();
         let next_dump = Instant::now() + config.stats_dump_interval;
+        let http_outputs = HttpOutputs::new(config.http_config.clone());
         FlowsMapper {
             config,
             messages,
@@ -73,6 +78,7 @@ impl FlowsMapper {
             subscriptions,
             watched_commands,
             processor,
+            http_outputs,
             next_dump,
             deferred_tick: false,
         }
@@ -111,6 +117,9 @@ impl Actor for FlowsMapper {
                     self.handle_fs_event(event).await?;
                     self.on_startup().await?;
                 }
+                InputMessage::HttpOutputFailure(failure) => {
+                    self.on_http_output_failure(failure).await?;
+                }
             }
         }
 
@@ -131,6 +140,9 @@ impl FlowsMapper {
                 message
             }
             _ = sleep_until(deadline) => Some(InputMessage::Tick(Tick)),
+            Some(failure) = self.http_outputs.next_failure() => {
+                Some(InputMessage::HttpOutputFailure(failure))
+            }
         }
     }
 
@@ -321,6 +333,12 @@ impl FlowsMapper {
                 self.on_input_message(&topic, Utf8Path::new(flow_path), line)
                     .await?;
             }
+            WatchEvent::HttpPost { topic, path, body } => {
+                let flow_path = flow_path_from_watch_topic(&topic);
+                let message = Message::new(path, body);
+                self.on_flow_input(Utf8Path::new(flow_path), message)
+                    .await?;
+            }
             WatchEvent::StderrLine { topic, line } => {
                 warn!(target: "flows", "Input command {topic}: {line}");
             }
@@ -347,20 +365,29 @@ impl FlowsMapper {
     ) -> Result<(), RuntimeError> {
         if let Some(flow) = self.processor.registry.flow(flow_path) {
             let topic = flow.input_topic_for_watch(watch_topic).to_string();
-            let timestamp = SystemTime::now();
             let message = Message::new(topic, line);
-            if let Some(result) = self
-                .processor
-                .on_flow_input(flow_path, timestamp, &message)
-                .await
-            {
-                self.publish_result(result).await?;
-            }
+            self.on_flow_input(flow_path, message).await?;
         }
 
         Ok(())
     }
 
+    async fn on_flow_input(
+        &mut self,
+        flow_path: &Utf8Path,
+        message: Message,
+    ) -> Result<(), RuntimeError> {
+        let timestamp = SystemTime::now();
+        if let Some(result) = self
+            .processor
+            .on_flow_input(flow_path, timestamp, &message)
+            .await
+        {
+            self.publish_result(result).await?;
+        }
+        Ok(())
+    }
+
     async fn on_input_error(
         &mut self,
         watch_topic: &str,
@@ -472,6 +499,11 @@ impl FlowsMapper {
                     error!(target: "flows", "{flow}: cannot flush {path}: {err}");
                 }
             }
+            FlowOutput::Http(output) => {
+                for message in messages {
+                    self.http_outputs.send(flow, output, message);
+                }
+            }
         }
         Ok(())
     }
@@ -486,6 +518,51 @@ impl FlowsMapper {
         self.publish(flow, vec![message], output).await
     }
 
+    async fn on_http_output_failure(
+        &mut self,
+        failure: HttpOutputFailure,
+    ) -> Result<(), RuntimeError> {
+        let HttpOutputFailure {
+            flow,
+            output,
+            error,
+            messages,
+        } = failure;
+        let Some(errors) = self
+            .processor
+            .registry
+            .flow(&flow)
+            .map(|f| f.as_ref().errors.clone())
+        else {
+            return Ok(());
+        };
+
+        // The messages not delivered are listed after the error, one per line, as `[topic] payload`
+        let mut error = format!("Error in {flow}: {}", FlowError::from(error));
+        for message in messages {
+            error.push_str(&format!("\n{message}"));
+        }
+
+        // Errors on the errors output are only logged, to avoid feedback loops
+        if is_http_output(&errors, &output) {
+            error!(target: "flows", "{error}");
+            return Ok(());
+        }
+        self.publish(&flow, vec![Message::new("", error)], &errors)
+            .await
+    }
+
+    /// Stop the HTTP outputs no more used after flow updates
+    fn drop_unused_http_outputs(&mut self) {
+        let registry = &self.processor.registry;
+        self.http_outputs.retain(|flow, output| {
+            registry.flow(flow).is_some_and(|flow| {
+                let flow = flow.as_ref();
+                is_http_output(&flow.output, output) || is_http_output(&flow.errors, output)
+            })
+        });
+    }
+
     async fn handle_fs_event(&mut self, event: FsWatchEvent) -> Result<(), RuntimeError> {
         match event {
             FsWatchEvent::DirectoryCreated(path) | FsWatchEvent::Modified(path) => {
@@ -573,6 +650,7 @@ impl FlowsMapper {
             self.update_all_flow_status(reloaded_flows).await?;
         } else if path.extension() == Some("toml") {
             self.processor.add_flow(path).await;
+            self.drop_unused_http_outputs();
             self.send_updated_subscriptions().await?;
             self.update_flow_status(path).await?;
         }
@@ -593,6 +671,7 @@ impl FlowsMapper {
         } else if path.extension() == Some("toml") {
             self.processor.remove_flow(path).await;
         }
+        self.drop_unused_http_outputs();
         self.send_updated_subscriptions().await?;
         self.update_flow_status(path).await?;
         Ok(())
@@ -600,6 +679,7 @@ impl FlowsMapper {
 
     async fn on_directory_updated(&mut self, path: &Utf8Path) -> Result<(), RuntimeError> {
         self.processor.load_all_flows_from_dir(path).await;
+        self.drop_unused_http_outputs();
 
         self.send_updated_subscriptions().await?;
 
@@ -624,6 +704,10 @@ impl FlowsMapper {
     }
 }
 
+fn is_http_output(flow_output: &FlowOutput, output: &HttpOutput) -> bool {
+    matches!(flow_output, FlowOutput::Http(flow_output) if flow_output == output)
+}
+
 pub(crate) fn flow_path_from_watch_topic(topic: &str) -> &str {
     topic.split_once("#input-").map_or(topic, |(flow, _)| flow)
 }
@@ -0,0 +1,176 @@
+//! Helpers shared by the cloud mappers turning cloud requests into thin-edge commands
+//! (e.g. AWS jobs or Azure direct methods)
+//!
+//! - The commands are published on `te/<entity>/cmd/<operation>/<prefix><request-id>`,
+//!   the prefix being specific to the cloud (e.g. `az-`).
+//! - Only the operations advertised by the entities on `te/<entity>/cmd/<operation>` can be requested.
+use crate::Message;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+/// The commands created by a mapper on behalf of cloud requests
+#[derive(Clone)]
+pub struct CloudCommands {
+    topic_root: String,
+
+    /// Prefix of the ids of the commands created for the cloud requests
+    cmd_id_prefix: &'static str,
+
+    /// The operations supported by each entity, as advertised on `te/<entity>/cmd/<operation>`
+    supported_operations: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// A command related message, as received on `te/<entity>/cmd/#`
+#[derive(Debug, Eq, PartialEq)]
+pub enum CommandMessage<'a> {
+    /// The operations supported by an entity have been updated
+    CapabilityUpdate { entity: String },
+
+    /// The status of a command created for a cloud request has been updated
+    StatusUpdate {
+        entity: String,
+        operation: &'a str,
+        cmd_id: &'a str,
+        request_id: &'a str,
+    },
+}
+
+impl CloudCommands {
+    pub fn new(cmd_id_prefix: &'static str) -> Self {
+        CloudCommands {
+            topic_root: "te".to_string(),
+            cmd_id_prefix,
+            supported_operations: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_topic_root(&mut self, topic_root: &str) {
+        self.topic_root = topic_root.to_owned();
+    }
+
+    /// Process a capability or a command status message
+    ///
+    /// Return `None` for unrelated messages, for capabilities that are not updated
+    /// and for the commands not created for cloud requests.
+    pub fn on_message<'a>(&mut self, message: &'a Message) -> Option<CommandMessage<'a>> {
+        let te_topic = message
+            .topic
+            .strip_prefix(&self.topic_root)
+            .and_then(|topic| topic.strip_prefix('/'))?;
+        match te_topic.split('/').collect::<Vec<_>>()[..] {
+            [e1, e2, e3, e4, "cmd", operation] => {
+                let entity = format!("{e1}/{e2}/{e3}/{e4}");
+                let operations = self.supported_operations.entry(entity.clone()).or_default();
+                let updated = if message.payload.is_empty() {
+                    operations.remove(operation)
+                } else {
+                    operations.insert(operation.to_string())
+                };
+                updated.then_some(CommandMessage::CapabilityUpdate { entity })
+            }
+            [e1, e2, e3, e4, "cmd", operation, cmd_id] if !message.payload.is_empty() => {
+                let request_id = cmd_id.strip_prefix(self.cmd_id_prefix)?;
+                Some(CommandMessage::StatusUpdate {
+                    entity: format!("{e1}/{e2}/{e3}/{e4}"),
+                    operation,
+                    cmd_id,
+                    request_id,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The operations supported by an entity
+    pub fn operations(&self, entity: &str) -> Option<&BTreeSet<String>> {
+        self.supported_operations.get(entity)
+    }
+
+    pub fn is_supported(&self, entity: &str, operation: &str) -> bool {
+        self.operations(entity)
+            .is_some_and(|operations| operations.contains(operation))
+    }
+
+    /// The topic of the command created for a cloud request
+    pub fn command_topic(&self, entity: &str, operation: &str, request_id: &str) -> String {
+        let topic_root = &self.topic_root;
+        let prefix = self.cmd_id_prefix;
+        format!("{topic_root}/{entity}/cmd/{operation}/{prefix}{request_id}")
+    }
+
+    /// Create the command requested by the cloud, with the given command specific properties
+    pub fn init_command(
+        &self,
+        entity: &str,
+        operation: &str,
+        request_id: &str,
+        mut payload: Map<String, Value>,
+    ) -> Message {
+        payload.insert("status".to_string(), json!("init"));
+        let topic = self.command_topic(entity, operation, request_id);
+        Message::new(topic, Value::Object(payload).to_string()).with_retain()
+    }
+
+    /// Clear a completed command
+    pub fn clear_command(&self, entity: &str, operation: &str, request_id: &str) -> Message {
+        let topic = self.command_topic(entity, operation, request_id);
+        Message::new(topic, "").with_retain()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_are_tracked() {
+        let mut commands = CloudCommands::new("az-");
+
+        let capability = Message::new("te/device/child///cmd/restart", "{}");
+        assert_eq!(
+            commands.on_message(&capability),
+            Some(CommandMessage::CapabilityUpdate {
+                entity: "device/child//".to_string()
+            })
+        );
+        assert!(commands.is_supported("device/child//", "restart"));
+
+        // Unchanged capabilities are not notified
+        assert_eq!(commands.on_message(&capability), None);
+
+        let removed = Message::new("te/device/child///cmd/restart", "");
+        assert!(commands.on_message(&removed).is_some());
+        assert!(!commands.is_supported("device/child//", "restart"));
+    }
+
+    #[test]
+    fn only_the_commands_created_for_cloud_requests_are_notified() {
+        let mut commands = CloudCommands::new("az-");
+
+        let status = Message::new(
+            "te/device/main///cmd/restart/az-42",
+            r#"{"status":"successful"}"#,
+        );
+        assert_eq!(
+            commands.on_message(&status),
+            Some(CommandMessage::StatusUpdate {
+                entity: "device/main//".to_string(),
+                operation: "restart",
+                cmd_id: "az-42",
+                request_id: "42",
+            })
+        );
+
+        let cleared = Message::new("te/device/main///cmd/restart/az-42", "");
+        assert_eq!(commands.on_message(&cleared), None);
+
+        let other = Message::new(
+            "te/device/main///cmd/restart/c8y-mapper-42",
+            r#"{"status":"successful"}"#,
+        );
+        assert_eq!(commands.on_message(&other), None);
+    }
+}
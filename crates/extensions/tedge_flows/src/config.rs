@@ -1,6 +1,13 @@
 use crate::flow::Flow;
 use crate::flow::FlowInput;
 use crate::flow::FlowOutput;
+use crate::http_output::BatchFormat;
+use crate::http_output::BatchPolicy;
+use crate::http_output::HttpOutput;
+use crate::http_output::RetryPolicy;
+use crate::http_output::UrlTemplate;
+use crate::js_lib::kv_store::ContextScope;
+use crate::js_lib::kv_store::PersistPolicy;
 use crate::js_runtime::JsRuntime;
 use crate::js_script::JsScript;
 use crate::params::is_params_file;
@@ -13,9 +20,13 @@ use camino::Utf8Component;
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
 use glob::glob;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use reqwest::Method;
 use serde::Deserialize;
 use serde_json::Map;
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::time::Duration;
@@ -49,6 +60,10 @@ pub struct FlowConfig {
     /// If true, output messages that match the input filter are not dropped
     #[serde(default)]
     expect_loop: bool,
+
+    /// Context entries to be persisted across mapper restarts
+    #[serde(default)]
+    persist: PersistConfig,
 }
 
 #[derive(Deserialize)]
@@ -86,6 +101,15 @@ pub struct InputConfig {
 
     #[serde(default, deserialize_with = "deserialize_one_or_many")]
     process: Vec<ProcessInputConfig>,
+
+    #[serde(default, deserialize_with = "deserialize_one_or_many")]
+    unix_socket: Vec<UnixSocketInputConfig>,
+
+    #[serde(default, deserialize_with = "deserialize_one_or_many")]
+    tcp: Vec<TcpInputConfig>,
+
+    #[serde(default, deserialize_with = "deserialize_one_or_many")]
+    http: Vec<HttpInputConfig>,
 }
 
 #[derive(Clone, Deserialize)]
@@ -120,6 +144,42 @@ pub struct ProcessInputConfig {
     interval: Option<IntervalConfig>,
 }
 
+#[derive(Clone, Deserialize)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct UnixSocketInputConfig {
+    path: Utf8PathBuf,
+
+    /// Default to path
+    topic: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct TcpInputConfig {
+    address: String,
+
+    /// Default to address
+    topic: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct HttpInputConfig {
+    address: String,
+}
+
+#[derive(Clone, Deserialize, Default)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct PersistConfig {
+    /// The context scopes to persist: `mapper`, `flow` and/or `script`
+    #[serde(default)]
+    scopes: Vec<ContextScope>,
+
+    /// Glob patterns selecting the keys to persist. Default to all keys.
+    #[serde(default)]
+    keys: Vec<String>,
+}
+
 #[derive(Deserialize)]
 #[cfg_attr(test, derive(Debug, Eq, PartialEq))]
 pub enum OutputConfig {
@@ -128,6 +188,58 @@ pub enum OutputConfig {
 
     #[serde(rename = "file")]
     File { path: Utf8PathBuf },
+
+    #[serde(rename = "http")]
+    Http(HttpOutputConfig),
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct HttpOutputConfig {
+    /// URL template, possibly referring to the message topic and payload
+    url: String,
+
+    #[serde(default = "default_http_method")]
+    method: String,
+
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+
+    #[serde(default)]
+    batch: HttpBatchConfig,
+
+    #[serde(default)]
+    retry: HttpRetryConfig,
+}
+
+#[derive(Deserialize, Default)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct HttpBatchConfig {
+    /// Default to 1, i.e. no batching
+    max_size: Option<usize>,
+
+    #[serde(default)]
+    #[serde(deserialize_with = "parse_human_interval")]
+    max_delay: Option<IntervalConfig>,
+
+    #[serde(default)]
+    format: BatchFormat,
+}
+
+#[derive(Deserialize, Default)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct HttpRetryConfig {
+    #[serde(default)]
+    #[serde(deserialize_with = "parse_human_interval")]
+    initial_interval: Option<IntervalConfig>,
+
+    #[serde(default)]
+    #[serde(deserialize_with = "parse_human_interval")]
+    max_interval: Option<IntervalConfig>,
+
+    #[serde(default)]
+    #[serde(deserialize_with = "parse_human_interval")]
+    max_elapsed_time: Option<IntervalConfig>,
 }
 
 #[derive(Clone)]
@@ -318,6 +430,7 @@ impl FlowConfig {
             steps: vec![step],
             output: default_output(),
             errors: default_errors(),
+            persist: PersistConfig::default(),
         }
     }
 
@@ -332,6 +445,7 @@ impl FlowConfig {
         let input = self.input.into_flow_inputs(source_dir)?;
         let output = self.output.try_into()?;
         let errors = self.errors.try_into()?;
+        let persist = self.persist.try_into()?;
         let mut steps = vec![];
         for (i, step) in self.steps.into_iter().enumerate() {
             let step = step
@@ -354,6 +468,9 @@ impl FlowConfig {
         }
 
         detect_loop(&name, &input, &output, self.expect_loop)?;
+        js_runtime
+            .context_handle()
+            .set_persist_policy(source.as_str(), persist);
 
         Ok(Flow {
             name,
@@ -512,6 +629,29 @@ impl InputConfig {
                     })
                 })
                 .collect::<Result<_, ConfigError>>()?,
+            unix_socket: self
+                .unix_socket
+                .into_iter()
+                .map(|input| UnixSocketInputConfig {
+                    path: params.substitute_inner_paths(input.path.as_str()).into(),
+                    topic: input.topic.map(|t| params.substitute_inner_paths(&t)),
+                })
+                .collect(),
+            tcp: self
+                .tcp
+                .into_iter()
+                .map(|input| TcpInputConfig {
+                    address: params.substitute_inner_paths(&input.address),
+                    topic: input.topic.map(|t| params.substitute_inner_paths(&t)),
+                })
+                .collect(),
+            http: self
+                .http
+                .into_iter()
+                .map(|input| HttpInputConfig {
+                    address: params.substitute_inner_paths(&input.address),
+                })
+                .collect(),
         })
     }
 }
@@ -607,12 +747,26 @@ impl InputConfig {
             inputs.push(input);
         }
 
+        for UnixSocketInputConfig { path, topic } in self.unix_socket {
+            let topic = topic.unwrap_or_else(|| path.to_string());
+            inputs.push(FlowInput::StreamUnixSocket { topic, path });
+        }
+
+        for TcpInputConfig { address, topic } in self.tcp {
+            let topic = topic.unwrap_or_else(|| address.clone());
+            inputs.push(FlowInput::StreamTcp { topic, address });
+        }
+
+        for HttpInputConfig { address } in self.http {
+            inputs.push(FlowInput::HttpListener { address });
+        }
+
         Ok(inputs)
     }
 }
 
 impl OutputConfig {
-    fn substitute_params(self, params: &Params<&dyn MapperParams>) -> Result<Self, LoadError> {
+    fn substitute_params(self, params: &Params<&dyn MapperParams>) -> Result<Self, ConfigError> {
         match self {
             OutputConfig::Mqtt { topic } => Ok(OutputConfig::Mqtt {
                 topic: topic.map(|t| params.substitute_inner_paths(&t)),
@@ -620,10 +774,37 @@ impl OutputConfig {
             OutputConfig::File { path } => Ok(OutputConfig::File {
                 path: params.substitute_inner_paths(path.as_str()).into(),
             }),
+            OutputConfig::Http(config) => Ok(OutputConfig::Http(config.substitute_params(params)?)),
         }
     }
 }
 
+impl HttpOutputConfig {
+    fn substitute_params(self, params: &Params<&dyn MapperParams>) -> Result<Self, ConfigError> {
+        let substitute = |interval: Option<IntervalConfig>| {
+            interval.map(|i| i.substitute_params(params)).transpose()
+        };
+        Ok(HttpOutputConfig {
+            url: params.substitute_inner_paths(&self.url),
+            method: params.substitute_inner_paths(&self.method),
+            headers: self
+                .headers
+                .into_iter()
+                .map(|(name, value)| (name, params.substitute_inner_paths(&value)))
+                .collect(),
+            batch: HttpBatchConfig {
+                max_delay: substitute(self.batch.max_delay)?,
+                ..self.batch
+            },
+            retry: HttpRetryConfig {
+                initial_interval: substitute(self.retry.initial_interval)?,
+                max_interval: substitute(self.retry.max_interval)?,
+                max_elapsed_time: substitute(self.retry.max_elapsed_time)?,
+            },
+        })
+    }
+}
+
 impl TryFrom<OutputConfig> for FlowOutput {
     type Error = ConfigError;
 
@@ -633,10 +814,80 @@ impl TryFrom<OutputConfig> for FlowOutput {
                 topic: topic.map(into_topic).transpose()?,
             },
             OutputConfig::File { path } => FlowOutput::File { path },
+            OutputConfig::Http(config) => FlowOutput::Http(config.try_into()?),
+        })
+    }
+}
+
+impl TryFrom<HttpOutputConfig> for HttpOutput {
+    type Error = ConfigError;
+
+    fn try_from(config: HttpOutputConfig) -> Result<Self, Self::Error> {
+        let incorrect = |setting: &str, err: String| {
+            ConfigError::IncorrectSetting(format!("http.{setting}: {err}"))
+        };
+        let duration = |interval: Option<IntervalConfig>, default: Duration| {
+            interval
+                .map(|i| i.duration())
+                .transpose()
+                .map(|d| d.unwrap_or(default))
+        };
+
+        let url = UrlTemplate::parse(&config.url).map_err(|err| incorrect("url", err))?;
+        let method = Method::from_bytes(config.method.to_uppercase().as_bytes())
+            .map_err(|err| incorrect("method", err.to_string()))?;
+        let mut headers = vec![];
+        for (name, value) in config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|err| incorrect("headers", format!("{name}: {err}")))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|err| incorrect("headers", format!("{name}: {err}")))?;
+            headers.push((name, value));
+        }
+
+        let default_batch = BatchPolicy::default();
+        let max_size = config.batch.max_size.unwrap_or(default_batch.max_size);
+        if max_size == 0 {
+            return Err(incorrect("batch.max_size", "must be positive".to_string()));
+        }
+        let batch = BatchPolicy {
+            max_size,
+            max_delay: duration(config.batch.max_delay, default_batch.max_delay)?,
+            format: config.batch.format,
+        };
+
+        let default_retry = RetryPolicy::default();
+        let retry = RetryPolicy {
+            initial_interval: duration(
+                config.retry.initial_interval,
+                default_retry.initial_interval,
+            )?,
+            max_interval: duration(config.retry.max_interval, default_retry.max_interval)?,
+            max_elapsed_time: duration(
+                config.retry.max_elapsed_time,
+                default_retry.max_elapsed_time,
+            )?,
+        };
+
+        Ok(HttpOutput {
+            url,
+            method,
+            headers,
+            batch,
+            retry,
         })
     }
 }
 
+impl TryFrom<PersistConfig> for PersistPolicy {
+    type Error = ConfigError;
+
+    fn try_from(config: PersistConfig) -> Result<Self, Self::Error> {
+        PersistPolicy::try_new(config.scopes, &config.keys)
+            .map_err(|err| ConfigError::IncorrectSetting(format!("persist.keys: {err}")))
+    }
+}
+
 impl IntervalConfig {
     fn substitute_params(self, params: &Params<&dyn MapperParams>) -> Result<Self, ConfigError> {
         match &self {
@@ -692,6 +943,10 @@ fn default_output() -> OutputConfig {
     OutputConfig::Mqtt { topic: None }
 }
 
+fn default_http_method() -> String {
+    "POST".to_string()
+}
+
 fn default_errors() -> OutputConfig {
     OutputConfig::Mqtt {
         topic: Some("te/error".to_string()),
@@ -1012,6 +1267,84 @@ topic = "te/device/main///e/"
         assert_eq!(expected_flow, flow.substitute_params(&params).unwrap());
     }
 
+    #[test]
+    fn params_substitute_http_output() {
+        let params_toml = r#"
+        endpoint = "https://example.com/api"
+        token = "secret"
+        "#;
+
+        let flow_toml = r#"
+        [output.http]
+        url = "${params.endpoint}/devices/${.topic.2}/${.payload.type}"
+        headers = { Authorization = "Bearer ${params.token}" }
+        "#;
+
+        let expected_flow_toml = r#"
+        [output.http]
+        url = "https://example.com/api/devices/${.topic.2}/${.payload.type}"
+        headers = { Authorization = "Bearer secret" }
+        "#;
+
+        let mapper_config = empty_mapper_params();
+        let params = Params::load_toml(mapper_config.as_ref(), params_toml).unwrap();
+        let flow: FlowConfig = toml::from_str(flow_toml).unwrap();
+        let expected_flow: FlowConfig = toml::from_str(expected_flow_toml).unwrap();
+
+        assert_eq!(expected_flow, flow.substitute_params(&params).unwrap());
+    }
+
+    #[test]
+    fn http_output_settings() {
+        let output_toml = r#"
+        [http]
+        url = "https://example.com/write?db=${.topic.1}"
+        method = "put"
+        headers = { Content-Type = "text/plain" }
+        batch = { max_size = 100, max_delay = "5s", format = "lines" }
+        retry = { max_elapsed_time = "10m" }
+        "#;
+
+        let output: OutputConfig = toml::from_str(output_toml).unwrap();
+        let output: Result<FlowOutput, _> = output.try_into();
+        let Ok(FlowOutput::Http(output)) = output else {
+            panic!("Expected an HTTP output")
+        };
+
+        assert_eq!(output.method, Method::PUT);
+        assert_eq!(
+            output.headers,
+            vec![(
+                HeaderName::from_static("content-type"),
+                HeaderValue::from_static("text/plain")
+            )]
+        );
+        assert_eq!(
+            output.batch,
+            BatchPolicy {
+                max_size: 100,
+                max_delay: Duration::from_secs(5),
+                format: BatchFormat::Lines,
+            }
+        );
+        assert_eq!(
+            output.retry,
+            RetryPolicy {
+                max_elapsed_time: Duration::from_secs(600),
+                ..RetryPolicy::default()
+            }
+        );
+    }
+
+    #[test_case(r#"url = "https://example.com/${.unknown}""#; "unknown url template")]
+    #[test_case(r#"url = "https://example.com", method = "NOT A METHOD""#; "invalid method")]
+    #[test_case(r#"url = "https://example.com", batch.max_size = 0"#; "empty batches")]
+    fn rejecting_incorrect_http_output_settings(settings: &str) {
+        let output: OutputConfig = toml::from_str(&format!("http = {{ {settings} }}")).unwrap();
+        let output: Result<FlowOutput, _> = output.try_into();
+        assert!(matches!(output, Err(ConfigError::IncorrectSetting(_))));
+    }
+
     #[tokio::test]
     async fn relative_command_in_subdirectory_resolves_to_flow_parent() {
         let flow_toml = r#"
@@ -1068,6 +1401,48 @@ topic = "te/device/main///e/"
         )
     }
 
+    #[tokio::test]
+    async fn flows_accept_socket_and_http_inputs() {
+        let flow_toml = r#"
+        input.unix_socket.path = "/run/legacy.sock"
+        input.tcp = [
+            { address = "127.0.0.1:7000" },
+            { address = "127.0.0.1:7001", topic = "legacy/meter" },
+        ]
+        input.http.address = "127.0.0.1:8800"
+        "#;
+
+        let flow: FlowConfig = toml::from_str(flow_toml).unwrap();
+        let rs_transformers = BuiltinTransformers::default();
+        let mut js_runtime = JsRuntime::with_default().await.unwrap();
+        let flows_dir = Utf8Path::new("/flows");
+        let source = Utf8PathBuf::from("/flows/my_flow.toml");
+        let compiled = flow
+            .compile(&rs_transformers, &mut js_runtime, flows_dir, source)
+            .await
+            .unwrap();
+        assert_eq!(
+            compiled.input,
+            vec![
+                FlowInput::StreamUnixSocket {
+                    topic: "/run/legacy.sock".into(),
+                    path: "/run/legacy.sock".into(),
+                },
+                FlowInput::StreamTcp {
+                    topic: "127.0.0.1:7000".into(),
+                    address: "127.0.0.1:7000".into(),
+                },
+                FlowInput::StreamTcp {
+                    topic: "legacy/meter".into(),
+                    address: "127.0.0.1:7001".into(),
+                },
+                FlowInput::HttpListener {
+                    address: "127.0.0.1:8800".into(),
+                },
+            ]
+        )
+    }
+
     #[test]
     fn stream_command_sets_cwd_from_flow_directory() {
         let flow_toml = r#"
@@ -8,8 +8,11 @@ use crate::input_source::CommandPollingSource;
 use crate::input_source::CommandStreamingSource;
 use crate::input_source::FilePollingSource;
 use crate::input_source::FileStreamingSource;
+use crate::input_source::HttpStreamingSource;
 use crate::input_source::PollingSource;
 use crate::input_source::StreamingSource;
+use crate::input_source::TcpStreamingSource;
+use crate::input_source::UnixSocketStreamingSource;
 use crate::params::MapperParams;
 use crate::registry::FlowRegistry;
 use crate::registry::FlowStore;
@@ -237,6 +240,9 @@ pub(crate) fn watch_request_topic(request: &WatchRequest) -> &str {
     match request {
         WatchRequest::WatchFile { topic, .. }
         | WatchRequest::WatchCommand { topic, .. }
+        | WatchRequest::WatchUnixSocket { topic, .. }
+        | WatchRequest::WatchTcp { topic, .. }
+        | WatchRequest::WatchHttp { topic, .. }
         | WatchRequest::UnWatch { topic } => topic,
     }
 }
@@ -255,6 +261,18 @@ fn streaming_source(flow_name: String, input: FlowInput) -> Option<Box<dyn Strea
             flow_name, topic, command, cwd,
         ))),
 
+        FlowInput::StreamUnixSocket { topic, path } => Some(Box::new(
+            UnixSocketStreamingSource::new(flow_name, topic, path),
+        )),
+
+        FlowInput::StreamTcp { topic, address } => {
+            Some(Box::new(TcpStreamingSource::new(flow_name, topic, address)))
+        }
+
+        FlowInput::HttpListener { address } => {
+            Some(Box::new(HttpStreamingSource::new(flow_name, address)))
+        }
+
         _ => None,
     }
 }
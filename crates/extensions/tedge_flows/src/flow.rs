@@ -1,3 +1,5 @@
+use crate::http_output::HttpOutput;
+use crate::http_output::HttpOutputError;
 use crate::input_source::PollingSourceError;
 use crate::js_runtime::JsRuntime;
 use crate::stats::Counter;
@@ -12,6 +14,7 @@ use std::fmt::Formatter;
 use std::time::Duration;
 use std::time::SystemTime;
 use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::MqttProperties;
 use tedge_mqtt_ext::QoS;
 use tedge_mqtt_ext::Topic;
 use tedge_mqtt_ext::TopicFilter;
@@ -93,12 +96,25 @@ pub enum FlowInput {
         command: String,
         cwd: Utf8PathBuf,
     },
+    StreamUnixSocket {
+        topic: String,
+        path: Utf8PathBuf,
+    },
+    StreamTcp {
+        topic: String,
+        address: String,
+    },
+    /// Messages posted over HTTP, using the request path as topic
+    HttpListener {
+        address: String,
+    },
 }
 
 #[derive(Clone)]
 pub enum FlowOutput {
     Mqtt { topic: Option<Topic> },
     File { path: Utf8PathBuf },
+    Http(HttpOutput),
 }
 
 /// The final outcome of a sequence of transformations applied by a flow to a message
@@ -144,6 +160,9 @@ pub enum Transport {
         qos: QoS,
         #[serde(default)]
         retain: bool,
+        /// MQTT 5 properties, exchanged with the broker only when using MQTT 5
+        #[serde(default, skip_serializing_if = "MqttProperties::is_empty")]
+        properties: MqttProperties,
     },
 }
 
@@ -165,6 +184,9 @@ pub enum FlowError {
     #[error(transparent)]
     StreamingSourceError(#[from] WatchError),
 
+    #[error(transparent)]
+    HttpOutputError(#[from] HttpOutputError),
+
     #[error(transparent)]
     Anyhow(#[from] anyhow::Error),
 }
@@ -529,6 +551,15 @@ impl Display for FlowInput {
             FlowInput::StreamCommand { command, .. } => {
                 write!(f, "Streaming command: {command}")
             }
+            FlowInput::StreamUnixSocket { path, .. } => {
+                write!(f, "Listening on unix socket: {path}")
+            }
+            FlowInput::StreamTcp { address, .. } => {
+                write!(f, "Listening on tcp: {address}")
+            }
+            FlowInput::HttpListener { address } => {
+                write!(f, "Listening on http: {address}")
+            }
         }
     }
 }
@@ -543,18 +574,24 @@ impl FlowInput {
 
     pub fn enforced_topic(&self) -> Option<&str> {
         match self {
-            FlowInput::Mqtt { .. } => None,
+            FlowInput::Mqtt { .. } | FlowInput::HttpListener { .. } => None,
             FlowInput::PollFile { topic, .. }
             | FlowInput::PollCommand { topic, .. }
             | FlowInput::StreamFile { topic, .. }
-            | FlowInput::StreamCommand { topic, .. } => Some(topic),
+            | FlowInput::StreamCommand { topic, .. }
+            | FlowInput::StreamUnixSocket { topic, .. }
+            | FlowInput::StreamTcp { topic, .. } => Some(topic),
         }
     }
 
     pub fn is_streaming(&self) -> bool {
         matches!(
             self,
-            FlowInput::StreamFile { .. } | FlowInput::StreamCommand { .. }
+            FlowInput::StreamFile { .. }
+                | FlowInput::StreamCommand { .. }
+                | FlowInput::StreamUnixSocket { .. }
+                | FlowInput::StreamTcp { .. }
+                | FlowInput::HttpListener { .. }
         )
     }
 
@@ -564,7 +601,10 @@ impl FlowInput {
             FlowInput::PollFile { topic, .. }
             | FlowInput::PollCommand { topic, .. }
             | FlowInput::StreamFile { topic, .. }
-            | FlowInput::StreamCommand { topic, .. } => topic == &message.topic,
+            | FlowInput::StreamCommand { topic, .. }
+            | FlowInput::StreamUnixSocket { topic, .. }
+            | FlowInput::StreamTcp { topic, .. } => topic == &message.topic,
+            FlowInput::HttpListener { .. } => false,
         }
     }
 }
@@ -603,6 +643,21 @@ impl Message {
     pub fn payload_str(&self) -> Option<&str> {
         std::str::from_utf8(&self.payload).ok()
     }
+
+    /// Mark this message to be published as retained, keeping its QoS and properties if any
+    pub fn with_retain(mut self) -> Self {
+        match &mut self.transport {
+            Some(Transport::Mqtt { retain, .. }) => *retain = true,
+            None => {
+                self.transport = Some(Transport::Mqtt {
+                    qos: QoS::AtLeastOnce,
+                    retain: true,
+                    properties: Default::default(),
+                })
+            }
+        }
+        self
+    }
 }
 
 impl Display for Message {
@@ -652,6 +707,7 @@ impl From<MqttMessage> for Message {
         let transport = Transport::Mqtt {
             qos: message.qos,
             retain: message.retain,
+            properties: message.properties.clone(),
         };
         let (topic, payload) = message.split();
         Message {
@@ -673,9 +729,14 @@ impl TryFrom<Message> for MqttMessage {
 
         let mqtt_message = MqttMessage::new(&topic, message.payload);
         match message.transport {
-            Some(Transport::Mqtt { qos, retain }) => {
-                Ok(mqtt_message.with_qos(qos).with_retain_flag(retain))
-            }
+            Some(Transport::Mqtt {
+                qos,
+                retain,
+                properties,
+            }) => Ok(mqtt_message
+                .with_qos(qos)
+                .with_retain_flag(retain)
+                .with_properties(properties)),
             _ => Ok(mqtt_message),
         }
     }
@@ -767,6 +828,29 @@ mod tests {
         assert_eq!(messages[1].topic, "te/other");
     }
 
+    #[test]
+    fn retained_messages_keep_their_properties() {
+        let properties = MqttProperties {
+            content_type: Some("application/json".to_string()),
+            ..MqttProperties::default()
+        };
+        let mut message = Message::new("te/device/main///cmd/restart/1", b"{}");
+        message.transport = Some(Transport::Mqtt {
+            qos: QoS::ExactlyOnce,
+            retain: false,
+            properties: properties.clone(),
+        });
+
+        assert!(
+            message.with_retain().transport
+                == Some(Transport::Mqtt {
+                    qos: QoS::ExactlyOnce,
+                    retain: true,
+                    properties,
+                })
+        );
+    }
+
     /// A malformed message and a valid message for the same not-yet-registered entity are
     /// both cached, then replayed together in a single batch when the entity is born. The
     /// malformed message must yield an error WITHOUT discarding the valid message batched
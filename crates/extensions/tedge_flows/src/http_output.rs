@@ -0,0 +1,979 @@
+//! HTTP outputs
+//!
+//! The messages produced by a flow can be sent to an HTTP endpoint,
+//! using a URL derived from the message topic and payload.
+//!
+//! To not block the flows on slow or unreachable endpoints, each HTTP output is served by a background task,
+//! which groups the messages into batches and retries the failed requests with an exponential backoff.
+//! While a request is retried, the task keeps receiving messages, which are grouped into the pending batches.
+//! The requests that eventually fail are reported back to the flows mapper, along with their messages,
+//! to be published on the `errors` output of the flow.
+use crate::flow::Message;
+use backoff::future::retry_notify;
+use backoff::ExponentialBackoff;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use certificate::http_client::is_status_retryable;
+use certificate::CloudHttpConfig;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::AsciiSet;
+use percent_encoding::NON_ALPHANUMERIC;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Identity;
+use reqwest::Method;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::future::pending;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::OnceCell;
+use tokio::time::sleep_until;
+use tokio::time::Instant;
+use tracing::warn;
+use tracing::Instrument;
+
+/// Maximum number of messages queued for an HTTP output, before dropping new ones
+///
+/// This bounds both the channel to the background task and the messages pending in this task.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// The characters percent-encoded in the values substituted in a URL template,
+/// i.e. all but the unreserved characters of RFC 3986
+const URL_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Loads on demand the root certificates, proxy and client identity used to send messages to HTTP outputs
+pub type HttpConfigLoader = Arc<
+    dyn Fn() -> BoxFuture<'static, anyhow::Result<(CloudHttpConfig, Option<Identity>)>>
+        + Send
+        + Sync,
+>;
+
+/// An HTTP endpoint to which the messages of a flow are sent
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct HttpOutput {
+    pub url: UrlTemplate,
+    pub method: Method,
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub batch: BatchPolicy,
+    pub retry: RetryPolicy,
+}
+
+/// How messages are grouped into HTTP requests
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct BatchPolicy {
+    /// Maximum number of messages sent in a single request
+    ///
+    /// When set to 1 (the default), each message payload is sent as is, in its own request.
+    pub max_size: usize,
+
+    /// Maximum delay a message is retained, waiting for a batch to be complete
+    pub max_delay: Duration,
+
+    /// How the payloads of a batch are combined into a request body
+    pub format: BatchFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Hash)]
+pub enum BatchFormat {
+    /// A JSON array of the message payloads, non-JSON payloads being sent as JSON strings
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+
+    /// The message payloads, one per line
+    #[serde(rename = "lines")]
+    Lines,
+}
+
+/// How failed requests are retried
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RetryPolicy {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+
+    /// Delay after which a request is abandoned, if still failing
+    pub max_elapsed_time: Duration,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        BatchPolicy {
+            max_size: 1,
+            max_delay: Duration::from_secs(1),
+            format: BatchFormat::Json,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(30),
+            max_elapsed_time: Duration::from_secs(120),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self) -> ExponentialBackoff {
+        ExponentialBackoff {
+            initial_interval: self.initial_interval,
+            max_interval: self.max_interval,
+            max_elapsed_time: Some(self.max_elapsed_time),
+            ..Default::default()
+        }
+    }
+}
+
+/// A URL with placeholders substituted for each message
+///
+/// - `${.topic}` is replaced by the message topic
+/// - `${.topic.N}` is replaced by the N-th level of the message topic (starting at 0)
+/// - `${.payload.x.y}` is replaced by the value of the `x.y` property of the JSON message payload
+///
+/// The substituted values are percent-encoded, so each of them is a single path segment or query value:
+/// e.g. the `/` of a topic are encoded as `%2F`.
+/// Empty values as well as `.` and `..` are rejected, as they would change the URL path.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UrlTemplate {
+    template: String,
+    parts: Vec<UrlPart>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+enum UrlPart {
+    Text(String),
+    Topic,
+    TopicLevel(usize),
+    PayloadProperty(Vec<String>),
+}
+
+impl UrlTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = vec![];
+        let mut input = template;
+        while let Some(start) = input.find("${") {
+            let Some(len) = input[start..].find('}') else {
+                return Err(format!("unclosed template expression in {template}"));
+            };
+            if start > 0 {
+                parts.push(UrlPart::Text(input[..start].to_string()));
+            }
+            let expr = input[start + 2..start + len].trim();
+            parts.push(UrlPart::parse(expr)?);
+            input = &input[start + len + 1..];
+        }
+        if !input.is_empty() {
+            parts.push(UrlPart::Text(input.to_string()));
+        }
+
+        Ok(UrlTemplate {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    pub fn render(&self, message: &Message) -> Result<Url, HttpOutputError> {
+        let mut url = String::new();
+        let mut payload = None;
+        for part in &self.parts {
+            match part {
+                UrlPart::Text(text) => url.push_str(text),
+                UrlPart::Topic => self.push_value(&mut url, &message.topic)?,
+                UrlPart::TopicLevel(index) => {
+                    let Some(level) = message.topic.split('/').nth(*index) else {
+                        return Err(
+                            self.error(format!("no level {index} in topic {}", message.topic))
+                        );
+                    };
+                    self.push_value(&mut url, level)?
+                }
+                UrlPart::PayloadProperty(path) => {
+                    if payload.is_none() {
+                        let json = serde_json::from_slice::<Value>(&message.payload)
+                            .map_err(|_| self.error("the payload is not JSON".to_string()))?;
+                        payload = Some(json);
+                    }
+                    let Some(value) = path
+                        .iter()
+                        .try_fold(payload.as_ref().unwrap(), |value, key| value.get(key))
+                    else {
+                        return Err(
+                            self.error(format!("no property {} in payload", path.join(".")))
+                        );
+                    };
+                    match value {
+                        Value::String(text) => self.push_value(&mut url, text)?,
+                        _ => self.push_value(&mut url, &value.to_string())?,
+                    }
+                }
+            }
+        }
+
+        Url::parse(&url).map_err(|err| HttpOutputError::InvalidUrl {
+            url,
+            reason: err.to_string(),
+        })
+    }
+
+    /// Append a percent-encoded value to a URL
+    fn push_value(&self, url: &mut String, value: &str) -> Result<(), HttpOutputError> {
+        if matches!(value, "" | "." | "..") {
+            return Err(self.error(format!("invalid URL value: {value:?}")));
+        }
+        url.extend(utf8_percent_encode(value, URL_VALUE));
+        Ok(())
+    }
+
+    fn error(&self, reason: String) -> HttpOutputError {
+        HttpOutputError::UrlTemplateError {
+            template: self.template.clone(),
+            reason,
+        }
+    }
+}
+
+impl UrlPart {
+    fn parse(expr: &str) -> Result<Self, String> {
+        let unknown = || format!("unknown template expression: ${{{expr}}}");
+        match expr.strip_prefix(".topic") {
+            Some("") => return Ok(UrlPart::Topic),
+            Some(level) => {
+                let index = level
+                    .strip_prefix('.')
+                    .and_then(|index| index.parse().ok())
+                    .ok_or_else(unknown)?;
+                return Ok(UrlPart::TopicLevel(index));
+            }
+            None => (),
+        }
+        match expr.strip_prefix(".payload.") {
+            Some(path) if !path.is_empty() => Ok(UrlPart::PayloadProperty(
+                path.split('.').map(|key| key.to_string()).collect(),
+            )),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+impl Display for UrlTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum HttpOutputError {
+    #[error("Cannot build the HTTP client: {0}")]
+    ClientError(String),
+
+    #[error("Cannot derive a URL from {template}: {reason}")]
+    UrlTemplateError { template: String, reason: String },
+
+    #[error("Not a valid URL {url}: {reason}")]
+    InvalidUrl { url: String, reason: String },
+
+    #[error("HTTP request to {url} failed: {reason}")]
+    RequestFailed { url: Url, reason: String },
+
+    #[error("HTTP request to {url} failed with status {status}")]
+    UnexpectedStatus { url: Url, status: u16 },
+
+    #[error("Too many messages queued for {url}: {dropped} message(s) dropped")]
+    Overloaded { url: UrlTemplate, dropped: usize },
+}
+
+/// A failure to deliver messages to the HTTP output of a flow
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpOutputFailure {
+    pub flow: Utf8PathBuf,
+    pub output: HttpOutput,
+    pub error: HttpOutputError,
+
+    /// The messages that have not been delivered, if known
+    ///
+    /// The messages dropped because too many messages are queued are only counted.
+    pub messages: Vec<Message>,
+}
+
+/// The HTTP outputs of all the flows, each being served by a background task
+pub(crate) struct HttpOutputs {
+    client: LazyHttpClient,
+    sinks: HashMap<(Utf8PathBuf, HttpOutput), mpsc::Sender<Message>>,
+    failure_sender: mpsc::UnboundedSender<HttpOutputFailure>,
+    failure_receiver: mpsc::UnboundedReceiver<HttpOutputFailure>,
+}
+
+impl HttpOutputs {
+    pub fn new(http_config: HttpConfigLoader) -> Self {
+        let (failure_sender, failure_receiver) = mpsc::unbounded_channel();
+        HttpOutputs {
+            client: LazyHttpClient {
+                loader: http_config,
+                client: Arc::new(OnceCell::new()),
+            },
+            sinks: HashMap::new(),
+            failure_sender,
+            failure_receiver,
+        }
+    }
+
+    /// Queue a message to be sent to the HTTP output of a flow
+    ///
+    /// Any failure is reported asynchronously by [HttpOutputs::next_failure].
+    pub fn send(&mut self, flow: &Utf8Path, output: &HttpOutput, message: Message) {
+        if let Err((error, message)) = self.try_send(flow, output, message) {
+            self.report(flow, output, error, vec![message]);
+        }
+    }
+
+    /// Wait for the next delivery failure
+    pub async fn next_failure(&mut self) -> Option<HttpOutputFailure> {
+        self.failure_receiver.recv().await
+    }
+
+    /// Stop the HTTP outputs that are no longer used by the flows
+    ///
+    /// The messages already queued for these outputs are sent before the background tasks terminate.
+    pub fn retain(&mut self, is_used: impl Fn(&Utf8Path, &HttpOutput) -> bool) {
+        self.sinks.retain(|(flow, output), _| is_used(flow, output))
+    }
+
+    fn try_send(
+        &mut self,
+        flow: &Utf8Path,
+        output: &HttpOutput,
+        message: Message,
+    ) -> Result<(), (HttpOutputError, Message)> {
+        let key = (flow.to_owned(), output.clone());
+        let message = match self.sinks.get(&key).map(|sink| sink.try_send(message)) {
+            None => message,
+            Some(Ok(())) => return Ok(()),
+            Some(Err(TrySendError::Full(message))) => {
+                let error = HttpOutputError::Overloaded {
+                    url: output.url.clone(),
+                    dropped: 1,
+                };
+                return Err((error, message));
+            }
+            Some(Err(TrySendError::Closed(message))) => message,
+        };
+
+        let sink = self.spawn_sink(flow, output);
+        let _ = sink.try_send(message);
+        self.sinks.insert(key, sink);
+        Ok(())
+    }
+
+    fn spawn_sink(&mut self, flow: &Utf8Path, output: &HttpOutput) -> mpsc::Sender<Message> {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let sink = HttpSink {
+            flow: flow.to_owned(),
+            output: output.clone(),
+            client: self.client.clone(),
+            failures: self.failure_sender.clone(),
+            batches: vec![],
+            ready: VecDeque::new(),
+            queued: 0,
+            dropped: 0,
+        };
+        tokio::spawn(sink.run(receiver).instrument(tracing::Span::current()));
+        sender
+    }
+
+    fn report(
+        &self,
+        flow: &Utf8Path,
+        output: &HttpOutput,
+        error: HttpOutputError,
+        messages: Vec<Message>,
+    ) {
+        let _ = self.failure_sender.send(HttpOutputFailure {
+            flow: flow.to_owned(),
+            output: output.clone(),
+            error,
+            messages,
+        });
+    }
+}
+
+/// The HTTP client shared by all the HTTP outputs
+///
+/// The client is only built when a first request is sent,
+/// so the certificates are not loaded unless an HTTP output is used.
+#[derive(Clone)]
+struct LazyHttpClient {
+    loader: HttpConfigLoader,
+    client: Arc<OnceCell<reqwest::Client>>,
+}
+
+impl LazyHttpClient {
+    /// Return the HTTP client, building it on first use
+    ///
+    /// On failure, the client will be built again on the next request.
+    async fn get(&self) -> Result<reqwest::Client, HttpOutputError> {
+        let client = self
+            .client
+            .get_or_try_init(|| async {
+                let (http_config, identity) = (self.loader)()
+                    .await
+                    .map_err(|err| HttpOutputError::ClientError(format!("{err:#}")))?;
+                let mut builder = http_config.client_builder();
+                if let Some(identity) = identity {
+                    builder = builder.identity(identity);
+                }
+                builder
+                    .build()
+                    .map_err(|err| HttpOutputError::ClientError(err.to_string()))
+            })
+            .await?;
+        Ok(client.clone())
+    }
+}
+
+/// The background task sending the messages of a flow to an HTTP output
+struct HttpSink {
+    flow: Utf8PathBuf,
+    output: HttpOutput,
+    client: LazyHttpClient,
+    failures: mpsc::UnboundedSender<HttpOutputFailure>,
+
+    /// The batches waiting to be complete
+    batches: Vec<Batch>,
+
+    /// The complete batches waiting for the request in flight to terminate
+    ready: VecDeque<Batch>,
+
+    /// Number of messages received and not sent yet
+    queued: usize,
+
+    /// Number of messages dropped since the last report
+    dropped: usize,
+}
+
+/// The messages waiting to be sent to the same URL
+struct Batch {
+    url: Url,
+    deadline: Instant,
+    messages: Vec<Message>,
+}
+
+/// A request sending a batch, resolving to the batch and the outcome of the request
+type Request = BoxFuture<'static, (Batch, Result<(), HttpOutputError>)>;
+
+impl HttpSink {
+    async fn run(mut self, mut messages: mpsc::Receiver<Message>) {
+        let mut in_flight: Option<Request> = None;
+        let mut closed = false;
+        loop {
+            if in_flight.is_none() {
+                in_flight = self.ready.pop_front().map(|batch| self.request(batch));
+            }
+            if closed && in_flight.is_none() {
+                break;
+            }
+
+            let deadline = self.batches.iter().map(|batch| batch.deadline).min();
+            tokio::select! {
+                message = messages.recv(), if !closed => match message {
+                    Some(message) => self.push(message),
+                    None => {
+                        // Send all the pending messages before terminating
+                        closed = true;
+                        self.ready.extend(std::mem::take(&mut self.batches));
+                    }
+                },
+                _ = sleep_until_some(deadline) => self.flush_expired(),
+                (batch, result) = complete_some(&mut in_flight) => {
+                    in_flight = None;
+                    self.on_sent(batch, result);
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, message: Message) {
+        let url = match self.output.url.render(&message) {
+            Ok(url) => url,
+            Err(error) => return self.report(error, vec![message]),
+        };
+        if self.queued >= QUEUE_CAPACITY {
+            self.dropped += 1;
+            return;
+        }
+        self.queued += 1;
+
+        let index = match self.batches.iter().position(|batch| batch.url == url) {
+            Some(index) => index,
+            None => {
+                self.batches.push(Batch {
+                    url,
+                    deadline: Instant::now() + self.output.batch.max_delay,
+                    messages: vec![],
+                });
+                self.batches.len() - 1
+            }
+        };
+        self.batches[index].messages.push(message);
+
+        if self.batches[index].messages.len() >= self.output.batch.max_size {
+            let batch = self.batches.remove(index);
+            self.ready.push_back(batch)
+        }
+    }
+
+    fn flush_expired(&mut self) {
+        let now = Instant::now();
+        let (expired, waiting) = std::mem::take(&mut self.batches)
+            .into_iter()
+            .partition(|batch| batch.deadline <= now);
+        self.batches = waiting;
+        self.ready.extend(expired);
+    }
+
+    fn on_sent(&mut self, batch: Batch, result: Result<(), HttpOutputError>) {
+        self.queued -= batch.messages.len();
+        if let Err(error) = result {
+            self.report(error, batch.messages)
+        }
+        if self.dropped > 0 {
+            let error = HttpOutputError::Overloaded {
+                url: self.output.url.clone(),
+                dropped: std::mem::take(&mut self.dropped),
+            };
+            self.report(error, vec![])
+        }
+    }
+
+    /// Build the request sending a batch, which is run while new messages are received
+    fn request(&self, batch: Batch) -> Request {
+        let client = self.client.clone();
+        let output = self.output.clone();
+        let flow = self.flow.clone();
+        let body = self.body(&batch.messages);
+        async move {
+            let result = send(&client, &output, &flow, &batch.url, &body).await;
+            (batch, result)
+        }
+        .boxed()
+    }
+
+    fn body(&self, messages: &[Message]) -> Vec<u8> {
+        if self.output.batch.max_size <= 1 && messages.len() == 1 {
+            return messages[0].payload.clone();
+        }
+        match self.output.batch.format {
+            BatchFormat::Json => {
+                let values: Vec<Value> = messages
+                    .iter()
+                    .map(|message| {
+                        serde_json::from_slice(&message.payload).unwrap_or_else(|_| {
+                            Value::String(String::from_utf8_lossy(&message.payload).to_string())
+                        })
+                    })
+                    .collect();
+                Value::Array(values).to_string().into_bytes()
+            }
+            BatchFormat::Lines => {
+                let payloads: Vec<&[u8]> = messages
+                    .iter()
+                    .map(|message| message.payload.as_slice())
+                    .collect();
+                payloads.join(&b'\n')
+            }
+        }
+    }
+
+    fn report(&self, error: HttpOutputError, messages: Vec<Message>) {
+        let _ = self.failures.send(HttpOutputFailure {
+            flow: self.flow.clone(),
+            output: self.output.clone(),
+            error,
+            messages,
+        });
+    }
+}
+
+/// Send a request, retrying on transient failures
+async fn send(
+    client: &LazyHttpClient,
+    output: &HttpOutput,
+    flow: &Utf8Path,
+    url: &Url,
+    body: &[u8],
+) -> Result<(), HttpOutputError> {
+    let client = client.get().await?;
+    let set_content_type = output.batch.max_size > 1
+        && output.batch.format == BatchFormat::Json
+        && !output.headers.iter().any(|(name, _)| name == CONTENT_TYPE);
+
+    let request = || send_request(&client, output, url, body, set_content_type);
+    retry_notify(
+        output.retry.backoff(),
+        request,
+        |err, delay| warn!(target: "flows", "{flow}: {err}: retrying in {delay:?}"),
+    )
+    .await
+}
+
+async fn send_request(
+    client: &reqwest::Client,
+    output: &HttpOutput,
+    url: &Url,
+    body: &[u8],
+    set_content_type: bool,
+) -> Result<(), backoff::Error<HttpOutputError>> {
+    let mut request = client
+        .request(output.method.clone(), url.clone())
+        .body(body.to_vec());
+    for (name, value) in &output.headers {
+        request = request.header(name, value);
+    }
+    if set_content_type {
+        request = request.header(CONTENT_TYPE, "application/json");
+    }
+
+    let response = request.send().await.map_err(|err| {
+        backoff::Error::transient(HttpOutputError::RequestFailed {
+            url: url.clone(),
+            reason: err.to_string(),
+        })
+    })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let error = HttpOutputError::UnexpectedStatus {
+        url: url.clone(),
+        status: status.as_u16(),
+    };
+    if is_status_retryable(status) {
+        Err(backoff::Error::transient(error))
+    } else {
+        Err(backoff::Error::permanent(error))
+    }
+}
+
+async fn complete_some<T>(request: &mut Option<BoxFuture<'static, T>>) -> T {
+    match request {
+        Some(request) => request.await,
+        None => pending().await,
+    }
+}
+
+async fn sleep_until_some(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => sleep_until(deadline).await,
+        None => pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+    use axum::http::StatusCode;
+    use axum::routing::any;
+    use axum::Router;
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use test_case::test_case;
+    use tokio::net::TcpListener;
+    use tokio::sync::Semaphore;
+
+    #[test_case(
+        "http://localhost/${.topic}",
+        "te/device/main///m/",
+        "{}",
+        "http://localhost/te%2Fdevice%2Fmain%2F%2F%2Fm%2F"
+    )]
+    #[test_case(
+        "http://localhost/devices/${.topic.2}",
+        "te/device/child///m/",
+        "{}",
+        "http://localhost/devices/child"
+    )]
+    #[test_case(
+        "http://localhost/${.payload.id}/${.payload.x.y}",
+        "t",
+        r#"{"id":"abc","x":{"y":42}}"#,
+        "http://localhost/abc/42"
+    )]
+    #[test_case(
+        "http://localhost/devices/${.payload.id}/data?type=${.payload.type}",
+        "t",
+        r#"{"id":"../admin?x=1#y","type":"a&b=c"}"#,
+        "http://localhost/devices/..%2Fadmin%3Fx%3D1%23y/data?type=a%26b%3Dc"
+    )]
+    fn rendering_url_templates(template: &str, topic: &str, payload: &str, expected: &str) {
+        let message = Message::new(topic, payload);
+        let url = UrlTemplate::parse(template)
+            .unwrap()
+            .render(&message)
+            .unwrap();
+        assert_eq!(url.as_str(), expected);
+    }
+
+    #[test_case("http://localhost/${.topic.9}", "a/b")]
+    #[test_case("http://localhost/${.payload.id}", "not json")]
+    #[test_case("http://localhost/${.payload.id}", r#"{"name":"abc"}"#)]
+    #[test_case("http://localhost/${.payload.id}", r#"{"id":".."}"#)]
+    #[test_case("http://localhost/${.payload.id}", r#"{"id":""}"#)]
+    fn failing_to_render_url_templates(template: &str, payload: &str) {
+        let message = Message::new("a/b", payload);
+        let error = UrlTemplate::parse(template).unwrap().render(&message);
+        assert!(matches!(
+            error,
+            Err(HttpOutputError::UrlTemplateError { .. })
+        ));
+    }
+
+    #[test_case("http://localhost/${topic}")]
+    #[test_case("http://localhost/${.topic.x}")]
+    #[test_case("http://localhost/${.payload}")]
+    #[test_case("http://localhost/${.topic")]
+    fn rejecting_ill_formed_url_templates(template: &str) {
+        assert!(UrlTemplate::parse(template).is_err());
+    }
+
+    #[tokio::test]
+    async fn posting_messages_to_a_url_derived_from_the_topic() {
+        let (base_url, mut requests) = spawn_server(vec![]).await;
+        let output = http_output(&format!("{base_url}/${{.topic.1}}"), BatchPolicy::default());
+        let mut outputs = HttpOutputs::new(test_http_config());
+
+        outputs.send(
+            Utf8Path::new("flow.toml"),
+            &output,
+            Message::new("sensors/s1", "21.5"),
+        );
+        outputs.send(
+            Utf8Path::new("flow.toml"),
+            &output,
+            Message::new("sensors/s2", "18.0"),
+        );
+
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            ("/s1".into(), "21.5".into())
+        );
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            ("/s2".into(), "18.0".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn sending_messages_in_batches() {
+        let (base_url, mut requests) = spawn_server(vec![]).await;
+        let batch = BatchPolicy {
+            max_size: 3,
+            max_delay: Duration::from_millis(100),
+            format: BatchFormat::Json,
+        };
+        let output = http_output(&format!("{base_url}/batch"), batch);
+        let mut outputs = HttpOutputs::new(test_http_config());
+
+        for payload in [r#"{"x":1}"#, r#"{"x":2}"#, r#"{"x":3}"#, "not json"] {
+            outputs.send(
+                Utf8Path::new("flow.toml"),
+                &output,
+                Message::new("t", payload),
+            );
+        }
+
+        // A batch is sent as soon as complete
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            ("/batch".into(), r#"[{"x":1},{"x":2},{"x":3}]"#.into())
+        );
+        // Or when its max delay is reached
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            ("/batch".into(), r#"["not json"]"#.into())
+        );
+    }
+
+    #[tokio::test]
+    async fn retrying_on_retryable_failures() {
+        let (base_url, mut requests) = spawn_server(vec![
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::TOO_MANY_REQUESTS,
+        ])
+        .await;
+        let output = http_output(&format!("{base_url}/retry"), BatchPolicy::default());
+        let mut outputs = HttpOutputs::new(test_http_config());
+
+        outputs.send(
+            Utf8Path::new("flow.toml"),
+            &output,
+            Message::new("t", "hello"),
+        );
+
+        for _ in 0..3 {
+            assert_eq!(
+                requests.recv().await.unwrap(),
+                ("/retry".into(), "hello".into())
+            );
+        }
+        assert!(outputs.failure_receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn reporting_failures() {
+        let (base_url, mut requests) = spawn_server(vec![StatusCode::BAD_REQUEST]).await;
+        let output = http_output(&format!("{base_url}/fail"), BatchPolicy::default());
+        let mut outputs = HttpOutputs::new(test_http_config());
+
+        outputs.send(
+            Utf8Path::new("flow.toml"),
+            &output,
+            Message::new("t", "hello"),
+        );
+
+        // Non-retryable failures are not retried
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            ("/fail".into(), "hello".into())
+        );
+        let failure = outputs.next_failure().await.unwrap();
+        assert_eq!(failure.flow, Utf8PathBuf::from("flow.toml"));
+        assert_eq!(failure.output, output);
+        assert_eq!(
+            failure.error,
+            HttpOutputError::UnexpectedStatus {
+                url: Url::parse(&format!("{base_url}/fail")).unwrap(),
+                status: 400
+            }
+        );
+        // Along with the messages not delivered
+        assert_eq!(failure.messages, vec![Message::new("t", "hello")]);
+    }
+
+    #[tokio::test]
+    async fn receiving_messages_while_a_request_is_pending() {
+        let (base_url, mut requests, responses) = spawn_paused_server().await;
+        let output = http_output(&format!("{base_url}/slow"), BatchPolicy::default());
+        let mut outputs = HttpOutputs::new(test_http_config());
+        let flow = Utf8Path::new("flow.toml");
+
+        outputs.send(flow, &output, Message::new("t", "first"));
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            ("/slow".into(), "first".into())
+        );
+
+        // While the first request is pending, the messages are still received,
+        // those exceeding the queue capacity being counted and dropped
+        for i in 0..QUEUE_CAPACITY + 5 {
+            outputs.send(flow, &output, Message::new("t", i.to_string()));
+            if i % 100 == 0 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(outputs.failure_receiver.try_recv().is_err());
+
+        // The dropped messages are reported once, when the pending request completes
+        responses.add_permits(2 * QUEUE_CAPACITY);
+        let failure = outputs.next_failure().await.unwrap();
+        assert_eq!(
+            failure.error,
+            HttpOutputError::Overloaded {
+                url: output.url.clone(),
+                dropped: 6
+            }
+        );
+        assert!(failure.messages.is_empty());
+
+        // The queued messages are sent
+        assert_eq!(requests.recv().await.unwrap(), ("/slow".into(), "0".into()));
+    }
+
+    fn test_http_config() -> HttpConfigLoader {
+        Arc::new(|| Box::pin(async { Ok((CloudHttpConfig::test_value(), None)) }))
+    }
+
+    fn http_output(url: &str, batch: BatchPolicy) -> HttpOutput {
+        HttpOutput {
+            url: UrlTemplate::parse(url).unwrap(),
+            method: Method::POST,
+            headers: vec![],
+            batch,
+            retry: RetryPolicy {
+                initial_interval: Duration::from_millis(10),
+                max_interval: Duration::from_millis(50),
+                max_elapsed_time: Duration::from_secs(5),
+            },
+        }
+    }
+
+    /// Spawn an HTTP server recording the (path, body) of each request,
+    /// and responding with the given statuses, and then with `200 OK`
+    async fn spawn_server(
+        statuses: Vec<StatusCode>,
+    ) -> (String, mpsc::UnboundedReceiver<(String, String)>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let statuses = Arc::new(Mutex::new(VecDeque::from(statuses)));
+        let app = Router::new().route(
+            "/{*path}",
+            any(move |Path(path): Path<String>, body: String| async move {
+                let _ = sender.send((format!("/{path}"), body));
+                statuses
+                    .lock()
+                    .unwrap()
+                    .pop_front()
+                    .unwrap_or(StatusCode::OK)
+            }),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(axum::serve(listener, app).into_future());
+
+        (format!("http://127.0.0.1:{port}"), receiver)
+    }
+
+    /// Spawn an HTTP server recording the (path, body) of each request,
+    /// and responding with `200 OK` only when given the permit to do so
+    async fn spawn_paused_server() -> (
+        String,
+        mpsc::UnboundedReceiver<(String, String)>,
+        Arc<Semaphore>,
+    ) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let responses = Arc::new(Semaphore::new(0));
+        let permits = responses.clone();
+        let app = Router::new().route(
+            "/{*path}",
+            any(move |Path(path): Path<String>, body: String| async move {
+                let _ = sender.send((format!("/{path}"), body));
+                permits.acquire().await.unwrap().forget();
+                StatusCode::OK
+            }),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(axum::serve(listener, app).into_future());
+
+        (format!("http://127.0.0.1:{port}"), receiver, responses)
+    }
+}
@@ -188,6 +188,86 @@ impl StreamingSource for FileStreamingSource {
     }
 }
 
+pub struct UnixSocketStreamingSource {
+    flow: String,
+    topic: String,
+    path: Utf8PathBuf,
+}
+
+impl UnixSocketStreamingSource {
+    pub fn new(flow: String, topic: String, path: Utf8PathBuf) -> Self {
+        UnixSocketStreamingSource { flow, topic, path }
+    }
+}
+
+impl StreamingSource for UnixSocketStreamingSource {
+    fn watch_request(&self) -> Option<WatchRequest> {
+        Some(WatchRequest::WatchUnixSocket {
+            topic: self.flow.clone(),
+            path: self.path.clone(),
+        })
+    }
+
+    fn input_topic(&self) -> &str {
+        &self.topic
+    }
+}
+
+pub struct TcpStreamingSource {
+    flow: String,
+    topic: String,
+    address: String,
+}
+
+impl TcpStreamingSource {
+    pub fn new(flow: String, topic: String, address: String) -> Self {
+        TcpStreamingSource {
+            flow,
+            topic,
+            address,
+        }
+    }
+}
+
+impl StreamingSource for TcpStreamingSource {
+    fn watch_request(&self) -> Option<WatchRequest> {
+        Some(WatchRequest::WatchTcp {
+            topic: self.flow.clone(),
+            address: self.address.clone(),
+        })
+    }
+
+    fn input_topic(&self) -> &str {
+        &self.topic
+    }
+}
+
+/// Messages posted over HTTP, the request path being used as message topic
+pub struct HttpStreamingSource {
+    flow: String,
+    address: String,
+}
+
+impl HttpStreamingSource {
+    pub fn new(flow: String, address: String) -> Self {
+        HttpStreamingSource { flow, address }
+    }
+}
+
+impl StreamingSource for HttpStreamingSource {
+    fn watch_request(&self) -> Option<WatchRequest> {
+        Some(WatchRequest::WatchHttp {
+            topic: self.flow.clone(),
+            address: self.address.clone(),
+        })
+    }
+
+    /// No fixed topic: each message is given the path of the request that posted it
+    fn input_topic(&self) -> &str {
+        ""
+    }
+}
+
 struct PollInterval {
     polling_interval: Duration,
     next_deadline: Instant,
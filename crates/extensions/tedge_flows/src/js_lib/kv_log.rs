@@ -0,0 +1,491 @@
+//! The context log is a persistent append-only log of flow context updates.
+//! Each line is the JSON representation of a key-value update in a given context.
+//! The underlying file is a JSON lines file, compacted when redundant entries pile up.
+//!
+//! The file is written by a background thread, so the flows are not blocked by disk writes.
+//! The updates are synced on disk in batches: all the updates queued while the previous batch was synced.
+use crate::js_lib::kv_store::FlowContext;
+use crate::js_value::JsonValue;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use tracing::warn;
+
+const LOG_FILE_NAME: &str = "flows_context.jsonl";
+const LOG_FILE_TEMP_NAME: &str = "flows_context.jsonl.tmp";
+const LOG_FORMAT_VERSION: &str = "1.0";
+const DEFAULT_REDUNDANCY_THRESHOLD: usize = 100;
+
+/// A persistent append-only log of flow context entries.
+///
+/// Tracks the latest value per context and key,
+/// and compacts the on-disk file when redundant entries exceed a configured threshold.
+#[derive(Debug)]
+pub(crate) struct KVLog {
+    writer: Option<LogWriter>,
+    redundancy_threshold: usize,
+    // Latest value per context and key; keys removed by null-value writes are absent
+    entries: BTreeMap<(PersistedContext, String), serde_json::Value>,
+    // Number of entries on disk; subtracting entries.len() gives the redundant entry count
+    total_entries: usize,
+}
+
+/// On-disk representation of a [FlowContext]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(tag = "scope", content = "name", rename_all = "lowercase")]
+enum PersistedContext {
+    Mapper,
+    Flow(String),
+    Script(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct LogEntry {
+    context: PersistedContext,
+    key: String,
+    value: serde_json::Value,
+}
+
+/// Handle to the background thread writing the log file
+#[derive(Debug)]
+struct LogWriter {
+    requests: mpsc::Sender<WriteRequest>,
+    thread: JoinHandle<()>,
+}
+
+enum WriteRequest {
+    /// Append a JSON line to the log
+    Append(String),
+    /// Replace the log content with the given JSON lines
+    Compact(Vec<String>),
+}
+
+/// The state of the background thread writing the log file
+struct LogFile {
+    writer: BufWriter<File>,
+    log_dir: PathBuf,
+}
+
+impl KVLog {
+    pub fn open<P>(log_dir: P) -> Result<KVLog, std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with_redundancy_threshold(log_dir, DEFAULT_REDUNDANCY_THRESHOLD)
+    }
+
+    pub fn open_with_redundancy_threshold<P>(
+        log_dir: P,
+        redundancy_threshold: usize,
+    ) -> Result<KVLog, std::io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let log_dir = log_dir.as_ref();
+        std::fs::create_dir_all(log_dir)?;
+        let log_path = log_dir.join(LOG_FILE_NAME);
+        Self::truncate_torn_line(&log_path)?;
+
+        let mut entries = BTreeMap::new();
+        let mut total_entries = 0;
+        if let Ok(lines) = Self::read_file(log_dir) {
+            for entry in lines {
+                total_entries += 1;
+                let key = (entry.context, entry.key);
+                if entry.value.is_null() {
+                    entries.remove(&key);
+                } else {
+                    entries.insert(key, entry.value);
+                }
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)?;
+        let metadata = file.metadata()?;
+        let mut writer = BufWriter::new(file);
+
+        if metadata.len() == 0 {
+            writeln!(writer, "{}", json!({ "version": LOG_FORMAT_VERSION }))?;
+            writer.flush()?;
+        }
+
+        let log_file = LogFile {
+            writer,
+            log_dir: log_dir.to_path_buf(),
+        };
+        let (requests, receiver) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("flows-context-log".to_string())
+            .spawn(move || log_file.run(receiver))?;
+
+        Ok(KVLog {
+            writer: Some(LogWriter { requests, thread }),
+            redundancy_threshold,
+            entries,
+            total_entries,
+        })
+    }
+
+    /// Remove the incomplete line a crash might have left at the end of the log,
+    /// so the next entry is not glued to it
+    fn truncate_torn_line(log_path: &Path) -> Result<(), std::io::Error> {
+        let content = match std::fs::read(log_path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if matches!(content.last(), None | Some(b'\n')) {
+            return Ok(());
+        }
+
+        let complete_len = content
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |pos| pos + 1);
+        warn!(target: "flows", "Truncating the incomplete last line of the flow context log");
+        let file = OpenOptions::new().write(true).open(log_path)?;
+        file.set_len(complete_len as u64)?;
+        file.sync_all()
+    }
+
+    /// Reads raw entries from the log file on disk
+    fn read_file(log_dir: &Path) -> Result<Vec<LogEntry>, std::io::Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(log_dir.join(LOG_FILE_NAME))?;
+        let mut reader = BufReader::new(file);
+
+        // Skip the version line
+        let mut version_info = String::new();
+        reader.read_line(&mut version_info)?;
+
+        let mut entries = Vec::new();
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            match reader.read_line(&mut buffer) {
+                Ok(0) => break,
+                Ok(_) => match serde_json::from_str::<LogEntry>(&buffer) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => {
+                        warn!(target: "flows", "Skipping corrupt flow context log entry: {e}")
+                    }
+                },
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Iterates over the latest value persisted for each context and key
+    pub fn entries(&self) -> impl Iterator<Item = (FlowContext, &str, JsonValue)> + '_ {
+        self.entries.iter().map(|((context, key), value)| {
+            (
+                FlowContext::from(context.clone()),
+                key.as_str(),
+                JsonValue::from(value.clone()),
+            )
+        })
+    }
+
+    /// Persists a context update to the log
+    ///
+    /// A `JsonValue::Null` value records the removal of the key.
+    pub fn append(
+        &mut self,
+        context: &FlowContext,
+        key: &str,
+        value: &JsonValue,
+    ) -> Result<(), std::io::Error> {
+        let entry = LogEntry {
+            context: PersistedContext::from(context),
+            key: key.to_string(),
+            value: serde_json::Value::from(value.clone()),
+        };
+        let entry_key = (entry.context.clone(), entry.key.clone());
+        let is_new_key = !self.entries.contains_key(&entry_key);
+        if is_new_key && entry.value.is_null() {
+            // Nothing to remove
+            return Ok(());
+        }
+
+        let json_line = serde_json::to_string(&entry)?;
+        self.send(WriteRequest::Append(json_line))?;
+
+        if entry.value.is_null() {
+            self.entries.remove(&entry_key);
+        } else {
+            self.entries.insert(entry_key, entry.value);
+        }
+        self.total_entries += 1;
+
+        if !is_new_key {
+            let redundant_count = self.total_entries - self.entries.len();
+            if redundant_count >= self.redundancy_threshold {
+                self.compact()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compact(&mut self) -> Result<(), std::io::Error> {
+        let mut lines = Vec::with_capacity(self.entries.len());
+        for ((context, key), value) in &self.entries {
+            let entry = LogEntry {
+                context: context.clone(),
+                key: key.clone(),
+                value: value.clone(),
+            };
+            lines.push(serde_json::to_string(&entry)?);
+        }
+        self.send(WriteRequest::Compact(lines))?;
+        self.total_entries = self.entries.len();
+
+        Ok(())
+    }
+
+    fn send(&self, request: WriteRequest) -> Result<(), std::io::Error> {
+        self.writer
+            .as_ref()
+            .and_then(|writer| writer.requests.send(request).ok())
+            .ok_or_else(|| std::io::Error::other("the flow context log writer has stopped"))
+    }
+}
+
+impl Drop for KVLog {
+    /// Wait for all the pending updates to be written on disk
+    fn drop(&mut self) {
+        if let Some(LogWriter { requests, thread }) = self.writer.take() {
+            drop(requests);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl LogFile {
+    fn run(mut self, requests: mpsc::Receiver<WriteRequest>) {
+        while let Ok(request) = requests.recv() {
+            // Write all the pending requests before syncing the file
+            let batch = std::iter::once(request).chain(requests.try_iter());
+            if let Err(err) = self.write(batch) {
+                warn!(target: "flows", "Failed to persist flow context entries: {err}");
+            }
+        }
+    }
+
+    fn write(&mut self, batch: impl Iterator<Item = WriteRequest>) -> Result<(), std::io::Error> {
+        for request in batch {
+            match request {
+                WriteRequest::Append(line) => writeln!(self.writer, "{line}")?,
+                WriteRequest::Compact(lines) => self.compact(lines)?,
+            }
+        }
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()
+    }
+
+    fn compact(&mut self, lines: Vec<String>) -> Result<(), std::io::Error> {
+        self.writer.flush()?;
+
+        // Write the in-memory compacted state to a temp file.
+        let temp_path = self.log_dir.join(LOG_FILE_TEMP_NAME);
+        {
+            let temp_file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&temp_path)?;
+            let mut temp_writer = BufWriter::new(temp_file);
+
+            writeln!(temp_writer, "{}", json!({ "version": LOG_FORMAT_VERSION }))?;
+            for line in lines {
+                writeln!(temp_writer, "{line}")?;
+            }
+            temp_writer.flush()?;
+            temp_writer.get_ref().sync_all()?;
+        }
+
+        // Atomically replace the log with the compacted version.
+        std::fs::rename(&temp_path, self.log_dir.join(LOG_FILE_NAME))?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(self.log_dir.join(LOG_FILE_NAME))?;
+        self.writer = BufWriter::new(file);
+
+        Ok(())
+    }
+}
+
+impl From<&FlowContext> for PersistedContext {
+    fn from(context: &FlowContext) -> Self {
+        match context {
+            FlowContext::Mapper => PersistedContext::Mapper,
+            FlowContext::Flow(name) => PersistedContext::Flow(name.clone()),
+            FlowContext::Script(name) => PersistedContext::Script(name.clone()),
+        }
+    }
+}
+
+impl From<PersistedContext> for FlowContext {
+    fn from(context: PersistedContext) -> Self {
+        match context {
+            PersistedContext::Mapper => FlowContext::Mapper,
+            PersistedContext::Flow(name) => FlowContext::Flow(name),
+            PersistedContext::Script(name) => FlowContext::Script(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn entries_are_persisted_across_log_instances() {
+        let temp_dir = tempdir().unwrap();
+
+        {
+            let mut log = KVLog::open(&temp_dir).unwrap();
+            log.append(&FlowContext::Mapper, "foo", &JsonValue::from(json!("bar")))
+                .unwrap();
+            log.append(
+                &FlowContext::flow("flow.toml"),
+                "count",
+                &JsonValue::from(json!(42)),
+            )
+            .unwrap();
+        }
+
+        let log = KVLog::open(&temp_dir).unwrap();
+        let entries: Vec<_> = log
+            .entries()
+            .map(|(context, key, value)| (context, key.to_string(), value))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    FlowContext::Mapper,
+                    "foo".to_string(),
+                    JsonValue::from(json!("bar"))
+                ),
+                (
+                    FlowContext::flow("flow.toml"),
+                    "count".to_string(),
+                    JsonValue::from(json!(42))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_keys_are_not_restored() {
+        let temp_dir = tempdir().unwrap();
+
+        {
+            let mut log = KVLog::open(&temp_dir).unwrap();
+            log.append(&FlowContext::Mapper, "foo", &JsonValue::from(json!("bar")))
+                .unwrap();
+            log.append(&FlowContext::Mapper, "foo", &JsonValue::Null)
+                .unwrap();
+        }
+
+        let log = KVLog::open(&temp_dir).unwrap();
+        assert_eq!(log.entries().count(), 0);
+    }
+
+    #[test]
+    fn compaction_keeps_only_the_latest_value_per_key() {
+        let temp_dir = tempdir().unwrap();
+
+        {
+            let mut log = KVLog::open_with_redundancy_threshold(&temp_dir, 1).unwrap();
+            log.append(&FlowContext::Mapper, "count", &JsonValue::from(json!(1)))
+                .unwrap();
+            log.append(&FlowContext::Mapper, "count", &JsonValue::from(json!(2)))
+                .unwrap(); // redundant=1 → compact
+            log.append(&FlowContext::Mapper, "count", &JsonValue::from(json!(3)))
+                .unwrap(); // redundant=1 → compact
+        }
+
+        let content = std::fs::read_to_string(temp_dir.path().join(LOG_FILE_NAME)).unwrap();
+        assert_eq!(content.lines().count(), 2);
+
+        let log = KVLog::open(&temp_dir).unwrap();
+        let entries: Vec<_> = log.entries().map(|(_, _, value)| value).collect();
+        assert_eq!(entries, vec![JsonValue::from(json!(3))]);
+    }
+
+    #[test]
+    fn corrupt_log_line_is_skipped_and_valid_entries_are_preserved() {
+        let temp_dir = tempdir().unwrap();
+
+        {
+            let mut log = KVLog::open(&temp_dir).unwrap();
+            log.append(&FlowContext::Mapper, "a", &JsonValue::from(json!(1)))
+                .unwrap();
+        }
+        {
+            let mut file = OpenOptions::new()
+                .append(true)
+                .open(temp_dir.path().join(LOG_FILE_NAME))
+                .unwrap();
+            writeln!(file, "this is not valid json").unwrap();
+        }
+        {
+            let mut log = KVLog::open(&temp_dir).unwrap();
+            log.append(&FlowContext::Mapper, "b", &JsonValue::from(json!(2)))
+                .unwrap();
+        }
+
+        let log = KVLog::open(&temp_dir).unwrap();
+        let keys: Vec<_> = log.entries().map(|(_, key, _)| key.to_string()).collect();
+        assert_eq!(keys, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn torn_trailing_line_is_truncated_before_appending() {
+        let temp_dir = tempdir().unwrap();
+        let log_path = temp_dir.path().join(LOG_FILE_NAME);
+
+        {
+            let mut log = KVLog::open(&temp_dir).unwrap();
+            log.append(&FlowContext::Mapper, "a", &JsonValue::from(json!(1)))
+                .unwrap();
+        }
+        {
+            // As left by a crash in the middle of a write
+            let mut file = OpenOptions::new().append(true).open(&log_path).unwrap();
+            write!(file, r#"{{"context":{{"scope":"mapper"}},"key":"b","va"#).unwrap();
+        }
+        {
+            let mut log = KVLog::open(&temp_dir).unwrap();
+            log.append(&FlowContext::Mapper, "c", &JsonValue::from(json!(3)))
+                .unwrap();
+        }
+
+        let content = std::fs::read_to_string(&log_path).unwrap();
+        assert_eq!(content.lines().count(), 3, "{content}");
+
+        let log = KVLog::open(&temp_dir).unwrap();
+        let keys: Vec<_> = log.entries().map(|(_, key, _)| key.to_string()).collect();
+        assert_eq!(keys, vec!["a".to_string(), "c".to_string()]);
+    }
+}
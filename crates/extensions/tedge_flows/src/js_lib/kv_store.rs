@@ -1,4 +1,6 @@
+use crate::js_lib::kv_log::KVLog;
 use crate::js_value::JsonValue;
+use glob::Pattern;
 use rquickjs::class::Trace;
 use rquickjs::Ctx;
 use rquickjs::IntoJs;
@@ -9,8 +11,10 @@ use rquickjs::Value;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::ops::Deref;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
+use tracing::warn;
 
 #[derive(Clone, Debug, Default, JsLifetime)]
 pub struct FlowContextHandle {
@@ -22,6 +26,31 @@ struct LayeredKVStore {
     global: BTreeMap<String, JsonValue>,
     scoped: HashMap<FlowContext, BTreeMap<String, JsonValue>>,
     updates: Vec<FlowContextUpdate>,
+    persistence: Option<ContextPersistence>,
+}
+
+/// Persists on disk the context entries selected by the flows
+#[derive(Debug)]
+struct ContextPersistence {
+    log: KVLog,
+    // Persistence policy per flow, indexed by flow source path
+    policies: HashMap<String, PersistPolicy>,
+}
+
+/// The context scopes that can be persisted
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextScope {
+    Mapper,
+    Flow,
+    Script,
+}
+
+/// Which context entries of a flow are persisted on disk
+#[derive(Clone, Debug, Default)]
+pub struct PersistPolicy {
+    scopes: Vec<ContextScope>,
+    keys: Vec<Pattern>,
 }
 
 #[derive(Debug)]
@@ -37,6 +66,35 @@ pub trait KVStore: Send + Sync {
 }
 
 impl FlowContextHandle {
+    /// Create a context handle which persists entries in the given directory
+    ///
+    /// The entries persisted by a previous instance are restored.
+    /// Only the entries selected by a flow [PersistPolicy] are persisted.
+    pub fn with_persistence(log_dir: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let log = KVLog::open(log_dir)?;
+        let mut store = LayeredKVStore::default();
+        for (context, key, value) in log.entries() {
+            store.entry(&context).set_value(key, value);
+        }
+        store.persistence = Some(ContextPersistence {
+            log,
+            policies: HashMap::new(),
+        });
+        Ok(FlowContextHandle {
+            handle: Arc::new(Mutex::new(store)),
+        })
+    }
+
+    /// Set the persistence policy of a flow, overriding any previous policy for that flow
+    ///
+    /// This is a no-op if this handle has not been created with persistence.
+    pub fn set_persist_policy(&self, flow: &str, policy: PersistPolicy) {
+        let mut data = self.handle.lock().unwrap();
+        if let Some(persistence) = data.persistence.as_mut() {
+            persistence.policies.insert(flow.to_string(), policy);
+        }
+    }
+
     pub fn get_value(&self, key: &str) -> JsonValue {
         self.get(&FlowContext::Mapper, key)
     }
@@ -49,6 +107,23 @@ impl FlowContextHandle {
         self.keys(&FlowContext::Mapper)
     }
 
+    /// Get a value from the context private to a flow step, given the instance name of that step
+    pub fn get_step_value(&self, step: &str, key: &str) -> JsonValue {
+        self.get(&FlowContext::script(step), key)
+    }
+
+    /// Set a value in the context private to a flow step, removing the key if the value is null
+    ///
+    /// Unlike for the mapper context, no [FlowContextUpdate] is notified.
+    pub fn set_step_value(&self, step: &str, key: &str, value: JsonValue) {
+        self.insert(&FlowContext::script(step), key, value);
+    }
+
+    /// List the keys of the context private to a flow step
+    pub fn get_step_keys(&self, step: &str) -> Vec<String> {
+        self.keys(&FlowContext::script(step))
+    }
+
     pub(crate) fn init(&self, ctx: &Ctx<'_>) {
         self.store_as_userdata(ctx)
     }
@@ -151,6 +226,9 @@ impl LayeredKVStore {
     }
 
     fn insert(&mut self, context: &FlowContext, key: &str, value: JsonValue) {
+        if let Some(persistence) = self.persistence.as_mut() {
+            persistence.persist(context, key, &value);
+        }
         self.entry(context).set_value(key, value);
         if context.is_global() {
             self.updates.push(FlowContextUpdate::Inserted {
@@ -160,6 +238,9 @@ impl LayeredKVStore {
     }
 
     pub fn remove(&mut self, context: &FlowContext, key: &str) {
+        if let Some(persistence) = self.persistence.as_mut() {
+            persistence.persist(context, key, &JsonValue::Null);
+        }
         if let Some(map) = self.context_mut(context) {
             map.set_value(key, JsonValue::Null);
             if context.is_global() {
@@ -171,6 +252,54 @@ impl LayeredKVStore {
     }
 }
 
+impl ContextPersistence {
+    fn persist(&mut self, context: &FlowContext, key: &str, value: &JsonValue) {
+        // Removals are always logged, as the policy might have changed since the key was persisted
+        if value != &JsonValue::Null && !self.is_persisted(context, key) {
+            return;
+        }
+        if let Err(err) = self.log.append(context, key, value) {
+            warn!(target: "flows", "Failed to persist context entry {key}: {err}");
+        }
+    }
+
+    fn is_persisted(&self, context: &FlowContext, key: &str) -> bool {
+        match context {
+            FlowContext::Mapper => self
+                .policies
+                .values()
+                .any(|policy| policy.persists(ContextScope::Mapper, key)),
+            FlowContext::Flow(flow) => self
+                .policies
+                .get(flow)
+                .is_some_and(|policy| policy.persists(ContextScope::Flow, key)),
+            FlowContext::Script(step) => step
+                .split('|')
+                .next()
+                .and_then(|flow| self.policies.get(flow))
+                .is_some_and(|policy| policy.persists(ContextScope::Script, key)),
+        }
+    }
+}
+
+impl PersistPolicy {
+    /// Build a policy persisting the keys matching any of the given glob patterns
+    ///
+    /// All the keys of the given scopes are persisted if no patterns are provided.
+    pub fn try_new(scopes: Vec<ContextScope>, keys: &[String]) -> Result<Self, glob::PatternError> {
+        let keys = keys
+            .iter()
+            .map(|key| Pattern::new(key))
+            .collect::<Result<_, _>>()?;
+        Ok(PersistPolicy { scopes, keys })
+    }
+
+    fn persists(&self, scope: ContextScope, key: &str) -> bool {
+        self.scopes.contains(&scope)
+            && (self.keys.is_empty() || self.keys.iter().any(|pattern| pattern.matches(key)))
+    }
+}
+
 #[derive(Clone, Debug, Trace, JsLifetime, Hash, Eq, PartialEq)]
 #[rquickjs::class(frozen)]
 pub(crate) enum FlowContext {
@@ -250,3 +379,83 @@ impl KVStore for FlowContextHandle {
         self.keys(&FlowContext::Mapper)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::steps::FlowStep;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn persisted_values_are_restored_after_a_restart() {
+        let temp_dir = tempdir().unwrap();
+        let policy = PersistPolicy::try_new(vec![ContextScope::Mapper], &[]).unwrap();
+
+        {
+            let context = FlowContextHandle::with_persistence(&temp_dir).unwrap();
+            context.set_persist_policy("flow.toml", policy.clone());
+            context.set_value("counter", JsonValue::from(json!(42)));
+            context.set_value("last-seen", JsonValue::from(json!({"temperature": 21.5})));
+        }
+
+        // Simulated restart
+        let context = FlowContextHandle::with_persistence(&temp_dir).unwrap();
+        context.set_persist_policy("flow.toml", policy);
+        assert_eq!(context.get_value("counter"), JsonValue::from(json!(42)));
+        assert_eq!(
+            context.get_value("last-seen"),
+            JsonValue::from(json!({"temperature": 21.5}))
+        );
+    }
+
+    #[test]
+    fn only_the_keys_selected_by_a_flow_are_persisted() {
+        let temp_dir = tempdir().unwrap();
+        let flow = "/flows/dedup.toml";
+        let step = FlowStep::instance_name(flow, "main.js", 0);
+
+        {
+            let context = FlowContextHandle::with_persistence(&temp_dir).unwrap();
+            let keys = ["window/*".to_string()];
+            let policy = PersistPolicy::try_new(vec![ContextScope::Script], &keys).unwrap();
+            context.set_persist_policy(flow, policy);
+            context.insert(&FlowContext::script(&step), "window/a", json!(1));
+            context.insert(&FlowContext::script(&step), "other", json!(2));
+            context.insert(&FlowContext::flow(flow), "window/b", json!(3));
+            context.set_value("window/c", JsonValue::from(json!(4)));
+        }
+
+        let context = FlowContextHandle::with_persistence(&temp_dir).unwrap();
+        assert_eq!(
+            context.get(&FlowContext::script(&step), "window/a"),
+            JsonValue::from(json!(1))
+        );
+        assert_eq!(
+            context.get(&FlowContext::script(&step), "other"),
+            JsonValue::Null
+        );
+        assert_eq!(
+            context.get(&FlowContext::flow(flow), "window/b"),
+            JsonValue::Null
+        );
+        assert_eq!(context.get_value("window/c"), JsonValue::Null);
+    }
+
+    #[test]
+    fn removed_values_are_not_restored_after_a_restart() {
+        let temp_dir = tempdir().unwrap();
+        let policy = PersistPolicy::try_new(vec![ContextScope::Flow], &[]).unwrap();
+        let flow = FlowContext::flow("flow.toml");
+
+        {
+            let context = FlowContextHandle::with_persistence(&temp_dir).unwrap();
+            context.set_persist_policy("flow.toml", policy);
+            context.insert(&flow, "foo", json!("bar"));
+            context.remove(&flow, "foo");
+        }
+
+        let context = FlowContextHandle::with_persistence(&temp_dir).unwrap();
+        assert_eq!(context.get(&flow, "foo"), JsonValue::Null);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod console;
 pub mod crypto;
+pub mod kv_log;
 pub mod kv_store;
 pub mod text_decoder;
 pub mod text_encoder;
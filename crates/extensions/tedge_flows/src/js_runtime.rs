@@ -104,6 +104,18 @@ impl JsRuntime {
         Ok(())
     }
 
+    /// Load a JavaScript module which is not part of a flow
+    ///
+    /// The functions exported by the module can then be called using [JsRuntime::call_function].
+    pub async fn load_module(
+        &mut self,
+        module_name: String,
+        path: impl AsRef<Utf8Path>,
+    ) -> Result<(), LoadError> {
+        self.load_file(module_name, path).await?;
+        Ok(())
+    }
+
     fn set_exports(script: &mut JsScript, exports: &[&str]) {
         for export in exports {
             match *export {
@@ -835,6 +835,56 @@ export function onMessage(message) {
         );
     }
 
+    #[tokio::test]
+    async fn reading_and_setting_mqtt5_properties() {
+        let js = r#"
+export function onMessage(message) {
+    let properties = message.mqtt.properties
+    let source = properties.userProperties.find(([key, _]) => key == "source")[1]
+    return {
+        topic: properties.responseTopic,
+        payload: `${source} sent ${properties.contentType}`,
+        mqtt: {
+            properties: {
+                userProperties: [["source", "flow"]],
+                correlationData: properties.correlationData,
+                messageExpiryInterval: 60,
+            }
+        }
+    }
+}
+        "#;
+        let (runtime, mut script) = runtime_with(js).await;
+
+        let input = MqttMessage::new(&Topic::new_unchecked("foo/bar"), "{}")
+            .with_user_property("source", "sensor")
+            .with_content_type("application/json")
+            .with_response_topic(&Topic::new_unchecked("foo/response"), b"1234".to_vec())
+            .into();
+        let output = script
+            .on_message(&runtime, SystemTime::now(), &input)
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+
+        let mqtt_message = MqttMessage::try_from(output).unwrap();
+        assert_eq!(mqtt_message.topic.name, "foo/response");
+        assert_eq!(
+            mqtt_message.payload_str().unwrap(),
+            "sensor sent application/json"
+        );
+        assert_eq!(
+            mqtt_message.properties,
+            tedge_mqtt_ext::MqttProperties {
+                user_properties: vec![("source".to_string(), "flow".to_string())],
+                message_expiry_interval: Some(60),
+                correlation_data: Some(b"1234".to_vec()),
+                ..Default::default()
+            }
+        );
+    }
+
     /// Regression test for https://github.com/thin-edge/thin-edge.io/issues/4028
     ///
     /// Repeatedly reloading a flow script used to exhaust the QuickJS 16 MB
@@ -873,6 +923,32 @@ export function onMessage(message) {
         }
     }
 
+    #[tokio::test]
+    async fn calling_a_function_of_a_standalone_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Utf8PathBuf::try_from(dir.path().join("step.js")).unwrap();
+        std::fs::write(
+            &path,
+            r#"export function onCommand(payload) { return { status: "next", count: payload.count + 1 } }"#,
+        )
+        .unwrap();
+
+        let mut runtime = JsRuntime::with_default().await.unwrap();
+        runtime
+            .load_module("step".to_string(), &path)
+            .await
+            .unwrap();
+
+        let output = runtime
+            .call_function("step", "onCommand", vec![json!({"count": 41}).into()])
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::Value::from(output),
+            json!({"status": "next", "count": 42})
+        );
+    }
+
     #[tokio::test]
     async fn using_onstartup() {
         let js = r#"export function onStartup() { return {topic: "te/main/device///m/", payload: "hello world" } }"#;
@@ -120,10 +120,20 @@ impl From<Message> for JsonValue {
     fn from(value: Message) -> Self {
         let payload = JsonValue::Bytes(value.payload.clone());
         let mqtt = value.transport.map(|transport| match transport {
-            Transport::Mqtt { qos, retain } => JsonValue::object([
-                ("qos", JsonValue::Number((qos as u8).into())),
-                ("retain", JsonValue::Bool(retain)),
-            ]),
+            Transport::Mqtt {
+                qos,
+                retain,
+                properties,
+            } => {
+                let properties = (!properties.is_empty())
+                    .then(|| JsonValue::from_value(properties).ok())
+                    .flatten();
+                JsonValue::object([
+                    ("qos", JsonValue::Number((qos as u8).into())),
+                    ("retain", JsonValue::Bool(retain)),
+                    ("properties", JsonValue::option(properties)),
+                ])
+            }
         });
         JsonValue::object([
             ("topic", JsonValue::string(value.topic)),
@@ -1,7 +1,9 @@
 mod actor;
+mod cloud_commands;
 mod config;
 mod connected_flow;
 mod flow;
+mod http_output;
 mod input_source;
 mod js_lib;
 mod js_runtime;
@@ -15,11 +17,15 @@ mod steps;
 mod transformers;
 
 use crate::actor::FlowsMapper;
+pub use crate::cloud_commands::CloudCommands;
+pub use crate::cloud_commands::CommandMessage;
 pub use crate::config::derive_flow_name;
 pub use crate::config::ConfigError;
 pub use crate::config::FlowConfig;
 pub use crate::connected_flow::ConnectedFlowRegistry;
 pub use crate::flow::*;
+pub use crate::http_output::HttpConfigLoader;
+use crate::http_output::HttpOutputFailure;
 pub use crate::params::empty_mapper_params;
 pub use crate::params::MapperParams;
 pub use crate::registry::BaseFlowRegistry;
@@ -30,12 +36,16 @@ use crate::stats::MqttStatsPublisher;
 use crate::stats::StatsFilter;
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
+use certificate::CloudHttpConfig;
+use futures::FutureExt;
 pub use js_lib::kv_store::FlowContextHandle;
 pub use js_lib::kv_store::FlowContextUpdate;
+pub use js_runtime::JsRuntime;
 pub use js_runtime::JsRuntimeConfig;
 pub use js_value::JsonValue;
 use std::convert::Infallible;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tedge_actors::fan_in_message_type;
 use tedge_actors::Builder;
@@ -68,6 +78,8 @@ pub struct FlowsMapperConfig {
     pub(crate) stats_dump_interval: Duration,
     pub(crate) stats_filter: StatsFilter,
     pub(crate) js_config: JsRuntimeConfig,
+    pub(crate) context_dir: Option<Utf8PathBuf>,
+    pub(crate) http_config: HttpConfigLoader,
 }
 
 impl Default for FlowsMapperConfig {
@@ -112,6 +124,11 @@ impl FlowsMapperConfig {
                 publish_on_startup_stats,
             },
             js_config: JsRuntimeConfig::default(),
+            context_dir: None,
+            http_config: Arc::new(|| {
+                let http_config = CloudHttpConfig::new(Vec::<reqwest::Certificate>::new(), None);
+                async move { Ok((http_config, None)) }.boxed()
+            }),
         }
     }
 
@@ -123,9 +140,28 @@ impl FlowsMapperConfig {
         };
         FlowsMapperConfig { js_config, ..self }
     }
+
+    /// Persist in the given directory the context entries selected by the flows
+    pub fn with_context_dir(self, context_dir: impl Into<Utf8PathBuf>) -> Self {
+        FlowsMapperConfig {
+            context_dir: Some(context_dir.into()),
+            ..self
+        }
+    }
+
+    /// Use the root certificates, proxy and client identity returned by the given loader to send messages to HTTP outputs
+    ///
+    /// The loader is only called when a message is sent to an HTTP output for the first time,
+    /// and again on failure.
+    pub fn with_http_config(self, loader: HttpConfigLoader) -> Self {
+        FlowsMapperConfig {
+            http_config: loader,
+            ..self
+        }
+    }
 }
 
-fan_in_message_type!(InputMessage[MqttMessage, WatchEvent, FsWatchEvent, Tick]: Clone, Debug, Eq, PartialEq);
+fan_in_message_type!(InputMessage[MqttMessage, WatchEvent, FsWatchEvent, Tick, HttpOutputFailure]: Clone, Debug, Eq, PartialEq);
 
 pub fn flows_dir(mapper_dir: &Utf8Path) -> Utf8PathBuf {
     mapper_dir.join("flows")
@@ -167,7 +203,11 @@ impl FlowsMapperBuilder {
         registry: ConnectedFlowRegistry,
         config: FlowsMapperConfig,
     ) -> Result<Self, LoadError> {
-        let context = FlowContextHandle::default();
+        let context = match &config.context_dir {
+            None => FlowContextHandle::default(),
+            Some(dir) => FlowContextHandle::with_persistence(dir)
+                .map_err(|err| LoadError::from_io(err, dir))?,
+        };
         let mut processor =
             MessageProcessor::with_context(registry, config.js_config.clone(), context).await?;
         let message_box = SimpleMessageBoxBuilder::new("TedgeFlows", 16);
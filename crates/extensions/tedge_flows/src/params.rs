@@ -181,11 +181,15 @@ impl<'a, T: MapperParams + ?Sized> Params<&'a T> {
     /// the string representation of that value is inserted.
     ///
     /// If a path reference no known parameter, the string "null" is used the replacement string.
+    ///
+    /// Path expressions starting with a dot, as `${.topic}`, are left unchanged,
+    /// as these refer to message values only known at runtime.
     pub fn substitute_inner_paths(&self, input: &str) -> String {
         input
             .split_inclusive('}')
             .flat_map(|s| match s.find("${") {
                 None => vec![s.to_string()],
+                Some(i) if s[i + 2..].trim_start().starts_with('.') => vec![s.to_string()],
                 Some(i) => {
                     let (prefix, expr) = s.split_at(i);
                     let value = self
@@ -354,6 +358,10 @@ mod tests {
             ("-- ${params.z} --", "-- [1,2,3] --"),
             ("-- ${params.y} --", r#"-- {"a":"foo","b":"bar"} --"#),
             ("-- ${params.unknown} --", r#"-- null --"#),
+            (
+                "${params.y.b}/${.topic.2}/${.payload.x}",
+                "bar/${.topic.2}/${.payload.x}",
+            ),
         ] {
             assert_eq!(params.substitute_inner_paths(expr).as_str(), value);
         }
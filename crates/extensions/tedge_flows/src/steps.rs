@@ -51,7 +51,8 @@ impl FlowStep {
         }
     }
 
-    pub fn new_transformer(instance_name: String, transformer: Box<dyn Transformer>) -> Self {
+    pub fn new_transformer(instance_name: String, mut transformer: Box<dyn Transformer>) -> Self {
+        transformer.set_step_name(&instance_name);
         FlowStep {
             handler: StepHandler::Transformer(instance_name, transformer),
             interval: Duration::ZERO,
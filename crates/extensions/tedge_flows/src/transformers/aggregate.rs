@@ -0,0 +1,766 @@
+use crate::ConfigError;
+use crate::FlowContextHandle;
+use crate::FlowError;
+use crate::JsonValue;
+use crate::Message;
+use crate::Transformer;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::SystemTime;
+use tedge_mqtt_ext::TopicFilter;
+
+/// The key under which the partial windows are stored in the context of the step
+const STATE_KEY: &str = "windows";
+
+/// Down-sample `te` measurements, publishing statistics computed over time windows.
+///
+/// Only the messages received on the measurement topics (`te/+/+/+/+/m/+`) or on the configured `topics` are aggregated.
+/// The numeric values of the measurements received on a topic are accumulated per series,
+/// a series being either a top-level value (`{"temperature": 21.3}`)
+/// or a value of a group (`{"pressure": {"in": 1.2, "out": 0.9}}`).
+///
+/// Windows are defined using processing time:
+///
+/// - The messages are accumulated into buckets of `slide` length, aligned on the epoch.
+/// - A window spans the `window / slide` consecutive buckets preceding its end.
+/// - On interval, a message is published for each window that has been closed since the previous interval.
+/// - Without `slide`, the windows are tumbling (`slide = window`), otherwise they are sliding.
+///
+/// The published message is sent on the input topic, with a `time` property set to the end of the window
+/// and, for each series and each configured statistic, a value which name is suffixed by the statistic name.
+///
+/// The partial windows are stored in the context private to the step, each time a bucket is created or published,
+/// so a reloaded flow resumes where the previous instance stopped.
+///
+/// - any message that cannot be processed (e.g. received on another topic or not a JSON object with numeric values)
+///   is forwarded unchanged
+/// - measurements published on different topics are never aggregated together
+/// - the `time` of the measurements is ignored, while their `type` is carried over to the published messages
+#[derive(Clone)]
+pub struct Aggregate {
+    window: Duration,
+    slide: Duration,
+    statistics: Vec<Statistic>,
+    suffixes: HashMap<Statistic, String>,
+    topics: TopicFilter,
+    step_name: String,
+    state: AggregateState,
+    /// Set once the state saved by a previous instance has been restored
+    restored: bool,
+    /// Set when the state has changed since last saved in the context
+    dirty: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Statistic {
+    Min,
+    Max,
+    Avg,
+    Sum,
+    Count,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct AggregateConfig {
+    window: String,
+    slide: Option<String>,
+    statistics: Vec<Statistic>,
+    suffixes: HashMap<Statistic, String>,
+    topics: Vec<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct AggregateState {
+    topics: BTreeMap<String, TopicWindows>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct TopicWindows {
+    /// The type of the last measurement received on the topic, if any
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    measurement_type: Option<String>,
+
+    /// End of the last published window, in milliseconds since the epoch
+    published_until: u64,
+
+    /// Statistics per series, per bucket indexed by start time in milliseconds since the epoch
+    buckets: BTreeMap<u64, BTreeMap<String, Stats>>,
+}
+
+/// The numeric values of a thin-edge JSON measurement, indexed by series name
+struct Measurement {
+    measurement_type: Option<String>,
+    values: Vec<(String, f64)>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Stats {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Aggregate {
+    fn default() -> Self {
+        Aggregate {
+            window: Duration::from_secs(60),
+            slide: Duration::from_secs(60),
+            statistics: vec![Statistic::Min, Statistic::Max, Statistic::Avg],
+            suffixes: HashMap::new(),
+            topics: TopicFilter::new_unchecked(super::MEASUREMENT_TOPICS),
+            step_name: "aggregate".to_string(),
+            state: AggregateState::default(),
+            restored: false,
+            dirty: false,
+        }
+    }
+}
+
+impl Default for AggregateConfig {
+    fn default() -> Self {
+        AggregateConfig {
+            window: "1m".to_string(),
+            slide: None,
+            statistics: vec![Statistic::Min, Statistic::Max, Statistic::Avg],
+            suffixes: HashMap::new(),
+            topics: vec![super::MEASUREMENT_TOPICS.to_string()],
+        }
+    }
+}
+
+impl Transformer for Aggregate {
+    fn name(&self) -> &str {
+        "aggregate"
+    }
+
+    fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError> {
+        let config: AggregateConfig = config.into_value().map_err(|err| {
+            ConfigError::IncorrectSetting(format!("Invalid aggregate config: {err}"))
+        })?;
+
+        let window = parse_duration("window", &config.window)?;
+        let slide = match &config.slide {
+            None => window,
+            Some(slide) => parse_duration("slide", slide)?,
+        };
+        if window.as_millis() % slide.as_millis() != 0 {
+            return Err(ConfigError::IncorrectSetting(format!(
+                "Invalid slide: {slide:?} is not a divisor of the window {window:?}"
+            )));
+        }
+        if config.statistics.is_empty() {
+            return Err(ConfigError::IncorrectSetting(
+                "Invalid statistics: at least one statistic is required".to_string(),
+            ));
+        }
+
+        let topics = crate::config::topic_filters(config.topics)?;
+
+        self.window = window;
+        self.slide = slide;
+        self.statistics = config.statistics;
+        self.suffixes = config.suffixes;
+        self.topics = topics;
+        Ok(())
+    }
+
+    fn set_step_name(&mut self, step_name: &str) {
+        self.step_name = step_name.to_string();
+    }
+
+    fn on_message(
+        &mut self,
+        timestamp: SystemTime,
+        message: &Message,
+        context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        if !self.topics.accept_topic_name(&message.topic) {
+            return Ok(vec![message.clone()]);
+        }
+        let Some(measurement) = Measurement::parse(message) else {
+            return Ok(vec![message.clone()]);
+        };
+
+        self.restore(context);
+        let bucket = bucket_start(timestamp, self.slide_ms());
+        let windows = self.state.topics.entry(message.topic.clone()).or_default();
+        if measurement.measurement_type.is_some() {
+            windows.measurement_type = measurement.measurement_type;
+        }
+        let new_bucket = !windows.buckets.contains_key(&bucket);
+        let series = windows.buckets.entry(bucket).or_default();
+        for (name, value) in measurement.values {
+            series
+                .entry(name)
+                .and_modify(|stats| stats.add(value))
+                .or_insert_with(|| Stats::new(value));
+        }
+        self.dirty = true;
+
+        // Saving the windows on each message would be too costly at high rates:
+        // the values received since the last save are only lost if the flow is reloaded before the next interval
+        if new_bucket {
+            self.save(context);
+        }
+        Ok(vec![])
+    }
+
+    fn is_periodic(&self) -> bool {
+        true
+    }
+
+    fn on_interval(
+        &mut self,
+        timestamp: SystemTime,
+        context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        self.restore(context);
+        let window = self.window_ms();
+        let slide = self.slide_ms();
+        // All the buckets starting before are complete
+        let closed_until = bucket_start(timestamp, slide);
+
+        let mut state = std::mem::take(&mut self.state);
+        let mut messages = vec![];
+        for (topic, windows) in state.topics.iter_mut() {
+            for end in windows.closed_window_ends(closed_until, window, slide) {
+                let from = end.saturating_sub(window);
+                if let Some(payload) = self.statistics_payload(windows, from, end) {
+                    messages.push(Message::new(topic.clone(), payload.to_string()));
+                }
+            }
+            if windows.published_until < closed_until {
+                windows.published_until = closed_until;
+                // Drop the buckets that will be part of no more windows
+                windows
+                    .buckets
+                    .retain(|start, _| start + window >= closed_until + slide);
+                self.dirty = true;
+            }
+        }
+        state
+            .topics
+            .retain(|_, windows| !windows.buckets.is_empty());
+        self.state = state;
+
+        self.save(context);
+        Ok(messages)
+    }
+}
+
+impl Aggregate {
+    fn window_ms(&self) -> u64 {
+        self.window.as_millis() as u64
+    }
+
+    fn slide_ms(&self) -> u64 {
+        self.slide.as_millis() as u64
+    }
+
+    fn suffix(&self, statistic: Statistic) -> String {
+        match self.suffixes.get(&statistic) {
+            Some(suffix) => suffix.clone(),
+            None => statistic.default_suffix().to_string(),
+        }
+    }
+
+    /// Restore the partial windows saved in the context by a previous instance of this step
+    fn restore(&mut self, context: &FlowContextHandle) {
+        if self.restored {
+            return;
+        }
+        self.restored = true;
+        match context.get_step_value(&self.step_name, STATE_KEY) {
+            JsonValue::Null => (),
+            saved => match saved.into_value() {
+                Ok(state) => self.state = state,
+                Err(err) => {
+                    tracing::warn!(target: "flows", "Ignoring aggregate windows saved by {}: {err}", self.step_name)
+                }
+            },
+        }
+    }
+
+    /// Save the partial windows in the context, so they are not lost when the flow is reloaded
+    fn save(&mut self, context: &FlowContextHandle) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let value = if self.state.topics.is_empty() {
+            JsonValue::Null
+        } else {
+            JsonValue::from_value(&self.state).unwrap_or(JsonValue::Null)
+        };
+        context.set_step_value(&self.step_name, STATE_KEY, value);
+    }
+
+    /// Build a thin-edge JSON payload from the statistics of the buckets in the `[from, to)` range
+    fn statistics_payload(&self, windows: &TopicWindows, from: u64, to: u64) -> Option<Value> {
+        let mut series: BTreeMap<&str, Stats> = BTreeMap::new();
+        for bucket in windows.buckets.range(from..to).map(|(_, bucket)| bucket) {
+            for (name, stats) in bucket {
+                series
+                    .entry(name)
+                    .and_modify(|merged| merged.merge(stats))
+                    .or_insert(*stats);
+            }
+        }
+        if series.is_empty() {
+            return None;
+        }
+
+        let mut payload = Map::new();
+        payload.insert("time".to_string(), json!(to as f64 / 1000.0));
+        if let Some(measurement_type) = &windows.measurement_type {
+            payload.insert("type".to_string(), json!(measurement_type));
+        }
+        for (name, stats) in series {
+            let (values, name) = match name.split_once('/') {
+                None => (&mut payload, name),
+                Some((group, name)) => {
+                    let group = payload
+                        .entry(group)
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    let Value::Object(group) = group else {
+                        continue;
+                    };
+                    (group, name)
+                }
+            };
+            for statistic in &self.statistics {
+                let key = format!("{name}{}", self.suffix(*statistic));
+                values.insert(key, stats.value(*statistic));
+            }
+        }
+        Some(Value::Object(payload))
+    }
+}
+
+impl Statistic {
+    fn default_suffix(&self) -> &'static str {
+        match self {
+            Statistic::Min => "_min",
+            Statistic::Max => "_max",
+            Statistic::Avg => "_avg",
+            Statistic::Sum => "_sum",
+            Statistic::Count => "_count",
+        }
+    }
+}
+
+impl TopicWindows {
+    /// The end times of the windows that are closed but not published yet, and that contain some values
+    fn closed_window_ends(&self, closed_until: u64, window: u64, slide: u64) -> Vec<u64> {
+        let (Some(first), Some(last)) =
+            (self.buckets.keys().next(), self.buckets.keys().next_back())
+        else {
+            return vec![];
+        };
+        let from = (first + slide).max(self.published_until + slide);
+        let to = closed_until.min(last + window);
+        (from..=to).step_by(slide as usize).collect()
+    }
+}
+
+impl Stats {
+    fn new(value: f64) -> Self {
+        Stats {
+            min: value,
+            max: value,
+            sum: value,
+            count: 1,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.merge(&Stats::new(value))
+    }
+
+    fn merge(&mut self, other: &Stats) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    fn value(&self, statistic: Statistic) -> Value {
+        match statistic {
+            Statistic::Min => json!(self.min),
+            Statistic::Max => json!(self.max),
+            Statistic::Avg => json!(self.sum / self.count as f64),
+            Statistic::Sum => json!(self.sum),
+            Statistic::Count => json!(self.count),
+        }
+    }
+}
+
+impl Measurement {
+    /// Extract the type and the numeric values of a thin-edge JSON measurement, ignoring its `time`
+    ///
+    /// Return `None` if the payload is not a JSON object or has no numeric values.
+    fn parse(message: &Message) -> Option<Self> {
+        let Ok(Value::Object(payload)) = serde_json::from_slice(message.payload.as_slice()) else {
+            return None;
+        };
+
+        let mut measurement_type = None;
+        let mut values = vec![];
+        for (name, value) in payload {
+            match (name.as_str(), value) {
+                ("time", _) => {}
+                ("type", Value::String(value)) => measurement_type = Some(value),
+                (_, Value::Number(number)) => {
+                    if let Some(value) = number.as_f64() {
+                        values.push((name, value))
+                    }
+                }
+                (_, Value::Object(group)) => {
+                    for (inner_name, inner_value) in group {
+                        if let Some(value) = inner_value.as_f64() {
+                            values.push((format!("{name}/{inner_name}"), value))
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        (!values.is_empty()).then_some(Measurement {
+            measurement_type,
+            values,
+        })
+    }
+}
+
+fn bucket_start(timestamp: SystemTime, slide: u64) -> u64 {
+    let epoch_ms = timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    epoch_ms - epoch_ms % slide
+}
+
+fn parse_duration(property: &str, value: &str) -> Result<Duration, ConfigError> {
+    match humantime::parse_duration(value) {
+        Ok(duration) if duration.as_millis() > 0 => Ok(duration),
+        _ => Err(ConfigError::IncorrectSetting(format!(
+            "Invalid {property}: not a positive duration: {value}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurements_are_aggregated_over_tumbling_windows() {
+        let context = FlowContextHandle::default();
+        let mut aggregate = aggregate(json!({ "window": "10s" }));
+
+        for (secs, payload) in [
+            (1, json!({ "temperature": 20.0 })),
+            (4, json!({ "temperature": 22.0, "pressure": { "in": 1.0 } })),
+            (9, json!({ "temperature": 24.0, "pressure": { "in": 3.0 } })),
+        ] {
+            let msg = Message::new("te/device/main///m/", payload.to_string());
+            assert!(aggregate
+                .on_message(at(secs), &msg, &context)
+                .unwrap()
+                .is_empty());
+        }
+
+        // Nothing is published till the window is closed
+        assert!(aggregate.on_interval(at(9), &context).unwrap().is_empty());
+
+        let messages = aggregate.on_interval(at(10), &context).unwrap();
+        assert_eq!(
+            topic_payloads(messages),
+            vec![(
+                "te/device/main///m/".to_string(),
+                json!({
+                    "time": 10.0,
+                    "temperature_min": 20.0,
+                    "temperature_max": 24.0,
+                    "temperature_avg": 22.0,
+                    "pressure": { "in_min": 1.0, "in_max": 3.0, "in_avg": 2.0 },
+                })
+            )]
+        );
+
+        // A window is published only once
+        assert!(aggregate.on_interval(at(11), &context).unwrap().is_empty());
+    }
+
+    #[test]
+    fn measurements_are_aggregated_over_sliding_windows() {
+        let context = FlowContextHandle::default();
+        let mut aggregate = aggregate(json!({
+            "window": "10s",
+            "slide": "5s",
+            "statistics": ["sum", "count"],
+        }));
+
+        for (secs, value) in [(1, 1.0), (6, 2.0), (11, 4.0)] {
+            let msg = Message::new(
+                "te/device/sensor///m/",
+                json!({ "flow": value }).to_string(),
+            );
+            aggregate.on_message(at(secs), &msg, &context).unwrap();
+        }
+
+        let messages = aggregate.on_interval(at(15), &context).unwrap();
+        assert_eq!(
+            topic_payloads(messages),
+            vec![
+                (
+                    "te/device/sensor///m/".to_string(),
+                    json!({ "time": 5.0, "flow_sum": 1.0, "flow_count": 1 })
+                ),
+                (
+                    "te/device/sensor///m/".to_string(),
+                    json!({ "time": 10.0, "flow_sum": 3.0, "flow_count": 2 })
+                ),
+                (
+                    "te/device/sensor///m/".to_string(),
+                    json!({ "time": 15.0, "flow_sum": 6.0, "flow_count": 2 })
+                ),
+            ]
+        );
+
+        // The values slide out of the windows
+        let messages = aggregate.on_interval(at(30), &context).unwrap();
+        assert_eq!(
+            topic_payloads(messages),
+            vec![(
+                "te/device/sensor///m/".to_string(),
+                json!({ "time": 20.0, "flow_sum": 4.0, "flow_count": 1 })
+            ),]
+        );
+        assert!(aggregate.on_interval(at(40), &context).unwrap().is_empty());
+    }
+
+    #[test]
+    fn messages_received_on_different_topics_are_aggregated_independently() {
+        let context = FlowContextHandle::default();
+        let mut aggregate = aggregate(json!({
+            "window": "10s",
+            "statistics": ["max"],
+            "suffixes": { "max": "" },
+        }));
+
+        for (topic, value) in [
+            ("te/device/child1///m/", 1.0),
+            ("te/device/child2///m/", 10.0),
+            ("te/device/child1///m/", 2.0),
+        ] {
+            let msg = Message::new(topic, json!({ "x": value }).to_string());
+            aggregate.on_message(at(1), &msg, &context).unwrap();
+        }
+
+        let messages = aggregate.on_interval(at(10), &context).unwrap();
+        assert_eq!(
+            topic_payloads(messages),
+            vec![
+                (
+                    "te/device/child1///m/".to_string(),
+                    json!({ "time": 10.0, "x": 2.0 })
+                ),
+                (
+                    "te/device/child2///m/".to_string(),
+                    json!({ "time": 10.0, "x": 10.0 })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_time_is_ignored_and_the_type_carried_over() {
+        let context = FlowContextHandle::default();
+        let mut aggregate = aggregate(json!({ "window": "10s", "statistics": ["max"] }));
+
+        for (secs, value) in [(1, 1.0), (2, 3.0)] {
+            let msg = Message::new(
+                "te/device/main///m/",
+                json!({ "time": 1700000000 + secs, "type": "flow", "x": value }).to_string(),
+            );
+            aggregate.on_message(at(secs), &msg, &context).unwrap();
+        }
+
+        let messages = aggregate.on_interval(at(10), &context).unwrap();
+        assert_eq!(
+            topic_payloads(messages),
+            vec![(
+                "te/device/main///m/".to_string(),
+                json!({ "time": 10.0, "type": "flow", "x_max": 3.0 })
+            )]
+        );
+    }
+
+    #[test]
+    fn partial_windows_are_restored_from_the_context() {
+        let context = FlowContextHandle::default();
+        let config = json!({ "window": "10s", "statistics": ["count"] });
+        let step = "my-flow|0|aggregate";
+
+        let mut aggregate_1 = aggregate(config.clone());
+        aggregate_1.set_step_name(step);
+        let msg = Message::new("te/device/sensor///m/", json!({ "x": 1.0 }).to_string());
+        aggregate_1.on_message(at(1), &msg, &context).unwrap();
+
+        // A new instance of the step, as created on flow reload, resumes the partial windows
+        let mut aggregate_2 = aggregate(config.clone());
+        aggregate_2.set_step_name(step);
+        aggregate_2.on_message(at(3), &msg, &context).unwrap();
+
+        // While the windows of other steps are independent
+        let mut other_step = aggregate(config);
+        other_step.set_step_name("other-flow|0|aggregate");
+        assert!(other_step.on_interval(at(10), &context).unwrap().is_empty());
+
+        let messages = aggregate_2.on_interval(at(10), &context).unwrap();
+        assert_eq!(
+            topic_payloads(messages),
+            vec![(
+                "te/device/sensor///m/".to_string(),
+                json!({ "time": 10.0, "x_count": 2 })
+            )]
+        );
+
+        // Once all the windows are published, the context entry is removed
+        assert_eq!(context.get_step_value(step, STATE_KEY), JsonValue::Null);
+    }
+
+    #[test]
+    fn messages_that_cannot_be_processed_are_forwarded() {
+        let context = FlowContextHandle::default();
+        let mut aggregate = Aggregate::default();
+
+        for payload in [
+            "not a valid thin-edge measurement",
+            r#"{"type":"no values"}"#,
+        ] {
+            let msg = Message::new("te/device/main///m/", payload);
+            assert_eq!(
+                aggregate.on_message(at(1), &msg, &context).unwrap(),
+                vec![msg]
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_measurements_are_aggregated() {
+        let context = FlowContextHandle::default();
+        let mut aggregate = Aggregate::default();
+
+        for topic in [
+            "te/device/main///e/login",
+            "te/device/main///a/temperature_high",
+            "sensors/s1",
+        ] {
+            let msg = Message::new(topic, json!({ "text": "x", "count": 3 }).to_string());
+            assert_eq!(
+                aggregate.on_message(at(1), &msg, &context).unwrap(),
+                vec![msg]
+            );
+        }
+        assert!(aggregate.on_interval(at(60), &context).unwrap().is_empty());
+    }
+
+    #[test]
+    fn the_aggregated_topics_can_be_configured() {
+        let context = FlowContextHandle::default();
+        let mut aggregate = aggregate(json!({
+            "window": "10s",
+            "statistics": ["max"],
+            "topics": ["sensors/+"],
+        }));
+
+        let sensor = Message::new("sensors/s1", json!({ "x": 1.0 }).to_string());
+        assert!(aggregate
+            .on_message(at(1), &sensor, &context)
+            .unwrap()
+            .is_empty());
+        let measurement = Message::new("te/device/main///m/", json!({ "x": 1.0 }).to_string());
+        assert_eq!(
+            aggregate.on_message(at(1), &measurement, &context).unwrap(),
+            vec![measurement]
+        );
+
+        let messages = aggregate.on_interval(at(10), &context).unwrap();
+        assert_eq!(
+            topic_payloads(messages),
+            vec![(
+                "sensors/s1".to_string(),
+                json!({ "time": 10.0, "x_max": 1.0 })
+            )]
+        );
+    }
+
+    #[test]
+    fn the_windows_are_saved_on_new_buckets_and_on_interval() {
+        let context = FlowContextHandle::default();
+        let mut aggregate = aggregate(json!({ "window": "10s", "statistics": ["count"] }));
+        let step = "aggregate";
+        let msg = Message::new("te/device/main///m/", json!({ "x": 1.0 }).to_string());
+
+        aggregate.on_message(at(1), &msg, &context).unwrap();
+        let saved = context.get_step_value(step, STATE_KEY);
+        assert_ne!(saved, JsonValue::Null);
+
+        // Not on each message
+        aggregate.on_message(at(2), &msg, &context).unwrap();
+        assert_eq!(context.get_step_value(step, STATE_KEY), saved);
+
+        // But on interval, when the state has changed
+        aggregate.on_interval(at(5), &context).unwrap();
+        assert_ne!(context.get_step_value(step, STATE_KEY), saved);
+    }
+
+    #[test]
+    fn rejecting_invalid_config() {
+        for config in [
+            json!({ "window": "soon" }),
+            json!({ "window": "10s", "slide": "3s" }),
+            json!({ "statistics": ["median"] }),
+            json!({ "statistics": [] }),
+            json!({ "topics": ["te/#/m"] }),
+        ] {
+            let mut aggregate = Aggregate::default();
+            assert!(
+                aggregate.set_config(config.clone().into()).is_err(),
+                "{config}"
+            );
+        }
+    }
+
+    fn aggregate(config: Value) -> Aggregate {
+        let mut aggregate = Aggregate::default();
+        aggregate.set_config(config.into()).unwrap();
+        aggregate
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn topic_payloads(messages: Vec<Message>) -> Vec<(String, Value)> {
+        messages
+            .into_iter()
+            .map(|msg| {
+                let payload = serde_json::from_slice(&msg.payload).unwrap();
+                (msg.topic, payload)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,472 @@
+use crate::ConfigError;
+use crate::FlowContextHandle;
+use crate::FlowError;
+use crate::JsonValue;
+use crate::Message;
+use crate::Transformer;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+use std::time::Duration;
+use std::time::SystemTime;
+use tedge_mqtt_ext::TopicFilter;
+
+/// The properties of a measurement that are not measured values
+const NON_MEASUREMENT_KEYS: [&str; 2] = ["time", "type"];
+
+/// Report `te` measurements by exception, suppressing the values that have not significantly changed.
+///
+/// A measurement value is forwarded only if it differs from the last forwarded value of the same series
+/// by more than the `absolute` threshold or the `percent` threshold (relative to the last forwarded value).
+/// With no thresholds, any change is forwarded.
+///
+/// Only the messages received on the measurement topics (`te/+/+/+/+/m/+`) or on the configured `topics` are filtered.
+///
+/// A value is forwarded whatever its change, when no value has been forwarded for the series
+/// since more than the `heartbeat` period.
+///
+/// The last forwarded value of each series is stored in the context private to the step,
+/// under `<topic>/<series>`, so the deadband survives flow reloads.
+///
+/// - the `time` and `type` properties are not filtered
+/// - the suppressed values are removed from the message, the other properties being forwarded unchanged
+/// - a message is dropped when all its values are suppressed
+/// - any message that cannot be processed (e.g. received on another topic or not a JSON object with numeric values)
+///   is forwarded unchanged
+#[derive(Clone)]
+pub struct Deadband {
+    absolute: Option<f64>,
+    percent: Option<f64>,
+    heartbeat: Option<Duration>,
+    topics: TopicFilter,
+    step_name: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct DeadbandConfig {
+    absolute: Option<f64>,
+    percent: Option<f64>,
+    heartbeat: Option<String>,
+    topics: Option<Vec<String>>,
+}
+
+/// The last value forwarded for a series
+#[derive(Serialize, Deserialize)]
+struct LastValue {
+    value: f64,
+    /// Processing time in milliseconds since the epoch
+    time: u64,
+}
+
+impl Default for Deadband {
+    fn default() -> Self {
+        Deadband {
+            absolute: None,
+            percent: None,
+            heartbeat: None,
+            topics: TopicFilter::new_unchecked(super::MEASUREMENT_TOPICS),
+            step_name: "deadband".to_string(),
+        }
+    }
+}
+
+impl Transformer for Deadband {
+    fn name(&self) -> &str {
+        "deadband"
+    }
+
+    fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError> {
+        let config: DeadbandConfig = config.into_value().map_err(|err| {
+            ConfigError::IncorrectSetting(format!("Invalid deadband config: {err}"))
+        })?;
+
+        for (property, threshold) in [("absolute", config.absolute), ("percent", config.percent)] {
+            if threshold.is_some_and(|threshold| threshold < 0.0) {
+                return Err(ConfigError::IncorrectSetting(format!(
+                    "Invalid {property}: the threshold cannot be negative"
+                )));
+            }
+        }
+        let heartbeat = match config.heartbeat {
+            None => None,
+            Some(heartbeat) => match humantime::parse_duration(&heartbeat) {
+                Ok(duration) if !duration.is_zero() => Some(duration),
+                _ => {
+                    return Err(ConfigError::IncorrectSetting(format!(
+                        "Invalid heartbeat: not a positive duration: {heartbeat}"
+                    )))
+                }
+            },
+        };
+
+        let topics = match config.topics {
+            None => TopicFilter::new_unchecked(super::MEASUREMENT_TOPICS),
+            Some(topics) => crate::config::topic_filters(topics)?,
+        };
+
+        self.absolute = config.absolute;
+        self.percent = config.percent;
+        self.heartbeat = heartbeat;
+        self.topics = topics;
+        Ok(())
+    }
+
+    fn set_step_name(&mut self, step_name: &str) {
+        self.step_name = step_name.to_string();
+    }
+
+    fn on_message(
+        &mut self,
+        timestamp: SystemTime,
+        message: &Message,
+        context: &FlowContextHandle,
+    ) -> Result<Vec<Message>, FlowError> {
+        if !self.topics.accept_topic_name(&message.topic) {
+            return Ok(vec![message.clone()]);
+        }
+        let Ok(Value::Object(payload)) = serde_json::from_slice(message.payload.as_slice()) else {
+            return Ok(vec![message.clone()]);
+        };
+        let time = epoch_ms(timestamp);
+
+        let mut has_values = false;
+        let mut has_changes = false;
+        let mut has_suppressed = false;
+        let mut filtered = Map::new();
+        for (name, value) in payload {
+            if NON_MEASUREMENT_KEYS.contains(&name.as_str()) {
+                filtered.insert(name, value);
+                continue;
+            }
+            match value {
+                Value::Number(number) => {
+                    has_values = true;
+                    let Some(value) = number.as_f64() else {
+                        continue;
+                    };
+                    if self.accept(context, &message.topic, &name, value, time) {
+                        has_changes = true;
+                        filtered.insert(name, Value::Number(number));
+                    } else {
+                        has_suppressed = true;
+                    }
+                }
+                Value::Object(group) => {
+                    let mut filtered_group = Map::new();
+                    let mut is_group = false;
+                    for (inner_name, inner_value) in group {
+                        let Some(value) = inner_value.as_f64() else {
+                            filtered_group.insert(inner_name, inner_value);
+                            continue;
+                        };
+                        is_group = true;
+                        let series = format!("{name}/{inner_name}");
+                        if self.accept(context, &message.topic, &series, value, time) {
+                            has_changes = true;
+                            filtered_group.insert(inner_name, inner_value);
+                        } else {
+                            has_suppressed = true;
+                        }
+                    }
+                    has_values |= is_group;
+                    if !is_group || !filtered_group.is_empty() {
+                        filtered.insert(name, Value::Object(filtered_group));
+                    }
+                }
+                value => {
+                    filtered.insert(name, value);
+                }
+            }
+        }
+
+        if !has_values || !has_suppressed {
+            return Ok(vec![message.clone()]);
+        }
+        if !has_changes {
+            return Ok(vec![]);
+        }
+        let payload = Value::Object(filtered).to_string();
+        Ok(vec![Message {
+            payload: payload.into_bytes(),
+            ..message.clone()
+        }])
+    }
+}
+
+impl Deadband {
+    /// Check if a value has to be forwarded, if so recording this value as the last forwarded one
+    fn accept(
+        &self,
+        context: &FlowContextHandle,
+        topic: &str,
+        series: &str,
+        value: f64,
+        time: u64,
+    ) -> bool {
+        let key = format!("{topic}/{series}");
+        let last = context
+            .get_step_value(&self.step_name, &key)
+            .into_value::<LastValue>()
+            .ok();
+        let accepted = match last {
+            None => true,
+            Some(last) => self.is_heartbeat_due(&last, time) || self.has_changed(&last, value),
+        };
+        if accepted {
+            let last = LastValue { value, time };
+            if let Ok(last) = JsonValue::from_value(last) {
+                context.set_step_value(&self.step_name, &key, last);
+            }
+        }
+        accepted
+    }
+
+    fn is_heartbeat_due(&self, last: &LastValue, time: u64) -> bool {
+        self.heartbeat
+            .is_some_and(|heartbeat| time.saturating_sub(last.time) >= heartbeat.as_millis() as u64)
+    }
+
+    fn has_changed(&self, last: &LastValue, value: f64) -> bool {
+        let delta = (value - last.value).abs();
+        match (self.absolute, self.percent) {
+            (None, None) => delta > 0.0,
+            (absolute, percent) => {
+                absolute.is_some_and(|threshold| delta > threshold)
+                    || percent.is_some_and(|threshold| delta > last.value.abs() * threshold / 100.0)
+            }
+        }
+    }
+}
+
+fn epoch_ms(timestamp: SystemTime) -> u64 {
+    timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_within_the_absolute_deadband_are_suppressed() {
+        let context = FlowContextHandle::default();
+        let mut deadband = deadband(json!({ "absolute": 0.5 }));
+
+        assert_eq!(forward(&mut deadband, &context, 1, 20.0), Some(20.0));
+        assert_eq!(forward(&mut deadband, &context, 2, 20.4), None);
+        assert_eq!(forward(&mut deadband, &context, 3, 19.6), None);
+        // The change is computed from the last forwarded value, not from the last received one
+        assert_eq!(forward(&mut deadband, &context, 4, 20.6), Some(20.6));
+        assert_eq!(forward(&mut deadband, &context, 5, 20.0), Some(20.0));
+    }
+
+    #[test]
+    fn values_within_the_percent_deadband_are_suppressed() {
+        let context = FlowContextHandle::default();
+        let mut deadband = deadband(json!({ "percent": 10 }));
+
+        assert_eq!(forward(&mut deadband, &context, 1, 200.0), Some(200.0));
+        assert_eq!(forward(&mut deadband, &context, 2, 219.0), None);
+        assert_eq!(forward(&mut deadband, &context, 3, 221.0), Some(221.0));
+        assert_eq!(forward(&mut deadband, &context, 4, 199.0), None);
+    }
+
+    #[test]
+    fn values_are_forwarded_on_any_change_when_no_threshold_is_set() {
+        let context = FlowContextHandle::default();
+        let mut deadband = Deadband::default();
+
+        assert_eq!(forward(&mut deadband, &context, 1, 1.0), Some(1.0));
+        assert_eq!(forward(&mut deadband, &context, 2, 1.0), None);
+        assert_eq!(forward(&mut deadband, &context, 3, 1.1), Some(1.1));
+    }
+
+    #[test]
+    fn values_are_forwarded_after_the_heartbeat_period() {
+        let context = FlowContextHandle::default();
+        let mut deadband = deadband(json!({ "absolute": 5, "heartbeat": "10s" }));
+
+        assert_eq!(forward(&mut deadband, &context, 0, 20.0), Some(20.0));
+        assert_eq!(forward(&mut deadband, &context, 9, 20.0), None);
+        assert_eq!(forward(&mut deadband, &context, 10, 20.0), Some(20.0));
+        assert_eq!(forward(&mut deadband, &context, 15, 21.0), None);
+    }
+
+    #[test]
+    fn deadband_state_survives_flow_reloads() {
+        let context = FlowContextHandle::default();
+        let config = json!({ "absolute": 1 });
+        let step = "my-flow|0|deadband";
+
+        let mut deadband_1 = deadband(config.clone());
+        deadband_1.set_step_name(step);
+        assert_eq!(forward(&mut deadband_1, &context, 1, 20.0), Some(20.0));
+
+        // A new instance of the step, as created on flow reload
+        let mut deadband_2 = deadband(config.clone());
+        deadband_2.set_step_name(step);
+        assert_eq!(forward(&mut deadband_2, &context, 2, 20.5), None);
+        assert_eq!(
+            context.get_step_value(step, "te/device/main///m/temperature/temperature"),
+            json!({ "value": 20.0, "time": 1000 }).into()
+        );
+
+        // The state is private to the step and not shared with the mapper context
+        assert_eq!(context.get_keys(), Vec::<String>::new());
+        let mut other_step = deadband(config);
+        other_step.set_step_name("other-flow|0|deadband");
+        assert_eq!(forward(&mut other_step, &context, 3, 20.5), Some(20.5));
+    }
+
+    #[test]
+    fn series_are_filtered_independently() {
+        let context = FlowContextHandle::default();
+        let mut deadband = deadband(json!({ "absolute": 1 }));
+        let topic = "te/device/main///m/environment";
+
+        let msg = Message::new(
+            topic,
+            json!({ "time": 1, "temperature": 20.0, "pressure": { "in": 1.0, "out": 2.0 } })
+                .to_string(),
+        );
+        assert_eq!(
+            deadband.on_message(at(1), &msg, &context).unwrap(),
+            vec![msg]
+        );
+
+        // Only the changed values are forwarded, along the other properties
+        let msg = Message::new(
+            topic,
+            json!({ "time": 2, "temperature": 20.5, "pressure": { "in": 1.2, "out": 4.0 } })
+                .to_string(),
+        );
+        let expected = Message::new(
+            topic,
+            json!({ "time": 2, "pressure": { "out": 4.0 } }).to_string(),
+        );
+        assert_eq!(
+            deadband.on_message(at(2), &msg, &context).unwrap(),
+            vec![expected]
+        );
+
+        // The same series on another topic are filtered independently
+        let msg = Message::new(
+            "te/device/child///m/environment",
+            json!({ "temperature": 20.5 }).to_string(),
+        );
+        assert_eq!(
+            deadband.on_message(at(3), &msg, &context).unwrap(),
+            vec![msg]
+        );
+    }
+
+    #[test]
+    fn messages_that_cannot_be_processed_are_forwarded() {
+        let context = FlowContextHandle::default();
+        let mut deadband = Deadband::default();
+
+        for payload in [
+            "not a valid thin-edge measurement",
+            r#"{"type":"no values"}"#,
+        ] {
+            let msg = Message::new("te/device/main///m/", payload);
+            assert_eq!(
+                deadband.on_message(at(1), &msg, &context).unwrap(),
+                vec![msg]
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_measurements_are_filtered() {
+        let context = FlowContextHandle::default();
+        let mut deadband = Deadband::default();
+
+        for topic in [
+            "te/device/main///e/login",
+            "te/device/main///a/temperature_high",
+            "sensors/s1",
+        ] {
+            // The same event or alarm, with unchanged values, is forwarded each time
+            for secs in [1, 2] {
+                let msg = Message::new(topic, json!({ "text": "x", "count": 3 }).to_string());
+                assert_eq!(
+                    deadband.on_message(at(secs), &msg, &context).unwrap(),
+                    vec![msg]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn the_filtered_topics_can_be_configured() {
+        let context = FlowContextHandle::default();
+        let mut deadband = deadband(json!({ "topics": ["sensors/+"] }));
+
+        let msg = Message::new("sensors/s1", json!({ "x": 1.0 }).to_string());
+        assert_eq!(
+            deadband.on_message(at(1), &msg, &context).unwrap(),
+            vec![msg.clone()]
+        );
+        assert!(deadband
+            .on_message(at(2), &msg, &context)
+            .unwrap()
+            .is_empty());
+
+        let msg = Message::new("te/device/main///m/", json!({ "x": 1.0 }).to_string());
+        for secs in [3, 4] {
+            assert_eq!(
+                deadband.on_message(at(secs), &msg, &context).unwrap(),
+                vec![msg.clone()]
+            );
+        }
+    }
+
+    #[test]
+    fn rejecting_invalid_config() {
+        for config in [
+            json!({ "absolute": -1 }),
+            json!({ "percent": "ten" }),
+            json!({ "heartbeat": "later" }),
+            json!({ "topics": ["te/#/m"] }),
+        ] {
+            let mut deadband = Deadband::default();
+            assert!(
+                deadband.set_config(config.clone().into()).is_err(),
+                "{config}"
+            );
+        }
+    }
+
+    fn deadband(config: Value) -> Deadband {
+        let mut deadband = Deadband::default();
+        deadband.set_config(config.into()).unwrap();
+        deadband
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    /// Send a temperature measurement, returning the forwarded value if any
+    fn forward(
+        deadband: &mut Deadband,
+        context: &FlowContextHandle,
+        secs: u64,
+        temperature: f64,
+    ) -> Option<f64> {
+        let msg = Message::new(
+            "te/device/main///m/temperature",
+            json!({ "temperature": temperature }).to_string(),
+        );
+        let messages = deadband.on_message(at(secs), &msg, context).unwrap();
+        let msg = messages.first()?;
+        let payload: Value = serde_json::from_slice(&msg.payload).unwrap();
+        payload["temperature"].as_f64()
+    }
+}
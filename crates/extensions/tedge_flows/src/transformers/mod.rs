@@ -9,6 +9,8 @@ use std::collections::HashMap;
 use std::time::SystemTime;
 
 mod add_timestamp;
+mod aggregate;
+mod deadband;
 mod group_measurements;
 mod ignore_topics;
 mod limit_payload_size;
@@ -16,11 +18,20 @@ mod set_topic;
 mod skip_mosquitto_health_status;
 mod update_context;
 
+/// The topics of the thin-edge measurements, processed by default by the measurement specific builtins
+const MEASUREMENT_TOPICS: &str = "te/+/+/+/+/m/+";
+
 pub trait Transformer: Send + Sync + 'static {
     fn name(&self) -> &str;
 
     fn set_config(&mut self, config: JsonValue) -> Result<(), ConfigError>;
 
+    /// Called when the transformer is instantiated as a flow step, with the instance name of that step
+    ///
+    /// This name identifies the context private to the step,
+    /// which is persisted along the `script` scope of the flow.
+    fn set_step_name(&mut self, _step_name: &str) {}
+
     fn on_message(
         &mut self,
         timestamp: SystemTime,
@@ -82,6 +93,8 @@ impl Default for BuiltinTransformers {
             transformers: HashMap::default(),
         };
         transformers.register(add_timestamp::AddTimestamp::default());
+        transformers.register(aggregate::Aggregate::default());
+        transformers.register(deadband::Deadband::default());
         transformers.register(group_measurements::GroupMeasurements::default());
         transformers.register(limit_payload_size::LimitPayloadSize::default());
         transformers.register(ignore_topics::IgnoreTopics::default());
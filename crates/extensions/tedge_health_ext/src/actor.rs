@@ -1,19 +1,37 @@
 use async_trait::async_trait;
+use serde_json::json;
+use serde_json::Map;
+use std::time::Duration;
 use tedge_actors::Actor;
+use tedge_actors::ActorSnapshot;
+use tedge_actors::ActorStatus;
 use tedge_actors::MessageReceiver;
 use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeHandle;
 use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_api::health::ServiceHealthTopic;
+use tedge_api::health::Status;
 use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
 
 pub struct HealthMonitorActor {
     // TODO(marcel): move this
     service_registration_message: Option<MqttMessage>,
     health_topic: ServiceHealthTopic,
+    runtime: Option<RuntimeHandle>,
+    /// Delay after which an actor with pending messages is considered stalled
+    stall_timeout: Duration,
+    runtime_topics: Option<RuntimeTopics>,
     messages: SimpleMessageBox<MqttMessage, MqttMessage>,
 }
 
+/// The topics used to request and publish a snapshot of the actors of the service
+pub struct RuntimeTopics {
+    pub request: Topic,
+    pub status: Topic,
+}
+
 impl HealthMonitorActor {
     pub fn new(
         service_registration_message: Option<MqttMessage>,
@@ -23,14 +41,88 @@ impl HealthMonitorActor {
         Self {
             service_registration_message,
             health_topic,
+            runtime: None,
+            stall_timeout: Duration::from_secs(60),
+            runtime_topics: None,
             messages,
         }
     }
 
+    /// Report the liveness of the actors running on the given runtime
+    pub fn with_runtime(self, runtime: Option<RuntimeHandle>, stall_timeout: Duration) -> Self {
+        Self {
+            runtime,
+            stall_timeout,
+            ..self
+        }
+    }
+
+    /// Answer the requests for a snapshot of the actors
+    pub fn with_runtime_topics(self, runtime_topics: RuntimeTopics) -> Self {
+        Self {
+            runtime_topics: Some(runtime_topics),
+            ..self
+        }
+    }
+
     pub fn up_health_status(&self) -> MqttMessage {
         self.health_topic.up_message()
     }
 
+    /// The health status of the service, `up` or `degraded` depending on the liveness of its actors
+    ///
+    /// Only the critical actors are considered: a stalled actor that is not critical is reported,
+    /// but the service is still `up`.
+    pub fn current_health_status(&self) -> MqttMessage {
+        let Some(runtime) = &self.runtime else {
+            return self.up_health_status();
+        };
+
+        let mut status = Status::Up;
+        let mut actors = Map::new();
+        for actor in runtime.health().check(self.stall_timeout) {
+            if actor.critical && actor.status == ActorStatus::Stalled {
+                status = Status::Degraded;
+            }
+            actors.insert(
+                actor.name,
+                json!({
+                    "status": actor.status.as_str(),
+                    "pending": actor.pending,
+                    "critical": actor.critical,
+                }),
+            );
+        }
+        self.health_topic
+            .health_message(status, Some(actors.into()))
+    }
+
+    /// A snapshot of the actors of the service, if this is a request for such a snapshot
+    ///
+    /// The snapshot is requested to the runtime running the actors.
+    pub async fn runtime_snapshot(
+        &mut self,
+        request: &MqttMessage,
+    ) -> Result<Option<MqttMessage>, RuntimeError> {
+        let Some(topics) = self.runtime_topics.as_ref() else {
+            return Ok(None);
+        };
+        if request.topic != topics.request {
+            return Ok(None);
+        }
+
+        let snapshot = match self.runtime.as_mut() {
+            Some(runtime) => runtime.snapshot(self.stall_timeout).await?,
+            None => vec![],
+        };
+        let actors: Vec<_> = snapshot.iter().map(actor_snapshot_json).collect();
+        let payload = json!({
+            "pid": std::process::id(),
+            "actors": actors,
+        });
+        Ok(Some(MqttMessage::new(&topics.status, payload.to_string())))
+    }
+
     pub fn down_health_status(&self) -> MqttMessage {
         self.health_topic.down_message()
     }
@@ -47,11 +139,43 @@ impl Actor for HealthMonitorActor {
             self.messages.send(registration_message.clone()).await?;
         }
 
-        self.messages.send(self.up_health_status()).await?;
+        self.messages.send(self.current_health_status()).await?;
 
-        while let Some(_message) = self.messages.recv().await {
-            self.messages.send(self.up_health_status()).await?;
+        while let Some(message) = self.messages.recv().await {
+            let response = match self.runtime_snapshot(&message).await? {
+                Some(snapshot) => snapshot,
+                None => self.current_health_status(),
+            };
+            self.messages.send(response).await?;
         }
         Ok(())
     }
 }
+
+fn actor_snapshot_json(actor: &ActorSnapshot) -> serde_json::Value {
+    let mailboxes: Vec<_> = actor
+        .mailboxes
+        .iter()
+        .map(|mailbox| {
+            json!({
+                "name": mailbox.name,
+                "capacity": mailbox.capacity,
+                "pending": mailbox.pending,
+                "received": mailbox.received,
+                "idle": mailbox.idle_time.as_secs_f64(),
+            })
+        })
+        .collect();
+    json!({
+        "name": actor.name,
+        "status": actor.status.as_str(),
+        "critical": actor.critical,
+        "uptime": actor.uptime.as_secs_f64(),
+        "pending": actor.pending(),
+        "received": actor.received(),
+        "throughput": actor.throughput(),
+        "mailboxes": mailboxes,
+        "receives_from": actor.receives_from,
+        "sends_to": actor.sends_to,
+    })
+}
@@ -4,19 +4,24 @@ mod actor;
 mod tests;
 
 use actor::HealthMonitorActor;
+use actor::RuntimeTopics;
 use serde_json::json;
 use serde_json::Map;
+use std::time::Duration;
 use tedge_actors::Builder;
 use tedge_actors::DynSender;
 use tedge_actors::LinkError;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
+use tedge_actors::RuntimeHandle;
 use tedge_actors::RuntimeRequest;
 use tedge_actors::RuntimeRequestSink;
 use tedge_actors::SimpleMessageBoxBuilder;
 use tedge_api::entity::EntityType;
 use tedge_api::entity_store::EntityRegistrationMessage;
+use tedge_api::health::service_runtime_request_topic;
+use tedge_api::health::service_runtime_status_topic;
 use tedge_api::health::ServiceHealthTopic;
 use tedge_api::mqtt_topics::Channel;
 use tedge_api::mqtt_topics::MqttSchema;
@@ -30,6 +35,9 @@ use tedge_mqtt_ext::TopicFilter;
 pub struct HealthMonitorBuilder {
     registration_message: Option<MqttMessage>,
     health_topic: ServiceHealthTopic,
+    runtime: Option<RuntimeHandle>,
+    stall_timeout: Duration,
+    runtime_topics: RuntimeTopics,
     box_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage>,
 }
 
@@ -47,11 +55,18 @@ impl HealthMonitorBuilder {
     ) -> Self {
         let mut service_type = service_config.ty.as_str();
         let time_format = service_config.timestamp_format;
+        let stall_timeout = service_config.stall_timeout.duration();
         let service_topic_id = &service.service_topic_id;
 
         let mut box_builder = SimpleMessageBoxBuilder::new(service_topic_id.as_str(), 16);
 
+        let runtime_topics = RuntimeTopics {
+            request: service_runtime_request_topic(mqtt_schema, service_topic_id.entity()),
+            status: service_runtime_status_topic(mqtt_schema, service_topic_id.entity()),
+        };
+
         let subscriptions: TopicFilter = [
+            runtime_topics.request.clone().into(),
             mqtt_schema
                 .topic_for(
                     service.service_topic_id.entity(),
@@ -103,6 +118,9 @@ impl HealthMonitorBuilder {
         let builder = HealthMonitorBuilder {
             health_topic,
             registration_message: Some(registration_message),
+            runtime: None,
+            stall_timeout,
+            runtime_topics,
             box_builder,
         };
 
@@ -116,6 +134,18 @@ impl HealthMonitorBuilder {
         builder
     }
 
+    /// Include in the health status the liveness of the actors running on a runtime
+    ///
+    /// The service is then reported as `degraded` when one of the critical actors is stalled,
+    /// i.e. when messages are waiting for this actor for more than `service.stall_timeout`.
+    /// The snapshots of the actors are also requested to this runtime.
+    pub fn with_runtime(self, runtime: RuntimeHandle) -> Self {
+        Self {
+            runtime: Some(runtime),
+            ..self
+        }
+    }
+
     fn set_init_and_last_will(&self, config: MqttConfig) -> MqttConfig {
         let name = self.health_topic.to_owned();
         let _name = name.clone();
@@ -138,7 +168,9 @@ impl Builder<HealthMonitorActor> for HealthMonitorBuilder {
         let message_box = self.box_builder.build();
 
         let actor =
-            HealthMonitorActor::new(self.registration_message, self.health_topic, message_box);
+            HealthMonitorActor::new(self.registration_message, self.health_topic, message_box)
+                .with_runtime(self.runtime, self.stall_timeout)
+                .with_runtime_topics(self.runtime_topics);
 
         Ok(actor)
     }
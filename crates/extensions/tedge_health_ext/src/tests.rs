@@ -1,5 +1,8 @@
 use crate::HealthMonitorBuilder;
 use crate::TopicFilter;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::convert::Infallible;
 use std::time::Duration;
 use tedge_actors::test_helpers::MessageReceiverExt;
 use tedge_actors::Actor;
@@ -8,6 +11,12 @@ use tedge_actors::DynSender;
 use tedge_actors::MessageReceiver;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
+use tedge_actors::NoMessage;
+use tedge_actors::Runtime;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_actors::SimpleMessageBoxBuilder;
 use tedge_api::mqtt_topics::EntityTopicId;
@@ -84,14 +93,204 @@ async fn health_check_set_init_and_last_will_message() -> Result<(), anyhow::Err
     Ok(())
 }
 
+#[tokio::test]
+async fn health_status_includes_actor_liveness() -> Result<(), anyhow::Error> {
+    let mut mqtt_config = MqttConfig::default();
+    let mut health_mqtt_builder = MqttActorBuilder::new(&mut mqtt_config);
+    let mut runtime = Runtime::new();
+
+    let health_actor = health_check_actor_builder("test", &mut health_mqtt_builder)
+        .with_runtime(runtime.get_handle());
+    runtime.spawn(health_actor).await?;
+    let mut mqtt_box = health_mqtt_builder.build();
+
+    // skip registration message
+    mqtt_box.skip(1).await;
+
+    let message = timeout(TEST_TIMEOUT, mqtt_box.recv())
+        .await?
+        .expect("a health message");
+    let payload: Value = serde_json::from_slice(message.payload_bytes())?;
+    assert_eq!(payload["status"], "up");
+    assert_eq!(payload["actors"]["HealthMonitorActor-0"]["status"], "up");
+    assert_eq!(payload["actors"]["HealthMonitorActor-0"]["critical"], false);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn only_critical_actors_degrade_the_health_status() -> Result<(), anyhow::Error> {
+    let mut mqtt_config = MqttConfig::default();
+    let mut health_mqtt_builder = MqttActorBuilder::new(&mut mqtt_config);
+    let mut runtime = Runtime::new();
+
+    let health_actor = health_check_actor_builder_with_config(
+        "test",
+        &mut health_mqtt_builder,
+        "service.stall_timeout = \"0s\"",
+    )
+    .with_runtime(runtime.get_handle());
+    runtime.spawn(health_actor).await?;
+    let mut mqtt_box = health_mqtt_builder.build();
+    mqtt_box.skip(2).await;
+
+    // An actor that is not critical stalls
+    let sleeper = SleeperBuilder::new();
+    let mut sleeper_input = sleeper.get_sender();
+    runtime.spawn(sleeper).await?;
+    sleeper_input.send(42).await?;
+
+    let health = check_health(&mut mqtt_box).await?;
+    assert_eq!(health["actors"]["Sleeper-1"]["status"], "stalled");
+    assert_eq!(health["status"], "up");
+
+    // A critical actor stalls
+    let critical_sleeper = SleeperBuilder::new();
+    let mut critical_sleeper_input = critical_sleeper.get_sender();
+    runtime.spawn_critical(critical_sleeper).await?;
+    critical_sleeper_input.send(42).await?;
+
+    let health = check_health(&mut mqtt_box).await?;
+    assert_eq!(health["actors"]["Sleeper-2"]["status"], "stalled");
+    assert_eq!(health["status"], "degraded");
+
+    Ok(())
+}
+
+async fn check_health(
+    mqtt_box: &mut SimpleMessageBox<MqttMessage, MqttMessage>,
+) -> Result<Value, anyhow::Error> {
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main/service/test/cmd/health/check"),
+            "",
+        ))
+        .await?;
+    let message = timeout(TEST_TIMEOUT, mqtt_box.recv())
+        .await?
+        .expect("a health message");
+    Ok(serde_json::from_slice(message.payload_bytes())?)
+}
+
+/// An actor that never reads its messages
+struct Sleeper {
+    messages: SimpleMessageBox<u32, NoMessage>,
+}
+
+#[async_trait]
+impl Actor for Sleeper {
+    fn name(&self) -> &str {
+        "Sleeper"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        self.messages.recv_signal().await;
+        Ok(())
+    }
+}
+
+struct SleeperBuilder {
+    box_builder: SimpleMessageBoxBuilder<u32, NoMessage>,
+}
+
+impl SleeperBuilder {
+    fn new() -> Self {
+        SleeperBuilder {
+            box_builder: SimpleMessageBoxBuilder::new("Sleeper", 16),
+        }
+    }
+
+    fn get_sender(&self) -> DynSender<u32> {
+        self.box_builder.get_sender()
+    }
+}
+
+impl RuntimeRequestSink for SleeperBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<Sleeper> for SleeperBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<Sleeper, Self::Error> {
+        Ok(Sleeper {
+            messages: self.box_builder.build(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn publish_runtime_snapshot_on_request() -> Result<(), anyhow::Error> {
+    let mut mqtt_config = MqttConfig::default();
+    let mut health_mqtt_builder = MqttActorBuilder::new(&mut mqtt_config);
+    let mut runtime = Runtime::new();
+
+    let health_actor = health_check_actor_builder("test", &mut health_mqtt_builder)
+        .with_runtime(runtime.get_handle());
+    runtime.spawn(health_actor).await?;
+    let mut mqtt_box = health_mqtt_builder.build();
+
+    // skip registration and health messages
+    mqtt_box.skip(2).await;
+
+    mqtt_box
+        .send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main/service/test/signal/runtime"),
+            "{}",
+        ))
+        .await?;
+    let message = timeout(TEST_TIMEOUT, mqtt_box.recv())
+        .await?
+        .expect("a runtime snapshot");
+    assert_eq!(
+        message.topic.name,
+        "te/device/main/service/test/status/runtime"
+    );
+
+    let payload: Value = serde_json::from_slice(message.payload_bytes())?;
+    let actor = &payload["actors"][0];
+    assert_eq!(actor["name"], "HealthMonitorActor-0");
+    assert_eq!(actor["status"], "up");
+    assert_eq!(actor["received"], 1);
+    assert_eq!(actor["mailboxes"][0]["capacity"], 16);
+
+    Ok(())
+}
+
 async fn spawn_a_health_check_actor(
     service_to_be_monitored: &str,
     mqtt_config: &mut MqttConfig,
 ) -> SimpleMessageBox<MqttMessage, MqttMessage> {
     let mut health_mqtt_builder = MqttActorBuilder::new(mqtt_config);
+    let health_actor =
+        health_check_actor_builder(service_to_be_monitored, &mut health_mqtt_builder);
+
+    let actor = health_actor.build();
+    tokio::spawn(async move { actor.run().await });
 
+    health_mqtt_builder.build()
+}
+
+fn health_check_actor_builder(
+    service_to_be_monitored: &str,
+    health_mqtt_builder: &mut MqttActorBuilder<'_>,
+) -> HealthMonitorBuilder {
+    health_check_actor_builder_with_config(
+        service_to_be_monitored,
+        health_mqtt_builder,
+        "service.ty = \"service\"",
+    )
+}
+
+fn health_check_actor_builder_with_config(
+    service_to_be_monitored: &str,
+    health_mqtt_builder: &mut MqttActorBuilder<'_>,
+    config: &str,
+) -> HealthMonitorBuilder {
     let mqtt_schema = MqttSchema::new();
-    let config = TEdgeConfig::load_toml_str("service.ty = \"service\"");
+    let config = TEdgeConfig::load_toml_str(config);
     let service = Service {
         service_topic_id: EntityTopicId::default_main_service(service_to_be_monitored)
             .unwrap()
@@ -99,17 +298,12 @@ async fn spawn_a_health_check_actor(
         device_topic_id: EntityTopicId::default_main_device().into(),
     };
 
-    let health_actor = HealthMonitorBuilder::from_service_topic_id(
+    HealthMonitorBuilder::from_service_topic_id(
         service,
-        &mut health_mqtt_builder,
+        health_mqtt_builder,
         &mqtt_schema,
         &config.service,
-    );
-
-    let actor = health_actor.build();
-    tokio::spawn(async move { actor.run().await });
-
-    health_mqtt_builder.build()
+    )
 }
 
 struct MqttActorBuilder<'a> {
@@ -1,9 +1,13 @@
 use super::error::LogManagementError;
 use super::LogManagerConfig;
 use super::DEFAULT_PLUGIN_CONFIG_FILE_NAME;
+use crate::config::InvalidPluginConfig;
 use crate::config::PluginConfig;
+use crate::config::Redaction;
+use crate::packaging;
 use crate::plugin_manager::ExternalPlugins;
 use async_trait::async_trait;
+use camino::Utf8PathBuf;
 use std::collections::HashMap;
 use std::path::Path;
 use tedge_actors::fan_in_message_type;
@@ -16,8 +20,10 @@ use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_api::commands::CmdMetaSyncSignal;
 use tedge_api::commands::CommandStatus;
+use tedge_api::commands::LogCompression;
 use tedge_api::commands::LogUploadCmd;
 use tedge_api::commands::LogUploadCmdMetadata;
+use tedge_api::commands::LogUploadCmdPayload;
 use tedge_api::mqtt_topics::OperationType;
 use tedge_api::workflow::GenericCommandData;
 use tedge_api::workflow::GenericCommandMetadata;
@@ -62,6 +68,8 @@ pub struct LogManagerActor {
     upload_sender: DynSender<LogUploadRequest>,
     external_plugins: ExternalPlugins,
     plugin_config: PluginConfig,
+    /// Set while the plugin config is invalid, log uploads being refused meanwhile
+    plugin_config_error: Option<InvalidPluginConfig>,
 }
 
 #[async_trait]
@@ -110,6 +118,7 @@ impl LogManagerActor {
             upload_sender,
             external_plugins,
             plugin_config,
+            plugin_config_error: None,
         }
     }
 
@@ -163,6 +172,11 @@ impl LogManagerActor {
         &mut self,
         request: &LogUploadCmd,
     ) -> Result<(), LogManagementError> {
+        // Uploading log files with redaction rules that cannot be loaded could leak secrets
+        if let Some(error) = &self.plugin_config_error {
+            return Err(error.clone().into());
+        }
+
         let topic = request.topic(&self.config.mqtt_schema).as_ref().to_string();
         let request_payload = &request.payload;
 
@@ -171,34 +185,42 @@ impl LogManagerActor {
             .split_once("::")
             .unwrap_or((&request_payload.log_type, "file"));
 
-        let log_path = if let Some(plugin) = self.external_plugins.by_plugin_type(plugin_name) {
-            let output_log_path = self.config.tmp_dir.file(format!(
-                "{}_{}_{}.log",
-                log_type,
-                plugin_name,
-                OffsetDateTime::now_utc().unix_timestamp()
-            ))?;
-
-            plugin
-                .get(
-                    log_type,
-                    output_log_path.path(),
-                    Some(request_payload.date_from),
-                    Some(request_payload.date_to),
-                    request_payload.search_text.as_deref(),
-                    Some(request_payload.lines),
-                )
-                .await?;
-
-            output_log_path
+        let log_types = if request_payload.bundle {
+            self.matching_log_types(plugin_name, log_type).await?
         } else {
-            return Err(LogManagementError::PluginError {
-                plugin_name: plugin_name.to_string(),
-                reason: "Plugin not found".to_string(),
-            });
+            vec![log_type.to_string()]
+        };
+
+        let mut log_files = Vec::new();
+        for log_type in log_types {
+            match self
+                .fetch_logfile(request_payload, &log_type, plugin_name)
+                .await
+            {
+                Ok(log_file) => log_files.push((format!("{log_type}.log"), log_file)),
+                Err(err) => {
+                    for (_, log_file) in &log_files {
+                        let _ = std::fs::remove_file(log_file);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        let redactions = self.plugin_config.redactions.clone();
+        let archive_path = if request_payload.bundle {
+            Some(self.tmp_file_path("bundle", plugin_name, "tar")?)
+        } else {
+            None
         };
+        let compression = request_payload.compression;
+        let log_path = tokio::task::spawn_blocking(move || {
+            package_log_files(log_files, archive_path, &redactions, compression)
+        })
+        .await
+        .map_err(|err| std::io::Error::other(err.to_string()))??;
 
-        let upload_request = UploadRequest::new(&request_payload.tedge_url, log_path.path());
+        let upload_request = UploadRequest::new(&request_payload.tedge_url, &log_path);
 
         info!(
             target: "log plugins",
@@ -212,6 +234,86 @@ impl LogManagerActor {
         Ok(())
     }
 
+    /// Fetches the content of a log type using the given plugin
+    async fn fetch_logfile(
+        &self,
+        request_payload: &LogUploadCmdPayload,
+        log_type: &str,
+        plugin_name: &str,
+    ) -> Result<Utf8PathBuf, LogManagementError> {
+        let Some(plugin) = self.external_plugins.by_plugin_type(plugin_name) else {
+            return Err(LogManagementError::PluginError {
+                plugin_name: plugin_name.to_string(),
+                reason: "Plugin not found".to_string(),
+            });
+        };
+
+        let output_log_path = self.tmp_file_path(log_type, plugin_name, "log")?;
+        plugin
+            .get(
+                log_type,
+                &output_log_path,
+                Some(request_payload.date_from),
+                Some(request_payload.date_to),
+                request_payload.search_text.as_deref(),
+                Some(request_payload.lines),
+            )
+            .await?;
+
+        Ok(output_log_path)
+    }
+
+    /// Lists the log types of a plugin matching a glob pattern
+    async fn matching_log_types(
+        &self,
+        plugin_name: &str,
+        pattern: &str,
+    ) -> Result<Vec<String>, LogManagementError> {
+        let Some(plugin) = self.external_plugins.by_plugin_type(plugin_name) else {
+            return Err(LogManagementError::PluginError {
+                plugin_name: plugin_name.to_string(),
+                reason: "Plugin not found".to_string(),
+            });
+        };
+
+        let glob = glob::Pattern::new(pattern).map_err(|err| {
+            LogManagementError::InvalidLogTypePattern {
+                pattern: pattern.to_string(),
+                reason: err.to_string(),
+            }
+        })?;
+        let log_types = plugin.list(None).await?;
+        let log_types: Vec<String> = self
+            .plugin_config
+            .filter_log_types(plugin_name, log_types)
+            .into_iter()
+            .filter(|log_type| glob.matches(log_type))
+            .collect();
+
+        if log_types.is_empty() {
+            return Err(LogManagementError::NoMatchingLogType {
+                pattern: pattern.to_string(),
+            });
+        }
+        Ok(log_types)
+    }
+
+    fn tmp_file_path(
+        &self,
+        log_type: impl std::fmt::Display,
+        plugin_name: &str,
+        extension: &str,
+    ) -> Result<Utf8PathBuf, LogManagementError> {
+        let file = self.config.tmp_dir.file(format!(
+            "{}_{}_{}.{}",
+            log_type,
+            plugin_name,
+            OffsetDateTime::now_utc().unix_timestamp(),
+            extension
+        ))?;
+        Ok(file.path().to_owned())
+    }
+
     async fn process_uploaded_log(
         &mut self,
         topic: &str,
@@ -303,9 +405,18 @@ impl LogManagerActor {
     async fn reload_supported_log_types(&mut self) -> Result<(), RuntimeError> {
         info!(target: "log plugins", "Reloading supported log types");
 
-        // Reload plugin configuration for up-to-date filtering rules
-        self.plugin_config =
-            PluginConfig::from_file(self.config.plugin_config_path.path().as_ref()).await;
+        // Reload plugin configuration for up-to-date filtering and redaction rules.
+        // On error, the last valid filtering rules are kept, but log uploads are refused.
+        match PluginConfig::from_file(self.config.plugin_config_path.path().as_ref()).await {
+            Ok(plugin_config) => {
+                self.plugin_config = plugin_config;
+                self.plugin_config_error = None;
+            }
+            Err(err) => {
+                error!(target: "log plugins", "Log uploads are refused until fixed: {err}");
+                self.plugin_config_error = Some(err);
+            }
+        }
 
         // Note: The log manager now only handles external plugins.
         // The file-based plugin configuration is handled by the standalone plugin.
@@ -379,6 +490,41 @@ impl LogManagerActor {
     }
 }
 
+/// Redacts, bundles and compresses the fetched log files, returning the path of the file to upload
+///
+/// The fetched files are removed once bundled into an archive.
+fn package_log_files(
+    log_files: Vec<(String, Utf8PathBuf)>,
+    archive_path: Option<Utf8PathBuf>,
+    redactions: &[Redaction],
+    compression: Option<LogCompression>,
+) -> Result<Utf8PathBuf, LogManagementError> {
+    for (_, log_file) in &log_files {
+        packaging::redact_file(log_file, redactions)?;
+    }
+
+    let log_path = match archive_path {
+        Some(archive_path) => {
+            let bundled = packaging::bundle_files(&log_files, &archive_path);
+            for (_, log_file) in &log_files {
+                let _ = std::fs::remove_file(log_file);
+            }
+            bundled?;
+            archive_path
+        }
+        None => log_files
+            .into_iter()
+            .map(|(_, log_file)| log_file)
+            .next()
+            .expect("a log file is fetched when not bundling"),
+    };
+
+    match compression {
+        Some(compression) => Ok(packaging::compress_file(&log_path, compression)?),
+        None => Ok(log_path),
+    }
+}
+
 fn deduplicate_messages(messages: &mut Vec<LogInput>) {
     // remove duplicate sync messages because processing them takes a long time and we really only
     // want to process the most recent one(#4169); but preserve their order so state updated after sync
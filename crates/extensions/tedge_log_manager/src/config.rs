@@ -103,6 +103,21 @@ impl LogManagerConfig {
 struct TomlPluginConfig {
     #[serde(default)]
     plugins: HashMap<String, TomlPluginEntry>,
+    #[serde(default)]
+    redactions: Vec<TomlRedactionEntry>,
+}
+
+/// Redaction rule applied to the content of all the uploaded log files
+#[derive(Clone, Deserialize, Debug)]
+pub struct TomlRedactionEntry {
+    #[serde(deserialize_with = "deserialize_required_regex_pattern")]
+    pub pattern: Regex,
+    #[serde(default = "default_redaction_replacement")]
+    pub replacement: String,
+}
+
+fn default_redaction_replacement() -> String {
+    "********".to_string()
 }
 
 /// Configuration for a single plugin
@@ -137,10 +152,36 @@ where
     }
 }
 
+fn deserialize_required_regex_pattern<'de, D>(deserializer: D) -> Result<Regex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_regex_pattern(deserializer)?
+        .ok_or_else(|| serde::de::Error::custom("Missing regex pattern"))
+}
+
 /// Plugin configuration (compiled runtime representation)
 #[derive(Clone, Debug, Default)]
 pub struct PluginConfig {
     pub plugins: HashMap<String, PluginEntry>,
+    pub redactions: Vec<Redaction>,
+}
+
+/// Secrets to be masked in the uploaded log files
+#[derive(Clone, Debug)]
+pub struct Redaction {
+    pub pattern: Regex,
+    /// Replacement text, which can refer to the capture groups of the pattern, e.g. `$1`
+    pub replacement: String,
+}
+
+impl From<TomlRedactionEntry> for Redaction {
+    fn from(toml_entry: TomlRedactionEntry) -> Self {
+        Redaction {
+            pattern: toml_entry.pattern,
+            replacement: toml_entry.replacement,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -185,34 +226,48 @@ impl From<TomlPluginConfig> for PluginConfig {
                 .into_iter()
                 .map(|(k, v)| (k, v.into()))
                 .collect(),
+            redactions: toml_config
+                .redactions
+                .into_iter()
+                .map(Redaction::from)
+                .collect(),
         }
     }
 }
 
+/// Error raised when the plugin configuration file cannot be read or parsed
+#[derive(thiserror::Error, Clone, Debug)]
+#[error("Invalid log plugin config {path}: {reason}")]
+pub struct InvalidPluginConfig {
+    pub path: String,
+    pub reason: String,
+}
+
 impl PluginConfig {
     /// Load plugin configuration from the given path
-    pub async fn from_file(path: &Path) -> Self {
-        match tokio::fs::read_to_string(path).await {
-            Ok(contents) => match toml::from_str::<TomlPluginConfig>(&contents) {
-                Ok(toml_config) => toml_config.into(),
-                Err(err) => {
-                    warn!(
-                        "Plugin filters not applied due to failure parsing the plugin config {}: {}",
-                        path.display(),
-                        err
-                    );
-                    Self::default()
-                }
-            },
-            Err(err) => {
+    ///
+    /// A missing file is not an error: log types are not filtered and log content is not redacted.
+    /// By contrast, an invalid file is an error and must not be ignored,
+    /// as this would silently disable the redaction rules.
+    pub async fn from_file(path: &Path) -> Result<Self, InvalidPluginConfig> {
+        let invalid_config = |reason: String| InvalidPluginConfig {
+            path: path.display().to_string(),
+            reason,
+        };
+        let contents = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
                 warn!(
-                    "Plugin filters not applied due to failure reading the plugin config {}: {}",
-                    path.display(),
-                    err
+                    "Plugin filters not applied as there is no plugin config {}",
+                    path.display()
                 );
-                Self::default()
+                return Ok(Self::default());
             }
-        }
+            Err(err) => return Err(invalid_config(err.to_string())),
+        };
+        let toml_config = toml::from_str::<TomlPluginConfig>(&contents)
+            .map_err(|err| invalid_config(err.to_string()))?;
+        Ok(toml_config.into())
     }
 
     pub(crate) fn get_filters(&self, plugin_name: &str) -> Option<&PluginFilterConfig> {
@@ -303,7 +358,7 @@ exclude = "unwanted-.*"
         temp_file.write_all(toml_content.as_bytes()).unwrap();
         temp_file.flush().unwrap();
 
-        let config = PluginConfig::from_file(temp_file.path()).await;
+        let config = PluginConfig::from_file(temp_file.path()).await.unwrap();
 
         // Check journald filters
         let journald_filters = config.get_filters("journald").unwrap();
@@ -353,7 +408,10 @@ exclude = "unwanted-.*"
             },
         );
 
-        let config = PluginConfig { plugins };
+        let config = PluginConfig {
+            plugins,
+            ..Default::default()
+        };
         let log_types: BTreeSet<String> = ["ssh", "tedge-agent", "mosquitto", "systemd-logind"]
             .iter()
             .map(|s| s.to_string())
@@ -382,7 +440,10 @@ exclude = "unwanted-.*"
             },
         );
 
-        let config = PluginConfig { plugins };
+        let config = PluginConfig {
+            plugins,
+            ..Default::default()
+        };
         let log_types: BTreeSet<String> = [
             "ssh",
             "tedge-agent",
@@ -417,7 +478,10 @@ exclude = "unwanted-.*"
             },
         );
 
-        let config = PluginConfig { plugins };
+        let config = PluginConfig {
+            plugins,
+            ..Default::default()
+        };
         let log_types: BTreeSet<String> = [
             "ssh",
             "tedge-agent",
@@ -442,7 +506,31 @@ exclude = "unwanted-.*"
     }
 
     #[tokio::test]
-    async fn test_invalid_regex_pattern_returns_default_config() {
+    async fn test_redactions_from_toml() {
+        let toml_content = r#"
+[[redactions]]
+pattern = "password=\\S+"
+
+[[redactions]]
+pattern = "(token:)\\s*\\w+"
+replacement = "$1 <redacted>"
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(toml_content.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let config = PluginConfig::from_file(temp_file.path()).await.unwrap();
+
+        assert_eq!(config.redactions.len(), 2);
+        assert_eq!(config.redactions[0].pattern.as_str(), r"password=\S+");
+        assert_eq!(config.redactions[0].replacement, "********");
+        assert_eq!(config.redactions[1].pattern.as_str(), r"(token:)\s*\w+");
+        assert_eq!(config.redactions[1].replacement, "$1 <redacted>");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_regex_pattern_is_an_error() {
         let toml_content = r#"
 [[plugins.journald.filters]]
 include = "[invalid"
@@ -452,8 +540,39 @@ include = "[invalid"
         temp_file.write_all(toml_content.as_bytes()).unwrap();
         temp_file.flush().unwrap();
 
-        let config = PluginConfig::from_file(temp_file.path()).await;
+        let error = PluginConfig::from_file(temp_file.path()).await.unwrap_err();
+
+        assert!(error.reason.contains("Invalid regex pattern '[invalid'"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_redaction_pattern_is_an_error() {
+        let toml_content = r#"
+[[redactions]]
+pattern = "password=\\S+"
+
+[[redactions]]
+pattern = "(token:\\s*\\w+"
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(toml_content.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let error = PluginConfig::from_file(temp_file.path()).await.unwrap_err();
+
+        assert!(error.reason.contains("Invalid regex pattern"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_config_file_is_not_an_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = PluginConfig::from_file(&temp_dir.path().join("tedge-log-plugin.toml"))
+            .await
+            .unwrap();
 
         assert!(config.plugins.is_empty());
+        assert!(config.redactions.is_empty());
     }
 }
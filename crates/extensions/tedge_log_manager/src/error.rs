@@ -19,6 +19,15 @@ pub enum LogManagementError {
 
     #[error("Log plugin '{plugin_name}' error: {reason}")]
     PluginError { plugin_name: String, reason: String },
+
+    #[error("Invalid log type pattern '{pattern}': {reason}")]
+    InvalidLogTypePattern { pattern: String, reason: String },
+
+    #[error("No log type matches '{pattern}'")]
+    NoMatchingLogType { pattern: String },
+
+    #[error(transparent)]
+    FromInvalidPluginConfig(#[from] crate::config::InvalidPluginConfig),
 }
 
 impl From<LogManagementError> for tedge_actors::RuntimeError {
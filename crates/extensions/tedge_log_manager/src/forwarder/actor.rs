@@ -0,0 +1,387 @@
+use crate::forwarder::config::ForwardRule;
+use crate::forwarder::config::LogForwarderConfig;
+use crate::forwarder::config::LogSource;
+use crate::forwarder::source::tail_files;
+use crate::forwarder::source::tail_journald;
+use crate::forwarder::source::FilePosition;
+use crate::forwarder::source::LogLine;
+use crate::forwarder::source::LogPosition;
+use async_trait::async_trait;
+use camino::Utf8Path;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::time::Duration;
+use tedge_actors::fan_in_message_type;
+use tedge_actors::Actor;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::LoggingSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::mqtt_topics::Channel;
+use tedge_file_system_ext::FsWatchEvent;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::QoS;
+use tedge_utils::fs::atomically_write_file_async;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+use tokio::time::sleep_until;
+use tokio::time::Instant;
+use tracing::info;
+use tracing::warn;
+
+/// Maximum delay between two saves of the checkpoints, when some lines have been read but not forwarded
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Delay between a publish and the save of the checkpoints
+///
+/// The lines published in a burst are checkpointed at once.
+const CHECKPOINT_BATCH_DELAY: Duration = Duration::from_millis(500);
+
+/// Window over which the rate of the published events is limited
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+fan_in_message_type!(LogForwarderInput[LogLine, FsWatchEvent] : Debug);
+
+pub struct LogForwarderActor {
+    config: LogForwarderConfig,
+    rules: Vec<ForwardRule>,
+    messages: SimpleMessageBox<LogForwarderInput, NoMessage>,
+    line_sender: DynSender<LogLine>,
+    mqtt_publisher: LoggingSender<MqttMessage>,
+}
+
+#[async_trait]
+impl Actor for LogForwarderActor {
+    fn name(&self) -> &str {
+        "LogForwarder"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let mut checkpoints = Checkpoints::load(&self.config.checkpoint_path).await;
+
+        // The rules are numbered across reloads,
+        // so the lines read on behalf of outdated rules can be ignored
+        let mut first_rule = 0;
+        let mut tasks = self.spawn_sources(first_rule, &checkpoints);
+        let mut states: Vec<RuleState> = self.rules.iter().map(RuleState::new).collect();
+        let mut save_deadline: Option<Instant> = None;
+
+        loop {
+            let input = match save_deadline {
+                Some(deadline) => tokio::select! {
+                    input = self.messages.recv() => input,
+                    _ = sleep_until(deadline) => {
+                        checkpoints.save(&self.config.checkpoint_path).await;
+                        save_deadline = None;
+                        continue;
+                    }
+                },
+                None => self.messages.recv().await,
+            };
+            match input {
+                None => break,
+                Some(LogForwarderInput::LogLine(line)) => {
+                    let Some(index) = line.rule.checked_sub(first_rule) else {
+                        continue;
+                    };
+                    let Some(state) = states.get_mut(index) else {
+                        continue;
+                    };
+                    let published = self.forward_line(index, state, &line).await?;
+                    checkpoints.update(&self.rules[index].checkpoint_key(index), line.position);
+
+                    // Persist the checkpoints shortly after a publish, so no lines are published twice on restart
+                    let delay = if published {
+                        CHECKPOINT_BATCH_DELAY
+                    } else {
+                        CHECKPOINT_INTERVAL
+                    };
+                    let deadline = Instant::now() + delay;
+                    save_deadline = Some(save_deadline.map_or(deadline, |d| d.min(deadline)));
+                }
+                Some(LogForwarderInput::FsWatchEvent(event)) => {
+                    if !self.plugin_config_updated(&event) {
+                        continue;
+                    }
+                    info!("Reloading the log forwarding rules");
+                    for task in tasks {
+                        task.abort();
+                    }
+                    checkpoints.save(&self.config.checkpoint_path).await;
+                    save_deadline = None;
+
+                    first_rule += self.rules.len();
+                    self.rules =
+                        ForwardRule::load_rules(self.config.plugin_config_path.as_std_path()).await;
+                    states = self.rules.iter().map(RuleState::new).collect();
+                    tasks = self.spawn_sources(first_rule, &checkpoints);
+                }
+            }
+        }
+
+        for task in tasks {
+            task.abort();
+        }
+        if save_deadline.is_some() {
+            checkpoints.save(&self.config.checkpoint_path).await;
+        }
+        Ok(())
+    }
+}
+
+impl LogForwarderActor {
+    pub fn new(
+        config: LogForwarderConfig,
+        rules: Vec<ForwardRule>,
+        messages: SimpleMessageBox<LogForwarderInput, NoMessage>,
+        line_sender: DynSender<LogLine>,
+        mqtt_publisher: LoggingSender<MqttMessage>,
+    ) -> Self {
+        Self {
+            config,
+            rules,
+            messages,
+            line_sender,
+            mqtt_publisher,
+        }
+    }
+
+    /// Whether a filesystem event is an update of the file defining the forward rules
+    fn plugin_config_updated(&self, event: &FsWatchEvent) -> bool {
+        match event {
+            FsWatchEvent::Modified(path) | FsWatchEvent::FileDeleted(path) => {
+                path == self.config.plugin_config_path.as_std_path()
+            }
+            _ => false,
+        }
+    }
+
+    fn spawn_sources(&self, first_rule: usize, checkpoints: &Checkpoints) -> Vec<JoinHandle<()>> {
+        let mut tasks = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let checkpoint = checkpoints.get(&rule.checkpoint_key(index));
+            let sender = self.line_sender.sender_clone();
+            let task = match &rule.source {
+                LogSource::Files { patterns } => {
+                    info!(
+                        "Forwarding the logs of {:?} as {} events",
+                        patterns, rule.event_type
+                    );
+                    tokio::spawn(tail_files(
+                        first_rule + index,
+                        patterns.clone(),
+                        checkpoint.files,
+                        sender,
+                    ))
+                }
+                LogSource::Journald { unit, priority } => {
+                    info!(
+                        "Forwarding the logs of {unit} as {} events",
+                        rule.event_type
+                    );
+                    tokio::spawn(tail_journald(
+                        first_rule + index,
+                        unit.clone(),
+                        *priority,
+                        checkpoint.cursor,
+                        sender,
+                    ))
+                }
+            };
+            tasks.push(task);
+        }
+        tasks
+    }
+
+    /// Publish the events and alarms triggered by a log line
+    ///
+    /// Return true if an event or an alarm has been published.
+    async fn forward_line(
+        &mut self,
+        index: usize,
+        state: &mut RuleState,
+        line: &LogLine,
+    ) -> Result<bool, RuntimeError> {
+        let rule = &self.rules[index];
+        let time = line
+            .time
+            .unwrap_or_else(OffsetDateTime::now_utc)
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        let mut published = false;
+
+        // Alarms are checked on all lines, independently of the event filters
+        for alarm in rule.alarms.iter() {
+            let raised = state.alarms.get(&alarm.alarm_type).copied();
+            let payload = if raised != Some(true) && alarm.raise.is_match(&line.text) {
+                state.alarms.insert(alarm.alarm_type.clone(), true);
+                json!({
+                    "text": line.text,
+                    "severity": alarm.severity,
+                    "time": time,
+                })
+                .to_string()
+            } else if raised != Some(false)
+                && alarm.clear.as_ref().is_some_and(|p| p.is_match(&line.text))
+            {
+                state.alarms.insert(alarm.alarm_type.clone(), false);
+                String::new()
+            } else {
+                continue;
+            };
+            let topic = self.config.mqtt_schema.topic_for(
+                &self.config.device_topic_id,
+                &Channel::Alarm {
+                    alarm_type: alarm.alarm_type.clone(),
+                },
+            );
+            let message = MqttMessage::new(&topic, payload)
+                .with_retain()
+                .with_qos(QoS::AtLeastOnce);
+            self.mqtt_publisher.send(message).await?;
+            published = true;
+        }
+
+        if rule.accept(&line.text) && state.rate_limiter.allow(&rule.event_type) {
+            let topic = self.config.mqtt_schema.topic_for(
+                &self.config.device_topic_id,
+                &Channel::Event {
+                    event_type: rule.event_type.clone(),
+                },
+            );
+            let payload = json!({
+                "text": line.text,
+                "time": time,
+            });
+            let message = MqttMessage::new(&topic, payload.to_string()).with_qos(QoS::AtLeastOnce);
+            self.mqtt_publisher.send(message).await?;
+            published = true;
+        }
+
+        Ok(published)
+    }
+}
+
+/// The runtime state of a forward rule
+struct RuleState {
+    rate_limiter: RateLimiter,
+    /// The alarms known to be raised (true) or cleared (false)
+    alarms: HashMap<String, bool>,
+}
+
+impl RuleState {
+    fn new(rule: &ForwardRule) -> Self {
+        RuleState {
+            rate_limiter: RateLimiter::new(rule.max_rate),
+            alarms: HashMap::new(),
+        }
+    }
+}
+
+/// Limit the number of events published over a fixed window of time
+struct RateLimiter {
+    max_rate: Option<u32>,
+    window_start: Instant,
+    count: u32,
+    dropped: u32,
+}
+
+impl RateLimiter {
+    fn new(max_rate: Option<u32>) -> Self {
+        RateLimiter {
+            max_rate,
+            window_start: Instant::now(),
+            count: 0,
+            dropped: 0,
+        }
+    }
+
+    fn allow(&mut self, event_type: &str) -> bool {
+        let Some(max_rate) = self.max_rate else {
+            return true;
+        };
+        if self.window_start.elapsed() >= RATE_WINDOW {
+            if self.dropped > 0 {
+                warn!(
+                    "{} {event_type} events have been dropped, exceeding the rate of {max_rate} events per minute",
+                    self.dropped
+                );
+            }
+            self.window_start = Instant::now();
+            self.count = 0;
+            self.dropped = 0;
+        }
+        if self.count < max_rate {
+            self.count += 1;
+            true
+        } else {
+            self.dropped += 1;
+            false
+        }
+    }
+}
+
+/// The positions reached in the forwarded logs, indexed by [ForwardRule::checkpoint_key]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Checkpoints {
+    rules: BTreeMap<String, Checkpoint>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    files: BTreeMap<String, FilePosition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+impl Checkpoints {
+    async fn load(path: &Utf8Path) -> Self {
+        match tokio::fs::read(path).await {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|err| {
+                warn!("Ignoring the log forwarding checkpoints, failed to parse {path}: {err}");
+                Checkpoints::default()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Checkpoints::default(),
+            Err(err) => {
+                warn!("Ignoring the log forwarding checkpoints, failed to read {path}: {err}");
+                Checkpoints::default()
+            }
+        }
+    }
+
+    async fn save(&mut self, path: &Utf8Path) {
+        for checkpoint in self.rules.values_mut() {
+            checkpoint
+                .files
+                .retain(|file, _| std::path::Path::new(file).exists());
+        }
+        let content = serde_json::to_vec(self).unwrap_or_default();
+        if let Err(err) = atomically_write_file_async(path, &content).await {
+            warn!("Failed to save the log forwarding checkpoints to {path}: {err}");
+        }
+    }
+
+    fn get(&self, key: &str) -> Checkpoint {
+        self.rules.get(key).cloned().unwrap_or_default()
+    }
+
+    fn update(&mut self, key: &str, position: LogPosition) {
+        let checkpoint = self.rules.entry(key.to_string()).or_default();
+        match position {
+            LogPosition::File { path, position } => {
+                checkpoint.files.insert(path, position);
+            }
+            LogPosition::Journald { cursor } => checkpoint.cursor = Some(cursor),
+        }
+    }
+}
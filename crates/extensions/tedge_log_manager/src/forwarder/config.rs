@@ -0,0 +1,318 @@
+use crate::config::deserialize_regex_pattern;
+use camino::Utf8PathBuf;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tracing::warn;
+
+pub const DEFAULT_CHECKPOINT_FILE_NAME: &str = "log-forwarder.json";
+
+/// Configuration of the Log Forwarder
+#[derive(Clone, Debug)]
+pub struct LogForwarderConfig {
+    pub mqtt_schema: MqttSchema,
+    pub device_topic_id: EntityTopicId,
+    /// The `tedge-log-plugin.toml` file where the `[[forward]]` rules are defined
+    pub plugin_config_path: Utf8PathBuf,
+    /// The file where the positions reached in the forwarded logs are persisted
+    pub checkpoint_path: Utf8PathBuf,
+}
+
+/// Forwarding rules parsed from tedge-log-plugin.toml
+#[derive(Deserialize, Debug, Default)]
+struct TomlForwarderConfig {
+    #[serde(default)]
+    files: Vec<TomlFileEntry>,
+    #[serde(default)]
+    forward: Vec<TomlForwardEntry>,
+}
+
+/// The file log types, as defined for the `file` log plugin
+#[derive(Deserialize, Debug)]
+struct TomlFileEntry {
+    #[serde(rename = "type")]
+    log_type: String,
+    path: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TomlForwardEntry {
+    /// A log type defined by a `[[files]]` entry
+    #[serde(rename = "type")]
+    log_type: Option<String>,
+    /// A systemd unit, which logs are read from journald
+    unit: Option<String>,
+    /// A journald priority level, from 0 (emerg) to 7 (debug)
+    priority: Option<u8>,
+    #[serde(default, deserialize_with = "deserialize_regex_pattern")]
+    include: Option<Regex>,
+    #[serde(default, deserialize_with = "deserialize_regex_pattern")]
+    exclude: Option<Regex>,
+    /// Maximum number of events published per minute
+    max_rate: Option<u32>,
+    event_type: Option<String>,
+    #[serde(default)]
+    alarms: Vec<TomlAlarmEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TomlAlarmEntry {
+    #[serde(rename = "type")]
+    alarm_type: String,
+    #[serde(deserialize_with = "deserialize_regex")]
+    raise: Regex,
+    #[serde(default, deserialize_with = "deserialize_regex_pattern")]
+    clear: Option<Regex>,
+    #[serde(default = "default_severity")]
+    severity: String,
+}
+
+fn default_severity() -> String {
+    "major".to_string()
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_regex_pattern(deserializer)?
+        .ok_or_else(|| serde::de::Error::custom("Missing regex pattern"))
+}
+
+/// Where the lines of a forwarded log are read from
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LogSource {
+    /// Files matching glob patterns
+    Files { patterns: Vec<String> },
+
+    /// The journal of a systemd unit
+    Journald { unit: String, priority: Option<u8> },
+}
+
+/// A rule telling how to forward the lines of a log
+#[derive(Clone, Debug)]
+pub struct ForwardRule {
+    pub source: LogSource,
+    pub event_type: String,
+    pub include: Option<Regex>,
+    pub exclude: Option<Regex>,
+    pub max_rate: Option<u32>,
+    pub alarms: Vec<AlarmRule>,
+}
+
+/// A rule telling when to raise and clear an alarm, depending on the log lines
+#[derive(Clone, Debug)]
+pub struct AlarmRule {
+    pub alarm_type: String,
+    pub raise: Regex,
+    pub clear: Option<Regex>,
+    pub severity: String,
+}
+
+impl ForwardRule {
+    /// The key under which the position reached by this rule is checkpointed
+    ///
+    /// The key doesn't depend on the event type, so a renamed event type doesn't reset the position,
+    /// but is specific to the rank of the rule, so several rules can forward the same log.
+    pub fn checkpoint_key(&self, index: usize) -> String {
+        match &self.source {
+            LogSource::Files { patterns } => format!("{index}:files:{}", patterns.join(",")),
+            LogSource::Journald { unit, .. } => format!("{index}:journald:{unit}"),
+        }
+    }
+
+    /// Load the forwarding rules from the given path
+    ///
+    /// Invalid rules are ignored with a warning.
+    pub async fn load_rules(path: &Path) -> Vec<ForwardRule> {
+        let contents = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return vec![],
+            Err(err) => {
+                warn!(
+                    "Logs not forwarded due to failure reading the plugin config {}: {}",
+                    path.display(),
+                    err
+                );
+                return vec![];
+            }
+        };
+        match toml::from_str::<TomlForwarderConfig>(&contents) {
+            Ok(config) => ForwardRule::from_toml(config),
+            Err(err) => {
+                warn!(
+                    "Logs not forwarded due to failure parsing the plugin config {}: {}",
+                    path.display(),
+                    err
+                );
+                vec![]
+            }
+        }
+    }
+
+    fn from_toml(config: TomlForwarderConfig) -> Vec<ForwardRule> {
+        let mut rules = Vec::new();
+        let mut event_types = HashSet::new();
+        for entry in config.forward {
+            let (source, default_event_type) = match (entry.log_type, entry.unit) {
+                (Some(log_type), None) => {
+                    let patterns: Vec<String> = config
+                        .files
+                        .iter()
+                        .filter(|file| file.log_type == log_type)
+                        .map(|file| file.path.clone())
+                        .collect();
+                    if patterns.is_empty() {
+                        warn!("Logs not forwarded for unknown log type: {log_type}");
+                        continue;
+                    }
+                    (LogSource::Files { patterns }, log_type)
+                }
+                (None, Some(unit)) => {
+                    let priority = entry.priority;
+                    (
+                        LogSource::Journald {
+                            unit: unit.clone(),
+                            priority,
+                        },
+                        unit,
+                    )
+                }
+                _ => {
+                    warn!("Logs not forwarded: a forward rule requires either a log type or a unit, but not both");
+                    continue;
+                }
+            };
+            if entry.priority.is_some_and(|priority| priority > 7) {
+                warn!("Logs not forwarded for {default_event_type}: the priority must be between 0 and 7");
+                continue;
+            }
+            let event_type = entry.event_type.unwrap_or(default_event_type);
+            if !event_types.insert(event_type.clone()) {
+                warn!("Logs not forwarded: the {event_type} event type is used by several forward rules");
+                continue;
+            }
+            rules.push(ForwardRule {
+                source,
+                event_type,
+                include: entry.include,
+                exclude: entry.exclude,
+                max_rate: entry.max_rate,
+                alarms: entry
+                    .alarms
+                    .into_iter()
+                    .map(|alarm| AlarmRule {
+                        alarm_type: alarm.alarm_type,
+                        raise: alarm.raise,
+                        clear: alarm.clear,
+                        severity: alarm.severity,
+                    })
+                    .collect(),
+            })
+        }
+        rules
+    }
+
+    /// Check if a line has to be forwarded as an event
+    ///
+    /// A line is forwarded if it matches the `include` pattern (if any)
+    /// and doesn't match the `exclude` pattern (if any).
+    pub fn accept(&self, line: &str) -> bool {
+        self.include.as_ref().is_none_or(|p| p.is_match(line))
+            && self.exclude.as_ref().is_none_or(|p| !p.is_match(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_rules(toml: &str) -> Vec<ForwardRule> {
+        ForwardRule::from_toml(toml::from_str(toml).unwrap())
+    }
+
+    #[test]
+    fn forward_rules_from_toml() {
+        let rules = parse_rules(
+            r#"
+[[files]]
+type = "software-management"
+path = "/var/log/tedge/agent/software-*"
+
+[[forward]]
+type = "software-management"
+include = "ERROR|WARN"
+max_rate = 10
+
+[[forward.alarms]]
+type = "install_failure"
+raise = "Failed to install"
+clear = "Successfully installed"
+
+[[forward]]
+unit = "mosquitto"
+priority = 4
+event_type = "mosquitto_logs"
+"#,
+        );
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            rules[0].source,
+            LogSource::Files {
+                patterns: vec!["/var/log/tedge/agent/software-*".to_string()]
+            }
+        );
+        assert_eq!(rules[0].event_type, "software-management");
+        assert_eq!(rules[0].max_rate, Some(10));
+        assert_eq!(rules[0].alarms[0].alarm_type, "install_failure");
+        assert_eq!(rules[0].alarms[0].severity, "major");
+        assert!(rules[0].accept("ERROR: no space left"));
+        assert!(!rules[0].accept("INFO: installing"));
+
+        assert_eq!(
+            rules[1].source,
+            LogSource::Journald {
+                unit: "mosquitto".to_string(),
+                priority: Some(4)
+            }
+        );
+        assert_eq!(rules[1].event_type, "mosquitto_logs");
+        assert!(rules[1].accept("any line"));
+    }
+
+    #[test]
+    fn invalid_forward_rules_are_ignored() {
+        let rules = parse_rules(
+            r#"
+[[files]]
+type = "agent"
+path = "/var/log/tedge/agent/*.log"
+
+[[forward]]
+type = "unknown"
+
+[[forward]]
+type = "agent"
+unit = "tedge-agent"
+
+[[forward]]
+unit = "tedge-agent"
+priority = 9
+
+[[forward]]
+type = "agent"
+
+[[forward]]
+unit = "tedge-agent"
+event_type = "agent"
+"#,
+        );
+
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].event_type, "agent");
+    }
+}
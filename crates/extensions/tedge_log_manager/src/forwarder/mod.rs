@@ -0,0 +1,88 @@
+//! Continuous forwarding of log lines as thin-edge events and alarms
+//!
+//! While the log manager uploads logs on demand, the log forwarder tails some of the logs
+//! and publishes, as they are appended, the lines matching the rules defined in `tedge-log-plugin.toml`.
+mod actor;
+mod config;
+mod source;
+
+#[cfg(test)]
+mod tests;
+
+pub use actor::*;
+pub use config::*;
+pub use source::FilePosition;
+pub use source::LogLine;
+pub use source::LogPosition;
+use std::convert::Infallible;
+use std::path::PathBuf;
+use tedge_actors::Builder;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::LoggingSender;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_file_system_ext::FsWatchEvent;
+use tedge_mqtt_ext::MqttMessage;
+
+/// This is an actor builder.
+pub struct LogForwarderBuilder {
+    config: LogForwarderConfig,
+    rules: Vec<ForwardRule>,
+    box_builder: SimpleMessageBoxBuilder<LogForwarderInput, NoMessage>,
+    mqtt_publisher: LoggingSender<MqttMessage>,
+}
+
+impl LogForwarderBuilder {
+    pub fn new(
+        config: LogForwarderConfig,
+        rules: Vec<ForwardRule>,
+        mqtt_actor: &mut impl MessageSink<MqttMessage>,
+        fs_notify: &mut impl MessageSource<FsWatchEvent, Vec<PathBuf>>,
+    ) -> Self {
+        let mqtt_publisher = LoggingSender::new(
+            "LogForwarderToMqttPublisher".into(),
+            mqtt_actor.get_sender(),
+        );
+        let box_builder = SimpleMessageBoxBuilder::new("LogForwarder", 64);
+
+        // The rules are reloaded when the plugin config file is updated
+        if let Some(config_dir) = config.plugin_config_path.parent() {
+            fs_notify.connect_sink(
+                vec![config_dir.as_std_path().to_path_buf()],
+                &box_builder.get_sender(),
+            );
+        }
+        Self {
+            config,
+            rules,
+            box_builder,
+            mqtt_publisher,
+        }
+    }
+}
+
+impl RuntimeRequestSink for LogForwarderBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<LogForwarderActor> for LogForwarderBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<LogForwarderActor, Self::Error> {
+        let line_sender = self.box_builder.get_sender().sender_clone();
+        Ok(LogForwarderActor::new(
+            self.config,
+            self.rules,
+            self.box_builder.build(),
+            line_sender,
+            self.mqtt_publisher,
+        ))
+    }
+}
@@ -0,0 +1,414 @@
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::io::SeekFrom;
+use std::os::unix::fs::MetadataExt;
+use std::process::Stdio;
+use std::time::Duration;
+use tedge_actors::ChannelError;
+use tedge_actors::DynSender;
+use tedge_actors::Sender;
+use time::OffsetDateTime;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncSeekExt;
+use tokio::io::BufReader;
+use tracing::warn;
+
+/// Delay between two scans of the forwarded log files
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Delay before restarting `journalctl` when it exits unexpectedly
+const JOURNALCTL_RESTART_DELAY: Duration = Duration::from_secs(10);
+
+/// A line read from a forwarded log
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogLine {
+    /// Index of the forward rule which source the line has been read from
+    pub rule: usize,
+    pub text: String,
+    /// The time of the log entry, if known
+    pub time: Option<OffsetDateTime>,
+    /// The position reached in the source once this line has been read
+    pub position: LogPosition,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LogPosition {
+    File {
+        path: String,
+        position: FilePosition,
+    },
+    Journald {
+        cursor: String,
+    },
+}
+
+/// Position reached in a log file
+///
+/// The inode is used to detect rotations, i.e. when a file is replaced by a new one with the same name.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FilePosition {
+    pub ino: u64,
+    pub offset: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum TailError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Channel(#[from] ChannelError),
+}
+
+/// Send to the forwarder the lines appended to the files matching the given glob patterns
+///
+/// The files are read from the given positions.
+/// If no positions are given, the lines already in the files are skipped,
+/// while the files created later are read from their beginning.
+/// Only complete lines are sent, i.e. lines terminated by a new line.
+///
+/// The files are kept open between two scans, so when a file is rotated
+/// the lines appended to the old file are forwarded before those of the new one.
+pub async fn tail_files(
+    rule: usize,
+    patterns: Vec<String>,
+    mut positions: BTreeMap<String, FilePosition>,
+    mut sender: DynSender<LogLine>,
+) {
+    let mut skip_existing_lines = positions.is_empty();
+    let mut files: BTreeMap<String, TailedFile> = BTreeMap::new();
+    loop {
+        let paths = matching_files(&patterns);
+        for path in paths.iter() {
+            let Ok(metadata) = tokio::fs::metadata(path).await else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            if files
+                .get(path)
+                .is_some_and(|file| file.position.ino != metadata.ino())
+            {
+                // The file has been rotated: the old file is read up to its end before being closed
+                if let Some(mut file) = files.remove(path) {
+                    if file.drain(rule, path, &mut sender).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            let file = match files.entry(path.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let position = match positions.remove(path) {
+                        Some(position) => position,
+                        None if skip_existing_lines => FilePosition {
+                            ino: metadata.ino(),
+                            offset: metadata.len(),
+                        },
+                        None => FilePosition::default(),
+                    };
+                    match TailedFile::open(path, position).await {
+                        Ok(file) => entry.insert(file),
+                        Err(err) => {
+                            warn!("Failed to open log file {path}: {err}");
+                            continue;
+                        }
+                    }
+                }
+            };
+            match file
+                .read_new_lines(rule, path, &metadata, &mut sender)
+                .await
+            {
+                Ok(()) => {}
+                Err(TailError::Io(err)) => warn!("Failed to read log file {path}: {err}"),
+                Err(TailError::Channel(_)) => return,
+            }
+        }
+
+        // The files that have been moved or removed are read up to their end before being closed
+        let gone: Vec<String> = files
+            .keys()
+            .filter(|path| !paths.contains(*path))
+            .cloned()
+            .collect();
+        for path in gone {
+            if let Some(mut file) = files.remove(&path) {
+                if file.drain(rule, &path, &mut sender).await.is_err() {
+                    return;
+                }
+            }
+        }
+        skip_existing_lines = false;
+        tokio::time::sleep(FILE_POLL_INTERVAL).await;
+    }
+}
+
+fn matching_files(patterns: &[String]) -> Vec<String> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        match glob::glob(pattern) {
+            Ok(paths) => files.extend(
+                paths
+                    .flatten()
+                    .map(|path| path.to_string_lossy().to_string()),
+            ),
+            Err(err) => warn!("Invalid log file pattern {pattern}: {err}"),
+        }
+    }
+    files
+}
+
+/// A log file being followed
+struct TailedFile {
+    reader: BufReader<tokio::fs::File>,
+    /// The position of the last complete line read from the file
+    position: FilePosition,
+    /// The beginning of a line not terminated yet
+    partial_line: Vec<u8>,
+}
+
+impl TailedFile {
+    /// Open a file to be read from the given position
+    ///
+    /// The file is read from its beginning if the position is related to another file.
+    async fn open(path: &str, mut position: FilePosition) -> Result<Self, std::io::Error> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let metadata = file.metadata().await?;
+        if position.ino != metadata.ino() || metadata.len() < position.offset {
+            position = FilePosition {
+                ino: metadata.ino(),
+                offset: 0,
+            };
+        }
+        file.seek(SeekFrom::Start(position.offset)).await?;
+        Ok(TailedFile {
+            reader: BufReader::new(file),
+            position,
+            partial_line: Vec::new(),
+        })
+    }
+
+    /// Send the lines appended to the file since the last read
+    async fn read_new_lines(
+        &mut self,
+        rule: usize,
+        path: &str,
+        metadata: &std::fs::Metadata,
+        sender: &mut DynSender<LogLine>,
+    ) -> Result<(), TailError> {
+        if metadata.len() < self.position.offset {
+            // The file has been truncated
+            self.reader.seek(SeekFrom::Start(0)).await?;
+            self.position.offset = 0;
+            self.partial_line.clear();
+        }
+        if metadata.len() == self.position.offset {
+            return Ok(());
+        }
+        loop {
+            let len = self
+                .reader
+                .read_until(b'\n', &mut self.partial_line)
+                .await?;
+            if len == 0 || self.partial_line.last() != Some(&b'\n') {
+                // Incomplete lines are read once terminated
+                return Ok(());
+            }
+            self.send_line(rule, path, sender).await?;
+        }
+    }
+
+    /// Send all the remaining lines of a file that is no longer written, including an unterminated last line
+    async fn drain(
+        &mut self,
+        rule: usize,
+        path: &str,
+        sender: &mut DynSender<LogLine>,
+    ) -> Result<(), ChannelError> {
+        loop {
+            match self.reader.read_until(b'\n', &mut self.partial_line).await {
+                Ok(0) => break,
+                Ok(_) if self.partial_line.last() == Some(&b'\n') => {
+                    self.send_line(rule, path, sender).await?
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    warn!("Failed to read rotated log file {path}: {err}");
+                    break;
+                }
+            }
+        }
+        if !self.partial_line.is_empty() {
+            self.send_line(rule, path, sender).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_line(
+        &mut self,
+        rule: usize,
+        path: &str,
+        sender: &mut DynSender<LogLine>,
+    ) -> Result<(), ChannelError> {
+        self.position.offset += self.partial_line.len() as u64;
+        let text = String::from_utf8_lossy(&self.partial_line)
+            .trim_end_matches(['\n', '\r'])
+            .to_string();
+        self.partial_line.clear();
+        sender
+            .send(LogLine {
+                rule,
+                text,
+                time: None,
+                position: LogPosition::File {
+                    path: path.to_string(),
+                    position: self.position,
+                },
+            })
+            .await
+    }
+}
+
+/// Send to the forwarder the entries added to the journal of a systemd unit
+///
+/// The journal is read after the given cursor, if any, or from its end.
+pub async fn tail_journald(
+    rule: usize,
+    unit: String,
+    priority: Option<u8>,
+    mut cursor: Option<String>,
+    mut sender: DynSender<LogLine>,
+) {
+    loop {
+        match follow_journal(rule, &unit, priority, &mut cursor, &mut sender).await {
+            Ok(()) => warn!("journalctl exited while following the logs of {unit}"),
+            Err(TailError::Io(err)) => warn!("Failed to follow the logs of {unit}: {err}"),
+            Err(TailError::Channel(_)) => return,
+        }
+        tokio::time::sleep(JOURNALCTL_RESTART_DELAY).await;
+    }
+}
+
+async fn follow_journal(
+    rule: usize,
+    unit: &str,
+    priority: Option<u8>,
+    cursor: &mut Option<String>,
+    sender: &mut DynSender<LogLine>,
+) -> Result<(), TailError> {
+    let mut command = tokio::process::Command::new("journalctl");
+    command
+        .args(["--follow", "--output=json", "--unit", unit])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    if let Some(priority) = priority {
+        command.arg(format!("--priority={priority}"));
+    }
+    match cursor {
+        Some(cursor) => command.arg(format!("--after-cursor={cursor}")),
+        None => command.arg("--lines=0"),
+    };
+
+    let mut child = command.spawn()?;
+    let Some(stdout) = child.stdout.take() else {
+        return Ok(());
+    };
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        let Some(entry) = JournalEntry::parse(&line) else {
+            continue;
+        };
+        *cursor = Some(entry.cursor.clone());
+        sender
+            .send(LogLine {
+                rule,
+                text: entry.message,
+                time: entry.time,
+                position: LogPosition::Journald {
+                    cursor: entry.cursor,
+                },
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// The fields of a journal entry, as output by `journalctl --output=json`
+#[derive(Debug, Eq, PartialEq)]
+struct JournalEntry {
+    message: String,
+    time: Option<OffsetDateTime>,
+    cursor: String,
+}
+
+impl JournalEntry {
+    fn parse(line: &str) -> Option<JournalEntry> {
+        let entry: Value = serde_json::from_str(line).ok()?;
+        let cursor = entry.get("__CURSOR")?.as_str()?.to_string();
+
+        // Messages that are not valid UTF-8 are given as arrays of bytes
+        let message = match entry.get("MESSAGE")? {
+            Value::String(message) => message.clone(),
+            Value::Array(bytes) => {
+                let bytes: Vec<u8> = bytes
+                    .iter()
+                    .filter_map(|b| b.as_u64())
+                    .map(|b| b as u8)
+                    .collect();
+                String::from_utf8_lossy(&bytes).to_string()
+            }
+            _ => return None,
+        };
+
+        let time = entry
+            .get("__REALTIME_TIMESTAMP")
+            .and_then(Value::as_str)
+            .and_then(|micros| micros.parse::<i128>().ok())
+            .and_then(|micros| OffsetDateTime::from_unix_timestamp_nanos(micros * 1000).ok());
+
+        Some(JournalEntry {
+            message,
+            time,
+            cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn parse_journal_entries() {
+        let line = r#"{"__CURSOR":"s=1;i=2","__REALTIME_TIMESTAMP":"1371913394123000","PRIORITY":"3","MESSAGE":"Connection refused"}"#;
+        assert_eq!(
+            JournalEntry::parse(line),
+            Some(JournalEntry {
+                message: "Connection refused".to_string(),
+                time: Some(datetime!(2013-06-22 15:03:14.123 +00:00)),
+                cursor: "s=1;i=2".to_string(),
+            })
+        );
+
+        let line = r#"{"__CURSOR":"s=1;i=3","MESSAGE":[104,105]}"#;
+        assert_eq!(
+            JournalEntry::parse(line),
+            Some(JournalEntry {
+                message: "hi".to_string(),
+                time: None,
+                cursor: "s=1;i=3".to_string(),
+            })
+        );
+
+        assert_eq!(JournalEntry::parse(r#"{"MESSAGE":"no cursor"}"#), None);
+        assert_eq!(JournalEntry::parse("not json"), None);
+    }
+}
@@ -0,0 +1,357 @@
+use crate::forwarder::ForwardRule;
+use crate::forwarder::LogForwarderBuilder;
+use crate::forwarder::LogForwarderConfig;
+use camino::Utf8Path;
+use serde_json::Value;
+use std::io::Write;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_file_system_ext::FsWatchEvent;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_test_utils::fs::TempTedgeDir;
+use tokio::task::JoinHandle;
+
+type MqttMessageBox = TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>>;
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay for the log files to be scanned by the forwarder
+///
+/// The tests are run with a paused clock, so this delay elapses as soon as the forwarder is idle.
+const SCAN_DELAY: Duration = Duration::from_millis(1500);
+
+struct TestForwarder {
+    mqtt: MqttMessageBox,
+    fs: SimpleMessageBox<NoMessage, FsWatchEvent>,
+    shutdown: DynSender<RuntimeRequest>,
+    actor: JoinHandle<Result<(), RuntimeError>>,
+}
+
+impl TestForwarder {
+    async fn spawn(temp_dir: &Utf8Path) -> Self {
+        let plugin_config_path = temp_dir.join("tedge-log-plugin.toml");
+        let config = LogForwarderConfig {
+            mqtt_schema: MqttSchema::default(),
+            device_topic_id: EntityTopicId::default_main_device(),
+            plugin_config_path: plugin_config_path.clone(),
+            checkpoint_path: temp_dir.join("log-forwarder.json"),
+        };
+        let rules = ForwardRule::load_rules(plugin_config_path.as_std_path()).await;
+        let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+            SimpleMessageBoxBuilder::new("MQTT", 16);
+        let mut fs_builder: SimpleMessageBoxBuilder<NoMessage, FsWatchEvent> =
+            SimpleMessageBoxBuilder::new("FS", 16);
+        let builder = LogForwarderBuilder::new(config, rules, &mut mqtt_builder, &mut fs_builder);
+        let shutdown = builder.get_signal_sender();
+        let actor = builder.build();
+        let actor = tokio::spawn(async move { actor.run().await });
+
+        // Let the forwarder skip the lines already in the log files
+        tokio::time::sleep(SCAN_DELAY).await;
+
+        TestForwarder {
+            mqtt: mqtt_builder.build().with_timeout(TEST_TIMEOUT),
+            fs: fs_builder.build(),
+            shutdown,
+            actor,
+        }
+    }
+
+    async fn stop(mut self) {
+        self.shutdown.send(RuntimeRequest::Shutdown).await.unwrap();
+        self.actor.await.unwrap().unwrap();
+    }
+
+    async fn expect(&mut self, topic: &str, text: Option<&str>) {
+        let message = self.mqtt.recv().await.expect("a message");
+        assert_eq!(message.topic.name, topic);
+        match text {
+            None => assert!(message.payload_bytes().is_empty()),
+            Some(text) => {
+                let payload: Value = serde_json::from_slice(message.payload_bytes()).unwrap();
+                assert_eq!(payload["text"], text);
+            }
+        }
+    }
+}
+
+fn prepare(config: &str) -> TempTedgeDir {
+    let temp_dir = TempTedgeDir::new();
+    let log_path = temp_dir.utf8_path().join("app.log");
+    let config = config.replace("${LOG_PATH}", log_path.as_str());
+    temp_dir
+        .file("tedge-log-plugin.toml")
+        .with_raw_content(&config);
+    temp_dir
+        .file("app.log")
+        .with_raw_content("INFO: a line logged before the forwarder started\n");
+    temp_dir
+}
+
+fn append_lines(temp_dir: &TempTedgeDir, lines: &[&str]) {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(temp_dir.utf8_path().join("app.log"))
+        .unwrap();
+    for line in lines {
+        writeln!(file, "{line}").unwrap();
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn forward_new_log_lines_as_events() {
+    let temp_dir = prepare(
+        r#"
+[[files]]
+type = "app"
+path = "${LOG_PATH}"
+
+[[forward]]
+type = "app"
+exclude = "DEBUG"
+"#,
+    );
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+
+    append_lines(
+        &temp_dir,
+        &["INFO: starting", "DEBUG: details", "ERROR: failure"],
+    );
+    forwarder
+        .expect("te/device/main///e/app", Some("INFO: starting"))
+        .await;
+    forwarder
+        .expect("te/device/main///e/app", Some("ERROR: failure"))
+        .await;
+
+    forwarder.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn resume_forwarding_from_checkpoint() {
+    let temp_dir = prepare(
+        r#"
+[[files]]
+type = "app"
+path = "${LOG_PATH}"
+
+[[forward]]
+type = "app"
+"#,
+    );
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+    append_lines(&temp_dir, &["line 1"]);
+    forwarder
+        .expect("te/device/main///e/app", Some("line 1"))
+        .await;
+    forwarder.stop().await;
+
+    // Lines appended while the forwarder is stopped are forwarded on restart, without duplicates
+    append_lines(&temp_dir, &["line 2", "line 3"]);
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+    forwarder
+        .expect("te/device/main///e/app", Some("line 2"))
+        .await;
+    forwarder
+        .expect("te/device/main///e/app", Some("line 3"))
+        .await;
+    forwarder.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn resume_forwarding_from_checkpoint_when_the_event_type_is_renamed() {
+    let temp_dir = prepare(
+        r#"
+[[files]]
+type = "app"
+path = "${LOG_PATH}"
+
+[[forward]]
+type = "app"
+"#,
+    );
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+    append_lines(&temp_dir, &["line 1"]);
+    forwarder
+        .expect("te/device/main///e/app", Some("line 1"))
+        .await;
+    forwarder.stop().await;
+
+    // The checkpoint is attached to the rule and its source, not to the event type
+    let config = std::fs::read_to_string(temp_dir.utf8_path().join("tedge-log-plugin.toml"))
+        .unwrap()
+        + "event_type = \"app_logs\"\n";
+    temp_dir
+        .file("tedge-log-plugin.toml")
+        .with_raw_content(&config);
+    append_lines(&temp_dir, &["line 2"]);
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+    forwarder
+        .expect("te/device/main///e/app_logs", Some("line 2"))
+        .await;
+    forwarder.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn raise_and_clear_alarms_on_patterns() {
+    let temp_dir = prepare(
+        r#"
+[[files]]
+type = "app"
+path = "${LOG_PATH}"
+
+[[forward]]
+type = "app"
+include = "^$"
+
+[[forward.alarms]]
+type = "disk_full"
+raise = "No space left"
+clear = "Disk space recovered"
+severity = "critical"
+"#,
+    );
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+
+    append_lines(
+        &temp_dir,
+        &[
+            "ERROR: No space left on device",
+            "ERROR: No space left on device",
+            "INFO: Disk space recovered",
+        ],
+    );
+    let message = forwarder.mqtt.recv().await.expect("an alarm");
+    assert_eq!(message.topic.name, "te/device/main///a/disk_full");
+    assert!(message.retain);
+    let payload: Value = serde_json::from_slice(message.payload_bytes()).unwrap();
+    assert_eq!(payload["severity"], "critical");
+    assert_eq!(payload["text"], "ERROR: No space left on device");
+
+    // The alarm is raised only once, and then cleared
+    forwarder.expect("te/device/main///a/disk_full", None).await;
+
+    forwarder.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn limit_the_rate_of_forwarded_events() {
+    let temp_dir = prepare(
+        r#"
+[[files]]
+type = "app"
+path = "${LOG_PATH}"
+
+[[forward]]
+type = "app"
+max_rate = 2
+"#,
+    );
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+
+    append_lines(&temp_dir, &["line 1", "line 2", "line 3", "line 4"]);
+    forwarder
+        .expect("te/device/main///e/app", Some("line 1"))
+        .await;
+    forwarder
+        .expect("te/device/main///e/app", Some("line 2"))
+        .await;
+    assert!(tokio::time::timeout(SCAN_DELAY, forwarder.mqtt.recv())
+        .await
+        .is_err());
+
+    forwarder.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn forward_the_end_of_rotated_files() {
+    let temp_dir = prepare(
+        r#"
+[[files]]
+type = "app"
+path = "${LOG_PATH}"
+
+[[forward]]
+type = "app"
+"#,
+    );
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+
+    // Lines are appended to the log file just before and after its rotation
+    append_lines(&temp_dir, &["line 1"]);
+    let log_path = temp_dir.utf8_path().join("app.log");
+    let mut rotated_file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&log_path)
+        .unwrap();
+    std::fs::rename(&log_path, temp_dir.utf8_path().join("app.log.1")).unwrap();
+    writeln!(rotated_file, "line 2").unwrap();
+    temp_dir.file("app.log").with_raw_content("line 3\n");
+
+    for line in ["line 1", "line 2", "line 3"] {
+        forwarder.expect("te/device/main///e/app", Some(line)).await;
+    }
+
+    forwarder.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn reload_the_rules_on_config_update() {
+    let temp_dir = prepare(
+        r#"
+[[files]]
+type = "app"
+path = "${LOG_PATH}"
+
+[[forward]]
+type = "app"
+"#,
+    );
+    let mut forwarder = TestForwarder::spawn(temp_dir.utf8_path()).await;
+    append_lines(&temp_dir, &["INFO: line 1"]);
+    forwarder
+        .expect("te/device/main///e/app", Some("INFO: line 1"))
+        .await;
+
+    let config_path = temp_dir.utf8_path().join("tedge-log-plugin.toml");
+    let config = r#"
+[[files]]
+type = "app"
+path = "${LOG_PATH}"
+
+[[forward]]
+type = "app"
+event_type = "app_errors"
+include = "ERROR"
+"#
+    .replace("${LOG_PATH}", temp_dir.utf8_path().join("app.log").as_str());
+    std::fs::write(&config_path, config).unwrap();
+    forwarder
+        .fs
+        .send(FsWatchEvent::Modified(config_path.into()))
+        .await
+        .unwrap();
+    tokio::time::sleep(SCAN_DELAY).await;
+
+    // The new rule applies to the lines appended after the reload
+    append_lines(&temp_dir, &["INFO: line 2", "ERROR: line 3"]);
+    forwarder
+        .expect("te/device/main///e/app_errors", Some("ERROR: line 3"))
+        .await;
+
+    forwarder.stop().await;
+}
@@ -1,6 +1,8 @@
 mod actor;
 mod config;
 mod error;
+mod forwarder;
+mod packaging;
 mod plugin;
 mod plugin_manager;
 
@@ -10,6 +12,7 @@ mod tests;
 use crate::plugin_manager::ExternalPlugins;
 pub use actor::*;
 pub use config::*;
+pub use forwarder::*;
 use std::path::PathBuf;
 use std::vec;
 use tedge_actors::Builder;
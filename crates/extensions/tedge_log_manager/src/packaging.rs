@@ -0,0 +1,249 @@
+//! Post-processing of the log files fetched from the plugins, before their upload
+//!
+//! - the secrets are masked using the redaction rules of the plugin configuration,
+//! - the files fetched for a globbed log type are bundled into a tar archive,
+//! - the uploaded file is compressed as requested.
+use crate::config::Redaction;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use flate2::write::GzEncoder;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Write;
+use tedge_api::commands::LogCompression;
+
+/// Mask all the matches of the redaction rules in a log file, rewriting the file in place
+///
+/// The line endings are preserved, as well as the lines with no matches,
+/// even if these are not valid UTF-8.
+pub fn redact_file(path: &Utf8Path, redactions: &[Redaction]) -> std::io::Result<()> {
+    if redactions.is_empty() {
+        return Ok(());
+    }
+
+    let redacted_path = with_extension_suffix(path, "redacted");
+    {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut writer = BufWriter::new(File::create(&redacted_path)?);
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            let content = line
+                .strip_suffix(b"\r\n")
+                .or_else(|| line.strip_suffix(b"\n"))
+                .unwrap_or(&line);
+            let (content, line_ending) = line.split_at(content.len());
+            match redact_line(&String::from_utf8_lossy(content), redactions) {
+                Cow::Owned(redacted) => writer.write_all(redacted.as_bytes())?,
+                Cow::Borrowed(_) => writer.write_all(content)?,
+            }
+            writer.write_all(line_ending)?;
+            line.clear();
+        }
+        writer.into_inner()?.sync_all()?;
+    }
+    std::fs::rename(redacted_path, path)
+}
+
+/// Mask the matches of the redaction rules in a line, borrowing the line if there is none
+fn redact_line<'a>(line: &'a str, redactions: &[Redaction]) -> Cow<'a, str> {
+    let mut redacted = Cow::Borrowed(line);
+    for redaction in redactions {
+        if redaction.pattern.is_match(&redacted) {
+            redacted = Cow::Owned(
+                redaction
+                    .pattern
+                    .replace_all(&redacted, redaction.replacement.as_str())
+                    .into_owned(),
+            );
+        }
+    }
+    redacted
+}
+
+/// Bundle a set of files into a tar archive, each file being stored under the given name
+pub fn bundle_files(
+    files: &[(String, Utf8PathBuf)],
+    archive_path: &Utf8Path,
+) -> std::io::Result<()> {
+    let mut archive = tar::Builder::new(BufWriter::new(File::create(archive_path)?));
+    for (name, path) in files {
+        archive.append_path_with_name(path, name)?;
+    }
+    archive.into_inner()?.into_inner()?.sync_all()
+}
+
+/// Compress a file, returning the path of the compressed file which replaces the original one
+pub fn compress_file(path: &Utf8Path, compression: LogCompression) -> std::io::Result<Utf8PathBuf> {
+    let compressed_path = with_extension_suffix(path, compression.extension());
+    {
+        let mut input = BufReader::new(File::open(path)?);
+        let output = BufWriter::new(File::create(&compressed_path)?);
+        let output = match compression {
+            LogCompression::Gzip => {
+                let mut encoder = GzEncoder::new(output, flate2::Compression::default());
+                std::io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?
+            }
+            LogCompression::Zstd => {
+                let mut encoder = zstd::Encoder::new(output, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+                std::io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?
+            }
+        };
+        output.into_inner()?.sync_all()?;
+    }
+    std::fs::remove_file(path)?;
+    Ok(compressed_path)
+}
+
+/// Append an extension to a path, e.g. `type_one.log` -> `type_one.log.gz`
+fn with_extension_suffix(path: &Utf8Path, extension: &str) -> Utf8PathBuf {
+    Utf8PathBuf::from(format!("{path}.{extension}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use std::io::Read;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn secrets_are_masked_line_by_line() {
+        let redactions = vec![
+            Redaction {
+                pattern: Regex::new(r"password=\S+").unwrap(),
+                replacement: "password=********".to_string(),
+            },
+            Redaction {
+                pattern: Regex::new(r"(Bearer) [\w.-]+").unwrap(),
+                replacement: "$1 <redacted>".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            redact_line("login user=admin password=s3cr3t retry=3", &redactions),
+            "login user=admin password=******** retry=3"
+        );
+        assert_eq!(
+            redact_line("Authorization: Bearer eyJhbGciOi.eyJzdWIi", &redactions),
+            "Authorization: Bearer <redacted>"
+        );
+        assert_eq!(
+            redact_line("nothing to hide", &redactions),
+            "nothing to hide"
+        );
+    }
+
+    #[test]
+    fn redacted_file_is_rewritten_in_place() {
+        let ttd = TempTedgeDir::new();
+        ttd.file("app.log")
+            .with_raw_content("user=admin password=s3cr3t\nstarted\n");
+        let path = ttd.utf8_path().join("app.log");
+
+        let redactions = vec![Redaction {
+            pattern: Regex::new(r"s3cr3t").unwrap(),
+            replacement: "***".to_string(),
+        }];
+        redact_file(&path, &redactions).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "user=admin password=***\nstarted\n"
+        );
+        assert!(!ttd.utf8_path().join("app.log.redacted").exists());
+    }
+
+    #[test]
+    fn redaction_preserves_line_endings_and_invalid_utf8() {
+        let ttd = TempTedgeDir::new();
+        let path = ttd.utf8_path().join("app.log");
+        let content = b"password=s3cr3t\r\nbinary \xff\xfe data\nno final newline s3cr3t";
+        std::fs::write(&path, content).unwrap();
+
+        let redactions = vec![Redaction {
+            pattern: Regex::new(r"s3cr3t$").unwrap(),
+            replacement: "***".to_string(),
+        }];
+        redact_file(&path, &redactions).unwrap();
+
+        let expected = b"password=***\r\nbinary \xff\xfe data\nno final newline ***";
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+    }
+
+    #[test]
+    fn compressed_files_can_be_decompressed() {
+        let ttd = TempTedgeDir::new();
+        let content = "INFO: Application initialized\n".repeat(100);
+
+        for compression in [LogCompression::Gzip, LogCompression::Zstd] {
+            ttd.file("app.log").with_raw_content(&content);
+            let path = ttd.utf8_path().join("app.log");
+
+            let compressed_path = compress_file(&path, compression).unwrap();
+            assert_eq!(
+                compressed_path,
+                ttd.utf8_path()
+                    .join(format!("app.log.{}", compression.extension()))
+            );
+            assert!(!path.exists(), "The original file is replaced");
+
+            let file = File::open(&compressed_path).unwrap();
+            let mut decompressed = String::new();
+            match compression {
+                LogCompression::Gzip => flate2::read::GzDecoder::new(file)
+                    .read_to_string(&mut decompressed)
+                    .unwrap(),
+                LogCompression::Zstd => zstd::Decoder::new(file)
+                    .unwrap()
+                    .read_to_string(&mut decompressed)
+                    .unwrap(),
+            };
+            assert_eq!(decompressed, content);
+        }
+    }
+
+    #[test]
+    fn files_are_bundled_under_their_log_type() {
+        let ttd = TempTedgeDir::new();
+        ttd.file("tmp_1.log").with_raw_content("first\n");
+        ttd.file("tmp_2.log").with_raw_content("second\n");
+        let files = vec![
+            (
+                "type_one.log".to_string(),
+                ttd.utf8_path().join("tmp_1.log"),
+            ),
+            (
+                "type_two.log".to_string(),
+                ttd.utf8_path().join("tmp_2.log"),
+            ),
+        ];
+        let archive_path = ttd.utf8_path().join("bundle.tar");
+
+        bundle_files(&files, &archive_path).unwrap();
+
+        let mut archive = tar::Archive::new(File::open(&archive_path).unwrap());
+        let entries: Vec<(String, String)> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let name = entry.path().unwrap().to_string_lossy().to_string();
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                (name, content)
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("type_one.log".to_string(), "first\n".to_string()),
+                ("type_two.log".to_string(), "second\n".to_string()),
+            ]
+        );
+    }
+}
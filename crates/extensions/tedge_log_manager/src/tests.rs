@@ -124,7 +124,9 @@ async fn new_log_manager_builder(
         sudo_enabled: false,
     };
 
-    let plugin_config = PluginConfig::from_file(plugin_config_path.path().as_ref()).await;
+    let plugin_config = PluginConfig::from_file(plugin_config_path.path().as_ref())
+        .await
+        .unwrap_or_default();
     let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
         SimpleMessageBoxBuilder::new("MQTT", 5);
     let mut fs_watcher_builder: SimpleMessageBoxBuilder<NoMessage, FsWatchEvent> =
@@ -600,6 +602,172 @@ async fn filter_logs_by_search_text_and_line_count() -> Result<(), anyhow::Error
     Ok(())
 }
 
+#[tokio::test]
+async fn upload_redacted_and_compressed_bundle_of_log_types() -> Result<(), anyhow::Error> {
+    let tempdir = prepare()?;
+    tempdir.file("tedge-log-plugin.toml").with_raw_content(
+        r#"
+[[redactions]]
+pattern = "Database"
+replacement = "<db>"
+"#,
+    );
+    let (mut mqtt, _fs, mut uploader) = spawn_log_manager_actor(tempdir.utf8_path()).await;
+
+    let logfile_topic = Topic::new_unchecked("te/device/main///cmd/log_upload/4321");
+
+    // Let's ignore the init message sent on start
+    mqtt.skip(1).await;
+
+    // When a log request is received for all the log types matching a glob pattern
+    let log_request = r#"
+        {
+            "status": "init",
+            "tedgeUrl": "http://127.0.0.1:3000/te/v1/files/main/log_upload/all-4321",
+            "type": "type_*",
+            "dateFrom": "1970-01-01T00:00:00+00:00",
+            "dateTo": "1970-01-01T00:00:30+00:00",
+            "searchText": "Database",
+            "lines": 1000,
+            "compression": "gzip",
+            "bundle": true
+        }"#;
+    mqtt.send(MqttMessage::new(&logfile_topic, log_request).with_retain())
+        .await?;
+
+    // The log manager notifies that the request has been received and is processed
+    let executing_message = mqtt.recv().await;
+    assert_eq!(
+        executing_message,
+        Some(MqttMessage::new(
+                &logfile_topic,
+                r#"{"status":"executing","tedgeUrl":"http://127.0.0.1:3000/te/v1/files/main/log_upload/all-4321","type":"type_*","dateFrom":"1970-01-01T00:00:00Z","dateTo":"1970-01-01T00:00:30Z","searchText":"Database","lines":1000,"compression":"gzip","bundle":true}"#
+            ).with_retain())
+        );
+    mqtt.send(executing_message.unwrap()).await?;
+
+    // The uploaded file is a compressed tar archive, with one redacted entry per log type
+    let (topic, upload_request) = uploader.recv().await.unwrap();
+    assert!(upload_request.file_path.as_str().ends_with(".tar.gz"));
+
+    let archive = flate2::read::GzDecoder::new(std::fs::File::open(&upload_request.file_path)?);
+    let mut archive = tar::Archive::new(archive);
+    let mut entries = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut content)?;
+        entries.push((name, content));
+    }
+    assert_eq!(
+        entries,
+        vec![
+            (
+                "type_one.log".to_string(),
+                "ERROR: <db> connection failed\nINFO: <db> connected successfully\n".to_string()
+            ),
+            ("type_two.log".to_string(), "".to_string()),
+        ]
+    );
+
+    // Simulate upload is completed.
+    let upload_response = UploadResponse::new(&upload_request.url, upload_request.file_path);
+    uploader.send((topic, Ok(upload_response))).await?;
+
+    assert_eq!(
+            mqtt.recv().await,
+            Some(MqttMessage::new(
+                &logfile_topic,
+                r#"{"status":"successful","tedgeUrl":"http://127.0.0.1:3000/te/v1/files/main/log_upload/all-4321","type":"type_*","dateFrom":"1970-01-01T00:00:00Z","dateTo":"1970-01-01T00:00:30Z","searchText":"Database","lines":1000,"compression":"gzip","bundle":true}"#
+            ).with_retain())
+        );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn log_uploads_are_refused_when_the_redaction_rules_are_invalid() -> Result<(), anyhow::Error>
+{
+    let tempdir = prepare()?;
+    tempdir.file("tedge-log-plugin.toml").with_raw_content(
+        r#"
+[[redactions]]
+pattern = "(password=\\S+"
+"#,
+    );
+    let (mut mqtt, _fs, mut uploader) = spawn_log_manager_actor(tempdir.utf8_path()).await;
+
+    let logfile_topic = Topic::new_unchecked("te/device/main///cmd/log_upload/5678");
+
+    // Let's ignore the init message sent on start
+    mqtt.skip(1).await;
+
+    let log_request = r#"
+        {
+            "status": "init",
+            "tedgeUrl": "http://127.0.0.1:3000/te/v1/files/main/log_upload/type_one-5678",
+            "type": "type_one",
+            "dateFrom": "1970-01-01T00:00:00+00:00",
+            "dateTo": "1970-01-01T00:00:30+00:00",
+            "lines": 1000
+        }"#;
+    mqtt.send(MqttMessage::new(&logfile_topic, log_request).with_retain())
+        .await?;
+
+    let executing_message = mqtt.recv().await.unwrap();
+    mqtt.send(executing_message).await?;
+
+    // The log file is not uploaded, as it cannot be redacted
+    let failed_message = mqtt.recv().await.unwrap();
+    let payload: serde_json::Value = serde_json::from_slice(failed_message.payload_bytes())?;
+    assert_eq!(payload["status"], "failed");
+    assert!(payload["reason"]
+        .as_str()
+        .unwrap()
+        .starts_with("Failed to initiate log file upload: Invalid log plugin config"));
+    assert!(uploader.recv().await.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_bundle_with_no_matching_log_type() -> Result<(), anyhow::Error> {
+    let tempdir = prepare()?;
+    let (mut mqtt, _fs, _uploader) = spawn_log_manager_actor(tempdir.utf8_path()).await;
+
+    let logfile_topic = Topic::new_unchecked("te/device/main///cmd/log_upload/8765");
+
+    // Let's ignore the init message sent on start
+    mqtt.skip(1).await;
+
+    let log_request = r#"
+        {
+            "status": "init",
+            "tedgeUrl": "http://127.0.0.1:3000/te/v1/files/main/log_upload/none-8765",
+            "type": "unknown_*",
+            "dateFrom": "1970-01-01T00:00:00+00:00",
+            "dateTo": "1970-01-01T00:00:30+00:00",
+            "lines": 1000,
+            "bundle": true
+        }"#;
+    mqtt.send(MqttMessage::new(&logfile_topic, log_request).with_retain())
+        .await?;
+
+    let executing_message = mqtt.recv().await.unwrap();
+    mqtt.send(executing_message).await?;
+
+    assert_eq!(
+        mqtt.recv().await,
+        Some(MqttMessage::new(
+            &logfile_topic,
+            r#"{"status":"failed","reason":"Failed to initiate log file upload: No log type matches 'unknown_*'","tedgeUrl":"http://127.0.0.1:3000/te/v1/files/main/log_upload/none-8765","type":"unknown_*","dateFrom":"1970-01-01T00:00:00Z","dateTo":"1970-01-01T00:00:30Z","lines":1000,"bundle":true}"#
+        ).with_retain())
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn request_logtype_that_does_not_exist() -> Result<(), anyhow::Error> {
     let tempdir = prepare()?;
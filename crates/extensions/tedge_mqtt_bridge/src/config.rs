@@ -3,6 +3,7 @@ use crate::config_toml::ExpandError;
 use crate::config_toml::ExpandedBridgeRule;
 use crate::config_toml::MapperConfigLookup;
 use crate::config_toml::NonExpansionReason;
+use crate::config_toml::RetainPolicy;
 use crate::topics::matches_ignore_dollar_prefix;
 use crate::topics::TopicConverter;
 use crate::AuthMethod;
@@ -17,8 +18,11 @@ use certificate::parse_root_certificate::create_tls_config_without_client_cert;
 use rumqttc::valid_filter;
 use rumqttc::valid_topic;
 use rumqttc::MqttOptions;
+use rumqttc::Publish;
+use rumqttc::QoS;
 use rumqttc::Transport;
 use std::borrow::Cow;
+use std::fmt;
 use std::path::Path;
 use tedge_config::tedge_toml::CloudConfig;
 use tedge_config::tedge_toml::ProfileName;
@@ -56,7 +60,7 @@ pub struct BridgeConfig {
     bidirectional_topics: Vec<(Cow<'static, str>, Cow<'static, str>)>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 /// A rule for forwarding MQTT messages from one broker to another
 ///
 /// A rule has three parts, a filter, a prefix to add and a prefix to remove. For instance, the rule
@@ -84,6 +88,59 @@ pub struct BridgeRule {
     topic_filter: Cow<'static, str>,
     prefix_to_remove: Cow<'static, str>,
     prefix_to_add: Cow<'static, str>,
+    options: RuleOptions,
+}
+
+// The forwarding options are omitted, as irrelevant to tell which topics are mapped by a rule
+impl fmt::Debug for BridgeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BridgeRule")
+            .field("topic_filter", &self.topic_filter)
+            .field("prefix_to_remove", &self.prefix_to_remove)
+            .field("prefix_to_add", &self.prefix_to_add)
+            .finish()
+    }
+}
+
+/// How the messages matching a [BridgeRule] are forwarded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleOptions {
+    /// The QoS used to subscribe to the source topics and to publish on the target topics
+    pub qos: QoS,
+
+    /// How the retain flag of the source messages is propagated to the target
+    pub retain: RetainPolicy,
+
+    /// Messages with larger payloads are dropped, rather than forwarded
+    pub max_payload_size: Option<usize>,
+}
+
+impl Default for RuleOptions {
+    fn default() -> Self {
+        RuleOptions {
+            qos: QoS::AtLeastOnce,
+            retain: RetainPolicy::Keep,
+            max_payload_size: None,
+        }
+    }
+}
+
+impl RuleOptions {
+    /// The QoS and retain flag to be used to forward the given message
+    pub fn qos_and_retain(&self, publish: &Publish) -> (QoS, bool) {
+        let retain = match self.retain {
+            RetainPolicy::Keep => publish.retain,
+            RetainPolicy::Force => true,
+            RetainPolicy::Strip => false,
+        };
+        (self.qos, retain)
+    }
+
+    /// Returns true if the message payload exceeds the rule limit, if any
+    pub fn is_oversized(&self, publish: &Publish) -> bool {
+        self.max_payload_size
+            .is_some_and(|limit| publish.payload.len() > limit)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -131,6 +188,7 @@ impl BridgeRule {
             topic_filter: prefix_to_remove.clone() + base_topic_filter.clone(),
             prefix_to_remove,
             prefix_to_add,
+            options: RuleOptions::default(),
         };
 
         validate_topic(&r.prefix_to_add)?;
@@ -158,6 +216,15 @@ impl BridgeRule {
         })
     }
 
+    /// Sets the QoS, retain policy and payload size limit of the messages forwarded by this rule
+    pub fn with_options(self, options: RuleOptions) -> Self {
+        BridgeRule { options, ..self }
+    }
+
+    pub fn options(&self) -> &RuleOptions {
+        &self.options
+    }
+
     pub fn topic_filter(&self) -> &str {
         &self.topic_filter
     }
@@ -182,11 +249,25 @@ impl BridgeConfig {
         local_prefix: impl Into<Cow<'static, str>>,
         remote_prefix: impl Into<Cow<'static, str>>,
     ) -> Result<(), InvalidBridgeRule> {
-        self.local_to_remote.push(BridgeRule::try_new(
-            topic.into(),
-            local_prefix.into(),
-            remote_prefix.into(),
-        )?);
+        self.forward_from_local_with_options(
+            topic,
+            local_prefix,
+            remote_prefix,
+            RuleOptions::default(),
+        )
+    }
+
+    pub fn forward_from_local_with_options(
+        &mut self,
+        topic: impl Into<Cow<'static, str>>,
+        local_prefix: impl Into<Cow<'static, str>>,
+        remote_prefix: impl Into<Cow<'static, str>>,
+        options: RuleOptions,
+    ) -> Result<(), InvalidBridgeRule> {
+        self.local_to_remote.push(
+            BridgeRule::try_new(topic.into(), local_prefix.into(), remote_prefix.into())?
+                .with_options(options),
+        );
         Ok(())
     }
 
@@ -196,11 +277,25 @@ impl BridgeConfig {
         local_prefix: impl Into<Cow<'static, str>>,
         remote_prefix: impl Into<Cow<'static, str>>,
     ) -> Result<(), InvalidBridgeRule> {
-        self.remote_to_local.push(BridgeRule::try_new(
-            topic.into(),
-            remote_prefix.into(),
-            local_prefix.into(),
-        )?);
+        self.forward_from_remote_with_options(
+            topic,
+            local_prefix,
+            remote_prefix,
+            RuleOptions::default(),
+        )
+    }
+
+    pub fn forward_from_remote_with_options(
+        &mut self,
+        topic: impl Into<Cow<'static, str>>,
+        local_prefix: impl Into<Cow<'static, str>>,
+        remote_prefix: impl Into<Cow<'static, str>>,
+        options: RuleOptions,
+    ) -> Result<(), InvalidBridgeRule> {
+        self.remote_to_local.push(
+            BridgeRule::try_new(topic.into(), remote_prefix.into(), local_prefix.into())?
+                .with_options(options),
+        );
         Ok(())
     }
 
@@ -213,6 +308,21 @@ impl BridgeConfig {
         topic: impl Into<Cow<'static, str>>,
         local_prefix: impl Into<Cow<'static, str>>,
         remote_prefix: impl Into<Cow<'static, str>>,
+    ) -> Result<(), InvalidBridgeRule> {
+        self.forward_bidirectionally_with_options(
+            topic,
+            local_prefix,
+            remote_prefix,
+            RuleOptions::default(),
+        )
+    }
+
+    pub fn forward_bidirectionally_with_options(
+        &mut self,
+        topic: impl Into<Cow<'static, str>>,
+        local_prefix: impl Into<Cow<'static, str>>,
+        remote_prefix: impl Into<Cow<'static, str>>,
+        options: RuleOptions,
     ) -> Result<(), InvalidBridgeRule> {
         let topic = topic.into();
         let local_prefix = local_prefix.into();
@@ -221,8 +331,13 @@ impl BridgeConfig {
             local_prefix.clone() + topic.clone(),
             remote_prefix.clone() + topic.clone(),
         ));
-        self.forward_from_local(topic.clone(), local_prefix.clone(), remote_prefix.clone())?;
-        self.forward_from_remote(topic, local_prefix, remote_prefix)?;
+        self.forward_from_local_with_options(
+            topic.clone(),
+            local_prefix.clone(),
+            remote_prefix.clone(),
+            options,
+        )?;
+        self.forward_from_remote_with_options(topic, local_prefix, remote_prefix, options)?;
         Ok(())
     }
 
@@ -236,6 +351,20 @@ impl BridgeConfig {
         self.remote_to_local.iter().map(|rule| &*rule.topic_filter)
     }
 
+    /// The local topic filters along the QoS to be used to subscribe to them
+    pub fn local_subscriptions_with_qos(&self) -> impl Iterator<Item = (&str, QoS)> {
+        self.local_to_remote
+            .iter()
+            .map(|rule| (rule.topic_filter.as_ref(), rule.options.qos))
+    }
+
+    /// The remote topic filters along the QoS to be used to subscribe to them
+    pub fn remote_subscriptions_with_qos(&self) -> impl Iterator<Item = (&str, QoS)> {
+        self.remote_to_local
+            .iter()
+            .map(|rule| (rule.topic_filter.as_ref(), rule.options.qos))
+    }
+
     pub(super) fn converters_and_bidirectional_topic_filters(
         self,
     ) -> [(TopicConverter, Vec<Cow<'static, str>>); 2] {
@@ -260,16 +389,27 @@ impl BridgeConfig {
         for rule in rules {
             match rule.direction {
                 Direction::Outbound => {
-                    self.forward_from_local(rule.topic, rule.local_prefix, rule.remote_prefix)?;
+                    self.forward_from_local_with_options(
+                        rule.topic,
+                        rule.local_prefix,
+                        rule.remote_prefix,
+                        rule.options,
+                    )?;
                 }
                 Direction::Inbound => {
-                    self.forward_from_remote(rule.topic, rule.local_prefix, rule.remote_prefix)?;
+                    self.forward_from_remote_with_options(
+                        rule.topic,
+                        rule.local_prefix,
+                        rule.remote_prefix,
+                        rule.options,
+                    )?;
                 }
                 Direction::Bidirectional => {
-                    self.forward_bidirectionally(
+                    self.forward_bidirectionally_with_options(
                         rule.topic,
                         rule.local_prefix,
                         rule.remote_prefix,
+                        rule.options,
                     )?;
                 }
             }
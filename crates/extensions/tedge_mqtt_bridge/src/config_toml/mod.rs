@@ -34,6 +34,8 @@ use crate::config_toml::parsing::template::expand_mapper_key;
 use crate::config_toml::parsing::template::expand_mapper_key_as_array;
 use crate::config_toml::parsing::template::parse_for_reference;
 use crate::config_toml::parsing::template::ForReference;
+use crate::RuleOptions;
+use rumqttc::QoS;
 
 #[cfg(test)]
 mod test_helpers;
@@ -57,6 +59,7 @@ pub struct ExpandedBridgeRule {
     pub remote_prefix: String,
     pub direction: Direction,
     pub topic: String,
+    pub options: RuleOptions,
 }
 
 #[derive(Debug)]
@@ -250,10 +253,21 @@ impl PersistedBridgeConfig {
                 String::new()
             });
 
+            let options = expand_rule_options(
+                rule.qos.as_ref(),
+                rule.retain,
+                rule.max_payload_size.as_ref(),
+            )
+            .unwrap_or_else(|mut e| {
+                errors.append(&mut e);
+                RuleOptions::default()
+            });
+
             let expanded = ExpandedBridgeRule {
                 local_prefix: final_local_prefix,
                 remote_prefix: final_remote_prefix,
                 direction: rule.direction,
+                options,
                 topic: expand_spanned(
                     &rule.topic,
                     static_cfg(),
@@ -338,6 +352,16 @@ impl PersistedBridgeConfig {
                 <_>::default()
             });
 
+            let options = expand_rule_options(
+                template.qos.as_ref(),
+                template.retain,
+                template.max_payload_size.as_ref(),
+            )
+            .unwrap_or_else(|mut e| {
+                errors.append(&mut e);
+                RuleOptions::default()
+            });
+
             if iterable.0.is_empty() {
                 let template_config = TemplateConfig {
                     r#for: "",
@@ -373,6 +397,7 @@ impl PersistedBridgeConfig {
                     local_prefix: final_local_prefix.clone(),
                     remote_prefix: final_remote_prefix.clone(),
                     direction: template.direction,
+                    options,
                     topic: expand_spanned(
                         &template.topic,
                         template_config,
@@ -419,6 +444,9 @@ struct StaticBridgeRule {
     direction: Direction,
     topic: Spanned<Template>,
     r#if: Option<Spanned<String>>,
+    qos: Option<Spanned<u8>>,
+    retain: Option<RetainPolicy>,
+    max_payload_size: Option<Spanned<u64>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -430,6 +458,68 @@ struct TemplateBridgeRule {
     remote_prefix: Option<Spanned<Template>>,
     direction: Direction,
     r#if: Option<Spanned<String>>,
+    qos: Option<Spanned<u8>>,
+    retain: Option<RetainPolicy>,
+    max_payload_size: Option<Spanned<u64>>,
+}
+
+/// The largest payload size that can be sent over MQTT
+const MAX_MQTT_PAYLOAD_SIZE: u64 = 268_435_455;
+
+/// Validates the forwarding options given to a `rule` or a `template_rule`
+fn expand_rule_options(
+    qos: Option<&Spanned<u8>>,
+    retain: Option<RetainPolicy>,
+    max_payload_size: Option<&Spanned<u64>>,
+) -> Result<RuleOptions, Vec<ExpandError>> {
+    let mut errors = Vec::new();
+    let default = RuleOptions::default();
+
+    let qos = match qos {
+        None => default.qos,
+        Some(qos) => match qos.get_ref() {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            invalid => {
+                errors.push(ExpandError {
+                    message: format!("Invalid QoS: {invalid}"),
+                    help: Some(
+                        "Use 0 (at most once), 1 (at least once) or 2 (exactly once)".into(),
+                    ),
+                    span: qos.span(),
+                });
+                default.qos
+            }
+        },
+    };
+
+    let max_payload_size = match max_payload_size {
+        None => None,
+        Some(size) => match *size.get_ref() {
+            valid @ 1..=MAX_MQTT_PAYLOAD_SIZE => Some(valid as usize),
+            invalid => {
+                errors.push(ExpandError {
+                    message: format!("Invalid max_payload_size: {invalid}"),
+                    help: Some(format!(
+                        "Use a number of bytes between 1 and {MAX_MQTT_PAYLOAD_SIZE}"
+                    )),
+                    span: size.span(),
+                });
+                None
+            }
+        },
+    };
+
+    if errors.is_empty() {
+        Ok(RuleOptions {
+            qos,
+            retain: retain.unwrap_or(default.retain),
+            max_payload_size,
+        })
+    } else {
+        Err(errors)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
@@ -440,6 +530,19 @@ pub enum Direction {
     Bidirectional,
 }
 
+/// How the retain flag of a message is propagated by a bridge rule
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetainPolicy {
+    /// Forward the message with its original retain flag
+    #[default]
+    Keep,
+    /// Forward the message as retained
+    Force,
+    /// Forward the message as not retained
+    Strip,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) enum Condition {
     AuthMethod(AuthMethod),
@@ -2334,5 +2437,132 @@ direction = "outbound"
                 errs[0].message
             );
         }
+
+        #[test]
+        fn rules_default_to_qos_1_and_keep_the_retain_flag() {
+            let toml = r#"
+[[rule]]
+local_prefix = "c8y/"
+remote_prefix = ""
+topic = "s/us"
+direction = "outbound"
+"#;
+            let (rules, _) = expand(toml).unwrap();
+
+            assert_eq!(rules[0].options, RuleOptions::default());
+            assert_eq!(rules[0].options.qos, QoS::AtLeastOnce);
+            assert_eq!(rules[0].options.retain, RetainPolicy::Keep);
+            assert_eq!(rules[0].options.max_payload_size, None);
+        }
+
+        #[test]
+        fn rules_can_set_qos_retain_and_max_payload_size() {
+            let toml = r#"
+local_prefix = "c8y/"
+remote_prefix = ""
+
+[[rule]]
+topic = "s/us"
+direction = "outbound"
+qos = 0
+retain = "strip"
+max_payload_size = 1024
+
+[[template_rule]]
+for = ['s', 't']
+topic = "${item}/ds"
+direction = "inbound"
+qos = 2
+retain = "force"
+"#;
+            let (rules, _) = expand(toml).unwrap();
+
+            assert_eq!(
+                rules[0].options,
+                RuleOptions {
+                    qos: QoS::AtMostOnce,
+                    retain: RetainPolicy::Strip,
+                    max_payload_size: Some(1024),
+                }
+            );
+            for rule in &rules[1..] {
+                assert_eq!(
+                    rule.options,
+                    RuleOptions {
+                        qos: QoS::ExactlyOnce,
+                        retain: RetainPolicy::Force,
+                        max_payload_size: None,
+                    }
+                );
+            }
+        }
+
+        #[test]
+        fn invalid_qos_has_correct_span_info() {
+            let toml = r#"
+[[rule]]
+local_prefix = "c8y/"
+remote_prefix = ""
+topic = "s/us"
+direction = "outbound"
+qos = 3
+"#;
+            let errors = expand(toml).unwrap_err();
+
+            assert_eq!(
+                errors.len(),
+                1,
+                "Expected only 1 error, actual errors were {errors:?}"
+            );
+            assert_eq!(errors[0].message, "Invalid QoS: 3");
+            assert_eq!(&toml[errors[0].span.clone()], "3");
+        }
+
+        #[test]
+        fn invalid_max_payload_size_has_correct_span_info() {
+            let toml = r#"
+[[template_rule]]
+local_prefix = "c8y/"
+remote_prefix = ""
+for = ['s', 't']
+topic = "${item}/us"
+direction = "outbound"
+max_payload_size = 0
+"#;
+            let errors = expand(toml).unwrap_err();
+
+            assert_eq!(
+                errors.len(),
+                1,
+                "Expected only 1 error, actual errors were {errors:?}"
+            );
+            assert_eq!(&toml[errors[0].span.clone()], "0");
+        }
+
+        #[test]
+        fn unknown_retain_policy_is_rejected() {
+            let toml = r#"
+[[rule]]
+topic = "s/us"
+direction = "outbound"
+retain = "always"
+"#;
+            let err = toml::from_str::<PersistedBridgeConfig>(toml).unwrap_err();
+
+            assert_eq!(&toml[err.span().unwrap()], r#""always""#);
+        }
+
+        fn expand(
+            toml: &str,
+        ) -> Result<(Vec<ExpandedBridgeRule>, Vec<NonExpansionReason>), Vec<ExpandError>> {
+            let config: PersistedBridgeConfig = toml::from_str(toml).unwrap();
+            let tedge_config = tedge_config::TEdgeConfig::load_toml_str("");
+            config.expand(
+                &tedge_config,
+                AuthMethod::Certificate,
+                None,
+                &TableMapperLookup(toml::Table::new()),
+            )
+        }
     }
 }
@@ -9,7 +9,12 @@ use rumqttc::Incoming;
 use rumqttc::Publish;
 use rumqttc::QoS;
 use std::collections::HashMap;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
+
+/// How often the number of spooled messages is refreshed in the health status
+const SPOOL_DEPTH_REPORT_INTERVAL: Duration = Duration::from_secs(10);
 
 /// A tool for monitoring and publishing the health of the two bridge halves
 ///
@@ -19,12 +24,14 @@ pub struct BridgeHealthMonitor {
     topic: String,
     rx_status: mpsc::Receiver<(&'static str, Status)>,
     companion_bridge_half: BridgeMessageSender,
+    spool_depth: Option<watch::Receiver<usize>>,
 }
 
 impl BridgeHealthMonitor {
     pub(crate) fn new<Client: MqttClient + 'static>(
         topic: String,
         bridge_half: &BridgeAsyncClient<Client>,
+        spool_depth: Option<watch::Receiver<usize>>,
     ) -> (mpsc::Sender<(&'static str, Status)>, Self) {
         let (tx, rx_status) = mpsc::channel(10);
         (
@@ -33,6 +40,7 @@ impl BridgeHealthMonitor {
                 topic,
                 rx_status,
                 companion_bridge_half: bridge_half.clone_sender(),
+                spool_depth,
             },
         )
     }
@@ -40,16 +48,32 @@ impl BridgeHealthMonitor {
     pub async fn monitor(mut self) -> ! {
         let mut statuses = HashMap::from([("local", None), ("cloud", None)]);
         let mut last_status = None;
+        let mut last_depth = None;
+        let mut depth_ticks = tokio::time::interval(SPOOL_DEPTH_REPORT_INTERVAL);
         loop {
-            let (name, status) = self.rx_status.recv().await.unwrap();
-            *statuses.entry(name).or_insert(Some(status)) = Some(status);
+            tokio::select! {
+                received = self.rx_status.recv() => {
+                    let (name, status) = received.unwrap();
+                    *statuses.entry(name).or_insert(Some(status)) = Some(status);
+                }
+                // The spool depth changes on each message, hence is only reported periodically
+                _ = depth_ticks.tick(), if self.spool_depth.is_some() => {}
+            }
 
             let status = statuses.values().fold(Some(Status::Up), overall_status);
-            if last_status != status {
+            let depth = self.spool_depth.as_ref().map(|depth| *depth.borrow());
+            let Some(current_status) = status else {
+                continue;
+            };
+            if last_status != status || last_depth != depth {
                 last_status = status;
+                last_depth = depth;
 
-                let mut health_msg =
-                    Publish::new(&self.topic, QoS::AtLeastOnce, status.unwrap().json());
+                let payload = match depth {
+                    None => current_status.json().to_string(),
+                    Some(queued) => current_status.json_with_queue_depth(queued),
+                };
+                let mut health_msg = Publish::new(&self.topic, QoS::AtLeastOnce, payload);
                 health_msg.retain = true;
 
                 // Publish the health message over MQTT, but with no duplicate for the companion
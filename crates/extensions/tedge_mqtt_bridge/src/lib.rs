@@ -12,7 +12,6 @@ use futures::channel::mpsc as futures_mpsc;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 pub use rumqttc;
-use rumqttc::ClientError;
 use rumqttc::ConnectionError;
 use rumqttc::Event;
 use rumqttc::Incoming;
@@ -22,7 +21,6 @@ use rumqttc::Outgoing;
 use rumqttc::PubAck;
 use rumqttc::PubRec;
 use rumqttc::Publish;
-use rumqttc::Request;
 use rumqttc::SubscribeFilter;
 use rumqttc::Transport;
 use std::borrow::Cow;
@@ -51,10 +49,12 @@ pub type MqttConfig = mqtt_channel::Config;
 
 use crate::health::BridgeHealth;
 use crate::health::BridgeHealthMonitor;
+use crate::mqtt_logging::ClientError;
 use crate::mqtt_logging::LoggingAsyncClient;
 pub use mqtt_channel::DebugPayload;
 pub use mqtt_channel::MqttError;
 pub use mqtt_channel::MqttMessage;
+use mqtt_channel::MqttProperties;
 pub use mqtt_channel::QoS;
 pub use mqtt_channel::Topic;
 use tedge_config::tedge_toml::TEdgeConfigReaderMqttBridgeReconnectPolicy;
@@ -65,6 +65,7 @@ use crate::topics::matches_ignore_dollar_prefix;
 use crate::topics::TopicConverter;
 pub use config::*;
 pub use config_toml::AuthMethod;
+pub use config_toml::RetainPolicy;
 pub use persist::load_bridge_rules_from_directory;
 pub use persist::persist_bridge_config_file;
 pub use persist::visit_bridge_config_dir;
@@ -97,6 +98,12 @@ macro_rules! log_event {
 // We have to declare these modules here as they depend on the macro defined above
 mod health;
 mod mqtt_logging;
+mod spool;
+
+use crate::spool::BridgeSpool;
+use crate::spool::SpoolId;
+use crate::spool::SpooledMessage;
+pub use spool::SpoolConfig;
 
 /// Tracks SUBACK progress for a bridge half's connection so outbound publishes
 /// can be gated until the broker has acknowledged all subscriptions.
@@ -216,6 +223,7 @@ impl MqttBridgeActorBuilder {
         local_config.set_clean_session(false);
 
         let reconnect_policy = tedge_config.mqtt.bridge.reconnect_policy.clone();
+        let protocol = tedge_config.mqtt.bridge.protocol.into();
 
         cloud_config.set_manual_acks(true);
         cloud_config.set_max_packet_size(MAX_PACKET_SIZE, MAX_PACKET_SIZE);
@@ -231,32 +239,47 @@ impl MqttBridgeActorBuilder {
         // To prevent that, rumqttc inflight is set far bigger than the number of expected inflight messages.
         let in_flight: u16 = 100;
         cloud_config.set_inflight(in_flight * 5);
-        let (local_client, local_event_loop) =
-            LoggingAsyncClient::new(local_config, in_flight.into(), "local".into(), None);
+        let (local_client, local_event_loop) = LoggingAsyncClient::new(
+            local_config,
+            protocol,
+            in_flight.into(),
+            "local".into(),
+            None,
+        );
         // The cloud connection may sign the TLS handshake using a key on an HSM/TPM, which can be
         // slow, so give it a longer connection timeout than rumqttc's default (see the constant).
         let (cloud_client, cloud_event_loop) = LoggingAsyncClient::new(
             cloud_config,
+            protocol,
             in_flight.into(),
             "cloud".into(),
             Some(CLOUD_CONNECTION_TIMEOUT),
         );
 
         let local_topics: Vec<_> = rules
-            .local_subscriptions()
-            .map(|t| SubscribeFilter::new(t.to_owned(), QoS::AtLeastOnce))
+            .local_subscriptions_with_qos()
+            .map(|(t, qos)| SubscribeFilter::new(t.to_owned(), qos))
             .collect();
         let cloud_topics: Vec<_> = rules
-            .remote_subscriptions()
-            .map(|t| SubscribeFilter::new(t.to_owned(), QoS::AtLeastOnce))
+            .remote_subscriptions_with_qos()
+            .map(|(t, qos)| SubscribeFilter::new(t.to_owned(), qos))
             .collect();
 
+        let spool = open_spool(tedge_config, service_name);
+        let spool_depth = spool.as_ref().map(BridgeSpool::watch_depth);
         let ([cloud_target, local_target], [cloud_gate_controller, local_gate_controller]) =
-            bidirectional_channel(cloud_client.clone(), local_client.clone(), in_flight.into());
+            bidirectional_channel(
+                cloud_client.clone(),
+                local_client.clone(),
+                in_flight.into(),
+                spool,
+            );
         let [(convert_local, bidir_local), (convert_cloud, bidir_cloud)] =
             rules.converters_and_bidirectional_topic_filters();
+        // The response topics of the requests are translated using the rules of the reverse direction
+        let response_convert_local = convert_local.clone();
         let (tx_status, monitor) =
-            BridgeHealthMonitor::new(health_topic.name.clone(), &local_target);
+            BridgeHealthMonitor::new(health_topic.name.clone(), &local_target, spool_depth);
         let cloud_tx = cloud_target.clone_sender();
         let local_tx = local_target.clone_sender();
         let monitor_task = tokio::spawn(
@@ -273,6 +296,7 @@ impl MqttBridgeActorBuilder {
                     local_client,
                     cloud_target,
                     convert_local,
+                    convert_cloud.clone(),
                     bidir_local,
                     tx_status.clone(),
                     "local",
@@ -292,6 +316,7 @@ impl MqttBridgeActorBuilder {
                     cloud_client,
                     local_target,
                     convert_cloud,
+                    response_convert_local,
                     bidir_cloud,
                     tx_status.clone(),
                     "cloud",
@@ -324,10 +349,38 @@ impl MqttBridgeActorBuilder {
     }
 }
 
+/// Opens the disk spool of the cloud-bound messages, if enabled
+///
+/// On error, the bridge falls back to the in-memory queue of messages.
+fn open_spool(tedge_config: &TEdgeConfig, service_name: &str) -> Option<BridgeSpool> {
+    let spool_config = &tedge_config.mqtt.bridge.spool;
+    if !spool_config.enable {
+        return None;
+    }
+    let config = SpoolConfig {
+        dir: tedge_config
+            .data_root()
+            .root()
+            .join("bridge")
+            .join(service_name),
+        max_size: spool_config.max_size.into(),
+        max_age: spool_config.max_age.duration(),
+    };
+    let dir = config.dir.clone();
+    match BridgeSpool::open(config) {
+        Ok(spool) => Some(spool),
+        Err(err) => {
+            tracing::error!(target: "MQTT bridge", "Failed to open the message spool {dir}, cloud-bound messages will not be persisted: {err}");
+            None
+        }
+    }
+}
+
 fn bidirectional_channel<Client: MqttClient + 'static>(
     cloud_client: Client,
     local_client: Client,
     buffer: usize,
+    spool: Option<BridgeSpool>,
 ) -> (
     [BridgeAsyncClient<Client>; 2],
     [SubscriptionGateController; 2],
@@ -341,8 +394,8 @@ fn bidirectional_channel<Client: MqttClient + 'static>(
     let (local_gate, local_gate_controller) = SubscriptionGate::new();
     (
         [
-            BridgeAsyncClient::new(cloud_client, tx_first, rx_second, cloud_gate),
-            BridgeAsyncClient::new(local_client, tx_second, rx_first, local_gate),
+            BridgeAsyncClient::new(cloud_client, tx_first, rx_second, cloud_gate, spool),
+            BridgeAsyncClient::new(local_client, tx_second, rx_first, local_gate, None),
         ],
         [cloud_gate_controller, local_gate_controller],
     )
@@ -361,19 +414,25 @@ fn bidirectional_channel<Client: MqttClient + 'static>(
 #[derive(Clone)]
 struct SubscriptionGate {
     open: watch::Receiver<bool>,
+    closings: Arc<AtomicUsize>,
 }
 
 struct SubscriptionGateController {
     open: watch::Sender<bool>,
+    closings: Arc<AtomicUsize>,
 }
 
 impl SubscriptionGate {
     /// Creates a gate, initially closed, with its controller
     fn new() -> (Self, SubscriptionGateController) {
         let (tx, rx) = watch::channel(false);
+        let closings = Arc::new(AtomicUsize::new(0));
         (
-            SubscriptionGate { open: rx },
-            SubscriptionGateController { open: tx },
+            SubscriptionGate {
+                open: rx,
+                closings: closings.clone(),
+            },
+            SubscriptionGateController { open: tx, closings },
         )
     }
 
@@ -382,6 +441,14 @@ impl SubscriptionGate {
         *self.open.borrow()
     }
 
+    /// Returns how many times the gate has been closed
+    ///
+    /// This tells a publisher that the connection has been re-established since last checked,
+    /// even if it missed the gate being closed and re-opened in between.
+    fn closings(&self) -> usize {
+        self.closings.load(Ordering::Acquire)
+    }
+
     /// Resolves when the gate's open/closed state next changes
     ///
     /// Stays pending once the controller is dropped, so it can sit in a `select!`
@@ -402,6 +469,7 @@ impl SubscriptionGateController {
 
     /// Holds outbound messages until the subscriptions are acknowledged again
     fn close(&self) {
+        self.closings.fetch_add(1, Ordering::Release);
         let _ = self.open.send(false);
     }
 }
@@ -412,7 +480,10 @@ enum BridgeMessage {
     /// This message will have to be acknowledged to its source by the companion half bridge
     BridgePub {
         target_topic: String,
+        qos: QoS,
+        retain: bool,
         publish: Publish,
+        properties: MqttProperties,
     },
 
     /// A message to be acknowledged on the target
@@ -424,6 +495,37 @@ enum BridgeMessage {
     ///
     /// This message has not to be acknowledged, as not received by the bridge.
     Pub { publish: Publish },
+
+    /// A message replayed from the spool
+    ///
+    /// This message will have to be removed from the spool once acknowledged by the target
+    SpoolPub {
+        id: SpoolId,
+        publish: Publish,
+        properties: MqttProperties,
+    },
+
+    /// A spooled message acknowledged by the target, to be removed from the spool
+    SpoolAck { id: SpoolId },
+}
+
+/// Notifies a half bridge of a message published by its companion on the connection it polls
+///
+/// The half bridge pairs this notification with the packet id of the outgoing message,
+/// in order to process the acknowledgement of that message when received.
+enum Published {
+    /// A message received by the companion, to be acknowledged to its source
+    Forwarded { topic: String, publish: Publish },
+
+    /// A message replayed from the spool, to be removed from the spool
+    Spooled {
+        topic: String,
+        publish: Publish,
+        id: SpoolId,
+    },
+
+    /// A message generated by the bridge, with no acknowledgement to forward
+    Internal,
 }
 
 /// Wraps the target of an half bridge with a channel to its half bridge companion.
@@ -436,7 +538,7 @@ struct BridgeAsyncClient<Client: MqttClient> {
     target: Client,
 
     /// Receives messages from the companion half bridge
-    rx: mpsc::Receiver<Option<(String, Publish)>>,
+    rx: mpsc::Receiver<Published>,
 
     /// Sends messages to a background task that forwards the messages to the target and companion
     sender: BridgeMessageSender,
@@ -449,10 +551,13 @@ struct BridgeAsyncClient<Client: MqttClient> {
 
     /// Holds back outbound publishes until the target's subscriptions are acknowledged
     gate: SubscriptionGate,
+
+    /// Persists the messages while the target is not connected, if enabled
+    spool: Option<BridgeSpool>,
 }
 
 impl<Client: MqttClient + 'static> BridgeAsyncClient<Client> {
-    pub async fn recv(&mut self) -> Option<Option<(String, Publish)>> {
+    pub async fn recv(&mut self) -> Option<Published> {
         self.rx.recv().await
     }
 
@@ -462,9 +567,10 @@ impl<Client: MqttClient + 'static> BridgeAsyncClient<Client> {
 
     fn new(
         target: Client,
-        tx: mpsc::Sender<Option<(String, Publish)>>,
-        rx: mpsc::Receiver<Option<(String, Publish)>>,
+        tx: mpsc::Sender<Published>,
+        rx: mpsc::Receiver<Published>,
         gate: SubscriptionGate,
+        spool: Option<BridgeSpool>,
     ) -> Self {
         let (unbounded_tx, unbounded_rx) = mpsc::unbounded_channel();
         let companion_bridge_half = BridgeAsyncClient {
@@ -474,13 +580,32 @@ impl<Client: MqttClient + 'static> BridgeAsyncClient<Client> {
             published: Arc::new(AtomicUsize::new(0)),
             acknowledged: Arc::new(AtomicUsize::new(0)),
             gate,
+            spool,
         };
         companion_bridge_half.spawn_publisher(tx, unbounded_rx);
         companion_bridge_half
     }
 
     fn publish(&mut self, target_topic: String, publish: Publish) {
-        self.sender.publish(target_topic, publish)
+        let (qos, retain) = (publish.qos, publish.retain);
+        let properties = MqttProperties::default();
+        self.sender
+            .publish(target_topic, qos, retain, publish, properties)
+    }
+
+    /// Publishes a message on the target with the QoS and retain flag set by a bridge rule
+    ///
+    /// The MQTT 5 properties are forwarded as given, i.e. with a response topic already translated for the target.
+    fn forward(
+        &mut self,
+        target_topic: String,
+        publish: Publish,
+        properties: MqttProperties,
+        options: &RuleOptions,
+    ) {
+        let (qos, retain) = options.qos_and_retain(&publish);
+        self.sender
+            .publish(target_topic, qos, retain, publish, properties)
     }
 
     fn ack(&mut self, publish: Publish) {
@@ -495,15 +620,25 @@ impl<Client: MqttClient + 'static> BridgeAsyncClient<Client> {
         self.acknowledged.load(Ordering::Relaxed)
     }
 
+    /// Returns the spool where to store the messages to be published, if the target is not connected
+    ///
+    /// While the spool is not empty, the messages have to be spooled even when connected,
+    /// so they are published after the spooled messages.
+    fn spool_if_disconnected(&self) -> Option<BridgeSpool> {
+        let spool = self.spool.as_ref()?;
+        (!self.gate.is_open() || !spool.is_empty()).then(|| spool.clone())
+    }
+
     fn spawn_publisher(
         &self,
-        tx: mpsc::Sender<Option<(String, Publish)>>,
+        tx: mpsc::Sender<Published>,
         mut unbounded_rx: mpsc::UnboundedReceiver<BridgeMessage>,
     ) {
         let target = self.target.clone();
         let published = self.published.clone();
         let acknowledged = self.acknowledged.clone();
         let mut gate = self.gate.clone();
+        let spool = self.spool.clone();
         tokio::spawn(
             async move {
                 // While the gate is closed (the target's subscriptions are not yet
@@ -512,17 +647,46 @@ impl<Client: MqttClient + 'static> BridgeAsyncClient<Client> {
                 // buffer, so they are always forwarded in arrival order. Acks are never
                 // gated.
                 let mut buffer: VecDeque<BridgeMessage> = VecDeque::new();
+                // Gate closings seen at the last replay of the spooled messages
+                let mut replay_closings = 0;
                 loop {
                     if gate.is_open() {
                         // Flush queued publishes in order before handling anything new
                         while let Some(message) = buffer.pop_front() {
                             publish_to_target(&target, &tx, &published, message).await;
                         }
+                        // Then replay the spooled messages, as long as the target is connected
+                        if let Some(spool) = &spool {
+                            // On a new connection, the replay restarts from the oldest unacknowledged message,
+                            // as messages replayed over the former connection might have been lost
+                            if replay_closings != gate.closings() {
+                                replay_closings = gate.closings();
+                                spool.rewind().await;
+                            }
+                            while gate.is_open() {
+                                let Some(SpooledMessage {
+                                    id,
+                                    publish,
+                                    properties,
+                                }) = spool.next_to_replay().await
+                                else {
+                                    break;
+                                };
+                                let message = BridgeMessage::SpoolPub {
+                                    id,
+                                    publish,
+                                    properties,
+                                };
+                                publish_to_target(&target, &tx, &published, message).await;
+                            }
+                        }
                     }
                     tokio::select! {
                         biased;
                         // Re-check the gate at the top of the loop when it opens or closes
                         _ = gate.await_change() => {}
+                        // Resume the replay when messages are spooled or acknowledged
+                        _ = crate::spool::updated(spool.as_ref()) => {}
                         message = unbounded_rx.recv() => match message {
                             // The companion half has disconnected, shut down the publisher
                             None => break,
@@ -531,6 +695,11 @@ impl<Client: MqttClient + 'static> BridgeAsyncClient<Client> {
                                 target.ack(&publish).await.unwrap();
                                 acknowledged.fetch_add(1, Ordering::Relaxed);
                             }
+                            Some(BridgeMessage::SpoolAck { id }) => {
+                                if let Some(spool) = &spool {
+                                    spool.acknowledge(id).await;
+                                }
+                            }
                             // Publishes are queued and flushed at the top of the loop
                             Some(message) => buffer.push_back(message),
                         }
@@ -546,32 +715,68 @@ impl<Client: MqttClient + 'static> BridgeAsyncClient<Client> {
 /// target publish atomic so a buffered message is never split across the two
 async fn publish_to_target<Client: MqttClient>(
     target: &Client,
-    tx: &mpsc::Sender<Option<(String, Publish)>>,
+    tx: &mpsc::Sender<Published>,
     published: &AtomicUsize,
     message: BridgeMessage,
 ) {
     match message {
         BridgeMessage::BridgePub {
             target_topic,
+            qos,
+            retain,
+            publish,
+            properties,
+        } => {
+            let payload = publish.payload.clone();
+            let duplicate = Published::Forwarded {
+                topic: target_topic.clone(),
+                publish,
+            };
+            tx.send(duplicate).await.unwrap();
+            target
+                .publish(target_topic, qos, retain, payload, properties)
+                .await
+                .unwrap();
+            published.fetch_add(1, Ordering::Relaxed);
+        }
+        BridgeMessage::SpoolPub {
+            id,
             publish,
+            properties,
         } => {
-            let duplicate = (target_topic.clone(), publish.clone());
-            tx.send(Some(duplicate)).await.unwrap();
+            let duplicate = Published::Spooled {
+                topic: publish.topic.clone(),
+                publish: publish.clone(),
+                id,
+            };
+            tx.send(duplicate).await.unwrap();
             target
-                .publish(target_topic, publish.qos, publish.retain, publish.payload)
+                .publish(
+                    publish.topic,
+                    publish.qos,
+                    publish.retain,
+                    publish.payload,
+                    properties,
+                )
                 .await
                 .unwrap();
             published.fetch_add(1, Ordering::Relaxed);
         }
         BridgeMessage::Pub { publish } => {
-            tx.send(None).await.unwrap();
+            tx.send(Published::Internal).await.unwrap();
             target
-                .publish(publish.topic, publish.qos, publish.retain, publish.payload)
+                .publish(
+                    publish.topic,
+                    publish.qos,
+                    publish.retain,
+                    publish.payload,
+                    MqttProperties::default(),
+                )
                 .await
                 .unwrap();
         }
         // Acks bypass the gate and are forwarded by the caller, never buffered
-        BridgeMessage::BridgeAck { .. } => {
+        BridgeMessage::BridgeAck { .. } | BridgeMessage::SpoolAck { .. } => {
             unreachable!("acks are forwarded directly and never published through the gate")
         }
     }
@@ -589,11 +794,21 @@ impl BridgeMessageSender {
             .unwrap()
     }
 
-    fn publish(&mut self, target_topic: String, publish: Publish) {
+    fn publish(
+        &mut self,
+        target_topic: String,
+        qos: QoS,
+        retain: bool,
+        publish: Publish,
+        properties: MqttProperties,
+    ) {
         self.unbounded_tx
             .send(BridgeMessage::BridgePub {
                 target_topic,
+                qos,
+                retain,
                 publish,
+                properties,
             })
             .unwrap()
     }
@@ -603,6 +818,12 @@ impl BridgeMessageSender {
             .send(BridgeMessage::BridgeAck { publish })
             .unwrap()
     }
+
+    fn spool_ack(&mut self, id: SpoolId) {
+        self.unbounded_tx
+            .send(BridgeMessage::SpoolAck { id })
+            .unwrap()
+    }
 }
 
 /// Forward messages received from `recv_event_loop` to `target`
@@ -712,6 +933,7 @@ async fn half_bridge(
     recv_client: impl MqttClient + 'static,
     mut target: BridgeAsyncClient<impl MqttClient + 'static>,
     transformer: TopicConverter,
+    reverse_transformer: TopicConverter,
     bidirectional_topic_filters: Vec<Cow<'static, str>>,
     tx_health: mpsc::Sender<(&'static str, Status)>,
     name: &'static str,
@@ -728,7 +950,7 @@ async fn half_bridge(
         reconnect_policy.maximum_interval.duration(),
         reconnect_policy.reset_window.duration(),
     );
-    let mut forward_pkid_to_received_msg = HashMap::<u16, Option<Publish>>::new();
+    let mut forward_pkid_to_received_msg = HashMap::<u16, Published>::new();
     let mut bridge_health = BridgeHealth::new(name, tx_health);
     let mut loop_breaker =
         MessageLoopBreaker::new(recv_client.clone(), bidirectional_topic_filters);
@@ -846,14 +1068,27 @@ async fn half_bridge(
 
             // Forward messages from event loop to target
             Event::Incoming(Incoming::Publish(publish)) => {
+                let properties = recv_event_loop.take_properties().unwrap_or_default();
                 if let Some(publish) = loop_breaker.ensure_not_looped(publish).await {
-                    if let Some(topic) = transformer.convert_topic(&publish.topic) {
+                    if let Some((topic, options)) = transformer.convert(&publish.topic) {
+                        let properties =
+                            bridge_response_topic(properties, &reverse_transformer, name);
                         let wire_size = mqtt_channel::publish_packet_size(
                             topic.as_ref(),
-                            publish.qos,
+                            options.qos,
                             publish.payload.len(),
                         );
-                        if let Some(limit) = max_payload_size.filter(|&limit| wire_size > limit) {
+                        if options.is_oversized(&publish) {
+                            log_event!(
+                                warn: name,
+                                "Dropping message on topic {topic}: payload size {} B exceeds the rule limit of {} B",
+                                publish.payload.len(),
+                                options.max_payload_size.unwrap_or_default()
+                            );
+                            recv_client.ack(&publish).await.unwrap()
+                        } else if let Some(limit) =
+                            max_payload_size.filter(|&limit| wire_size > limit)
+                        {
                             // The message is too large to ever be accepted by the cloud broker.
                             // Acknowledge it locally so it is not redelivered, and drop it rather
                             // than let it block the cloud connection.
@@ -862,9 +1097,25 @@ async fn half_bridge(
                                 "Dropping cloud-bound message on topic {topic}: packet size {wire_size} B exceeds the configured limit of {limit} B"
                             );
                             recv_client.ack(&publish).await.unwrap()
+                        } else if let Some(spool) = target.spool_if_disconnected() {
+                            // The target is not connected: persist the message till reconnected
+                            // and acknowledge it locally, the spool taking over its delivery.
+                            let mut spooled = publish.clone();
+                            (spooled.qos, spooled.retain) = options.qos_and_retain(&publish);
+                            match spool
+                                .push(topic.to_string(), spooled, properties.clone())
+                                .await
+                            {
+                                Ok(()) => recv_client.ack(&publish).await.unwrap(),
+                                Err(err) => {
+                                    log_event!(error: name, "Failed to spool message on topic {topic}: {err}");
+                                    received += 1;
+                                    target.forward(topic.to_string(), publish, properties, options);
+                                }
+                            }
                         } else {
                             received += 1;
-                            target.publish(topic.to_string(), publish);
+                            target.forward(topic.to_string(), publish, properties, options);
                         }
                     } else {
                         // Being not forwarded to this bridge target
@@ -880,11 +1131,15 @@ async fn half_bridge(
                 | Incoming::PubRec(PubRec { pkid: ack_pkid }),
             ) => {
                 match forward_pkid_to_received_msg.remove(&ack_pkid) {
-                    Some(Some(msg)) => {
+                    Some(Published::Forwarded { publish, .. }) => {
+                        acknowledged += 1;
+                        target.ack(publish);
+                    }
+                    Some(Published::Spooled { id, .. }) => {
                         acknowledged += 1;
-                        target.ack(msg);
+                        self_tx.spool_ack(id);
                     }
-                    Some(None) => {
+                    Some(Published::Internal) => {
                         // A health message was acked, nothing to do
                     }
                     None => {
@@ -898,19 +1153,36 @@ async fn half_bridge(
                 if let hash_map::Entry::Vacant(e) = forward_pkid_to_received_msg.entry(pkid) {
                     match target.recv().await {
                         // A message was forwarded by the other bridge half, note the packet id
-                        Some(Some((topic, msg))) => {
+                        Some(msg @ (Published::Forwarded { .. } | Published::Spooled { .. })) => {
                             published += 1;
-                            loop_breaker.forward_on_topic(topic, &msg);
+                            if let Published::Forwarded { topic, publish }
+                            | Published::Spooled { topic, publish, .. } = &msg
+                            {
+                                loop_breaker.forward_on_topic(topic.as_str(), publish);
+                            }
                             if pkid != 0 {
                                 // Messages with pkid 0 (meaning QoS=0) should not be added to the hashmap
                                 // as multiple messages with the pkid=0 can be received
-                                e.insert(Some(msg));
+                                e.insert(msg);
+                            } else {
+                                // No acknowledgement is expected for QoS=0 messages,
+                                // but the source message might have been received with a higher QoS
+                                match msg {
+                                    Published::Forwarded { publish, .. }
+                                        if publish.qos != QoS::AtMostOnce =>
+                                    {
+                                        acknowledged += 1;
+                                        target.ack(publish)
+                                    }
+                                    Published::Spooled { id, .. } => self_tx.spool_ack(id),
+                                    _ => {}
+                                }
                             }
                         }
 
                         // A healthcheck message was published, ack should ignore this packet id
-                        Some(None) => {
-                            e.insert(None);
+                        Some(Published::Internal) => {
+                            e.insert(Published::Internal);
                         }
 
                         // The other bridge half has disconnected, break the loop and shut down the bridge
@@ -964,11 +1236,38 @@ async fn half_bridge(
     }
 }
 
+/// Translates the response topic of a bridged request, so the response is bridged back to the requester
+///
+/// The response topic is a topic of the source broker, which has to be mapped to the target broker
+/// using the rules of the reverse direction. It is dropped if no such rule bridges it back.
+fn bridge_response_topic(
+    mut properties: MqttProperties,
+    reverse_transformer: &TopicConverter,
+    name: &str,
+) -> MqttProperties {
+    if let Some(response_topic) = properties.response_topic.take() {
+        properties.response_topic = reverse_transformer.source_topic(&response_topic);
+        if properties.response_topic.is_none() {
+            log_event!(warn: name, "Dropping response topic {response_topic}: it is not bridged back by any rule");
+        }
+    }
+    properties
+}
+
 #[async_trait::async_trait]
 trait MqttEvents: Send {
+    /// The requests pending on the event loop, to be republished on reconnection
+    type Request: std::fmt::Debug + Send;
+
     async fn poll(&mut self) -> Result<Event, ConnectionError>;
-    fn take_pending(&mut self) -> VecDeque<Request>;
-    fn set_pending(&mut self, requests: Vec<Request>);
+
+    /// Takes the MQTT 5 properties of the message just returned by [Self::poll], if any
+    fn take_properties(&mut self) -> Option<MqttProperties> {
+        None
+    }
+
+    fn take_pending(&mut self) -> VecDeque<Self::Request>;
+    fn set_pending(&mut self, requests: Vec<Self::Request>);
 }
 
 #[async_trait::async_trait]
@@ -980,6 +1279,7 @@ trait MqttClient: MqttAck + Clone + Send + Sync {
         qos: QoS,
         retain: bool,
         payload: Bytes,
+        properties: MqttProperties,
     ) -> Result<(), ClientError>;
 }
 
@@ -995,8 +1295,9 @@ impl MqttClient for LoggingAsyncClient {
         qos: QoS,
         retain: bool,
         payload: Bytes,
+        properties: MqttProperties,
     ) -> Result<(), ClientError> {
-        LoggingAsyncClient::publish(self, topic, qos, retain, payload).await
+        LoggingAsyncClient::publish(self, topic, qos, retain, payload, properties).await
     }
 }
 
@@ -1013,6 +1314,15 @@ impl Status {
             Status::Down => r#"{"status":"down"}"#,
         }
     }
+
+    /// The health status along the number of messages waiting in the spool
+    fn json_with_queue_depth(self, queued: usize) -> String {
+        let status = match self {
+            Status::Up => "up",
+            Status::Down => "down",
+        };
+        format!(r#"{{"status":"{status}","queued":{queued}}}"#)
+    }
 }
 
 fn overall_status(lhs: Option<Status>, rhs: &Option<Status>) -> Option<Status> {
@@ -1040,7 +1350,7 @@ fn have_same_content(fwd_msg: &Publish, cmp: &Publish) -> bool {
 #[cfg_attr(test, mockall::automock)]
 #[async_trait::async_trait]
 trait MqttAck {
-    async fn ack(&self, publish: &Publish) -> Result<(), rumqttc::ClientError>;
+    async fn ack(&self, publish: &Publish) -> Result<(), ClientError>;
 }
 
 #[async_trait::async_trait]
@@ -1512,6 +1822,69 @@ mod tests {
             )
         }
 
+        #[tokio::test]
+        async fn forwards_the_mqtt5_properties_of_the_messages() {
+            let properties = MqttProperties {
+                user_properties: vec![("key".to_string(), "value".to_string())],
+                content_type: Some("text/plain".to_string()),
+                message_expiry_interval: Some(60),
+                response_topic: Some("s/us".to_string()),
+                correlation_data: Some(b"request-1".to_vec()),
+            };
+            let incoming_msg = Publish::new("s/ds", QoS::AtLeastOnce, "payload");
+            let cloud_events = FixedEventStream::from([inc!(connack), inc!(publish(incoming_msg))])
+                .with_properties(properties.clone());
+
+            let bridge = Bridge::default()
+                .with_cloud_custom_events(cloud_events)
+                .with_local_events([inc!(connack)])
+                .with_c8y_topics()
+                .process_all_events()
+                .await;
+
+            // The response topic is translated, so the local response is bridged back to the cloud
+            assert_eq!(
+                bridge.local_client.next_action().unwrap(),
+                Action::PublishWithProperties(
+                    Publish::new("c8y/s/ds", QoS::AtLeastOnce, "payload"),
+                    MqttProperties {
+                        response_topic: Some("c8y/s/us".to_string()),
+                        ..properties
+                    }
+                )
+            );
+        }
+
+        #[tokio::test]
+        async fn drops_the_response_topics_which_are_not_bridged_back() {
+            let properties = MqttProperties {
+                response_topic: Some("not/bridged".to_string()),
+                correlation_data: Some(b"request-1".to_vec()),
+                ..MqttProperties::default()
+            };
+            let incoming_msg = Publish::new("c8y/s/us", QoS::AtLeastOnce, "payload");
+            let local_events = FixedEventStream::from([inc!(publish(incoming_msg))])
+                .with_properties(properties.clone());
+
+            let bridge = Bridge::default()
+                .with_local_custom_events(local_events)
+                .with_cloud_events([inc!(connack)])
+                .with_c8y_topics()
+                .process_all_events()
+                .await;
+
+            assert_eq!(
+                bridge.cloud_client.next_action().unwrap(),
+                Action::PublishWithProperties(
+                    Publish::new("s/us", QoS::AtLeastOnce, "payload"),
+                    MqttProperties {
+                        response_topic: None,
+                        ..properties
+                    }
+                )
+            );
+        }
+
         #[tokio::test]
         async fn over_limit_cloud_bound_message_is_acked_and_not_forwarded() {
             let big_msg = Publish::new("c8y/s/us", QoS::AtLeastOnce, vec![b'x'; 100]);
@@ -1538,6 +1911,78 @@ mod tests {
             );
         }
 
+        #[tokio::test]
+        async fn forwards_messages_with_the_qos_and_retain_flag_of_the_rule() {
+            let incoming_msg = Publish::new("c8y/s/us", QoS::AtLeastOnce, "payload");
+            let events = [inc!(publish(incoming_msg))];
+
+            let bridge = Bridge::default()
+                .with_local_events(events)
+                .with_cloud_events([inc!(connack)])
+                .with_c8y_topics_and_options(RuleOptions {
+                    qos: QoS::AtMostOnce,
+                    retain: RetainPolicy::Force,
+                    max_payload_size: None,
+                })
+                .process_all_events()
+                .await;
+
+            let mut outgoing_msg = Publish::new("s/us", QoS::AtMostOnce, "payload");
+            outgoing_msg.retain = true;
+            assert_eq!(
+                bridge.cloud_client.next_action().unwrap(),
+                Action::Publish(outgoing_msg)
+            )
+        }
+
+        #[tokio::test]
+        async fn messages_downgraded_to_qos_0_are_acknowledged_once_published() {
+            let incoming_msg = Publish::new("c8y/s/us", QoS::AtLeastOnce, "payload");
+            let local_events = [inc!(publish(incoming_msg))];
+            let cloud_events = [inc!(connack), out!(publish(0))];
+
+            let bridge = Bridge::default()
+                .with_local_events(local_events)
+                .with_cloud_events(cloud_events)
+                .with_c8y_topics_and_options(RuleOptions {
+                    qos: QoS::AtMostOnce,
+                    ..RuleOptions::default()
+                })
+                .process_all_events()
+                .await;
+
+            assert_eq!(
+                bridge.local_client.next_action().unwrap(),
+                Action::Ack(incoming_msg)
+            )
+        }
+
+        #[tokio::test]
+        async fn messages_exceeding_the_rule_payload_limit_are_acked_and_not_forwarded() {
+            let big_msg = Publish::new("c8y/s/us", QoS::AtLeastOnce, vec![b'x'; 100]);
+            let small_msg = Publish::new("c8y/s/us", QoS::AtLeastOnce, "payload");
+            let events = [inc!(publish(big_msg)), inc!(publish(small_msg))];
+
+            let bridge = Bridge::default()
+                .with_local_events(events)
+                .with_cloud_events([inc!(connack)])
+                .with_c8y_topics_and_options(RuleOptions {
+                    max_payload_size: Some(50),
+                    ..RuleOptions::default()
+                })
+                .process_all_events()
+                .await;
+
+            assert_eq!(
+                bridge.local_client.next_action().unwrap(),
+                Action::Ack(big_msg)
+            );
+            assert_eq!(
+                bridge.cloud_client.next_action().unwrap(),
+                Action::Publish(Publish::new("s/us", QoS::AtLeastOnce, "payload"))
+            );
+        }
+
         #[tokio::test]
         async fn over_limit_cloud_to_local_message_is_forwarded_unchanged() {
             let big_msg = Publish::new("s/ds", QoS::AtLeastOnce, vec![b'x'; 100]);
@@ -2011,7 +2456,7 @@ mod tests {
                 controller: SubscriptionGateController,
                 target: ActionLogger,
                 // Keeps the companion-notification channel open so publishes do not fail
-                _companion_rx: mpsc::Receiver<Option<(String, Publish)>>,
+                _companion_rx: mpsc::Receiver<Published>,
             }
 
             /// Builds a publisher whose forwarded messages land in its `target`, starting with
@@ -2023,7 +2468,7 @@ mod tests {
                 let (tx, companion_rx) = mpsc::channel(10);
                 // companion -> this: unused by these tests, so its sender is dropped immediately
                 let (_unused_tx, rx) = mpsc::channel(10);
-                let client = BridgeAsyncClient::new(target.clone(), tx, rx, gate);
+                let client = BridgeAsyncClient::new(target.clone(), tx, rx, gate, None);
                 FocusedPublisher {
                     client,
                     controller,
@@ -2598,6 +3043,14 @@ mod tests {
                 }
             }
 
+            fn with_c8y_topics_and_options(self, options: RuleOptions) -> Self {
+                let local_rules = vec![bridge_rule!("s/us" - "c8y/" + "").with_options(options)];
+                Self {
+                    local_topic_converter: TopicConverter(local_rules),
+                    ..self.with_c8y_topics()
+                }
+            }
+
             fn with_cloud_reconnect_message(self, message: Option<Publish>) -> Self {
                 Self {
                     cloud_reconnect_message: message,
@@ -2657,6 +3110,23 @@ mod tests {
                 }
             }
 
+            fn with_local_custom_events<NewLoEv>(
+                self,
+                events: NewLoEv,
+            ) -> Bridge<NewLoEv, ClEv, LoCl, ClCl> {
+                Bridge {
+                    local_events: events,
+                    cloud_events: self.cloud_events,
+                    local_client: self.local_client,
+                    cloud_client: self.cloud_client,
+                    subscription_topics: self.subscription_topics,
+                    local_topic_converter: self.local_topic_converter,
+                    cloud_topic_converter: self.cloud_topic_converter,
+                    cloud_reconnect_message: self.cloud_reconnect_message,
+                    max_payload_size: self.max_payload_size,
+                }
+            }
+
             /// Spawn both bridge halves and wait for them to process all queued events
             async fn process_all_events(self) -> CompletedBridge<LoCl, ClCl> {
                 let (tx0, rx0) = mpsc::channel(10);
@@ -2668,9 +3138,9 @@ mod tests {
                 let (cloud_gate, cloud_gate_controller) = SubscriptionGate::new();
                 let (local_gate, local_gate_controller) = SubscriptionGate::new();
                 let cloud_target =
-                    BridgeAsyncClient::new(self.cloud_client.clone(), tx0, rx1, cloud_gate);
+                    BridgeAsyncClient::new(self.cloud_client.clone(), tx0, rx1, cloud_gate, None);
                 let local_target =
-                    BridgeAsyncClient::new(self.local_client.clone(), tx1, rx0, local_gate);
+                    BridgeAsyncClient::new(self.local_client.clone(), tx1, rx0, local_gate, None);
                 let cloud_sender = cloud_target.clone_sender();
                 let local_sender = local_target.clone_sender();
 
@@ -2678,7 +3148,8 @@ mod tests {
                     self.local_events.clone(),
                     self.local_client.clone(),
                     cloud_target,
-                    self.local_topic_converter,
+                    self.local_topic_converter.clone(),
+                    self.cloud_topic_converter.clone(),
                     vec![],
                     tx_health.clone(),
                     "local",
@@ -2694,6 +3165,7 @@ mod tests {
                     self.cloud_client.clone(),
                     local_target,
                     self.cloud_topic_converter,
+                    self.local_topic_converter,
                     vec![],
                     tx_health,
                     "cloud",
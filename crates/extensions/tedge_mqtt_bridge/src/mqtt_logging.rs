@@ -2,18 +2,28 @@ use std::collections::VecDeque;
 use std::time::Duration;
 
 use bytes::Bytes;
+use mqtt_channel::qos_from_v5;
+use mqtt_channel::qos_to_v5;
+use mqtt_channel::MqttProperties;
+use mqtt_channel::MqttProtocol;
+use rumqttc::v5;
 use rumqttc::AsyncClient;
-use rumqttc::ClientError;
+use rumqttc::ConnAck;
+use rumqttc::ConnectReturnCode;
 use rumqttc::ConnectionError;
 use rumqttc::Event;
 use rumqttc::EventLoop;
 use rumqttc::MqttOptions;
 use rumqttc::Outgoing;
 use rumqttc::Packet;
+use rumqttc::PubAck;
+use rumqttc::PubRec;
 use rumqttc::Publish;
 use rumqttc::QoS;
 use rumqttc::Request;
+use rumqttc::SubAck;
 use rumqttc::SubscribeFilter;
+use rumqttc::SubscribeReasonCode;
 use rumqttc::{self};
 use tokio::sync::mpsc::error::TryRecvError;
 use tokio::sync::mpsc::UnboundedReceiver;
@@ -21,10 +31,43 @@ use tokio::sync::mpsc::UnboundedSender;
 
 use crate::MqttEvents;
 
+/// An error returned by the MQTT 3.1.1 or the MQTT 5 client of a bridge connection
+#[derive(thiserror::Error, Debug)]
+pub enum ClientError {
+    #[error(transparent)]
+    V311(#[from] rumqttc::ClientError),
+
+    #[error(transparent)]
+    V5(#[from] v5::ClientError),
+}
+
+/// A request pending on the MQTT 3.1.1 or the MQTT 5 event loop of a bridge connection
+#[derive(Debug)]
+pub enum PendingRequest {
+    V311(Request),
+    V5(v5::Request),
+}
+
+/// The MQTT 3.1.1 or MQTT 5 client of a bridge connection
+#[derive(Clone, Debug)]
+enum Client {
+    V311(AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+/// The event loop of a [Client]
+enum Connection {
+    V311(EventLoop),
+    V5(v5::EventLoop),
+}
+
 /// A wrapper around [AsyncClient] for logging packets with [LoggingEventLoop]
+///
+/// When connected using MQTT 5, the MQTT 5 packets are translated to their MQTT 3.1.1 counterparts,
+/// except for the properties of the messages that are forwarded along the messages.
 #[derive(Clone, Debug)]
 pub struct LoggingAsyncClient {
-    inner: AsyncClient,
+    inner: Client,
     log_tx: UnboundedSender<Vec<SubscribeFilter>>,
 }
 
@@ -36,16 +79,30 @@ impl LoggingAsyncClient {
     /// used for the cloud connection, whose TLS handshake can be slow when signing with an HSM/TPM.
     pub fn new(
         options: MqttOptions,
+        protocol: MqttProtocol,
         cap: usize,
         log_prefix: String,
         connection_timeout: Option<Duration>,
     ) -> (Self, LoggingEventLoop) {
-        let (client, mut eventloop) = AsyncClient::new(options, cap);
-        if let Some(connection_timeout) = connection_timeout {
-            eventloop
-                .network_options
-                .set_connection_timeout(connection_timeout.as_secs());
-        }
+        let (client, eventloop) = match protocol {
+            MqttProtocol::V311 => {
+                let (client, mut eventloop) = AsyncClient::new(options, cap);
+                if let Some(connection_timeout) = connection_timeout {
+                    eventloop
+                        .network_options
+                        .set_connection_timeout(connection_timeout.as_secs());
+                }
+                (Client::V311(client), Connection::V311(eventloop))
+            }
+            MqttProtocol::V5 => {
+                let mut options = v5_options(&options);
+                if let Some(connection_timeout) = connection_timeout {
+                    options.set_connection_timeout(connection_timeout.as_secs());
+                }
+                let (client, eventloop) = v5::AsyncClient::new(options, cap);
+                (Client::V5(client), Connection::V5(eventloop))
+            }
+        };
         let (log_tx, log_rx) = tokio::sync::mpsc::unbounded_channel();
 
         (
@@ -58,6 +115,7 @@ impl LoggingAsyncClient {
                 log_rx,
                 log_prefix,
                 has_logged_connect: false,
+                properties: None,
             },
         )
     }
@@ -67,31 +125,101 @@ impl LoggingAsyncClient {
         let subscriptions = vec![SubscribeFilter::new(topic_str.clone(), qos)];
 
         let _ = self.log_tx.send(subscriptions);
-        self.inner.subscribe(topic_str, qos).await
+        match &self.inner {
+            Client::V311(client) => client.subscribe(topic_str, qos).await?,
+            Client::V5(client) => client.subscribe(topic_str, qos_to_v5(qos)).await?,
+        }
+        Ok(())
     }
 
+    /// Publishes a message, along its properties when connected using MQTT 5
     pub async fn publish(
         &self,
         topic: String,
         qos: QoS,
         retain: bool,
         payload: Bytes,
+        properties: MqttProperties,
     ) -> Result<(), ClientError> {
-        self.inner.publish(topic, qos, retain, payload).await
+        match &self.inner {
+            Client::V311(client) => client.publish(topic, qos, retain, payload).await?,
+            Client::V5(client) if properties.is_empty() => {
+                client
+                    .publish(topic, qos_to_v5(qos), retain, payload)
+                    .await?
+            }
+            Client::V5(client) => {
+                client
+                    .publish_with_properties(
+                        topic,
+                        qos_to_v5(qos),
+                        retain,
+                        payload,
+                        properties.into(),
+                    )
+                    .await?
+            }
+        }
+        Ok(())
     }
 
     pub async fn ack(&self, publish: &Publish) -> Result<(), ClientError> {
-        self.inner.ack(publish).await
+        match &self.inner {
+            Client::V311(client) => client.ack(publish).await?,
+            Client::V5(client) => {
+                // Only the QoS and the packet id are used to acknowledge a message
+                let mut ack = v5::mqttbytes::v5::Publish::new(
+                    &publish.topic,
+                    qos_to_v5(publish.qos),
+                    "",
+                    None,
+                );
+                ack.pkid = publish.pkid;
+                client.ack(&ack).await?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Translates the MQTT 3.1.1 options of a bridge connection into MQTT 5 options
+fn v5_options(options: &MqttOptions) -> v5::MqttOptions {
+    let (host, port) = options.broker_address();
+    let mut v5_options = v5::MqttOptions::new(options.client_id(), host, port);
+    v5_options.set_keep_alive(options.keep_alive());
+    v5_options.set_clean_start(options.clean_session());
+    // Unlike with MQTT 3.1.1, an MQTT 5 session ends with the connection unless an expiry interval is set
+    if !options.clean_session() {
+        v5_options.set_session_expiry_interval(Some(u32::MAX));
     }
+    if let Some((username, password)) = options.credentials() {
+        v5_options.set_credentials(username, password);
+    }
+    v5_options.set_transport(options.transport());
+    if let Some(last_will) = options.last_will() {
+        v5_options.set_last_will(v5::mqttbytes::v5::LastWill::new(
+            last_will.topic,
+            last_will.message,
+            qos_to_v5(last_will.qos),
+            last_will.retain,
+            None,
+        ));
+    }
+    v5_options.set_max_packet_size(Some(crate::MAX_PACKET_SIZE as u32));
+    v5_options.set_manual_acks(options.manual_acks());
+    v5_options
 }
 
 /// A wrapper around [rumqttc::EventLoop] that logs key MQTT events with a
 /// configurable prefix.
 pub struct LoggingEventLoop {
-    inner: EventLoop,
+    inner: Connection,
     log_rx: UnboundedReceiver<Vec<SubscribeFilter>>,
     log_prefix: String,
     has_logged_connect: bool,
+
+    /// The MQTT 5 properties of the last received message, if any
+    properties: Option<MqttProperties>,
 }
 
 impl LoggingEventLoop {
@@ -105,7 +233,21 @@ impl LoggingEventLoop {
             self.has_logged_connect = true;
         }
 
-        let event = self.inner.poll().await;
+        self.properties = None;
+        let event = match &mut self.inner {
+            Connection::V311(eventloop) => eventloop.poll().await,
+            Connection::V5(eventloop) => loop {
+                match eventloop.poll().await {
+                    Ok(event) => {
+                        if let Some((event, properties)) = from_v5_event(event) {
+                            self.properties = properties;
+                            break Ok(event);
+                        }
+                    }
+                    Err(err) => break Err(from_v5_error(err)),
+                }
+            },
+        };
 
         match &event {
             Ok(Event::Outgoing(Outgoing::Subscribe(pkid))) => {
@@ -171,18 +313,113 @@ impl LoggingEventLoop {
     }
 }
 
+/// Translates an MQTT 5 event into its MQTT 3.1.1 counterpart, along the properties of a received message
+///
+/// Return `None` for the packets ignored by the bridge and with no MQTT 3.1.1 counterpart.
+fn from_v5_event(event: v5::Event) -> Option<(Event, Option<MqttProperties>)> {
+    use v5::mqttbytes::v5 as mqtt5;
+    let packet = match event {
+        v5::Event::Outgoing(outgoing) => return Some((Event::Outgoing(outgoing), None)),
+        v5::Event::Incoming(packet) => packet,
+    };
+    let packet = match packet {
+        mqtt5::Packet::Publish(publish) => {
+            let properties = publish
+                .properties
+                .map(MqttProperties::from)
+                .filter(|properties| !properties.is_empty());
+            let mut v3_publish = Publish::new(
+                String::from_utf8_lossy(&publish.topic),
+                qos_from_v5(publish.qos),
+                publish.payload,
+            );
+            v3_publish.dup = publish.dup;
+            v3_publish.retain = publish.retain;
+            v3_publish.pkid = publish.pkid;
+            return Some((Event::Incoming(Packet::Publish(v3_publish)), properties));
+        }
+        // A refused connection is returned as a connection error
+        mqtt5::Packet::ConnAck(ack) => Packet::ConnAck(ConnAck {
+            session_present: ack.session_present,
+            code: ConnectReturnCode::Success,
+        }),
+        mqtt5::Packet::SubAck(ack) => Packet::SubAck(SubAck {
+            pkid: ack.pkid,
+            return_codes: ack
+                .return_codes
+                .into_iter()
+                .map(|code| match code {
+                    mqtt5::SubscribeReasonCode::Success(qos) => {
+                        SubscribeReasonCode::Success(qos_from_v5(qos))
+                    }
+                    _ => SubscribeReasonCode::Failure,
+                })
+                .collect(),
+        }),
+        mqtt5::Packet::PubAck(ack) => Packet::PubAck(PubAck::new(ack.pkid)),
+        mqtt5::Packet::PubRec(ack) => Packet::PubRec(PubRec::new(ack.pkid)),
+        mqtt5::Packet::Disconnect(_) => Packet::Disconnect,
+        mqtt5::Packet::PingReq(_) => Packet::PingReq,
+        mqtt5::Packet::PingResp(_) => Packet::PingResp,
+        _ => return None,
+    };
+    Some((Event::Incoming(packet), None))
+}
+
+fn from_v5_error(err: v5::ConnectionError) -> ConnectionError {
+    match err {
+        v5::ConnectionError::NetworkTimeout => ConnectionError::NetworkTimeout,
+        v5::ConnectionError::Io(err) => ConnectionError::Io(err),
+        err => ConnectionError::Io(std::io::Error::other(err)),
+    }
+}
+
 #[async_trait::async_trait]
 impl MqttEvents for LoggingEventLoop {
+    type Request = PendingRequest;
+
     async fn poll(&mut self) -> Result<Event, ConnectionError> {
         LoggingEventLoop::poll(self).await
     }
 
-    fn take_pending(&mut self) -> VecDeque<Request> {
-        std::mem::take(&mut self.inner.pending)
+    fn take_properties(&mut self) -> Option<MqttProperties> {
+        self.properties.take()
     }
 
-    fn set_pending(&mut self, requests: Vec<Request>) {
-        self.inner.pending = requests.into_iter().collect();
+    fn take_pending(&mut self) -> VecDeque<PendingRequest> {
+        match &mut self.inner {
+            Connection::V311(eventloop) => std::mem::take(&mut eventloop.pending)
+                .into_iter()
+                .map(PendingRequest::V311)
+                .collect(),
+            Connection::V5(eventloop) => std::mem::take(&mut eventloop.pending)
+                .into_iter()
+                .map(PendingRequest::V5)
+                .collect(),
+        }
+    }
+
+    fn set_pending(&mut self, requests: Vec<PendingRequest>) {
+        match &mut self.inner {
+            Connection::V311(eventloop) => {
+                eventloop.pending = requests
+                    .into_iter()
+                    .filter_map(|request| match request {
+                        PendingRequest::V311(request) => Some(request),
+                        PendingRequest::V5(_) => None,
+                    })
+                    .collect()
+            }
+            Connection::V5(eventloop) => {
+                eventloop.pending = requests
+                    .into_iter()
+                    .filter_map(|request| match request {
+                        PendingRequest::V5(request) => Some(request),
+                        PendingRequest::V311(_) => None,
+                    })
+                    .collect()
+            }
+        }
     }
 }
 
@@ -198,11 +435,17 @@ mod tests {
     fn applies_connection_timeout_when_set() {
         let (_client, eventloop) = LoggingAsyncClient::new(
             dummy_options(),
+            MqttProtocol::V311,
             10,
             "cloud".into(),
             Some(Duration::from_secs(60)),
         );
-        assert_eq!(eventloop.inner.network_options().connection_timeout(), 60);
+        assert_eq!(
+            v311_eventloop(eventloop)
+                .network_options()
+                .connection_timeout(),
+            60
+        );
     }
 
     #[test]
@@ -211,11 +454,70 @@ mod tests {
         let (_client, default_eventloop) = AsyncClient::new(dummy_options(), 10);
         let default_timeout = default_eventloop.network_options().connection_timeout();
 
-        let (_client, eventloop) =
-            LoggingAsyncClient::new(dummy_options(), 10, "local".into(), None);
+        let (_client, eventloop) = LoggingAsyncClient::new(
+            dummy_options(),
+            MqttProtocol::V311,
+            10,
+            "local".into(),
+            None,
+        );
         assert_eq!(
-            eventloop.inner.network_options().connection_timeout(),
+            v311_eventloop(eventloop)
+                .network_options()
+                .connection_timeout(),
             default_timeout
         );
     }
+
+    #[test]
+    fn mqtt5_messages_are_translated_along_their_properties() {
+        let properties = MqttProperties {
+            user_properties: vec![("key".to_string(), "value".to_string())],
+            content_type: Some("application/json".to_string()),
+            message_expiry_interval: Some(60),
+            response_topic: Some("te/responses".to_string()),
+            correlation_data: Some(b"123".to_vec()),
+        };
+        let mut publish = v5::mqttbytes::v5::Publish::new(
+            "c8y/s/us",
+            v5::mqttbytes::QoS::AtLeastOnce,
+            "payload",
+            Some(properties.clone().into()),
+        );
+        publish.pkid = 42;
+        publish.retain = true;
+
+        let (event, received_properties) =
+            from_v5_event(v5::Event::Incoming(v5::Incoming::Publish(publish))).unwrap();
+
+        let mut expected = Publish::new("c8y/s/us", QoS::AtLeastOnce, "payload");
+        expected.pkid = 42;
+        expected.retain = true;
+        assert_eq!(event, Event::Incoming(Packet::Publish(expected)));
+        assert_eq!(received_properties, Some(properties));
+    }
+
+    #[test]
+    fn mqtt5_messages_without_properties_are_translated() {
+        let publish = v5::mqttbytes::v5::Publish::new(
+            "c8y/s/us",
+            v5::mqttbytes::QoS::AtMostOnce,
+            "payload",
+            None,
+        );
+
+        let (event, received_properties) =
+            from_v5_event(v5::Event::Incoming(v5::Incoming::Publish(publish))).unwrap();
+
+        let expected = Publish::new("c8y/s/us", QoS::AtMostOnce, "payload");
+        assert_eq!(event, Event::Incoming(Packet::Publish(expected)));
+        assert_eq!(received_properties, None);
+    }
+
+    fn v311_eventloop(eventloop: LoggingEventLoop) -> EventLoop {
+        match eventloop.inner {
+            Connection::V311(eventloop) => eventloop,
+            Connection::V5(_) => panic!("Expected an MQTT 3.1.1 event loop"),
+        }
+    }
 }
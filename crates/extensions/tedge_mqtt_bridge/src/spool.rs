@@ -0,0 +1,588 @@
+//! A disk-backed store-and-forward queue for the cloud-bound messages
+//!
+//! While the cloud connection is down, the messages received from the local broker are stored
+//! on disk, one file per message, and acknowledged to the local broker. These messages are then
+//! replayed in order once the cloud connection is re-established, and removed from the spool
+//! only when acknowledged by the cloud broker. Hence, the spooled messages survive a restart of
+//! the bridge.
+//!
+//! The spool is bounded both in size and age; when a limit is exceeded the oldest messages are
+//! dropped first.
+//!
+//! The spool operations, writing and syncing files, are run on the blocking thread pool
+//! and never block the bridge tasks.
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use mqtt_channel::MqttProperties;
+use rumqttc::Publish;
+use rumqttc::QoS;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tokio::sync::watch;
+use tokio::sync::Notify;
+
+const LOG_PREFIX: &str = "spool";
+const SPOOL_FILE_EXTENSION: &str = "msg";
+const SPOOL_TEMP_FILE_EXTENSION: &str = "tmp";
+
+/// Size of the header stored before the topic, properties and payload of a message:
+/// timestamp (8 bytes), qos (1 byte), retain flag (1 byte), topic length (2 bytes)
+/// and properties length (4 bytes)
+const HEADER_SIZE: usize = 16;
+
+/// Maximum number of spooled messages handed over to the cloud connection and not acknowledged yet
+const REPLAY_WINDOW: usize = 50;
+
+pub type SpoolId = u64;
+
+/// The size and age limits of a [BridgeSpool]
+#[derive(Clone, Debug)]
+pub struct SpoolConfig {
+    pub dir: Utf8PathBuf,
+    pub max_size: u64,
+    pub max_age: Duration,
+}
+
+/// A message read from the spool, to be published on the cloud
+#[derive(Debug)]
+pub(crate) struct SpooledMessage {
+    pub id: SpoolId,
+    pub publish: Publish,
+    pub properties: MqttProperties,
+}
+
+/// A handle to a disk spool, shared by the bridge tasks
+#[derive(Clone)]
+pub(crate) struct BridgeSpool {
+    inner: Arc<Mutex<DiskSpool>>,
+    updated: Arc<Notify>,
+    depth: Arc<watch::Sender<usize>>,
+}
+
+impl BridgeSpool {
+    pub fn open(config: SpoolConfig) -> std::io::Result<Self> {
+        let spool = DiskSpool::open(config)?;
+        let (depth, _) = watch::channel(spool.len());
+        Ok(BridgeSpool {
+            inner: Arc::new(Mutex::new(spool)),
+            updated: Arc::new(Notify::new()),
+            depth: Arc::new(depth),
+        })
+    }
+
+    /// Stores a message on disk, to be published later on the given topic with the given properties
+    pub async fn push(
+        &self,
+        target_topic: String,
+        publish: Publish,
+        properties: MqttProperties,
+    ) -> std::io::Result<()> {
+        self.run(move |spool| spool.push(&target_topic, &publish, &properties, SystemTime::now()))
+            .await?;
+        self.updated.notify_one();
+        Ok(())
+    }
+
+    /// Returns the next message to be replayed, if any and if the replay window is not full
+    pub async fn next_to_replay(&self) -> Option<SpooledMessage> {
+        // Expired messages might have been dropped, but there is nothing new to replay
+        self.run(|spool| spool.next_to_replay(SystemTime::now()))
+            .await
+    }
+
+    /// Removes a message from the spool, once acknowledged by the cloud broker
+    pub async fn acknowledge(&self, id: SpoolId) {
+        self.run(move |spool| spool.remove(id)).await;
+        self.updated.notify_one();
+    }
+
+    /// Restarts the replay from the oldest message not acknowledged yet
+    ///
+    /// To be called on each new connection, as the messages replayed over a former connection
+    /// might have been lost with that connection.
+    pub async fn rewind(&self) {
+        self.run(|spool| spool.rewind()).await;
+        self.updated.notify_one();
+    }
+
+    /// Returns true if there is no message in the spool, neither waiting to be replayed nor to be acknowledged
+    pub fn is_empty(&self) -> bool {
+        *self.depth.borrow() == 0
+    }
+
+    /// Resolves when messages are added to or removed from the spool
+    pub async fn updated(&self) {
+        self.updated.notified().await
+    }
+
+    /// Watches the number of messages in the spool
+    pub fn watch_depth(&self) -> watch::Receiver<usize> {
+        self.depth.subscribe()
+    }
+
+    /// Runs an operation on the disk spool, updating the spool depth
+    async fn run<T: Send + 'static>(
+        &self,
+        operation: impl FnOnce(&mut DiskSpool) -> T + Send + 'static,
+    ) -> T {
+        let inner = self.inner.clone();
+        let depth = self.depth.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut spool = inner.lock().unwrap();
+            let result = operation(&mut spool);
+            depth.send_if_modified(|depth| {
+                let changed = *depth != spool.len();
+                *depth = spool.len();
+                changed
+            });
+            result
+        })
+        .await
+        .unwrap()
+    }
+}
+
+/// Resolves when the spool, if any, is updated; never resolves if there is no spool
+pub(crate) async fn updated(spool: Option<&BridgeSpool>) {
+    match spool {
+        Some(spool) => spool.updated().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// The on-disk queue of messages, one file per message named after the message id
+struct DiskSpool {
+    config: SpoolConfig,
+    entries: BTreeMap<SpoolId, EntryMetadata>,
+    next_id: SpoolId,
+    // Id of the first message not yet handed over to the cloud connection
+    next_replay: SpoolId,
+    total_size: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct EntryMetadata {
+    size: u64,
+    stored_at: SystemTime,
+}
+
+impl DiskSpool {
+    fn open(config: SpoolConfig) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+
+        let mut entries = BTreeMap::new();
+        for entry in std::fs::read_dir(&config.dir)? {
+            let path = entry?.path();
+            let Some(path) = Utf8Path::from_path(&path) else {
+                continue;
+            };
+            if path.extension() == Some(SPOOL_TEMP_FILE_EXTENSION) {
+                // A message that has not been fully written
+                let _ = std::fs::remove_file(path);
+                continue;
+            }
+            if path.extension() != Some(SPOOL_FILE_EXTENSION) {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| stem.parse().ok()) else {
+                continue;
+            };
+            match read_metadata(path) {
+                Ok(metadata) => {
+                    entries.insert(id, metadata);
+                }
+                Err(err) => {
+                    log_event!(warn: LOG_PREFIX, "Dropping unreadable spooled message {path}: {err}");
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+
+        let next_id = entries.keys().next_back().map_or(0, |id| id + 1);
+        let next_replay = entries.keys().next().copied().unwrap_or(next_id);
+        let total_size = entries.values().map(|entry| entry.size).sum();
+        let mut spool = DiskSpool {
+            config,
+            entries,
+            next_id,
+            next_replay,
+            total_size,
+        };
+        spool.drop_expired(SystemTime::now());
+        if !spool.entries.is_empty() {
+            log_event!(
+                LOG_PREFIX,
+                "Restored {} cloud-bound messages from {}",
+                spool.entries.len(),
+                spool.config.dir
+            );
+        }
+        Ok(spool)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn push(
+        &mut self,
+        target_topic: &str,
+        publish: &Publish,
+        properties: &MqttProperties,
+        now: SystemTime,
+    ) -> std::io::Result<()> {
+        let bytes = encode(target_topic, publish, properties, now)?;
+        let size = bytes.len() as u64;
+        if size > self.config.max_size {
+            log_event!(warn: LOG_PREFIX, "Dropping message on {target_topic}: its size {size} B exceeds the spool size limit");
+            return Ok(());
+        }
+
+        let id = self.next_id;
+        let path = self.path_of(id);
+        let temp_path = path.with_extension(SPOOL_TEMP_FILE_EXTENSION);
+        {
+            let mut file = File::create(&temp_path)?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+        }
+        std::fs::rename(&temp_path, &path)?;
+
+        self.next_id += 1;
+        self.total_size += size;
+        self.entries.insert(
+            id,
+            EntryMetadata {
+                size,
+                stored_at: now,
+            },
+        );
+
+        self.drop_expired(now);
+        while self.total_size > self.config.max_size {
+            let Some(oldest) = self.entries.keys().next().copied() else {
+                break;
+            };
+            log_event!(warn: LOG_PREFIX, "Spool size limit exceeded, dropping the oldest message");
+            self.remove(oldest);
+        }
+        Ok(())
+    }
+
+    fn next_to_replay(&mut self, now: SystemTime) -> Option<SpooledMessage> {
+        self.drop_expired(now);
+        loop {
+            let in_flight = self.entries.range(..self.next_replay).count();
+            if in_flight >= REPLAY_WINDOW {
+                return None;
+            }
+            let (&id, _) = self.entries.range(self.next_replay..).next()?;
+            self.next_replay = id + 1;
+            match std::fs::read(self.path_of(id))
+                .and_then(|bytes| decode(&bytes).ok_or_else(invalid_data))
+            {
+                Ok((publish, properties)) => {
+                    return Some(SpooledMessage {
+                        id,
+                        publish,
+                        properties,
+                    })
+                }
+                Err(err) => {
+                    log_event!(warn: LOG_PREFIX, "Dropping unreadable spooled message {id}: {err}");
+                    self.remove(id);
+                }
+            }
+        }
+    }
+
+    fn rewind(&mut self) {
+        self.next_replay = self.entries.keys().next().copied().unwrap_or(self.next_id);
+    }
+
+    fn remove(&mut self, id: SpoolId) {
+        if let Some(entry) = self.entries.remove(&id) {
+            self.total_size -= entry.size;
+            if let Err(err) = std::fs::remove_file(self.path_of(id)) {
+                log_event!(warn: LOG_PREFIX, "Failed to remove spooled message {id}: {err}");
+            }
+        }
+    }
+
+    fn drop_expired(&mut self, now: SystemTime) {
+        let max_age = self.config.max_age;
+        let expired: Vec<_> = self
+            .entries
+            .iter()
+            .take_while(|(_, entry)| {
+                now.duration_since(entry.stored_at)
+                    .is_ok_and(|age| age > max_age)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        if !expired.is_empty() {
+            log_event!(warn: LOG_PREFIX, "Dropping {} spooled messages older than {max_age:?}", expired.len());
+        }
+        for id in expired {
+            self.remove(id);
+        }
+    }
+
+    fn path_of(&self, id: SpoolId) -> Utf8PathBuf {
+        self.config
+            .dir
+            .join(format!("{id:020}.{SPOOL_FILE_EXTENSION}"))
+    }
+}
+
+fn invalid_data() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid spooled message")
+}
+
+fn read_metadata(path: &Utf8Path) -> std::io::Result<EntryMetadata> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut timestamp = [0u8; 8];
+    file.read_exact(&mut timestamp)?;
+    let stored_at = UNIX_EPOCH + Duration::from_millis(u64::from_be_bytes(timestamp));
+    Ok(EntryMetadata { size, stored_at })
+}
+
+fn encode(
+    target_topic: &str,
+    publish: &Publish,
+    properties: &MqttProperties,
+    now: SystemTime,
+) -> std::io::Result<Vec<u8>> {
+    let topic_len = u16::try_from(target_topic.len()).map_err(|_| invalid_data())?;
+    // Most messages have no properties, for which nothing is stored
+    let properties = if properties == &MqttProperties::default() {
+        Vec::new()
+    } else {
+        serde_json::to_vec(properties)?
+    };
+    let properties_len = u32::try_from(properties.len()).map_err(|_| invalid_data())?;
+    let timestamp = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let qos = match publish.qos {
+        QoS::AtMostOnce => 0u8,
+        QoS::AtLeastOnce => 1,
+        QoS::ExactlyOnce => 2,
+    };
+
+    let mut bytes = Vec::with_capacity(
+        HEADER_SIZE + target_topic.len() + properties.len() + publish.payload.len(),
+    );
+    bytes.extend_from_slice(&timestamp.to_be_bytes());
+    bytes.push(qos);
+    bytes.push(publish.retain as u8);
+    bytes.extend_from_slice(&topic_len.to_be_bytes());
+    bytes.extend_from_slice(&properties_len.to_be_bytes());
+    bytes.extend_from_slice(target_topic.as_bytes());
+    bytes.extend_from_slice(&properties);
+    bytes.extend_from_slice(&publish.payload);
+    Ok(bytes)
+}
+
+fn decode(bytes: &[u8]) -> Option<(Publish, MqttProperties)> {
+    let header = bytes.get(..HEADER_SIZE)?;
+    let qos = match header[8] {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        2 => QoS::ExactlyOnce,
+        _ => return None,
+    };
+    let retain = header[9] != 0;
+    let topic_len = u16::from_be_bytes([header[10], header[11]]) as usize;
+    let properties_len =
+        u32::from_be_bytes([header[12], header[13], header[14], header[15]]) as usize;
+    let topic_end = HEADER_SIZE + topic_len;
+    let properties_end = topic_end + properties_len;
+    let topic = bytes.get(HEADER_SIZE..topic_end)?;
+    let topic = std::str::from_utf8(topic).ok()?;
+    let properties = match bytes.get(topic_end..properties_end)? {
+        [] => MqttProperties::default(),
+        properties => serde_json::from_slice(properties).ok()?,
+    };
+    let payload = &bytes[properties_end..];
+
+    let mut publish = Publish::new(topic, qos, payload.to_vec());
+    publish.retain = retain;
+    Some((publish, properties))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn messages_are_replayed_in_order() {
+        let ttd = TempTedgeDir::new();
+        let mut spool = DiskSpool::open(config(&ttd, 1024, 3600)).unwrap();
+        let now = SystemTime::now();
+
+        spool
+            .push("s/us", &publish("a"), &no_properties(), now)
+            .unwrap();
+        spool
+            .push("s/us", &publish("b"), &no_properties(), now)
+            .unwrap();
+
+        assert_eq!(replayed_payload(&mut spool, now), Some("a".to_string()));
+        assert_eq!(replayed_payload(&mut spool, now), Some("b".to_string()));
+        assert_eq!(replayed_payload(&mut spool, now), None);
+
+        // Replayed messages are kept till acknowledged
+        assert_eq!(spool.len(), 2);
+    }
+
+    #[test]
+    fn unacknowledged_messages_survive_a_restart() {
+        let ttd = TempTedgeDir::new();
+        let now = SystemTime::now();
+        {
+            let mut spool = DiskSpool::open(config(&ttd, 1024, 3600)).unwrap();
+            spool
+                .push("s/us", &publish("a"), &no_properties(), now)
+                .unwrap();
+            spool
+                .push("s/us", &publish("b"), &no_properties(), now)
+                .unwrap();
+            let first = spool.next_to_replay(now).unwrap();
+            spool.next_to_replay(now).unwrap();
+            spool.remove(first.id);
+        }
+
+        let mut spool = DiskSpool::open(config(&ttd, 1024, 3600)).unwrap();
+        let message = spool.next_to_replay(now).unwrap();
+        assert_eq!(message.publish.topic, "s/us");
+        assert_eq!(message.publish.qos, QoS::AtLeastOnce);
+        assert_eq!(&message.publish.payload[..], b"b");
+        assert!(spool.next_to_replay(now).is_none());
+    }
+
+    #[test]
+    fn message_properties_survive_a_restart() {
+        let ttd = TempTedgeDir::new();
+        let now = SystemTime::now();
+        let properties = MqttProperties {
+            user_properties: vec![("key".to_string(), "value".to_string())],
+            content_type: Some("text/plain".to_string()),
+            message_expiry_interval: Some(60),
+            response_topic: Some("s/ds".to_string()),
+            correlation_data: Some(b"request-1".to_vec()),
+        };
+        {
+            let mut spool = DiskSpool::open(config(&ttd, 1024, 3600)).unwrap();
+            spool.push("s/us", &publish("a"), &properties, now).unwrap();
+        }
+
+        let mut spool = DiskSpool::open(config(&ttd, 1024, 3600)).unwrap();
+        let message = spool.next_to_replay(now).unwrap();
+        assert_eq!(&message.publish.payload[..], b"a");
+        assert_eq!(message.properties, properties);
+    }
+
+    #[test]
+    fn unacknowledged_messages_are_replayed_again_after_a_rewind() {
+        let ttd = TempTedgeDir::new();
+        let mut spool = DiskSpool::open(config(&ttd, 1024, 3600)).unwrap();
+        let now = SystemTime::now();
+
+        spool
+            .push("s/us", &publish("a"), &no_properties(), now)
+            .unwrap();
+        spool
+            .push("s/us", &publish("b"), &no_properties(), now)
+            .unwrap();
+        spool
+            .push("s/us", &publish("c"), &no_properties(), now)
+            .unwrap();
+        let first = spool.next_to_replay(now).unwrap();
+        spool.next_to_replay(now).unwrap();
+        spool.remove(first.id);
+
+        // The connection has been lost before "b" was acknowledged
+        spool.rewind();
+
+        assert_eq!(replayed_payload(&mut spool, now), Some("b".to_string()));
+        assert_eq!(replayed_payload(&mut spool, now), Some("c".to_string()));
+        assert_eq!(replayed_payload(&mut spool, now), None);
+    }
+
+    #[test]
+    fn oldest_messages_are_dropped_when_the_size_limit_is_exceeded() {
+        let ttd = TempTedgeDir::new();
+        // Room for two messages of the form: header + "s/us" + one byte payload
+        let message_size = (HEADER_SIZE + 5) as u64;
+        let mut spool = DiskSpool::open(config(&ttd, 2 * message_size, 3600)).unwrap();
+        let now = SystemTime::now();
+
+        spool
+            .push("s/us", &publish("a"), &no_properties(), now)
+            .unwrap();
+        spool
+            .push("s/us", &publish("b"), &no_properties(), now)
+            .unwrap();
+        spool
+            .push("s/us", &publish("c"), &no_properties(), now)
+            .unwrap();
+
+        assert_eq!(spool.len(), 2);
+        assert_eq!(replayed_payload(&mut spool, now), Some("b".to_string()));
+        assert_eq!(replayed_payload(&mut spool, now), Some("c".to_string()));
+    }
+
+    #[test]
+    fn expired_messages_are_dropped() {
+        let ttd = TempTedgeDir::new();
+        let mut spool = DiskSpool::open(config(&ttd, 1024, 60)).unwrap();
+        let now = SystemTime::now();
+
+        spool
+            .push(
+                "s/us",
+                &publish("old"),
+                &no_properties(),
+                now - Duration::from_secs(120),
+            )
+            .unwrap();
+        spool
+            .push("s/us", &publish("new"), &no_properties(), now)
+            .unwrap();
+
+        assert_eq!(replayed_payload(&mut spool, now), Some("new".to_string()));
+        assert_eq!(spool.len(), 1);
+    }
+
+    fn config(ttd: &TempTedgeDir, max_size: u64, max_age_secs: u64) -> SpoolConfig {
+        SpoolConfig {
+            dir: ttd.utf8_path().join("spool"),
+            max_size,
+            max_age: Duration::from_secs(max_age_secs),
+        }
+    }
+
+    fn no_properties() -> MqttProperties {
+        MqttProperties::default()
+    }
+
+    fn publish(payload: &str) -> Publish {
+        Publish::new("c8y/s/us", QoS::AtLeastOnce, payload)
+    }
+
+    fn replayed_payload(spool: &mut DiskSpool, now: SystemTime) -> Option<String> {
+        let message = spool.next_to_replay(now)?;
+        Some(String::from_utf8(message.publish.payload.to_vec()).unwrap())
+    }
+}
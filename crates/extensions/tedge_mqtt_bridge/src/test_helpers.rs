@@ -3,7 +3,7 @@ use anyhow::bail;
 use bytes::Bytes;
 use core::panic;
 use futures::future::pending;
-use rumqttc::ClientError;
+use mqtt_channel::MqttProperties;
 use rumqttc::ConnectionError;
 use rumqttc::Event;
 use rumqttc::Incoming;
@@ -24,6 +24,7 @@ use std::time::Instant;
 use tokio::sync::mpsc;
 use tokio::sync::Mutex as TokioMutex;
 
+use crate::mqtt_logging::ClientError;
 use crate::MqttAck;
 use crate::MqttClient;
 use crate::MqttEvents;
@@ -93,24 +94,36 @@ pub trait AllProcessed {
 /// A fixed stream of events
 pub struct FixedEventStream {
     events: Arc<Mutex<VecDeque<EventRes>>>,
+    /// The MQTT 5 properties of all the received messages
+    properties: Option<MqttProperties>,
 }
 
 impl FixedEventStream {
     fn next_event(&self) -> Option<EventRes> {
         self.events.lock().unwrap().pop_front()
     }
+
+    pub fn with_properties(self, properties: MqttProperties) -> Self {
+        Self {
+            properties: Some(properties),
+            ..self
+        }
+    }
 }
 
 impl<I: Into<VecDeque<EventRes>>> From<I> for FixedEventStream {
     fn from(value: I) -> Self {
         Self {
             events: Arc::new(Mutex::new(value.into())),
+            properties: None,
         }
     }
 }
 
 #[async_trait::async_trait]
 impl MqttEvents for FixedEventStream {
+    type Request = Request;
+
     async fn poll(&mut self) -> Result<Event, ConnectionError> {
         if let Some(event) = self.next_event() {
             event
@@ -119,6 +132,10 @@ impl MqttEvents for FixedEventStream {
         }
     }
 
+    fn take_properties(&mut self) -> Option<MqttProperties> {
+        self.properties.clone()
+    }
+
     fn take_pending(&mut self) -> VecDeque<Request> {
         <_>::default()
     }
@@ -151,7 +168,7 @@ pub struct BlockingSubscribeClient;
 
 #[async_trait::async_trait]
 impl MqttAck for BlockingSubscribeClient {
-    async fn ack(&self, _publish: &Publish) -> Result<(), rumqttc::ClientError> {
+    async fn ack(&self, _publish: &Publish) -> Result<(), ClientError> {
         Ok(())
     }
 }
@@ -162,7 +179,14 @@ impl MqttClient for BlockingSubscribeClient {
         pending().await
     }
 
-    async fn publish(&self, _: String, _: QoS, _: bool, _: Bytes) -> Result<(), ClientError> {
+    async fn publish(
+        &self,
+        _: String,
+        _: QoS,
+        _: bool,
+        _: Bytes,
+        _: MqttProperties,
+    ) -> Result<(), ClientError> {
         unimplemented!()
     }
 }
@@ -172,6 +196,7 @@ pub enum Action {
     SubscribeMany(Vec<SubscribeFilter>),
     Ack(Publish),
     Publish(Publish),
+    PublishWithProperties(Publish, MqttProperties),
 }
 
 #[derive(Default, Debug, Clone)]
@@ -199,7 +224,7 @@ impl ActionLogger {
 
 #[async_trait::async_trait]
 impl MqttAck for ActionLogger {
-    async fn ack(&self, publish: &Publish) -> Result<(), rumqttc::ClientError> {
+    async fn ack(&self, publish: &Publish) -> Result<(), ClientError> {
         self.log(Action::Ack(publish.clone()));
         Ok(())
     }
@@ -217,10 +242,15 @@ impl MqttClient for ActionLogger {
         qos: QoS,
         retain: bool,
         payload: Bytes,
+        properties: MqttProperties,
     ) -> Result<(), ClientError> {
         let mut publish = Publish::new(topic, qos, payload);
         publish.retain = retain;
-        self.log(Action::Publish(publish));
+        if properties.is_empty() {
+            self.log(Action::Publish(publish));
+        } else {
+            self.log(Action::PublishWithProperties(publish, properties));
+        }
         Ok(())
     }
 }
@@ -281,6 +311,8 @@ impl AllProcessed for ChannelEvents {
 
 #[async_trait::async_trait]
 impl MqttEvents for ChannelEvents {
+    type Request = Request;
+
     async fn poll(&mut self) -> Result<Event, ConnectionError> {
         let mut inner = self.0.lock().await;
         if !inner.connack_sent {
@@ -354,7 +386,7 @@ impl ChannelClient {
 
 #[async_trait::async_trait]
 impl MqttAck for ChannelClient {
-    async fn ack(&self, _publish: &Publish) -> Result<(), rumqttc::ClientError> {
+    async fn ack(&self, _publish: &Publish) -> Result<(), ClientError> {
         Ok(())
     }
 }
@@ -371,6 +403,7 @@ impl MqttClient for ChannelClient {
         qos: QoS,
         _: bool,
         payload: Bytes,
+        _: MqttProperties,
     ) -> Result<(), ClientError> {
         self.count_in_progress.fetch_add(1, Ordering::SeqCst);
         self.tx
@@ -1,4 +1,5 @@
 use crate::BridgeRule;
+use crate::RuleOptions;
 use rumqttc::matches;
 use std::borrow::Cow;
 use tracing::warn;
@@ -10,18 +11,36 @@ pub fn matches_ignore_dollar_prefix(topic: &str, filter: &str) -> bool {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(test, derive(Default))]
 pub struct TopicConverter(pub Vec<BridgeRule>);
 
 impl TopicConverter {
     pub fn convert_topic<'a>(&'a self, topic: &'a str) -> Option<Cow<'a, str>> {
+        self.convert(topic).map(|(topic, _)| topic)
+    }
+
+    /// Converts the topic using the first matching rule, returning the options of that rule
+    pub fn convert<'a>(&'a self, topic: &'a str) -> Option<(Cow<'a, str>, &'a RuleOptions)> {
         self.0
             .iter()
-            .find_map(|rule| rule.apply(topic))
+            .find_map(|rule| {
+                rule.apply(topic)
+                    .map(|converted| (converted, rule.options()))
+            })
             .or_else(|| {
                 warn!("Failed to convert {topic:?}");
                 None
             })
     }
+
+    /// Returns the topic that is converted into the given topic, if any
+    pub fn source_topic(&self, topic: &str) -> Option<String> {
+        self.0.iter().find_map(|rule| {
+            let suffix = topic.strip_prefix(rule.prefix_to_add())?;
+            let source = format!("{}{suffix}", rule.prefix_to_remove());
+            let converted = self.0.iter().find_map(|rule| rule.apply(&source))?;
+            (converted == topic).then_some(source)
+        })
+    }
 }
@@ -10,6 +10,8 @@ use tedge_mqtt_bridge::MqttBridgeActorBuilder;
 use test_broker::TestMqttBroker;
 use tracing::warn;
 
+// Not all the test broker features are used by each test crate
+#[allow(dead_code)]
 mod test_broker;
 
 async fn new_broker() -> Arc<TestMqttBroker> {
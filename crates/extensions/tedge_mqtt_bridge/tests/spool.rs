@@ -0,0 +1,139 @@
+use mqttbytes::QoS;
+use rumqttc::MqttOptions;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tedge_config::TEdgeConfig;
+use tedge_mqtt_bridge::BridgeConfig;
+use tedge_mqtt_bridge::MqttBridgeActorBuilder;
+use tedge_test_utils::fs::TempTedgeDir;
+use test_broker::TestMqttBroker;
+
+// Not all the test broker features are used by each test crate
+#[allow(dead_code)]
+mod test_broker;
+
+async fn new_broker() -> Arc<TestMqttBroker> {
+    let broker = Arc::new(TestMqttBroker::new().await.unwrap());
+    start_broker(&broker);
+    broker
+}
+
+fn start_broker(broker: &Arc<TestMqttBroker>) {
+    let broker = broker.clone();
+    tokio::spawn(async move { broker.start().await });
+}
+
+async fn start_mqtt_bridge(local_port: u16, cloud_port: u16, data_dir: &TempTedgeDir) {
+    let mut rules = BridgeConfig::new();
+    rules.forward_from_local("s/us", "c8y/", "").unwrap();
+    rules.forward_from_remote("s/ds", "c8y/", "").unwrap();
+
+    let cloud_config = MqttOptions::new("cloud-device", "127.0.0.1", cloud_port);
+    let service_name = "tedge-mapper-test";
+    let health_topic = format!("te/device/main/service/{service_name}/status/health")
+        .as_str()
+        .try_into()
+        .unwrap();
+    MqttBridgeActorBuilder::new(
+        &tedge_mqtt_config(local_port, data_dir),
+        service_name,
+        &health_topic,
+        rules,
+        cloud_config,
+        None,
+        // No effective limit: exercise the bridge's existing forwarding behaviour.
+        268_435_455,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn messages_are_spooled_while_the_cloud_is_unreachable() {
+    std::env::set_var("RUST_LOG", "tedge_mqtt_bridge=debug,info");
+    let _ = env_logger::try_init();
+    let data_dir = TempTedgeDir::new();
+    let local_broker = new_broker().await;
+    // The cloud broker is not started, hence the bridge cannot connect to the cloud
+    let cloud_broker = Arc::new(TestMqttBroker::new().await.unwrap());
+
+    start_mqtt_bridge(local_broker.port(), cloud_broker.port(), &data_dir).await;
+    local_broker.wait_until_subscribed("c8y/s/us").await;
+
+    for payload in ["1", "2", "3"] {
+        local_broker
+            .publish_to_clients("c8y/s/us", payload.as_bytes(), QoS::AtLeastOnce)
+            .await
+            .unwrap();
+    }
+
+    // The messages are acknowledged on the local broker, even if not yet delivered to the cloud
+    local_broker.wait_until_all_messages_acked().await;
+
+    // Once the cloud is reachable, the spooled messages are delivered in order
+    start_broker(&cloud_broker);
+    for payload in ["1", "2", "3"] {
+        let message = cloud_broker.next_message_matching("s/us").await;
+        assert_eq!(&message.payload[..], payload.as_bytes());
+    }
+}
+
+#[tokio::test]
+async fn spooled_messages_are_replayed_again_when_the_cloud_connection_is_lost() {
+    std::env::set_var("RUST_LOG", "tedge_mqtt_bridge=debug,info");
+    let _ = env_logger::try_init();
+    let data_dir = TempTedgeDir::new();
+    let local_broker = new_broker().await;
+    let cloud_broker = Arc::new(TestMqttBroker::new().await.unwrap());
+
+    start_mqtt_bridge(local_broker.port(), cloud_broker.port(), &data_dir).await;
+    local_broker.wait_until_subscribed("c8y/s/us").await;
+
+    for payload in ["1", "2", "3"] {
+        local_broker
+            .publish_to_clients("c8y/s/us", payload.as_bytes(), QoS::AtLeastOnce)
+            .await
+            .unwrap();
+    }
+    local_broker.wait_until_all_messages_acked().await;
+
+    // The spooled messages are replayed, but the cloud drops before acknowledging them
+    cloud_broker.disable_acknowledgements().await;
+    start_broker(&cloud_broker);
+    for payload in ["1", "2", "3"] {
+        let message = cloud_broker.next_message_matching("s/us").await;
+        assert_eq!(&message.payload[..], payload.as_bytes());
+    }
+    cloud_broker.disconnect_clients_abruptly().await;
+    cloud_broker.enable_acknowledgements().await;
+
+    // Once reconnected, the unacknowledged messages are delivered again
+    let mut delivered = BTreeSet::new();
+    while delivered.len() < 3 {
+        let message = cloud_broker.next_message_matching("s/us").await;
+        delivered.insert(String::from_utf8(message.payload.to_vec()).unwrap());
+    }
+    assert_eq!(delivered, BTreeSet::from(["1", "2", "3"].map(String::from)));
+
+    // And removed from the spool once acknowledged
+    let spool_dir = data_dir.path().join("bridge").join("tedge-mapper-test");
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while std::fs::read_dir(&spool_dir).unwrap().next().is_some() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("timed out waiting for the spool to be emptied");
+}
+
+fn tedge_mqtt_config(mqtt_port: u16, data_dir: &TempTedgeDir) -> TEdgeConfig {
+    TEdgeConfig::load_toml_str(&format!(
+        "
+    data.path = \"{}\"
+    mqtt.client.port = {mqtt_port}
+    mqtt.bridge.reconnect_policy.initial_interval = \"0s\"
+    mqtt.bridge.spool.enable = true
+    ",
+        data_dir.path().display()
+    ))
+}
@@ -194,6 +194,25 @@ impl TestMqttBroker {
         .expect("timed out waiting for message")
     }
 
+    /// Waits until a client has subscribed to a filter matching the given topic
+    pub async fn wait_until_subscribed(&self, topic: &str) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                {
+                    let subscriptions = self.subscriptions.lock().await;
+                    if subscriptions.iter().any(|(filter, clients)| {
+                        mqttbytes::matches(topic, filter) && !clients.is_empty()
+                    }) {
+                        return;
+                    }
+                }
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("timed out waiting for a subscription")
+    }
+
     pub async fn wait_until_all_messages_acked(&self) {
         let mut last_acks = None;
         let res = tokio::time::timeout(Duration::from_secs(5), async {
@@ -6,17 +6,20 @@ pub mod trie;
 
 use async_trait::async_trait;
 pub use mqtt_channel::deserialize_qos;
+use mqtt_channel::is_shared_subscription;
 pub use mqtt_channel::publish_packet_size;
 pub use mqtt_channel::serialize_qos;
 pub use mqtt_channel::DebugPayload;
 pub use mqtt_channel::MqttError;
 pub use mqtt_channel::MqttMessage;
+pub use mqtt_channel::MqttProperties;
 pub use mqtt_channel::QoS;
 use mqtt_channel::SinkExt;
 use mqtt_channel::StreamExt;
 use mqtt_channel::SubscriberOps;
 pub use mqtt_channel::Topic;
 pub use mqtt_channel::TopicFilter;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::convert::Infallible;
 use std::sync::Arc;
@@ -24,12 +27,15 @@ use std::time::Duration;
 use std::time::Instant;
 use tedge_actors::fan_in_message_type;
 use tedge_actors::futures::channel::mpsc;
+use tedge_actors::futures::channel::oneshot;
+use tedge_actors::register_probe;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
 use tedge_actors::ChannelError;
 use tedge_actors::ClientMessageBox;
 use tedge_actors::CloneSender;
 use tedge_actors::DynSender;
+use tedge_actors::MailboxProbe;
 use tedge_actors::MessageReceiver;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
@@ -57,6 +63,7 @@ pub struct MqttActorBuilder {
     trie: TrieService,
     current_id: usize,
     subscription_diff: SubscriptionDiff,
+    shared_subscriptions: Vec<(ClientId, String)>,
     dynamic_connect_sender: mpsc::Sender<(
         TrieInsertRequest,
         Box<dyn CloneSender<MqttMessage> + 'static>,
@@ -76,6 +83,7 @@ impl MqttRequest {
 struct InputCombiner {
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
     request_receiver: mpsc::Receiver<MqttRequest>,
+    probe: MailboxProbe,
 }
 
 impl MessageSource<MqttMessage, &mut DynSubscriptions> for MqttActorBuilder {
@@ -230,6 +238,7 @@ impl MessageReceiver<MqttRequest> for InputCombiner {
                 Err(runtime_request)
             }
             Some(request) = self.request_receiver.next() => {
+                self.probe.message_received();
                 Ok(Some(request))
             }
             else => Ok(None)
@@ -257,6 +266,7 @@ impl MqttActorBuilder {
         let input_receiver = InputCombiner {
             signal_receiver,
             request_receiver,
+            probe: MailboxProbe::for_mailbox("MQTT", 10),
         };
 
         MqttActorBuilder {
@@ -267,6 +277,7 @@ impl MqttActorBuilder {
             request_sender,
             trie,
             subscription_diff: SubscriptionDiff::empty(),
+            shared_subscriptions: Vec::new(),
             current_id: 0,
             dynamic_connect_sender,
             dynamic_connect_receiver,
@@ -274,6 +285,7 @@ impl MqttActorBuilder {
     }
 
     pub(crate) fn build_actor(self) -> MqttActor {
+        register_probe(&self.input_receiver.probe);
         let mut topic_filter = TopicFilter::empty();
         for pattern in &self.subscription_diff.subscribe {
             topic_filter.try_add(pattern).unwrap();
@@ -289,6 +301,7 @@ impl MqttActorBuilder {
         MqttActor::new(
             mqtt_config,
             base_config,
+            self.shared_subscriptions,
             self.input_receiver,
             self.subscriber_addresses,
             self.trie.builder(),
@@ -321,7 +334,11 @@ impl MqttActorBuilder {
     ) -> ClientId {
         let client_id = self.add_new_subscriber(peer.get_sender());
         for topic in topics.patterns() {
-            self.subscription_diff += self.trie.trie.insert(topic, client_id);
+            if is_shared_subscription(topic) {
+                self.shared_subscriptions.push((client_id, topic.clone()));
+            } else {
+                self.subscription_diff += self.trie.trie.insert(topic, client_id);
+            }
         }
         client_id
     }
@@ -339,7 +356,11 @@ impl MqttActorBuilder {
 
 impl MessageSink<MqttMessage> for MqttActorBuilder {
     fn get_sender(&self) -> DynSender<MqttMessage> {
-        Box::new(self.request_sender.clone())
+        Box::new(
+            self.input_receiver
+                .probe
+                .sender(self.request_sender.clone()),
+        )
     }
 }
 
@@ -420,7 +441,10 @@ pub struct SubscriptionRequest {
 
 impl MessageSink<MqttRequest> for MqttActorBuilder {
     fn get_sender(&self) -> DynSender<MqttRequest> {
-        self.request_sender.clone().into()
+        self.input_receiver
+            .probe
+            .sender(self.request_sender.clone())
+            .into()
     }
 }
 
@@ -446,6 +470,30 @@ pub struct FromPeers {
     input_receiver: InputCombiner,
     base_config: mqtt_channel::Config,
     subscriptions: ClientMessageBox<TrieRequest, TrieResponse>,
+    shared_subscriptions: SharedSubscriptions,
+}
+
+/// The shared subscriptions of the peers, each served by a dedicated MQTT connection
+///
+/// When several subscriptions of a connection match a topic, the broker delivers one copy
+/// of the message per subscription, nothing telling which copy is for a shared subscription.
+/// With a dedicated connection per shared subscription, the messages dispatched by the broker
+/// to a group member are only routed to the shared subscriber,
+/// the peers with overlapping plain subscriptions receiving their own copies.
+struct SharedSubscriptions {
+    /// The shared subscriptions created by the builder, the connections being opened on start
+    pending: Vec<(ClientId, String)>,
+    /// Dropping the sender closes the connection of a shared subscription
+    connections: HashMap<(ClientId, String), oneshot::Sender<()>>,
+}
+
+impl SharedSubscriptions {
+    fn new(pending: Vec<(ClientId, String)>) -> Self {
+        SharedSubscriptions {
+            pending,
+            connections: HashMap::new(),
+        }
+    }
 }
 
 pub struct ToPeers {
@@ -471,6 +519,10 @@ impl FromPeers {
         client: impl SubscriberOps + Clone + Send + 'static,
         rx_to_peers: &mut mpsc::UnboundedReceiver<MqttRequest>,
     ) -> Result<(), RuntimeError> {
+        for (client_id, pattern) in std::mem::take(&mut self.shared_subscriptions.pending) {
+            self.subscribe_shared(client_id, pattern, tx_to_peers.clone());
+        }
+
         while let Ok(Some(message)) = self.try_recv(rx_to_peers).await {
             match message {
                 MqttRequest::Publish(message) => {
@@ -479,7 +531,8 @@ impl FromPeers {
                         .await
                         .map_err(Box::new)?;
                 }
-                MqttRequest::Subscribe(request) => {
+                MqttRequest::Subscribe(mut request) => {
+                    self.update_shared_subscriptions(&mut request, tx_to_peers);
                     let TrieResponse::Diff(diff) = self
                         .subscriptions
                         .await_response(TrieRequest::SubscriptionRequest(request.clone()))
@@ -551,6 +604,85 @@ impl FromPeers {
         Ok(())
     }
 
+    /// Serve the shared subscriptions of a request with dedicated connections
+    ///
+    /// The request is left with the plain subscriptions, which are managed using the trie.
+    fn update_shared_subscriptions(
+        &mut self,
+        request: &mut SubscriptionRequest,
+        tx_to_peers: &mpsc::UnboundedSender<(ClientId, MqttMessage)>,
+    ) {
+        let client_id = request.client_id;
+
+        let (shared, plain): (HashSet<String>, HashSet<String>) =
+            std::mem::take(&mut request.diff.unsubscribe)
+                .into_iter()
+                .partition(|pattern| is_shared_subscription(pattern));
+        request.diff.unsubscribe = plain;
+        for pattern in shared {
+            self.shared_subscriptions
+                .connections
+                .remove(&(client_id, pattern));
+        }
+
+        let (shared, plain): (HashSet<String>, HashSet<String>) =
+            std::mem::take(&mut request.diff.subscribe)
+                .into_iter()
+                .partition(|pattern| is_shared_subscription(pattern));
+        request.diff.subscribe = plain;
+        for pattern in shared {
+            self.subscribe_shared(client_id, pattern, tx_to_peers.clone());
+        }
+    }
+
+    /// Open a connection dedicated to a shared subscription,
+    /// forwarding the messages received on this connection to the subscriber only
+    fn subscribe_shared(
+        &mut self,
+        client_id: ClientId,
+        pattern: String,
+        mut sender: mpsc::UnboundedSender<(ClientId, MqttMessage)>,
+    ) {
+        let key = (client_id, pattern.clone());
+        if self.shared_subscriptions.connections.contains_key(&key) {
+            return;
+        }
+        let (stop_sender, mut stop_receiver) = oneshot::channel::<()>();
+        self.shared_subscriptions
+            .connections
+            .insert(key, stop_sender);
+
+        tracing::info!(target: "MQTT sub", "{pattern}");
+        let config = self
+            .base_config
+            .clone()
+            .with_subscriptions(TopicFilter::new_unchecked(&pattern));
+        tokio::spawn(
+            async move {
+                let mut conn = match mqtt_channel::Connection::new(&config).await {
+                    Ok(conn) => conn,
+                    Err(err) => {
+                        tracing::error!(target: "MQTT sub", "Failed to subscribe to {pattern}: {err}");
+                        return;
+                    }
+                };
+                loop {
+                    tokio::select! {
+                        _ = &mut stop_receiver => break,
+                        message = conn.received.next() => {
+                            let Some(message) = message else { break };
+                            if SinkExt::send(&mut sender, (client_id, message)).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                conn.close().await;
+            }
+            .instrument(tracing::Span::current()),
+        );
+    }
+
     fn forward_retain_messages_to<Packet: Send + 'static>(
         &self,
         mut sender: mpsc::UnboundedSender<Packet>,
@@ -677,9 +809,11 @@ pub struct MqttActor {
 }
 
 impl MqttActor {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         mqtt_config: mqtt_channel::Config,
         base_config: mqtt_channel::Config,
+        shared_subscriptions: Vec<(ClientId, String)>,
         input_receiver: InputCombiner,
         peer_senders: Vec<DynSender<MqttMessage>>,
         mut trie_service: ServerActorBuilder<TrieService, Sequential>,
@@ -695,6 +829,7 @@ impl MqttActor {
                 input_receiver,
                 base_config,
                 subscriptions: ClientMessageBox::new(&mut trie_service),
+                shared_subscriptions: SharedSubscriptions::new(shared_subscriptions),
             },
             to_peers: ToPeers {
                 peer_senders,
@@ -1029,6 +1164,7 @@ mod unit_tests {
             let input_combiner = InputCombiner {
                 signal_receiver: sig_rx,
                 request_receiver: req_rx,
+                probe: MailboxProbe::default(),
             };
 
             let mut ts = TrieService::with_default_subscriptions(default_subscriptions);
@@ -1036,6 +1172,7 @@ mod unit_tests {
                 input_receiver: input_combiner,
                 base_config: <_>::default(),
                 subscriptions: ClientMessageBox::new(&mut ts),
+                shared_subscriptions: SharedSubscriptions::new(vec![]),
             };
             let mut sent_to_clients = HashMap::new();
             let mut peer_senders = Vec::new();
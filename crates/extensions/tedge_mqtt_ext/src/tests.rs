@@ -316,6 +316,61 @@ async fn client_with_overlapping_subscriptions() {
     }
 }
 
+#[tokio::test]
+async fn shared_and_plain_subscribers_with_overlapping_subscriptions() {
+    let p = uniquify!("");
+    let broker = mqtt_tests::test_mqtt_broker();
+    let mqtt_config = MqttConfig::default().with_port(broker.port);
+    let mut mqtt = MqttActorBuilder::new(mqtt_config);
+    let mut publisher = SimpleMessageBoxBuilder::<MqttRequest, _>::new("publisher", 16);
+    publisher.connect_sink(NoConfig, &mqtt);
+
+    // A plain subscriber and a shared subscriber with overlapping subscriptions
+    let mut plain_subscriber =
+        MqttClientBuilder::new("plain", &TopicFilter::new_unchecked(&format!("{p}a/#")))
+            .with_connection(&mut mqtt)
+            .build();
+    let mut shared_subscriber = MqttClientBuilder::new(
+        "shared",
+        &TopicFilter::new_unchecked(&format!("$share/group/{p}a/+")),
+    )
+    .with_connection(&mut mqtt)
+    .build();
+
+    let mqtt = mqtt.build();
+    tokio::spawn(async move { mqtt.run().await.unwrap() });
+    let mut publisher = publisher.build();
+
+    // Give the dedicated connection of the shared subscription time to subscribe
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // A message published on the overlap is received once by each subscriber
+    let msg = MqttMessage::new(&Topic::new_unchecked(&format!("{p}a/b")), "hello");
+    publisher
+        .send(MqttRequest::Publish(msg.clone()))
+        .await
+        .unwrap();
+    assert_eq!(timeout(plain_subscriber.recv()).await.unwrap(), msg);
+    assert_eq!(timeout(shared_subscriber.recv()).await.unwrap(), msg);
+
+    // A message only matching the plain subscription is not routed to the shared subscriber
+    let msg = MqttMessage::new(&Topic::new_unchecked(&format!("{p}a/b/c")), "hello");
+    publisher
+        .send(MqttRequest::Publish(msg.clone()))
+        .await
+        .unwrap();
+    assert_eq!(timeout(plain_subscriber.recv()).await.unwrap(), msg);
+
+    // And no duplicates are received
+    for subscriber in [&mut plain_subscriber, &mut shared_subscriber] {
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), subscriber.recv())
+                .await
+                .is_err()
+        );
+    }
+}
+
 #[tokio::test]
 async fn dynamic_subscribers_receive_retain_messages() {
     let t_ab = uniquify!("a/b");
@@ -521,6 +576,7 @@ async fn retrieve_retain_does_not_forward_last_will() {
             payload: "should not be sent".to_owned().into(),
             qos: QoS::AtLeastOnce,
             retain: true,
+            properties: Default::default(),
         });
     let mut mqtt = MqttActorBuilder::new(mqtt_config);
 
@@ -1,12 +1,21 @@
 use crate::WatchError;
 use crate::WatchEvent;
 use crate::WatchRequest;
+use axum::extract::Path;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::Router;
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
+use futures::StreamExt;
 use std::collections::HashMap;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::fs::PermissionsExt;
 use std::os::unix::prelude::ExitStatusExt;
 use std::process::ExitStatus;
 use std::process::Stdio;
+use std::time::Duration;
 use tedge_actors::Actor;
 use tedge_actors::CloneSender;
 use tedge_actors::DynSender;
@@ -16,19 +25,34 @@ use tedge_actors::NullSender;
 use tedge_actors::RuntimeError;
 use tedge_actors::Sender;
 use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
 use tokio::io::BufReader;
+use tokio::net::TcpListener;
+use tokio::net::UnixListener;
+use tokio::net::UnixStream;
 use tokio::process::Child;
 use tokio::process::ChildStderr;
 use tokio::process::ChildStdout;
 use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio::task::JoinSet;
+use tokio_util::codec::FramedRead;
+use tokio_util::codec::LinesCodec;
+use tokio_util::codec::LinesCodecError;
+use tracing::warn;
 
 type ClientId = u32;
 type Topic = String;
 type CommandLine = String;
 
+/// The maximum length of a line received over a socket, longer lines being discarded
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
 pub struct Watcher {
     /// The collection of commands watched by each client
     processes: HashMap<(ClientId, Topic), (CommandLine, Child)>,
+    /// The collection of sockets listened to on behalf of each client
+    listeners: HashMap<(ClientId, Topic), Listener>,
     /// The channels to send events to clients identified by their slot
     event_senders: Vec<DynSender<WatchEvent>>,
     /// Channel used to send requests on behalf of a client
@@ -48,6 +72,9 @@ impl Actor for Watcher {
             let topic = match &request {
                 WatchRequest::WatchFile { topic, .. }
                 | WatchRequest::WatchCommand { topic, .. }
+                | WatchRequest::WatchUnixSocket { topic, .. }
+                | WatchRequest::WatchTcp { topic, .. }
+                | WatchRequest::WatchHttp { topic, .. }
                 | WatchRequest::UnWatch { topic } => topic.clone(),
             };
             let result = match request {
@@ -59,6 +86,15 @@ impl Actor for Watcher {
                     command,
                     cwd,
                 } => self.watch_command(client, topic, command, cwd).await,
+                WatchRequest::WatchUnixSocket { topic, path } => {
+                    self.watch_unix_socket(client, topic, path).await
+                }
+                WatchRequest::WatchTcp { topic, address } => {
+                    self.watch_tcp(client, topic, address).await
+                }
+                WatchRequest::WatchHttp { topic, address } => {
+                    self.watch_http(client, topic, address).await
+                }
                 WatchRequest::UnWatch { topic } => self.unwatch(client, topic).await,
             };
             if let Err(error) = result {
@@ -79,6 +115,7 @@ impl Watcher {
     ) -> Self {
         Watcher {
             processes: HashMap::new(),
+            listeners: HashMap::new(),
             event_senders,
             request_sender,
             request_receiver,
@@ -117,6 +154,140 @@ impl Watcher {
         Ok(())
     }
 
+    pub async fn watch_unix_socket(
+        &mut self,
+        client: u32,
+        topic: Topic,
+        path: Utf8PathBuf,
+    ) -> Result<(), WatchError> {
+        self.stop_listener(client, &topic).await;
+        // A socket file left over by a previous run prevents to bind the socket,
+        // but a socket still used by another process must not be stolen
+        if std::fs::metadata(&path).is_ok_and(|metadata| metadata.file_type().is_socket())
+            && UnixStream::connect(&path).await.is_err()
+        {
+            let _ = std::fs::remove_file(&path);
+        }
+        let listener = UnixListener::bind(&path).map_err(|err| WatchError::ListenFailed {
+            address: path.to_string(),
+            error: err.to_string(),
+        })?;
+        // Only the owner is allowed to send messages, whatever the umask
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).map_err(|err| {
+            WatchError::ListenFailed {
+                address: path.to_string(),
+                error: err.to_string(),
+            }
+        })?;
+
+        let task = self.spawn_line_listener(client, topic.clone(), LineListener::Unix(listener));
+        let socket = Some(path);
+        self.listeners
+            .insert((client, topic), Listener { task, socket });
+        Ok(())
+    }
+
+    pub async fn watch_tcp(
+        &mut self,
+        client: u32,
+        topic: Topic,
+        address: String,
+    ) -> Result<(), WatchError> {
+        self.stop_listener(client, &topic).await;
+        let listener = bind_tcp(&address).await?;
+        let task = self.spawn_line_listener(client, topic.clone(), LineListener::Tcp(listener));
+        self.listeners
+            .insert((client, topic), Listener { task, socket: None });
+        Ok(())
+    }
+
+    pub async fn watch_http(
+        &mut self,
+        client: u32,
+        topic: Topic,
+        address: String,
+    ) -> Result<(), WatchError> {
+        self.stop_listener(client, &topic).await;
+        let listener = bind_tcp(&address).await?;
+        let input = HttpInput {
+            topic: topic.clone(),
+            event_sender: self.client_sender(client),
+        };
+        let app = Router::new()
+            .route("/{*path}", post(forward_http_post))
+            .with_state(input);
+
+        let mut event_sender = self.client_sender(client);
+        let mut request_sender = self.request_sender.sender_clone();
+        let watch_topic = topic.clone();
+        let task = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+            end_of_stream(client, watch_topic, &mut event_sender, &mut request_sender).await;
+        });
+        self.listeners
+            .insert((client, topic), Listener { task, socket: None });
+        Ok(())
+    }
+
+    /// Stop listening on behalf of a client, releasing the socket before returning
+    async fn stop_listener(&mut self, client: ClientId, topic: &Topic) {
+        if let Some(listener) = self.listeners.remove(&(client, topic.clone())) {
+            listener.task.abort();
+            let _ = listener.task.await;
+            if let Some(socket) = listener.socket {
+                let _ = std::fs::remove_file(socket);
+            }
+        }
+    }
+
+    /// Spawn a task accepting connections and forwarding the lines received on each
+    fn spawn_line_listener(
+        &self,
+        client: ClientId,
+        topic: Topic,
+        listener: LineListener,
+    ) -> JoinHandle<()> {
+        let event_sender = self.client_sender(client);
+        tokio::spawn(async move {
+            // Dropped along this task, aborting all the connections when the listener is unwatched
+            let mut connections = JoinSet::new();
+            loop {
+                let stream = match listener.accept().await {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        // Accept errors are transient (e.g. too many open files): retry later
+                        warn!("Failed to accept a connection for {topic}: {err}");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                while connections.try_join_next().is_some() {}
+                let topic = topic.clone();
+                let mut event_sender = event_sender.sender_clone();
+                connections.spawn(async move {
+                    let codec = LinesCodec::new_with_max_length(MAX_LINE_LENGTH);
+                    let mut lines = FramedRead::new(stream, codec);
+                    while let Some(line) = lines.next().await {
+                        let line = match line {
+                            Ok(line) => line,
+                            Err(LinesCodecError::MaxLineLengthExceeded) => {
+                                warn!("Discarding a line longer than {MAX_LINE_LENGTH} bytes received for {topic}");
+                                continue;
+                            }
+                            Err(LinesCodecError::Io(_)) => break,
+                        };
+                        let _ = event_sender
+                            .send(WatchEvent::StdoutLine {
+                                topic: topic.clone(),
+                                line,
+                            })
+                            .await;
+                    }
+                });
+            }
+        })
+    }
+
     fn client_sender(&self, client: u32) -> DynSender<WatchEvent> {
         self.event_senders
             .get(client as usize)
@@ -139,15 +310,7 @@ impl Watcher {
                     })
                     .await;
             }
-            let _ = request_sender
-                .send((
-                    client,
-                    WatchRequest::UnWatch {
-                        topic: topic.clone(),
-                    },
-                ))
-                .await;
-            let _ = event_sender.send(WatchEvent::EndOfStream { topic }).await;
+            end_of_stream(client, topic, &mut event_sender, &mut request_sender).await;
         });
     }
 
@@ -168,6 +331,7 @@ impl Watcher {
     }
 
     pub async fn unwatch(&mut self, client: u32, topic: Topic) -> Result<(), WatchError> {
+        self.stop_listener(client, &topic).await;
         if let Some((command, mut child)) = self.processes.remove(&(client, topic)) {
             if let Ok(Some(status)) = child.try_wait() {
                 return check_status(&command, status);
@@ -184,6 +348,93 @@ impl Watcher {
     }
 }
 
+/// A socket listened to on behalf of a client
+struct Listener {
+    task: JoinHandle<()>,
+    /// The Unix socket file to be removed when the listener is stopped
+    socket: Option<Utf8PathBuf>,
+}
+
+enum LineListener {
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+impl LineListener {
+    async fn accept(&self) -> std::io::Result<Box<dyn AsyncRead + Send + Unpin>> {
+        match self {
+            LineListener::Unix(listener) => Ok(Box::new(listener.accept().await?.0)),
+            LineListener::Tcp(listener) => Ok(Box::new(listener.accept().await?.0)),
+        }
+    }
+}
+
+async fn bind_tcp(address: &str) -> Result<TcpListener, WatchError> {
+    TcpListener::bind(address)
+        .await
+        .map_err(|err| WatchError::ListenFailed {
+            address: address.to_string(),
+            error: err.to_string(),
+        })
+}
+
+/// Notify the client that a source is exhausted
+async fn end_of_stream(
+    client: ClientId,
+    topic: Topic,
+    event_sender: &mut DynSender<WatchEvent>,
+    request_sender: &mut DynSender<(ClientId, WatchRequest)>,
+) {
+    let _ = request_sender
+        .send((
+            client,
+            WatchRequest::UnWatch {
+                topic: topic.clone(),
+            },
+        ))
+        .await;
+    let _ = event_sender.send(WatchEvent::EndOfStream { topic }).await;
+}
+
+/// The state shared by the HTTP requests received on a listener
+struct HttpInput {
+    topic: Topic,
+    event_sender: DynSender<WatchEvent>,
+}
+
+impl Clone for HttpInput {
+    fn clone(&self) -> Self {
+        HttpInput {
+            topic: self.topic.clone(),
+            event_sender: self.event_sender.sender_clone(),
+        }
+    }
+}
+
+async fn forward_http_post(
+    State(mut input): State<HttpInput>,
+    Path(path): Path<String>,
+    body: String,
+) -> StatusCode {
+    // The path is used as a topic name, hence must not contain any wildcard
+    if !is_valid_topic_name(&path) {
+        return StatusCode::BAD_REQUEST;
+    }
+    let event = WatchEvent::HttpPost {
+        topic: input.topic,
+        path,
+        body,
+    };
+    match input.event_sender.send(event).await {
+        Ok(()) => StatusCode::OK,
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+fn is_valid_topic_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['+', '#', '\0'])
+}
+
 fn spawn(command: &str, cwd: &Utf8Path) -> Result<Child, WatchError> {
     let args = shell_words::split(command).map_err(|err| WatchError::InvalidCommand {
         command: command.to_string(),
@@ -26,6 +26,21 @@ pub enum WatchRequest {
         command: String,
         cwd: Utf8PathBuf,
     },
+    /// Listen on a Unix socket, forwarding each line received on a connection
+    WatchUnixSocket {
+        topic: String,
+        path: Utf8PathBuf,
+    },
+    /// Listen on a TCP address, forwarding each line received on a connection
+    WatchTcp {
+        topic: String,
+        address: String,
+    },
+    /// Listen for HTTP POST requests on a TCP address
+    WatchHttp {
+        topic: String,
+        address: String,
+    },
     UnWatch {
         topic: String,
     },
@@ -33,10 +48,28 @@ pub enum WatchRequest {
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum WatchEvent {
-    StdoutLine { topic: String, line: String },
-    StderrLine { topic: String, line: String },
-    EndOfStream { topic: String },
-    Error { topic: String, error: WatchError },
+    /// A line output by a watched process or received on a watched socket
+    StdoutLine {
+        topic: String,
+        line: String,
+    },
+    StderrLine {
+        topic: String,
+        line: String,
+    },
+    /// The body of an HTTP POST request, along the request path
+    HttpPost {
+        topic: String,
+        path: String,
+        body: String,
+    },
+    EndOfStream {
+        topic: String,
+    },
+    Error {
+        topic: String,
+        error: WatchError,
+    },
 }
 
 #[derive(thiserror::Error, Clone, Debug, Eq, PartialEq)]
@@ -55,6 +88,9 @@ pub enum WatchError {
 
     #[error("Failed to kill `{command}`: {error}")]
     TerminationFailed { command: String, error: String },
+
+    #[error("Failed to listen on {address}: {error}")]
+    ListenFailed { address: String, error: String },
 }
 
 pub use actor::command_output;
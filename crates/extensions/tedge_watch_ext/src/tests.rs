@@ -2,12 +2,18 @@ use crate::WatchActorBuilder;
 use crate::WatchEvent;
 use crate::WatchRequest;
 use camino::Utf8PathBuf;
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
 use tedge_actors::MessageReceiver;
 use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_actors::SimpleMessageBoxBuilder;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::net::UnixStream;
 
 #[tokio::test]
 async fn reading_process_stdout() {
@@ -89,6 +95,246 @@ async fn serving_independent_clients() {
     assert_eq!(&topic, "seq");
 }
 
+#[tokio::test]
+async fn reading_lines_from_tcp_connections() {
+    let mut actor = launch_watcher(1).pop().unwrap();
+    let address = free_local_address();
+
+    actor
+        .send(WatchRequest::WatchTcp {
+            topic: "tcp".to_string(),
+            address: address.clone(),
+        })
+        .await
+        .unwrap();
+
+    let mut stream = retry(|| TcpStream::connect(address.clone())).await;
+    stream.write_all(b"hello\nworld\n").await.unwrap();
+
+    for expected in ["hello", "world"] {
+        let msg = actor.recv().await;
+        let Some(WatchEvent::StdoutLine { topic, line }) = msg else {
+            panic!("Expecting line from tcp connection, got: {:?}", msg);
+        };
+        assert_eq!(&topic, "tcp");
+        assert_eq!(line, expected);
+    }
+}
+
+#[tokio::test]
+async fn reading_lines_from_unix_socket() {
+    let mut actor = launch_watcher(1).pop().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = Utf8PathBuf::from_path_buf(dir.path().join("input.sock")).unwrap();
+
+    actor
+        .send(WatchRequest::WatchUnixSocket {
+            topic: "socket".to_string(),
+            path: path.clone(),
+        })
+        .await
+        .unwrap();
+
+    let mut stream = retry(|| UnixStream::connect(path.clone())).await;
+    stream.write_all(b"hello\n").await.unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let msg = actor.recv().await;
+    let Some(WatchEvent::StdoutLine { topic, line }) = msg else {
+        panic!("Expecting line from unix socket, got: {:?}", msg);
+    };
+    assert_eq!(&topic, "socket");
+    assert_eq!(line, "hello");
+
+    actor
+        .send(WatchRequest::UnWatch {
+            topic: "socket".to_string(),
+        })
+        .await
+        .unwrap();
+    retry(|| async {
+        match path.exists() {
+            true => Err("socket not removed"),
+            false => Ok(()),
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn discarding_too_long_lines() {
+    let mut actor = launch_watcher(1).pop().unwrap();
+    let address = free_local_address();
+
+    actor
+        .send(WatchRequest::WatchTcp {
+            topic: "tcp".to_string(),
+            address: address.clone(),
+        })
+        .await
+        .unwrap();
+
+    let mut stream = retry(|| TcpStream::connect(address.clone())).await;
+    let too_long = "x".repeat(100 * 1024);
+    stream.write_all(too_long.as_bytes()).await.unwrap();
+    stream.write_all(b"\nhello\n").await.unwrap();
+
+    let msg = actor.recv().await;
+    let Some(WatchEvent::StdoutLine { line, .. }) = msg else {
+        panic!("Expecting line from tcp socket, got: {:?}", msg);
+    };
+    assert_eq!(line, "hello");
+}
+
+#[tokio::test]
+async fn not_removing_a_socket_used_by_another_process() {
+    let mut actor = launch_watcher(1).pop().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = Utf8PathBuf::from_path_buf(dir.path().join("input.sock")).unwrap();
+    let _listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+    actor
+        .send(WatchRequest::WatchUnixSocket {
+            topic: "socket".to_string(),
+            path: path.clone(),
+        })
+        .await
+        .unwrap();
+
+    let msg = actor.recv().await;
+    let Some(WatchEvent::Error { topic, .. }) = msg else {
+        panic!("Expecting a listen error, got: {:?}", msg);
+    };
+    assert_eq!(&topic, "socket");
+    assert!(UnixStream::connect(&path).await.is_ok());
+}
+
+#[tokio::test]
+async fn receiving_http_posts() {
+    let mut actor = launch_watcher(1).pop().unwrap();
+    let address = free_local_address();
+
+    actor
+        .send(WatchRequest::WatchHttp {
+            topic: "http".to_string(),
+            address: address.clone(),
+        })
+        .await
+        .unwrap();
+
+    let mut stream = retry(|| TcpStream::connect(address.clone())).await;
+    let body = r#"{"temperature":21.5}"#;
+    let request = format!(
+        "POST /sensors/room-1 HTTP/1.1\r\nHost: {address}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let msg = actor.recv().await;
+    let Some(WatchEvent::HttpPost { topic, path, body }) = msg else {
+        panic!("Expecting http post, got: {:?}", msg);
+    };
+    assert_eq!(&topic, "http");
+    assert_eq!(path, "sensors/room-1");
+    assert_eq!(body, r#"{"temperature":21.5}"#);
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+}
+
+#[tokio::test]
+async fn rejecting_http_posts_on_invalid_topics() {
+    let mut actor = launch_watcher(1).pop().unwrap();
+    let address = free_local_address();
+
+    actor
+        .send(WatchRequest::WatchHttp {
+            topic: "http".to_string(),
+            address: address.clone(),
+        })
+        .await
+        .unwrap();
+
+    retry(|| TcpStream::connect(address.clone())).await;
+    for path in ["/sensors/+/temperature", "/sensors/%23"] {
+        let response = http_post(&address, path, "{}").await;
+        assert!(
+            response.starts_with("HTTP/1.1 400 Bad Request"),
+            "{response}"
+        );
+    }
+
+    // Only the valid request is forwarded
+    let response = http_post(&address, "/sensors/room-1", "{}").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    let msg = actor.recv().await;
+    let Some(WatchEvent::HttpPost { path, .. }) = msg else {
+        panic!("Expecting http post, got: {:?}", msg);
+    };
+    assert_eq!(path, "sensors/room-1");
+}
+
+async fn http_post(address: &str, path: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {address}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn reporting_listen_failures() {
+    let mut actor = launch_watcher(1).pop().unwrap();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    actor
+        .send(WatchRequest::WatchTcp {
+            topic: "tcp".to_string(),
+            address,
+        })
+        .await
+        .unwrap();
+
+    let msg = actor.recv().await;
+    let Some(WatchEvent::Error { topic, .. }) = msg else {
+        panic!("Expecting a listen error, got: {:?}", msg);
+    };
+    assert_eq!(&topic, "tcp");
+}
+
+/// Return a local address that is not yet used
+fn free_local_address() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+/// Retry an action until the watcher has processed the request
+async fn retry<F, Fut, T, E>(action: F) -> T
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+    E: std::fmt::Debug,
+{
+    let mut attempts = 50;
+    loop {
+        match action().await {
+            Ok(value) => return value,
+            Err(err) if attempts == 0 => panic!("Giving up: {err:?}"),
+            Err(_) => {
+                attempts -= 1;
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        }
+    }
+}
+
 fn launch_watcher(client_count: u32) -> Vec<SimpleMessageBox<WatchEvent, WatchRequest>> {
     let mut watcher = WatchActorBuilder::new();
     let clients = (0..=client_count)
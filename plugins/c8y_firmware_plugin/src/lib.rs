@@ -101,7 +101,8 @@ async fn run_with(
         &mut mqtt_actor,
         &mqtt_schema,
         &tedge_config.service,
-    );
+    )
+    .with_runtime(runtime.get_handle());
 
     // Instantiate firmware manager actor
     let firmware_manager_config =
@@ -118,9 +119,9 @@ async fn run_with(
 
     // Run the actors
     runtime.spawn(signal_actor).await?;
-    runtime.spawn(mqtt_actor).await?;
+    runtime.spawn_critical(mqtt_actor).await?;
     runtime.spawn(downloader_actor).await?;
-    runtime.spawn(firmware_actor).await?;
+    runtime.spawn_critical(firmware_actor).await?;
     runtime.spawn(health_actor).await?;
 
     runtime.run_to_completion().await?;
@@ -1,3 +1,4 @@
+use crate::timestamp::TimestampFormat;
 use serde::Deserialize;
 use std::collections::HashSet;
 use std::fs;
@@ -14,6 +15,9 @@ pub struct FileEntry {
     pub(crate) path: String,
     #[serde(rename = "type")]
     pub config_type: String,
+    /// Format of the line timestamps, used to filter the lines of the log files
+    #[serde(default)]
+    pub timestamp_format: Option<TimestampFormat>,
 }
 
 impl PartialEq for FileEntry {
@@ -68,10 +72,12 @@ mod tests {
             FileEntry {
                 path: "a/path".to_string(),
                 config_type: "type_one".to_string(),
+                timestamp_format: None,
             },
             FileEntry {
                 path: "some/path".to_string(),
                 config_type: "type_one".to_string(),
+                timestamp_format: None,
             },
         ];
         let logs_config = LogPluginConfig { files };
@@ -3,10 +3,12 @@ pub mod bin;
 mod config;
 mod error;
 mod log_utils;
+mod timestamp;
 
 pub use config::*;
 pub use error::*;
 pub use log_utils::*;
+pub use timestamp::*;
 
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
@@ -36,11 +38,17 @@ impl FileLogPlugin {
         &self,
         log_type: &str,
         since: Option<OffsetDateTime>,
-        _until: Option<OffsetDateTime>,
+        until: Option<OffsetDateTime>,
     ) -> Result<Utf8PathBuf, LogManagementError> {
         let date_from = since.unwrap_or(OffsetDateTime::UNIX_EPOCH);
 
-        let log_path = new_read_logs(&self.config.files, log_type, date_from, &self.tmp_dir)?;
+        let log_path = new_read_logs(
+            &self.config.files,
+            log_type,
+            date_from,
+            until,
+            &self.tmp_dir,
+        )?;
 
         Ok(log_path)
     }
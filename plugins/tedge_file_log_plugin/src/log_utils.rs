@@ -1,41 +1,62 @@
 use super::config::FileEntry;
 use super::error::LogRetrievalError;
+use crate::timestamp::TimestampFormat;
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
-use easy_reader::EasyReader;
+use flate2::read::MultiGzDecoder;
 use glob::glob;
+use glob::Pattern;
 use regex::Regex;
 use std::cmp::Reverse;
-use std::collections::VecDeque;
+use std::collections::HashSet;
 use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use time::OffsetDateTime;
 
+/// A log file selected for a log request
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    modification_time: OffsetDateTime,
+    wildcard_match: bool,
+    timestamp_format: Option<TimestampFormat>,
+}
+
 /// read any log file coming from `obj.log.log_type`
+///
+/// The lines of the log files with a `timestamp_format` are filtered to only keep those
+/// in the `date_from` / `date_to` time window.
 pub fn new_read_logs(
     files: &[FileEntry],
     log_type: &str,
     date_from: OffsetDateTime,
+    date_to: Option<OffsetDateTime>,
     tmp_dir: &Utf8Path,
 ) -> Result<Utf8PathBuf, LogRetrievalError> {
     //filter logs on type and date
     let logfiles_to_read = filter_logs(files, log_type, date_from)?;
 
     let temp_path = tmp_dir.join(format!("{log_type}-{}", rand::random::<u128>()));
-    let mut temp_file = File::create(&temp_path)?;
+    let mut temp_file = BufWriter::new(File::create(&temp_path)?);
+    let time_window = TimeWindow {
+        from: date_from,
+        to: date_to,
+        now: OffsetDateTime::now_utc(),
+    };
 
     for logfile in logfiles_to_read {
-        match read_log_content(logfile.as_path()) {
-            Ok(file_content) => {
-                temp_file.write_all(file_content.as_bytes())?;
-            }
-            Err(error) => {
-                temp_file.flush()?;
-                return Err(error);
-            }
-        };
+        let filter = logfile
+            .timestamp_format
+            .map(|format| (format, &time_window));
+        if let Err(error) = copy_log_content(&logfile.path, &mut temp_file, filter) {
+            temp_file.flush()?;
+            return Err(error);
+        }
     }
 
     temp_file.flush()?;
@@ -43,30 +64,72 @@ pub fn new_read_logs(
     Ok(temp_path)
 }
 
-fn read_log_content(logfile: &Path) -> Result<String, LogRetrievalError> {
-    let mut file_content = VecDeque::new();
-    let file = std::fs::File::open(logfile)?;
-    let file_name = format!(
-        "filename: {}\n",
-        logfile.file_name().unwrap().to_str().unwrap() // never fails because we check file exists
-    );
-    let reader = EasyReader::new(file);
-    match reader {
-        Ok(mut reader) => {
-            reader.eof();
-            while let Some(line) = reader.prev_line()? {
-                file_content.push_front(format!("{}\n", line));
+/// The time window of a log request
+struct TimeWindow {
+    from: OffsetDateTime,
+    to: Option<OffsetDateTime>,
+    now: OffsetDateTime,
+}
+
+impl TimeWindow {
+    fn contains(&self, time: OffsetDateTime) -> bool {
+        self.from <= time && self.to.is_none_or(|to| time <= to)
+    }
+}
+
+/// Copy the content of a log file, line by line, prefixed by the file name
+///
+/// If a timestamp format is provided, only the lines in the time window are copied.
+/// A line with no timestamp, say a stack trace, is kept along the previous line.
+/// The lines before the first timestamp are considered out of the time window,
+/// unless no line of the file has a timestamp: the whole file is then copied.
+/// Rotated files compressed with gzip are decompressed on the fly.
+///
+/// Nothing is copied, not even the file name, if no lines are selected.
+fn copy_log_content(
+    logfile: &Path,
+    output: &mut impl Write,
+    filter: Option<(TimestampFormat, &TimeWindow)>,
+) -> Result<(), LogRetrievalError> {
+    let file = File::open(logfile)?;
+    let mut reader: Box<dyn BufRead> = if logfile.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+    let file_name = logfile.file_name().unwrap().to_string_lossy(); // never fails because we check file exists
+
+    let mut header_written = false;
+    let mut keep_line = filter.is_none();
+    let mut timestamp_found = false;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            if filter.is_some() && !timestamp_found {
+                return copy_log_content(logfile, output, None);
             }
+            return Ok(());
+        }
 
-            file_content.push_front(file_name);
+        if let Some((format, window)) = filter {
+            if let Some(time) = format.parse_line(&String::from_utf8_lossy(&line), window.now) {
+                keep_line = window.contains(time);
+                timestamp_found = true;
+            }
+        }
+        if !keep_line {
+            continue;
+        }
 
-            let file_content = file_content
-                .iter()
-                .map(|x| x.to_string())
-                .collect::<String>();
-            Ok(file_content)
+        if !header_written {
+            writeln!(output, "filename: {file_name}")?;
+            header_written = true;
+        }
+        output.write_all(&line)?;
+        if !line.ends_with(b"\n") {
+            output.write_all(b"\n")?;
         }
-        Err(_err) => Ok(String::new()),
     }
 }
 
@@ -74,7 +137,7 @@ fn filter_logs(
     files: &[FileEntry],
     log_type: &str,
     date_from: OffsetDateTime,
-) -> Result<Vec<PathBuf>, LogRetrievalError> {
+) -> Result<Vec<LogFile>, LogRetrievalError> {
     let mut file_list = filter_logs_by_type(files, log_type)?;
     sort_logs_by_date(&mut file_list);
 
@@ -92,25 +155,38 @@ fn filter_logs(
 fn filter_logs_by_type(
     files: &[FileEntry],
     log_type: &str,
-) -> Result<Vec<(PathBuf, OffsetDateTime, bool)>, LogRetrievalError> {
+) -> Result<Vec<LogFile>, LogRetrievalError> {
     let mut file_list = Vec::new();
+    let mut listed = HashSet::new();
     let wildcard_regex = Regex::new(r"^.*\*.*").unwrap();
 
-    let files: Vec<&str> = files
-        .iter()
-        .filter(|file| file.config_type.eq(log_type))
-        .map(|file| file.path.as_str())
-        .collect();
+    let files = files.iter().filter(|file| file.config_type.eq(log_type));
 
     for file in files {
-        let paths = glob(file)?;
+        let paths = glob(&file.path)?;
         for path in paths {
             let entry = path?;
-            file_list.push((
-                entry.to_owned(),
-                get_modification_date(entry.as_path()),
-                wildcard_regex.is_match(file),
-            ))
+            let timestamp_format = file.timestamp_format;
+
+            // When lines can be filtered by timestamp, rotated siblings are read too
+            let rotated_pattern = format!("{}.[0-9]*", Pattern::escape(&entry.to_string_lossy()));
+            let rotated_files = match timestamp_format {
+                Some(_) => glob(&rotated_pattern)?.collect::<Result<Vec<_>, _>>()?,
+                None => vec![],
+            };
+
+            let wildcard_match = wildcard_regex.is_match(&file.path);
+            let siblings = rotated_files.into_iter().map(|path| (path, true));
+            for (path, wildcard_match) in [(entry, wildcard_match)].into_iter().chain(siblings) {
+                if listed.insert(path.clone()) {
+                    file_list.push(LogFile {
+                        modification_time: get_modification_date(&path),
+                        path,
+                        wildcard_match,
+                        timestamp_format,
+                    })
+                }
+            }
         }
     }
 
@@ -128,22 +204,16 @@ fn get_modification_date(path: impl AsRef<Path>) -> OffsetDateTime {
     OffsetDateTime::UNIX_EPOCH
 }
 
-fn filter_logs_by_date(
-    files: Vec<(PathBuf, OffsetDateTime, bool)>,
-    date_from: OffsetDateTime,
-) -> Vec<PathBuf> {
+fn filter_logs_by_date(files: Vec<LogFile>, date_from: OffsetDateTime) -> Vec<LogFile> {
     // include log file with static path no matter whether or not it is in date range
     files
         .into_iter()
-        .filter(|(_, modification_time, wildcard_match)| {
-            !wildcard_match || *modification_time >= date_from
-        })
-        .map(|(path, _, _)| path)
+        .filter(|file| !file.wildcard_match || file.modification_time >= date_from)
         .collect()
 }
 
-fn sort_logs_by_date(files: &mut [(PathBuf, OffsetDateTime, bool)]) {
-    files.sort_by_key(|(_, modification_time, _)| Reverse(*modification_time));
+fn sort_logs_by_date(files: &mut [LogFile]) {
+    files.sort_by_key(|file| Reverse(file.modification_time));
 }
 
 #[cfg(test)]
@@ -186,10 +256,12 @@ mod tests {
             FileEntry {
                 path: format!("{tempdir_path}/*_one"),
                 config_type: "type_one".to_string(),
+                timestamp_format: None,
             },
             FileEntry {
                 path: format!("{tempdir_path}/*_two"),
                 config_type: "type_two".to_string(),
+                timestamp_format: None,
             },
         ];
 
@@ -212,7 +284,12 @@ mod tests {
         let (tempdir, files) = prepare();
         let tempdir_path = tempdir.path().to_str().unwrap();
 
-        let logs = filter_logs(&files, "type_one", datetime!(1970-01-01 00:00:03 +00:00)).unwrap();
+        let logs: Vec<PathBuf> =
+            filter_logs(&files, "type_one", datetime!(1970-01-01 00:00:03 +00:00))
+                .unwrap()
+                .into_iter()
+                .map(|file| file.path)
+                .collect();
 
         assert_eq!(
             logs,
@@ -246,22 +323,31 @@ mod tests {
             FileEntry {
                 path: format!("{tempdir_path}/file_a_one"),
                 config_type: "type_one".to_string(),
+                timestamp_format: None,
             },
             FileEntry {
                 path: format!("{tempdir_path}/file_b_one"),
                 config_type: "type_one".to_string(),
+                timestamp_format: None,
             },
             FileEntry {
                 path: format!("{tempdir_path}/file_c_two"),
                 config_type: "type_two".to_string(),
+                timestamp_format: None,
             },
             FileEntry {
                 path: format!("{tempdir_path}/file_d_one"),
                 config_type: "type_one".to_string(),
+                timestamp_format: None,
             },
         ];
 
-        let logs = filter_logs(&files, "type_one", datetime!(1970-01-01 00:00:03 +00:00)).unwrap();
+        let logs: Vec<PathBuf> =
+            filter_logs(&files, "type_one", datetime!(1970-01-01 00:00:03 +00:00))
+                .unwrap()
+                .into_iter()
+                .map(|file| file.path)
+                .collect();
 
         assert_eq!(
             logs,
@@ -299,7 +385,9 @@ mod tests {
         log_file.write_all(data.as_bytes()).unwrap();
         log_file.flush().unwrap();
 
-        let result = read_log_content(Path::new(file_path)).unwrap();
+        let mut result = Vec::new();
+        copy_log_content(Path::new(file_path), &mut result, None).unwrap();
+        let result = String::from_utf8(result).unwrap();
 
         assert_eq!(result, "filename: file_a_one\nthis is the first line.\nthis is the second line.\nthis is the third line.\nthis is the forth line.\nthis is the fifth line.\n");
     }
@@ -349,6 +437,7 @@ mod tests {
             &files,
             "type_one",
             datetime!(1970-01-01 00:00:03 +00:00),
+            None,
             tempdir.utf8_path(),
         )
         .unwrap();
@@ -358,4 +447,91 @@ mod tests {
         let result = std::fs::read_to_string(temp_path).unwrap();
         assert_eq!(result, String::from("filename: file_d_one\nthis is the first line of file_d_one.\nthis is the second line of file_d_one.\nthis is the third line of file_d_one.\nthis is the forth line of file_d_one.\nthis is the fifth line of file_d_one.\nfilename: file_b_one\nthis is the first line of file_b_one.\nthis is the second line of file_b_one.\nthis is the third line of file_b_one.\nthis is the forth line of file_b_one.\nthis is the fifth line of file_b_one.\n"))
     }
+
+    #[test]
+    /// Only the lines in the requested time window are returned,
+    /// with the lines with no timestamp kept along the previous line.
+    ///
+    /// The compressed rotated siblings of the log file are read too,
+    /// the most recent file being read first.
+    fn test_read_log_lines_in_time_window() {
+        let tempdir = TempTedgeDir::new();
+        let tempdir_path = tempdir.path().to_str().unwrap();
+
+        let log_file = format!("{tempdir_path}/app.log");
+        std::fs::write(&log_file, "1371913200 INFO too late\n").unwrap();
+        set_file_mtime(&log_file, FileTime::from_unix_time(1371913200, 0)).unwrap();
+
+        let rotated_file = format!("{tempdir_path}/app.log.1.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&rotated_file).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder
+            .write_all(b"1371910000 INFO too early\n1371911000 ERROR in the window\n    with a stack trace\n1371912000 INFO in the window\n")
+            .unwrap();
+        encoder.finish().unwrap();
+        set_file_mtime(&rotated_file, FileTime::from_unix_time(1371912000, 0)).unwrap();
+
+        let files = vec![FileEntry {
+            path: log_file,
+            config_type: "app".to_string(),
+            timestamp_format: Some(TimestampFormat::Epoch),
+        }];
+
+        let temp_path = new_read_logs(
+            &files,
+            "app",
+            datetime!(2013-06-22 14:10:00 +00:00),
+            Some(datetime!(2013-06-22 14:45:00 +00:00)),
+            tempdir.utf8_path(),
+        )
+        .unwrap();
+
+        let result = std::fs::read_to_string(temp_path).unwrap();
+        assert_eq!(result, "filename: app.log.1.gz\n1371911000 ERROR in the window\n    with a stack trace\n1371912000 INFO in the window\n");
+    }
+
+    #[test]
+    /// The lines before the first timestamp are out of the time window,
+    /// unless the file has no timestamp at all.
+    fn test_read_log_lines_before_the_first_timestamp() {
+        let tempdir = TempTedgeDir::new();
+        let tempdir_path = tempdir.path().to_str().unwrap();
+
+        let log_file = format!("{tempdir_path}/app.log");
+        std::fs::write(
+            &log_file,
+            "    end of an old stack trace\n1371911000 ERROR in the window\n",
+        )
+        .unwrap();
+        let other_file = format!("{tempdir_path}/other.log");
+        std::fs::write(&other_file, "no timestamp\n").unwrap();
+
+        let files = vec![
+            FileEntry {
+                path: log_file,
+                config_type: "app".to_string(),
+                timestamp_format: Some(TimestampFormat::Epoch),
+            },
+            FileEntry {
+                path: other_file,
+                config_type: "other".to_string(),
+                timestamp_format: Some(TimestampFormat::Epoch),
+            },
+        ];
+
+        let from = datetime!(2013-06-22 14:10:00 +00:00);
+        let to = Some(datetime!(2013-06-22 14:45:00 +00:00));
+        let temp_path = new_read_logs(&files, "app", from, to, tempdir.utf8_path()).unwrap();
+        let result = std::fs::read_to_string(temp_path).unwrap();
+        assert_eq!(
+            result,
+            "filename: app.log\n1371911000 ERROR in the window\n"
+        );
+
+        let temp_path = new_read_logs(&files, "other", from, to, tempdir.utf8_path()).unwrap();
+        let result = std::fs::read_to_string(temp_path).unwrap();
+        assert_eq!(result, "filename: other.log\nno timestamp\n");
+    }
 }
@@ -0,0 +1,181 @@
+use serde::Deserialize;
+use time::format_description::well_known::Iso8601;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::Duration;
+use time::OffsetDateTime;
+use time::PrimitiveDateTime;
+
+/// Format of the timestamps found at the beginning of the lines of a log file
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampFormat {
+    /// `Jun 22 17:03:14`
+    ///
+    /// As there is no year nor time zone, the timestamp is assumed to be UTC and less than a year old.
+    Syslog,
+
+    /// `2013-06-22T17:03:14.000+02:00`
+    Rfc3339,
+
+    /// `2013-06-22 17:03:14,123+0200`
+    ///
+    /// The date and time can be separated by a `T` or a space.
+    /// The time zone is optional and defaults to UTC.
+    Iso8601,
+
+    /// `1371913394` or `1371913394123`
+    ///
+    /// Seconds or milliseconds since the unix epoch.
+    Epoch,
+}
+
+impl TimestampFormat {
+    /// Parse the timestamp at the beginning of a log line
+    ///
+    /// A leading `[` is ignored, as well as any trailing `]`, `:` or `,` after the timestamp.
+    /// Return `None` if the line doesn't start with a timestamp.
+    pub fn parse_line(&self, line: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        let line = line.trim_start().trim_start_matches('[');
+        match self {
+            TimestampFormat::Syslog => parse_syslog(line, now),
+            TimestampFormat::Rfc3339 => OffsetDateTime::parse(first_word(line), &Rfc3339).ok(),
+            TimestampFormat::Iso8601 => parse_iso8601(line),
+            TimestampFormat::Epoch => parse_epoch(first_word(line)),
+        }
+    }
+}
+
+fn first_word(line: &str) -> &str {
+    line.split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches([']', ':', ','])
+}
+
+fn parse_syslog(line: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    let format = format_description!(
+        "[year] [month repr:short] [day padding:space] [hour]:[minute]:[second]"
+    );
+    let timestamp = line.get(..15)?;
+    let parse = |year: i32| {
+        PrimitiveDateTime::parse(&format!("{year} {timestamp}"), format)
+            .ok()
+            .map(PrimitiveDateTime::assume_utc)
+    };
+
+    // A timestamp in the future is from the previous year
+    let time = parse(now.year())?;
+    if time > now + Duration::DAY {
+        parse(now.year() - 1)
+    } else {
+        Some(time)
+    }
+}
+
+fn parse_iso8601(line: &str) -> Option<OffsetDateTime> {
+    let date = line.get(..10)?;
+    let time = first_word(line.get(11..)?);
+    match line.get(10..11)? {
+        " " | "T" => (),
+        _ => return None,
+    }
+    let mut time = time.replace(',', ".");
+
+    // Use the extended format for the offset too, i.e. `+02:00` rather than `+0200`
+    if let Some(offset) = time.len().checked_sub(5).and_then(|i| time.get(i..)) {
+        let (sign, digits) = offset.split_at(1);
+        if (sign == "+" || sign == "-") && digits.bytes().all(|b| b.is_ascii_digit()) {
+            time.insert(time.len() - 2, ':');
+        }
+    }
+
+    let timestamp = format!("{date}T{time}");
+    OffsetDateTime::parse(&timestamp, &Iso8601::DEFAULT)
+        .ok()
+        .or_else(|| {
+            PrimitiveDateTime::parse(&timestamp, &Iso8601::DEFAULT)
+                .ok()
+                .map(PrimitiveDateTime::assume_utc)
+        })
+}
+
+fn parse_epoch(word: &str) -> Option<OffsetDateTime> {
+    if !word.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let number: i64 = word.parse().ok()?;
+    match word.len() {
+        10 => OffsetDateTime::from_unix_timestamp(number).ok(),
+        13 => OffsetDateTime::from_unix_timestamp_nanos(number as i128 * 1_000_000).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    const NOW: OffsetDateTime = datetime!(2013-06-25 10:00:00 +00:00);
+
+    #[test]
+    fn parse_syslog_timestamps() {
+        let format = TimestampFormat::Syslog;
+        assert_eq!(
+            format.parse_line("Jun 22 17:03:14 host sshd[42]: Accepted", NOW),
+            Some(datetime!(2013-06-22 17:03:14 +00:00))
+        );
+        assert_eq!(
+            format.parse_line("Jun  2 07:03:14 host kernel: boot", NOW),
+            Some(datetime!(2013-06-02 07:03:14 +00:00))
+        );
+        assert_eq!(
+            format.parse_line("Dec 31 23:59:59 host cron: job", NOW),
+            Some(datetime!(2012-12-31 23:59:59 +00:00))
+        );
+        assert_eq!(format.parse_line("    at some.stack.Trace", NOW), None);
+    }
+
+    #[test]
+    fn parse_rfc3339_timestamps() {
+        let format = TimestampFormat::Rfc3339;
+        assert_eq!(
+            format.parse_line("2013-06-22T17:03:14.000+02:00 INFO started", NOW),
+            Some(datetime!(2013-06-22 15:03:14 +00:00))
+        );
+        assert_eq!(
+            format.parse_line("[2013-06-22T17:03:14Z] INFO started", NOW),
+            Some(datetime!(2013-06-22 17:03:14 +00:00))
+        );
+        assert_eq!(format.parse_line("INFO started", NOW), None);
+    }
+
+    #[test]
+    fn parse_iso8601_timestamps() {
+        let format = TimestampFormat::Iso8601;
+        assert_eq!(
+            format.parse_line("2013-06-22 17:03:14,123+0200 INFO started", NOW),
+            Some(datetime!(2013-06-22 15:03:14.123 +00:00))
+        );
+        assert_eq!(
+            format.parse_line("2013-06-22T17:03:14 INFO started", NOW),
+            Some(datetime!(2013-06-22 17:03:14 +00:00))
+        );
+        assert_eq!(format.parse_line("2013-06-22 INFO started", NOW), None);
+    }
+
+    #[test]
+    fn parse_epoch_timestamps() {
+        let format = TimestampFormat::Epoch;
+        assert_eq!(
+            format.parse_line("1371913394: mosquitto version 2.0.11 starting", NOW),
+            Some(datetime!(2013-06-22 15:03:14 +00:00))
+        );
+        assert_eq!(
+            format.parse_line("1371913394123 started", NOW),
+            Some(datetime!(2013-06-22 15:03:14.123 +00:00))
+        );
+        assert_eq!(format.parse_line("42 items processed", NOW), None);
+    }
+}